@@ -1,4 +1,4 @@
-use shenron::{Event, Result, Server, Session};
+use shenron::{Event, Result, Server, Session, auth::StaticUsers};
 
 async fn whoami(session: &mut Session) -> Result {
     session
@@ -29,33 +29,25 @@ async fn main() -> Result<()> {
         .with_max_level(tracing::Level::INFO)
         .init();
 
-    let allowed_users = vec!["admin", "alice", "bob"];
-    let admin_password = "supersecret";
+    // Hashes, not passwords: generate your own with `shenron::auth::hash_argon2`
+    // (or `htpasswd -nbB user password` for bcrypt).
+    let users = StaticUsers::new()
+        .user(
+            "admin",
+            "$argon2id$v=19$m=19456,t=2,p=1$FMV1B3AxctikPLAeLiDGAQ$jFRNY+X8TmWm7KhQ+qmfdYTWwwLkVUb0x84OluAVD0U",
+        )?
+        .user(
+            "alice",
+            "$2b$10$zotJKU9s0x.i9qFO0l6yte.dJ2wSnBvsg.7PCrXjS/JTcahjVbBRu",
+        )?;
 
     tracing::info!("Starting auth example on 0.0.0.0:2222");
     tracing::info!("Connect with: ssh -p 2222 admin@localhost");
-    tracing::info!("Password: supersecret");
+    tracing::info!("Password: supersecret (or alice / hunter2)");
 
     Server::new()
         .bind("0.0.0.0:2222")
-        .password_auth(move |user, password| {
-            let allowed = allowed_users.clone();
-            let admin_pw = admin_password;
-            async move {
-                if !allowed.contains(&user.as_str()) {
-                    tracing::warn!("Unknown user attempted login: {}", user);
-                    return false;
-                }
-
-                if user == "admin" && password == admin_pw {
-                    tracing::info!("Admin logged in with password");
-                    return true;
-                }
-
-                tracing::warn!("Password auth failed for user: {}", user);
-                false
-            }
-        })
+        .password_auth(users.handler())
         .app(whoami)
         .serve()
         .await
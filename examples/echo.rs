@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use shenron::{Event, Exit, Next, Result, Server, Session};
 
 async fn echo(session: &mut Session) -> Result {
@@ -12,6 +14,8 @@ async fn echo(session: &mut Session) -> Result {
         .write_str("Press Ctrl+C or Ctrl+D to exit.\r\n\r\n")
         .await?;
 
+    session.set_idle_timeout(Some(Duration::from_mins(5)));
+
     while let Some(event) = session.next().await {
         match event {
             Event::Input(data) => {
@@ -26,6 +30,10 @@ async fn echo(session: &mut Session) -> Result {
             Event::Resize(size) => {
                 tracing::debug!("Resized to {}x{}", size.width, size.height);
             }
+            Event::IdleTimeout => {
+                session.write_str("\r\nIdle too long, goodbye!\r\n").await?;
+                break;
+            }
             Event::Eof => break,
             Event::Signal(_) => {}
         }
@@ -0,0 +1,52 @@
+use shenron::{Auth, Result, Server, Session, auth::Prompt};
+
+async fn whoami(session: &mut Session) -> Result {
+    session
+        .write_str(&format!(
+            "Welcome {}, both factors checked out.\r\n",
+            session.user()
+        ))
+        .await
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    tracing_subscriber::fmt()
+        .with_max_level(tracing::Level::INFO)
+        .init();
+
+    tracing::info!("Starting keyboard-interactive example on 0.0.0.0:2222");
+    tracing::info!(
+        "Connect with: ssh -p 2222 -o PreferredAuthentications=keyboard-interactive admin@localhost"
+    );
+    tracing::info!("Password: supersecret, then code: 123456");
+
+    Server::new()
+        .bind("0.0.0.0:2222")
+        .keyboard_interactive_auth(|user, mut ch| async move {
+            // Round one: the password. Unknown users and wrong passwords
+            // fail the same way, so the prompt doesn't leak which users exist.
+            let answers = ch
+                .challenge("Login", "", [Prompt::hidden("Password: ")])
+                .await?;
+
+            if user != "admin" || answers[0] != "supersecret" {
+                tracing::warn!("Bad password for {user}");
+                return Ok(Auth::reject());
+            }
+
+            // Round two: a one-time code.
+            let answers = ch
+                .challenge(
+                    "Two-factor",
+                    "Enter the code from your authenticator app.",
+                    [Prompt::echo("Code: ")],
+                )
+                .await?;
+
+            Ok(Auth::from(answers[0] == "123456"))
+        })
+        .app(whoami)
+        .serve()
+        .await
+}
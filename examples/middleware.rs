@@ -24,7 +24,7 @@ async fn main() -> Result<()> {
 
     Server::new()
         .bind("0.0.0.0:2222")
-        .with(Comment("Cya! Wouldn't wanna be ya!\r\n".into()))
+        .with(Comment::from("Cya! Wouldn't wanna be ya!\r\n"))
         .with(elapsed)
         .app(sleep_and_die)
         .serve()
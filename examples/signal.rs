@@ -30,7 +30,7 @@ async fn signal(session: &mut Session) -> shenron::Result {
                 }
             },
             Event::Eof => break,
-            Event::Resize(_) => {}
+            Event::Resize(_) | Event::IdleTimeout => {}
         }
     }
 
@@ -0,0 +1,33 @@
+use std::time::Duration;
+
+use shenron::{Result, Server, Session};
+
+async fn app(session: &mut Session) -> Result {
+    session
+        .write_str("Hello from a background server!\r\n")
+        .await?;
+
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    tracing_subscriber::fmt()
+        .with_max_level(tracing::Level::INFO)
+        .init();
+
+    let server = Server::new().bind("127.0.0.1:0").app(app).spawn().await?;
+
+    tracing::info!("SSH server listening on {}", server.local_addr());
+    tracing::info!(
+        "Connect with: ssh -p {} localhost",
+        server.local_addr().port()
+    );
+
+    // The server runs in the background; this task is free for other work,
+    // like an HTTP server. Here we just wait a minute, then stop it.
+    tokio::time::sleep(Duration::from_mins(1)).await;
+
+    server.shutdown();
+    server.stopped().await
+}
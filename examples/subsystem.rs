@@ -1,4 +1,4 @@
-// examples/graceful_shutdown.rs
+// examples/subsystem.rs
 
 use shenron::{Server, Session, SessionKind};
 
@@ -6,30 +6,36 @@ use shenron::{Server, Session, SessionKind};
 async fn main() -> shenron::Result<()> {
     println!("Starting server on 127.0.0.1:2222");
 
-    Server::new().bind("0.0.0.0:2222").app(app).serve().await?;
+    Server::new()
+        .bind("0.0.0.0:2222")
+        .subsystem("echo", echo)
+        .app(app)
+        .serve()
+        .await?;
 
     println!("Server stopped");
 
     Ok(())
 }
 
+async fn echo(session: &mut Session) -> shenron::Result<u32> {
+    while let Some(data) = session.input().await {
+        let s = String::from_utf8_lossy(&data);
+        session.write_str(&format!("Got: {s}\r\n")).await?;
+    }
+
+    Ok(0)
+}
+
+/// Everything the `echo` route didn't claim ends up here.
 async fn app(session: &mut Session) -> shenron::Result<u32> {
     match session.kind() {
-        SessionKind::Subsystem { name } => match name.as_str() {
-            "echo" => {
-                while let Some(data) = session.input().await {
-                    let s = String::from_utf8_lossy(&data);
-                    session.write_str(&format!("Got: {s}\r\n")).await?;
-                }
-                Ok(0)
-            }
-            other => {
-                session
-                    .write_stderr_str(&format!("Unknown subsystem: {other}\n"))
-                    .await?;
-                Ok(1)
-            }
-        },
+        SessionKind::Subsystem { name } => {
+            session
+                .write_stderr_str(&format!("Unknown subsystem: {name}\n"))
+                .await?;
+            Ok(1)
+        }
         SessionKind::Shell => {
             session
                 .write_str("This server only supports subsystems.\r\n")
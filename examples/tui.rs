@@ -68,7 +68,7 @@ async fn counter(session: &mut Session) -> Result {
             Some(tui::Event::Paste(text)) => state.message = format!("Pasted: {text}"),
             Some(tui::Event::App(Msg::Tick)) => state.ticks += 1,
             Some(tui::Event::Resize(_)) => {}
-            Some(tui::Event::Eof) | None => break,
+            Some(tui::Event::Eof | tui::Event::IdleTimeout) | None => break,
         }
     }
 
@@ -0,0 +1,19 @@
+use crate::{Auth, BoxFuture};
+
+/// Type-erased handler for the `none` method
+pub trait AnonymousAuth: Send + Sync {
+    fn verify(&self, user: &str) -> BoxFuture<Auth>;
+}
+
+impl<F, Fut> AnonymousAuth for F
+where
+    F: Fn(String) -> Fut + Send + Sync,
+    Fut: Future + Send + 'static,
+    Fut::Output: Into<Auth>,
+{
+    fn verify(&self, user: &str) -> BoxFuture<Auth> {
+        let fut = (self)(user.to_string());
+
+        Box::pin(async move { fut.await.into() })
+    }
+}
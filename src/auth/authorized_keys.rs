@@ -1,18 +1,26 @@
-use std::{collections::HashSet, future::Ready, path::Path};
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::SystemTime,
+};
 
 use russh::keys::{
     PublicKey,
     ssh_key::{AuthorizedKeys, public::KeyData},
 };
 
+use crate::BoxFuture;
+
 /// A ready-made pubkey handler, accepted by
 /// [`pubkey_auth`](crate::server::Server::pubkey_auth) like any closure.
-pub type PubkeyHandler = Box<dyn Fn(String, PublicKey) -> Ready<bool> + Send + Sync>;
+pub type PubkeyHandler = Box<dyn Fn(String, PublicKey) -> BoxFuture<bool> + Send + Sync>;
 
 /// Build a pubkey handler that accepts only keys listed in an OpenSSH
 /// `authorized_keys` file.
 ///
-/// The file is read once, here; edits require a restart. Keys are compared by
+/// The file is read once, here; edits require a restart (or use
+/// [`authorized_keys_reloading`]). Keys are compared by
 /// key material, so comments and per-line options don't affect matching. Like
 /// Wish's `WithAuthorizedKeys`, the allowlist is server-wide — the username is
 /// not consulted.
@@ -34,16 +42,192 @@ pub type PubkeyHandler = Box<dyn Fn(String, PublicKey) -> Ready<bool> + Send + S
 ///
 /// Returns `Err` if the file cannot be read or parsed.
 pub fn authorized_keys(path: impl AsRef<Path>) -> crate::Result<PubkeyHandler> {
-    let keys: HashSet<KeyData> = AuthorizedKeys::read_file(path.as_ref())?
-        .into_iter()
-        .map(|entry| entry.public_key().key_data().clone())
-        .collect();
+    let keys = read(path.as_ref())?;
 
     Ok(Box::new(move |_user: String, key: PublicKey| {
-        std::future::ready(keys.contains(key.key_data()))
+        Box::pin(std::future::ready(keys.contains(key.key_data())))
     }))
 }
 
+/// Like [`authorized_keys`], but picks up edits without a restart.
+///
+/// Each login checks the file's modification time and size (one `stat`) and
+/// re-reads it when either changed, both on tokio's blocking pool. Deleting
+/// the file revokes every key. A file that fails to parse — say, caught
+/// mid-edit — keeps the last good key set in effect and logs a warning, so a
+/// typo can't lock everyone out.
+///
+/// # Errors
+///
+/// Returns `Err` if the file cannot be read or parsed at startup.
+pub fn authorized_keys_reloading(path: impl Into<PathBuf>) -> crate::Result<PubkeyHandler> {
+    let path = path.into();
+    let stamp = stamp(&path)?;
+    let keys = read(&path)?;
+
+    let watched = Arc::new(Reloading {
+        path,
+        state: Mutex::new((Some(stamp), keys)),
+    });
+
+    Ok(Box::new(move |_user: String, key: PublicKey| {
+        let watched = Arc::clone(&watched);
+
+        Box::pin(async move { watched.contains(key.key_data()).await })
+    }))
+}
+
+/// Build a pubkey handler that reads each user's own `authorized_keys`,
+/// like sshd's `AuthorizedKeysFile`.
+///
+/// `%u` in `template` is replaced with the username and `%%` with a literal
+/// `%`, e.g. `"/home/%u/.ssh/authorized_keys"`. The file is read at every
+/// login, on tokio's blocking pool, so edits apply immediately; a missing or
+/// unparsable file rejects the key. Usernames that could escape the
+/// template's directory (empty, `.`, `..`, or containing `/` or NUL) are
+/// always rejected.
+///
+/// ```no_run
+/// # use shenron::Server;
+/// let _server = Server::new()
+///     .pubkey_auth(shenron::auth::authorized_keys_per_user("/srv/keys/%u"));
+/// ```
+pub fn authorized_keys_per_user(template: impl Into<String>) -> PubkeyHandler {
+    let template = template.into();
+
+    Box::new(move |user: String, key: PublicKey| {
+        let path = user_path(&template, &user);
+
+        Box::pin(async move {
+            let Some(path) = path else {
+                tracing::debug!(user, "username not usable in an authorized_keys path");
+
+                return false;
+            };
+
+            let file = path.clone();
+
+            match blocking(move || read(&file)).await {
+                Ok(keys) => keys.contains(key.key_data()),
+                Err(e) => {
+                    tracing::debug!(user, path = %path.display(), "authorized_keys unreadable: {e}");
+
+                    false
+                }
+            }
+        })
+    })
+}
+
+/// Run file I/O on tokio's blocking pool, so a slow disk or NFS mount
+/// stalls only the login waiting on it. A panic comes back as an error.
+pub(crate) async fn blocking<T: Send + 'static>(
+    f: impl FnOnce() -> crate::Result<T> + Send + 'static,
+) -> crate::Result<T> {
+    tokio::task::spawn_blocking(f)
+        .await
+        .map_err(|e| crate::Error::Panic(e.to_string()))?
+}
+
+fn read(path: &Path) -> crate::Result<HashSet<KeyData>> {
+    Ok(AuthorizedKeys::read_file(path)?
+        .into_iter()
+        .map(|entry| entry.public_key().key_data().clone())
+        .collect())
+}
+
+/// Cheap change detection: modification time plus size.
+pub(crate) type Stamp = (SystemTime, u64);
+
+pub(crate) fn stamp(path: &Path) -> std::io::Result<Stamp> {
+    let meta = std::fs::metadata(path)?;
+
+    Ok((meta.modified()?, meta.len()))
+}
+
+/// The key set behind [`authorized_keys_reloading`], refreshed lazily.
+/// `None` as the stamp means the file was missing at the last check.
+struct Reloading {
+    path: PathBuf,
+    state: Mutex<(Option<Stamp>, HashSet<KeyData>)>,
+}
+
+impl Reloading {
+    /// Whether `key` is listed, re-reading the file first if it changed.
+    /// The lock is only held to compare and swap the state, never across
+    /// the I/O, so concurrent logins don't queue behind a slow read.
+    async fn contains(&self, key: &KeyData) -> bool {
+        let path = self.path.clone();
+
+        // A check that panicked leaves the keys as they were.
+        if let Ok(current) = blocking(move || Ok(stamp(&path).ok())).await {
+            let changed = current != self.state().0;
+
+            if changed {
+                self.reload(current).await;
+            }
+        }
+
+        self.state().1.contains(key)
+    }
+
+    async fn reload(&self, current: Option<Stamp>) {
+        if current.is_none() {
+            tracing::info!(path = %self.path.display(), "authorized_keys removed, revoking all keys");
+            *self.state() = (None, HashSet::new());
+
+            return;
+        }
+
+        let path = self.path.clone();
+
+        match blocking(move || read(&path)).await {
+            Ok(keys) => {
+                tracing::info!(path = %self.path.display(), "authorized_keys reloaded");
+                *self.state() = (current, keys);
+            }
+            // Leave the stamp stale so the next login retries.
+            Err(e) => tracing::warn!(
+                path = %self.path.display(),
+                "authorized_keys reload failed, keeping previous keys: {e}"
+            ),
+        }
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, (Option<Stamp>, HashSet<KeyData>)> {
+        self.state.lock().expect("authorized_keys state poisoned")
+    }
+}
+
+/// Expand `%u`/`%%` in `template`, refusing usernames that would change which
+/// directory the path points into.
+fn user_path(template: &str, user: &str) -> Option<PathBuf> {
+    if user.is_empty() || user == "." || user == ".." || user.contains(['/', '\\', '\0']) {
+        return None;
+    }
+
+    let mut path = String::with_capacity(template.len() + user.len());
+    let mut chars = template.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            path.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('u') => path.push_str(user),
+            Some('%') | None => path.push('%'),
+            Some(other) => {
+                path.push('%');
+                path.push(other);
+            }
+        }
+    }
+
+    Some(PathBuf::from(path))
+}
+
 #[cfg(test)]
 mod tests {
     use russh::keys::{Algorithm, PrivateKey};
@@ -103,4 +287,66 @@ mod tests {
     fn missing_file_errors() {
         assert!(authorized_keys("/nonexistent/authorized_keys").is_err());
     }
+
+    #[tokio::test]
+    async fn reloading_picks_up_edits_and_deletion() {
+        let first = generate();
+        let second = generate();
+        let file = write_authorized_keys(&[first.to_openssh().expect("openssh")]);
+
+        let handler = authorized_keys_reloading(file.path()).expect("parse");
+
+        assert!(handler("alice".into(), first.clone()).await);
+        assert!(!handler("alice".into(), second.clone()).await);
+
+        let both = [first.clone(), second.clone()].map(|k| k.to_openssh().expect("openssh"));
+        std::fs::write(file.path(), both.join("\n")).expect("rewrite");
+
+        assert!(handler("alice".into(), second.clone()).await);
+
+        std::fs::remove_file(file.path()).expect("remove");
+
+        assert!(!handler("alice".into(), first).await);
+    }
+
+    #[tokio::test]
+    async fn reloading_keeps_last_good_keys_on_parse_error() {
+        let listed = generate();
+        let file = write_authorized_keys(&[listed.to_openssh().expect("openssh")]);
+
+        let handler = authorized_keys_reloading(file.path()).expect("parse");
+
+        std::fs::write(file.path(), "ssh-ed25519 not-base64!").expect("corrupt");
+
+        assert!(handler("alice".into(), listed).await);
+    }
+
+    #[tokio::test]
+    async fn per_user_reads_the_users_own_file() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let alice = generate();
+        std::fs::write(
+            dir.path().join("alice"),
+            alice.to_openssh().expect("openssh"),
+        )
+        .expect("write");
+
+        let template = format!("{}/%u", dir.path().display());
+        let handler = authorized_keys_per_user(template);
+
+        assert!(handler("alice".into(), alice.clone()).await);
+        assert!(!handler("bob".into(), alice).await);
+    }
+
+    #[test]
+    fn user_path_expands_and_refuses_traversal() {
+        assert_eq!(
+            user_path("/home/%u/.ssh/keys%%", "alice"),
+            Some(PathBuf::from("/home/alice/.ssh/keys%"))
+        );
+
+        for user in ["", ".", "..", "../root", "a/b", "a\\b", "a\0b"] {
+            assert_eq!(user_path("/srv/%u", user), None, "{user:?}");
+        }
+    }
 }
@@ -0,0 +1,121 @@
+use std::time::Duration;
+
+/// A growing delay before answering each failed auth attempt on a
+/// connection, installed with [`Server::auth_backoff`](crate::Server::auth_backoff).
+///
+/// The first failure waits [`initial`](Self::initial), and each one after
+/// that waits [`multiplier`](Self::multiplier) times longer, up to
+/// [`max`](Self::max). A user who mistypes once gets a quick retry; a
+/// script guessing passwords slows to a crawl.
+///
+/// ```
+/// # use std::time::Duration;
+/// # use shenron::auth::AuthBackoff;
+/// // 100ms, 300ms, 900ms, 2.7s, then 5s per failure.
+/// let _backoff = AuthBackoff::new()
+///     .initial(Duration::from_millis(100))
+///     .multiplier(3.0)
+///     .max(Duration::from_secs(5));
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct AuthBackoff {
+    initial: Duration,
+    multiplier: f64,
+    max: Duration,
+}
+
+impl Default for AuthBackoff {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AuthBackoff {
+    /// Start at 250ms and double per failure, up to 8 seconds.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            initial: Duration::from_millis(250),
+            multiplier: 2.0,
+            max: Duration::from_secs(8),
+        }
+    }
+
+    /// Delay before answering the first failure.
+    #[must_use]
+    pub const fn initial(mut self, delay: Duration) -> Self {
+        self.initial = delay;
+
+        self
+    }
+
+    /// How much longer each failure waits than the one before. Clamped to
+    /// at least 1, so the delay never shrinks.
+    #[must_use]
+    pub const fn multiplier(mut self, multiplier: f64) -> Self {
+        self.multiplier = multiplier.max(1.0);
+
+        self
+    }
+
+    /// Cap on the delay, however many failures came before.
+    #[must_use]
+    pub const fn max(mut self, delay: Duration) -> Self {
+        self.max = delay;
+
+        self
+    }
+
+    /// The delay for the `failures`th failure, counting from 1.
+    pub(crate) fn delay(&self, failures: usize) -> Duration {
+        let mut delay = self.initial.min(self.max);
+
+        for _ in 1..failures {
+            if delay >= self.max {
+                break;
+            }
+
+            // A multiplier too big for a Duration just means the max.
+            delay = Duration::try_from_secs_f64(delay.as_secs_f64() * self.multiplier)
+                .map_or(self.max, |next| next.min(self.max));
+        }
+
+        delay
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delay_grows_by_the_multiplier_up_to_the_max() {
+        let backoff = AuthBackoff::new()
+            .initial(Duration::from_millis(100))
+            .multiplier(3.0)
+            .max(Duration::from_secs(1));
+
+        let delays: Vec<_> = (1..=5).map(|n| backoff.delay(n)).collect();
+
+        assert_eq!(
+            delays,
+            [100, 300, 900, 1000, 1000].map(Duration::from_millis)
+        );
+    }
+
+    #[test]
+    fn multiplier_below_one_holds_the_delay_steady() {
+        let backoff = AuthBackoff::new().multiplier(0.5);
+
+        assert_eq!(backoff.delay(10), Duration::from_millis(250));
+    }
+
+    #[test]
+    fn huge_multipliers_jump_to_the_max() {
+        for multiplier in [1e20, f64::MAX, f64::INFINITY] {
+            let backoff = AuthBackoff::new().multiplier(multiplier);
+
+            assert_eq!(backoff.delay(3), Duration::from_secs(8));
+        }
+    }
+}
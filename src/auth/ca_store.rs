@@ -0,0 +1,334 @@
+#[cfg(feature = "krl")]
+use std::path::PathBuf;
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+};
+
+use russh::keys::{
+    Certificate, HashAlg, PublicKey,
+    ssh_key::{Fingerprint, certificate::CertType},
+};
+
+#[cfg(feature = "krl")]
+use crate::auth::krl::RevokedKeys;
+use crate::{
+    Auth, BoxFuture,
+    auth::{AuthContext, AuthMethod, AuthProvider, trusted_ca},
+};
+
+type RevokedBy = Box<dyn Fn(Certificate) -> BoxFuture<bool> + Send + Sync>;
+
+/// Declarative certificate auth: trusted CAs, which principals may log in
+/// as which users, and revocation. Install it with
+/// [`Server::auth_provider`](crate::Server::auth_provider).
+///
+/// A certificate is accepted when it's a *user* certificate, signed by a
+/// trusted CA and inside its validity window, with no critical options
+/// (none are enforced yet, and the spec says unknown ones must fail), not
+/// revoked, and carrying a principal allowed for the username. By default a
+/// principal allows the username equal to it, as with sshd's
+/// `TrustedUserCAKeys`; [`principal`](Self::principal) maps others, like an
+/// `AuthorizedPrincipalsFile`.
+///
+/// ```no_run
+/// # use shenron::{Server, auth::CaTrustStore};
+/// # fn main() -> shenron::Result<()> {
+/// let store = CaTrustStore::new()
+///     .ca_file("/etc/ssh/user_ca.pub")?
+///     .principal("oncall", "deploy")
+///     .principal("oncall", "postgres");
+///
+/// let _server = Server::new().auth_provider(store);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Default)]
+pub struct CaTrustStore {
+    cas: Vec<Fingerprint>,
+    /// Principal to the usernames it may log in as.
+    principals: HashMap<String, HashSet<String>>,
+    mapped_only: bool,
+    #[cfg(feature = "krl")]
+    krls: Vec<RevokedKeys>,
+    revoked_by: Vec<RevokedBy>,
+}
+
+impl CaTrustStore {
+    /// A store trusting no CAs yet, so accepting nothing.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Trust certificates signed by `key`.
+    #[must_use]
+    pub fn ca(mut self, key: &PublicKey) -> Self {
+        self.cas.push(key.fingerprint(HashAlg::Sha256));
+
+        self
+    }
+
+    /// Trust every CA listed in `path`, in sshd's `TrustedUserCAKeys`
+    /// format: one public key per line, `#` comments.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the file cannot be read or parsed, or lists no keys.
+    pub fn ca_file(mut self, path: impl AsRef<Path>) -> crate::Result<Self> {
+        let keys = trusted_ca::parse(&std::fs::read_to_string(path.as_ref())?)?;
+        self.cas.extend(keys);
+
+        Ok(self)
+    }
+
+    /// Let certificates with `principal` log in as `user`. Call it again to
+    /// map more users or principals.
+    #[must_use]
+    pub fn principal(mut self, principal: impl Into<String>, user: impl Into<String>) -> Self {
+        self.principals
+            .entry(principal.into())
+            .or_default()
+            .insert(user.into());
+
+        self
+    }
+
+    /// Only allow the [`principal`](Self::principal) mappings, not a
+    /// principal equal to the username. Off by default.
+    #[must_use]
+    pub const fn mapped_principals_only(mut self, only: bool) -> Self {
+        self.mapped_only = only;
+
+        self
+    }
+
+    /// Refuse certificates revoked by the list at `path`: an OpenSSH KRL
+    /// (`ssh-keygen -k`) or a plain file of public keys, like sshd's
+    /// `RevokedKeys`. A revoked CA or key revokes every certificate for it.
+    ///
+    /// The file is re-read when it changes, so publishing a new list takes
+    /// effect on the next login. An update that fails to parse keeps the
+    /// previous list; deleting the file refuses every certificate. Requires
+    /// the `krl` feature.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the file cannot be read or parsed at startup.
+    #[cfg(feature = "krl")]
+    pub fn revoked_keys(mut self, path: impl Into<PathBuf>) -> crate::Result<Self> {
+        self.krls.push(RevokedKeys::load(path.into())?);
+
+        Ok(self)
+    }
+
+    /// Ask `check` whether a certificate is revoked — for a revocation
+    /// service, or a database of serials. Runs after every other check
+    /// passes.
+    #[must_use]
+    pub fn revoked_by<F, Fut>(mut self, check: F) -> Self
+    where
+        F: Fn(Certificate) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = bool> + Send + 'static,
+    {
+        self.revoked_by
+            .push(Box::new(move |cert| Box::pin(check(cert))));
+
+        self
+    }
+
+    fn allows(&self, principal: &str, user: &str) -> bool {
+        (!self.mapped_only && principal == user)
+            || self
+                .principals
+                .get(principal)
+                .is_some_and(|users| users.contains(user))
+    }
+
+    /// Everything short of the revocation callbacks; the reason on refusal.
+    #[cfg_attr(
+        not(feature = "krl"),
+        expect(clippy::unused_async, reason = "only KRL reloads await")
+    )]
+    async fn check(&self, user: &str, cert: &Certificate) -> Result<(), &'static str> {
+        if cert.cert_type() != CertType::User {
+            return Err("not a user certificate");
+        }
+
+        if cert.validate(&self.cas).is_err() {
+            return Err("untrusted CA or outside validity window");
+        }
+
+        if !cert.critical_options().is_empty() {
+            return Err("unsupported critical options");
+        }
+
+        if !cert
+            .valid_principals()
+            .iter()
+            .any(|principal| self.allows(principal, user))
+        {
+            return Err("no principal for this user");
+        }
+
+        #[cfg(feature = "krl")]
+        for krl in &self.krls {
+            if krl.revokes(cert).await {
+                return Err("certificate revoked");
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl AuthProvider for CaTrustStore {
+    const METHODS: &'static [AuthMethod] = &[AuthMethod::Certificate];
+
+    async fn openssh_cert(&self, user: &str, cert: &Certificate, _ctx: &AuthContext) -> Auth {
+        if let Err(reason) = self.check(user, cert).await {
+            return Auth::reject().reason(reason);
+        }
+
+        for check in &self.revoked_by {
+            if check(cert.clone()).await {
+                return Auth::reject().reason("certificate revoked");
+            }
+        }
+
+        Auth::accept()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use russh::keys::{Algorithm, PrivateKey, ssh_key::certificate::Builder};
+
+    use super::*;
+
+    fn generate() -> PrivateKey {
+        PrivateKey::random(&mut rand::rng(), Algorithm::Ed25519).expect("keygen")
+    }
+
+    fn cert(ca: &PrivateKey, serial: u64, principal: &str) -> Certificate {
+        let now = SystemTime::now();
+
+        let mut builder = Builder::new_with_validity_times(
+            [0u8; 16],
+            generate().public_key().key_data().clone(),
+            now - Duration::from_mins(1),
+            now + Duration::from_hours(1),
+        )
+        .expect("builder");
+
+        builder
+            .serial(serial)
+            .expect("serial")
+            .key_id(format!("cert-{serial}"))
+            .expect("key id")
+            .cert_type(CertType::User)
+            .expect("cert type")
+            .valid_principal(principal)
+            .expect("principal");
+
+        builder.sign(ca).expect("sign")
+    }
+
+    #[tokio::test]
+    async fn principals_map_to_users() {
+        let ca = generate();
+        let store = CaTrustStore::new()
+            .ca(ca.public_key())
+            .principal("oncall", "deploy");
+
+        assert!(store.check("alice", &cert(&ca, 1, "alice")).await.is_ok());
+        assert!(store.check("deploy", &cert(&ca, 1, "oncall")).await.is_ok());
+        assert!(store.check("root", &cert(&ca, 1, "oncall")).await.is_err());
+        assert!(
+            store
+                .check("alice", &cert(&generate(), 1, "alice"))
+                .await
+                .is_err()
+        );
+
+        let strict = store.mapped_principals_only(true);
+        assert!(strict.check("alice", &cert(&ca, 1, "alice")).await.is_err());
+        assert!(
+            strict
+                .check("deploy", &cert(&ca, 1, "oncall"))
+                .await
+                .is_ok()
+        );
+    }
+
+    #[cfg(feature = "krl")]
+    #[tokio::test]
+    async fn krl_revokes_by_serial_key_id_and_key() {
+        let ca = generate();
+        let ca_blob = ca.public_key().to_bytes().expect("blob");
+        let revoked_key = cert(&ca, 9, "alice");
+        let key_blob = PublicKey::from(revoked_key.public_key().clone())
+            .to_bytes()
+            .expect("blob");
+
+        let string = |bytes: &[u8]| {
+            let mut out = u32::try_from(bytes.len())
+                .expect("len")
+                .to_be_bytes()
+                .to_vec();
+            out.extend_from_slice(bytes);
+            out
+        };
+
+        let mut certs = string(&ca_blob);
+        certs.extend(string(b""));
+        certs.push(0x21);
+        certs.extend(string(&[3u64.to_be_bytes(), 5u64.to_be_bytes()].concat()));
+        certs.push(0x23);
+        certs.extend(string(&string(b"cert-7")));
+
+        let mut krl = b"SSHKRL\n\0".to_vec();
+        krl.extend(1u32.to_be_bytes());
+        krl.extend([0u8; 24]);
+        krl.extend(string(b""));
+        krl.extend(string(b""));
+        krl.push(1);
+        krl.extend(string(&certs));
+        krl.push(2);
+        krl.extend(string(&string(&key_blob)));
+
+        let file = tempfile::NamedTempFile::new().expect("tempfile");
+        std::fs::write(file.path(), &krl).expect("write");
+
+        let store = CaTrustStore::new()
+            .ca(ca.public_key())
+            .revoked_keys(file.path())
+            .expect("load");
+
+        assert!(store.check("alice", &cert(&ca, 2, "alice")).await.is_ok());
+        assert!(store.check("alice", &cert(&ca, 4, "alice")).await.is_err());
+        assert!(store.check("alice", &cert(&ca, 7, "alice")).await.is_err());
+        assert!(store.check("alice", &revoked_key).await.is_err());
+
+        std::fs::remove_file(file.path()).expect("remove");
+        assert!(store.check("alice", &cert(&ca, 2, "alice")).await.is_err());
+    }
+
+    #[cfg(feature = "krl")]
+    #[tokio::test]
+    async fn text_revocation_lists_revoke_cas() {
+        let ca = generate();
+        let file = tempfile::NamedTempFile::new().expect("tempfile");
+        let line = ca.public_key().to_openssh().expect("openssh");
+        std::fs::write(file.path(), format!("# revoked\n{line}\n")).expect("write");
+
+        let store = CaTrustStore::new()
+            .ca(ca.public_key())
+            .revoked_keys(file.path())
+            .expect("load");
+
+        assert!(store.check("alice", &cert(&ca, 1, "alice")).await.is_err());
+    }
+}
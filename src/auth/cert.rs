@@ -1,10 +1,10 @@
 use russh::keys::Certificate;
 
-use crate::{Auth, BoxFuture};
+use crate::{Auth, BoxFuture, auth::AuthContext};
 
 /// Type erased certificate auth handler
 pub trait CertAuth: Send + Sync {
-    fn verify(&self, user: &str, cert: &Certificate) -> BoxFuture<Auth>;
+    fn verify(&self, user: &str, cert: &Certificate, ctx: &AuthContext) -> BoxFuture<Auth>;
 }
 
 impl<F, Fut> CertAuth for F
@@ -13,7 +13,7 @@ where
     Fut: Future + Send + 'static,
     Fut::Output: Into<Auth>,
 {
-    fn verify(&self, user: &str, cert: &Certificate) -> BoxFuture<Auth> {
+    fn verify(&self, user: &str, cert: &Certificate, _ctx: &AuthContext) -> BoxFuture<Auth> {
         let fut = (self)(user.to_string(), cert.clone());
         Box::pin(async move { fut.await.into() })
     }
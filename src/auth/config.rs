@@ -1,21 +1,54 @@
 use std::sync::Arc;
 
-use russh::{MethodKind, MethodSet};
+use russh::{MethodKind, MethodSet, keys::ssh_key::public::KeyData};
 
-use crate::auth::{CertAuth, KeyboardInteractiveAuth, PasswordAuth, PubkeyAuth};
+use crate::auth::{
+    AnonymousAuth, AuthMethod, CertAuth, ErasedResolver, KeyPolicy, KeyboardInteractiveAuth,
+    PasswordAuth, PubkeyAuth, PubkeyOffered,
+};
 
 /// Configured authentication for a server
 #[derive(Default, Clone)]
 pub struct AuthConfig {
+    pub anonymous: Option<Arc<dyn AnonymousAuth>>,
     pub password: Option<Arc<dyn PasswordAuth>>,
     pub pubkey: Option<Arc<dyn PubkeyAuth>>,
+    /// Screens key offers before the client signs; see
+    /// [`Server::pubkey_offered`](crate::Server::pubkey_offered).
+    pub pubkey_offered: Option<Arc<dyn PubkeyOffered>>,
     pub cert: Option<Arc<dyn CertAuth>>,
     pub keyboard_interactive: Option<Arc<dyn KeyboardInteractiveAuth>>,
+    /// Alternative sets of methods that must all pass before a connection is
+    /// accepted, like OpenSSH's `AuthenticationMethods`. Empty means any one
+    /// configured method is enough.
+    pub required: Vec<Vec<AuthMethod>>,
+    /// Turns the authenticated username into an account; see
+    /// [`Server::user_resolver`](crate::Server::user_resolver).
+    pub resolver: Option<Arc<dyn ErasedResolver>>,
+    /// Username patterns refused before any handler runs; see
+    /// [`Server::reject_users`](crate::Server::reject_users).
+    pub denied_users: Vec<String>,
+    /// Client key types refused before any handler runs; see
+    /// [`Server::key_policy`](crate::Server::key_policy).
+    pub key_policy: Option<KeyPolicy>,
 }
 
 impl AuthConfig {
+    /// Whether `user` matches one of the denied patterns.
+    pub fn denies(&self, user: &str) -> bool {
+        self.denied_users
+            .iter()
+            .any(|pattern| crate::pattern::glob(pattern, user))
+    }
+
+    /// Why the key policy refuses `key`, if it does.
+    pub fn refuses_key(&self, key: &KeyData) -> Option<String> {
+        self.key_policy.as_ref()?.check(key).err()
+    }
+
     pub fn is_empty(&self) -> bool {
-        self.password.is_none()
+        self.anonymous.is_none()
+            && self.password.is_none()
             && self.pubkey.is_none()
             && self.cert.is_none()
             && self.keyboard_interactive.is_none()
@@ -26,20 +59,109 @@ impl AuthConfig {
     /// keyboard-interactive even when no handler is configured.
     ///
     /// An open server (no handlers) accepts `none`; password and publickey
-    /// stay advertised for clients that skip `none`.
+    /// stay advertised for clients that skip `none`. With an anonymous
+    /// handler, `none` is advertised alongside the other configured methods.
     pub fn methods(&self) -> MethodSet {
+        self.methods_after(&[])
+    }
+
+    /// The methods still worth offering once `passed` have succeeded: those
+    /// completing some required set that `passed` is part of. Without
+    /// required sets this is every configured method.
+    pub fn methods_after(&self, passed: &[AuthMethod]) -> MethodSet {
+        let configured = self.configured();
+
+        if self.required.is_empty() {
+            return configured.as_slice().into();
+        }
+
+        let wanted: Vec<MethodKind> = self
+            .candidates(passed)
+            .flatten()
+            .filter(|method| !passed.contains(&factor(**method)))
+            .map(|method| kind(*method))
+            .collect();
+
+        configured
+            .into_iter()
+            .filter(|method| wanted.contains(method))
+            .collect::<Vec<_>>()
+            .as_slice()
+            .into()
+    }
+
+    /// Whether `passed` completes a required set, i.e. the connection can be
+    /// accepted.
+    pub fn satisfied(&self, passed: &[AuthMethod]) -> bool {
+        self.required.is_empty()
+            || self
+                .candidates(passed)
+                .any(|set| set.iter().all(|method| passed.contains(&factor(*method))))
+    }
+
+    /// Whether `method` succeeding moves `passed` toward some required set.
+    /// A success that fits no set doesn't count as a factor.
+    pub fn advances(&self, passed: &[AuthMethod], method: AuthMethod) -> bool {
+        let method = factor(method);
+
+        !passed.contains(&method)
+            && self
+                .candidates(passed)
+                .any(|set| set.iter().any(|m| factor(*m) == method))
+    }
+
+    /// Check the required sets against the configured handlers, so a typo
+    /// surfaces at startup instead of as a server nobody can log into.
+    pub fn validate(&self) -> crate::Result<()> {
+        let configured = self.configured();
+
+        for set in &self.required {
+            if set.is_empty() {
+                return Err(crate::Error::Config(
+                    "required auth methods must not be empty".into(),
+                ));
+            }
+
+            if let Some(method) = set
+                .iter()
+                .find(|m| **m == AuthMethod::None || !configured.contains(&kind(**m)))
+            {
+                return Err(crate::Error::Config(format!(
+                    "required auth method {method:?} has no handler configured"
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Required sets that still fit everything that has passed.
+    fn candidates<'a>(
+        &'a self,
+        passed: &'a [AuthMethod],
+    ) -> impl Iterator<Item = &'a Vec<AuthMethod>> + 'a {
+        self.required.iter().filter(move |set| {
+            passed
+                .iter()
+                .all(|done| set.iter().any(|m| factor(*m) == *done))
+        })
+    }
+
+    fn configured(&self) -> Vec<MethodKind> {
         if self.is_empty() {
-            let all = [
+            return vec![
                 MethodKind::None,
                 MethodKind::Password,
                 MethodKind::PublicKey,
             ];
-
-            return all.as_slice().into();
         }
 
         let mut methods: Vec<MethodKind> = vec![];
 
+        if self.anonymous.is_some() {
+            methods.push(MethodKind::None);
+        }
+
         if self.password.is_some() {
             methods.push(MethodKind::Password);
         }
@@ -54,7 +176,25 @@ impl AuthConfig {
             methods.push(MethodKind::KeyboardInteractive);
         }
 
-        methods.as_slice().into()
+        methods
+    }
+}
+
+/// A certificate is a public key for the purpose of required methods, as in
+/// OpenSSH: both arrive over `publickey`.
+pub const fn factor(method: AuthMethod) -> AuthMethod {
+    match method {
+        AuthMethod::Certificate => AuthMethod::PublicKey,
+        method => method,
+    }
+}
+
+pub const fn kind(method: AuthMethod) -> MethodKind {
+    match method {
+        AuthMethod::None => MethodKind::None,
+        AuthMethod::Password => MethodKind::Password,
+        AuthMethod::PublicKey | AuthMethod::Certificate => MethodKind::PublicKey,
+        AuthMethod::KeyboardInteractive => MethodKind::KeyboardInteractive,
     }
 }
 
@@ -79,4 +219,60 @@ mod tests {
         assert!(!methods.contains(&MethodKind::Password));
         assert!(!methods.contains(&MethodKind::None));
     }
+
+    fn mfa_config() -> AuthConfig {
+        AuthConfig {
+            password: Some(Arc::new(|_user: String, _pw: String| async { true })),
+            pubkey: Some(Arc::new(
+                |_user: String, _key: russh::keys::PublicKey| async { true },
+            )),
+            keyboard_interactive: Some(Arc::new(
+                |_user: String, _ch: crate::auth::Challenger| async { Ok(crate::Auth::accept()) },
+            )),
+            required: vec![
+                vec![AuthMethod::PublicKey, AuthMethod::Password],
+                vec![AuthMethod::PublicKey, AuthMethod::KeyboardInteractive],
+            ],
+            ..AuthConfig::default()
+        }
+    }
+
+    #[test]
+    fn required_sets_narrow_offered_methods() {
+        let config = mfa_config();
+
+        let after_key = config.methods_after(&[AuthMethod::PublicKey]);
+        assert!(!after_key.contains(&MethodKind::PublicKey));
+        assert!(after_key.contains(&MethodKind::Password));
+        assert!(after_key.contains(&MethodKind::KeyboardInteractive));
+
+        let after_password = config.methods_after(&[AuthMethod::Password]);
+        assert!(after_password.contains(&MethodKind::PublicKey));
+        assert!(!after_password.contains(&MethodKind::KeyboardInteractive));
+    }
+
+    #[test]
+    fn satisfied_once_a_whole_set_passes() {
+        let config = mfa_config();
+
+        assert!(!config.satisfied(&[AuthMethod::PublicKey]));
+        assert!(config.satisfied(&[AuthMethod::PublicKey, AuthMethod::KeyboardInteractive]));
+        assert!(!config.advances(&[AuthMethod::Password], AuthMethod::KeyboardInteractive));
+        assert!(config.advances(&[AuthMethod::Password], AuthMethod::Certificate));
+    }
+
+    #[test]
+    fn required_methods_need_handlers() {
+        let config = AuthConfig {
+            required: vec![vec![AuthMethod::Password]],
+            ..mfa_config()
+        };
+        assert!(config.validate().is_ok());
+
+        let config = AuthConfig {
+            password: None,
+            ..config
+        };
+        assert!(config.validate().is_err());
+    }
 }
@@ -0,0 +1,69 @@
+use std::net::SocketAddr;
+
+use crate::ClientFingerprint;
+
+/// The connection an auth attempt arrives on, passed to each
+/// [`AuthProvider`](crate::auth::AuthProvider) method — for policies that
+/// depend on where a client connects from, and for audit logs.
+#[derive(Debug, Clone)]
+pub struct AuthContext {
+    remote_addr: SocketAddr,
+    local_addr: SocketAddr,
+    client_version: String,
+    client_fingerprint: Option<ClientFingerprint>,
+    attempt: u32,
+}
+
+impl AuthContext {
+    pub(crate) const fn new(
+        remote_addr: SocketAddr,
+        local_addr: SocketAddr,
+        client_version: String,
+        client_fingerprint: Option<ClientFingerprint>,
+        attempt: u32,
+    ) -> Self {
+        Self {
+            remote_addr,
+            local_addr,
+            client_version,
+            client_fingerprint,
+            attempt,
+        }
+    }
+
+    /// The client's address.
+    #[must_use]
+    pub const fn remote_addr(&self) -> SocketAddr {
+        self.remote_addr
+    }
+
+    /// The server address the client connected to, e.g. to allow password
+    /// logins only on an internal interface.
+    #[must_use]
+    pub const fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// The identification string the client sent, e.g.
+    /// `SSH-2.0-OpenSSH_9.6`. Client-supplied, so good for logs and
+    /// heuristics, not for trust.
+    #[must_use]
+    pub fn client_version(&self) -> &str {
+        &self.client_version
+    }
+
+    /// The client's key exchange proposal and its HASSH fingerprint. `None`
+    /// if it couldn't be read, which real clients don't cause.
+    #[must_use]
+    pub const fn client_fingerprint(&self) -> Option<&ClientFingerprint> {
+        self.client_fingerprint.as_ref()
+    }
+
+    /// Which auth attempt on this connection this is, counting from 1.
+    /// Every handler call counts, whatever the method or outcome; declined
+    /// key offers don't.
+    #[must_use]
+    pub const fn attempt(&self) -> u32 {
+        self.attempt
+    }
+}
@@ -0,0 +1,190 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, LazyLock},
+};
+
+use argon2::{Argon2, PasswordHash, PasswordHasher, PasswordVerifier, password_hash::SaltString};
+
+use crate::BoxFuture;
+
+/// A ready-made password handler, accepted by
+/// [`password_auth`](crate::server::Server::password_auth) like any closure.
+pub type PasswordHandler = Box<dyn Fn(String, String) -> BoxFuture<bool> + Send + Sync>;
+
+/// Checked against when the user doesn't exist, so an unknown name takes as
+/// long to reject as a wrong password.
+static DECOY: LazyLock<String> = LazyLock::new(|| hash_argon2("decoy"));
+
+/// Whether `password` matches an Argon2 PHC string
+/// (`$argon2id$v=19$m=...`). The parameters are read from the hash itself.
+/// A malformed hash never matches.
+///
+/// Hashing is deliberately slow: from async code, call this on
+/// [`spawn_blocking`](tokio::task::spawn_blocking).
+#[must_use]
+pub fn verify_argon2(password: &str, hash: &str) -> bool {
+    PasswordHash::new(hash).is_ok_and(|hash| {
+        Argon2::default()
+            .verify_password(password.as_bytes(), &hash)
+            .is_ok()
+    })
+}
+
+/// Whether `password` matches a bcrypt hash (`$2b$12$...`, including the
+/// `$2y$` hashes `htpasswd -B` writes). A malformed hash never matches.
+///
+/// Like [`verify_argon2`], this is slow on purpose; keep it off the async
+/// runtime.
+#[must_use]
+pub fn verify_bcrypt(password: &str, hash: &str) -> bool {
+    bcrypt::verify(password, hash).unwrap_or(false)
+}
+
+/// Hash `password` with Argon2id and a random salt, for storing and later
+/// checking with [`verify_argon2`].
+#[must_use]
+#[expect(
+    clippy::missing_panics_doc,
+    reason = "the salt length and parameters are fixed, so hashing can't fail"
+)]
+pub fn hash_argon2(password: &str) -> String {
+    let salt =
+        SaltString::encode_b64(&rand::random::<[u8; 16]>()).expect("16 bytes is a valid salt");
+
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .expect("default parameters are valid")
+        .to_string()
+}
+
+#[derive(Clone)]
+enum Hash {
+    Argon2(String),
+    Bcrypt(String),
+}
+
+impl Hash {
+    fn parse(hash: &str) -> Option<Self> {
+        if PasswordHash::new(hash).is_ok_and(|h| h.algorithm.as_str().starts_with("argon2")) {
+            return Some(Self::Argon2(hash.to_string()));
+        }
+
+        hash.parse::<bcrypt::HashParts>()
+            .is_ok()
+            .then(|| Self::Bcrypt(hash.to_string()))
+    }
+
+    fn verify(&self, password: &str) -> bool {
+        match self {
+            Self::Argon2(hash) => verify_argon2(password, hash),
+            Self::Bcrypt(hash) => verify_bcrypt(password, hash),
+        }
+    }
+}
+
+/// A fixed set of users and their password hashes — Argon2 or bcrypt, mixed
+/// freely — for servers that don't need a user database.
+///
+/// ```
+/// # fn main() -> shenron::Result<()> {
+/// # use shenron::{Server, auth::{StaticUsers, hash_argon2}};
+/// let users = StaticUsers::new()
+///     .user("admin", hash_argon2("swordfish"))?
+///     .user("alice", "$2b$10$zotJKU9s0x.i9qFO0l6yte.dJ2wSnBvsg.7PCrXjS/JTcahjVbBRu")?;
+///
+/// let _server = Server::new().password_auth(users.handler());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Default)]
+pub struct StaticUsers {
+    users: HashMap<String, Hash>,
+}
+
+impl StaticUsers {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add `name`, replacing any earlier entry for it.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Config`](crate::Error::Config) if `hash` is neither
+    /// an Argon2 PHC string nor a bcrypt hash — most often a plaintext
+    /// password pasted by mistake.
+    pub fn user(mut self, name: impl Into<String>, hash: impl AsRef<str>) -> crate::Result<Self> {
+        let name = name.into();
+        let hash = Hash::parse(hash.as_ref()).ok_or_else(|| {
+            crate::Error::Config(format!("password hash for {name:?} isn't Argon2 or bcrypt"))
+        })?;
+
+        self.users.insert(name, hash);
+
+        Ok(self)
+    }
+
+    /// Whether `password` is `user`'s. Blocks while hashing, unknown users
+    /// included.
+    #[must_use]
+    pub fn verify(&self, user: &str, password: &str) -> bool {
+        self.users.get(user).map_or_else(
+            || {
+                let _ = verify_argon2(password, &DECOY);
+                false
+            },
+            |hash| hash.verify(password),
+        )
+    }
+
+    /// A handler for [`password_auth`](crate::server::Server::password_auth)
+    /// that runs each check on tokio's blocking pool.
+    #[must_use]
+    pub fn handler(self) -> PasswordHandler {
+        let users = Arc::new(self);
+
+        Box::new(move |user, password| {
+            let users = Arc::clone(&users);
+
+            Box::pin(async move {
+                tokio::task::spawn_blocking(move || users.verify(&user, &password))
+                    .await
+                    .unwrap_or(false)
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn argon2_round_trips() {
+        let hash = hash_argon2("hunter2");
+
+        assert!(verify_argon2("hunter2", &hash));
+        assert!(!verify_argon2("hunter3", &hash));
+        assert!(!verify_argon2("hunter2", "hunter2"));
+    }
+
+    #[test]
+    fn users_mix_hash_kinds() {
+        let bcrypt = bcrypt::hash("swordfish", 4).expect("bcrypt");
+        let users = StaticUsers::new()
+            .user("admin", &bcrypt)
+            .and_then(|users| users.user("alice", hash_argon2("hunter2")))
+            .expect("valid hashes");
+
+        assert!(users.verify("admin", "swordfish"));
+        assert!(users.verify("alice", "hunter2"));
+        assert!(!users.verify("alice", "swordfish"));
+        assert!(!users.verify("mallory", "hunter2"));
+    }
+
+    #[test]
+    fn plaintext_is_not_a_hash() {
+        assert!(StaticUsers::new().user("admin", "swordfish").is_err());
+    }
+}
@@ -0,0 +1,170 @@
+use russh::keys::{Algorithm, ssh_key::public::KeyData};
+
+/// Which client key types public-key and certificate auth will take,
+/// installed with [`Server::key_policy`](crate::Server::key_policy).
+///
+/// Keys outside the policy are turned away before any handler sees them:
+/// offers are declined (the client moves on to its next key), and signed
+/// attempts are rejected as failures with the reason logged. A
+/// certificate's key and its CA's key must both pass.
+///
+/// The default refuses DSA, RSA under 2048 bits, and advertising SHA-1
+/// RSA signatures; everything else is allowed.
+///
+/// ```
+/// # use shenron::{Algorithm, EcdsaCurve, auth::KeyPolicy};
+/// // Only Ed25519 and P-256.
+/// let _policy = KeyPolicy::new().allow_only([
+///     Algorithm::Ed25519,
+///     Algorithm::Ecdsa { curve: EcdsaCurve::NistP256 },
+/// ]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct KeyPolicy {
+    allowed: Option<Vec<Algorithm>>,
+    denied: Vec<Algorithm>,
+    min_rsa_bits: u32,
+    rsa_sha1: bool,
+}
+
+impl Default for KeyPolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl KeyPolicy {
+    /// Refuse DSA and RSA under 2048 bits, and stop advertising SHA-1 RSA
+    /// signatures.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            allowed: None,
+            denied: vec![Algorithm::Dsa],
+            min_rsa_bits: 2048,
+            rsa_sha1: false,
+        }
+    }
+
+    /// Accept only these key types. For RSA the hash is ignored:
+    /// `Algorithm::Rsa { hash: None }` allows every RSA key.
+    #[must_use]
+    pub fn allow_only(mut self, algorithms: impl IntoIterator<Item = Algorithm>) -> Self {
+        self.allowed = Some(algorithms.into_iter().collect());
+
+        self
+    }
+
+    /// Refuse this key type too.
+    #[must_use]
+    pub fn deny(mut self, algorithm: Algorithm) -> Self {
+        self.denied.push(algorithm);
+
+        self
+    }
+
+    /// Smallest RSA modulus accepted, in bits.
+    #[must_use]
+    pub const fn min_rsa_bits(mut self, bits: u32) -> Self {
+        self.min_rsa_bits = bits;
+
+        self
+    }
+
+    /// Whether to advertise `ssh-rsa` (RSA with SHA-1) signatures to
+    /// clients. Off by default, so clients that follow the advertised list
+    /// (OpenSSH does) sign with `rsa-sha2-256`/`512`. russh doesn't tell the
+    /// server which hash a signature used, so this steers clients rather
+    /// than enforcing; to rule SHA-1 out entirely, refuse RSA.
+    #[must_use]
+    pub const fn rsa_sha1(mut self, advertise: bool) -> Self {
+        self.rsa_sha1 = advertise;
+
+        self
+    }
+
+    pub(crate) const fn advertises_rsa_sha1(&self) -> bool {
+        self.rsa_sha1
+    }
+
+    /// `Ok` if `key` is allowed, else why not.
+    pub(crate) fn check(&self, key: &KeyData) -> Result<(), String> {
+        let algorithm = key.algorithm();
+        let same = |a: &Algorithm| match (a, &algorithm) {
+            (Algorithm::Rsa { .. }, Algorithm::Rsa { .. }) => true,
+            (a, b) => a == b,
+        };
+
+        if self.denied.iter().any(same)
+            || self.allowed.as_ref().is_some_and(|a| !a.iter().any(same))
+        {
+            return Err(format!("{} keys not allowed", algorithm.as_str()));
+        }
+
+        if let KeyData::Rsa(rsa) = key
+            && rsa.key_size() < self.min_rsa_bits
+        {
+            return Err(format!(
+                "{}-bit RSA key, below the {}-bit minimum",
+                rsa.key_size(),
+                self.min_rsa_bits
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use russh::keys::{EcdsaCurve, PrivateKey};
+
+    use super::*;
+
+    fn key(algorithm: Algorithm) -> KeyData {
+        PrivateKey::random(&mut rand::rng(), algorithm)
+            .expect("keygen")
+            .public_key()
+            .key_data()
+            .clone()
+    }
+
+    #[test]
+    fn default_allows_modern_keys() {
+        let policy = KeyPolicy::new();
+
+        assert!(policy.check(&key(Algorithm::Ed25519)).is_ok());
+        assert!(
+            policy
+                .check(&key(Algorithm::Ecdsa {
+                    curve: EcdsaCurve::NistP256
+                }))
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn allowlists_and_denials_match_by_key_type() {
+        let ed25519 = key(Algorithm::Ed25519);
+        let p256 = key(Algorithm::Ecdsa {
+            curve: EcdsaCurve::NistP256,
+        });
+
+        let only_ed25519 = KeyPolicy::new().allow_only([Algorithm::Ed25519]);
+        assert!(only_ed25519.check(&ed25519).is_ok());
+        assert!(only_ed25519.check(&p256).is_err());
+
+        let no_p384 = KeyPolicy::new().deny(Algorithm::Ecdsa {
+            curve: EcdsaCurve::NistP384,
+        });
+        assert!(no_p384.check(&p256).is_ok());
+
+        let no_p256 = KeyPolicy::new().deny(Algorithm::Ecdsa {
+            curve: EcdsaCurve::NistP256,
+        });
+        assert_eq!(
+            no_p256.check(&p256),
+            Err("ecdsa-sha2-nistp256 keys not allowed".into())
+        );
+    }
+}
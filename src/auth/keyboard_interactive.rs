@@ -1,6 +1,6 @@
 use tokio::sync::{mpsc, oneshot};
 
-use crate::{Auth, BoxFuture};
+use crate::{Auth, BoxFuture, auth::AuthContext};
 
 /// A single line shown to the user during a keyboard-interactive challenge.
 ///
@@ -91,7 +91,12 @@ pub fn channel() -> (Challenger, mpsc::Receiver<Challenge>) {
 
 /// Type-erased keyboard-interactive auth handler
 pub trait KeyboardInteractiveAuth: Send + Sync {
-    fn verify(&self, user: &str, challenger: Challenger) -> BoxFuture<crate::Result<Auth>>;
+    fn verify(
+        &self,
+        user: &str,
+        challenger: Challenger,
+        ctx: &AuthContext,
+    ) -> BoxFuture<crate::Result<Auth>>;
 }
 
 impl<F, Fut> KeyboardInteractiveAuth for F
@@ -99,7 +104,12 @@ where
     F: Fn(String, Challenger) -> Fut + Send + Sync,
     Fut: Future<Output = crate::Result<Auth>> + Send + 'static,
 {
-    fn verify(&self, user: &str, challenger: Challenger) -> BoxFuture<crate::Result<Auth>> {
+    fn verify(
+        &self,
+        user: &str,
+        challenger: Challenger,
+        _ctx: &AuthContext,
+    ) -> BoxFuture<crate::Result<Auth>> {
         Box::pin((self)(user.to_string(), challenger))
     }
 }
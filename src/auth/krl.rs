@@ -0,0 +1,328 @@
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    sync::{Mutex, MutexGuard},
+};
+
+use russh::keys::{
+    Certificate, HashAlg, PublicKey,
+    ssh_key::{Fingerprint, public::KeyData},
+};
+use sha1::{Digest, Sha1};
+
+use crate::auth::authorized_keys::{Stamp, blocking, stamp};
+
+const MAGIC: &[u8] = b"SSHKRL\n\0";
+const FORMAT_VERSION: u32 = 1;
+
+const SECTION_CERTIFICATES: u8 = 1;
+const SECTION_EXPLICIT_KEY: u8 = 2;
+const SECTION_FINGERPRINT_SHA1: u8 = 3;
+const SECTION_SIGNATURE: u8 = 4;
+const SECTION_FINGERPRINT_SHA256: u8 = 5;
+
+const CERT_SERIAL_LIST: u8 = 0x20;
+const CERT_SERIAL_RANGE: u8 = 0x21;
+const CERT_SERIAL_BITMAP: u8 = 0x22;
+const CERT_KEY_ID: u8 = 0x23;
+
+/// Revoked keys and certificates, from an OpenSSH key revocation list
+/// (`ssh-keygen -k`) or a plain file of public keys — the two formats sshd's
+/// `RevokedKeys` accepts.
+#[derive(Debug, Default)]
+pub struct Krl {
+    keys: HashSet<Vec<u8>>,
+    sha1: HashSet<Vec<u8>>,
+    sha256: HashSet<Vec<u8>>,
+    certs: Vec<CertRevocations>,
+}
+
+/// Certificates revoked under one CA, or under any CA when `ca` is `None`.
+#[derive(Debug, Default)]
+struct CertRevocations {
+    ca: Option<Vec<u8>>,
+    serials: Vec<(u64, u64)>,
+    key_ids: HashSet<String>,
+}
+
+impl Krl {
+    pub fn parse(input: &[u8]) -> crate::Result<Self> {
+        if input.starts_with(MAGIC) {
+            return Self::parse_binary(&input[MAGIC.len()..]);
+        }
+
+        let text = std::str::from_utf8(input)
+            .map_err(|_| crate::Error::Config("revoked keys: neither a KRL nor text".into()))?;
+
+        let mut krl = Self::default();
+
+        for (n, line) in text.lines().enumerate() {
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let key: PublicKey = line.parse().map_err(|err| {
+                crate::Error::Config(format!("revoked keys line {}: {err}", n + 1))
+            })?;
+
+            krl.keys.insert(blob(key.key_data()));
+        }
+
+        Ok(krl)
+    }
+
+    fn parse_binary(input: &[u8]) -> crate::Result<Self> {
+        let mut r = Reader(input);
+
+        if r.u32()? != FORMAT_VERSION {
+            return Err(malformed("unsupported format version"));
+        }
+
+        // KRL version, generation date, flags, reserved, comment.
+        r.u64()?;
+        r.u64()?;
+        r.u64()?;
+        r.string()?;
+        r.string()?;
+
+        let mut krl = Self::default();
+
+        while !r.0.is_empty() {
+            let kind = r.u8()?;
+            let mut section = Reader(r.string()?);
+
+            match kind {
+                SECTION_CERTIFICATES => krl.certs.push(CertRevocations::parse(&mut section)?),
+                SECTION_EXPLICIT_KEY => krl.keys.extend(section.strings()?),
+                SECTION_FINGERPRINT_SHA1 => krl.sha1.extend(section.strings()?),
+                SECTION_FINGERPRINT_SHA256 => krl.sha256.extend(section.strings()?),
+                // Signatures are optional and, like sshd, not checked:
+                // the file's integrity is the filesystem's job.
+                SECTION_SIGNATURE => {}
+                _ => return Err(malformed("unknown section")),
+            }
+        }
+
+        Ok(krl)
+    }
+
+    /// Whether `key` (a plain key, or a certificate's inner key) is revoked.
+    fn key_revoked(&self, key: &KeyData) -> bool {
+        let blob = blob(key);
+
+        self.keys.contains(&blob)
+            || self.sha1.contains(Sha1::digest(&blob).as_slice())
+            || self
+                .sha256
+                .contains(Fingerprint::new(HashAlg::Sha256, key).as_bytes())
+    }
+
+    /// Whether `cert` is revoked: its CA, its key, or the cert itself by
+    /// serial or key ID.
+    pub fn cert_revoked(&self, cert: &Certificate) -> bool {
+        let ca = blob(cert.signature_key());
+
+        self.key_revoked(cert.signature_key())
+            || self.key_revoked(cert.public_key())
+            || self
+                .certs
+                .iter()
+                .filter(|section| section.ca.as_ref().is_none_or(|c| *c == ca))
+                .any(|section| section.revokes(cert))
+    }
+}
+
+impl CertRevocations {
+    fn parse(r: &mut Reader) -> crate::Result<Self> {
+        let ca = r.string()?;
+        r.string()?;
+
+        let mut section = Self {
+            ca: (!ca.is_empty()).then(|| ca.to_vec()),
+            ..Self::default()
+        };
+
+        while !r.0.is_empty() {
+            let kind = r.u8()?;
+            let mut data = Reader(r.string()?);
+
+            match kind {
+                CERT_SERIAL_LIST => {
+                    while !data.0.is_empty() {
+                        let serial = data.u64()?;
+                        section.serials.push((serial, serial));
+                    }
+                }
+                CERT_SERIAL_RANGE => {
+                    let range = (data.u64()?, data.u64()?);
+                    section.serials.push(range);
+                }
+                CERT_SERIAL_BITMAP => {
+                    let offset = data.u64()?;
+                    let bitmap = data.string()?;
+
+                    // An mpint: big-endian, so bit 0 is the last byte's lowest.
+                    for (i, byte) in bitmap.iter().rev().enumerate() {
+                        for bit in 0..8 {
+                            if byte & (1 << bit) != 0 {
+                                let serial = offset + (i as u64) * 8 + bit;
+                                section.serials.push((serial, serial));
+                            }
+                        }
+                    }
+                }
+                CERT_KEY_ID => {
+                    for id in data.strings()? {
+                        let id = String::from_utf8(id).map_err(|_| malformed("key ID"))?;
+                        section.key_ids.insert(id);
+                    }
+                }
+                _ => return Err(malformed("unknown certificate section")),
+            }
+        }
+
+        Ok(section)
+    }
+
+    fn revokes(&self, cert: &Certificate) -> bool {
+        let serial = cert.serial();
+
+        self.serials
+            .iter()
+            .any(|&(min, max)| (min..=max).contains(&serial))
+            || self.key_ids.contains(cert.key_id())
+    }
+}
+
+/// A [`Krl`] file re-read when it changes, like
+/// [`authorized_keys_reloading`](crate::auth::authorized_keys_reloading).
+/// A deleted file revokes every certificate: better locked out than letting
+/// revoked certs back in.
+pub struct RevokedKeys {
+    path: PathBuf,
+    state: Mutex<(Option<Stamp>, Option<Krl>)>,
+}
+
+impl RevokedKeys {
+    pub fn load(path: PathBuf) -> crate::Result<Self> {
+        let stamp = stamp(&path)?;
+        let krl = read(&path)?;
+
+        Ok(Self {
+            path,
+            state: Mutex::new((Some(stamp), Some(krl))),
+        })
+    }
+
+    /// Whether `cert` is revoked, re-reading the list first if the file
+    /// changed. The checks and reads run on tokio's blocking pool, and the
+    /// lock is only held to compare and swap the list.
+    pub async fn revokes(&self, cert: &Certificate) -> bool {
+        let path = self.path.clone();
+
+        // A check that panicked leaves the list as it was.
+        if let Ok(current) = blocking(move || Ok(stamp(&path).ok())).await {
+            let changed = current != self.state().0;
+
+            if changed {
+                self.reload(current).await;
+            }
+        }
+
+        self.state()
+            .1
+            .as_ref()
+            .is_none_or(|krl| krl.cert_revoked(cert))
+    }
+
+    async fn reload(&self, current: Option<Stamp>) {
+        if current.is_none() {
+            tracing::warn!(path = %self.path.display(), "revoked keys file removed, refusing all certificates");
+            *self.state() = (None, None);
+
+            return;
+        }
+
+        let path = self.path.clone();
+
+        match blocking(move || read(&path)).await {
+            Ok(krl) => {
+                tracing::info!(path = %self.path.display(), "revoked keys reloaded");
+                *self.state() = (current, Some(krl));
+            }
+            // Leave the stamp stale so the next login retries.
+            Err(e) => tracing::warn!(
+                path = %self.path.display(),
+                "revoked keys reload failed, keeping previous list: {e}"
+            ),
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, (Option<Stamp>, Option<Krl>)> {
+        self.state.lock().expect("revoked keys state poisoned")
+    }
+}
+
+fn read(path: &Path) -> crate::Result<Krl> {
+    Krl::parse(&std::fs::read(path)?)
+}
+
+/// A key's wire encoding, the form KRLs list and hash.
+fn blob(key: &KeyData) -> Vec<u8> {
+    PublicKey::from(key.clone()).to_bytes().unwrap_or_default()
+}
+
+fn malformed(what: &str) -> crate::Error {
+    crate::Error::Config(format!("malformed KRL: {what}"))
+}
+
+/// Reads SSH wire-format fields off the front of a buffer.
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> crate::Result<&'a [u8]> {
+        if self.0.len() < n {
+            return Err(malformed("truncated"));
+        }
+
+        let (head, rest) = self.0.split_at(n);
+        self.0 = rest;
+
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> crate::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> crate::Result<u32> {
+        let bytes = self.take(4)?.try_into().map_err(|_| malformed("u32"))?;
+
+        Ok(u32::from_be_bytes(bytes))
+    }
+
+    fn u64(&mut self) -> crate::Result<u64> {
+        let bytes = self.take(8)?.try_into().map_err(|_| malformed("u64"))?;
+
+        Ok(u64::from_be_bytes(bytes))
+    }
+
+    fn string(&mut self) -> crate::Result<&'a [u8]> {
+        let len = self.u32()? as usize;
+
+        self.take(len)
+    }
+
+    /// Every remaining field, each a string.
+    fn strings(&mut self) -> crate::Result<Vec<Vec<u8>>> {
+        let mut strings = Vec::new();
+
+        while !self.0.is_empty() {
+            strings.push(self.string()?.to_vec());
+        }
+
+        Ok(strings)
+    }
+}
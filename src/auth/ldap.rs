@@ -0,0 +1,312 @@
+use std::{sync::Mutex, time::Duration};
+
+use ldap3::{Ldap, LdapConnAsync, LdapConnSettings, Scope, SearchEntry, dn_escape, ldap_escape};
+use tokio::sync::Semaphore;
+
+use crate::{
+    Auth,
+    auth::{AuthContext, AuthMethod, AuthProvider},
+};
+
+/// `invalidCredentials`: wrong password, unknown DN, or — on Active
+/// Directory — a disabled, expired, or locked account.
+const INVALID_CREDENTIALS: u32 = 49;
+
+/// Checks passwords against an LDAP directory or Active Directory. Requires
+/// the `ldap` feature.
+///
+/// Two ways to find the user's entry:
+///
+/// - [`bind`](Self::bind) fills the username into a DN template and binds
+///   as it. Simplest, and enough for AD's `user@domain` names.
+/// - [`search`](Self::search) looks the user up with a filter — optionally
+///   as a [`service_account`](Self::service_account) — and then binds as the
+///   one entry found. Use it when DNs don't follow the username.
+///
+/// Use an `ldaps://` URL or [`starttls`](Self::starttls) for TLS; servers
+/// are verified against the system's root certificates. Connections are
+/// pooled and reused across logins, up to [`pool_size`](Self::pool_size).
+///
+/// Empty passwords are always rejected: LDAP treats a bind with one as an
+/// anonymous bind, which succeeds. If the directory can't be reached the
+/// login is rejected and a warning logged.
+///
+/// ```no_run
+/// # use shenron::{Server, auth::LdapAuth};
+/// let ad = LdapAuth::bind("ldaps://dc.corp.example.com", "{user}@corp.example.com");
+///
+/// let _server = Server::new().auth_provider(ad);
+/// ```
+pub struct LdapAuth {
+    url: String,
+    lookup: Lookup,
+    starttls: bool,
+    timeout: Duration,
+    pool: Pool,
+}
+
+enum Lookup {
+    Bind {
+        template: String,
+    },
+    Search {
+        base: String,
+        filter: String,
+        service: Option<(String, String)>,
+    },
+}
+
+impl LdapAuth {
+    /// Bind as `template` with `{user}` replaced by the (DN-escaped)
+    /// username, e.g. `uid={user},ou=people,dc=example,dc=com`.
+    #[must_use]
+    pub fn bind(url: impl Into<String>, template: impl Into<String>) -> Self {
+        Self::with_lookup(
+            url.into(),
+            Lookup::Bind {
+                template: template.into(),
+            },
+        )
+    }
+
+    /// Search the subtree under `base` with `filter`, `{user}` replaced by
+    /// the (filter-escaped) username, e.g. `(&(objectClass=person)(uid={user}))`,
+    /// then bind as the entry found. The search is anonymous unless a
+    /// [`service_account`](Self::service_account) is set.
+    #[must_use]
+    pub fn search(
+        url: impl Into<String>,
+        base: impl Into<String>,
+        filter: impl Into<String>,
+    ) -> Self {
+        Self::with_lookup(
+            url.into(),
+            Lookup::Search {
+                base: base.into(),
+                filter: filter.into(),
+                service: None,
+            },
+        )
+    }
+
+    fn with_lookup(url: String, lookup: Lookup) -> Self {
+        Self {
+            url,
+            lookup,
+            starttls: false,
+            timeout: Duration::from_secs(10),
+            pool: Pool::new(8),
+        }
+    }
+
+    /// Bind as `dn` before searching. Ignored by [`bind`](Self::bind).
+    #[must_use]
+    pub fn service_account(mut self, dn: impl Into<String>, password: impl Into<String>) -> Self {
+        if let Lookup::Search { service, .. } = &mut self.lookup {
+            *service = Some((dn.into(), password.into()));
+        }
+
+        self
+    }
+
+    /// Upgrade `ldap://` connections with `StartTLS`. Off by default.
+    #[must_use]
+    pub const fn starttls(mut self, enabled: bool) -> Self {
+        self.starttls = enabled;
+
+        self
+    }
+
+    /// Limit on connecting and on each request. Defaults to 10 seconds.
+    #[must_use]
+    pub const fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+
+        self
+    }
+
+    /// Most connections open at once; further logins wait for one. Defaults
+    /// to 8, and is clamped to at least 1.
+    #[must_use]
+    pub fn pool_size(mut self, size: usize) -> Self {
+        self.pool = Pool::new(size.max(1));
+
+        self
+    }
+
+    /// Whether `password` is `user`'s. `Err` means the directory couldn't
+    /// answer, not that the password is wrong.
+    async fn check(&self, user: &str, password: &str) -> crate::Result<bool> {
+        if password.is_empty() {
+            return Ok(false);
+        }
+
+        let _permit = self.pool.permits.acquire().await.expect("never closed");
+        let mut ldap = match self.pool.take() {
+            Some(ldap) => ldap,
+            None => self.connect().await?,
+        };
+
+        let Some(dn) = self.find(&mut ldap, user).await? else {
+            self.pool.put(ldap);
+            return Ok(false);
+        };
+
+        let result = ldap
+            .with_timeout(self.timeout)
+            .simple_bind(&dn, password)
+            .await?;
+        let ok = match result.rc {
+            0 => true,
+            INVALID_CREDENTIALS => false,
+            _ => return Err(result.success().expect_err("rc is non-zero").into()),
+        };
+
+        self.pool.put(ldap);
+
+        Ok(ok)
+    }
+
+    /// The DN to bind as, or `None` if the search finds no single entry.
+    async fn find(&self, ldap: &mut Ldap, user: &str) -> crate::Result<Option<String>> {
+        let (base, filter, service) = match &self.lookup {
+            Lookup::Bind { template } => {
+                return Ok(Some(fill(template, &dn_escape(user))));
+            }
+            Lookup::Search {
+                base,
+                filter,
+                service,
+            } => (base, filter, service),
+        };
+
+        // A pooled connection is still bound as whoever logged in last.
+        let (dn, password) = service
+            .as_ref()
+            .map_or(("", ""), |(dn, pw)| (dn.as_str(), pw.as_str()));
+        ldap.with_timeout(self.timeout)
+            .simple_bind(dn, password)
+            .await?
+            .success()?;
+
+        let filter = fill(filter, &ldap_escape(user));
+        let (mut entries, _) = ldap
+            .with_timeout(self.timeout)
+            .search(base, Scope::Subtree, &filter, vec!["1.1"])
+            .await?
+            .success()?;
+
+        if entries.len() > 1 {
+            tracing::warn!(user, filter, "LDAP search matched several entries");
+        }
+
+        Ok(match (entries.pop(), entries.is_empty()) {
+            (Some(entry), true) => Some(SearchEntry::construct(entry).dn),
+            _ => None,
+        })
+    }
+
+    async fn connect(&self) -> crate::Result<Ldap> {
+        let settings = LdapConnSettings::new()
+            .set_conn_timeout(self.timeout)
+            .set_starttls(self.starttls);
+        let (conn, ldap) = LdapConnAsync::with_settings(settings, &self.url).await?;
+
+        tokio::spawn(async move {
+            if let Err(e) = conn.drive().await {
+                tracing::debug!(error = %e, "LDAP connection closed");
+            }
+        });
+
+        Ok(ldap)
+    }
+}
+
+/// `template` with each `{user}` replaced by the already-escaped `user`.
+#[expect(
+    clippy::literal_string_with_formatting_args,
+    reason = "`{user}` is our placeholder, not a format argument"
+)]
+fn fill(template: &str, user: &str) -> String {
+    template.replace("{user}", user)
+}
+
+impl AuthProvider for LdapAuth {
+    const METHODS: &'static [AuthMethod] = &[AuthMethod::Password];
+
+    async fn password(&self, user: &str, password: &str, _ctx: &AuthContext) -> Auth {
+        self.check(user, password).await.map_or_else(
+            |e| {
+                tracing::warn!(user, url = self.url, error = %e, "LDAP authentication failed to run");
+
+                Auth::reject()
+            },
+            Auth::from,
+        )
+    }
+}
+
+/// Idle connections, and permits capping how many exist at once.
+struct Pool {
+    idle: Mutex<Vec<Ldap>>,
+    permits: Semaphore,
+}
+
+impl Pool {
+    fn new(size: usize) -> Self {
+        Self {
+            idle: Mutex::new(Vec::with_capacity(size)),
+            permits: Semaphore::new(size),
+        }
+    }
+
+    /// An idle connection that's still open, if any.
+    fn take(&self) -> Option<Ldap> {
+        let mut idle = self.idle.lock().expect("LDAP pool poisoned");
+
+        while let Some(mut ldap) = idle.pop() {
+            if !ldap.is_closed() {
+                return Some(ldap);
+            }
+        }
+
+        None
+    }
+
+    fn put(&self, ldap: Ldap) {
+        self.idle.lock().expect("LDAP pool poisoned").push(ldap);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn usernames_are_escaped_into_templates() {
+        assert_eq!(
+            fill("uid={user},ou=people", &dn_escape("a,b")),
+            "uid=a\\2cb,ou=people"
+        );
+        assert_eq!(
+            fill("(uid={user})", &ldap_escape("*)(uid=*")),
+            "(uid=\\2a\\29\\28uid=\\2a)"
+        );
+    }
+
+    #[tokio::test]
+    async fn empty_passwords_never_reach_the_directory() {
+        let ldap = LdapAuth::bind("ldap://invalid.", "uid={user}");
+
+        assert!(!ldap.check("alice", "").await.expect("no connection made"));
+    }
+
+    #[tokio::test]
+    async fn unreachable_directory_is_an_error() {
+        let ldap =
+            LdapAuth::bind("ldap://127.0.0.1:1", "uid={user}").timeout(Duration::from_secs(1));
+
+        assert!(ldap.check("alice", "hunter2").await.is_err());
+        assert!(ldap.pool.take().is_none());
+    }
+}
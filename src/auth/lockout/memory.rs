@@ -0,0 +1,146 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use super::LockoutStore;
+
+/// Keys tracked at once. Past this, expired entries are swept and then the
+/// one closest to expiry is dropped, so a flood of distinct usernames can't
+/// grow memory without bound.
+const CAPACITY: usize = 65_536;
+
+/// In-process [`LockoutStore`]; the default for [`Lockout`](super::Lockout).
+/// Bans are lost on restart and not shared between servers.
+#[derive(Default)]
+pub struct MemoryStore {
+    failures: Mutex<HashMap<String, Failures>>,
+    bans: Mutex<HashMap<String, Instant>>,
+}
+
+struct Failures {
+    count: u32,
+    expires: Instant,
+}
+
+impl LockoutStore for MemoryStore {
+    async fn record_failure(&self, key: &str, window: Duration) -> crate::Result<u32> {
+        let mut failures = self.failures.lock().expect("lockout store poisoned");
+
+        Ok(count_failure(&mut failures, key, window))
+    }
+
+    async fn clear(&self, key: &str) -> crate::Result<()> {
+        self.failures
+            .lock()
+            .expect("lockout store poisoned")
+            .remove(key);
+
+        Ok(())
+    }
+
+    async fn ban(&self, key: &str, duration: Duration) -> crate::Result<()> {
+        let now = Instant::now();
+        let mut bans = self.bans.lock().expect("lockout store poisoned");
+
+        make_room(&mut bans, key, now, |until| *until);
+        bans.insert(key.to_string(), now + duration);
+        drop(bans);
+
+        Ok(())
+    }
+
+    async fn banned(&self, key: &str) -> crate::Result<Option<Duration>> {
+        let until = self
+            .bans
+            .lock()
+            .expect("lockout store poisoned")
+            .get(key)
+            .copied();
+
+        Ok(until
+            .map(|until| until.saturating_duration_since(Instant::now()))
+            .filter(|remaining| !remaining.is_zero()))
+    }
+}
+
+fn count_failure(failures: &mut HashMap<String, Failures>, key: &str, window: Duration) -> u32 {
+    let now = Instant::now();
+
+    make_room(failures, key, now, |f| f.expires);
+
+    let entry = failures.entry(key.to_string()).or_insert(Failures {
+        count: 0,
+        expires: now,
+    });
+
+    if entry.expires <= now {
+        entry.count = 0;
+    }
+
+    entry.count = entry.count.saturating_add(1);
+    entry.expires = now + window;
+
+    entry.count
+}
+
+/// Make space for `key` if it's new and the map is full.
+fn make_room<V>(
+    map: &mut HashMap<String, V>,
+    key: &str,
+    now: Instant,
+    expires: impl Fn(&V) -> Instant,
+) {
+    if map.len() < CAPACITY || map.contains_key(key) {
+        return;
+    }
+
+    map.retain(|_, v| expires(v) > now);
+
+    if map.len() >= CAPACITY
+        && let Some(soonest) = map
+            .iter()
+            .min_by_key(|(_, v)| expires(v))
+            .map(|(k, _)| k.clone())
+    {
+        map.remove(&soonest);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn failures_expire_after_the_window() {
+        let store = MemoryStore::default();
+
+        assert_eq!(
+            store.record_failure("ip:a", Duration::ZERO).await.ok(),
+            Some(1)
+        );
+        assert_eq!(
+            store.record_failure("ip:a", Duration::ZERO).await.ok(),
+            Some(1)
+        );
+
+        let window = Duration::from_mins(1);
+        store.record_failure("ip:b", window).await.expect("record");
+        assert_eq!(store.record_failure("ip:b", window).await.ok(), Some(2));
+    }
+
+    #[tokio::test]
+    async fn bans_run_out() {
+        let store = MemoryStore::default();
+
+        store.ban("ip:a", Duration::ZERO).await.expect("ban");
+        assert_eq!(store.banned("ip:a").await.expect("banned"), None);
+
+        store
+            .ban("ip:a", Duration::from_mins(1))
+            .await
+            .expect("ban");
+        assert!(store.banned("ip:a").await.expect("banned").is_some());
+    }
+}
@@ -0,0 +1,299 @@
+mod memory;
+#[cfg(feature = "redis")]
+mod redis;
+
+use std::{net::IpAddr, pin::Pin, sync::Arc, time::Duration};
+
+pub use memory::MemoryStore;
+#[cfg(feature = "redis")]
+pub use redis::RedisStore;
+
+/// Where [`Lockout`] keeps failure counts and bans.
+///
+/// Keys are opaque strings (`ip:203.0.113.7`, `user:alice`). The in-memory
+/// [`MemoryStore`] suits a single server; implement this over a shared
+/// database — or use `RedisStore` with the `redis` feature — so replicas
+/// see each other's bans.
+pub trait LockoutStore: Send + Sync + 'static {
+    /// Count a failure for `key` and return the total. The count is
+    /// forgotten once `window` passes with no further failures.
+    fn record_failure(
+        &self,
+        key: &str,
+        window: Duration,
+    ) -> impl Future<Output = crate::Result<u32>> + Send;
+
+    /// Forget `key`'s failures.
+    fn clear(&self, key: &str) -> impl Future<Output = crate::Result<()>> + Send;
+
+    /// Ban `key` for `duration`, replacing any ban already in place.
+    fn ban(&self, key: &str, duration: Duration) -> impl Future<Output = crate::Result<()>> + Send;
+
+    /// How much longer `key` is banned, or `None` if it isn't.
+    fn banned(&self, key: &str) -> impl Future<Output = crate::Result<Option<Duration>>> + Send;
+}
+
+type StoreFuture<'a, T> = Pin<Box<dyn Future<Output = crate::Result<T>> + Send + 'a>>;
+
+/// Object-safe view of a [`LockoutStore`].
+trait ErasedStore: Send + Sync {
+    fn record_failure<'a>(&'a self, key: &'a str, window: Duration) -> StoreFuture<'a, u32>;
+    fn clear<'a>(&'a self, key: &'a str) -> StoreFuture<'a, ()>;
+    fn ban<'a>(&'a self, key: &'a str, duration: Duration) -> StoreFuture<'a, ()>;
+    fn banned<'a>(&'a self, key: &'a str) -> StoreFuture<'a, Option<Duration>>;
+}
+
+impl<S: LockoutStore> ErasedStore for S {
+    fn record_failure<'a>(&'a self, key: &'a str, window: Duration) -> StoreFuture<'a, u32> {
+        Box::pin(LockoutStore::record_failure(self, key, window))
+    }
+
+    fn clear<'a>(&'a self, key: &'a str) -> StoreFuture<'a, ()> {
+        Box::pin(LockoutStore::clear(self, key))
+    }
+
+    fn ban<'a>(&'a self, key: &'a str, duration: Duration) -> StoreFuture<'a, ()> {
+        Box::pin(LockoutStore::ban(self, key, duration))
+    }
+
+    fn banned<'a>(&'a self, key: &'a str) -> StoreFuture<'a, Option<Duration>> {
+        Box::pin(LockoutStore::banned(self, key))
+    }
+}
+
+/// fail2ban-style lockout for repeated auth failures, installed with
+/// [`Server::lockout`](crate::Server::lockout).
+///
+/// Failures are counted per client IP (and optionally per username). Once a
+/// key reaches the [`threshold`](Self::threshold) it is banned, and each
+/// further failure while the count is remembered doubles the ban, up to
+/// [`max_ban`](Self::max_ban). A banned client is disconnected on its next
+/// attempt, before any auth handler runs. A successful login clears the
+/// username's count, never the IP's.
+///
+/// If the store errors, the attempt is let through and a warning logged: a
+/// store outage shouldn't lock everyone out.
+///
+/// ```
+/// # use std::time::Duration;
+/// # use shenron::auth::Lockout;
+/// let _lockout = Lockout::new()
+///     .threshold(3)
+///     .ban(Duration::from_secs(30))
+///     .per_user(true);
+/// ```
+#[derive(Clone)]
+pub struct Lockout {
+    store: Arc<dyn ErasedStore>,
+    threshold: u32,
+    window: Duration,
+    ban: Duration,
+    max_ban: Duration,
+    per_ip: bool,
+    per_user: bool,
+}
+
+impl Default for Lockout {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Lockout {
+    /// Per-IP lockout in memory: 5 failures within 30 minutes ban for a
+    /// minute, doubling per further failure up to 30 minutes.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::with_store(MemoryStore::default())
+    }
+
+    /// The same defaults as [`new`](Self::new), kept in `store`.
+    #[must_use]
+    pub fn with_store(store: impl LockoutStore) -> Self {
+        Self {
+            store: Arc::new(store),
+            threshold: 5,
+            window: Duration::from_mins(30),
+            ban: Duration::from_mins(1),
+            max_ban: Duration::from_mins(30),
+            per_ip: true,
+            per_user: false,
+        }
+    }
+
+    /// Failures that trigger the first ban. Clamped to at least 1.
+    #[must_use]
+    pub fn threshold(mut self, failures: u32) -> Self {
+        self.threshold = failures.max(1);
+
+        self
+    }
+
+    /// How long a failure is remembered after the most recent one.
+    #[must_use]
+    pub const fn window(mut self, window: Duration) -> Self {
+        self.window = window;
+
+        self
+    }
+
+    /// Length of the first ban.
+    #[must_use]
+    pub const fn ban(mut self, duration: Duration) -> Self {
+        self.ban = duration;
+
+        self
+    }
+
+    /// Cap on how long repeated failures can stretch a ban.
+    #[must_use]
+    pub const fn max_ban(mut self, duration: Duration) -> Self {
+        self.max_ban = duration;
+
+        self
+    }
+
+    /// Count failures per client IP. On by default.
+    #[must_use]
+    pub const fn per_ip(mut self, enabled: bool) -> Self {
+        self.per_ip = enabled;
+
+        self
+    }
+
+    /// Count failures per username too, which catches attacks spread across
+    /// many addresses. Off by default: anyone can then lock a user out by
+    /// failing as them.
+    #[must_use]
+    pub const fn per_user(mut self, enabled: bool) -> Self {
+        self.per_user = enabled;
+
+        self
+    }
+
+    fn keys(&self, ip: IpAddr, user: &str) -> Vec<String> {
+        let mut keys = Vec::with_capacity(2);
+
+        if self.per_ip {
+            keys.push(format!("ip:{}", ip.to_canonical()));
+        }
+
+        if self.per_user {
+            keys.push(format!("user:{user}"));
+        }
+
+        keys
+    }
+
+    /// How much longer `ip` or `user` is banned, whichever is longer.
+    pub(crate) async fn banned(&self, ip: IpAddr, user: &str) -> Option<Duration> {
+        let mut longest = None;
+
+        for key in self.keys(ip, user) {
+            match self.store.banned(&key).await {
+                Ok(remaining) => longest = longest.max(remaining),
+                Err(e) => tracing::warn!(key, error = %e, "lockout store failed, allowing attempt"),
+            }
+        }
+
+        longest
+    }
+
+    /// Count a failed attempt. Returns the ban it triggered, if any.
+    pub(crate) async fn failed(&self, ip: IpAddr, user: &str) -> Option<Duration> {
+        let mut longest = None;
+
+        for key in self.keys(ip, user) {
+            let result = async {
+                let count = self.store.record_failure(&key, self.window).await?;
+                let Some(ban) = self.ban_for(count) else {
+                    return Ok(None);
+                };
+
+                self.store.ban(&key, ban).await?;
+
+                Ok::<_, crate::Error>(Some(ban))
+            };
+
+            match result.await {
+                Ok(ban) => longest = longest.max(ban),
+                Err(e) => {
+                    tracing::warn!(key, error = %e, "lockout store failed, failure not counted");
+                }
+            }
+        }
+
+        longest
+    }
+
+    /// Forget the user's failures after a successful login.
+    pub(crate) async fn succeeded(&self, user: &str) {
+        if !self.per_user {
+            return;
+        }
+
+        let key = format!("user:{user}");
+
+        if let Err(e) = self.store.clear(&key).await {
+            tracing::warn!(key, error = %e, "lockout store failed to clear");
+        }
+    }
+
+    /// The ban earned by the `count`th failure: none below the threshold,
+    /// then doubling per failure beyond it.
+    fn ban_for(&self, count: u32) -> Option<Duration> {
+        let over = count.checked_sub(self.threshold)?;
+        let factor = 1u32.checked_shl(over).unwrap_or(u32::MAX);
+
+        Some(self.ban.saturating_mul(factor).min(self.max_ban))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use super::*;
+
+    const IP: IpAddr = IpAddr::V4(Ipv4Addr::new(203, 0, 113, 7));
+
+    #[test]
+    fn bans_double_up_to_the_cap() {
+        let lockout = Lockout::new()
+            .threshold(3)
+            .ban(Duration::from_secs(10))
+            .max_ban(Duration::from_secs(35));
+
+        assert_eq!(lockout.ban_for(2), None);
+        assert_eq!(lockout.ban_for(3), Some(Duration::from_secs(10)));
+        assert_eq!(lockout.ban_for(4), Some(Duration::from_secs(20)));
+        assert_eq!(lockout.ban_for(5), Some(Duration::from_secs(35)));
+        assert_eq!(lockout.ban_for(100), Some(Duration::from_secs(35)));
+    }
+
+    #[tokio::test]
+    async fn reaching_the_threshold_bans_the_ip() {
+        let lockout = Lockout::new().threshold(2);
+
+        assert_eq!(lockout.failed(IP, "alice").await, None);
+        assert_eq!(lockout.banned(IP, "alice").await, None);
+
+        assert_eq!(
+            lockout.failed(IP, "bob").await,
+            Some(Duration::from_mins(1))
+        );
+        assert!(lockout.banned(IP, "carol").await.is_some());
+    }
+
+    #[tokio::test]
+    async fn success_clears_only_the_user() {
+        let lockout = Lockout::new().threshold(2).per_ip(false).per_user(true);
+
+        lockout.failed(IP, "alice").await;
+        lockout.succeeded("alice").await;
+
+        assert_eq!(lockout.failed(IP, "alice").await, None);
+        assert!(lockout.failed(IP, "alice").await.is_some());
+        assert_eq!(lockout.banned(IP, "bob").await, None);
+    }
+}
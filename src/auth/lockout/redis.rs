@@ -0,0 +1,116 @@
+use std::time::Duration;
+
+use redis::{AsyncCommands, aio::ConnectionManager};
+
+use super::LockoutStore;
+
+/// [`LockoutStore`] in Redis, so every replica behind a load balancer sees
+/// the same counts and bans. Requires the `redis` feature.
+///
+/// Counts and bans are plain keys with a TTL, so Redis expires them on its
+/// own.
+///
+/// ```no_run
+/// # async fn run() -> shenron::Result<()> {
+/// # use shenron::{Server, auth::{Lockout, RedisStore}};
+/// let client = redis::Client::open("redis://127.0.0.1/")?;
+/// let store = RedisStore::new(client).await?;
+///
+/// let _server = Server::new().lockout(Lockout::with_store(store));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct RedisStore {
+    conn: ConnectionManager,
+    prefix: String,
+}
+
+impl RedisStore {
+    /// Connect through `client`; the connection reconnects on its own.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the first connection can't be made.
+    pub async fn new(client: redis::Client) -> crate::Result<Self> {
+        Ok(Self {
+            conn: ConnectionManager::new(client).await?,
+            prefix: "shenron:lockout:".into(),
+        })
+    }
+
+    /// Prefix for every key, so servers can share a Redis without sharing
+    /// bans. Defaults to `shenron:lockout:`.
+    #[must_use]
+    pub fn prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = prefix.into();
+
+        self
+    }
+
+    fn failures_key(&self, key: &str) -> String {
+        format!("{}failures:{key}", self.prefix)
+    }
+
+    fn ban_key(&self, key: &str) -> String {
+        format!("{}ban:{key}", self.prefix)
+    }
+}
+
+/// Redis TTLs are whole milliseconds and must be positive.
+fn millis(duration: Duration) -> i64 {
+    i64::try_from(duration.as_millis())
+        .unwrap_or(i64::MAX)
+        .max(1)
+}
+
+impl LockoutStore for RedisStore {
+    async fn record_failure(&self, key: &str, window: Duration) -> crate::Result<u32> {
+        let key = self.failures_key(key);
+        let mut conn = self.conn.clone();
+
+        let (count,): (u32,) = redis::pipe()
+            .atomic()
+            .incr(&key, 1)
+            .pexpire(&key, millis(window))
+            .ignore()
+            .query_async(&mut conn)
+            .await?;
+
+        Ok(count)
+    }
+
+    async fn clear(&self, key: &str) -> crate::Result<()> {
+        let mut conn = self.conn.clone();
+
+        let () = conn.del(self.failures_key(key)).await?;
+
+        Ok(())
+    }
+
+    async fn ban(&self, key: &str, duration: Duration) -> crate::Result<()> {
+        let mut conn = self.conn.clone();
+
+        let () = redis::cmd("SET")
+            .arg(self.ban_key(key))
+            .arg(1)
+            .arg("PX")
+            .arg(millis(duration))
+            .query_async(&mut conn)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn banned(&self, key: &str) -> crate::Result<Option<Duration>> {
+        let mut conn = self.conn.clone();
+
+        // -2: no such key; -1: no TTL, which a ban never lacks.
+        let ttl: i64 = conn.pttl(self.ban_key(key)).await?;
+
+        Ok(u64::try_from(ttl)
+            .ok()
+            .filter(|ms| *ms > 0)
+            .map(Duration::from_millis))
+    }
+}
@@ -0,0 +1,12 @@
+/// The SSH authentication method a client used, or tried to use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AuthMethod {
+    /// The `none` method: no credentials. Accepted only by open servers;
+    /// clients also send it to discover which methods are available.
+    None,
+    Password,
+    PublicKey,
+    /// An OpenSSH certificate, carried over the `publickey` method.
+    Certificate,
+    KeyboardInteractive,
+}
@@ -1,18 +1,60 @@
+pub(crate) mod anonymous;
 pub(crate) mod authorized_keys;
+pub(crate) mod backoff;
+pub(crate) mod ca_store;
 pub(crate) mod cert;
 pub(crate) mod config;
+pub(crate) mod context;
+#[cfg(feature = "hashing")]
+pub(crate) mod hashing;
+pub(crate) mod key_policy;
 pub(crate) mod keyboard_interactive;
+#[cfg(feature = "krl")]
+pub(crate) mod krl;
+#[cfg(feature = "ldap")]
+pub(crate) mod ldap;
+pub(crate) mod lockout;
+pub(crate) mod method;
+#[cfg(feature = "oidc")]
+pub(crate) mod oidc;
 pub mod outcome;
+#[cfg(feature = "pam")]
+pub(crate) mod pam;
 pub(crate) mod password;
+pub(crate) mod provider;
 pub(crate) mod pubkey;
+pub(crate) mod resolver;
 pub(crate) mod trusted_ca;
 
-pub use authorized_keys::{PubkeyHandler, authorized_keys};
+pub(crate) use anonymous::*;
+pub use authorized_keys::{
+    PubkeyHandler, authorized_keys, authorized_keys_per_user, authorized_keys_reloading,
+};
+pub use backoff::AuthBackoff;
+pub use ca_store::CaTrustStore;
 pub(crate) use cert::*;
 pub(crate) use config::*;
+pub use context::AuthContext;
+#[cfg(feature = "hashing")]
+pub use hashing::{PasswordHandler, StaticUsers, hash_argon2, verify_argon2, verify_bcrypt};
+pub use key_policy::KeyPolicy;
 pub(crate) use keyboard_interactive::*;
 pub use keyboard_interactive::{Challenger, Prompt};
+#[cfg(feature = "ldap")]
+pub use ldap::LdapAuth;
+#[cfg(feature = "redis")]
+pub use lockout::RedisStore;
+pub use lockout::{Lockout, LockoutStore, MemoryStore};
+pub use method::AuthMethod;
+#[cfg(feature = "oidc")]
+pub use oidc::{OidcDeviceAuth, OidcIdentity};
 pub use outcome::*;
+#[cfg(feature = "pam")]
+pub use pam::{InteractivePamAuth, PamAuth};
 pub(crate) use password::*;
+pub use provider::AuthProvider;
+pub(crate) use provider::install as install_provider;
 pub(crate) use pubkey::*;
+pub(crate) use resolver::ErasedResolver;
+pub use resolver::UserResolver;
 pub use trusted_ca::{CertHandler, trusted_ca_keys};
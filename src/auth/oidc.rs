@@ -0,0 +1,405 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use tokio::{sync::OnceCell, time::Instant};
+
+use crate::{
+    Auth,
+    auth::{AuthContext, AuthMethod, AuthProvider, Challenger},
+};
+
+const DEVICE_CODE_GRANT: &str = "urn:ietf:params:oauth:grant-type:device_code";
+
+/// Single sign-on through an OIDC provider's device flow (RFC 8628), over
+/// keyboard-interactive. Requires the `oidc` feature.
+///
+/// The client is shown a URL and a short code, the user signs in with their
+/// browser, and the provider is polled until they finish. The login is
+/// accepted when the ID token's [`claim`](Self::claim) — the subject by
+/// default — equals the SSH username, and the [`OidcIdentity`] is attached to
+/// the session.
+///
+/// The ID token comes straight from the provider's token endpoint over TLS,
+/// which OIDC allows in place of checking its signature; its
+/// issuer, audience, and expiry are still checked.
+///
+/// Users can take minutes in the browser, so leave
+/// [`pre_auth_timeout`](crate::Server::pre_auth_timeout) generous enough.
+///
+/// ```no_run
+/// # use shenron::{Server, auth::OidcDeviceAuth};
+/// let sso = OidcDeviceAuth::new("https://accounts.example.com", "shenron-ssh")
+///     .claim("preferred_username");
+///
+/// let _server = Server::new().auth_provider(sso);
+/// ```
+pub struct OidcDeviceAuth {
+    issuer: String,
+    client_id: String,
+    client_secret: Option<String>,
+    scopes: Vec<String>,
+    claim: String,
+    http: reqwest::Client,
+    provider: OnceCell<Provider>,
+}
+
+/// Who signed in, attached to the session on accept; read it back with
+/// `session.get::<OidcIdentity>()`.
+#[derive(Debug, Clone)]
+pub struct OidcIdentity {
+    /// The `sub` claim: the provider's stable id for the user.
+    pub subject: String,
+    /// Every claim in the ID token.
+    pub claims: Map<String, Value>,
+}
+
+/// The parts of the provider's discovery document we use.
+#[derive(Deserialize)]
+struct Provider {
+    issuer: String,
+    device_authorization_endpoint: String,
+    token_endpoint: String,
+}
+
+#[derive(Deserialize)]
+struct DeviceAuthorization {
+    device_code: String,
+    user_code: String,
+    // Google's endpoint predates the RFC's name for this.
+    #[serde(alias = "verification_url")]
+    verification_uri: String,
+    verification_uri_complete: Option<String>,
+    expires_in: u64,
+    #[serde(default = "default_interval")]
+    interval: u64,
+}
+
+const fn default_interval() -> u64 {
+    5
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    id_token: Option<String>,
+    error: Option<String>,
+}
+
+/// What one poll of the token endpoint said.
+#[derive(Debug, PartialEq, Eq)]
+enum Poll {
+    Pending,
+    SlowDown,
+    Done(String),
+    Failed(String),
+}
+
+impl From<TokenResponse> for Poll {
+    fn from(response: TokenResponse) -> Self {
+        match (response.id_token, response.error.as_deref()) {
+            (Some(token), None) => Self::Done(token),
+            (_, Some("authorization_pending")) => Self::Pending,
+            (_, Some("slow_down")) => Self::SlowDown,
+            (_, Some(error)) => Self::Failed(error.to_string()),
+            (None, None) => Self::Failed("no ID token; is the openid scope allowed?".into()),
+        }
+    }
+}
+
+impl OidcDeviceAuth {
+    /// Sign in through `issuer`'s device flow as the public client
+    /// `client_id`. Endpoints are discovered from
+    /// `<issuer>/.well-known/openid-configuration` on first use.
+    #[must_use]
+    pub fn new(issuer: impl Into<String>, client_id: impl Into<String>) -> Self {
+        Self {
+            issuer: issuer.into(),
+            client_id: client_id.into(),
+            client_secret: None,
+            scopes: vec!["openid".into()],
+            claim: "sub".into(),
+            http: reqwest::Client::new(),
+            provider: OnceCell::new(),
+        }
+    }
+
+    /// Authenticate to the provider, for confidential clients.
+    #[must_use]
+    pub fn client_secret(mut self, secret: impl Into<String>) -> Self {
+        self.client_secret = Some(secret.into());
+
+        self
+    }
+
+    /// Request `scope` as well as `openid`, e.g. `profile` or `email` for
+    /// the claims [`claim`](Self::claim) names.
+    #[must_use]
+    pub fn scope(mut self, scope: impl Into<String>) -> Self {
+        self.scopes.push(scope.into());
+
+        self
+    }
+
+    /// The ID token claim that must equal the SSH username. Defaults to
+    /// `sub`; `preferred_username` or `email` read better, but only use one
+    /// the provider won't let users change to someone else's.
+    #[must_use]
+    pub fn claim(mut self, claim: impl Into<String>) -> Self {
+        self.claim = claim.into();
+
+        self
+    }
+
+    async fn provider(&self) -> crate::Result<&Provider> {
+        self.provider
+            .get_or_try_init(|| async {
+                let url = format!(
+                    "{}/.well-known/openid-configuration",
+                    self.issuer.trim_end_matches('/')
+                );
+
+                Ok(self
+                    .http
+                    .get(url)
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .json()
+                    .await?)
+            })
+            .await
+    }
+
+    /// POST `form` plus our client credentials to `url`.
+    async fn post<T: for<'de> Deserialize<'de>>(
+        &self,
+        url: &str,
+        form: &[(&str, &str)],
+    ) -> crate::Result<T> {
+        let mut request = self.http.post(url);
+        let mut fields = form.to_vec();
+
+        if let Some(secret) = &self.client_secret {
+            request = request.basic_auth(&self.client_id, Some(secret));
+        } else {
+            fields.push(("client_id", &self.client_id));
+        }
+
+        // Token errors arrive as 400s with a JSON body, so don't bail on status.
+        Ok(request.form(&fields).send().await?.json().await?)
+    }
+
+    async fn sign_in(&self, user: &str, mut challenger: Challenger) -> crate::Result<Auth> {
+        let provider = self.provider().await?;
+        let scope = self.scopes.join(" ");
+        let device: DeviceAuthorization = self
+            .post(
+                &provider.device_authorization_endpoint,
+                &[("scope", &scope)],
+            )
+            .await?;
+
+        let url = device
+            .verification_uri_complete
+            .as_deref()
+            .unwrap_or(&device.verification_uri);
+        let instructions = format!(
+            "To sign in, open {url}\nand enter the code {}\n",
+            device.user_code
+        );
+
+        // No prompts: the client shows the instructions and waits on us.
+        challenger
+            .challenge("Single sign-on", instructions, [])
+            .await?;
+
+        let deadline = Instant::now() + Duration::from_secs(device.expires_in);
+        let mut interval = Duration::from_secs(device.interval);
+
+        let token = loop {
+            tokio::time::sleep(interval).await;
+
+            if Instant::now() >= deadline {
+                return Ok(Auth::reject().reason("device code expired"));
+            }
+
+            let response: TokenResponse = self
+                .post(
+                    &provider.token_endpoint,
+                    &[
+                        ("grant_type", DEVICE_CODE_GRANT),
+                        ("device_code", &device.device_code),
+                    ],
+                )
+                .await?;
+
+            match Poll::from(response) {
+                Poll::Pending => {}
+                Poll::SlowDown => interval += Duration::from_secs(5),
+                Poll::Done(token) => break token,
+                Poll::Failed(error) => return Ok(Auth::reject().reason(error)),
+            }
+        };
+
+        Ok(self.admit(user, &provider.issuer, &token))
+    }
+
+    /// Check the ID token and that it names `user`.
+    fn admit(&self, user: &str, issuer: &str, token: &str) -> Auth {
+        let identity = match identity(token, issuer, &self.client_id, unix_now()) {
+            Ok(identity) => identity,
+            Err(reason) => return Auth::reject().reason(reason),
+        };
+
+        match identity.claims.get(&self.claim).and_then(Value::as_str) {
+            Some(name) if name == user => Auth::accept().with(identity),
+            _ => Auth::reject().reason(format!("{} claim doesn't match the username", self.claim)),
+        }
+    }
+}
+
+impl AuthProvider for OidcDeviceAuth {
+    const METHODS: &'static [AuthMethod] = &[AuthMethod::KeyboardInteractive];
+
+    async fn keyboard_interactive(
+        &self,
+        user: &str,
+        challenger: Challenger,
+        _ctx: &AuthContext,
+    ) -> crate::Result<Auth> {
+        match self.sign_in(user, challenger).await {
+            Err(crate::Error::Http(e)) => {
+                tracing::warn!(user, issuer = self.issuer, error = %e, "OIDC provider unreachable");
+
+                Ok(Auth::reject().reason("identity provider unreachable"))
+            }
+            result => result,
+        }
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+/// Decode an ID token's claims and check its issuer, audience, and expiry.
+fn identity(token: &str, issuer: &str, client_id: &str, now: u64) -> Result<OidcIdentity, String> {
+    let claims: Map<String, Value> = token
+        .split('.')
+        .nth(1)
+        .and_then(|payload| URL_SAFE_NO_PAD.decode(payload).ok())
+        .and_then(|json| serde_json::from_slice(&json).ok())
+        .ok_or("malformed ID token")?;
+
+    if claims.get("iss").and_then(Value::as_str) != Some(issuer) {
+        return Err("ID token from the wrong issuer".into());
+    }
+
+    let audience = match claims.get("aud") {
+        Some(Value::String(aud)) => aud == client_id,
+        Some(Value::Array(auds)) => auds.iter().any(|aud| aud.as_str() == Some(client_id)),
+        _ => false,
+    };
+
+    if !audience {
+        return Err("ID token for another client".into());
+    }
+
+    if claims
+        .get("exp")
+        .and_then(Value::as_u64)
+        .is_none_or(|exp| exp <= now)
+    {
+        return Err("ID token expired".into());
+    }
+
+    let subject = claims
+        .get("sub")
+        .and_then(Value::as_str)
+        .ok_or("ID token has no subject")?
+        .to_string();
+
+    Ok(OidcIdentity { subject, claims })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    const ISSUER: &str = "https://idp.example.com";
+
+    fn token(claims: &Value) -> String {
+        let header = URL_SAFE_NO_PAD.encode(r#"{"alg":"RS256"}"#);
+        let payload = URL_SAFE_NO_PAD.encode(claims.to_string());
+
+        format!("{header}.{payload}.c2ln")
+    }
+
+    #[test]
+    fn identity_checks_issuer_audience_and_expiry() {
+        let good = json!({ "iss": ISSUER, "aud": ["other", "ssh"], "exp": 200, "sub": "u-1" });
+        let identity = identity(&token(&good), ISSUER, "ssh", 100).expect("valid token");
+        assert_eq!(identity.subject, "u-1");
+
+        for (claim, value) in [
+            ("iss", json!("https://evil.example.com")),
+            ("aud", json!("other")),
+            ("exp", json!(100)),
+        ] {
+            let mut bad = good.clone();
+            bad[claim] = value;
+            assert!(
+                super::identity(&token(&bad), ISSUER, "ssh", 100).is_err(),
+                "{claim}"
+            );
+        }
+
+        assert!(super::identity("not-a-jwt", ISSUER, "ssh", 100).is_err());
+    }
+
+    #[test]
+    fn token_responses_map_to_polls() {
+        let poll = |id_token: Option<&str>, error: Option<&str>| {
+            Poll::from(TokenResponse {
+                id_token: id_token.map(Into::into),
+                error: error.map(Into::into),
+            })
+        };
+
+        assert_eq!(poll(None, Some("authorization_pending")), Poll::Pending);
+        assert_eq!(poll(None, Some("slow_down")), Poll::SlowDown);
+        assert_eq!(poll(Some("t"), None), Poll::Done("t".into()));
+        assert_eq!(
+            poll(None, Some("access_denied")),
+            Poll::Failed("access_denied".into())
+        );
+        assert!(matches!(poll(None, None), Poll::Failed(_)));
+    }
+
+    #[test]
+    fn the_claim_must_name_the_user() {
+        let sso = OidcDeviceAuth::new(ISSUER, "ssh").claim("preferred_username");
+        let claims = json!({
+            "iss": ISSUER, "aud": "ssh", "exp": u64::MAX, "sub": "u-1",
+            "preferred_username": "alice",
+        });
+
+        assert!(
+            sso.admit("alice", ISSUER, &token(&claims))
+                .into_parts()
+                .0
+                .passed()
+        );
+        assert!(
+            !sso.admit("bob", ISSUER, &token(&claims))
+                .into_parts()
+                .0
+                .passed()
+        );
+    }
+}
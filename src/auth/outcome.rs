@@ -1,9 +1,9 @@
 use std::any::Any;
 
-use crate::Extensions;
+use crate::{Extensions, auth::AuthMethod};
 
-/// The outcome of an auth handler: accept or reject, plus any typed data to
-/// attach to the session on accept.
+/// The outcome of an auth handler: accept, reject, or pass one factor of
+/// several, plus any typed data to attach to the session on accept.
 ///
 /// Plain `-> bool` closures keep working through `From<bool>`; reach for
 /// [`accept`](Self::accept) and [`with`](Self::with) only when you want to
@@ -15,16 +15,49 @@ use crate::Extensions;
 /// struct Account { id: u32 }
 /// let _ = Auth::accept().with(Account { id: 7 });
 /// ```
+#[doc(alias = "AuthResult")]
 pub struct Auth {
-    accepted: bool,
+    verdict: Verdict,
     extensions: Extensions,
 }
 
+/// What an [`Auth`] decided, as the connection acts on it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Verdict {
+    Accept,
+    /// The credential is good, but the client must also pass one of these.
+    Partial(Vec<AuthMethod>),
+    Reject {
+        reason: Option<String>,
+        retry: bool,
+    },
+}
+
+impl Verdict {
+    /// Whether the credential itself checked out (fully or as one factor).
+    pub(crate) const fn passed(&self) -> bool {
+        matches!(self, Self::Accept | Self::Partial(_))
+    }
+}
+
+impl From<bool> for Verdict {
+    fn from(accepted: bool) -> Self {
+        if accepted {
+            Self::Accept
+        } else {
+            Self::Reject {
+                reason: None,
+                retry: true,
+            }
+        }
+    }
+}
+
 impl Auth {
     #[must_use]
     pub fn accept() -> Self {
         Self {
-            accepted: true,
+            verdict: Verdict::Accept,
             extensions: Extensions::default(),
         }
     }
@@ -32,11 +65,58 @@ impl Auth {
     #[must_use]
     pub fn reject() -> Self {
         Self {
-            accepted: false,
+            verdict: Verdict::from(false),
+            extensions: Extensions::default(),
+        }
+    }
+
+    /// The credential is good, but not enough on its own: the client must
+    /// also pass one of `remaining` before it's let in, and is told so with
+    /// `partial_success`. Data attached with [`with`](Self::with) is kept for
+    /// the session. If the server has none of `remaining` configured, the
+    /// login is rejected instead, and a warning logged.
+    ///
+    /// For a fixed policy (every login needs a key and a password) prefer
+    /// [`Server::require_auth_methods`](crate::Server::require_auth_methods);
+    /// this is for per-user decisions, like stepping up only admins.
+    ///
+    /// ```
+    /// # use shenron::{Auth, auth::AuthMethod};
+    /// let _ = Auth::partial([AuthMethod::KeyboardInteractive]);
+    /// ```
+    #[must_use]
+    pub fn partial(remaining: impl IntoIterator<Item = AuthMethod>) -> Self {
+        Self {
+            verdict: Verdict::Partial(remaining.into_iter().collect()),
             extensions: Extensions::default(),
         }
     }
 
+    /// Record why an attempt was rejected. SSH has no way to show the client
+    /// a reason, so it goes to the server log and to
+    /// [`ServerEvent::AuthFailed`](crate::ServerEvent::AuthFailed) for
+    /// auditing. No effect unless the outcome is a rejection.
+    #[must_use]
+    pub fn reason(mut self, reason: impl Into<String>) -> Self {
+        if let Verdict::Reject { reason: r, .. } = &mut self.verdict {
+            *r = Some(reason.into());
+        }
+
+        self
+    }
+
+    /// Reject and end the connection instead of letting the client try
+    /// again — for credentials that are known-bad rather than mistyped, like
+    /// a revoked key. No effect unless the outcome is a rejection.
+    #[must_use]
+    pub const fn no_retry(mut self) -> Self {
+        if let Verdict::Reject { retry, .. } = &mut self.verdict {
+            *retry = false;
+        }
+
+        self
+    }
+
     /// Attach a typed value, readable in the handler via
     /// [`Session::get`](crate::Session::get). No effect on a rejected outcome —
     /// rejected data is dropped.
@@ -46,12 +126,8 @@ impl Auth {
         self
     }
 
-    pub(crate) const fn accepted(&self) -> bool {
-        self.accepted
-    }
-
-    pub(crate) fn into_extensions(self) -> Extensions {
-        self.extensions
+    pub(crate) fn into_parts(self) -> (Verdict, Extensions) {
+        (self.verdict, self.extensions)
     }
 }
 
@@ -74,20 +150,43 @@ mod tests {
 
     #[test]
     fn accept_and_reject() {
-        assert!(Auth::accept().accepted());
-        assert!(!Auth::reject().accepted());
+        assert_eq!(Auth::accept().verdict, Verdict::Accept);
+        assert!(!Auth::reject().verdict.passed());
     }
 
     #[test]
     fn from_bool() {
-        assert!(Auth::from(true).accepted());
-        assert!(!Auth::from(false).accepted());
+        assert!(Auth::from(true).verdict.passed());
+        assert!(!Auth::from(false).verdict.passed());
     }
 
     #[test]
     fn with_attaches_data() {
-        let ext = Auth::accept().with(Account(42)).into_extensions();
+        let (_, ext) = Auth::accept().with(Account(42)).into_parts();
 
         assert_eq!(ext.get::<Account>().map(|a| a.0), Some(42));
     }
+
+    #[test]
+    fn rejection_details_only_apply_to_rejections() {
+        assert_eq!(
+            Auth::reject().reason("revoked").no_retry().verdict,
+            Verdict::Reject {
+                reason: Some("revoked".into()),
+                retry: false,
+            }
+        );
+        assert_eq!(
+            Auth::accept().reason("ignored").no_retry().verdict,
+            Verdict::Accept
+        );
+    }
+
+    #[test]
+    fn partial_passes_with_remaining_methods() {
+        let verdict = Auth::partial([AuthMethod::Password]).verdict;
+
+        assert!(verdict.passed());
+        assert_eq!(verdict, Verdict::Partial(vec![AuthMethod::Password]));
+    }
 }
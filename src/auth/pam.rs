@@ -0,0 +1,457 @@
+#![expect(unsafe_code, reason = "FFI to the host's libpam")]
+
+use std::{
+    ffi::{CStr, CString, c_char, c_int, c_void},
+    ptr,
+};
+
+use tokio::runtime::Handle;
+
+use crate::{
+    Auth,
+    auth::{AuthContext, AuthMethod, AuthProvider, Challenger, Prompt},
+};
+
+const PAM_SUCCESS: c_int = 0;
+const PAM_BUF_ERR: c_int = 5;
+const PAM_CONV_ERR: c_int = 19;
+
+const PAM_PROMPT_ECHO_OFF: c_int = 1;
+const PAM_PROMPT_ECHO_ON: c_int = 2;
+
+const PAM_SILENT: c_int = 0x8000;
+const PAM_DISALLOW_NULL_AUTHTOK: c_int = 0x0001;
+
+#[repr(C)]
+struct PamHandle {
+    _private: [u8; 0],
+}
+
+#[repr(C)]
+struct PamMessage {
+    msg_style: c_int,
+    msg: *const c_char,
+}
+
+#[repr(C)]
+struct PamResponse {
+    resp: *mut c_char,
+    resp_retcode: c_int,
+}
+
+type ConvFn = unsafe extern "C" fn(
+    num_msg: c_int,
+    msg: *const *const PamMessage,
+    resp: *mut *mut PamResponse,
+    appdata_ptr: *mut c_void,
+) -> c_int;
+
+#[repr(C)]
+struct PamConv {
+    conv: Option<ConvFn>,
+    appdata_ptr: *mut c_void,
+}
+
+// Declared by hand rather than through bindgen so building needs only
+// libpam itself, not its headers and a C toolchain.
+#[link(name = "pam")]
+unsafe extern "C" {
+    fn pam_start(
+        service_name: *const c_char,
+        user: *const c_char,
+        pam_conversation: *const PamConv,
+        pamh: *mut *mut PamHandle,
+    ) -> c_int;
+    fn pam_authenticate(pamh: *mut PamHandle, flags: c_int) -> c_int;
+    fn pam_acct_mgmt(pamh: *mut PamHandle, flags: c_int) -> c_int;
+    fn pam_end(pamh: *mut PamHandle, pam_status: c_int) -> c_int;
+    fn pam_strerror(pamh: *mut PamHandle, errnum: c_int) -> *const c_char;
+}
+
+/// Authenticates against the host's PAM stack, so shenron can front existing
+/// system accounts. Requires the `pam` feature and libpam at link time.
+///
+/// Passwords are checked by running the PAM service's `auth` and `account`
+/// stacks, answering every hidden prompt with the password — enough for
+/// `pam_unix` and friends. Stacks that ask for more (an OTP, a second
+/// factor) need [`interactive`](Self::interactive), which relays each PAM
+/// prompt to the client over keyboard-interactive.
+///
+/// PAM modules block, so every conversation runs on tokio's blocking pool.
+/// Reading `/etc/shadow` usually takes root, or membership of the `shadow`
+/// group.
+///
+/// ```no_run
+/// # use shenron::{Server, auth::PamAuth};
+/// let _server = Server::new().auth_provider(PamAuth::new("sshd"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct PamAuth {
+    service: String,
+}
+
+impl PamAuth {
+    /// Use the PAM service `service`, i.e. `/etc/pam.d/<service>`.
+    #[must_use]
+    pub fn new(service: impl Into<String>) -> Self {
+        Self {
+            service: service.into(),
+        }
+    }
+
+    /// Also offer keyboard-interactive, relaying the stack's own prompts.
+    #[must_use]
+    pub const fn interactive(self) -> InteractivePamAuth {
+        InteractivePamAuth(self)
+    }
+
+    /// Run `conv` through the stack on the blocking pool.
+    async fn run<C: Converse + Send + 'static>(&self, user: &str, conv: C) -> crate::Result<Auth> {
+        let service = self.service.clone();
+        let user = user.to_string();
+
+        tokio::task::spawn_blocking(move || authenticate(&service, &user, conv))
+            .await
+            .map_err(|e| crate::Error::Panic(e.to_string()))?
+    }
+}
+
+impl AuthProvider for PamAuth {
+    const METHODS: &'static [AuthMethod] = &[AuthMethod::Password];
+
+    async fn password(&self, user: &str, password: &str, _ctx: &AuthContext) -> Auth {
+        let conv = PasswordConv {
+            password: password.to_string(),
+        };
+
+        self.run(user, conv).await.unwrap_or_else(|e| {
+            tracing::warn!(user, error = %e, "PAM authentication failed to run");
+
+            Auth::reject()
+        })
+    }
+}
+
+/// [`PamAuth`] that also answers keyboard-interactive, built with
+/// [`PamAuth::interactive`].
+#[derive(Debug, Clone)]
+pub struct InteractivePamAuth(PamAuth);
+
+impl AuthProvider for InteractivePamAuth {
+    const METHODS: &'static [AuthMethod] = &[AuthMethod::Password, AuthMethod::KeyboardInteractive];
+
+    async fn password(&self, user: &str, password: &str, ctx: &AuthContext) -> Auth {
+        self.0.password(user, password, ctx).await
+    }
+
+    async fn keyboard_interactive(
+        &self,
+        user: &str,
+        challenger: Challenger,
+        _ctx: &AuthContext,
+    ) -> crate::Result<Auth> {
+        let conv = InteractiveConv {
+            challenger,
+            runtime: Handle::current(),
+        };
+
+        self.0.run(user, conv).await
+    }
+}
+
+/// One PAM message: its style and text.
+struct Message {
+    style: c_int,
+    text: String,
+}
+
+impl Message {
+    const fn is_prompt(&self) -> bool {
+        matches!(self.style, PAM_PROMPT_ECHO_OFF | PAM_PROMPT_ECHO_ON)
+    }
+}
+
+/// Answers a PAM conversation: one reply per message (`None` for
+/// informational ones), or `None` to abort the conversation.
+trait Converse {
+    fn converse(&mut self, messages: &[Message]) -> Option<Vec<Option<String>>>;
+}
+
+/// The password method: hidden prompts get the password, informational
+/// messages are acknowledged, and a visible prompt — which a password can't
+/// answer — aborts, like sshd's password conversation.
+struct PasswordConv {
+    password: String,
+}
+
+impl Converse for PasswordConv {
+    fn converse(&mut self, messages: &[Message]) -> Option<Vec<Option<String>>> {
+        messages
+            .iter()
+            .map(|message| match message.style {
+                PAM_PROMPT_ECHO_OFF => Some(Some(self.password.clone())),
+                PAM_PROMPT_ECHO_ON => None,
+                _ => Some(None),
+            })
+            .collect()
+    }
+}
+
+/// Keyboard-interactive: each PAM round becomes one challenge, with any
+/// informational text as its instructions.
+struct InteractiveConv {
+    challenger: Challenger,
+    runtime: Handle,
+}
+
+impl Converse for InteractiveConv {
+    fn converse(&mut self, messages: &[Message]) -> Option<Vec<Option<String>>> {
+        let instructions = messages
+            .iter()
+            .filter(|m| !m.is_prompt())
+            .map(|m| m.text.as_str())
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let prompts = messages.iter().filter(|m| m.is_prompt()).map(|m| {
+            if m.style == PAM_PROMPT_ECHO_ON {
+                Prompt::echo(&m.text)
+            } else {
+                Prompt::hidden(&m.text)
+            }
+        });
+
+        // On a blocking-pool thread, so waiting on the client is fine here.
+        let mut answers = self
+            .runtime
+            .block_on(self.challenger.challenge("", instructions, prompts))
+            .ok()?
+            .into_iter();
+
+        messages
+            .iter()
+            .map(|m| {
+                if m.is_prompt() {
+                    answers.next().map(Some)
+                } else {
+                    Some(None)
+                }
+            })
+            .collect()
+    }
+}
+
+/// Run the `auth` and `account` stacks of `service` for `user`.
+fn authenticate<C: Converse>(service: &str, user: &str, mut conv: C) -> crate::Result<Auth> {
+    let service = CString::new(service)
+        .map_err(|_| crate::Error::Config("PAM service name contains NUL".into()))?;
+
+    let Ok(user) = CString::new(user) else {
+        return Ok(Auth::reject().reason("username contains NUL"));
+    };
+
+    let pam_conv = PamConv {
+        conv: Some(converse::<C>),
+        appdata_ptr: ptr::from_mut(&mut conv).cast(),
+    };
+
+    let mut pamh = ptr::null_mut();
+
+    // SAFETY: the strings and `pam_conv` outlive the handle, which is ended
+    // below before they drop; `conv` is only touched through the callback.
+    let status = unsafe {
+        pam_start(
+            service.as_ptr(),
+            user.as_ptr(),
+            &raw const pam_conv,
+            &raw mut pamh,
+        )
+    };
+
+    if status != PAM_SUCCESS {
+        return Err(crate::Error::Protocol(format!(
+            "pam_start failed ({status})"
+        )));
+    }
+
+    let flags = PAM_SILENT | PAM_DISALLOW_NULL_AUTHTOK;
+
+    // SAFETY: `pamh` was initialised by a successful `pam_start`.
+    let mut status = unsafe { pam_authenticate(pamh, flags) };
+
+    if status == PAM_SUCCESS {
+        // SAFETY: as above.
+        status = unsafe { pam_acct_mgmt(pamh, flags) };
+    }
+
+    let reason = (status != PAM_SUCCESS).then(|| {
+        // SAFETY: as above; PAM returns a static string or NULL.
+        let message = unsafe { pam_strerror(pamh, status) };
+
+        if message.is_null() {
+            format!("PAM error {status}")
+        } else {
+            // SAFETY: non-null, NUL-terminated, and static.
+            unsafe { CStr::from_ptr(message) }
+                .to_string_lossy()
+                .into_owned()
+        }
+    });
+
+    // SAFETY: as above; the handle isn't used again.
+    unsafe { pam_end(pamh, status) };
+
+    Ok(reason.map_or_else(Auth::accept, |reason| Auth::reject().reason(reason)))
+}
+
+/// The C conversation callback: decodes PAM's messages, asks `C` for
+/// answers, and hands them back in `malloc`ed memory that PAM frees.
+unsafe extern "C" fn converse<C: Converse>(
+    num_msg: c_int,
+    msg: *const *const PamMessage,
+    resp: *mut *mut PamResponse,
+    appdata_ptr: *mut c_void,
+) -> c_int {
+    let Ok(count) = usize::try_from(num_msg) else {
+        return PAM_CONV_ERR;
+    };
+
+    if count == 0 || msg.is_null() || resp.is_null() || appdata_ptr.is_null() {
+        return PAM_CONV_ERR;
+    }
+
+    // SAFETY: `appdata_ptr` is the `&mut C` set up in `authenticate`, which
+    // is blocked in PAM for as long as this runs.
+    let conv = unsafe { &mut *appdata_ptr.cast::<C>() };
+
+    // SAFETY: Linux-PAM and OpenPAM pass `count` valid message pointers.
+    let messages: Vec<Message> = (0..count)
+        .map(|i| unsafe {
+            let message = &**msg.add(i);
+            let text = if message.msg.is_null() {
+                String::new()
+            } else {
+                CStr::from_ptr(message.msg).to_string_lossy().into_owned()
+            };
+
+            Message {
+                style: message.msg_style,
+                text,
+            }
+        })
+        .collect();
+
+    let Some(answers) = conv.converse(&messages) else {
+        return PAM_CONV_ERR;
+    };
+
+    // SAFETY: plain allocation; zeroed, so unanswered slots are NULL.
+    let replies = unsafe { libc::calloc(count, size_of::<PamResponse>()) }.cast::<PamResponse>();
+
+    if replies.is_null() {
+        return PAM_BUF_ERR;
+    }
+
+    for (i, answer) in answers.into_iter().enumerate().take(count) {
+        let Some(answer) = answer else {
+            continue;
+        };
+
+        let Ok(answer) = CString::new(answer) else {
+            // SAFETY: `replies` holds `count` entries, each NULL or strdup'd.
+            unsafe { free_replies(replies, count) };
+
+            return PAM_CONV_ERR;
+        };
+
+        // SAFETY: `i < count`; PAM frees the copy with `free`.
+        unsafe {
+            (*replies.add(i)).resp = libc::strdup(answer.as_ptr());
+
+            if (*replies.add(i)).resp.is_null() {
+                free_replies(replies, count);
+
+                return PAM_BUF_ERR;
+            }
+        }
+    }
+
+    // SAFETY: `resp` is non-null; PAM takes ownership of `replies`.
+    unsafe { *resp = replies };
+
+    PAM_SUCCESS
+}
+
+/// Free a reply array that won't be handed to PAM.
+unsafe fn free_replies(replies: *mut PamResponse, count: usize) {
+    for i in 0..count {
+        // SAFETY: the caller passes an array of `count` entries, each NULL or
+        // `malloc`ed.
+        unsafe { libc::free((*replies.add(i)).resp.cast()) };
+    }
+
+    // SAFETY: allocated with `calloc` by the caller.
+    unsafe { libc::free(replies.cast()) };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(style: c_int, text: &str) -> Message {
+        Message {
+            style,
+            text: text.into(),
+        }
+    }
+
+    #[test]
+    fn password_answers_hidden_prompts_only() {
+        let mut conv = PasswordConv {
+            password: "hunter2".into(),
+        };
+
+        let answers = conv.converse(&[
+            message(4, "Last login: yesterday"),
+            message(PAM_PROMPT_ECHO_OFF, "Password: "),
+        ]);
+        assert_eq!(answers, Some(vec![None, Some("hunter2".into())]));
+
+        assert_eq!(
+            conv.converse(&[message(PAM_PROMPT_ECHO_ON, "Token: ")]),
+            None
+        );
+    }
+
+    #[test]
+    fn callback_hands_back_malloced_answers() {
+        let mut conv = PasswordConv {
+            password: "hunter2".into(),
+        };
+        let prompt = c"Password: ";
+        let message = PamMessage {
+            msg_style: PAM_PROMPT_ECHO_OFF,
+            msg: prompt.as_ptr(),
+        };
+        let messages = [&raw const message];
+        let mut replies = ptr::null_mut();
+
+        // SAFETY: one valid message, a valid out-pointer, and a live `conv`.
+        let status = unsafe {
+            converse::<PasswordConv>(
+                1,
+                messages.as_ptr(),
+                &raw mut replies,
+                ptr::from_mut(&mut conv).cast(),
+            )
+        };
+        assert_eq!(status, PAM_SUCCESS);
+
+        // SAFETY: the callback succeeded, so `replies` holds one answer.
+        let answer = unsafe { CStr::from_ptr((*replies).resp) };
+        assert_eq!(answer.to_str().ok(), Some("hunter2"));
+
+        // SAFETY: we own the replies PAM would otherwise free.
+        unsafe { free_replies(replies, 1) };
+    }
+}
@@ -1,8 +1,8 @@
-use crate::{Auth, BoxFuture};
+use crate::{Auth, BoxFuture, auth::AuthContext};
 
 /// Type-erased password auth handler
 pub trait PasswordAuth: Send + Sync {
-    fn verify(&self, user: &str, password: &str) -> BoxFuture<Auth>;
+    fn verify(&self, user: &str, password: &str, ctx: &AuthContext) -> BoxFuture<Auth>;
 }
 
 impl<F, Fut> PasswordAuth for F
@@ -11,9 +11,26 @@ where
     Fut: Future + Send + 'static,
     Fut::Output: Into<Auth>,
 {
-    fn verify(&self, user: &str, password: &str) -> BoxFuture<Auth> {
+    fn verify(&self, user: &str, password: &str, _ctx: &AuthContext) -> BoxFuture<Auth> {
         let fut = (self)(user.to_string(), password.to_string());
 
         Box::pin(async move { fut.await.into() })
     }
 }
+
+/// A password handler that also takes the attempt's [`AuthContext`], from
+/// [`Server::password_auth_with_context`](crate::Server::password_auth_with_context).
+pub struct PasswordWithContext<F>(pub F);
+
+impl<F, Fut> PasswordAuth for PasswordWithContext<F>
+where
+    F: Fn(String, String, AuthContext) -> Fut + Send + Sync,
+    Fut: Future + Send + 'static,
+    Fut::Output: Into<Auth>,
+{
+    fn verify(&self, user: &str, password: &str, ctx: &AuthContext) -> BoxFuture<Auth> {
+        let fut = (self.0)(user.to_string(), password.to_string(), ctx.clone());
+
+        Box::pin(async move { fut.await.into() })
+    }
+}
@@ -0,0 +1,205 @@
+use std::sync::Arc;
+
+use russh::keys::{Certificate, PublicKey};
+
+use crate::{
+    Auth, BoxFuture,
+    auth::{
+        AuthConfig, AuthContext, AuthMethod, CertAuth, Challenger, KeyboardInteractiveAuth,
+        PasswordAuth, PubkeyAuth, PubkeyOffered,
+    },
+};
+
+/// One value that answers several auth methods, for backends with state —
+/// a database pool, a cache, a client for an identity service.
+///
+/// Closures passed to [`Server::password_auth`](crate::Server::password_auth)
+/// and friends are the lighter option; a provider keeps the shared state in
+/// `self` instead of cloning it into each closure. List the methods it
+/// answers in [`METHODS`](Self::METHODS) and implement those; the rest reject
+/// by default and are never offered to clients.
+///
+/// Each method also gets the [`AuthContext`] — the client's address, version
+/// string, and attempt number — for policies closures can't see.
+///
+/// ```
+/// # use std::collections::HashMap;
+/// # use shenron::{Auth, auth::{AuthContext, AuthMethod, AuthProvider}};
+/// struct Accounts {
+///     passwords: HashMap<String, String>,
+/// }
+///
+/// impl AuthProvider for Accounts {
+///     const METHODS: &'static [AuthMethod] = &[AuthMethod::Password];
+///
+///     async fn password(&self, user: &str, password: &str, _ctx: &AuthContext) -> Auth {
+///         Auth::from(self.passwords.get(user).is_some_and(|p| p == password))
+///     }
+/// }
+/// ```
+pub trait AuthProvider: Send + Sync + 'static {
+    /// The methods this provider answers. Only these are installed; listing
+    /// [`AuthMethod::None`] has no effect.
+    const METHODS: &'static [AuthMethod];
+
+    fn password(
+        &self,
+        user: &str,
+        password: &str,
+        ctx: &AuthContext,
+    ) -> impl Future<Output = Auth> + Send {
+        let _ = (user, password, ctx);
+
+        async { Auth::reject() }
+    }
+
+    fn pubkey(
+        &self,
+        user: &str,
+        key: &PublicKey,
+        ctx: &AuthContext,
+    ) -> impl Future<Output = Auth> + Send {
+        let _ = (user, key, ctx);
+
+        async { Auth::reject() }
+    }
+
+    /// Screens a key offer before the client signs; see
+    /// [`Server::pubkey_offered`](crate::Server::pubkey_offered). Accepts
+    /// every key by default.
+    fn pubkey_offered(&self, user: &str, key: &PublicKey) -> impl Future<Output = bool> + Send {
+        let _ = (user, key);
+
+        async { true }
+    }
+
+    /// Called for OpenSSH certificates, after russh has checked the
+    /// signature and validity window; see
+    /// [`Server::cert_auth`](crate::Server::cert_auth).
+    fn openssh_cert(
+        &self,
+        user: &str,
+        cert: &Certificate,
+        ctx: &AuthContext,
+    ) -> impl Future<Output = Auth> + Send {
+        let _ = (user, cert, ctx);
+
+        async { Auth::reject() }
+    }
+
+    /// See [`Server::keyboard_interactive_auth`](crate::Server::keyboard_interactive_auth).
+    fn keyboard_interactive(
+        &self,
+        user: &str,
+        challenger: Challenger,
+        ctx: &AuthContext,
+    ) -> impl Future<Output = crate::Result<Auth>> + Send {
+        let _ = (user, challenger, ctx);
+
+        async { Ok(Auth::reject()) }
+    }
+}
+
+/// Adapts a shared provider to the per-method handler traits.
+struct Provided<P>(Arc<P>);
+
+impl<P: AuthProvider> PasswordAuth for Provided<P> {
+    fn verify(&self, user: &str, password: &str, ctx: &AuthContext) -> BoxFuture<Auth> {
+        let provider = Arc::clone(&self.0);
+        let (user, password, ctx) = (user.to_string(), password.to_string(), ctx.clone());
+
+        Box::pin(async move { provider.password(&user, &password, &ctx).await })
+    }
+}
+
+impl<P: AuthProvider> PubkeyAuth for Provided<P> {
+    fn verify(&self, user: &str, key: &PublicKey, ctx: &AuthContext) -> BoxFuture<Auth> {
+        let provider = Arc::clone(&self.0);
+        let (user, key, ctx) = (user.to_string(), key.clone(), ctx.clone());
+
+        Box::pin(async move { provider.pubkey(&user, &key, &ctx).await })
+    }
+}
+
+impl<P: AuthProvider> PubkeyOffered for Provided<P> {
+    fn consider(&self, user: &str, key: &PublicKey) -> BoxFuture<bool> {
+        let provider = Arc::clone(&self.0);
+        let (user, key) = (user.to_string(), key.clone());
+
+        Box::pin(async move { provider.pubkey_offered(&user, &key).await })
+    }
+}
+
+impl<P: AuthProvider> CertAuth for Provided<P> {
+    fn verify(&self, user: &str, cert: &Certificate, ctx: &AuthContext) -> BoxFuture<Auth> {
+        let provider = Arc::clone(&self.0);
+        let (user, cert, ctx) = (user.to_string(), cert.clone(), ctx.clone());
+
+        Box::pin(async move { provider.openssh_cert(&user, &cert, &ctx).await })
+    }
+}
+
+impl<P: AuthProvider> KeyboardInteractiveAuth for Provided<P> {
+    fn verify(
+        &self,
+        user: &str,
+        challenger: Challenger,
+        ctx: &AuthContext,
+    ) -> BoxFuture<crate::Result<Auth>> {
+        let provider = Arc::clone(&self.0);
+        let (user, ctx) = (user.to_string(), ctx.clone());
+
+        Box::pin(async move { provider.keyboard_interactive(&user, challenger, &ctx).await })
+    }
+}
+
+/// Install `provider` as the handler for each method it lists, replacing
+/// any handler already set for those methods.
+pub fn install<P: AuthProvider>(config: &mut AuthConfig, provider: P) {
+    let provider = Arc::new(provider);
+
+    for method in P::METHODS {
+        let handler = Arc::new(Provided(Arc::clone(&provider)));
+
+        match method {
+            AuthMethod::None => {}
+            AuthMethod::Password => config.password = Some(handler),
+            AuthMethod::PublicKey => {
+                config.pubkey_offered = Some(Arc::clone(&handler) as _);
+                config.pubkey = Some(handler);
+            }
+            AuthMethod::Certificate => config.cert = Some(handler),
+            AuthMethod::KeyboardInteractive => config.keyboard_interactive = Some(handler),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use russh::MethodKind;
+
+    use super::*;
+
+    struct KeysOnly;
+
+    impl AuthProvider for KeysOnly {
+        const METHODS: &'static [AuthMethod] = &[AuthMethod::PublicKey];
+
+        async fn pubkey(&self, user: &str, _key: &PublicKey, _ctx: &AuthContext) -> Auth {
+            Auth::from(user == "alice")
+        }
+    }
+
+    #[test]
+    fn only_listed_methods_are_installed() {
+        let mut config = AuthConfig::default();
+        install(&mut config, KeysOnly);
+
+        assert!(config.pubkey.is_some());
+        assert!(config.password.is_none());
+
+        let methods = config.methods();
+        assert!(methods.contains(&MethodKind::PublicKey));
+        assert!(!methods.contains(&MethodKind::Password));
+    }
+}
@@ -1,10 +1,10 @@
 use russh::keys::PublicKey;
 
-use crate::{Auth, BoxFuture};
+use crate::{Auth, BoxFuture, auth::AuthContext};
 
 /// Type erased pubkey auth handler
 pub trait PubkeyAuth: Send + Sync {
-    fn verify(&self, user: &str, key: &PublicKey) -> BoxFuture<Auth>;
+    fn verify(&self, user: &str, key: &PublicKey, ctx: &AuthContext) -> BoxFuture<Auth>;
 }
 
 impl<F, Fut> PubkeyAuth for F
@@ -13,8 +13,41 @@ where
     Fut: Future + Send + 'static,
     Fut::Output: Into<Auth>,
 {
-    fn verify(&self, user: &str, key: &PublicKey) -> BoxFuture<Auth> {
+    fn verify(&self, user: &str, key: &PublicKey, _ctx: &AuthContext) -> BoxFuture<Auth> {
         let fut = (self)(user.to_string(), key.clone());
         Box::pin(async move { fut.await.into() })
     }
 }
+
+/// A pubkey handler that also takes the attempt's [`AuthContext`], from
+/// [`Server::pubkey_auth_with_context`](crate::Server::pubkey_auth_with_context).
+pub struct PubkeyWithContext<F>(pub F);
+
+impl<F, Fut> PubkeyAuth for PubkeyWithContext<F>
+where
+    F: Fn(String, PublicKey, AuthContext) -> Fut + Send + Sync,
+    Fut: Future + Send + 'static,
+    Fut::Output: Into<Auth>,
+{
+    fn verify(&self, user: &str, key: &PublicKey, ctx: &AuthContext) -> BoxFuture<Auth> {
+        let fut = (self.0)(user.to_string(), key.clone(), ctx.clone());
+
+        Box::pin(async move { fut.await.into() })
+    }
+}
+
+/// Type erased check run on a key offer, before the client proves it holds
+/// the private key
+pub trait PubkeyOffered: Send + Sync {
+    fn consider(&self, user: &str, key: &PublicKey) -> BoxFuture<bool>;
+}
+
+impl<F, Fut> PubkeyOffered for F
+where
+    F: Fn(String, PublicKey) -> Fut + Send + Sync,
+    Fut: Future<Output = bool> + Send + 'static,
+{
+    fn consider(&self, user: &str, key: &PublicKey) -> BoxFuture<bool> {
+        Box::pin((self)(user.to_string(), key.clone()))
+    }
+}
@@ -0,0 +1,84 @@
+use std::{any::Any, pin::Pin};
+
+use crate::Extensions;
+
+type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// Turns an authenticated username into your application's account type,
+/// installed with [`Server::user_resolver`](crate::Server::user_resolver).
+///
+/// It runs once per connection, after every required auth method has
+/// passed and before the login is accepted. The account is attached to the
+/// session, so the app and middleware read it with
+/// `session.get::<Self::User>()` instead of looking the username up again.
+/// With no account for the user (`Ok(None)`) or a failed lookup, the login
+/// is refused.
+///
+/// ```no_run
+/// # use shenron::auth::UserResolver;
+/// # use std::path::PathBuf;
+/// #[derive(Clone)]
+/// struct Account {
+///     id: u64,
+///     roles: Vec<String>,
+///     home: PathBuf,
+/// }
+///
+/// struct Directory;
+///
+/// impl UserResolver for Directory {
+///     type User = Account;
+///
+///     async fn resolve(&self, user: &str) -> shenron::Result<Option<Account>> {
+///         Ok((user == "alice").then(|| Account {
+///             id: 1,
+///             roles: vec!["admin".into()],
+///             home: "/srv/alice".into(),
+///         }))
+///     }
+/// }
+/// ```
+///
+/// A closure returning `Option<User>` works too.
+pub trait UserResolver: Send + Sync + 'static {
+    /// The account attached to the session.
+    type User: Any + Clone + Send + Sync;
+
+    /// Look up `user`'s account, or `None` if they don't have one.
+    fn resolve(&self, user: &str)
+    -> impl Future<Output = crate::Result<Option<Self::User>>> + Send;
+}
+
+impl<F, Fut, U> UserResolver for F
+where
+    F: Fn(String) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Option<U>> + Send,
+    U: Any + Clone + Send + Sync,
+{
+    type User = U;
+
+    async fn resolve(&self, user: &str) -> crate::Result<Option<U>> {
+        Ok((self)(user.to_string()).await)
+    }
+}
+
+/// Type-erased [`UserResolver`], handing the account over in an
+/// [`Extensions`] to merge into the session's.
+pub trait ErasedResolver: Send + Sync {
+    fn resolve<'a>(&'a self, user: &'a str) -> BoxFuture<'a, crate::Result<Option<Extensions>>>;
+}
+
+impl<R: UserResolver> ErasedResolver for R {
+    fn resolve<'a>(&'a self, user: &'a str) -> BoxFuture<'a, crate::Result<Option<Extensions>>> {
+        Box::pin(async move {
+            let account = UserResolver::resolve(self, user).await?;
+
+            Ok(account.map(|account| {
+                let mut extensions = Extensions::default();
+                extensions.insert(account);
+
+                extensions
+            }))
+        })
+    }
+}
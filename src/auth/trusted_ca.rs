@@ -45,7 +45,7 @@ pub fn trusted_ca_keys(path: impl AsRef<Path>) -> crate::Result<CertHandler> {
     }))
 }
 
-fn parse(input: &str) -> crate::Result<Vec<Fingerprint>> {
+pub fn parse(input: &str) -> crate::Result<Vec<Fingerprint>> {
     let mut fingerprints = vec![];
 
     for (n, line) in input.lines().enumerate() {
@@ -25,6 +25,26 @@ pub enum Error {
 
     #[error("Integer conversion error: {0}")]
     Int(#[from] std::num::TryFromIntError),
+
+    #[cfg(feature = "ldap")]
+    #[error("LDAP error: {0}")]
+    Ldap(#[from] ldap3::LdapError),
+
+    #[cfg(feature = "oidc")]
+    #[error("HTTP error: {0}")]
+    Http(#[from] reqwest::Error),
+
+    #[cfg(feature = "redis")]
+    #[error("Redis error: {0}")]
+    Redis(#[from] redis::RedisError),
+
+    #[cfg(feature = "tower")]
+    #[error("Tower error: {0}")]
+    Tower(tower::BoxError),
+
+    #[cfg(feature = "wasm")]
+    #[error("WASM error: {0}")]
+    Wasm(#[from] wasmtime::Error),
 }
 
 impl From<Error> for std::io::Error {
@@ -22,6 +22,9 @@ pub enum Event<M = ()> {
     App(M),
     /// The client sent EOF; no more input will arrive.
     Eof,
+    /// The client was silent for the session's
+    /// [idle timeout](crate::Session::set_idle_timeout).
+    IdleTimeout,
 }
 
 impl<M> From<crate::Event> for Event<M> {
@@ -31,6 +34,7 @@ impl<M> From<crate::Event> for Event<M> {
             crate::Event::Resize(size) => Self::Resize(size),
             crate::Event::Signal(signal) => Self::Signal(signal),
             crate::Event::Eof => Self::Eof,
+            crate::Event::IdleTimeout => Self::IdleTimeout,
         }
     }
 }
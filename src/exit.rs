@@ -1,3 +1,5 @@
+use crate::Signal;
+
 /// What a session reports when the handler chain returns.
 ///
 /// Produced from handler return values via [`IntoExit`]; consumed by the
@@ -9,19 +11,72 @@ pub enum Exit {
     /// Exit with status 1; the error is logged by the server and visible to
     /// middleware inspecting the chain's result.
     Error(crate::Error),
+    /// Terminated by `signal`, reported with SSH's `exit-signal` instead of
+    /// an exit status — the way OpenSSH reports a command killed by a
+    /// signal. `message` is shown to the user by some clients.
+    Signal {
+        signal: Signal,
+        core_dumped: bool,
+        message: String,
+    },
 }
 
 impl Exit {
-    /// The status code this exit reports to the client.
+    /// The status code this exit reports to the client. For a signal, which
+    /// the client sees as such, the shell's convention of 128 plus the
+    /// signal number (128 alone for a custom signal) — for logs and events.
     #[must_use]
-    pub const fn code(&self) -> u32 {
+    pub fn code(&self) -> u32 {
         match self {
             Self::Code(code) => *code,
             Self::Error(_) => 1,
+            Self::Signal { signal, .. } => 128 + signal_number(signal).unwrap_or(0),
         }
     }
 }
 
+/// A signal's number on Unix, where the common ones agree.
+const fn signal_number(signal: &Signal) -> Option<u32> {
+    Some(match signal {
+        Signal::HUP => 1,
+        Signal::INT => 2,
+        Signal::QUIT => 3,
+        Signal::ILL => 4,
+        Signal::ABRT => 6,
+        Signal::FPE => 8,
+        Signal::KILL => 9,
+        Signal::USR1 if cfg!(target_os = "linux") => 10,
+        Signal::USR1 => 30,
+        Signal::SEGV => 11,
+        Signal::PIPE => 13,
+        Signal::ALRM => 14,
+        Signal::TERM => 15,
+        Signal::Custom(_) => return None,
+    })
+}
+
+/// The signal numbered `number`, as a custom one if SSH has no name for it.
+#[cfg(unix)]
+fn signal_named(number: i32) -> Signal {
+    [
+        Signal::HUP,
+        Signal::INT,
+        Signal::QUIT,
+        Signal::ILL,
+        Signal::ABRT,
+        Signal::FPE,
+        Signal::KILL,
+        Signal::USR1,
+        Signal::SEGV,
+        Signal::PIPE,
+        Signal::ALRM,
+        Signal::TERM,
+    ]
+    .into_iter()
+    .find(|signal| signal_number(signal).and_then(|n| i32::try_from(n).ok()) == Some(number))
+    .unwrap_or_else(|| Signal::Custom(number.to_string()))
+}
+
 /// Conversion from a handler's return value into an [`Exit`], in the spirit
 /// of [`std::process::Termination`] and axum's `IntoResponse`.
 ///
@@ -29,6 +84,8 @@ impl Exit {
 /// - `()` — exit 0
 /// - `u32` — exit with that code
 /// - [`Exit`] — passed through unchanged
+/// - [`std::process::ExitStatus`] — a child process's status: its code, or
+///   on Unix the signal that killed it
 /// - `Result<T, E>` where `T: IntoExit`, `E: Into<Error>` — `Ok` defers to
 ///   `T`, `Err` becomes [`Exit::Error`] (exit 1, logged)
 pub trait IntoExit {
@@ -53,6 +110,25 @@ impl IntoExit for Exit {
     }
 }
 
+impl IntoExit for std::process::ExitStatus {
+    fn into_exit(self) -> Exit {
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::ExitStatusExt;
+
+            if let Some(signal) = self.signal() {
+                return Exit::Signal {
+                    signal: signal_named(signal),
+                    core_dumped: self.core_dumped(),
+                    message: String::new(),
+                };
+            }
+        }
+
+        Exit::Code(self.code().map_or(1, i32::cast_unsigned))
+    }
+}
+
 impl<T: IntoExit, E: Into<crate::Error>> IntoExit for Result<T, E> {
     fn into_exit(self) -> Exit {
         match self {
@@ -91,4 +167,25 @@ mod tests {
         assert_eq!(exit.code(), 1);
         assert!(matches!(exit, Exit::Error(_)));
     }
+
+    #[cfg(unix)]
+    #[test]
+    fn killed_children_exit_with_their_signal() {
+        use std::os::unix::process::ExitStatusExt;
+
+        let exit = std::process::ExitStatus::from_raw(9).into_exit();
+
+        assert!(matches!(
+            exit,
+            Exit::Signal {
+                signal: Signal::KILL,
+                core_dumped: false,
+                ..
+            }
+        ));
+        assert_eq!(exit.code(), 137);
+
+        let exit = std::process::ExitStatus::from_raw(3 << 8).into_exit();
+        assert!(matches!(exit, Exit::Code(3)));
+    }
 }
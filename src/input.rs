@@ -1,12 +1,124 @@
-use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
-
-/// Input parsed out of one SSH data packet.
-#[derive(Debug)]
-pub(super) enum Input {
+//! Terminal input parsing: the bytes a client's terminal sends for keys
+//! and pastes, turned into [`KeyEvent`]s any handler can match on.
+//!
+//! ```no_run
+//! use shenron::{Session, input::{self, Input, KeyCode}};
+//!
+//! async fn menu(session: &mut Session) -> shenron::Result {
+//!     while let Some(data) = session.input().await {
+//!         for input in input::parse(&data) {
+//!             match input {
+//!                 Input::Key(key) if key.code == KeyCode::Up => { /* ... */ }
+//!                 Input::Key(key) if key.is_ctrl('c') => return Ok(()),
+//!                 _ => {}
+//!             }
+//!         }
+//!     }
+//!
+//!     Ok(())
+//! }
+//! ```
+
+use std::ops::{BitOr, BitOrAssign};
+
+/// A key or paste parsed out of client input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Input {
     Key(KeyEvent),
+    /// Text inserted via bracketed paste, as one piece rather than a key
+    /// per character.
     Paste(String),
 }
 
+/// A key press: which key, and the modifiers held with it.
+///
+/// Follows crossterm's conventions, and converts to its `KeyEvent` with the
+/// `ratatui` feature: control characters are the letter with
+/// [`CONTROL`](KeyModifiers::CONTROL), and an uppercase letter carries
+/// [`SHIFT`](KeyModifiers::SHIFT).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyEvent {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl KeyEvent {
+    #[must_use]
+    pub const fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        Self { code, modifiers }
+    }
+
+    /// Whether this is Ctrl plus `c`, e.g. `is_ctrl('c')` for an interrupt.
+    #[must_use]
+    pub fn is_ctrl(&self, c: char) -> bool {
+        self.code == KeyCode::Char(c) && self.modifiers.contains(KeyModifiers::CONTROL)
+    }
+}
+
+/// The keys terminals report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum KeyCode {
+    Char(char),
+    Enter,
+    Tab,
+    /// Shift+Tab.
+    BackTab,
+    Backspace,
+    Esc,
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    Delete,
+    Insert,
+    /// A function key, `F(1)` for F1.
+    F(u8),
+}
+
+/// Modifier keys held during a [`KeyEvent`]; combine with `|`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct KeyModifiers(u8);
+
+impl KeyModifiers {
+    pub const NONE: Self = Self(0);
+    pub const SHIFT: Self = Self(1);
+    pub const CONTROL: Self = Self(1 << 1);
+    pub const ALT: Self = Self(1 << 2);
+    pub const SUPER: Self = Self(1 << 3);
+    pub const HYPER: Self = Self(1 << 4);
+    pub const META: Self = Self(1 << 5);
+
+    /// Whether every modifier in `other` is held.
+    #[must_use]
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    #[must_use]
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+}
+
+impl BitOr for KeyModifiers {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for KeyModifiers {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
 const PASTE_START: &[u8] = b"\x1b[200~";
 const PASTE_END: &[u8] = b"\x1b[201~";
 
@@ -17,7 +129,8 @@ const MAX_SEQUENCE: usize = 32;
 /// Parse every key and paste out of a packet. Unknown sequences, mouse
 /// reports, and key releases are consumed and dropped, never mangled into
 /// phantom keys.
-pub(super) fn parse_input(data: &[u8]) -> Vec<Input> {
+#[must_use]
+pub fn parse(data: &[u8]) -> Vec<Input> {
     let mut inputs = Vec::new();
     let mut rest = data;
 
@@ -132,8 +245,7 @@ fn convert(event: terminput::Event) -> Option<Input> {
             convert_key(&key).map(Input::Key)
         }
         terminput::Event::Paste(text) => Some(Input::Paste(text)),
-        // Mouse, focus, resize-via-CSI, and key releases are not part of the
-        // Tui event surface.
+        // Mouse, focus, resize-via-CSI, and key releases aren't key input.
         _ => None,
     }
 }
@@ -183,13 +295,65 @@ fn convert_modifiers(modifiers: terminput::KeyModifiers) -> KeyModifiers {
     .fold(KeyModifiers::NONE, |acc, (_, to)| acc | to)
 }
 
+#[cfg(feature = "ratatui")]
+mod crossterm {
+    use ratatui::crossterm::event as ct;
+
+    use super::{KeyCode, KeyEvent, KeyModifiers};
+
+    impl From<KeyEvent> for ct::KeyEvent {
+        fn from(key: KeyEvent) -> Self {
+            Self::new(key.code.into(), key.modifiers.into())
+        }
+    }
+
+    impl From<KeyCode> for ct::KeyCode {
+        fn from(code: KeyCode) -> Self {
+            match code {
+                KeyCode::Char(c) => Self::Char(c),
+                KeyCode::Enter => Self::Enter,
+                KeyCode::Tab => Self::Tab,
+                KeyCode::BackTab => Self::BackTab,
+                KeyCode::Backspace => Self::Backspace,
+                KeyCode::Esc => Self::Esc,
+                KeyCode::Left => Self::Left,
+                KeyCode::Right => Self::Right,
+                KeyCode::Up => Self::Up,
+                KeyCode::Down => Self::Down,
+                KeyCode::Home => Self::Home,
+                KeyCode::End => Self::End,
+                KeyCode::PageUp => Self::PageUp,
+                KeyCode::PageDown => Self::PageDown,
+                KeyCode::Delete => Self::Delete,
+                KeyCode::Insert => Self::Insert,
+                KeyCode::F(n) => Self::F(n),
+            }
+        }
+    }
+
+    impl From<KeyModifiers> for ct::KeyModifiers {
+        fn from(modifiers: KeyModifiers) -> Self {
+            [
+                (KeyModifiers::SHIFT, Self::SHIFT),
+                (KeyModifiers::CONTROL, Self::CONTROL),
+                (KeyModifiers::ALT, Self::ALT),
+                (KeyModifiers::SUPER, Self::SUPER),
+                (KeyModifiers::HYPER, Self::HYPER),
+                (KeyModifiers::META, Self::META),
+            ]
+            .into_iter()
+            .filter(|(from, _)| modifiers.contains(*from))
+            .fold(Self::NONE, |acc, (_, to)| acc | to)
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::{Input, parse_input};
-    use ratatui::crossterm::event::{KeyCode, KeyModifiers};
+    use super::{Input, KeyCode, KeyModifiers, parse};
 
     fn keys(data: &[u8]) -> Vec<(KeyCode, KeyModifiers)> {
-        parse_input(data)
+        parse(data)
             .into_iter()
             .filter_map(|input| match input {
                 Input::Key(key) => Some((key.code, key.modifiers)),
@@ -200,7 +364,7 @@ mod tests {
 
     #[test]
     fn empty_input_is_nothing() {
-        assert!(parse_input(&[]).is_empty());
+        assert!(parse(&[]).is_empty());
     }
 
     #[test]
@@ -247,6 +411,15 @@ mod tests {
         assert_eq!(keys(b"\x1b[1;3A"), vec![(KeyCode::Up, KeyModifiers::ALT)]);
     }
 
+    #[test]
+    fn function_keys_parse() {
+        assert_eq!(keys(b"\x1bOP"), vec![(KeyCode::F(1), KeyModifiers::NONE)]);
+        assert_eq!(
+            keys(b"\x1b[15;5~"),
+            vec![(KeyCode::F(5), KeyModifiers::CONTROL)]
+        );
+    }
+
     #[test]
     fn bare_esc_is_esc() {
         assert_eq!(keys(b"\x1b"), vec![(KeyCode::Esc, KeyModifiers::NONE)]);
@@ -255,25 +428,25 @@ mod tests {
     #[test]
     fn mouse_reports_are_dropped() {
         // SGR mouse press: parsed by terminput as a mouse event, not a key.
-        assert!(parse_input(b"\x1b[<0;1;1M").is_empty());
+        assert!(parse(b"\x1b[<0;1;1M").is_empty());
     }
 
     #[test]
     fn unknown_csi_is_skipped_not_mangled() {
         // Private-mode set: not an input event in any protocol.
-        assert!(parse_input(b"\x1b[?2004h").is_empty());
+        assert!(parse(b"\x1b[?2004h").is_empty());
     }
 
     #[test]
     fn bracketed_paste_is_one_event() {
-        let inputs = parse_input(b"\x1b[200~hi there\x1b[201~");
+        let inputs = parse(b"\x1b[200~hi there\x1b[201~");
 
         assert!(matches!(&inputs[..], [Input::Paste(text)] if text == "hi there"));
     }
 
     #[test]
     fn keys_after_a_paste_still_arrive() {
-        let inputs = parse_input(b"\x1b[200~hi\x1b[201~x");
+        let inputs = parse(b"\x1b[200~hi\x1b[201~x");
 
         assert_eq!(inputs.len(), 2);
         assert!(matches!(&inputs[0], Input::Paste(text) if text == "hi"));
@@ -286,6 +459,7 @@ mod tests {
             keys(&[3]),
             vec![(KeyCode::Char('c'), KeyModifiers::CONTROL)]
         );
+        assert!(matches!(&parse(&[3])[..], [Input::Key(key)] if key.is_ctrl('c')));
     }
 
     #[test]
@@ -301,4 +475,20 @@ mod tests {
             vec![(KeyCode::BackTab, KeyModifiers::SHIFT)]
         );
     }
+
+    #[cfg(feature = "ratatui")]
+    #[test]
+    fn converts_to_crossterm() {
+        use ratatui::crossterm::event as ct;
+
+        let key = super::KeyEvent::new(KeyCode::Left, KeyModifiers::CONTROL | KeyModifiers::SHIFT);
+
+        assert_eq!(
+            ct::KeyEvent::from(key),
+            ct::KeyEvent::new(
+                ct::KeyCode::Left,
+                ct::KeyModifiers::CONTROL | ct::KeyModifiers::SHIFT
+            )
+        );
+    }
 }
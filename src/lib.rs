@@ -4,7 +4,13 @@ pub mod auth;
 mod error;
 pub mod events;
 mod exit;
+pub mod input;
 pub mod middleware;
+mod pattern;
+#[cfg(all(unix, feature = "process"))]
+pub mod process;
+#[cfg(feature = "rhai")]
+pub mod script;
 pub mod server;
 mod session;
 #[cfg(feature = "ratatui")]
@@ -22,7 +28,13 @@ pub use events::Events;
 pub use exit::{Exit, IntoExit};
 pub use middleware::{Middleware, Next, terminal};
 pub use russh::keys::{Algorithm, EcdsaCurve};
-pub use server::{HostKeyOptions, Server};
-pub use session::{Event, Extensions, PtySize, Session, SessionKind, Signal};
+pub use server::{
+    AuthDecision, AuthEvent, ClientFingerprint, ForwardRequest, HostKeyOptions, PassphraseProvider,
+    Server, ServerEvent, ServerHandle,
+};
+pub use session::{
+    AgentClient, Bus, ChannelWriter, ConnectionId, Direction, Event, Extensions, PtySize, Session,
+    SessionId, SessionIo, SessionKind, SessionStats, SessionStream, Signal, Tap, Utf8Decoder,
+};
 
 pub(crate) type BoxFuture<T> = Pin<Box<dyn Future<Output = T> + Send>>;
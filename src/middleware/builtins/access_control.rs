@@ -1,29 +1,189 @@
-use crate::{Exit, Middleware, Next, Result, Session};
+use std::collections::HashMap;
 
-/// Allowlist of programs an exec request may run (Wish `accesscontrol` parity).
+use crate::{Exit, Middleware, Next, Result, Session, pattern};
+
+/// One entry in an [`AccessControl`] allowlist.
 ///
-/// Compares the *program* — `argv[0]` of the POSIX-parsed command — exactly
-/// against the list, so allowing `git` permits `git push` and `git pull`
-/// alike. Sessions without an exec command (shells, subsystems) pass through
-/// untouched. Commands that fail to parse are denied.
+/// Plain strings convert by shape: a single word without wildcards is a
+/// [`Program`](Self::Program), anything else a [`Glob`](Self::Glob), so
+/// `"git"`, `"git-*"` and `"rsync --server*"` all mean what they look like.
+pub enum CommandPattern {
+    /// The program, `argv[0]`, is exactly this; any arguments are allowed.
+    Program(String),
+    /// The whole command line matches this shell-style pattern (`*` and `?`
+    /// wildcards).
+    Glob(String),
+    /// The whole command line contains a match for this regex; anchor it with
+    /// `^…$` to match all of it.
+    #[cfg(feature = "regex")]
+    Regex(regex::Regex),
+}
+
+impl CommandPattern {
+    /// Whether `argv`, the parsed command, is allowed by this pattern.
+    ///
+    /// Globs and regexes see the arguments joined by single spaces, so
+    /// quoting and repeated whitespace in the raw command don't matter.
+    fn matches(&self, argv: &[String]) -> bool {
+        match self {
+            Self::Program(program) => argv.first() == Some(program),
+            Self::Glob(glob) => pattern::glob(glob, &argv.join(" ")),
+            #[cfg(feature = "regex")]
+            Self::Regex(regex) => regex.is_match(&argv.join(" ")),
+        }
+    }
+}
+
+impl From<String> for CommandPattern {
+    fn from(pattern: String) -> Self {
+        if pattern.contains(['*', '?']) || pattern.contains(char::is_whitespace) {
+            Self::Glob(pattern)
+        } else {
+            Self::Program(pattern)
+        }
+    }
+}
+
+impl From<&str> for CommandPattern {
+    fn from(pattern: &str) -> Self {
+        pattern.to_owned().into()
+    }
+}
+
+#[cfg(feature = "regex")]
+impl From<regex::Regex> for CommandPattern {
+    fn from(regex: regex::Regex) -> Self {
+        Self::Regex(regex)
+    }
+}
+
+/// Allowlist of commands an exec request may run (Wish `accesscontrol` parity).
+///
+/// Each entry is a [`CommandPattern`]: a bare program name like `"git"`
+/// compares `argv[0]` of the POSIX-parsed command exactly, so it permits
+/// `git push` and `git pull` alike, while globs like `"git-*"` or
+/// `"rsync --server*"` and (with the `regex` feature) compiled regexes match
+/// the full command line. Sessions without an exec command (shells,
+/// subsystems) pass through untouched. Commands that fail to parse are
+/// denied.
+///
+/// Permissions can differ by user: [`for_user`](Self::for_user) and, with
+/// [`roles`](Self::roles) telling it who holds which role,
+/// [`for_role`](Self::for_role) give sessions their own rule sets, merged
+/// when several apply. Everyone else gets the list passed to
+/// [`new`](Self::new); starting from [`default`](Self::default) instead
+/// leaves them unrestricted, or denied everything after
+/// [`default_deny`](Self::default_deny).
+///
+/// ```no_run
+/// # use shenron::{Server, middleware::AccessControl};
+/// let _server = Server::new().with(
+///     AccessControl::default()
+///         .for_user("deploy", ["git-*"])
+///         .for_user("backup", ["rsync --server --sender*"])
+///         .default_deny(),
+/// );
+/// ```
 ///
 /// This is only a security boundary if the app executes the parsed argv
 /// directly (`Command::new(&argv[0]).args(&argv[1..])`). Never hand
 /// [`Session::raw_command`] to a shell: `allowed && anything` parses with
 /// `argv[0] == "allowed"` and would sail through this check.
+#[derive(Default)]
 pub struct AccessControl {
-    allowed: Vec<String>,
+    /// For sessions no user or role rules cover; `None` allows anything.
+    allowed: Option<Vec<CommandPattern>>,
+    users: HashMap<String, Vec<CommandPattern>>,
+    roles: HashMap<String, Vec<CommandPattern>>,
+    roles_of: Option<Box<RolesFn>>,
 }
 
+type RolesFn = dyn Fn(&Session) -> Vec<String> + Send + Sync;
+
 impl AccessControl {
-    pub fn new(allowed: impl IntoIterator<Item = impl Into<String>>) -> Self {
+    pub fn new(allowed: impl IntoIterator<Item = impl Into<CommandPattern>>) -> Self {
         Self {
-            allowed: allowed.into_iter().map(Into::into).collect(),
+            allowed: Some(allowed.into_iter().map(Into::into).collect()),
+            ..Self::default()
         }
     }
 
-    fn is_allowed(&self, program: &str) -> bool {
-        self.allowed.iter().any(|allowed| allowed == program)
+    /// Give `user` their own allowlist in place of the default one.
+    #[must_use]
+    pub fn for_user(
+        mut self,
+        user: impl Into<String>,
+        allowed: impl IntoIterator<Item = impl Into<CommandPattern>>,
+    ) -> Self {
+        self.users
+            .entry(user.into())
+            .or_default()
+            .extend(allowed.into_iter().map(Into::into));
+
+        self
+    }
+
+    /// Give holders of `role` their own allowlist in place of the default
+    /// one. Needs [`roles`](Self::roles) to say who holds it.
+    #[must_use]
+    pub fn for_role(
+        mut self,
+        role: impl Into<String>,
+        allowed: impl IntoIterator<Item = impl Into<CommandPattern>>,
+    ) -> Self {
+        self.roles
+            .entry(role.into())
+            .or_default()
+            .extend(allowed.into_iter().map(Into::into));
+
+        self
+    }
+
+    /// How to find a session's roles, typically from the account a
+    /// [`UserResolver`](crate::auth::UserResolver) attached:
+    /// `|session| session.get::<Account>().map(|a| a.roles.clone()).unwrap_or_default()`.
+    #[must_use]
+    pub fn roles(
+        mut self,
+        roles_of: impl Fn(&Session) -> Vec<String> + Send + Sync + 'static,
+    ) -> Self {
+        self.roles_of = Some(Box::new(roles_of));
+
+        self
+    }
+
+    /// Deny every command to users no [`for_user`](Self::for_user) or
+    /// [`for_role`](Self::for_role) rules cover.
+    #[must_use]
+    pub fn default_deny(mut self) -> Self {
+        self.allowed = Some(Vec::new());
+
+        self
+    }
+
+    /// Whether `user`, holding `roles`, may run `argv`.
+    fn permits(&self, user: &str, roles: &[String], argv: &[String]) -> bool {
+        let mut rule_sets = self
+            .users
+            .get(user)
+            .into_iter()
+            .chain(roles.iter().filter_map(|role| self.roles.get(role)))
+            .peekable();
+
+        if rule_sets.peek().is_none() {
+            return self.is_allowed(argv);
+        }
+
+        !argv.is_empty() && rule_sets.flatten().any(|pattern| pattern.matches(argv))
+    }
+
+    /// Whether the default allowlist permits `argv`.
+    fn is_allowed(&self, argv: &[String]) -> bool {
+        !argv.is_empty()
+            && self
+                .allowed
+                .as_ref()
+                .is_none_or(|allowed| allowed.iter().any(|pattern| pattern.matches(argv)))
     }
 }
 
@@ -35,9 +195,14 @@ impl Middleware for AccessControl {
             return Ok(next.run(session).await);
         }
 
+        let roles = self
+            .roles_of
+            .as_ref()
+            .map(|roles_of| roles_of(session))
+            .unwrap_or_default();
+
         if let Some(argv) = session.command()
-            && let Some(program) = argv.first()
-            && self.is_allowed(program)
+            && self.permits(session.user(), &roles, &argv)
         {
             return Ok(next.run(session).await);
         }
@@ -55,21 +220,77 @@ impl Middleware for AccessControl {
 mod tests {
     use super::AccessControl;
 
+    fn argv(command: &str) -> Vec<String> {
+        shell_words::split(command).expect("parses")
+    }
+
     #[test]
     fn only_listed_programs_are_allowed() {
         let ac = AccessControl::new(["ls", "cat"]);
 
-        assert!(ac.is_allowed("ls"));
-        assert!(ac.is_allowed("cat"));
-        assert!(!ac.is_allowed("rm"));
-        assert!(!ac.is_allowed(""));
+        assert!(ac.is_allowed(&argv("ls")));
+        assert!(ac.is_allowed(&argv("cat")));
+        assert!(!ac.is_allowed(&argv("rm")));
+        assert!(!ac.is_allowed(&[String::new()]));
+        assert!(!ac.is_allowed(&[]));
     }
 
     #[test]
     fn matching_is_exact_not_prefix() {
         let ac = AccessControl::new(["ls"]);
 
-        assert!(!ac.is_allowed("lsof"));
-        assert!(!ac.is_allowed("ls -la"));
+        assert!(!ac.is_allowed(&argv("lsof")));
+        assert!(!ac.is_allowed(&["ls -la".to_owned()]));
+        assert!(ac.is_allowed(&argv("ls -la")));
+    }
+
+    #[test]
+    fn globs_match_the_whole_command_line() {
+        let ac = AccessControl::new(["git-*", "rsync --server*"]);
+
+        assert!(ac.is_allowed(&argv("git-upload-pack 'repo.git'")));
+        assert!(ac.is_allowed(&argv("rsync  --server --sender -e.LsfxC . /srv")));
+        assert!(!ac.is_allowed(&argv("rsync -av /etc host:")));
+        assert!(!ac.is_allowed(&argv("sudo git-upload-pack repo.git")));
+    }
+
+    #[test]
+    fn users_and_roles_get_their_own_rules() {
+        let ac = AccessControl::new(["ls"])
+            .for_user("deploy", ["git-*"])
+            .for_role("ops", ["systemctl status *"]);
+        let ops = ["ops".to_owned()];
+
+        assert!(ac.permits("alice", &[], &argv("ls")));
+        assert!(!ac.permits("alice", &[], &argv("git-upload-pack x")));
+        assert!(ac.permits("deploy", &[], &argv("git-upload-pack x")));
+        assert!(!ac.permits("deploy", &[], &argv("ls")));
+        assert!(ac.permits("deploy", &ops, &argv("systemctl status sshd")));
+        assert!(ac.permits("deploy", &ops, &argv("git-receive-pack x")));
+        assert!(!ac.permits("bob", &ops, &argv("ls")));
+    }
+
+    #[test]
+    fn unlisted_users_are_unrestricted_unless_denied_by_default() {
+        let open = AccessControl::default().for_user("deploy", ["git-*"]);
+        let closed = AccessControl::default()
+            .for_user("deploy", ["git-*"])
+            .default_deny();
+
+        assert!(open.permits("alice", &[], &argv("rm -rf /tmp/x")));
+        assert!(!closed.permits("alice", &[], &argv("ls")));
+        assert!(closed.permits("deploy", &[], &argv("git-upload-pack x")));
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn regexes_match_the_command_line() {
+        let ac = AccessControl::new([
+            regex::Regex::new(r"^git-(upload|receive)-pack \S+$").expect("regex")
+        ]);
+
+        assert!(ac.is_allowed(&argv("git-receive-pack 'repo.git'")));
+        assert!(!ac.is_allowed(&argv("git-upload-archive repo.git")));
+        assert!(!ac.is_allowed(&argv("git-upload-pack a b")));
     }
 }
@@ -1,16 +1,291 @@
-use crate::{Exit, Next, Session};
+use std::{collections::HashMap, ops::AsyncFnMut};
 
-/// Middleware that rejects sessions without an active PTY.
+use crate::{
+    Exit, IntoExit, Next, Session,
+    middleware::{ErasedMiddleware, Middleware, terminal},
+};
+
+type Requirement = Box<dyn Fn(&TermCaps) -> bool + Send + Sync>;
+
+/// How many colors a terminal can show, from least to most.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ColorDepth {
+    /// No color: `dumb` and hardware terminals, or a client that set
+    /// `NO_COLOR`.
+    Monochrome,
+    /// The 16 ANSI colors.
+    Ansi16,
+    /// The xterm 256-color palette.
+    Ansi256,
+    /// 24-bit RGB.
+    TrueColor,
+}
+
+/// What a session's terminal can likely do, guessed from its `TERM` and the
+/// `COLORTERM`, `NO_COLOR` and locale variables the client sent.
+///
+/// [`active_term`] and [`ActiveTerm`] store one on PTY sessions, for apps to
+/// pick how fancy to draw:
+///
+/// ```no_run
+/// # use shenron::{Session, middleware::{ColorDepth, TermCaps}};
+/// async fn app(session: &mut Session) -> shenron::Result {
+///     let caps = session.get::<TermCaps>().copied().unwrap_or_default();
+///     let check = if caps.unicode { "✓" } else { "OK" };
+///
+///     if caps.colors >= ColorDepth::Ansi16 {
+///         session.write_str(&format!("\x1b[32m{check}\x1b[0m\r\n")).await
+///     } else {
+///         session.write_str(&format!("{check}\r\n")).await
+///     }
+/// }
+/// ```
+///
+/// Clients only send `COLORTERM` and the locale if they're configured to
+/// (`SendEnv` in OpenSSH) and the server [accepts](crate::Server::accept_env)
+/// them, so these are guesses that err towards less.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TermCaps {
+    pub colors: ColorDepth,
+    /// Whether the terminal can be sent UTF-8 beyond ASCII.
+    pub unicode: bool,
+    /// Whether the terminal probably reports the mouse when asked to.
+    pub mouse: bool,
+}
+
+impl Default for TermCaps {
+    /// The least a terminal can do.
+    fn default() -> Self {
+        Self {
+            colors: ColorDepth::Monochrome,
+            unicode: false,
+            mouse: false,
+        }
+    }
+}
+
+impl TermCaps {
+    /// The capabilities of a session's terminal, or `None` without a PTY.
+    #[must_use]
+    pub fn detect(session: &Session) -> Option<Self> {
+        session
+            .term()
+            .map(|term| Self::from_env(term, session.env()))
+    }
+
+    /// The capabilities of a terminal named `term`, in an environment `env`.
+    #[must_use]
+    pub fn from_env(term: &str, env: &HashMap<String, String>) -> Self {
+        let term = term.to_ascii_lowercase();
+        let var = |name: &str| env.get(name).map(String::as_str).filter(|v| !v.is_empty());
+
+        let family = term.split('-').next().unwrap_or_default();
+        let modern = matches!(
+            family,
+            "kitty" | "alacritty" | "wezterm" | "foot" | "ghostty"
+        ) || term.starts_with("xterm-kitty")
+            || term.starts_with("xterm-ghostty");
+        let xterm_like = modern
+            || matches!(
+                family,
+                "xterm" | "screen" | "tmux" | "rxvt" | "putty" | "konsole" | "gnome" | "vte"
+            );
+        let hardware = family
+            .strip_prefix("vt")
+            .is_some_and(|model| model.starts_with(|c: char| c.is_ascii_digit()));
+        let incapable = term.is_empty() || family == "dumb" || hardware;
+
+        let colors = if incapable || var("NO_COLOR").is_some() {
+            ColorDepth::Monochrome
+        } else if modern
+            || term.contains("direct")
+            || matches!(var("COLORTERM"), Some("truecolor" | "24bit"))
+        {
+            ColorDepth::TrueColor
+        } else if term.contains("256color") {
+            ColorDepth::Ansi256
+        } else {
+            ColorDepth::Ansi16
+        };
+
+        let locale = var("LC_ALL")
+            .or_else(|| var("LC_CTYPE"))
+            .or_else(|| var("LANG"))
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        let unicode = !incapable && (modern || locale.contains("utf-8") || locale.contains("utf8"));
+
+        Self {
+            colors,
+            unicode,
+            mouse: xterm_like,
+        }
+    }
+}
+
+/// Middleware that rejects sessions without an active PTY, and stores the
+/// terminal's [`TermCaps`] on those with one.
 ///
 /// # Errors
 ///
 /// Returns `Err` if writing the rejection to the session fails.
 pub async fn active_term(session: &mut Session, next: Next<'_>) -> crate::Result<Exit> {
-    if session.pty().is_none() {
+    let Some(caps) = TermCaps::detect(session) else {
         session.write_stderr_str("PTY required\n").await?;
 
         return Ok(Exit::Code(1));
-    }
+    };
+
+    session.insert(caps);
 
     Ok(next.run(session).await)
 }
+
+/// Like [`active_term`], but picky about the terminal too: sessions whose
+/// [`TermCaps`] don't meet a [requirement](Self::require) are turned away,
+/// or handed to a plainer [`fallback`](Self::fallback) instead of the app.
+///
+/// ```no_run
+/// # use shenron::{Server, Session, middleware::{ActiveTerm, ColorDepth}};
+/// # async fn plain(session: &mut Session) -> shenron::Result { Ok(()) }
+/// let _server = Server::new().with(
+///     ActiveTerm::new()
+///         .require(|caps| caps.colors >= ColorDepth::Ansi256 && caps.unicode)
+///         .fallback(plain),
+/// );
+/// ```
+pub struct ActiveTerm {
+    require: Option<Requirement>,
+    message: String,
+    fallback: Option<Box<dyn ErasedMiddleware>>,
+}
+
+impl Default for ActiveTerm {
+    fn default() -> Self {
+        Self {
+            require: None,
+            message: "Your terminal isn't supported.\r\n".into(),
+            fallback: None,
+        }
+    }
+}
+
+impl ActiveTerm {
+    /// Require a PTY and nothing more, as [`active_term`] does.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only let in terminals whose capabilities `require` returns `true`
+    /// for.
+    #[must_use]
+    pub fn require(mut self, require: impl Fn(&TermCaps) -> bool + Send + Sync + 'static) -> Self {
+        self.require = Some(Box::new(require));
+
+        self
+    }
+
+    /// What turned-away sessions with a PTY are told.
+    #[must_use]
+    pub fn message(mut self, message: impl Into<String>) -> Self {
+        self.message = message.into();
+
+        self
+    }
+
+    /// Serve sessions that don't make the cut, PTY or not, with `handler`
+    /// rather than turning them away.
+    #[must_use]
+    pub fn fallback<F, R>(mut self, handler: F) -> Self
+    where
+        F: AsyncFn(&mut Session) -> R + Send + Sync + 'static,
+        for<'a> <F as AsyncFnMut<(&'a mut Session,)>>::CallRefFuture<'a>: Send,
+        R: IntoExit,
+    {
+        self.fallback = Some(Box::new(terminal(handler)));
+
+        self
+    }
+}
+
+impl Middleware for ActiveTerm {
+    type Output = crate::Result<Exit>;
+
+    async fn handle(&self, session: &'_ mut Session, next: Next<'_>) -> crate::Result<Exit> {
+        let caps = TermCaps::detect(session);
+
+        if let Some(caps) = caps {
+            session.insert(caps);
+        }
+
+        let capable =
+            caps.is_some_and(|caps| self.require.as_ref().is_none_or(|require| require(&caps)));
+
+        if capable {
+            return Ok(next.run(session).await);
+        }
+
+        if let Some(fallback) = &self.fallback {
+            return Ok(fallback.handle(session, next).await);
+        }
+
+        tracing::info!(session = %session.id(), user = %session.user(), term = ?session.term(), "terminal not supported");
+
+        match caps {
+            Some(_) => session.write_stderr_str(&self.message).await?,
+            None => session.write_stderr_str("PTY required\n").await?,
+        }
+
+        Ok(Exit::Code(1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn caps(term: &str, env: &[(&str, &str)]) -> TermCaps {
+        let env = env
+            .iter()
+            .map(|(name, value)| ((*name).to_string(), (*value).to_string()))
+            .collect();
+
+        TermCaps::from_env(term, &env)
+    }
+
+    #[test]
+    fn detects_color_depth() {
+        assert_eq!(caps("dumb", &[]).colors, ColorDepth::Monochrome);
+        assert_eq!(caps("vt100", &[]).colors, ColorDepth::Monochrome);
+        assert_eq!(caps("xterm", &[]).colors, ColorDepth::Ansi16);
+        assert_eq!(caps("xterm-256color", &[]).colors, ColorDepth::Ansi256);
+        assert_eq!(caps("xterm-kitty", &[]).colors, ColorDepth::TrueColor);
+        assert_eq!(
+            caps("xterm-256color", &[("COLORTERM", "truecolor")]).colors,
+            ColorDepth::TrueColor
+        );
+        assert_eq!(
+            caps("xterm-256color", &[("NO_COLOR", "1")]).colors,
+            ColorDepth::Monochrome
+        );
+    }
+
+    #[test]
+    fn detects_unicode_and_mouse() {
+        let plain = caps("linux", &[("LANG", "C")]);
+        assert!(!plain.unicode);
+        assert!(!plain.mouse);
+
+        let utf8 = caps(
+            "xterm-256color",
+            &[("LANG", "C"), ("LC_CTYPE", "en_US.UTF-8")],
+        );
+        assert!(utf8.unicode);
+        assert!(utf8.mouse);
+
+        assert!(caps("alacritty", &[]).unicode);
+        assert!(caps("vte-256color", &[]).mouse);
+        assert!(!caps("dumb", &[("LANG", "en_US.UTF-8")]).unicode);
+    }
+}
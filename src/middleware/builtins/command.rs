@@ -0,0 +1,71 @@
+use crate::{Exit, IntoExit, Middleware, Next, Session};
+
+/// Routes exec requests for one program (`ssh host deploy prod`) to its own
+/// handler.
+///
+/// Matches `argv[0]` of the POSIX-parsed command exactly, so the handler
+/// reads its arguments from [`Session::command_args`]. Other commands, commands
+/// that fail to parse, shells, and subsystems pass through to the next
+/// middleware. Usually registered with
+/// [`Server::command`](crate::Server::command).
+pub struct Command<H> {
+    program: String,
+    handler: H,
+}
+
+impl<H: Middleware> Command<H> {
+    /// Serve exec requests whose program is `program` with `handler`. The
+    /// handler receives the rest of the chain as its `next`, so it can itself
+    /// be a middleware.
+    pub fn new(program: impl Into<String>, handler: H) -> Self {
+        Self {
+            program: program.into(),
+            handler,
+        }
+    }
+
+    fn matches(&self, session: &Session) -> bool {
+        session
+            .command()
+            .is_some_and(|argv| argv.first() == Some(&self.program))
+    }
+}
+
+impl<H: Middleware> Middleware for Command<H> {
+    type Output = Exit;
+
+    async fn handle(&self, session: &'_ mut Session, next: Next<'_>) -> Exit {
+        if self.matches(session) {
+            self.handler.handle(session, next).await.into_exit()
+        } else {
+            next.run(session).await
+        }
+    }
+}
+
+/// Catches every exec request that reaches it.
+///
+/// Placed after the [`Command`] routes, that means every command none of
+/// them matched. Shells and subsystems pass through. Usually registered with
+/// [`Server::command_fallback`](crate::Server::command_fallback).
+pub struct CommandFallback<H> {
+    handler: H,
+}
+
+impl<H: Middleware> CommandFallback<H> {
+    pub const fn new(handler: H) -> Self {
+        Self { handler }
+    }
+}
+
+impl<H: Middleware> Middleware for CommandFallback<H> {
+    type Output = Exit;
+
+    async fn handle(&self, session: &'_ mut Session, next: Next<'_>) -> Exit {
+        if session.raw_command().is_some() {
+            self.handler.handle(session, next).await.into_exit()
+        } else {
+            next.run(session).await
+        }
+    }
+}
@@ -1,15 +1,214 @@
-use crate::{Exit, Middleware, Next, Result, Session};
+use crate::{Exit, Middleware, Next, Result, Session, SessionKind};
 
-/// Middleware to print a message at the end of a session
-pub struct Comment(pub String);
+/// Middleware to print a message at the end of a session, or at its start
+/// with [`before`](Self::before).
+///
+/// The message is printed as given unless you opt in to
+/// [`templated`](Self::templated), which fills in details of the session,
+/// or [`strip_ansi`](Self::strip_ansi), which keeps color codes away from
+/// sessions without a PTY.
+///
+/// ```no_run
+/// # use shenron::{Server, middleware::Comment};
+/// let _server = Server::new()
+///     .with(
+///         Comment::new("\x1b[1mWelcome, {user}!\x1b[0m\r\n")
+///             .templated()
+///             .strip_ansi()
+///             .before(),
+///     )
+///     .with(Comment::from("Bye!\r\n"));
+/// ```
+pub struct Comment {
+    message: String,
+    before: bool,
+    templated: bool,
+    strip_ansi: bool,
+}
+
+impl Comment {
+    #[must_use]
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            before: false,
+            templated: false,
+            strip_ansi: false,
+        }
+    }
+
+    /// Print the message before the rest of the chain runs, as a greeting,
+    /// rather than after it.
+    #[must_use]
+    pub const fn before(mut self) -> Self {
+        self.before = true;
+
+        self
+    }
+
+    /// Treat the message as a template: `{user}`, `{addr}` (the client's
+    /// address) and `{kind}` (`shell`, `exec` or `subsystem`) are filled in
+    /// from the session, and `{{` and `}}` are literal braces.
+    #[must_use]
+    pub const fn templated(mut self) -> Self {
+        self.templated = true;
+
+        self
+    }
+
+    /// Strip ANSI escape codes from the message for sessions without a PTY,
+    /// which are usually scripts rather than terminals.
+    #[must_use]
+    pub const fn strip_ansi(mut self) -> Self {
+        self.strip_ansi = true;
+
+        self
+    }
+
+    fn render(&self, session: &Session) -> String {
+        let text = if self.templated {
+            interpolate(&self.message, |name| match name {
+                // The username is the client's to choose; keep its escape
+                // codes out of the user's terminal.
+                "user" => Some(session.user().chars().filter(|c| !c.is_control()).collect()),
+                "addr" => Some(session.remote_addr().to_string()),
+                "kind" => Some(
+                    match session.kind() {
+                        SessionKind::Shell => "shell",
+                        SessionKind::Exec { .. } => "exec",
+                        SessionKind::Subsystem { .. } => "subsystem",
+                    }
+                    .into(),
+                ),
+                _ => None,
+            })
+        } else {
+            self.message.clone()
+        };
+
+        if self.strip_ansi && session.pty().is_none() {
+            strip_ansi(&text)
+        } else {
+            text
+        }
+    }
+}
+
+impl From<String> for Comment {
+    fn from(message: String) -> Self {
+        Self::new(message)
+    }
+}
+
+impl From<&str> for Comment {
+    fn from(message: &str) -> Self {
+        Self::new(message)
+    }
+}
 
 impl Middleware for Comment {
     type Output = Result<Exit>;
 
     async fn handle<'a>(&'a self, session: &'a mut Session, next: Next<'a>) -> Result<Exit> {
+        if self.before {
+            session.write_str(&self.render(session)).await?;
+
+            return Ok(next.run(session).await);
+        }
+
         let exit = next.run(session).await;
-        session.write_str(&self.0).await?;
+        session.write_str(&self.render(session)).await?;
 
         Ok(exit)
     }
 }
+
+/// `template` with each `{name}` replaced by `value(name)`. Unknown names
+/// are left as they are, and `{{` and `}}` stand for single braces.
+pub(super) fn interpolate(template: &str, value: impl Fn(&str) -> Option<String>) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(i) = rest.find(['{', '}']) {
+        out.push_str(&rest[..i]);
+        rest = &rest[i..];
+
+        if let Some(after) = rest.strip_prefix("{{").or_else(|| rest.strip_prefix("}}")) {
+            out.push_str(&rest[..1]);
+            rest = after;
+        } else if rest.starts_with('{')
+            && let Some(end) = rest.find('}')
+            && let Some(value) = value(&rest[1..end])
+        {
+            out.push_str(&value);
+            rest = &rest[end + 1..];
+        } else {
+            out.push_str(&rest[..1]);
+            rest = &rest[1..];
+        }
+    }
+
+    out.push_str(rest);
+
+    out
+}
+
+/// `text` without ANSI escape sequences: CSI sequences like colors
+/// (`ESC [ … final`), OSC sequences like titles (`ESC ] … BEL` or `ESC \`),
+/// and two-byte escapes.
+fn strip_ansi(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            out.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('[') => {
+                for c in chars.by_ref() {
+                    if ('\x40'..='\x7e').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            Some(']') => {
+                while let Some(c) = chars.next() {
+                    if c == '\x07' || (c == '\x1b' && chars.next_if_eq(&'\\').is_some()) {
+                        break;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{interpolate, strip_ansi};
+
+    fn vars(name: &str) -> Option<String> {
+        (name == "user").then(|| "alice".into())
+    }
+
+    #[test]
+    fn variables_are_filled_in() {
+        assert_eq!(interpolate("Bye, {user}!", vars), "Bye, alice!");
+        assert_eq!(interpolate("{user}{user}", vars), "alicealice");
+        assert_eq!(interpolate("{{user}} {nope} }{", vars), "{user} {nope} }{");
+        assert_eq!(interpolate("{user", vars), "{user");
+    }
+
+    #[test]
+    fn escape_codes_are_stripped() {
+        assert_eq!(strip_ansi("\x1b[1;32mgreen\x1b[0m"), "green");
+        assert_eq!(strip_ansi("\x1b]0;title\x07text"), "text");
+        assert_eq!(strip_ansi("\x1b]0;title\x1b\\text"), "text");
+        assert_eq!(strip_ansi("plain\r\n"), "plain\r\n");
+    }
+}
@@ -1,4 +1,22 @@
-use crate::{Exit, Next, Session};
+use std::{
+    sync::LazyLock,
+    time::{Duration, Instant},
+};
+
+use super::comment::interpolate;
+use crate::{Exit, Middleware, Next, Session};
+
+/// Where [`Elapsed`] reports a session's duration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ElapsedTarget {
+    /// The client's terminal, on stdout.
+    #[default]
+    Stdout,
+    /// The client's terminal, on stderr, keeping it out of piped output.
+    Stderr,
+    /// Only the server's log, as a `tracing` event.
+    Log,
+}
 
 /// Middleware that prints the elapsed time the session took
 ///
@@ -6,12 +24,106 @@ use crate::{Exit, Next, Session};
 ///
 /// Returns `Err` if writing to the session fails.
 pub async fn elapsed(session: &mut Session, next: Next<'_>) -> crate::Result<Exit> {
-    let start = std::time::Instant::now();
-    let exit = next.run(session).await;
+    static DEFAULT: LazyLock<Elapsed> = LazyLock::new(Elapsed::new);
+
+    DEFAULT.handle(session, next).await
+}
+
+/// Middleware reporting how long a session took, like [`elapsed`] but
+/// configurable.
+///
+/// The message is a template, as for
+/// [`Comment::templated`](super::Comment::templated):
+/// `{elapsed}` is the duration and `{user}` the username. Sessions shorter
+/// than the [`threshold`](Self::threshold) report nothing.
+///
+/// ```no_run
+/// # use std::time::Duration;
+/// # use shenron::{Server, middleware::{Elapsed, ElapsedTarget}};
+/// let _server = Server::new().with(
+///     Elapsed::new()
+///         .target(ElapsedTarget::Stderr)
+///         .threshold(Duration::from_secs(5))
+///         .message("took {elapsed}\r\n"),
+/// );
+/// ```
+pub struct Elapsed {
+    target: ElapsedTarget,
+    threshold: Duration,
+    template: String,
+}
+
+impl Default for Elapsed {
+    fn default() -> Self {
+        Self {
+            target: ElapsedTarget::default(),
+            threshold: Duration::ZERO,
+            template: "Session lasted: {elapsed}\r\n".into(),
+        }
+    }
+}
+
+impl Elapsed {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Where the duration goes; the client's stdout unless set.
+    #[must_use]
+    pub const fn target(mut self, target: ElapsedTarget) -> Self {
+        self.target = target;
+
+        self
+    }
+
+    /// Only report sessions lasting at least `threshold`.
+    #[must_use]
+    pub const fn threshold(mut self, threshold: Duration) -> Self {
+        self.threshold = threshold;
+
+        self
+    }
+
+    /// The message template; `Session lasted: {elapsed}\r\n` unless set.
+    #[must_use]
+    pub fn message(mut self, template: impl Into<String>) -> Self {
+        self.template = template.into();
+
+        self
+    }
+
+    fn render(&self, session: &Session, elapsed: Duration) -> String {
+        interpolate(&self.template, |name| match name {
+            "elapsed" => Some(format!("{elapsed:?}")),
+            "user" => Some(session.user().chars().filter(|c| !c.is_control()).collect()),
+            _ => None,
+        })
+    }
+}
+
+impl Middleware for Elapsed {
+    type Output = crate::Result<Exit>;
+
+    async fn handle(&self, session: &'_ mut Session, next: Next<'_>) -> crate::Result<Exit> {
+        let start = Instant::now();
+        let exit = next.run(session).await;
+        let elapsed = start.elapsed();
+
+        if elapsed < self.threshold {
+            return Ok(exit);
+        }
+
+        let message = self.render(session, elapsed);
 
-    session
-        .write_str(&format!("Session lasted: {:?}\r\n", start.elapsed()))
-        .await?;
+        match self.target {
+            ElapsedTarget::Stdout => session.write_str(&message).await?,
+            ElapsedTarget::Stderr => session.write_stderr_str(&message).await?,
+            ElapsedTarget::Log => {
+                tracing::info!(session = %session.id(), user = %session.user(), ?elapsed, "{}", message.trim_end());
+            }
+        }
 
-    Ok(exit)
+        Ok(exit)
+    }
 }
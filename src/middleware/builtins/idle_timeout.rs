@@ -0,0 +1,174 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use super::TIME_LIMIT_EXIT_CODE;
+use crate::{ChannelWriter, Direction, Exit, Middleware, Next, Session};
+
+/// Middleware that ends sessions whose user has stopped typing.
+///
+/// A session is idle after no input for the timeout, however much output
+/// the app is producing: the usual policy for audited environments, and
+/// separate from
+/// [`Server::inactivity_timeout`](crate::Server::inactivity_timeout), which
+/// any traffic resets.
+///
+/// With a [`warning`](Self::warning), the user is told on stderr that far
+/// ahead, and typing anything keeps the session. At the timeout the chain is
+/// dropped mid-await, as with [`MaxDuration`](super::MaxDuration), and the
+/// session closes with exit status 124 unless [`exit_code`](Self::exit_code)
+/// says otherwise.
+///
+/// Input counts when the app reads it, so an app that stops reading while it
+/// works counts as idle too.
+///
+/// ```no_run
+/// # use std::time::Duration;
+/// # use shenron::{Server, middleware::IdleTimeout};
+/// let _server = Server::new().with(
+///     IdleTimeout::new(Duration::from_secs(15 * 60)).warning(Duration::from_secs(60)),
+/// );
+/// ```
+pub struct IdleTimeout {
+    timeout: Duration,
+    warning: Option<Duration>,
+    exit_code: u32,
+}
+
+impl IdleTimeout {
+    #[must_use]
+    pub const fn new(timeout: Duration) -> Self {
+        Self {
+            timeout,
+            warning: None,
+            exit_code: TIME_LIMIT_EXIT_CODE,
+        }
+    }
+
+    /// Warn the user `before` the timeout that the session is about to end.
+    #[must_use]
+    pub const fn warning(mut self, before: Duration) -> Self {
+        self.warning = Some(before);
+
+        self
+    }
+
+    /// The exit status a session ended for idling reports.
+    #[must_use]
+    pub const fn exit_code(mut self, code: u32) -> Self {
+        self.exit_code = code;
+
+        self
+    }
+
+    /// Resolves once the user has been idle for the timeout, warning them on
+    /// `stderr` first.
+    async fn idle(&self, activity: &Activity, stderr: Option<ChannelWriter>) {
+        let warn_after = self
+            .warning
+            .and_then(|before| self.timeout.checked_sub(before))
+            .filter(|_| stderr.is_some());
+        let mut warned_at = None;
+
+        loop {
+            let idle = activity.idle();
+
+            if idle >= self.timeout {
+                return;
+            }
+
+            let Some(warn_after) = warn_after else {
+                tokio::time::sleep(self.timeout.saturating_sub(idle)).await;
+                continue;
+            };
+
+            // Warn once per quiet spell; input starts a new one.
+            if idle >= warn_after && warned_at != Some(activity.last()) {
+                warned_at = Some(activity.last());
+
+                if let Some(stderr) = &stderr {
+                    let left = self.timeout.saturating_sub(idle).as_millis().div_ceil(1000);
+                    let _ = stderr
+                        .write_str(&format!(
+                            "\r\nsession idle; closing in {left}s unless there's input\r\n"
+                        ))
+                        .await;
+                }
+            }
+
+            let until = if idle < warn_after {
+                warn_after
+            } else {
+                self.timeout
+            };
+            tokio::time::sleep(until.saturating_sub(idle)).await;
+        }
+    }
+}
+
+impl Middleware for IdleTimeout {
+    type Output = Exit;
+
+    async fn handle(&self, session: &'_ mut Session, next: Next<'_>) -> Exit {
+        let activity = Arc::new(Activity::new());
+
+        let _tap = session.tap({
+            let activity = Arc::clone(&activity);
+
+            move |direction, _| {
+                if direction == Direction::Input {
+                    activity.touch();
+                }
+            }
+        });
+
+        let stderr = session.stderr().ok();
+
+        tokio::select! {
+            exit = next.run(session) => exit,
+            () = self.idle(&activity, stderr) => {
+                tracing::info!(session = %session.id(), user = %session.user(), timeout = ?self.timeout, "session idle timeout");
+
+                let _ = session
+                    .write_stderr_str("\r\nsession closed after idling\r\n")
+                    .await;
+
+                Exit::Code(self.exit_code)
+            }
+        }
+    }
+}
+
+/// When the user last typed, in nanoseconds since the session started.
+struct Activity {
+    started: Instant,
+    last: AtomicU64,
+}
+
+impl Activity {
+    fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            last: AtomicU64::new(0),
+        }
+    }
+
+    fn touch(&self) {
+        let now = u64::try_from(self.started.elapsed().as_nanos()).unwrap_or(u64::MAX);
+        self.last.store(now, Ordering::Relaxed);
+    }
+
+    fn last(&self) -> u64 {
+        self.last.load(Ordering::Relaxed)
+    }
+
+    fn idle(&self) -> Duration {
+        self.started
+            .elapsed()
+            .saturating_sub(Duration::from_nanos(self.last()))
+    }
+}
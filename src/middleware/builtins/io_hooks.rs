@@ -0,0 +1,159 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use crate::{Direction, Exit, Middleware, Next, Session, SessionId};
+
+type ChunkFn = dyn Fn(&Chunk<'_>) + Send + Sync;
+type RedactFn = dyn Fn(Direction, &[u8]) -> Vec<u8> + Send + Sync;
+
+/// One piece of a session's traffic, as handed to [`IoHooks`] callbacks.
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct Chunk<'a> {
+    pub session: SessionId,
+    pub user: &'a str,
+    pub direction: Direction,
+    /// The bytes, after any redaction.
+    pub data: &'a [u8],
+    /// How long after the middleware started the chunk went by.
+    pub elapsed: Duration,
+}
+
+/// Middleware calling back with every chunk of input a session reads and
+/// output it writes, for keystroke auditing, anomaly detection or usage
+/// analytics.
+///
+/// Callbacks run inline on the session's I/O path, as with
+/// [`Session::tap`], so they should be quick: count, sample, or hand the
+/// chunk to a channel. [`redact_input`](Self::redact_input) hides what was
+/// typed while keeping its timing, and [`redact`](Self::redact) rewrites
+/// chunks in either direction before any callback sees them.
+///
+/// ```no_run
+/// # use shenron::{Server, middleware::IoHooks};
+/// let _server = Server::new().with(
+///     IoHooks::new()
+///         .on_input(|chunk| eprintln!("{}: typed {} bytes", chunk.session, chunk.data.len()))
+///         .redact_input(),
+/// );
+/// ```
+#[derive(Default)]
+pub struct IoHooks {
+    on_input: Option<Arc<ChunkFn>>,
+    on_output: Option<Arc<ChunkFn>>,
+    redact: Option<Arc<RedactFn>>,
+    redact_input: bool,
+}
+
+impl IoHooks {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Call `f` with each chunk read from the client.
+    #[must_use]
+    pub fn on_input(mut self, f: impl Fn(&Chunk<'_>) + Send + Sync + 'static) -> Self {
+        self.on_input = Some(Arc::new(f));
+
+        self
+    }
+
+    /// Call `f` with each chunk written to the client, stdout and stderr
+    /// alike; [`Chunk::direction`] tells them apart.
+    #[must_use]
+    pub fn on_output(mut self, f: impl Fn(&Chunk<'_>) + Send + Sync + 'static) -> Self {
+        self.on_output = Some(Arc::new(f));
+
+        self
+    }
+
+    /// Replace each byte of input with `*`, leaving line breaks, so
+    /// callbacks see when and how much was typed but never passwords or
+    /// other secrets.
+    #[must_use]
+    pub const fn redact_input(mut self) -> Self {
+        self.redact_input = true;
+
+        self
+    }
+
+    /// Rewrite every chunk with `f` before the callbacks see it: to mask
+    /// tokens in output, say. Runs after [`redact_input`](Self::redact_input).
+    #[must_use]
+    pub fn redact(
+        mut self,
+        f: impl Fn(Direction, &[u8]) -> Vec<u8> + Send + Sync + 'static,
+    ) -> Self {
+        self.redact = Some(Arc::new(f));
+
+        self
+    }
+}
+
+impl Middleware for IoHooks {
+    type Output = Exit;
+
+    async fn handle(&self, session: &'_ mut Session, next: Next<'_>) -> Exit {
+        if self.on_input.is_none() && self.on_output.is_none() {
+            return next.run(session).await;
+        }
+
+        let on_input = self.on_input.clone();
+        let on_output = self.on_output.clone();
+        let redact = self.redact.clone();
+        let redact_input = self.redact_input;
+        let id = session.id();
+        let user = session.user().to_owned();
+        let started = Instant::now();
+
+        let _tap = session.tap(move |direction, data| {
+            let Some(hook) = (match direction {
+                Direction::Input => &on_input,
+                Direction::Stdout | Direction::Stderr => &on_output,
+            }) else {
+                return;
+            };
+
+            let masked;
+            let mut data = data;
+
+            if redact_input && direction == Direction::Input {
+                masked = mask(data);
+                data = &masked;
+            }
+
+            let rewritten;
+
+            if let Some(redact) = &redact {
+                rewritten = redact(direction, data);
+                data = &rewritten;
+            }
+
+            hook(&Chunk {
+                session: id,
+                user: &user,
+                direction,
+                data,
+                elapsed: started.elapsed(),
+            });
+        });
+
+        next.run(session).await
+    }
+}
+
+/// `data` with every byte but line breaks replaced by `*`.
+fn mask(data: &[u8]) -> Vec<u8> {
+    data.iter()
+        .map(|&byte| {
+            if matches!(byte, b'\r' | b'\n') {
+                byte
+            } else {
+                b'*'
+            }
+        })
+        .collect()
+}
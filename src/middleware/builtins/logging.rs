@@ -1,58 +1,413 @@
-use std::fmt::Write as _;
+use std::{
+    collections::HashSet,
+    fmt::Write as _,
+    io::Write,
+    sync::{LazyLock, Mutex},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
 
-use tracing::{error, info};
+use tracing::{error, field::display, info};
 
-use crate::{Exit, Next, Session, SessionKind};
+use super::json::json_string;
+use crate::{Exit, Middleware, Next, Session, SessionKind, pattern};
 
-/// Middleware that logs session starting, ending and errors
+/// What [`Logging`] writes in place of a redacted word of a command.
+const REDACTED: &str = "[redacted]";
+
+/// A piece of what [`Logging`] records about each session. The outcome (exit
+/// code, error or signal) is always logged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LogField {
+    /// The session's [`id`](Session::id).
+    Session,
+    /// Its [`connection_id`](Session::connection_id).
+    Connection,
+    User,
+    /// The client's address.
+    Remote,
+    /// Shell, exec or subsystem, and which subsystem.
+    Kind,
+    /// An exec's command line, after [redaction](Logging::redact).
+    Command,
+    /// The PTY's terminal type and size, if there is one.
+    Pty,
+    /// How long the session ran, on endings.
+    Elapsed,
+    /// Bytes read and written, on endings.
+    Bytes,
+}
+
+impl LogField {
+    const ALL: [Self; 9] = [
+        Self::Session,
+        Self::Connection,
+        Self::User,
+        Self::Remote,
+        Self::Kind,
+        Self::Command,
+        Self::Pty,
+        Self::Elapsed,
+        Self::Bytes,
+    ];
+}
+
+/// Middleware that logs session starting, ending and errors, tagged with the
+/// session's [`id`](Session::id) and [`connection_id`](Session::connection_id);
+/// endings include the bytes transferred. [`Logging`] does the same with
+/// more say over what's logged and how.
 pub async fn logging(session: &mut Session, next: Next<'_>) -> Exit {
-    let user = session.user().to_owned();
-    let remote = session.remote_addr();
-    let mut kind = match session.kind() {
-        SessionKind::Exec { command } => format!("exec({command})"),
-        SessionKind::Shell => "shell".to_string(),
-        SessionKind::Subsystem { name } => format!("subsystem({name})"),
-    };
-
-    if let Some((term, size)) = session.pty() {
-        let _ = write!(
-            kind,
-            " pty(term={}, size={}x{})",
-            term, size.width, size.height
+    static DEFAULT: LazyLock<Logging> = LazyLock::new(Logging::new);
+
+    DEFAULT.handle(session, next).await
+}
+
+/// Middleware that logs session starting, ending and errors, like
+/// [`logging`], configured.
+///
+/// By default each line has every [`LogField`], as `tracing` events. Pick
+/// fewer with [`fields`](Self::fields), tag every line with
+/// [`field`](Self::field), hide secrets passed on command lines with
+/// [`redact`](Self::redact), or write JSON lines somewhere instead of
+/// going through `tracing` with [`json`](Self::json).
+///
+/// ```no_run
+/// # use shenron::{Server, middleware::{LogField, Logging}};
+/// let _server = Server::new().with(
+///     Logging::new()
+///         .fields([LogField::Session, LogField::User, LogField::Command, LogField::Elapsed])
+///         .field("service", "deploy-gate")
+///         .redact(["--password=*", "--token=*"])
+///         .json(std::io::stdout()),
+/// );
+/// ```
+pub struct Logging {
+    fields: HashSet<LogField>,
+    custom: Vec<(String, String)>,
+    redact: Vec<String>,
+    json: Option<Mutex<Box<dyn Write + Send>>>,
+}
+
+impl Default for Logging {
+    fn default() -> Self {
+        Self {
+            fields: LogField::ALL.into_iter().collect(),
+            custom: Vec::new(),
+            redact: Vec::new(),
+            json: None,
+        }
+    }
+}
+
+impl Logging {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Log only `fields`, rather than all of them.
+    #[must_use]
+    pub fn fields(mut self, fields: impl IntoIterator<Item = LogField>) -> Self {
+        self.fields = fields.into_iter().collect();
+
+        self
+    }
+
+    /// Add `name=value` to every line, e.g. the service or region.
+    #[must_use]
+    pub fn field(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.custom.push((name.into(), value.into()));
+
+        self
+    }
+
+    /// Log the words of commands matching any of the shell-style `patterns`
+    /// as `[redacted]`: `--password=*` hides `--password=hunter2`.
+    #[must_use]
+    pub fn redact(mut self, patterns: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.redact.extend(patterns.into_iter().map(Into::into));
+
+        self
+    }
+
+    /// Write each line to `writer` as a JSON object, one per line, instead
+    /// of as a `tracing` event. Lines that fail to write are dropped.
+    #[must_use]
+    pub fn json(mut self, writer: impl Write + Send + 'static) -> Self {
+        self.json = Some(Mutex::new(Box::new(writer)));
+
+        self
+    }
+
+    fn has(&self, field: LogField) -> bool {
+        self.fields.contains(&field)
+    }
+
+    fn command(&self, command: &str) -> String {
+        command
+            .split(' ')
+            .map(|word| {
+                if self.redact.iter().any(|p| pattern::glob(p, word)) {
+                    REDACTED
+                } else {
+                    word
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// The fields a session's lines share, in order.
+    fn common(&self, session: &Session) -> Vec<(&'static str, String)> {
+        let mut fields = Vec::new();
+
+        if self.has(LogField::Session) {
+            fields.push(("session", session.id().to_string()));
+        }
+
+        if self.has(LogField::Connection) {
+            fields.push(("connection", session.connection_id().to_string()));
+        }
+
+        if self.has(LogField::User) {
+            fields.push(("user", session.user().to_owned()));
+        }
+
+        if self.has(LogField::Remote) {
+            fields.push(("remote", session.remote_addr().to_string()));
+        }
+
+        if self.has(LogField::Kind) {
+            let kind = match session.kind() {
+                SessionKind::Exec { command } if self.has(LogField::Command) => {
+                    format!("exec({})", self.command(command))
+                }
+                SessionKind::Exec { .. } => "exec".to_string(),
+                SessionKind::Shell => "shell".to_string(),
+                SessionKind::Subsystem { name } => format!("subsystem({name})"),
+            };
+
+            fields.push(("kind", kind));
+        } else if self.has(LogField::Command)
+            && let Some(command) = session.raw_command()
+        {
+            fields.push(("command", self.command(command)));
+        }
+
+        if self.has(LogField::Pty)
+            && let Some((term, size)) = session.pty()
+        {
+            fields.push((
+                "pty",
+                format!("term={}, size={}x{}", term, size.width, size.height),
+            ));
+        }
+
+        fields
+    }
+
+    fn started(&self, common: &[(&'static str, String)]) {
+        if self.json.is_some() {
+            self.write_json("info", "session started", common, &[]);
+
+            return;
+        }
+
+        let field = |name: &str| field(common, name);
+        info!(
+            session = field("session"),
+            connection = field("connection"),
+            user = field("user"),
+            remote = field("remote"),
+            kind = field("kind"),
+            command = field("command"),
+            pty = field("pty"),
+            fields = self.custom_fields(),
+            "session started"
         );
     }
 
-    info!(
-        user = %user,
-        remote = %remote,
-        kind = %kind,
-        "session started"
-    );
+    fn ended(
+        &self,
+        common: &[(&'static str, String)],
+        exit: &Exit,
+        elapsed: Duration,
+        session: &Session,
+    ) {
+        let stats = session.stats();
+        let elapsed = self.has(LogField::Elapsed).then_some(elapsed);
+        let bytes = self
+            .has(LogField::Bytes)
+            .then(|| (stats.bytes_read(), stats.bytes_written()));
+
+        if self.json.is_some() {
+            let mut ending = Vec::new();
+
+            if let Some(elapsed) = elapsed {
+                ending.push(("elapsed_ms", elapsed.as_millis().to_string()));
+            }
+
+            if let Some((read, written)) = bytes {
+                ending.push(("bytes_read", read.to_string()));
+                ending.push(("bytes_written", written.to_string()));
+            }
+
+            let (level, message, outcome) = match exit {
+                Exit::Code(code) => ("info", "session ended", ("exit_code", code.to_string())),
+                Exit::Error(e) => (
+                    "error",
+                    "session error",
+                    ("error", json_string(&e.to_string())),
+                ),
+                Exit::Signal { signal, .. } => (
+                    "info",
+                    "session ended by signal",
+                    ("signal", json_string(&format!("{signal:?}"))),
+                ),
+            };
+            ending.push(outcome);
+
+            self.write_json(level, message, common, &ending);
 
-    let start = std::time::Instant::now();
-    let exit = next.run(session).await;
-    let elapsed = start.elapsed();
+            return;
+        }
+
+        let field = |name: &str| field(common, name);
+        let elapsed = elapsed.map(tracing::field::debug);
+        let bytes_read = bytes.map(|(read, _)| read);
+        let bytes_written = bytes.map(|(_, written)| written);
+        let fields = self.custom_fields();
 
-    match &exit {
-        Exit::Code(code) => {
-            info!(
-                user = %user,
-                remote = %remote,
-                elapsed = ?elapsed,
+        match exit {
+            Exit::Code(code) => info!(
+                session = field("session"),
+                connection = field("connection"),
+                user = field("user"),
+                remote = field("remote"),
+                elapsed,
+                bytes_read,
+                bytes_written,
                 exit_code = %code,
+                fields,
                 "session ended"
-            );
-        }
-        Exit::Error(e) => {
-            error!(
-                user = %user,
-                remote = %remote,
-                elapsed = ?elapsed,
+            ),
+            Exit::Error(e) => error!(
+                session = field("session"),
+                connection = field("connection"),
+                user = field("user"),
+                remote = field("remote"),
+                elapsed,
+                bytes_read,
+                bytes_written,
                 error = %e,
+                fields,
                 "session error"
-            );
+            ),
+            Exit::Signal { signal, .. } => info!(
+                session = field("session"),
+                connection = field("connection"),
+                user = field("user"),
+                remote = field("remote"),
+                elapsed,
+                bytes_read,
+                bytes_written,
+                signal = ?signal,
+                fields,
+                "session ended by signal"
+            ),
+        }
+    }
+
+    /// The [`field`](Self::field)s as one `name=value ...` value, since
+    /// `tracing` field names are fixed.
+    fn custom_fields(&self) -> Option<tracing::field::DisplayValue<String>> {
+        if self.custom.is_empty() {
+            return None;
+        }
+
+        let mut fields = String::new();
+
+        for (name, value) in &self.custom {
+            if !fields.is_empty() {
+                fields.push(' ');
+            }
+
+            let _ = write!(fields, "{name}={value}");
+        }
+
+        Some(display(fields))
+    }
+
+    /// Write a JSON line; `raw` values are already JSON, the rest strings.
+    fn write_json(
+        &self,
+        level: &str,
+        message: &str,
+        common: &[(&'static str, String)],
+        raw: &[(&'static str, String)],
+    ) {
+        let Some(writer) = &self.json else {
+            return;
+        };
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+
+        let mut line = format!(
+            "{{\"timestamp_ms\":{timestamp},\"level\":\"{level}\",\"message\":{}",
+            json_string(message)
+        );
+
+        let strings = common.iter().map(|(name, value)| (*name, value)).chain(
+            self.custom
+                .iter()
+                .map(|(name, value)| (name.as_str(), value)),
+        );
+
+        for (name, value) in strings {
+            let _ = write!(line, ",{}:{}", json_string(name), json_string(value));
+        }
+
+        for (name, value) in raw {
+            let _ = write!(line, ",{}:{value}", json_string(name));
         }
+
+        line.push_str("}\n");
+
+        let Ok(mut writer) = writer.lock() else {
+            return;
+        };
+
+        let _ = writer
+            .write_all(line.as_bytes())
+            .and_then(|()| writer.flush());
     }
+}
+
+impl Middleware for Logging {
+    type Output = Exit;
+
+    async fn handle(&self, session: &'_ mut Session, next: Next<'_>) -> Exit {
+        let common = self.common(session);
+        self.started(&common);
+
+        let start = Instant::now();
+        let exit = next.run(session).await;
+
+        self.ended(&common, &exit, start.elapsed(), session);
+
+        exit
+    }
+}
 
-    exit
+/// The value of `name` among `fields`, for a `tracing` event.
+fn field<'a>(
+    fields: &'a [(&'static str, String)],
+    name: &str,
+) -> Option<tracing::field::DisplayValue<&'a String>> {
+    fields
+        .iter()
+        .find(|(field, _)| *field == name)
+        .map(|(_, value)| display(value))
 }
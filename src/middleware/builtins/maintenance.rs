@@ -0,0 +1,105 @@
+use std::{
+    collections::HashSet,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+use tokio::sync::watch;
+
+use crate::{Exit, Middleware, Next, Session};
+
+/// The switch a [`Maintenance`] middleware reads: an `Arc<AtomicBool>` or a
+/// `watch::Receiver<bool>`, held on to by whatever turns maintenance on and
+/// off.
+pub enum MaintenanceFlag {
+    Atomic(Arc<AtomicBool>),
+    Watch(watch::Receiver<bool>),
+}
+
+impl MaintenanceFlag {
+    fn is_on(&self) -> bool {
+        match self {
+            Self::Atomic(flag) => flag.load(Ordering::Relaxed),
+            Self::Watch(rx) => *rx.borrow(),
+        }
+    }
+}
+
+impl From<Arc<AtomicBool>> for MaintenanceFlag {
+    fn from(flag: Arc<AtomicBool>) -> Self {
+        Self::Atomic(flag)
+    }
+}
+
+impl From<watch::Receiver<bool>> for MaintenanceFlag {
+    fn from(rx: watch::Receiver<bool>) -> Self {
+        Self::Watch(rx)
+    }
+}
+
+/// Middleware that turns new sessions away while maintenance is on, so a
+/// deployment can drain traffic without restarting the server.
+///
+/// The flag is read as each session starts: sessions already running carry
+/// on, and flipping it back lets new ones in again. Turned-away sessions get
+/// the [`message`](Self::message) on stderr and exit status 1; users on the
+/// [`exempt`](Self::exempt) list get in regardless.
+///
+/// ```no_run
+/// # use std::sync::{Arc, atomic::{AtomicBool, Ordering}};
+/// # use shenron::{Server, middleware::Maintenance};
+/// let maintenance = Arc::new(AtomicBool::new(false));
+///
+/// let _server = Server::new().with(Maintenance::new(Arc::clone(&maintenance)).exempt(["admin"]));
+///
+/// // Later, from an admin endpoint or signal handler:
+/// maintenance.store(true, Ordering::Relaxed);
+/// ```
+pub struct Maintenance {
+    flag: MaintenanceFlag,
+    message: String,
+    exempt: HashSet<String>,
+}
+
+impl Maintenance {
+    pub fn new(flag: impl Into<MaintenanceFlag>) -> Self {
+        Self {
+            flag: flag.into(),
+            message: "Down for maintenance, back soon.\r\n".into(),
+            exempt: HashSet::new(),
+        }
+    }
+
+    /// What turned-away sessions are told.
+    #[must_use]
+    pub fn message(mut self, message: impl Into<String>) -> Self {
+        self.message = message.into();
+
+        self
+    }
+
+    /// Let `users` in during maintenance, e.g. the admins doing it.
+    #[must_use]
+    pub fn exempt(mut self, users: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.exempt.extend(users.into_iter().map(Into::into));
+
+        self
+    }
+}
+
+impl Middleware for Maintenance {
+    type Output = crate::Result<Exit>;
+
+    async fn handle(&self, session: &'_ mut Session, next: Next<'_>) -> crate::Result<Exit> {
+        if !self.flag.is_on() || self.exempt.contains(session.user()) {
+            return Ok(next.run(session).await);
+        }
+
+        tracing::info!(session = %session.id(), user = %session.user(), "turned away for maintenance");
+        session.write_stderr_str(&self.message).await?;
+
+        Ok(Exit::Code(1))
+    }
+}
@@ -0,0 +1,93 @@
+use std::time::Duration;
+
+use crate::{Exit, Middleware, Next, Session};
+
+/// The exit status of a session cut off by [`MaxDuration`], as `timeout(1)`
+/// reports a command it killed.
+pub const TIME_LIMIT_EXIT_CODE: u32 = 124;
+
+type Limit = Box<dyn Fn(&Session) -> Option<Duration> + Send + Sync>;
+
+/// Middleware that ends sessions running longer than a time limit, for
+/// shared demo servers and per-plan limits.
+///
+/// When the limit passes, the rest of the chain is dropped mid-await, the
+/// client is told on stderr, and the session closes with exit status
+/// [`TIME_LIMIT_EXIT_CODE`] (124) unless [`exit_code`](Self::exit_code)
+/// says otherwise. Anything the handler does after an `.await` never runs,
+/// so keep cleanup that must happen in `Drop` impls or outer middleware.
+///
+/// ```no_run
+/// # use std::time::Duration;
+/// # use shenron::{Server, middleware::MaxDuration};
+/// # struct Plan { minutes: u64 }
+/// let _server = Server::new()
+///     // Or per user, from what auth stored on the session:
+///     .with(MaxDuration::from_fn(|session| {
+///         session
+///             .get::<Plan>()
+///             .map(|plan| Duration::from_secs(plan.minutes * 60))
+///     }));
+/// ```
+pub struct MaxDuration {
+    limit: Limit,
+    message: String,
+    exit_code: u32,
+}
+
+impl MaxDuration {
+    /// Limit every session to `limit`.
+    #[must_use]
+    pub fn new(limit: Duration) -> Self {
+        Self::from_fn(move |_| Some(limit))
+    }
+
+    /// Limit each session to what `limit` returns for it; `None` leaves it
+    /// unlimited.
+    #[must_use]
+    pub fn from_fn(limit: impl Fn(&Session) -> Option<Duration> + Send + Sync + 'static) -> Self {
+        Self {
+            limit: Box::new(limit),
+            message: "session time limit reached".into(),
+            exit_code: TIME_LIMIT_EXIT_CODE,
+        }
+    }
+
+    /// What the client is told when the limit passes.
+    #[must_use]
+    pub fn message(mut self, message: impl Into<String>) -> Self {
+        self.message = message.into();
+
+        self
+    }
+
+    /// The exit status a session cut off reports.
+    #[must_use]
+    pub const fn exit_code(mut self, code: u32) -> Self {
+        self.exit_code = code;
+
+        self
+    }
+}
+
+impl Middleware for MaxDuration {
+    type Output = Exit;
+
+    async fn handle(&self, session: &'_ mut Session, next: Next<'_>) -> Exit {
+        let Some(limit) = (self.limit)(session) else {
+            return next.run(session).await;
+        };
+
+        let Ok(exit) = tokio::time::timeout(limit, next.run(session)).await else {
+            tracing::info!(session = %session.id(), user = %session.user(), ?limit, "session time limit reached");
+
+            let _ = session
+                .write_stderr_str(&format!("\r\n{}\r\n", self.message))
+                .await;
+
+            return Exit::Code(self.exit_code);
+        };
+
+        exit
+    }
+}
@@ -1,9 +1,28 @@
 pub mod access_control;
 pub mod active_term;
+pub mod command;
 pub mod comment;
 pub mod elapsed;
+pub mod honeypot;
+pub mod idle_timeout;
+pub mod io_hooks;
+mod json;
 pub mod logging;
+pub mod maintenance;
+pub mod max_duration;
+pub mod presence;
+pub mod recorder;
 pub mod recover;
+pub mod route;
+pub mod rsync;
+pub mod session_limit;
+pub mod subsystem;
+pub mod tarpit;
+pub mod trusted_proxy;
+pub mod wall;
+
+#[cfg(feature = "playback")]
+pub mod playback;
 
 #[cfg(feature = "rate-limiting")]
 mod rate_limit;
@@ -11,15 +30,39 @@ mod rate_limit;
 #[cfg(feature = "sftp")]
 pub mod sftp;
 
+#[cfg(feature = "wasm")]
+mod wasm;
+
 pub use access_control::*;
 pub use active_term::*;
+pub use command::*;
 pub use comment::*;
 pub use elapsed::*;
+pub use honeypot::*;
+pub use idle_timeout::*;
+pub use io_hooks::*;
 pub use logging::*;
+pub use maintenance::*;
+pub use max_duration::*;
+pub use presence::*;
+pub use recorder::*;
 pub use recover::*;
+pub use route::*;
+pub use rsync::*;
+pub use session_limit::*;
+pub use subsystem::*;
+pub use tarpit::*;
+pub use trusted_proxy::*;
+pub use wall::*;
+
+#[cfg(feature = "playback")]
+pub use playback::*;
 
 #[cfg(feature = "rate-limiting")]
 pub use rate_limit::*;
 
 #[cfg(feature = "sftp")]
 pub use sftp::*;
+
+#[cfg(feature = "wasm")]
+pub use wasm::*;
@@ -0,0 +1,239 @@
+use std::{path::Path, time::Duration};
+
+use serde_json::Value;
+
+use crate::{Event, Exit, Middleware, Next, Session};
+
+/// A session recorded as an [asciinema] v2 cast, e.g. by a
+/// [`Recorder`](super::Recorder), ready to [play](Playback) back.
+///
+/// Only what was sent to the client (`"o"` events) is kept; input and
+/// markers are skipped. Requires the `playback` feature.
+///
+/// [asciinema]: https://docs.asciinema.org/manual/asciicast/v2/
+#[derive(Debug, Clone)]
+pub struct Cast {
+    width: u32,
+    height: u32,
+    /// Output, each with how long after the last it was sent.
+    frames: Vec<(Duration, String)>,
+}
+
+impl Cast {
+    /// Parse a cast from its text.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if `text` isn't an asciinema v2 cast
+    pub fn parse(text: &str) -> crate::Result<Self> {
+        let mut lines = text.lines().filter(|line| !line.trim().is_empty());
+
+        let header = lines.next().ok_or_else(|| invalid("empty cast"))?;
+        let header: Value =
+            serde_json::from_str(header).map_err(|_| invalid("not an asciinema v2 cast"))?;
+
+        if header_number(&header, "version") != Some(2) {
+            return Err(invalid("not an asciinema v2 cast"));
+        }
+
+        let mut frames = Vec::new();
+        let mut last = Duration::ZERO;
+
+        for (n, line) in lines.enumerate() {
+            let (at, code, data): (f64, String, String) = serde_json::from_str(line)
+                .map_err(|_| invalid(&format!("bad event on line {}", n + 2)))?;
+
+            if code != "o" {
+                continue;
+            }
+
+            let at = Duration::try_from_secs_f64(at)
+                .unwrap_or_default()
+                .max(last);
+            frames.push((at - last, data));
+            last = at;
+        }
+
+        Ok(Self {
+            width: header_number(&header, "width").unwrap_or(80),
+            height: header_number(&header, "height").unwrap_or(24),
+            frames,
+        })
+    }
+
+    /// Read and [parse](Self::parse) the cast at `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the file can't be read, or as for
+    /// [`parse`](Self::parse)
+    pub async fn load(path: impl AsRef<Path>) -> crate::Result<Self> {
+        Self::parse(&tokio::fs::read_to_string(path).await?)
+    }
+
+    /// The terminal size it was recorded at, as `(width, height)`.
+    #[must_use]
+    pub const fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// How long it takes to play at normal speed.
+    #[must_use]
+    pub fn duration(&self) -> Duration {
+        self.frames.iter().map(|(delay, _)| *delay).sum()
+    }
+}
+
+/// Plays a [`Cast`] to the client as it was recorded, for reviewing audit
+/// recordings or showing demos over SSH.
+///
+/// Use it as the end of a chain, like an [`app`](crate::Server::app), or
+/// call [`play`](Self::play) from a handler that picks the recording. The
+/// client can stop it early with `q` or Ctrl+C; playback ends the session
+/// with exit status 0 either way.
+///
+/// ```no_run
+/// # use std::time::Duration;
+/// # use shenron::{Server, middleware::{Cast, Playback}};
+/// # async fn run() -> shenron::Result {
+/// let cast = Cast::load("/var/log/ssh-casts/demo.cast").await?;
+///
+/// let _server = Server::new().with(
+///     Playback::new(cast)
+///         .speed(2.0)
+///         .max_idle(Duration::from_secs(2)),
+/// );
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Playback {
+    cast: Cast,
+    speed: f64,
+    max_idle: Option<Duration>,
+}
+
+impl Playback {
+    #[must_use]
+    pub const fn new(cast: Cast) -> Self {
+        Self {
+            cast,
+            speed: 1.0,
+            max_idle: None,
+        }
+    }
+
+    /// Play `speed` times faster than recorded: `2.0` takes half as long,
+    /// `0.5` twice as long.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `speed` isn't a positive, finite number.
+    #[must_use]
+    pub fn speed(mut self, speed: f64) -> Self {
+        assert!(
+            speed.is_finite() && speed > 0.0,
+            "playback speed must be positive"
+        );
+        self.speed = speed;
+
+        self
+    }
+
+    /// Cut pauses longer than `max_idle` short, so a recording of someone
+    /// stepping away doesn't stall.
+    #[must_use]
+    pub const fn max_idle(mut self, max_idle: Duration) -> Self {
+        self.max_idle = Some(max_idle);
+
+        self
+    }
+
+    /// Play the cast to `session`, returning once it's done, the client
+    /// stops it, or the input ends.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if writing to the session fails.
+    pub async fn play(&self, session: &mut Session) -> crate::Result {
+        for (delay, data) in &self.cast.frames {
+            let delay = self
+                .max_idle
+                .map_or(*delay, |max| (*delay).min(max))
+                .div_f64(self.speed);
+
+            let sleep = tokio::time::sleep(delay);
+            tokio::pin!(sleep);
+
+            loop {
+                tokio::select! {
+                    () = &mut sleep => break,
+                    event = session.next() => match event {
+                        Some(Event::Input(input)) if input.iter().any(|&b| b == b'q' || b == 0x03) => {
+                            return session.flush().await;
+                        }
+                        None | Some(Event::Eof) => return session.flush().await,
+                        Some(_) => {}
+                    },
+                }
+            }
+
+            session.write_str(data).await?;
+        }
+
+        session.flush().await
+    }
+}
+
+impl Middleware for Playback {
+    type Output = crate::Result<Exit>;
+
+    async fn handle(&self, session: &'_ mut Session, _next: Next<'_>) -> crate::Result<Exit> {
+        tracing::info!(session = %session.id(), user = %session.user(), duration = ?self.cast.duration(), "playing back recording");
+        self.play(session).await?;
+
+        Ok(Exit::Code(0))
+    }
+}
+
+fn invalid(message: &str) -> crate::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, format!("cast: {message}")).into()
+}
+
+/// The whole-number value of `"key"` in the header.
+fn header_number(header: &Value, key: &str) -> Option<u32> {
+    header.get(key)?.as_u64()?.try_into().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_output_events() {
+        let cast = Cast::parse(concat!(
+            "{\"version\": 2, \"width\": 100, \"height\": 30, \"env\": {\"TERM\": \"xterm\"}}\n",
+            "[0.5, \"o\", \"$ \"]\n",
+            "[0.75, \"i\", \"l\"]\n",
+            "[1.5, \"o\", \"ls\\r\\n\\u001b[0m\\ud83d\\ude00\\\"\"]\n",
+        ))
+        .expect("parses");
+
+        assert_eq!(cast.size(), (100, 30));
+        assert_eq!(cast.duration(), Duration::from_millis(1500));
+        assert_eq!(
+            cast.frames,
+            [
+                (Duration::from_millis(500), "$ ".to_string()),
+                (Duration::from_secs(1), "ls\r\n\x1b[0m😀\"".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_what_isnt_a_cast() {
+        assert!(Cast::parse("").is_err());
+        assert!(Cast::parse("{\"version\": 1}").is_err());
+        assert!(Cast::parse("{\"version\": 2}\n[0.5, \"o\"]").is_err());
+    }
+}
@@ -0,0 +1,132 @@
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::{Arc, Mutex, MutexGuard},
+    time::SystemTime,
+};
+
+use crate::{Exit, Middleware, Next, Session, SessionId, SessionKind};
+
+/// One session [`Presence`] knows is connected.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct Present {
+    pub session: SessionId,
+    pub user: String,
+    pub remote_addr: SocketAddr,
+    pub kind: SessionKind,
+    pub connected_at: SystemTime,
+}
+
+/// Middleware keeping a registry of who's connected, for `who`-style
+/// commands and admin dashboards.
+///
+/// Sessions are listed from when they pass through the middleware until
+/// they end, however they end. Clones share the registry: keep one to query
+/// from outside the server, and handlers find one on the session with
+/// [`Session::get`].
+///
+/// ```no_run
+/// # use shenron::{Server, Session, middleware::Presence};
+/// async fn who(session: &mut Session) -> shenron::Result {
+///     let Some(presence) = session.get::<Presence>().cloned() else {
+///         return Ok(());
+///     };
+///
+///     for present in presence.list() {
+///         session.write_str(&format!("{}\t{}\r\n", present.user, present.remote_addr)).await?;
+///     }
+///
+///     Ok(())
+/// }
+///
+/// let presence = Presence::new();
+/// let _server = Server::new().with(presence.clone()).app(who);
+/// ```
+#[derive(Clone, Default)]
+pub struct Presence {
+    sessions: Arc<Mutex<HashMap<SessionId, Present>>>,
+}
+
+impl Presence {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Everyone connected, oldest session first.
+    #[must_use]
+    pub fn list(&self) -> Vec<Present> {
+        let mut list: Vec<_> = self.lock().values().cloned().collect();
+        list.sort_by_key(|present| (present.connected_at, present.session));
+
+        list
+    }
+
+    /// `user`'s sessions, oldest first.
+    #[must_use]
+    pub fn user(&self, user: &str) -> Vec<Present> {
+        let mut list = self.list();
+        list.retain(|present| present.user == user);
+
+        list
+    }
+
+    /// Whether `user` has a session connected.
+    #[must_use]
+    pub fn is_online(&self, user: &str) -> bool {
+        self.lock().values().any(|present| present.user == user)
+    }
+
+    /// How many sessions are connected.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<SessionId, Present>> {
+        self.sessions.lock().expect("presence poisoned")
+    }
+}
+
+/// Takes a session off the registry when dropped, so sessions cancelled
+/// mid-chain leave too.
+struct Listed<'a> {
+    presence: &'a Presence,
+    session: SessionId,
+}
+
+impl Drop for Listed<'_> {
+    fn drop(&mut self) {
+        self.presence.lock().remove(&self.session);
+    }
+}
+
+impl Middleware for Presence {
+    type Output = Exit;
+
+    async fn handle(&self, session: &'_ mut Session, next: Next<'_>) -> Exit {
+        let present = Present {
+            session: session.id(),
+            user: session.user().to_owned(),
+            remote_addr: session.remote_addr(),
+            kind: session.kind().clone(),
+            connected_at: SystemTime::now(),
+        };
+
+        self.lock().insert(present.session, present);
+        let _listed = Listed {
+            presence: self,
+            session: session.id(),
+        };
+
+        session.insert(self.clone());
+
+        next.run(session).await
+    }
+}
@@ -1,10 +1,12 @@
 use std::{
+    hash::Hash,
     net::IpAddr,
     num::NonZeroU32,
     sync::{
         Arc,
         atomic::{AtomicU64, Ordering},
     },
+    time::Duration,
 };
 
 use governor::{
@@ -14,18 +16,37 @@ use governor::{
     state::keyed::DashMapStateStore,
 };
 
-use crate::{Exit, Middleware, Next, Result, Session};
+use crate::{
+    Exit, IntoExit, Middleware, Next, Result, Session,
+    middleware::{ErasedMiddleware, TrustedProxies, terminal},
+};
+
+type KeyedLimiter<K, C> =
+    GovernorLimiter<K, DashMapStateStore<K>, C, NoOpMiddleware<<C as Clock>::Instant>>;
 
-type KeyedLimiter<C> =
-    GovernorLimiter<IpAddr, DashMapStateStore<IpAddr>, C, NoOpMiddleware<<C as Clock>::Instant>>;
+type KeyFn<K> = dyn Fn(&Session) -> K + Send + Sync;
 
-/// Sweep expired per-IP state every this many checks. Amortized inline
+/// Attached to a session turned away by a [`RateLimiter`] before its
+/// [`on_reject`](RateLimiter::on_reject) handler runs; read it with
+/// `session.get::<RateLimited>()`.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct RateLimited {
+    /// How long until the key could start a session again.
+    pub retry_after: Duration,
+}
+
+/// Sweep expired per-key state every this many checks. Amortized inline
 /// instead of a background task: no runtime needed at construction, no task
 /// lifecycle, and sweeps only happen while there is actual load.
 const SWEEP_INTERVAL: u64 = 256;
 
 /// Per-IP rate limiting for established sessions.
 ///
+/// Sessions are counted by client IP unless [`keyed_by`](Self::keyed_by)
+/// picks another key: the username, the public key's fingerprint, or an
+/// IP and user pair, for clients sharing an address behind NAT.
+///
 /// Note: this runs as middleware, so it only sees sessions that have already
 /// authenticated and opened a channel. It throttles abusive *session* rates,
 /// not raw connection or failed-auth floods — pair it with network-level
@@ -33,10 +54,12 @@ const SWEEP_INTERVAL: u64 = 256;
 /// Generic over the clock only so tests can drive eviction deterministically
 /// with [`governor::clock::FakeRelativeClock`]; servers use the default.
 #[derive(Clone)]
-pub struct RateLimiter<C: Clock = DefaultClock> {
+pub struct RateLimiter<C: Clock = DefaultClock, K: Hash + Eq + Clone = IpAddr> {
     quota: Quota,
-    limiter: Arc<KeyedLimiter<C>>,
+    limiter: Arc<KeyedLimiter<K, C>>,
     checks: Arc<AtomicU64>,
+    key: Arc<KeyFn<K>>,
+    on_reject: Option<Arc<dyn ErasedMiddleware>>,
 }
 
 impl RateLimiter {
@@ -70,7 +93,24 @@ impl RateLimiter {
         Self::from_quota(Quota::per_hour(non_zero(count)))
     }
 
-    /// Cap how many sessions an IP can start at once, independent of the
+    /// Count sessions from [`TrustedProxies`] by the client's IP they
+    /// report, rather than the proxy's. Set it before
+    /// [`keyed_by`](Self::keyed_by), which replaces the key.
+    #[must_use]
+    pub fn trusted_proxies(self, proxies: TrustedProxies) -> Self {
+        Self {
+            key: Arc::new(move |session| proxies.client_addr(session).ip()),
+            ..self
+        }
+    }
+
+    fn from_quota(quota: Quota) -> Self {
+        Self::keyed(quota, Arc::new(|session| session.remote_addr().ip()))
+    }
+}
+
+impl<K: Hash + Eq + Clone + Send + Sync + 'static> RateLimiter<DefaultClock, K> {
+    /// Cap how many sessions a key can start at once, independent of the
     /// sustained rate. Defaults to the sustained `count` when not set.
     ///
     /// # Panics
@@ -78,14 +118,51 @@ impl RateLimiter {
     /// Panics if `count` is zero
     #[must_use]
     pub fn burst(self, count: u32) -> Self {
-        Self::from_quota(self.quota.allow_burst(non_zero(count)))
+        Self {
+            on_reject: self.on_reject,
+            ..Self::keyed(self.quota.allow_burst(non_zero(count)), self.key)
+        }
     }
 
-    fn from_quota(quota: Quota) -> Self {
+    /// Count sessions by `key` instead of by client IP, e.g.
+    /// `|session| (session.remote_addr().ip(), session.user().to_owned())`.
+    #[must_use]
+    pub fn keyed_by<T>(
+        self,
+        key: impl Fn(&Session) -> T + Send + Sync + 'static,
+    ) -> RateLimiter<DefaultClock, T>
+    where
+        T: Hash + Eq + Clone + Send + Sync + 'static,
+    {
+        RateLimiter {
+            on_reject: self.on_reject,
+            ..RateLimiter::keyed(self.quota, Arc::new(key))
+        }
+    }
+
+    /// Turn limited sessions away with `handler` instead of the default
+    /// stderr message and exit status 1: to render a nicer message, say when
+    /// to retry from the [`RateLimited`] attached to the session, or close
+    /// without a word. The handler's return value is the session's exit.
+    #[must_use]
+    pub fn on_reject<F, R>(mut self, handler: F) -> Self
+    where
+        F: AsyncFn(&mut Session) -> R + Send + Sync + 'static,
+        for<'a> <F as AsyncFnMut<(&'a mut Session,)>>::CallRefFuture<'a>: Send,
+        R: IntoExit,
+    {
+        self.on_reject = Some(Arc::new(terminal(handler)));
+
+        self
+    }
+
+    fn keyed(quota: Quota, key: Arc<KeyFn<K>>) -> Self {
         Self {
             quota,
             limiter: Arc::new(GovernorLimiter::dashmap(quota)),
             checks: Arc::new(AtomicU64::new(0)),
+            key,
+            on_reject: None,
         }
     }
 }
@@ -97,12 +174,23 @@ impl<C: Clock> RateLimiter<C> {
             quota,
             limiter: Arc::new(GovernorLimiter::dashmap_with_clock(quota, clock)),
             checks: Arc::new(AtomicU64::new(0)),
+            key: Arc::new(|session| session.remote_addr().ip()),
+            on_reject: None,
         }
     }
+}
 
-    fn check(&self, ip: IpAddr) -> bool {
-        // Without periodic eviction the per-IP map grows forever (one entry
-        // per address ever seen); retain_recent drops entries whose quota has
+impl<C: Clock, K: Hash + Eq + Clone> RateLimiter<C, K> {
+    #[cfg(test)]
+    fn check(&self, key: &K) -> bool {
+        self.retry_after(key).is_none()
+    }
+
+    /// Count a session for `key`, or if it's over its quota, say how long
+    /// until it isn't.
+    fn retry_after(&self, key: &K) -> Option<Duration> {
+        // Without periodic eviction the per-key map grows forever (one entry
+        // per key ever seen); retain_recent drops entries whose quota has
         // fully replenished.
         if self
             .checks
@@ -112,7 +200,10 @@ impl<C: Clock> RateLimiter<C> {
             self.limiter.retain_recent();
         }
 
-        self.limiter.check_key(&ip).is_ok()
+        self.limiter
+            .check_key(key)
+            .err()
+            .map(|not_until| not_until.wait_time_from(self.limiter.clock().now()))
     }
 }
 
@@ -120,19 +211,25 @@ const fn non_zero(count: u32) -> NonZeroU32 {
     NonZeroU32::new(count).expect("count cannot be 0")
 }
 
-impl Middleware for RateLimiter {
+impl<K: Hash + Eq + Clone + Send + Sync + 'static> Middleware for RateLimiter<DefaultClock, K> {
     type Output = Result<Exit>;
 
     async fn handle(&self, session: &'_ mut Session, next: Next<'_>) -> Result<Exit> {
-        if !self.check(session.remote_addr().ip()) {
-            session
-                .write_stderr_str("Rate limit exceeded, try again later\n")
-                .await?;
+        let Some(retry_after) = self.retry_after(&(self.key)(session)) else {
+            return Ok(next.run(session).await);
+        };
 
-            return Ok(Exit::Code(1));
+        if let Some(on_reject) = &self.on_reject {
+            session.insert(RateLimited { retry_after });
+
+            return Ok(on_reject.handle(session, next).await);
         }
 
-        Ok(next.run(session).await)
+        session
+            .write_stderr_str("Rate limit exceeded, try again later\n")
+            .await?;
+
+        Ok(Exit::Code(1))
     }
 }
 
@@ -148,28 +245,39 @@ mod tests {
     fn burst_defaults_to_sustained_count() {
         let limiter = RateLimiter::per_minute(3);
 
-        assert!(limiter.check(ip(1)));
-        assert!(limiter.check(ip(1)));
-        assert!(limiter.check(ip(1)));
-        assert!(!limiter.check(ip(1)));
+        assert!(limiter.check(&ip(1)));
+        assert!(limiter.check(&ip(1)));
+        assert!(limiter.check(&ip(1)));
+        assert!(!limiter.check(&ip(1)));
     }
 
     #[test]
     fn burst_caps_below_sustained_count() {
         let limiter = RateLimiter::per_hour(100).burst(2);
 
-        assert!(limiter.check(ip(1)));
-        assert!(limiter.check(ip(1)));
-        assert!(!limiter.check(ip(1)));
+        assert!(limiter.check(&ip(1)));
+        assert!(limiter.check(&ip(1)));
+        assert!(!limiter.check(&ip(1)));
     }
 
     #[test]
     fn keys_are_independent() {
         let limiter = RateLimiter::per_minute(1);
 
-        assert!(limiter.check(ip(1)));
-        assert!(!limiter.check(ip(1)));
-        assert!(limiter.check(ip(2)));
+        assert!(limiter.check(&ip(1)));
+        assert!(!limiter.check(&ip(1)));
+        assert!(limiter.check(&ip(2)));
+    }
+
+    #[test]
+    fn custom_keys_keep_the_quota() {
+        let limiter = RateLimiter::per_hour(100)
+            .burst(1)
+            .keyed_by(|session| session.user().to_owned());
+
+        assert!(limiter.check(&"alice".to_owned()));
+        assert!(!limiter.check(&"alice".to_owned()));
+        assert!(limiter.check(&"bob".to_owned()));
     }
 
     #[test]
@@ -180,7 +288,7 @@ mod tests {
         let clock = governor::clock::FakeRelativeClock::default();
         let limiter = RateLimiter::with_clock(Quota::per_second(non_zero(1)), clock.clone());
 
-        assert!(limiter.check(ip(1)));
+        assert!(limiter.check(&ip(1)));
         assert_eq!(limiter.limiter.len(), 1);
 
         // An entry is droppable once its theoretical arrival time falls a full
@@ -0,0 +1,213 @@
+use std::{
+    net::SocketAddr,
+    path::PathBuf,
+    sync::Mutex,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use tokio::{
+    io::{AsyncWrite, AsyncWriteExt},
+    sync::mpsc,
+};
+
+use super::json::json_string;
+use crate::{Direction, Exit, Middleware, Next, Session, SessionId, Utf8Decoder};
+
+/// Where a [`Recorder`] writes its recordings: one writer per session.
+///
+/// [`CastDirectory`] keeps them as files; implement this to ship them
+/// elsewhere, e.g. to object storage for an audit trail.
+pub trait RecordingSink: Send + Sync + 'static {
+    type Writer: AsyncWrite + Send + Unpin + 'static;
+
+    /// Open the destination for `recording`.
+    fn open(
+        &self,
+        recording: &Recording,
+    ) -> impl Future<Output = crate::Result<Self::Writer>> + Send;
+}
+
+/// The session a recording is of, for a [`RecordingSink`] to name or file
+/// it by.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct Recording {
+    pub session_id: SessionId,
+    pub user: String,
+    pub remote_addr: SocketAddr,
+    pub started: SystemTime,
+}
+
+/// Writes each recording to `<dir>/<session id>.cast`, creating `dir` if it
+/// doesn't exist.
+#[derive(Debug, Clone)]
+pub struct CastDirectory(PathBuf);
+
+impl CastDirectory {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self(dir.into())
+    }
+}
+
+impl RecordingSink for CastDirectory {
+    type Writer = tokio::fs::File;
+
+    async fn open(&self, recording: &Recording) -> crate::Result<Self::Writer> {
+        tokio::fs::create_dir_all(&self.0).await?;
+
+        let path = self.0.join(format!("{}.cast", recording.session_id));
+
+        Ok(tokio::fs::File::create(path).await?)
+    }
+}
+
+/// Middleware that records PTY sessions as [asciinema] v2 casts, for audit
+/// trails and demos; play one back with `asciinema play`.
+///
+/// Everything sent to the client is recorded, stdout and stderr alike, with
+/// its timing; with [`record_input`](Self::record_input), so is what the
+/// client typed — passwords included, so mind where the casts go. Sessions
+/// without a PTY aren't recorded. If the sink can't be opened, the session
+/// runs unrecorded and a warning is logged.
+///
+/// ```no_run
+/// # use shenron::{Server, middleware::{CastDirectory, Recorder}};
+/// let _server = Server::new().with(Recorder::new(CastDirectory::new("/var/log/ssh-casts")));
+/// ```
+///
+/// [asciinema]: https://docs.asciinema.org/manual/asciicast/v2/
+pub struct Recorder<S> {
+    sink: S,
+    input: bool,
+}
+
+impl<S: RecordingSink> Recorder<S> {
+    pub const fn new(sink: S) -> Self {
+        Self { sink, input: false }
+    }
+
+    /// Record the client's input too, as `"i"` events.
+    #[must_use]
+    pub const fn record_input(mut self, input: bool) -> Self {
+        self.input = input;
+
+        self
+    }
+}
+
+impl<S: RecordingSink> Middleware for Recorder<S> {
+    type Output = Exit;
+
+    async fn handle(&self, session: &'_ mut Session, next: Next<'_>) -> Exit {
+        let Some((term, size)) = session.pty().map(|(term, size)| (term.to_string(), size)) else {
+            return next.run(session).await;
+        };
+
+        let recording = Recording {
+            session_id: session.id(),
+            user: session.user().to_string(),
+            remote_addr: session.remote_addr(),
+            started: SystemTime::now(),
+        };
+
+        let writer = match self.sink.open(&recording).await {
+            Ok(writer) => writer,
+            Err(e) => {
+                tracing::warn!(session = %recording.session_id, error = %e, "recording failed to open, session not recorded");
+
+                return next.run(session).await;
+            }
+        };
+
+        let (tx, rx) = mpsc::unbounded_channel();
+        let _ = tx.send(header(size.width, size.height, recording.started, &term));
+
+        let started = Instant::now();
+        let input = self.input;
+        // Output and input each have their own characters split across writes.
+        let decoders = Mutex::new([Utf8Decoder::new(), Utf8Decoder::new()]);
+
+        let tap = session.tap(move |direction, data| {
+            let (code, index) = match direction {
+                Direction::Stdout | Direction::Stderr => ("o", 0),
+                Direction::Input if input => ("i", 1),
+                Direction::Input => return,
+            };
+
+            let text = decoders.lock().expect("recorder decoders poisoned")[index].decode(data);
+
+            if !text.is_empty() {
+                let _ = tx.send(event(started.elapsed(), code, &text));
+            }
+        });
+
+        let writing = tokio::spawn(write_cast(writer, rx, recording.session_id));
+        let exit = next.run(session).await;
+
+        // Ends the cast: the tap held the only sender.
+        drop(tap);
+        let _ = writing.await;
+
+        exit
+    }
+}
+
+async fn write_cast(
+    mut writer: impl AsyncWrite + Unpin,
+    mut lines: mpsc::UnboundedReceiver<String>,
+    session_id: SessionId,
+) {
+    while let Some(line) = lines.recv().await {
+        if let Err(e) = writer.write_all(line.as_bytes()).await {
+            tracing::warn!(session = %session_id, error = %e, "recording failed, rest of session not recorded");
+
+            return;
+        }
+    }
+
+    let _ = writer.shutdown().await;
+}
+
+/// The cast's first line.
+fn header(width: u32, height: u32, started: SystemTime, term: &str) -> String {
+    let timestamp = started
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs());
+
+    format!(
+        "{{\"version\": 2, \"width\": {width}, \"height\": {height}, \"timestamp\": {timestamp}, \"env\": {{\"TERM\": {}}}}}\n",
+        json_string(term)
+    )
+}
+
+/// One `[time, code, data]` event line.
+fn event(at: Duration, code: &str, text: &str) -> String {
+    format!(
+        "[{:.6}, \"{code}\", {}]\n",
+        at.as_secs_f64(),
+        json_string(text)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events_are_json_arrays() {
+        assert_eq!(
+            event(Duration::from_millis(1500), "o", "a\"b\\\r\n\x1b[0m"),
+            "[1.500000, \"o\", \"a\\\"b\\\\\\r\\n\\u001b[0m\"]\n"
+        );
+    }
+
+    #[test]
+    fn header_carries_size_and_term() {
+        let started = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+
+        assert_eq!(
+            header(80, 24, started, "xterm-256color"),
+            "{\"version\": 2, \"width\": 80, \"height\": 24, \"timestamp\": 1700000000, \"env\": {\"TERM\": \"xterm-256color\"}}\n"
+        );
+    }
+}
@@ -0,0 +1,163 @@
+use std::{collections::HashMap, ops::AsyncFnMut};
+
+use crate::{
+    Exit, IntoExit, Next, Session, SessionKind,
+    middleware::{ErasedMiddleware, Middleware, terminal},
+};
+
+type Arm = Box<dyn ErasedMiddleware>;
+
+/// Picks a handler by what the client asked for, instead of one app
+/// matching on [`Session::kind`].
+///
+/// The most specific arm wins: a named [`subsystem`](Self::subsystem) or
+/// [`command`](Self::command), then [`exec`](Self::exec) for any other
+/// command, [`pty`](Self::pty) for an interactive shell, [`shell`](Self::shell)
+/// for any other shell, and last the [`fallback`](Self::fallback). Sessions no
+/// arm takes continue down the chain, so a `Route` without a fallback can sit
+/// in front of an [`app`](crate::Server::app). Arms are plain handlers, like
+/// `app`; share state between them by cloning an `Arc` into each.
+///
+/// ```no_run
+/// # use shenron::{Server, Session, middleware::Route};
+/// # async fn tui(session: &mut Session) -> shenron::Result { Ok(()) }
+/// # async fn cli(session: &mut Session) -> shenron::Result { Ok(()) }
+/// # async fn metrics(session: &mut Session) -> shenron::Result { Ok(()) }
+/// # async fn unsupported(session: &mut Session) -> shenron::Result<u32> { Ok(1) }
+/// let _server = Server::new().with(
+///     Route::new()
+///         .pty(tui)
+///         .exec(cli)
+///         .subsystem("metrics", metrics)
+///         .fallback(unsupported),
+/// );
+/// ```
+#[derive(Default)]
+pub struct Route {
+    subsystems: HashMap<String, Arm>,
+    commands: HashMap<String, Arm>,
+    exec: Option<Arm>,
+    pty: Option<Arm>,
+    shell: Option<Arm>,
+    fallback: Option<Arm>,
+}
+
+impl Route {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Serve the subsystem `name` (`ssh -s name`) with `handler`.
+    #[must_use]
+    pub fn subsystem<F, R>(mut self, name: impl Into<String>, handler: F) -> Self
+    where
+        F: AsyncFn(&mut Session) -> R + Send + Sync + 'static,
+        for<'a> <F as AsyncFnMut<(&'a mut Session,)>>::CallRefFuture<'a>: Send,
+        R: IntoExit,
+    {
+        self.subsystems
+            .insert(name.into(), Box::new(terminal(handler)));
+
+        self
+    }
+
+    /// Serve exec requests whose program (`argv[0]`) is `program` with
+    /// `handler`, as [`Command`](super::Command) does.
+    #[must_use]
+    pub fn command<F, R>(mut self, program: impl Into<String>, handler: F) -> Self
+    where
+        F: AsyncFn(&mut Session) -> R + Send + Sync + 'static,
+        for<'a> <F as AsyncFnMut<(&'a mut Session,)>>::CallRefFuture<'a>: Send,
+        R: IntoExit,
+    {
+        self.commands
+            .insert(program.into(), Box::new(terminal(handler)));
+
+        self
+    }
+
+    /// Serve exec requests no [`command`](Self::command) arm took, with or
+    /// without a PTY (`ssh -t host cmd` is an exec).
+    #[must_use]
+    pub fn exec<F, R>(mut self, handler: F) -> Self
+    where
+        F: AsyncFn(&mut Session) -> R + Send + Sync + 'static,
+        for<'a> <F as AsyncFnMut<(&'a mut Session,)>>::CallRefFuture<'a>: Send,
+        R: IntoExit,
+    {
+        self.exec = Some(Box::new(terminal(handler)));
+
+        self
+    }
+
+    /// Serve shells with a PTY: an interactive `ssh host`, the usual home of
+    /// a TUI.
+    #[must_use]
+    pub fn pty<F, R>(mut self, handler: F) -> Self
+    where
+        F: AsyncFn(&mut Session) -> R + Send + Sync + 'static,
+        for<'a> <F as AsyncFnMut<(&'a mut Session,)>>::CallRefFuture<'a>: Send,
+        R: IntoExit,
+    {
+        self.pty = Some(Box::new(terminal(handler)));
+
+        self
+    }
+
+    /// Serve shells the [`pty`](Self::pty) arm didn't take: all of them
+    /// without one, or those without a PTY (`ssh -T host`) alongside it.
+    #[must_use]
+    pub fn shell<F, R>(mut self, handler: F) -> Self
+    where
+        F: AsyncFn(&mut Session) -> R + Send + Sync + 'static,
+        for<'a> <F as AsyncFnMut<(&'a mut Session,)>>::CallRefFuture<'a>: Send,
+        R: IntoExit,
+    {
+        self.shell = Some(Box::new(terminal(handler)));
+
+        self
+    }
+
+    /// Serve every session no other arm took, rather than passing it down
+    /// the chain.
+    #[must_use]
+    pub fn fallback<F, R>(mut self, handler: F) -> Self
+    where
+        F: AsyncFn(&mut Session) -> R + Send + Sync + 'static,
+        for<'a> <F as AsyncFnMut<(&'a mut Session,)>>::CallRefFuture<'a>: Send,
+        R: IntoExit,
+    {
+        self.fallback = Some(Box::new(terminal(handler)));
+
+        self
+    }
+
+    fn arm(&self, session: &Session) -> Option<&Arm> {
+        let specific = match session.kind() {
+            SessionKind::Subsystem { name } => self.subsystems.get(name),
+            SessionKind::Exec { .. } => session
+                .command()
+                .and_then(|argv| argv.into_iter().next())
+                .and_then(|program| self.commands.get(&program))
+                .or(self.exec.as_ref()),
+            SessionKind::Shell if session.pty().is_some() => {
+                self.pty.as_ref().or(self.shell.as_ref())
+            }
+            SessionKind::Shell => self.shell.as_ref(),
+        };
+
+        specific.or(self.fallback.as_ref())
+    }
+}
+
+impl Middleware for Route {
+    type Output = Exit;
+
+    async fn handle(&self, session: &'_ mut Session, next: Next<'_>) -> Exit {
+        match self.arm(session) {
+            Some(arm) => arm.handle(session, next).await,
+            None => next.run(session).await,
+        }
+    }
+}
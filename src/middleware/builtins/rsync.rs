@@ -0,0 +1,403 @@
+use std::{
+    path::{Component, Path, PathBuf},
+    process::Stdio,
+};
+
+use tokio::process::Command;
+
+use crate::{Exit, IntoExit, Middleware, Next, Session};
+
+/// Server options an rsync client sends for ordinary transfers, taking no
+/// value. Anything else is refused: a denylist can't keep up with options
+/// that name paths of their own (`--log-file`, `--temp-dir`, filter rules
+/// merging files) or read the real arguments from the protocol instead
+/// (`--secluded-args`).
+const FLAGS: &[&str] = &[
+    "--server",
+    "--sender",
+    "--append",
+    "--append-verify",
+    "--delay-updates",
+    "--delete",
+    "--delete-after",
+    "--delete-before",
+    "--delete-delay",
+    "--delete-during",
+    "--delete-excluded",
+    "--existing",
+    "--force",
+    "--ignore-errors",
+    "--ignore-existing",
+    "--ignore-times",
+    "--inplace",
+    "--mkpath",
+    "--munge-links",
+    "--no-implied-dirs",
+    "--numeric-ids",
+    "--omit-dir-times",
+    "--omit-link-times",
+    "--partial",
+    "--remove-source-files",
+    "--safe-links",
+    "--size-only",
+];
+
+/// Allowed server options taking a value, which clients send as
+/// `--option=value`.
+const VALUED: &[&str] = &[
+    "--bwlimit",
+    "--checksum-choice",
+    "--checksum-seed",
+    "--compress-choice",
+    "--compress-level",
+    "--max-delete",
+    "--max-size",
+    "--min-size",
+    "--modify-window",
+    "--timeout",
+];
+
+/// Allowed short options, bundled like `-vlogDtpr`. None take a value; the
+/// `e` that ends most bundles carries the client's protocol capabilities
+/// (`-e.iLsfxC`) rather than a remote shell.
+const SHORT: &str = "vqbunlLkKHAXEpogDtUNOJSWxzRmdcryCiIh";
+
+/// Whether rsync would take `option` as one of the allowed server options.
+fn allowed(option: &str) -> bool {
+    if option.starts_with("--") {
+        return match option.split_once('=') {
+            Some((name, _)) => VALUED.contains(&name),
+            None => FLAGS.contains(&option),
+        };
+    }
+
+    let Some(bundle) = option.strip_prefix('-') else {
+        return false;
+    };
+    let letters = bundle
+        .split_once('e')
+        .map_or(bundle, |(letters, _)| letters);
+
+    !bundle.is_empty() && letters.chars().all(|letter| SHORT.contains(letter))
+}
+
+/// One `rsync --server` invocation an [`RsyncBackend`] serves, checked and
+/// confined to its root.
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct RsyncRequest {
+    /// The directory the transfer is confined to.
+    pub root: PathBuf,
+    /// The server's arguments from `--server` on, with every path rewritten
+    /// relative to [`root`](Self::root).
+    pub args: Vec<String>,
+    /// Whether the client is downloading: the server sends the files.
+    pub sender: bool,
+}
+
+impl RsyncRequest {
+    /// Check `command`, a parsed `rsync --server …` command, and rewrite its
+    /// paths relative to `root`: absolute paths are taken from the root,
+    /// and `..`, options outside the allowlist, and paths rsync would read
+    /// as options are refused.
+    fn parse(command: &[String], root: PathBuf) -> Result<Self, String> {
+        let dot = command
+            .iter()
+            .position(|arg| arg == ".")
+            .ok_or("missing the `.` before the paths")?;
+        let (options, paths) = command[1..].split_at(dot - 1);
+
+        if let Some(option) = options.iter().find(|option| !allowed(option)) {
+            return Err(format!("option not allowed: {option}"));
+        }
+
+        let mut args = options.to_vec();
+        args.push(".".into());
+
+        for path in &paths[1..] {
+            args.push(confine(path).ok_or_else(|| format!("path not allowed: {path}"))?);
+        }
+
+        Ok(Self {
+            root,
+            sender: options.iter().any(|option| option == "--sender"),
+            args,
+        })
+    }
+
+    /// The paths being transferred, relative to [`root`](Self::root).
+    #[must_use]
+    pub fn paths(&self) -> &[String] {
+        let dot = self.args.iter().position(|arg| arg == ".").unwrap_or(0);
+
+        &self.args[dot + 1..]
+    }
+}
+
+/// `path` relative to the root, or `None` if it climbs out with `..` or
+/// rsync would parse it as an option.
+fn confine(path: &str) -> Option<String> {
+    if path.starts_with('-') {
+        return None;
+    }
+
+    let mut confined = PathBuf::new();
+
+    for component in Path::new(path).components() {
+        match component {
+            Component::Normal(part) => confined.push(part),
+            Component::RootDir | Component::CurDir | Component::Prefix(_) => {}
+            Component::ParentDir => return None,
+        }
+    }
+
+    // rsync reads a trailing slash as "the directory's contents".
+    let mut confined = confined.to_str()?.to_owned();
+    if confined.starts_with('-') {
+        return None;
+    }
+    if confined.is_empty() {
+        confined.push('.');
+    }
+    if path.ends_with('/') && !confined.ends_with('/') {
+        confined.push('/');
+    }
+
+    Some(confined)
+}
+
+/// What actually speaks the rsync protocol for [`Rsync`]: the system
+/// binary ([`RsyncProcess`]) or an implementation of your own.
+pub trait RsyncBackend: Send + Sync + 'static {
+    /// Serve `request` over `session`, whose input and stdout carry the
+    /// protocol, until the transfer ends.
+    fn serve(
+        &self,
+        session: &mut Session,
+        request: &RsyncRequest,
+    ) -> impl Future<Output = crate::Result<Exit>> + Send;
+}
+
+/// Runs the system's `rsync` in the root, bridging its stdin and stdout to
+/// the channel and its stderr to the client's stderr.
+///
+/// It always adds `--munge-links`, so symlinks clients upload are stored
+/// harmless and can't be used to reach outside the root.
+pub struct RsyncProcess {
+    program: PathBuf,
+}
+
+impl RsyncProcess {
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            program: "rsync".into(),
+        }
+    }
+
+    /// Run `program` instead of the `rsync` on `PATH`.
+    #[must_use]
+    pub fn program(mut self, program: impl Into<PathBuf>) -> Self {
+        self.program = program.into();
+
+        self
+    }
+}
+
+impl Default for RsyncProcess {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RsyncBackend for RsyncProcess {
+    async fn serve(&self, session: &mut Session, request: &RsyncRequest) -> crate::Result<Exit> {
+        let (server, args) = request.args.split_first().expect("starts with --server");
+        let mut child = Command::new(&self.program)
+            .arg(server)
+            .arg("--munge-links")
+            .args(args)
+            .current_dir(&request.root)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()?;
+
+        let (Some(stdin), Some(stdout), Some(mut stderr)) =
+            (child.stdin.take(), child.stdout.take(), child.stderr.take())
+        else {
+            unreachable!("all three were piped");
+        };
+
+        let mut client_stderr = session.stderr()?;
+        let errors =
+            tokio::spawn(async move { tokio::io::copy(&mut stderr, &mut client_stderr).await });
+
+        session.pipe(tokio::io::join(stdout, stdin)).await?;
+        let status = child.wait().await?;
+        let _ = errors.await;
+
+        Ok(status.into_exit())
+    }
+}
+
+type RootFn = dyn Fn(&Session) -> Option<PathBuf> + Send + Sync;
+
+/// Middleware serving rsync over SSH (`rsync -a src/ host:backups/`).
+///
+/// Exec requests for `rsync --server` are handed to a [`RsyncBackend`], the
+/// system binary unless [`backend`](Self::backend) says otherwise.
+///
+/// Every transfer is confined to a root directory, the same for everyone
+/// with [`new`](Self::new) or chosen per session with
+/// [`from_fn`](Self::from_fn): client paths are taken relative to it,
+/// `..` is refused, and only the server options clients send for ordinary
+/// transfers are accepted, so none that name paths of their own, like
+/// `--log-file` or `--temp-dir`. Sessions given no root, and refused
+/// requests, get the reason on stderr and exit status 1. Other sessions
+/// pass through.
+///
+/// [`RsyncProcess`] munges the symlinks clients upload; symlinks already
+/// inside the root are followed as usual. Backends of your own have to keep
+/// uploaded symlinks from leading out of the root themselves.
+///
+/// ```no_run
+/// # use shenron::{Server, middleware::Rsync};
+/// let _server = Server::new().with(Rsync::from_fn(|session| {
+///     Some(format!("/srv/backups/{}", session.user()).into())
+/// }));
+/// ```
+pub struct Rsync<B = RsyncProcess> {
+    backend: B,
+    root: Box<RootFn>,
+}
+
+impl Rsync {
+    /// Confine every transfer to `root`.
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        let root = root.into();
+
+        Self::from_fn(move |_| Some(root.clone()))
+    }
+
+    /// Confine each session's transfers to the root `root_for` picks for
+    /// it, or refuse them on `None`.
+    pub fn from_fn(root_for: impl Fn(&Session) -> Option<PathBuf> + Send + Sync + 'static) -> Self {
+        Self {
+            backend: RsyncProcess::new(),
+            root: Box::new(root_for),
+        }
+    }
+}
+
+impl<B: RsyncBackend> Rsync<B> {
+    /// Serve transfers with `backend` instead of the system binary.
+    #[must_use]
+    pub fn backend<T: RsyncBackend>(self, backend: T) -> Rsync<T> {
+        Rsync {
+            backend,
+            root: self.root,
+        }
+    }
+}
+
+impl<B: RsyncBackend> Middleware for Rsync<B> {
+    type Output = crate::Result<Exit>;
+
+    async fn handle(&self, session: &'_ mut Session, next: Next<'_>) -> crate::Result<Exit> {
+        let Some(argv) = session
+            .command()
+            .filter(|argv| argv.len() > 1 && argv[0] == "rsync" && argv[1] == "--server")
+        else {
+            return Ok(next.run(session).await);
+        };
+
+        let request = (self.root)(session)
+            .ok_or_else(|| "permission denied".to_owned())
+            .and_then(|root| RsyncRequest::parse(&argv, root));
+
+        match request {
+            Ok(request) => {
+                tracing::info!(session = %session.id(), user = %session.user(), sender = request.sender, paths = ?request.paths(), "rsync");
+
+                self.backend.serve(session, &request).await
+            }
+            Err(reason) => {
+                tracing::info!(session = %session.id(), user = %session.user(), %reason, "rsync refused");
+                session
+                    .write_stderr_str(&format!("rsync: {reason}\n"))
+                    .await?;
+
+                Ok(Exit::Code(1))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RsyncRequest, confine};
+
+    fn parse(command: &str) -> Result<RsyncRequest, String> {
+        RsyncRequest::parse(&shell_words::split(command).expect("parses"), "/srv".into())
+    }
+
+    #[test]
+    fn paths_are_confined_to_the_root() {
+        assert_eq!(confine("/etc/passwd").as_deref(), Some("etc/passwd"));
+        assert_eq!(confine("backups/").as_deref(), Some("backups/"));
+        assert_eq!(confine("./a/./b").as_deref(), Some("a/b"));
+        assert_eq!(confine("/").as_deref(), Some("./"));
+        assert_eq!(confine("a/../../b"), None);
+    }
+
+    #[test]
+    fn server_commands_are_rewritten() {
+        let request = parse("rsync --server --sender -vlogDtpre.iLsfxC . /docs 'my files/'")
+            .expect("allowed");
+
+        assert!(request.sender);
+        assert_eq!(
+            request.args,
+            [
+                "--server",
+                "--sender",
+                "-vlogDtpre.iLsfxC",
+                ".",
+                "docs",
+                "my files/"
+            ]
+        );
+        assert_eq!(request.paths(), ["docs", "my files/"]);
+    }
+
+    #[test]
+    fn escapes_are_refused() {
+        assert!(parse("rsync --server -e.LsfxC . ../x").is_err());
+        assert!(parse("rsync --server --log-file=/tmp/log . x").is_err());
+        assert!(parse("rsync --server --temp-dir /tmp . x").is_err());
+        assert!(parse("rsync --server -vlogDtpre.iLsfxC").is_err());
+        assert!(parse("rsync --server --delete-after -r . x").is_ok());
+        assert!(parse("rsync --server --timeout=30 -vlogDtpre.iLsfxC . x").is_ok());
+
+        assert!(parse("rsync --server . --log-file=/etc/x dst").is_err());
+        assert!(parse("rsync --server . x -T /tmp").is_err());
+        assert!(parse("rsync --server . /--log-file=/etc/x").is_err());
+
+        assert!(parse("rsync --server -T /tmp . x").is_err());
+        assert!(parse("rsync --server -vlT/tmp . x").is_err());
+        assert!(parse("rsync --server -f 'merge /etc/filter' . x").is_err());
+        assert!(parse("rsync --server --filter='merge /etc/filter' . x").is_err());
+        assert!(parse("rsync --server --exclude-from=/etc/shadow . x").is_err());
+        assert!(parse("rsync --server --exclude-f=/etc/shadow . x").is_err());
+        assert!(parse("rsync --server --log=/tmp/log . x").is_err());
+
+        // `-s` sends the real arguments over the protocol, unchecked.
+        assert!(parse("rsync --server -vlogDtprs . x").is_err());
+        assert!(parse("rsync --server --secluded-args . x").is_err());
+        assert!(parse("rsync --server --no-munge-links -r . x").is_err());
+        assert!(parse("rsync --server --copy-unsafe-links -r . x").is_err());
+        assert!(parse("rsync --server --timeout 30 . x").is_err());
+    }
+}
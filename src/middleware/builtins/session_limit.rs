@@ -0,0 +1,293 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    hash::Hash,
+    net::IpAddr,
+    sync::{
+        Arc, Mutex, MutexGuard,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+use tokio::sync::{Notify, futures::Notified};
+
+use crate::{Exit, Middleware, Next, Result, Session, middleware::TrustedProxies};
+
+/// Middleware capping how many sessions one client IP may have open at
+/// once, so a single host can't take every slot on the server.
+///
+/// Unlike [`RateLimiter`](super::RateLimiter), which counts how fast
+/// sessions start, this counts how many are running: sessions past the cap
+/// are told so on stderr and exit with status 1, and a slot frees up as
+/// soon as one of the IP's sessions ends. Several sessions multiplexed over
+/// one connection each count.
+///
+/// ```no_run
+/// # use shenron::{Server, middleware::MaxSessionsPerIp};
+/// let _server = Server::new().with(MaxSessionsPerIp::new(4));
+/// ```
+pub struct MaxSessionsPerIp {
+    max: usize,
+    live: Arc<Live<IpAddr>>,
+    message: String,
+    proxies: TrustedProxies,
+}
+
+impl MaxSessionsPerIp {
+    #[must_use]
+    pub fn new(max: usize) -> Self {
+        Self {
+            max,
+            live: Arc::default(),
+            message: "Too many sessions from your address; close one and try again\n".into(),
+            proxies: TrustedProxies::default(),
+        }
+    }
+
+    /// What sessions past the cap are told.
+    #[must_use]
+    pub fn message(mut self, message: impl Into<String>) -> Self {
+        self.message = message.into();
+
+        self
+    }
+
+    /// Count sessions from [`TrustedProxies`] by the client's IP they
+    /// report, rather than the proxy's.
+    #[must_use]
+    pub fn trusted_proxies(mut self, proxies: TrustedProxies) -> Self {
+        self.proxies = proxies;
+
+        self
+    }
+}
+
+impl Middleware for MaxSessionsPerIp {
+    type Output = Result<Exit>;
+
+    async fn handle(&self, session: &'_ mut Session, next: Next<'_>) -> Result<Exit> {
+        let ip = self.proxies.client_addr(session).ip();
+
+        let Some(_slot) = Live::enter(&self.live, ip, self.max, false) else {
+            tracing::info!(session = %session.id(), %ip, max = self.max, "too many sessions from address");
+            session.write_stderr_str(&self.message).await?;
+
+            return Ok(Exit::Code(1));
+        };
+
+        Ok(next.run(session).await)
+    }
+}
+
+/// Middleware capping how many sessions one user may have open at once, a
+/// common policy for admin gateways.
+///
+/// Sessions past the cap are turned away on stderr with exit status 1, or,
+/// with [`evict_oldest`](Self::evict_oldest), let in while the user's
+/// oldest session is closed to make room: it's told why on stderr, its
+/// chain is dropped mid-await as with [`MaxDuration`](super::MaxDuration),
+/// and it exits with status 1. The count covers all of the user's
+/// sessions, on any connection.
+///
+/// ```no_run
+/// # use shenron::{Server, middleware::MaxSessionsPerUser};
+/// let _server = Server::new().with(MaxSessionsPerUser::new(1).evict_oldest());
+/// ```
+pub struct MaxSessionsPerUser {
+    max: usize,
+    live: Arc<Live<String>>,
+    evict_oldest: bool,
+    message: String,
+}
+
+impl MaxSessionsPerUser {
+    #[must_use]
+    pub fn new(max: usize) -> Self {
+        Self {
+            max,
+            live: Arc::default(),
+            evict_oldest: false,
+            message: "Too many sessions for your account; close one and try again\n".into(),
+        }
+    }
+
+    /// Close the user's oldest session to make room for a new one, instead
+    /// of turning the new one away.
+    #[must_use]
+    pub const fn evict_oldest(mut self) -> Self {
+        self.evict_oldest = true;
+
+        self
+    }
+
+    /// What sessions past the cap are told, or with
+    /// [`evict_oldest`](Self::evict_oldest), the sessions evicted.
+    #[must_use]
+    pub fn message(mut self, message: impl Into<String>) -> Self {
+        self.message = message.into();
+
+        self
+    }
+}
+
+impl Middleware for MaxSessionsPerUser {
+    type Output = Result<Exit>;
+
+    async fn handle(&self, session: &'_ mut Session, next: Next<'_>) -> Result<Exit> {
+        let user = session.user().to_owned();
+
+        let Some(slot) = Live::enter(&self.live, user, self.max, self.evict_oldest) else {
+            tracing::info!(session = %session.id(), user = %session.user(), max = self.max, "too many sessions for user");
+            session.write_stderr_str(&self.message).await?;
+
+            return Ok(Exit::Code(1));
+        };
+
+        tokio::select! {
+            exit = next.run(session) => Ok(exit),
+            () = slot.evicted() => {
+                tracing::info!(session = %session.id(), user = %session.user(), "session evicted by a newer one");
+                session.write_stderr_str(&self.message).await?;
+
+                Ok(Exit::Code(1))
+            }
+        }
+    }
+}
+
+/// The sessions live per key, oldest first.
+struct Live<K> {
+    next_id: AtomicU64,
+    sessions: Mutex<HashMap<K, VecDeque<Entry>>>,
+}
+
+struct Entry {
+    id: u64,
+    evicted: Arc<Notify>,
+}
+
+impl<K> Default for Live<K> {
+    fn default() -> Self {
+        Self {
+            next_id: AtomicU64::new(0),
+            sessions: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl<K: Hash + Eq + Clone> Live<K> {
+    /// Count a session for `key`, unless it already has `max`; with `evict`,
+    /// make room by evicting its oldest sessions instead. The slot stops
+    /// counting when dropped.
+    fn enter(live: &Arc<Self>, key: K, max: usize, evict: bool) -> Option<Slot<K>> {
+        let mut sessions = live.lock();
+
+        // Checked before inserting, so turning a key away doesn't leave an
+        // empty entry behind.
+        if sessions.get(&key).map_or(0, VecDeque::len) >= max && (!evict || max == 0) {
+            return None;
+        }
+
+        let entries = sessions.entry(key.clone()).or_default();
+
+        while entries.len() >= max {
+            if let Some(oldest) = entries.pop_front() {
+                oldest.evicted.notify_one();
+            }
+        }
+
+        let id = live.next_id.fetch_add(1, Ordering::Relaxed);
+        let evicted = Arc::new(Notify::new());
+        entries.push_back(Entry {
+            id,
+            evicted: Arc::clone(&evicted),
+        });
+        drop(sessions);
+
+        Some(Slot {
+            live: Arc::clone(live),
+            key,
+            id,
+            evicted,
+        })
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<K, VecDeque<Entry>>> {
+        self.sessions.lock().expect("live sessions poisoned")
+    }
+}
+
+/// One live session, counted against its key until dropped.
+struct Slot<K: Hash + Eq + Clone> {
+    live: Arc<Live<K>>,
+    key: K,
+    id: u64,
+    evicted: Arc<Notify>,
+}
+
+impl<K: Hash + Eq + Clone> Slot<K> {
+    /// Resolves once a newer session has evicted this one.
+    fn evicted(&self) -> Notified<'_> {
+        self.evicted.notified()
+    }
+}
+
+impl<K: Hash + Eq + Clone> Drop for Slot<K> {
+    fn drop(&mut self) {
+        let mut sessions = self.live.lock();
+
+        if let Some(entries) = sessions.get_mut(&self.key) {
+            entries.retain(|entry| entry.id != self.id);
+
+            if entries.is_empty() {
+                sessions.remove(&self.key);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::Live;
+
+    #[test]
+    fn slots_free_up_when_dropped() {
+        let live = Arc::new(Live::default());
+
+        let first = Live::enter(&live, "a", 2, false).expect("room");
+        let _second = Live::enter(&live, "a", 2, false).expect("room");
+        assert!(Live::enter(&live, "a", 2, false).is_none());
+        assert!(Live::enter(&live, "b", 2, false).is_some());
+
+        drop(first);
+        assert!(Live::enter(&live, "a", 2, false).is_some());
+    }
+
+    #[test]
+    fn keys_without_sessions_are_forgotten() {
+        let live = Arc::new(Live::default());
+
+        drop(Live::enter(&live, "a", 1, false));
+        assert!(Live::enter(&live, "b", 0, false).is_none());
+        assert!(Live::enter(&live, "c", 0, true).is_none());
+
+        assert!(live.lock().is_empty());
+    }
+
+    #[tokio::test]
+    async fn evicting_frees_the_oldest_slot() {
+        let live = Arc::new(Live::default());
+
+        let first = Live::enter(&live, "a", 2, true).expect("room");
+        let second = Live::enter(&live, "a", 2, true).expect("room");
+        let third = Live::enter(&live, "a", 2, true).expect("evicted one");
+
+        first.evicted().await;
+        drop(first);
+        assert_eq!(live.lock()["a"].len(), 2);
+
+        drop((second, third));
+        assert!(live.lock().is_empty());
+    }
+}
@@ -0,0 +1,27 @@
+use std::net::SocketAddr;
+
+use crate::{Extensions, Session, SessionId};
+
+/// Who an SFTP request comes from: the session serving it, snapshotted when
+/// the subsystem starts.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct SftpContext {
+    pub session: SessionId,
+    pub user: String,
+    pub remote_addr: SocketAddr,
+    /// What auth and earlier middleware attached to the session, e.g. the
+    /// user's account or tenant.
+    pub extensions: Extensions,
+}
+
+impl SftpContext {
+    pub(crate) fn new(session: &Session) -> Self {
+        Self {
+            session: session.id(),
+            user: session.user().to_owned(),
+            remote_addr: session.remote_addr(),
+            extensions: session.extensions().clone(),
+        }
+    }
+}
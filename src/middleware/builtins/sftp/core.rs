@@ -1,24 +1,239 @@
-use std::path::Path;
+use std::{io, path::Path, sync::Arc};
 
 use crate::{
-    Exit, Middleware, Next, Session, SessionKind,
+    BoxFuture, Exit, Middleware, Next, Session, SessionKind,
     middleware::builtins::sftp::{
-        filesystem::Filesystem, handler::SftpHandler, local::LocalFilesystem,
+        context::SftpContext,
+        event::{EventHook, SftpEvent},
+        filesystem::Filesystem,
+        handler::SftpHandler,
+        local::LocalFilesystem,
+        pace::Pace,
+        policy::SftpPolicy,
+        read_only::ReadOnly,
     },
 };
 
+/// Picks a session's filesystem, for [`Sftp::per_session`].
+type Select<F> = Arc<dyn Fn(&Session) -> BoxFuture<io::Result<F>> + Send + Sync>;
+
+/// Picks a session's transfer rate, for [`Sftp::max_rate_by`].
+type Rate = Arc<dyn Fn(&Session) -> Option<u64> + Send + Sync>;
+
+enum Backend<F> {
+    /// One filesystem for every session.
+    Shared(F),
+    PerSession(Select<F>),
+}
+
+impl<F: Clone> Clone for Backend<F> {
+    fn clone(&self) -> Self {
+        match self {
+            Self::Shared(fs) => Self::Shared(fs.clone()),
+            Self::PerSession(select) => Self::PerSession(Arc::clone(select)),
+        }
+    }
+}
+
 /// Middleware that serves the `sftp` subsystem from a [`Filesystem`].
 ///
 /// Non-SFTP sessions pass through to the next middleware untouched.
 #[derive(Clone)]
 pub struct Sftp<F: Filesystem> {
-    fs: F,
+    backend: Backend<F>,
+    options: Options,
+}
+
+/// Everything but the filesystem, kept when [`Sftp::read_only`] swaps that.
+#[derive(Clone)]
+struct Options {
+    policy: Option<Arc<dyn SftpPolicy>>,
+    on_event: Option<EventHook>,
+    max_rate: Option<Rate>,
 }
 
 impl<F: Filesystem> Sftp<F> {
     /// Serve SFTP requests from `fs`.
     pub const fn new(fs: F) -> Self {
-        Self { fs }
+        Self {
+            backend: Backend::Shared(fs),
+            options: Options {
+                policy: None,
+                on_event: None,
+                max_rate: None,
+            },
+        }
+    }
+
+    /// Serve each session its own filesystem, picked by `select` once the
+    /// client is authenticated — per-user chroots, per-tenant buckets.
+    ///
+    /// If `select` fails, the session is refused with exit status 1.
+    ///
+    /// ```no_run
+    /// use std::path::Path;
+    ///
+    /// use shenron::sftp::{LocalFilesystem, Sftp};
+    ///
+    /// let sftp = Sftp::per_session(|session| {
+    ///     let root = Path::new("/srv/sftp").join(session.user());
+    ///
+    ///     async move { LocalFilesystem::try_new(root) }
+    /// });
+    /// ```
+    ///
+    /// The user name is whatever the client authenticated as; make sure
+    /// your auth only accepts names that are safe to use in a path.
+    #[must_use]
+    pub fn per_session<S, Fut>(select: S) -> Self
+    where
+        S: Fn(&Session) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = io::Result<F>> + Send + 'static,
+    {
+        Self {
+            backend: Backend::PerSession(Arc::new(move |session| Box::pin(select(session)))),
+            options: Options {
+                policy: None,
+                on_event: None,
+                max_rate: None,
+            },
+        }
+    }
+
+    /// Check each operation against `policy` before running it, e.g. to
+    /// keep users to their own directory of a shared tree.
+    ///
+    /// ```no_run
+    /// use shenron::sftp::{Sftp, SftpContext, SftpOp};
+    ///
+    /// fn allow(context: &SftpContext, op: &SftpOp<'_>) -> bool {
+    ///     match op {
+    ///         SftpOp::Read(_) | SftpOp::ReadDir(_) => true,
+    ///         SftpOp::Write(path) => path.starts_with(&format!("/incoming/{}/", context.user)),
+    ///         _ => false,
+    ///     }
+    /// }
+    ///
+    /// let sftp = Sftp::local("/srv/shared").policy(allow);
+    /// ```
+    #[must_use]
+    pub fn policy(mut self, policy: impl SftpPolicy) -> Self {
+        self.options.policy = Some(Arc::new(policy));
+
+        self
+    }
+
+    /// Call `hook` with each upload and download as it starts and finishes,
+    /// and with each file removed or renamed — e.g. to kick off processing
+    /// when a file lands. It runs inline, so hand the event off rather than
+    /// doing I/O in it.
+    ///
+    /// ```no_run
+    /// use shenron::sftp::{Sftp, SftpContext, SftpEvent};
+    ///
+    /// fn landed(context: &SftpContext, event: &SftpEvent) {
+    ///     if let SftpEvent::UploadFinished { path, bytes, .. } = event {
+    ///         println!("{} uploaded {path} ({bytes} bytes)", context.user);
+    ///     }
+    /// }
+    ///
+    /// let sftp = Sftp::local("/srv/incoming").on_event(landed);
+    /// ```
+    #[must_use]
+    pub fn on_event(
+        mut self,
+        hook: impl Fn(&SftpContext, &SftpEvent) + Send + Sync + 'static,
+    ) -> Self {
+        self.options.on_event = Some(Arc::new(hook));
+
+        self
+    }
+
+    /// Hold each session's transfers to `bytes_per_second`, uploads and
+    /// downloads together. Each session gets the whole rate, however many
+    /// others are running.
+    ///
+    /// ```no_run
+    /// use shenron::sftp::Sftp;
+    ///
+    /// let sftp = Sftp::local("/srv/files").max_rate(1024 * 1024);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bytes_per_second` is zero.
+    #[must_use]
+    pub fn max_rate(self, bytes_per_second: u64) -> Self {
+        assert!(bytes_per_second > 0, "SFTP rate must be positive");
+
+        self.max_rate_by(move |_| Some(bytes_per_second))
+    }
+
+    /// Like [`max_rate`](Self::max_rate), with the rate picked per session
+    /// by `rate`, e.g. from what auth attached; `None` or zero leaves the
+    /// session unlimited.
+    ///
+    /// ```no_run
+    /// use shenron::sftp::Sftp;
+    ///
+    /// #[derive(Clone, PartialEq)]
+    /// enum Plan {
+    ///     Free,
+    ///     Paid,
+    /// }
+    ///
+    /// let sftp = Sftp::local("/srv/files").max_rate_by(|session| {
+    ///     (session.get::<Plan>() != Some(&Plan::Paid)).then_some(1024 * 1024)
+    /// });
+    /// ```
+    #[must_use]
+    pub fn max_rate_by(
+        mut self,
+        rate: impl Fn(&Session) -> Option<u64> + Send + Sync + 'static,
+    ) -> Self {
+        self.options.max_rate = Some(Arc::new(rate));
+
+        self
+    }
+
+    /// Serve the filesystem [read-only](ReadOnly): clients can list and
+    /// download, but not upload, delete, rename or change anything.
+    ///
+    /// ```no_run
+    /// use shenron::sftp::Sftp;
+    ///
+    /// let sftp = Sftp::local("/srv/releases").read_only();
+    /// ```
+    #[must_use]
+    pub fn read_only(self) -> Sftp<ReadOnly<F>> {
+        let sftp = match self.backend {
+            Backend::Shared(fs) => Sftp::new(ReadOnly::new(fs)),
+            Backend::PerSession(select) => Sftp::per_session(move |session| {
+                let fs = select(session);
+
+                async move { fs.await.map(ReadOnly::new) }
+            }),
+        };
+
+        Sftp {
+            options: self.options,
+            ..sftp
+        }
+    }
+
+    fn pace(&self, session: &Session) -> Option<Pace> {
+        let rate = self.options.max_rate.as_ref()?;
+        let rate = rate(session)?;
+
+        (rate > 0).then(|| Pace::new(rate))
+    }
+
+    /// The filesystem to serve `session`.
+    async fn filesystem(&self, session: &Session) -> io::Result<F> {
+        match &self.backend {
+            Backend::Shared(fs) => Ok(fs.clone()),
+            Backend::PerSession(select) => select(session).await,
+        }
     }
 }
 
@@ -47,12 +262,23 @@ impl<F: Filesystem> Middleware for Sftp<F> {
     async fn handle(&self, session: &'_ mut Session, next: Next<'_>) -> Exit {
         match session.kind() {
             SessionKind::Subsystem { name } if name == "sftp" => {
-                let Some(channel) = session.take_channel() else {
+                let fs = match self.filesystem(session).await {
+                    Ok(fs) => fs,
+                    Err(e) => {
+                        tracing::warn!(session = %session.id(), user = %session.user(), error = %e, "no SFTP filesystem for session, refusing it");
+                        return Exit::Code(1);
+                    }
+                };
+
+                let Ok(stream) = session.take_stream() else {
                     return Exit::Code(0);
                 };
 
-                let stream = channel.into_stream();
-                let handler = SftpHandler::new(self.fs.clone());
+                let handler = SftpHandler::new(fs)
+                    .context(SftpContext::new(session))
+                    .policy(self.options.policy.clone())
+                    .on_event(self.options.on_event.clone())
+                    .pace(self.pace(session));
 
                 russh_sftp::server::run(stream, handler).await;
 
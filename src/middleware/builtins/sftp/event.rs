@@ -0,0 +1,42 @@
+use std::{sync::Arc, time::Duration};
+
+use crate::middleware::builtins::sftp::context::SftpContext;
+
+/// Callback registered with [`Sftp::on_event`](super::Sftp::on_event).
+pub(crate) type EventHook = Arc<dyn Fn(&SftpContext, &SftpEvent) + Send + Sync>;
+
+/// A transfer or change an SFTP client made, reported to
+/// [`Sftp::on_event`](super::Sftp::on_event) once it has happened.
+///
+/// A transfer starts when the client opens the file and finishes when it
+/// closes it. One the client abandons by disconnecting with the file still
+/// open never finishes, so a finished upload is a complete one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SftpEvent {
+    UploadStarted {
+        path: String,
+    },
+    UploadFinished {
+        path: String,
+        /// How much the client wrote.
+        bytes: u64,
+        elapsed: Duration,
+    },
+    DownloadStarted {
+        path: String,
+    },
+    DownloadFinished {
+        path: String,
+        /// How much the client read.
+        bytes: u64,
+        elapsed: Duration,
+    },
+    Removed {
+        path: String,
+    },
+    Renamed {
+        from: String,
+        to: String,
+    },
+}
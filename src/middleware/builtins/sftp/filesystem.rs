@@ -2,6 +2,8 @@ use std::io;
 
 use russh_sftp::protocol::{FileAttributes, OpenFlags};
 
+use crate::middleware::builtins::sftp::context::SftpContext;
+
 /// Trait for filesystem operations.
 ///
 /// Methods are async and run on the tokio runtime, so implementations must
@@ -14,6 +16,16 @@ pub trait Filesystem: Sync + Clone + 'static {
     /// [`Filesystem::open_write`].
     type Handle: FileHandle;
 
+    /// This filesystem as it serves the session `context` describes, called
+    /// once as the session starts; the operations that follow all come from
+    /// it. Backends that need to know who's asking — to log or authorize
+    /// each operation, or to pick the user's data — keep what they need of
+    /// the context. By default every session is served the same.
+    #[must_use]
+    fn for_session(&self, _context: &SftpContext) -> Self {
+        self.clone()
+    }
+
     /// Read from a directory
     ///
     /// # Errors
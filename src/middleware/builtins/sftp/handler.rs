@@ -1,15 +1,25 @@
 use std::{
     collections::HashMap,
     io,
-    sync::atomic::{AtomicU64, Ordering},
-    time::{SystemTime, UNIX_EPOCH},
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Instant, SystemTime, UNIX_EPOCH},
 };
 
-use russh_sftp::protocol::{
-    Attrs, Data, FileAttributes, Handle, Name, OpenFlags, Status, StatusCode, Version,
+use russh_sftp::{
+    protocol::{Attrs, Data, FileAttributes, Handle, Name, OpenFlags, Status, StatusCode, Version},
+    server::StatusReply,
 };
 
-use crate::middleware::builtins::sftp::filesystem::{DirEntry, FileAttr, FileHandle, Filesystem};
+use crate::middleware::builtins::sftp::{
+    context::SftpContext,
+    event::{EventHook, SftpEvent},
+    filesystem::{DirEntry, FileAttr, FileHandle, Filesystem},
+    pace::Pace,
+    policy::{SftpOp, SftpPolicy},
+};
 
 /// `len` in `SSH_FXP_READ` is client-controlled; clamp it so a hostile
 /// `len = u32::MAX` can't force a 4 GiB allocation. Short reads are legal —
@@ -25,29 +35,101 @@ pub struct SftpHandler<F: Filesystem> {
     fs: F,
     handles: HashMap<String, HandleType<F::Handle>>,
     next_handle: AtomicU64,
+    context: Option<SftpContext>,
+    policy: Option<Arc<dyn SftpPolicy>>,
+    on_event: Option<EventHook>,
+    pace: Option<Pace>,
 
     version: Option<u32>,
 }
 
 enum HandleType<H> {
-    File(H),
+    File {
+        file: H,
+        /// Where it was opened, for the policy to judge `fsetstat` by.
+        path: String,
+        transfer: Transfer,
+    },
     Dir {
         entries: Vec<DirEntry>,
         offset: usize,
     },
 }
 
+/// What's gone through a file handle, for its [`SftpEvent`] on close.
+struct Transfer {
+    upload: bool,
+    started: Instant,
+    bytes: u64,
+}
+
 impl<F: Filesystem> SftpHandler<F> {
     pub fn new(fs: F) -> Self {
         Self {
             fs,
             handles: HashMap::new(),
             next_handle: AtomicU64::new(0),
+            context: None,
+            policy: None,
+            on_event: None,
+            pace: None,
 
             version: None,
         }
     }
 
+    /// Who the requests come from, for the filesystem, policy and event
+    /// hook.
+    pub fn context(mut self, context: SftpContext) -> Self {
+        self.fs = self.fs.for_session(&context);
+        self.context = Some(context);
+
+        self
+    }
+
+    /// Consult `policy` before each operation.
+    pub fn policy(mut self, policy: Option<Arc<dyn SftpPolicy>>) -> Self {
+        self.policy = policy;
+
+        self
+    }
+
+    /// Report transfers and changes to `hook`.
+    pub fn on_event(mut self, hook: Option<EventHook>) -> Self {
+        self.on_event = hook;
+
+        self
+    }
+
+    /// Hold reads and writes to `pace`.
+    pub fn pace(mut self, pace: Option<Pace>) -> Self {
+        self.pace = pace;
+
+        self
+    }
+
+    fn check(&self, op: &SftpOp<'_>) -> Result<(), StatusReply> {
+        match (&self.policy, &self.context) {
+            (Some(policy), Some(context)) if !policy.allow(context, op) => {
+                tracing::debug!(session = %context.session, user = %context.user, ?op, "SFTP operation denied by policy");
+                Err(StatusCode::PermissionDenied.into())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn emit(&self, event: &SftpEvent) {
+        let Some(context) = &self.context else {
+            return;
+        };
+
+        tracing::debug!(session = %context.session, user = %context.user, ?event, "sftp");
+
+        if let Some(hook) = &self.on_event {
+            hook(context, event);
+        }
+    }
+
     fn next_handle(&self) -> String {
         let id = self.next_handle.fetch_add(1, Ordering::SeqCst);
 
@@ -56,10 +138,10 @@ impl<F: Filesystem> SftpHandler<F> {
 }
 
 impl<F: Filesystem> russh_sftp::server::Handler for SftpHandler<F> {
-    type Error = StatusCode;
+    type Error = StatusReply;
 
     fn unimplemented(&self) -> Self::Error {
-        StatusCode::OpUnsupported
+        StatusCode::OpUnsupported.into()
     }
 
     async fn init(
@@ -69,7 +151,7 @@ impl<F: Filesystem> russh_sftp::server::Handler for SftpHandler<F> {
     ) -> Result<Version, Self::Error> {
         if self.version.is_some() {
             tracing::error!("duplicate SSH_FXP_VERSION packet");
-            return Err(StatusCode::ConnectionLost);
+            return Err(StatusCode::ConnectionLost.into());
         }
 
         self.version = Some(version);
@@ -78,9 +160,32 @@ impl<F: Filesystem> russh_sftp::server::Handler for SftpHandler<F> {
 
     async fn close(&mut self, id: u32, handle: String) -> Result<Status, Self::Error> {
         match self.handles.remove(&handle) {
-            Some(HandleType::File(f)) => f.close().await.map_err(|e| status_code(&e))?,
+            Some(HandleType::File {
+                file,
+                path,
+                transfer,
+            }) => {
+                file.close().await.map_err(|e| status(&e))?;
+
+                let elapsed = transfer.started.elapsed();
+                let bytes = transfer.bytes;
+
+                self.emit(&if transfer.upload {
+                    SftpEvent::UploadFinished {
+                        path,
+                        bytes,
+                        elapsed,
+                    }
+                } else {
+                    SftpEvent::DownloadFinished {
+                        path,
+                        bytes,
+                        elapsed,
+                    }
+                });
+            }
             Some(HandleType::Dir { .. }) => {}
-            None => return Err(StatusCode::Failure),
+            None => return Err(StatusCode::Failure.into()),
         }
 
         status_ok(id)
@@ -95,14 +200,39 @@ impl<F: Filesystem> russh_sftp::server::Handler for SftpHandler<F> {
     ) -> Result<Handle, Self::Error> {
         let handle = self.next_handle();
 
-        let file = if pflags.contains(OpenFlags::WRITE) || pflags.contains(OpenFlags::CREATE) {
+        let upload = pflags.contains(OpenFlags::WRITE) || pflags.contains(OpenFlags::CREATE);
+
+        let file = if upload {
+            self.check(&SftpOp::Write(&filename))?;
             self.fs.open_write(&filename, pflags, attrs.into()).await
         } else {
+            self.check(&SftpOp::Read(&filename))?;
             self.fs.open_read(&filename).await
         }
-        .map_err(|e| status_code(&e))?;
+        .map_err(|e| status(&e))?;
 
-        self.handles.insert(handle.clone(), HandleType::File(file));
+        self.emit(&if upload {
+            SftpEvent::UploadStarted {
+                path: filename.clone(),
+            }
+        } else {
+            SftpEvent::DownloadStarted {
+                path: filename.clone(),
+            }
+        });
+
+        self.handles.insert(
+            handle.clone(),
+            HandleType::File {
+                file,
+                path: filename,
+                transfer: Transfer {
+                    upload,
+                    started: Instant::now(),
+                    bytes: 0,
+                },
+            },
+        );
 
         Ok(Handle { id, handle })
     }
@@ -114,17 +244,25 @@ impl<F: Filesystem> russh_sftp::server::Handler for SftpHandler<F> {
         offset: u64,
         len: u32,
     ) -> Result<Data, Self::Error> {
-        let Some(HandleType::File(f)) = self.handles.get_mut(&handle) else {
-            return Err(StatusCode::Failure);
+        let Some(HandleType::File {
+            file: f, transfer, ..
+        }) = self.handles.get_mut(&handle)
+        else {
+            return Err(StatusCode::Failure.into());
         };
 
         let data = f
             .read(offset, len.min(MAX_READ_LEN))
             .await
-            .map_err(|e| status_code(&e))?;
+            .map_err(|e| status(&e))?;
+        transfer.bytes += data.len() as u64;
+
+        if let Some(pace) = &mut self.pace {
+            pace.take(data.len() as u64).await;
+        }
 
         if data.is_empty() {
-            return Err(StatusCode::Eof);
+            return Err(StatusCode::Eof.into());
         }
 
         Ok(Data { id, data })
@@ -137,17 +275,26 @@ impl<F: Filesystem> russh_sftp::server::Handler for SftpHandler<F> {
         offset: u64,
         data: Vec<u8>,
     ) -> Result<Status, Self::Error> {
-        let Some(HandleType::File(f)) = self.handles.get_mut(&handle) else {
-            return Err(StatusCode::Failure);
+        let Some(HandleType::File {
+            file: f, transfer, ..
+        }) = self.handles.get_mut(&handle)
+        else {
+            return Err(StatusCode::Failure.into());
         };
 
-        f.write(offset, data).await.map_err(|e| status_code(&e))?;
+        let written = f.write(offset, data).await.map_err(|e| status(&e))?;
+        transfer.bytes += u64::from(written);
+
+        if let Some(pace) = &mut self.pace {
+            pace.take(u64::from(written)).await;
+        }
 
         status_ok(id)
     }
 
     async fn opendir(&mut self, id: u32, path: String) -> Result<Handle, Self::Error> {
-        let entries = self.fs.read_dir(&path).await.map_err(|e| status_code(&e))?;
+        self.check(&SftpOp::ReadDir(&path))?;
+        let entries = self.fs.read_dir(&path).await.map_err(|e| status(&e))?;
         let handle = self.next_handle();
 
         self.handles
@@ -158,11 +305,11 @@ impl<F: Filesystem> russh_sftp::server::Handler for SftpHandler<F> {
 
     async fn readdir(&mut self, id: u32, handle: String) -> Result<Name, Self::Error> {
         let Some(HandleType::Dir { entries, offset }) = self.handles.get_mut(&handle) else {
-            return Err(StatusCode::Failure);
+            return Err(StatusCode::Failure.into());
         };
 
         if *offset >= entries.len() {
-            return Err(StatusCode::Eof);
+            return Err(StatusCode::Eof.into());
         }
 
         let now = unix_now();
@@ -182,7 +329,7 @@ impl<F: Filesystem> russh_sftp::server::Handler for SftpHandler<F> {
     }
 
     async fn realpath(&mut self, id: u32, path: String) -> Result<Name, Self::Error> {
-        let real = self.fs.realpath(&path).await.map_err(|e| status_code(&e))?;
+        let real = self.fs.realpath(&path).await.map_err(|e| status(&e))?;
 
         Ok(Name {
             id,
@@ -195,7 +342,7 @@ impl<F: Filesystem> russh_sftp::server::Handler for SftpHandler<F> {
     }
 
     async fn stat(&mut self, id: u32, path: String) -> Result<Attrs, Self::Error> {
-        let attrs = self.fs.stat(&path).await.map_err(|e| status_code(&e))?;
+        let attrs = self.fs.stat(&path).await.map_err(|e| status(&e))?;
 
         Ok(Attrs {
             id,
@@ -204,7 +351,7 @@ impl<F: Filesystem> russh_sftp::server::Handler for SftpHandler<F> {
     }
 
     async fn lstat(&mut self, id: u32, path: String) -> Result<Attrs, Self::Error> {
-        let attrs = self.fs.lstat(&path).await.map_err(|e| status_code(&e))?;
+        let attrs = self.fs.lstat(&path).await.map_err(|e| status(&e))?;
 
         Ok(Attrs {
             id,
@@ -213,11 +360,11 @@ impl<F: Filesystem> russh_sftp::server::Handler for SftpHandler<F> {
     }
 
     async fn fstat(&mut self, id: u32, handle: String) -> Result<Attrs, Self::Error> {
-        let Some(HandleType::File(f)) = self.handles.get_mut(&handle) else {
-            return Err(StatusCode::Failure);
+        let Some(HandleType::File { file: f, .. }) = self.handles.get_mut(&handle) else {
+            return Err(StatusCode::Failure.into());
         };
 
-        let attrs = f.stat().await.map_err(|e| status_code(&e))?;
+        let attrs = f.stat().await.map_err(|e| status(&e))?;
 
         Ok(Attrs {
             id,
@@ -226,10 +373,9 @@ impl<F: Filesystem> russh_sftp::server::Handler for SftpHandler<F> {
     }
 
     async fn remove(&mut self, id: u32, filename: String) -> Result<Status, Self::Error> {
-        self.fs
-            .remove(&filename)
-            .await
-            .map_err(|e| status_code(&e))?;
+        self.check(&SftpOp::Remove(&filename))?;
+        self.fs.remove(&filename).await.map_err(|e| status(&e))?;
+        self.emit(&SftpEvent::Removed { path: filename });
 
         status_ok(id)
     }
@@ -240,16 +386,18 @@ impl<F: Filesystem> russh_sftp::server::Handler for SftpHandler<F> {
         path: String,
         attrs: FileAttributes,
     ) -> Result<Status, Self::Error> {
+        self.check(&SftpOp::Mkdir(&path))?;
         self.fs
             .mkdir(&path, attrs.into())
             .await
-            .map_err(|e| status_code(&e))?;
+            .map_err(|e| status(&e))?;
 
         status_ok(id)
     }
 
     async fn rmdir(&mut self, id: u32, path: String) -> Result<Status, Self::Error> {
-        self.fs.rmdir(&path).await.map_err(|e| status_code(&e))?;
+        self.check(&SftpOp::Rmdir(&path))?;
+        self.fs.rmdir(&path).await.map_err(|e| status(&e))?;
 
         status_ok(id)
     }
@@ -260,10 +408,18 @@ impl<F: Filesystem> russh_sftp::server::Handler for SftpHandler<F> {
         oldpath: String,
         newpath: String,
     ) -> Result<Status, Self::Error> {
+        self.check(&SftpOp::Rename {
+            from: &oldpath,
+            to: &newpath,
+        })?;
         self.fs
             .rename(&oldpath, &newpath)
             .await
-            .map_err(|e| status_code(&e))?;
+            .map_err(|e| status(&e))?;
+        self.emit(&SftpEvent::Renamed {
+            from: oldpath,
+            to: newpath,
+        });
 
         status_ok(id)
     }
@@ -274,10 +430,11 @@ impl<F: Filesystem> russh_sftp::server::Handler for SftpHandler<F> {
         path: String,
         attrs: FileAttributes,
     ) -> Result<Status, Self::Error> {
+        self.check(&SftpOp::SetStat(&path))?;
         self.fs
             .set_stat(&path, attrs.into())
             .await
-            .map_err(|e| status_code(&e))?;
+            .map_err(|e| status(&e))?;
 
         status_ok(id)
     }
@@ -288,20 +445,24 @@ impl<F: Filesystem> russh_sftp::server::Handler for SftpHandler<F> {
         handle: String,
         attrs: FileAttributes,
     ) -> Result<Status, Self::Error> {
-        let Some(HandleType::File(f)) = self.handles.get_mut(&handle) else {
-            return Err(StatusCode::Failure);
+        let Some(HandleType::File { path, .. }) = self.handles.get(&handle) else {
+            return Err(StatusCode::Failure.into());
         };
 
-        f.set_stat(attrs.into())
-            .await
-            .map_err(|e| status_code(&e))?;
+        self.check(&SftpOp::SetStat(path))?;
+
+        let Some(HandleType::File { file: f, .. }) = self.handles.get_mut(&handle) else {
+            return Err(StatusCode::Failure.into());
+        };
+
+        f.set_stat(attrs.into()).await.map_err(|e| status(&e))?;
 
         status_ok(id)
     }
 }
 
 #[allow(clippy::unnecessary_wraps)]
-fn status_ok(id: u32) -> Result<Status, StatusCode> {
+fn status_ok(id: u32) -> Result<Status, StatusReply> {
     Ok(Status {
         id,
         status_code: StatusCode::Ok,
@@ -311,13 +472,17 @@ fn status_ok(id: u32) -> Result<Status, StatusCode> {
 }
 
 /// SFTP v3 has no "already exists" code, so `AlreadyExists` (e.g. EEXIST
-/// under `CREATE|EXCLUDE`) falls through to the generic `Failure`.
-fn status_code(err: &io::Error) -> StatusCode {
+/// under `CREATE|EXCLUDE`) falls through to the generic `Failure`. Hitting a
+/// [quota](super::Quota) is a `Failure` too, with the limit as the message.
+fn status(err: &io::Error) -> StatusReply {
     match err.kind() {
-        io::ErrorKind::NotFound => StatusCode::NoSuchFile,
-        io::ErrorKind::PermissionDenied => StatusCode::PermissionDenied,
-        io::ErrorKind::Unsupported => StatusCode::OpUnsupported,
-        _ => StatusCode::Failure,
+        io::ErrorKind::NotFound => StatusCode::NoSuchFile.into(),
+        io::ErrorKind::PermissionDenied => StatusCode::PermissionDenied.into(),
+        io::ErrorKind::Unsupported => StatusCode::OpUnsupported.into(),
+        io::ErrorKind::QuotaExceeded | io::ErrorKind::FileTooLarge => {
+            StatusReply::new(StatusCode::Failure).with_message(err.to_string())
+        }
+        _ => StatusCode::Failure.into(),
     }
 }
 
@@ -410,7 +575,10 @@ mod tests {
         loop {
             match h.readdir(1, dir.clone()).await {
                 Ok(name) => pages.push(name.files.len()),
-                Err(StatusCode::Eof) => break,
+                Err(StatusReply {
+                    status_code: StatusCode::Eof,
+                    ..
+                }) => break,
                 Err(other) => panic!("unexpected status: {other:?}"),
             }
         }
@@ -425,7 +593,13 @@ mod tests {
 
         let dir = h.opendir(0, "/".into()).await.expect("opendir").handle;
 
-        assert!(matches!(h.readdir(1, dir).await, Err(StatusCode::Eof)));
+        assert!(matches!(
+            h.readdir(1, dir).await,
+            Err(StatusReply {
+                status_code: StatusCode::Eof,
+                ..
+            })
+        ));
     }
 
     #[tokio::test]
@@ -465,7 +639,13 @@ mod tests {
             )
             .await;
 
-        assert!(matches!(result, Err(StatusCode::Failure)));
+        assert!(matches!(
+            result,
+            Err(StatusReply {
+                status_code: StatusCode::Failure,
+                ..
+            })
+        ));
     }
 
     #[tokio::test]
@@ -482,7 +662,13 @@ mod tests {
             )
             .await;
 
-        assert!(matches!(result, Err(StatusCode::NoSuchFile)));
+        assert!(matches!(
+            result,
+            Err(StatusReply {
+                status_code: StatusCode::NoSuchFile,
+                ..
+            })
+        ));
     }
 
     #[tokio::test]
@@ -503,7 +689,13 @@ mod tests {
             .handle;
 
         assert!(h.close(1, file.clone()).await.is_ok());
-        assert!(matches!(h.close(2, file).await, Err(StatusCode::Failure)));
+        assert!(matches!(
+            h.close(2, file).await,
+            Err(StatusReply {
+                status_code: StatusCode::Failure,
+                ..
+            })
+        ));
     }
 
     #[tokio::test]
@@ -515,22 +707,52 @@ mod tests {
 
         assert!(matches!(
             h.read(0, bogus(), 0, 16).await,
-            Err(StatusCode::Failure)
+            Err(StatusReply {
+                status_code: StatusCode::Failure,
+                ..
+            })
         ));
         assert!(matches!(
             h.write(1, bogus(), 0, b"x".to_vec()).await,
-            Err(StatusCode::Failure)
+            Err(StatusReply {
+                status_code: StatusCode::Failure,
+                ..
+            })
         ));
         assert!(matches!(
             h.fstat(2, bogus()).await,
-            Err(StatusCode::Failure)
+            Err(StatusReply {
+                status_code: StatusCode::Failure,
+                ..
+            })
         ));
         assert!(matches!(
             h.readdir(3, bogus()).await,
-            Err(StatusCode::Failure)
+            Err(StatusReply {
+                status_code: StatusCode::Failure,
+                ..
+            })
         ));
     }
 
+    #[test]
+    fn quota_errors_tell_the_client_the_limit() {
+        let reply = status(&io::Error::new(
+            io::ErrorKind::QuotaExceeded,
+            "quota exceeded: 5 bytes allowed",
+        ));
+
+        assert_eq!(reply.status_code, StatusCode::Failure);
+        assert_eq!(
+            reply.error_message.as_deref(),
+            Some("quota exceeded: 5 bytes allowed")
+        );
+        assert_eq!(
+            status(&io::Error::other("disk on fire")).error_message,
+            None
+        );
+    }
+
     #[test]
     fn longname_formats_recent_file() {
         // 2023-11-14 22:13:20 UTC
@@ -1,8 +1,16 @@
-use std::{io, path::Path, sync::Arc};
+use std::{
+    fs::FileTimes,
+    io,
+    path::Path,
+    sync::Arc,
+    time::{Duration, UNIX_EPOCH},
+};
 
+#[cfg(unix)]
+use cap_std::fs::{MetadataExt, PermissionsExt};
 use cap_std::{
     ambient_authority,
-    fs::{Dir, File, FileExt, Metadata, MetadataExt, OpenOptions, Permissions, PermissionsExt},
+    fs::{Dir, File, FileExt, Metadata, OpenOptions, Permissions},
 };
 use russh_sftp::protocol::OpenFlags;
 
@@ -67,6 +75,7 @@ fn rel(path: &str) -> String {
     if trimmed.is_empty() { "." } else { trimmed }.to_string()
 }
 
+#[cfg(unix)]
 fn meta_to_attr(meta: &Metadata) -> FileAttr {
     FileAttr {
         size: Some(meta.len()),
@@ -78,6 +87,54 @@ fn meta_to_attr(meta: &Metadata) -> FileAttr {
     }
 }
 
+/// Windows has no owners or mode bits, so clients are sent no uid/gid and a
+/// mode made up from the file type and the read-only flag: `rwx` for
+/// everyone on a writable directory, `r--` on a read-only file, and so on.
+#[cfg(not(unix))]
+fn meta_to_attr(meta: &Metadata) -> FileAttr {
+    let secs = |time: io::Result<cap_std::time::SystemTime>| {
+        let since = time.ok()?.into_std().duration_since(UNIX_EPOCH).ok()?;
+
+        u32::try_from(since.as_secs()).ok()
+    };
+
+    let file_type = meta.file_type();
+    let (kind, exec) = if file_type.is_dir() {
+        (0o040_000, 0o111)
+    } else if file_type.is_symlink() {
+        (0o120_000, 0)
+    } else {
+        (0o100_000, 0)
+    };
+    let access = if meta.permissions().readonly() {
+        0o444
+    } else {
+        0o666
+    };
+
+    FileAttr {
+        size: Some(meta.len()),
+        uid: None,
+        gid: None,
+        permissions: Some(kind | access | exec),
+        atime: secs(meta.accessed()),
+        mtime: secs(meta.modified()),
+    }
+}
+
+/// Set `permissions` to the `mode` a client asked for.
+#[cfg(unix)]
+fn set_mode(permissions: &mut Permissions, mode: u32) {
+    permissions.set_mode(mode & 0o7777);
+}
+
+/// Set `permissions` to the `mode` a client asked for. All Windows keeps is
+/// whether the file is read-only: it is if the owner can't write.
+#[cfg(not(unix))]
+fn set_mode(permissions: &mut Permissions, mode: u32) {
+    permissions.set_readonly(mode & 0o200 == 0);
+}
+
 impl Filesystem for LocalFilesystem {
     type Handle = LocalFile;
 
@@ -155,6 +212,8 @@ impl Filesystem for LocalFilesystem {
             if let Some(mode) = attrs.permissions {
                 cap_std::fs::OpenOptionsExt::mode(&mut opts, mode & 0o7777);
             }
+            #[cfg(not(unix))]
+            let _ = attrs;
 
             Ok(LocalFile::new(root.open_with(path, &opts)?))
         })
@@ -173,6 +232,8 @@ impl Filesystem for LocalFilesystem {
 
                 return root.create_dir_with(path, &builder);
             }
+            #[cfg(not(unix))]
+            let _ = attrs;
 
             root.create_dir(path)
         })
@@ -207,7 +268,10 @@ impl Filesystem for LocalFilesystem {
 
         blocking(move || {
             if let Some(mode) = attrs.permissions {
-                root.set_permissions(&path, Permissions::from_mode(mode))?;
+                let mut permissions = root.metadata(&path)?.permissions();
+                set_mode(&mut permissions, mode);
+
+                root.set_permissions(&path, permissions)?;
             }
 
             if let Some(size) = attrs.size {
@@ -215,6 +279,10 @@ impl Filesystem for LocalFilesystem {
                     .set_len(size)?;
             }
 
+            if let Some(times) = file_times(&attrs) {
+                set_times(&root.open(&path)?, times)?;
+            }
+
             Ok(())
         })
         .await
@@ -226,7 +294,10 @@ impl Filesystem for LocalFilesystem {
 
         blocking(move || {
             let canonical = root.canonicalize(path)?;
-            let virtual_path = canonical.to_string_lossy();
+            // Clients always see `/`, whatever the host separates paths with.
+            let virtual_path = canonical
+                .to_string_lossy()
+                .replace(std::path::MAIN_SEPARATOR, "/");
 
             if virtual_path.is_empty() {
                 Ok("/".to_string())
@@ -238,6 +309,68 @@ impl Filesystem for LocalFilesystem {
     }
 }
 
+/// The access and modification times `attrs` sets, if it sets either;
+/// the other is left alone.
+fn file_times(attrs: &FileAttr) -> Option<FileTimes> {
+    let at = |secs: u32| UNIX_EPOCH + Duration::from_secs(secs.into());
+
+    if attrs.atime.is_none() && attrs.mtime.is_none() {
+        return None;
+    }
+
+    let mut times = FileTimes::new();
+
+    if let Some(atime) = attrs.atime {
+        times = times.set_accessed(at(atime));
+    }
+
+    if let Some(mtime) = attrs.mtime {
+        times = times.set_modified(at(mtime));
+    }
+
+    Some(times)
+}
+
+/// cap-std's `File` has no `set_times`; a duplicate of its descriptor as a
+/// std `File` does, and the times land on the same inode.
+fn set_times(file: &File, times: FileTimes) -> io::Result<()> {
+    file.try_clone()?.into_std().set_times(times)
+}
+
+#[cfg(unix)]
+fn read_at(file: &File, buffer: &mut [u8], offset: u64) -> io::Result<usize> {
+    file.read_at(buffer, offset)
+}
+
+/// Windows reads at an offset with `seek_read`, which also moves the file's
+/// cursor; nothing here uses the cursor.
+#[cfg(windows)]
+fn read_at(file: &File, buffer: &mut [u8], offset: u64) -> io::Result<usize> {
+    file.seek_read(buffer, offset)
+}
+
+#[cfg(unix)]
+fn write_all_at(file: &File, data: &[u8], offset: u64) -> io::Result<()> {
+    file.write_all_at(data, offset)
+}
+
+#[cfg(windows)]
+fn write_all_at(file: &File, mut data: &[u8], mut offset: u64) -> io::Result<()> {
+    while !data.is_empty() {
+        match file.seek_write(data, offset) {
+            Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+            Ok(written) => {
+                data = &data[written..];
+                offset += written as u64;
+            }
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+
+    Ok(())
+}
+
 /// Positional I/O (`read_at`/`write_at`) needs only `&File`, so the handle is
 /// shared with the blocking pool via `Arc` instead of moved back and forth.
 pub struct LocalFile {
@@ -258,7 +391,7 @@ impl FileHandle for LocalFile {
 
         blocking(move || {
             let mut buffer = vec![0u8; len as usize];
-            let len = file.read_at(&mut buffer, offset)?;
+            let len = read_at(&file, &mut buffer, offset)?;
 
             buffer.truncate(len);
 
@@ -271,7 +404,7 @@ impl FileHandle for LocalFile {
         let file = Arc::clone(&self.file);
 
         blocking(move || {
-            file.write_all_at(&data, offset)?;
+            write_all_at(&file, &data, offset)?;
 
             u32::try_from(data.len()).map_err(io::Error::other)
         })
@@ -289,13 +422,20 @@ impl FileHandle for LocalFile {
 
         blocking(move || {
             if let Some(mode) = attrs.permissions {
-                file.set_permissions(Permissions::from_mode(mode))?;
+                let mut permissions = file.metadata()?.permissions();
+                set_mode(&mut permissions, mode);
+
+                file.set_permissions(permissions)?;
             }
 
             if let Some(size) = attrs.size {
                 file.set_len(size)?;
             }
 
+            if let Some(times) = file_times(&attrs) {
+                set_times(&file, times)?;
+            }
+
             Ok(())
         })
         .await
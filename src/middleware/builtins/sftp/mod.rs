@@ -1,8 +1,21 @@
+mod context;
 pub mod core;
+mod event;
 mod filesystem;
 mod handler;
 mod local;
+mod mount;
+mod pace;
+mod policy;
+mod quota;
+mod read_only;
 
+pub use context::SftpContext;
 pub use core::Sftp;
+pub use event::SftpEvent;
 pub use filesystem::{DirEntry, FileAttr, FileHandle, Filesystem};
 pub use local::{LocalFile, LocalFilesystem};
+pub use mount::{MountFile, MountFs};
+pub use policy::{SftpOp, SftpPolicy};
+pub use quota::{MemoryUsage, Quota, QuotaFile, Usage, UsageStore};
+pub use read_only::{ReadOnly, ReadOnlyFile};
@@ -0,0 +1,488 @@
+use std::{cmp::Reverse, io, pin::Pin, sync::Arc};
+
+use russh_sftp::protocol::OpenFlags;
+
+use crate::middleware::builtins::sftp::{
+    context::SftpContext,
+    filesystem::{DirEntry, FileAttr, FileHandle, Filesystem},
+};
+
+type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// A [`Filesystem`] made of others mounted at paths, for virtual layouts
+/// spread over several backends:
+///
+/// ```no_run
+/// use shenron::sftp::{LocalFilesystem, MountFs, ReadOnly, Sftp};
+///
+/// let fs = MountFs::new()
+///     .mount("/public", ReadOnly::new(LocalFilesystem::new("/srv/public")))
+///     .mount("/uploads", LocalFilesystem::new("/srv/uploads"));
+///
+/// let sftp = Sftp::new(fs);
+/// ```
+///
+/// A path goes to the filesystem mounted at its longest matching prefix,
+/// which sees it relative to its own root. Mount points appear as
+/// directories in listings of the directory above them, whether or not
+/// anything is mounted there, and can't be removed or renamed. Paths under
+/// no mount don't exist, and renaming from one mount to another fails.
+#[derive(Clone, Default)]
+pub struct MountFs {
+    /// Longest prefix first, so the first match is the most specific.
+    mounts: Vec<Mount>,
+}
+
+#[derive(Clone)]
+struct Mount {
+    /// Normalized, like `/` or `/a/b`.
+    prefix: String,
+    fs: Arc<dyn DynFilesystem>,
+}
+
+impl MountFs {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Serve `fs` at `prefix`, replacing anything already mounted there.
+    #[must_use]
+    pub fn mount<F>(mut self, prefix: &str, fs: F) -> Self
+    where
+        F: Filesystem,
+        F::Handle: Send,
+    {
+        let prefix = normalize(prefix);
+
+        self.mounts.retain(|mount| mount.prefix != prefix);
+        self.mounts.push(Mount {
+            prefix,
+            fs: Arc::new(fs),
+        });
+        self.mounts.sort_by_key(|mount| Reverse(mount.prefix.len()));
+
+        self
+    }
+
+    /// The filesystem serving `path`, and the path within it.
+    fn resolve(&self, path: &str) -> Option<(&Mount, String)> {
+        self.mounts
+            .iter()
+            .find_map(|mount| Some((mount, within(&mount.prefix, path)?)))
+    }
+
+    /// [`resolve`](Self::resolve), failing for paths under no mount.
+    fn route(&self, path: &str) -> io::Result<(&Mount, String)> {
+        self.resolve(&normalize(path)).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("nothing mounted at {path}"),
+            )
+        })
+    }
+
+    /// Names of the mount points directly in `dir`.
+    fn mounted_in(&self, dir: &str) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .mounts
+            .iter()
+            .filter_map(|mount| {
+                let rest = within(dir, &mount.prefix)?;
+                let name = rest.trim_start_matches('/').split('/').next()?;
+
+                (!name.is_empty()).then(|| name.to_owned())
+            })
+            .collect();
+
+        names.sort();
+        names.dedup();
+
+        names
+    }
+
+    /// Whether removing or renaming `path` would take a mount point with it.
+    fn holds_mount(&self, path: &str) -> bool {
+        let path = normalize(path);
+
+        self.mounts
+            .iter()
+            .any(|mount| within(&path, &mount.prefix).is_some())
+    }
+
+    fn check_not_mount(&self, path: &str) -> io::Result<()> {
+        if self.holds_mount(path) {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!("{path} is a mount point"),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// `path`'s attributes as a directory mount points are in, if it is one.
+    fn mount_dir_attrs(&self, path: &str) -> Option<FileAttr> {
+        (!self.mounted_in(path).is_empty()).then(dir_attrs)
+    }
+}
+
+impl Filesystem for MountFs {
+    type Handle = MountFile;
+
+    fn for_session(&self, context: &SftpContext) -> Self {
+        let mounts = self
+            .mounts
+            .iter()
+            .map(|mount| Mount {
+                prefix: mount.prefix.clone(),
+                fs: mount.fs.for_session(context),
+            })
+            .collect();
+
+        Self { mounts }
+    }
+
+    async fn read_dir(&self, path: &str) -> io::Result<Vec<DirEntry>> {
+        let path = normalize(path);
+        let mounted = self.mounted_in(&path);
+
+        let mut entries = match self.resolve(&path) {
+            Some((mount, rest)) => match mount.fs.read_dir(&rest).await {
+                Ok(entries) => entries,
+                Err(_) if !mounted.is_empty() => vec![],
+                Err(e) => return Err(e),
+            },
+            None if !mounted.is_empty() => vec![],
+            None => return Err(io::ErrorKind::NotFound.into()),
+        };
+
+        entries.retain(|entry| !mounted.contains(&entry.name));
+        entries.extend(mounted.into_iter().map(|name| DirEntry {
+            name,
+            attrs: dir_attrs(),
+        }));
+
+        Ok(entries)
+    }
+
+    async fn stat(&self, path: &str) -> io::Result<FileAttr> {
+        let path = normalize(path);
+
+        match self.resolve(&path) {
+            Some((mount, rest)) => match mount.fs.stat(&rest).await {
+                Err(e) => self.mount_dir_attrs(&path).ok_or(e),
+                attrs => attrs,
+            },
+            None => self
+                .mount_dir_attrs(&path)
+                .ok_or_else(|| io::ErrorKind::NotFound.into()),
+        }
+    }
+
+    async fn lstat(&self, path: &str) -> io::Result<FileAttr> {
+        let path = normalize(path);
+
+        match self.resolve(&path) {
+            Some((mount, rest)) => match mount.fs.lstat(&rest).await {
+                Err(e) => self.mount_dir_attrs(&path).ok_or(e),
+                attrs => attrs,
+            },
+            None => self
+                .mount_dir_attrs(&path)
+                .ok_or_else(|| io::ErrorKind::NotFound.into()),
+        }
+    }
+
+    async fn open_read(&self, path: &str) -> io::Result<MountFile> {
+        let (mount, rest) = self.route(path)?;
+
+        mount.fs.open_read(&rest).await
+    }
+
+    async fn open_write(
+        &self,
+        path: &str,
+        flags: OpenFlags,
+        attrs: FileAttr,
+    ) -> io::Result<MountFile> {
+        let (mount, rest) = self.route(path)?;
+
+        mount.fs.open_write(&rest, flags, attrs).await
+    }
+
+    async fn mkdir(&self, path: &str, attrs: FileAttr) -> io::Result<()> {
+        let (mount, rest) = self.route(path)?;
+
+        mount.fs.mkdir(&rest, attrs).await
+    }
+
+    async fn rmdir(&self, path: &str) -> io::Result<()> {
+        self.check_not_mount(path)?;
+        let (mount, rest) = self.route(path)?;
+
+        mount.fs.rmdir(&rest).await
+    }
+
+    async fn remove(&self, path: &str) -> io::Result<()> {
+        self.check_not_mount(path)?;
+        let (mount, rest) = self.route(path)?;
+
+        mount.fs.remove(&rest).await
+    }
+
+    async fn rename(&self, from: &str, to: &str) -> io::Result<()> {
+        self.check_not_mount(from)?;
+        self.check_not_mount(to)?;
+
+        let (mount, from) = self.route(from)?;
+        let (to_mount, to) = self.route(to)?;
+
+        if mount.prefix != to_mount.prefix {
+            return Err(io::Error::new(
+                io::ErrorKind::CrossesDevices,
+                "can't rename across mounts",
+            ));
+        }
+
+        mount.fs.rename(&from, &to).await
+    }
+
+    async fn set_stat(&self, path: &str, attrs: FileAttr) -> io::Result<()> {
+        let (mount, rest) = self.route(path)?;
+
+        mount.fs.set_stat(&rest, attrs).await
+    }
+
+    async fn realpath(&self, path: &str) -> io::Result<String> {
+        let path = normalize(path);
+
+        match self.resolve(&path) {
+            Some((mount, rest)) => match mount.fs.realpath(&rest).await {
+                Ok(real) => Ok(join(&mount.prefix, &normalize(&real))),
+                Err(_) if self.mount_dir_attrs(&path).is_some() => Ok(path),
+                Err(e) => Err(e),
+            },
+            None => Ok(path),
+        }
+    }
+}
+
+/// A file opened through a [`MountFs`], from whichever filesystem it's on.
+pub struct MountFile(Box<dyn DynFile>);
+
+impl FileHandle for MountFile {
+    fn read(&mut self, offset: u64, len: u32) -> impl Future<Output = io::Result<Vec<u8>>> + Send {
+        self.0.read(offset, len)
+    }
+
+    fn write(
+        &mut self,
+        offset: u64,
+        data: Vec<u8>,
+    ) -> impl Future<Output = io::Result<u32>> + Send {
+        self.0.write(offset, data)
+    }
+
+    fn stat(&self) -> impl Future<Output = io::Result<FileAttr>> + Send {
+        self.0.stat()
+    }
+
+    fn set_stat(&mut self, attrs: FileAttr) -> impl Future<Output = io::Result<()>> + Send {
+        self.0.set_stat(attrs)
+    }
+
+    fn close(self) -> impl Future<Output = io::Result<()>> + Send {
+        self.0.close()
+    }
+}
+
+/// [`Filesystem`] as a trait object, so mounts can be different types.
+trait DynFilesystem: Send + Sync {
+    fn for_session(&self, context: &SftpContext) -> Arc<dyn DynFilesystem>;
+    fn read_dir<'a>(&'a self, path: &'a str) -> BoxFuture<'a, io::Result<Vec<DirEntry>>>;
+    fn stat<'a>(&'a self, path: &'a str) -> BoxFuture<'a, io::Result<FileAttr>>;
+    fn lstat<'a>(&'a self, path: &'a str) -> BoxFuture<'a, io::Result<FileAttr>>;
+    fn open_read<'a>(&'a self, path: &'a str) -> BoxFuture<'a, io::Result<MountFile>>;
+    fn open_write<'a>(
+        &'a self,
+        path: &'a str,
+        flags: OpenFlags,
+        attrs: FileAttr,
+    ) -> BoxFuture<'a, io::Result<MountFile>>;
+    fn mkdir<'a>(&'a self, path: &'a str, attrs: FileAttr) -> BoxFuture<'a, io::Result<()>>;
+    fn rmdir<'a>(&'a self, path: &'a str) -> BoxFuture<'a, io::Result<()>>;
+    fn remove<'a>(&'a self, path: &'a str) -> BoxFuture<'a, io::Result<()>>;
+    fn rename<'a>(&'a self, from: &'a str, to: &'a str) -> BoxFuture<'a, io::Result<()>>;
+    fn set_stat<'a>(&'a self, path: &'a str, attrs: FileAttr) -> BoxFuture<'a, io::Result<()>>;
+    fn realpath<'a>(&'a self, path: &'a str) -> BoxFuture<'a, io::Result<String>>;
+}
+
+impl<F> DynFilesystem for F
+where
+    F: Filesystem,
+    F::Handle: Send,
+{
+    fn for_session(&self, context: &SftpContext) -> Arc<dyn DynFilesystem> {
+        Arc::new(Filesystem::for_session(self, context))
+    }
+
+    fn read_dir<'a>(&'a self, path: &'a str) -> BoxFuture<'a, io::Result<Vec<DirEntry>>> {
+        Box::pin(Filesystem::read_dir(self, path))
+    }
+
+    fn stat<'a>(&'a self, path: &'a str) -> BoxFuture<'a, io::Result<FileAttr>> {
+        Box::pin(Filesystem::stat(self, path))
+    }
+
+    fn lstat<'a>(&'a self, path: &'a str) -> BoxFuture<'a, io::Result<FileAttr>> {
+        Box::pin(Filesystem::lstat(self, path))
+    }
+
+    fn open_read<'a>(&'a self, path: &'a str) -> BoxFuture<'a, io::Result<MountFile>> {
+        Box::pin(async move {
+            let file = Filesystem::open_read(self, path).await?;
+
+            Ok(MountFile(Box::new(file)))
+        })
+    }
+
+    fn open_write<'a>(
+        &'a self,
+        path: &'a str,
+        flags: OpenFlags,
+        attrs: FileAttr,
+    ) -> BoxFuture<'a, io::Result<MountFile>> {
+        Box::pin(async move {
+            let file = Filesystem::open_write(self, path, flags, attrs).await?;
+
+            Ok(MountFile(Box::new(file)))
+        })
+    }
+
+    fn mkdir<'a>(&'a self, path: &'a str, attrs: FileAttr) -> BoxFuture<'a, io::Result<()>> {
+        Box::pin(Filesystem::mkdir(self, path, attrs))
+    }
+
+    fn rmdir<'a>(&'a self, path: &'a str) -> BoxFuture<'a, io::Result<()>> {
+        Box::pin(Filesystem::rmdir(self, path))
+    }
+
+    fn remove<'a>(&'a self, path: &'a str) -> BoxFuture<'a, io::Result<()>> {
+        Box::pin(Filesystem::remove(self, path))
+    }
+
+    fn rename<'a>(&'a self, from: &'a str, to: &'a str) -> BoxFuture<'a, io::Result<()>> {
+        Box::pin(Filesystem::rename(self, from, to))
+    }
+
+    fn set_stat<'a>(&'a self, path: &'a str, attrs: FileAttr) -> BoxFuture<'a, io::Result<()>> {
+        Box::pin(Filesystem::set_stat(self, path, attrs))
+    }
+
+    fn realpath<'a>(&'a self, path: &'a str) -> BoxFuture<'a, io::Result<String>> {
+        Box::pin(Filesystem::realpath(self, path))
+    }
+}
+
+/// [`FileHandle`] as a trait object.
+trait DynFile: Send {
+    fn read(&mut self, offset: u64, len: u32) -> BoxFuture<'_, io::Result<Vec<u8>>>;
+    fn write(&mut self, offset: u64, data: Vec<u8>) -> BoxFuture<'_, io::Result<u32>>;
+    fn stat(&self) -> BoxFuture<'_, io::Result<FileAttr>>;
+    fn set_stat(&mut self, attrs: FileAttr) -> BoxFuture<'_, io::Result<()>>;
+    fn close(self: Box<Self>) -> BoxFuture<'static, io::Result<()>>;
+}
+
+impl<H: FileHandle + Send> DynFile for H {
+    fn read(&mut self, offset: u64, len: u32) -> BoxFuture<'_, io::Result<Vec<u8>>> {
+        Box::pin(FileHandle::read(self, offset, len))
+    }
+
+    fn write(&mut self, offset: u64, data: Vec<u8>) -> BoxFuture<'_, io::Result<u32>> {
+        Box::pin(FileHandle::write(self, offset, data))
+    }
+
+    fn stat(&self) -> BoxFuture<'_, io::Result<FileAttr>> {
+        Box::pin(FileHandle::stat(self))
+    }
+
+    fn set_stat(&mut self, attrs: FileAttr) -> BoxFuture<'_, io::Result<()>> {
+        Box::pin(FileHandle::set_stat(self, attrs))
+    }
+
+    fn close(self: Box<Self>) -> BoxFuture<'static, io::Result<()>> {
+        Box::pin(FileHandle::close(*self))
+    }
+}
+
+/// `path` made absolute, with `.`, `..` and repeated slashes resolved
+/// without looking at any filesystem; `..` stops at the root.
+fn normalize(path: &str) -> String {
+    let mut parts = vec![];
+
+    for part in path.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            part => parts.push(part),
+        }
+    }
+
+    format!("/{}", parts.join("/"))
+}
+
+/// `path` as seen from `dir`, if it's `dir` or under it. Both normalized.
+fn within(dir: &str, path: &str) -> Option<String> {
+    if dir == "/" {
+        return Some(path.to_owned());
+    }
+
+    match path.strip_prefix(dir)? {
+        "" => Some("/".to_owned()),
+        rest if rest.starts_with('/') => Some(rest.to_owned()),
+        _ => None,
+    }
+}
+
+/// `path` within the mount at `prefix`, as a path of the whole tree.
+fn join(prefix: &str, path: &str) -> String {
+    match (prefix, path) {
+        ("/", path) => path.to_owned(),
+        (prefix, "/") => prefix.to_owned(),
+        (prefix, path) => format!("{prefix}{path}"),
+    }
+}
+
+fn dir_attrs() -> FileAttr {
+    FileAttr {
+        permissions: Some(0o040_555),
+        ..FileAttr::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_paths() {
+        assert_eq!(normalize(""), "/");
+        assert_eq!(normalize("."), "/");
+        assert_eq!(normalize("a//b/./c/"), "/a/b/c");
+        assert_eq!(normalize("/a/../../b"), "/b");
+    }
+
+    #[test]
+    fn finds_paths_within_a_directory() {
+        assert_eq!(within("/", "/a/b").as_deref(), Some("/a/b"));
+        assert_eq!(within("/a", "/a").as_deref(), Some("/"));
+        assert_eq!(within("/a", "/a/b").as_deref(), Some("/b"));
+        assert_eq!(within("/a", "/ab"), None);
+        assert_eq!(join("/a", "/"), "/a");
+        assert_eq!(join("/a", "/b"), "/a/b");
+        assert_eq!(join("/", "/b"), "/b");
+    }
+}
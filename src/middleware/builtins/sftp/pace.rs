@@ -0,0 +1,28 @@
+use std::time::{Duration, Instant};
+
+/// Holds a session's transfers to a rate, for
+/// [`Sftp::max_rate`](super::Sftp::max_rate). Reads and writes share it.
+pub struct Pace {
+    bytes_per_second: u64,
+    /// When what's gone so far will have been sent at the rate.
+    next: Instant,
+}
+
+impl Pace {
+    pub fn new(bytes_per_second: u64) -> Self {
+        Self {
+            bytes_per_second,
+            next: Instant::now(),
+        }
+    }
+
+    /// Wait until `bytes` more have been sent at the rate. Time spent idle
+    /// isn't saved up for a burst.
+    pub async fn take(&mut self, bytes: u64) {
+        let nanos = u128::from(bytes) * 1_000_000_000 / u128::from(self.bytes_per_second);
+        let time = Duration::from_nanos(u64::try_from(nanos).unwrap_or(u64::MAX));
+
+        self.next = self.next.max(Instant::now()) + time;
+        tokio::time::sleep_until(self.next.into()).await;
+    }
+}
@@ -0,0 +1,45 @@
+use crate::middleware::builtins::sftp::context::SftpContext;
+
+/// An SFTP operation about to run, with the path(s) it touches as the
+/// client sent them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SftpOp<'a> {
+    /// Open a file for reading.
+    Read(&'a str),
+    /// Open a file for writing, creating it if asked to.
+    Write(&'a str),
+    /// List a directory.
+    ReadDir(&'a str),
+    /// Delete a file.
+    Remove(&'a str),
+    Rename {
+        from: &'a str,
+        to: &'a str,
+    },
+    Mkdir(&'a str),
+    Rmdir(&'a str),
+    /// Change a file's attributes: permissions, times, size. Through an
+    /// open handle too, with the path it was opened at.
+    SetStat(&'a str),
+}
+
+/// Decides which SFTP operations a session may run, for permissions finer
+/// than a whole [`Filesystem`](super::Filesystem) — per user, per directory.
+/// Set one with [`Sftp::policy`](super::Sftp::policy).
+///
+/// It's consulted before each [`SftpOp`]; denied operations fail with
+/// permission denied and never reach the filesystem. Closures taking the
+/// context and operation are policies.
+pub trait SftpPolicy: Send + Sync + 'static {
+    fn allow(&self, context: &SftpContext, op: &SftpOp<'_>) -> bool;
+}
+
+impl<F> SftpPolicy for F
+where
+    F: Fn(&SftpContext, &SftpOp<'_>) -> bool + Send + Sync + 'static,
+{
+    fn allow(&self, context: &SftpContext, op: &SftpOp<'_>) -> bool {
+        self(context, op)
+    }
+}
@@ -0,0 +1,417 @@
+use std::{
+    io,
+    sync::{Arc, Mutex},
+};
+
+use russh_sftp::protocol::OpenFlags;
+
+use crate::middleware::builtins::sftp::{
+    context::SftpContext,
+    filesystem::{DirEntry, FileAttr, FileHandle, Filesystem},
+};
+
+/// How much of a filesystem is in use: bytes and number of regular files.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Usage {
+    pub bytes: u64,
+    pub files: u64,
+}
+
+impl Usage {
+    /// Add up the regular files under `fs`'s root, to seed a
+    /// [`UsageStore`] for a tree that already has files in it.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if a directory can't be read.
+    pub async fn measure<F: Filesystem>(fs: &F) -> io::Result<Self> {
+        let mut usage = Self::default();
+        let mut dirs = vec![String::from("/")];
+
+        while let Some(dir) = dirs.pop() {
+            for entry in fs.read_dir(&dir).await? {
+                if entry.name == "." || entry.name == ".." {
+                    continue;
+                }
+
+                if is_dir(&entry.attrs) {
+                    dirs.push(format!("{}/{}", dir.trim_end_matches('/'), entry.name));
+                } else if is_file(&entry.attrs) {
+                    usage.bytes += entry.attrs.size.unwrap_or(0);
+                    usage.files += 1;
+                }
+            }
+        }
+
+        Ok(usage)
+    }
+}
+
+/// Where a [`Quota`] keeps its [`Usage`]. Implement it over a database or
+/// Redis for usage that survives restarts and is shared between servers;
+/// [`MemoryUsage`] keeps it in memory.
+#[trait_variant::make(Send)]
+pub trait UsageStore: Sync + Clone + 'static {
+    /// The usage so far.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the store can't be read; the operation checked
+    /// against it fails.
+    async fn usage(&self) -> io::Result<Usage>;
+
+    /// Record `bytes` and `files` more in use, or fewer if negative.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the store can't be written; the client sees the
+    /// operation fail, though it has already happened.
+    async fn add(&self, bytes: i64, files: i64) -> io::Result<()>;
+}
+
+/// A [`UsageStore`] in memory. Clones share it, so sessions given clones of
+/// one count against the same quota.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryUsage(Arc<Mutex<Usage>>);
+
+impl MemoryUsage {
+    /// Start counting from `usage`, e.g. from [`Usage::measure`].
+    #[must_use]
+    pub fn new(usage: Usage) -> Self {
+        Self(Arc::new(Mutex::new(usage)))
+    }
+}
+
+impl UsageStore for MemoryUsage {
+    async fn usage(&self) -> io::Result<Usage> {
+        Ok(*self.0.lock().expect("usage poisoned"))
+    }
+
+    async fn add(&self, bytes: i64, files: i64) -> io::Result<()> {
+        let mut usage = self.0.lock().expect("usage poisoned");
+        usage.bytes = usage.bytes.saturating_add_signed(bytes);
+        usage.files = usage.files.saturating_add_signed(files);
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Limits {
+    bytes: Option<u64>,
+    file_size: Option<u64>,
+    files: Option<u64>,
+}
+
+impl Limits {
+    /// Fail if `bytes` and `files` more would go over the quota.
+    async fn check<S: UsageStore>(&self, store: &S, bytes: u64, files: u64) -> io::Result<()> {
+        if (self.bytes.is_none() || bytes == 0) && (self.files.is_none() || files == 0) {
+            return Ok(());
+        }
+
+        let usage = store.usage().await?;
+
+        if let Some(max) = self.bytes
+            && usage.bytes.saturating_add(bytes) > max
+        {
+            return Err(exceeded(format!("quota exceeded: {max} bytes allowed")));
+        }
+
+        if let Some(max) = self.files
+            && usage.files.saturating_add(files) > max
+        {
+            return Err(exceeded(format!("quota exceeded: {max} files allowed")));
+        }
+
+        Ok(())
+    }
+
+    fn check_file_size(&self, size: u64) -> io::Result<()> {
+        match self.file_size {
+            Some(max) if size > max => Err(io::Error::new(
+                io::ErrorKind::FileTooLarge,
+                format!("file too large: {max} bytes allowed"),
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// A [`Filesystem`] with limits on how much goes into it: total bytes, the
+/// size of any one file, and the number of files. Writes, truncation,
+/// removal and renames over existing files are counted in a
+/// [`UsageStore`]; an operation that would go over fails, and the client is
+/// told which limit it hit.
+///
+/// Wrap each root in its own, with its own store, for quotas per user:
+///
+/// ```no_run
+/// use std::path::Path;
+///
+/// use shenron::sftp::{LocalFilesystem, MemoryUsage, Quota, Sftp, Usage};
+///
+/// let sftp = Sftp::per_session(|session| {
+///     let root = Path::new("/srv/sftp").join(session.user());
+///
+///     async move {
+///         let fs = LocalFilesystem::try_new(root)?;
+///         let usage = MemoryUsage::new(Usage::measure(&fs).await?);
+///
+///         Ok(Quota::new(fs, usage).max_bytes(1 << 30).max_files(10_000))
+///     }
+/// });
+/// ```
+///
+/// Sessions writing concurrently are each checked against the usage before
+/// the others' writes land, so together they can overshoot by up to a
+/// write each.
+#[derive(Clone)]
+pub struct Quota<F, S> {
+    inner: F,
+    store: S,
+    limits: Limits,
+}
+
+impl<F: Filesystem, S: UsageStore> Quota<F, S> {
+    /// Count what goes into `inner` in `store`. Nothing is limited until
+    /// limits are set.
+    pub const fn new(inner: F, store: S) -> Self {
+        Self {
+            inner,
+            store,
+            limits: Limits {
+                bytes: None,
+                file_size: None,
+                files: None,
+            },
+        }
+    }
+
+    /// Limit the total size of all files.
+    #[must_use]
+    pub const fn max_bytes(mut self, bytes: u64) -> Self {
+        self.limits.bytes = Some(bytes);
+
+        self
+    }
+
+    /// Limit the size of any one file.
+    #[must_use]
+    pub const fn max_file_size(mut self, bytes: u64) -> Self {
+        self.limits.file_size = Some(bytes);
+
+        self
+    }
+
+    /// Limit the number of files. Directories don't count.
+    #[must_use]
+    pub const fn max_files(mut self, files: u64) -> Self {
+        self.limits.files = Some(files);
+
+        self
+    }
+
+    async fn file(&self, file: F::Handle) -> io::Result<QuotaFile<F::Handle, S>> {
+        let size = file.stat().await?.size.unwrap_or(0);
+
+        Ok(QuotaFile {
+            inner: file,
+            store: self.store.clone(),
+            limits: self.limits,
+            size,
+        })
+    }
+}
+
+impl<F: Filesystem, S: UsageStore> Filesystem for Quota<F, S> {
+    type Handle = QuotaFile<F::Handle, S>;
+
+    fn for_session(&self, context: &SftpContext) -> Self {
+        Self {
+            inner: self.inner.for_session(context),
+            store: self.store.clone(),
+            limits: self.limits,
+        }
+    }
+
+    async fn read_dir(&self, path: &str) -> io::Result<Vec<DirEntry>> {
+        self.inner.read_dir(path).await
+    }
+
+    async fn stat(&self, path: &str) -> io::Result<FileAttr> {
+        self.inner.stat(path).await
+    }
+
+    async fn lstat(&self, path: &str) -> io::Result<FileAttr> {
+        self.inner.lstat(path).await
+    }
+
+    async fn open_read(&self, path: &str) -> io::Result<Self::Handle> {
+        self.file(self.inner.open_read(path).await?).await
+    }
+
+    async fn open_write(
+        &self,
+        path: &str,
+        flags: OpenFlags,
+        attrs: FileAttr,
+    ) -> io::Result<Self::Handle> {
+        let before = self.inner.stat(path).await.ok();
+
+        if before.is_none() {
+            self.limits.check(&self.store, 0, 1).await?;
+        }
+
+        let file = self
+            .file(self.inner.open_write(path, flags, attrs).await?)
+            .await?;
+        let old = before.as_ref().and_then(|attrs| attrs.size).unwrap_or(0);
+
+        self.store
+            .add(change(old, file.size), i64::from(before.is_none()))
+            .await?;
+
+        Ok(file)
+    }
+
+    async fn mkdir(&self, path: &str, attrs: FileAttr) -> io::Result<()> {
+        self.inner.mkdir(path, attrs).await
+    }
+
+    async fn rmdir(&self, path: &str) -> io::Result<()> {
+        self.inner.rmdir(path).await
+    }
+
+    async fn remove(&self, path: &str) -> io::Result<()> {
+        let before = self.inner.lstat(path).await?;
+
+        self.inner.remove(path).await?;
+
+        if is_file(&before) {
+            self.store
+                .add(change(before.size.unwrap_or(0), 0), -1)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn rename(&self, from: &str, to: &str) -> io::Result<()> {
+        let replaced = self.inner.lstat(to).await.ok().filter(is_file);
+
+        self.inner.rename(from, to).await?;
+
+        // Renaming over a file removes it.
+        if let Some(replaced) = replaced
+            && from != to
+        {
+            self.store
+                .add(change(replaced.size.unwrap_or(0), 0), -1)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn set_stat(&self, path: &str, attrs: FileAttr) -> io::Result<()> {
+        let Some(size) = attrs.size else {
+            return self.inner.set_stat(path, attrs).await;
+        };
+
+        let old = self.inner.stat(path).await?.size.unwrap_or(0);
+
+        self.limits.check_file_size(size)?;
+        self.limits
+            .check(&self.store, size.saturating_sub(old), 0)
+            .await?;
+
+        self.inner.set_stat(path, attrs).await?;
+        self.store.add(change(old, size), 0).await
+    }
+
+    async fn realpath(&self, path: &str) -> io::Result<String> {
+        self.inner.realpath(path).await
+    }
+}
+
+/// A file opened through [`Quota`], counting what's written to it.
+pub struct QuotaFile<H, S> {
+    inner: H,
+    store: S,
+    limits: Limits,
+    /// Its size as far as the quota knows.
+    size: u64,
+}
+
+impl<H: FileHandle, S: UsageStore> FileHandle for QuotaFile<H, S> {
+    async fn read(&mut self, offset: u64, len: u32) -> io::Result<Vec<u8>> {
+        self.inner.read(offset, len).await
+    }
+
+    async fn write(&mut self, offset: u64, data: Vec<u8>) -> io::Result<u32> {
+        let end = offset.saturating_add(data.len() as u64);
+
+        self.limits.check_file_size(end)?;
+        self.limits
+            .check(&self.store, end.saturating_sub(self.size), 0)
+            .await?;
+
+        let written = self.inner.write(offset, data).await?;
+        let end = offset.saturating_add(u64::from(written));
+
+        if end > self.size {
+            let grown = change(self.size, end);
+            self.size = end;
+            self.store.add(grown, 0).await?;
+        }
+
+        Ok(written)
+    }
+
+    async fn stat(&self) -> io::Result<FileAttr> {
+        self.inner.stat().await
+    }
+
+    async fn set_stat(&mut self, attrs: FileAttr) -> io::Result<()> {
+        let Some(size) = attrs.size else {
+            return self.inner.set_stat(attrs).await;
+        };
+
+        self.limits.check_file_size(size)?;
+        self.limits
+            .check(&self.store, size.saturating_sub(self.size), 0)
+            .await?;
+
+        self.inner.set_stat(attrs).await?;
+
+        let changed = change(self.size, size);
+        self.size = size;
+        self.store.add(changed, 0).await
+    }
+
+    async fn close(self) -> io::Result<()> {
+        self.inner.close().await
+    }
+}
+
+fn exceeded(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::QuotaExceeded, message)
+}
+
+/// The signed difference from `before` to `after`.
+fn change(before: u64, after: u64) -> i64 {
+    if after >= before {
+        i64::try_from(after - before).unwrap_or(i64::MAX)
+    } else {
+        -i64::try_from(before - after).unwrap_or(i64::MAX)
+    }
+}
+
+const fn is_dir(attrs: &FileAttr) -> bool {
+    matches!(attrs.permissions, Some(mode) if mode & 0o170_000 == 0o040_000)
+}
+
+const fn is_file(attrs: &FileAttr) -> bool {
+    matches!(attrs.permissions, Some(mode) if mode & 0o170_000 == 0o100_000)
+}
@@ -0,0 +1,111 @@
+use std::io;
+
+use russh_sftp::protocol::OpenFlags;
+
+use crate::middleware::builtins::sftp::{
+    context::SftpContext,
+    filesystem::{DirEntry, FileAttr, FileHandle, Filesystem},
+};
+
+fn denied() -> io::Error {
+    io::Error::new(io::ErrorKind::PermissionDenied, "read-only filesystem")
+}
+
+/// A [`Filesystem`] serving another one read-only: listing, stat and
+/// reading work, while anything that would change it — opening for
+/// writing, removing, renaming, making or removing directories, setting
+/// attributes — fails with permission denied.
+///
+/// [`Sftp::read_only`](super::Sftp::read_only) wraps a filesystem in one.
+#[derive(Clone)]
+pub struct ReadOnly<F> {
+    inner: F,
+}
+
+impl<F: Filesystem> ReadOnly<F> {
+    pub const fn new(inner: F) -> Self {
+        Self { inner }
+    }
+}
+
+impl<F: Filesystem> Filesystem for ReadOnly<F> {
+    type Handle = ReadOnlyFile<F::Handle>;
+
+    fn for_session(&self, context: &SftpContext) -> Self {
+        Self::new(self.inner.for_session(context))
+    }
+
+    async fn read_dir(&self, path: &str) -> io::Result<Vec<DirEntry>> {
+        self.inner.read_dir(path).await
+    }
+
+    async fn stat(&self, path: &str) -> io::Result<FileAttr> {
+        self.inner.stat(path).await
+    }
+
+    async fn lstat(&self, path: &str) -> io::Result<FileAttr> {
+        self.inner.lstat(path).await
+    }
+
+    async fn open_read(&self, path: &str) -> io::Result<Self::Handle> {
+        Ok(ReadOnlyFile(self.inner.open_read(path).await?))
+    }
+
+    async fn open_write(
+        &self,
+        _path: &str,
+        _flags: OpenFlags,
+        _attrs: FileAttr,
+    ) -> io::Result<Self::Handle> {
+        Err(denied())
+    }
+
+    async fn mkdir(&self, _path: &str, _attrs: FileAttr) -> io::Result<()> {
+        Err(denied())
+    }
+
+    async fn rmdir(&self, _path: &str) -> io::Result<()> {
+        Err(denied())
+    }
+
+    async fn remove(&self, _path: &str) -> io::Result<()> {
+        Err(denied())
+    }
+
+    async fn rename(&self, _from: &str, _to: &str) -> io::Result<()> {
+        Err(denied())
+    }
+
+    async fn set_stat(&self, _path: &str, _attrs: FileAttr) -> io::Result<()> {
+        Err(denied())
+    }
+
+    async fn realpath(&self, path: &str) -> io::Result<String> {
+        self.inner.realpath(path).await
+    }
+}
+
+/// A file opened through [`ReadOnly`]: readable, never changed.
+pub struct ReadOnlyFile<H>(H);
+
+impl<H: FileHandle> FileHandle for ReadOnlyFile<H> {
+    async fn read(&mut self, offset: u64, len: u32) -> io::Result<Vec<u8>> {
+        self.0.read(offset, len).await
+    }
+
+    async fn write(&mut self, _offset: u64, _data: Vec<u8>) -> io::Result<u32> {
+        Err(denied())
+    }
+
+    async fn stat(&self) -> io::Result<FileAttr> {
+        self.0.stat().await
+    }
+
+    async fn set_stat(&mut self, _attrs: FileAttr) -> io::Result<()> {
+        Err(denied())
+    }
+
+    async fn close(self) -> io::Result<()> {
+        self.0.close().await
+    }
+}
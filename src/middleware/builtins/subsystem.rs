@@ -0,0 +1,62 @@
+use crate::{Exit, IntoExit, Middleware, Next, Session, SessionKind};
+
+/// Routes one named subsystem (`ssh -s name`) to its own handler.
+///
+/// Sessions for other subsystems, shells, and exec commands pass through to
+/// the next middleware. Usually registered with
+/// [`Server::subsystem`](crate::Server::subsystem), which wraps a plain
+/// `async fn(&mut Session)`.
+pub struct Subsystem<H> {
+    name: String,
+    handler: H,
+}
+
+impl<H: Middleware> Subsystem<H> {
+    /// Serve the subsystem `name` with `handler`. The handler receives the
+    /// rest of the chain as its `next`, so it can itself be a middleware.
+    pub fn new(name: impl Into<String>, handler: H) -> Self {
+        Self {
+            name: name.into(),
+            handler,
+        }
+    }
+
+    fn matches(&self, kind: &SessionKind) -> bool {
+        matches!(kind, SessionKind::Subsystem { name } if *name == self.name)
+    }
+}
+
+impl<H: Middleware> Middleware for Subsystem<H> {
+    type Output = Exit;
+
+    async fn handle(&self, session: &'_ mut Session, next: Next<'_>) -> Exit {
+        if self.matches(session.kind()) {
+            self.handler.handle(session, next).await.into_exit()
+        } else {
+            next.run(session).await
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn noop(_session: &mut Session) {}
+
+    #[test]
+    fn matches_only_its_own_subsystem() {
+        let route = Subsystem::new("metrics", crate::terminal(noop));
+
+        assert!(route.matches(&SessionKind::Subsystem {
+            name: "metrics".into()
+        }));
+        assert!(!route.matches(&SessionKind::Subsystem {
+            name: "sftp".into()
+        }));
+        assert!(!route.matches(&SessionKind::Exec {
+            command: "metrics".into()
+        }));
+        assert!(!route.matches(&SessionKind::Shell));
+    }
+}
@@ -0,0 +1,88 @@
+use std::time::{Duration, Instant};
+
+use crate::{Exit, Middleware, Next, Session};
+
+type Trap = Box<dyn Fn(&Session) -> bool + Send + Sync>;
+
+/// Shortest [`Tarpit::interval`]; any faster and the tarpit costs the
+/// server more than the bot.
+const MIN_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Middleware that wastes a bot's time instead of turning it away.
+///
+/// Sessions the predicate picks (banned addresses, usernames only scanners
+/// try, a [`client_fingerprint`](Session::client_fingerprint) known to
+/// belong to a bot) never reach the app: they get one random printable byte
+/// every [`interval`](Self::interval) for as long as the client stays
+/// connected, while their input is read and discarded. The session only
+/// ends when the client gives up. Other sessions pass through.
+///
+/// ```no_run
+/// # use shenron::{Server, middleware::Tarpit};
+/// let _server = Server::new().with(Tarpit::new(|session| {
+///     matches!(session.user(), "root" | "admin" | "ubnt")
+/// }));
+/// ```
+pub struct Tarpit {
+    trap: Trap,
+    interval: Duration,
+}
+
+impl Tarpit {
+    /// Trap the sessions `trap` returns `true` for.
+    #[must_use]
+    pub fn new(trap: impl Fn(&Session) -> bool + Send + Sync + 'static) -> Self {
+        Self {
+            trap: Box::new(trap),
+            interval: Duration::from_secs(10),
+        }
+    }
+
+    /// How long to wait between bytes; 10 seconds unless set, and at
+    /// least 10ms.
+    #[must_use]
+    pub const fn interval(mut self, interval: Duration) -> Self {
+        self.interval = if interval.as_nanos() < MIN_INTERVAL.as_nanos() {
+            MIN_INTERVAL
+        } else {
+            interval
+        };
+
+        self
+    }
+}
+
+impl Middleware for Tarpit {
+    type Output = Exit;
+
+    async fn handle(&self, session: &'_ mut Session, next: Next<'_>) -> Exit {
+        if !(self.trap)(session) {
+            return next.run(session).await;
+        }
+
+        tracing::info!(session = %session.id(), user = %session.user(), remote = %session.remote_addr(), "tarpitting session");
+
+        let trapped = Instant::now();
+        let mut drip = tokio::time::interval(self.interval);
+        drip.reset();
+
+        loop {
+            tokio::select! {
+                event = session.next() => if event.is_none() {
+                    break;
+                },
+                _ = drip.tick() => {
+                    let byte = rand::random_range(b' '..=b'~');
+
+                    if session.write(&[byte]).await.is_err() || session.flush().await.is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+
+        tracing::info!(session = %session.id(), wasted = ?trapped.elapsed(), "tarpitted session gave up");
+
+        Exit::Code(0)
+    }
+}
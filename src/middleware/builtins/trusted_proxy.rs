@@ -0,0 +1,154 @@
+use std::net::{IpAddr, SocketAddr};
+
+use crate::{Error, Result, Session};
+
+/// The address of the client behind a front proxy, as the proxy reported it
+/// (the PROXY protocol, or however the proxy passes it on). Whatever
+/// learned it attaches it to the session — auth with
+/// [`Auth::with`](crate::Auth::with), or an outer middleware with
+/// [`Session::insert`] — and [`TrustedProxies`] decides whether to believe
+/// it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClientAddr(pub SocketAddr);
+
+/// The front proxies whose word on a client's address is taken, for
+/// builtins that key on the client's IP: [`RateLimiter`](super::RateLimiter)
+/// and [`MaxSessionsPerIp`](super::MaxSessionsPerIp).
+///
+/// A session connecting from one of these addresses counts as coming from
+/// its [`ClientAddr`], if it has one; any other session counts as coming
+/// from where it connected from, so clients can't claim another address by
+/// attaching one themselves. None are trusted by default.
+///
+/// ```no_run
+/// # use shenron::{Server, middleware::{MaxSessionsPerIp, TrustedProxies}};
+/// # fn main() -> shenron::Result {
+/// let proxies = TrustedProxies::new(["10.0.0.0/8", "192.168.1.5"])?;
+///
+/// let _server = Server::new().with(MaxSessionsPerIp::new(4).trusted_proxies(proxies));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct TrustedProxies {
+    /// Networks as an address and prefix length.
+    networks: Vec<(IpAddr, u8)>,
+}
+
+impl TrustedProxies {
+    /// Trust the proxies at `networks`: addresses (`10.0.0.1`) or CIDR
+    /// ranges (`10.0.0.0/8`, `fd00::/8`).
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if one isn't an address or range
+    pub fn new(networks: impl IntoIterator<Item = impl AsRef<str>>) -> Result<Self> {
+        let networks = networks
+            .into_iter()
+            .map(|network| parse_network(network.as_ref()))
+            .collect::<Result<_>>()?;
+
+        Ok(Self { networks })
+    }
+
+    /// Whether `ip` is one of the trusted proxies.
+    #[must_use]
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        let ip = ip.to_canonical();
+
+        self.networks
+            .iter()
+            .any(|&(network, prefix)| in_network(ip, network, prefix))
+    }
+
+    /// Where `session`'s client really is: its [`ClientAddr`] if it came
+    /// through a trusted proxy, otherwise the address it connected from.
+    #[must_use]
+    pub fn client_addr(&self, session: &Session) -> SocketAddr {
+        let remote = session.remote_addr();
+
+        match session.get::<ClientAddr>() {
+            Some(&ClientAddr(client)) if self.contains(remote.ip()) => client,
+            _ => remote,
+        }
+    }
+}
+
+fn parse_network(network: &str) -> Result<(IpAddr, u8)> {
+    let invalid = || Error::Config(format!("invalid trusted proxy `{network}`"));
+
+    let (ip, prefix) = match network.split_once('/') {
+        Some((ip, prefix)) => (ip, Some(prefix)),
+        None => (network, None),
+    };
+
+    let ip: IpAddr = ip.trim().parse().map_err(|_| invalid())?;
+    let ip = ip.to_canonical();
+    let max = if ip.is_ipv4() { 32 } else { 128 };
+
+    let prefix = match prefix {
+        Some(prefix) => prefix.trim().parse().map_err(|_| invalid())?,
+        None => max,
+    };
+
+    if prefix > max {
+        return Err(invalid());
+    }
+
+    Ok((ip, prefix))
+}
+
+fn in_network(ip: IpAddr, network: IpAddr, prefix: u8) -> bool {
+    match (ip, network) {
+        (IpAddr::V4(ip), IpAddr::V4(network)) => {
+            let mask = u32::MAX.checked_shl(32 - u32::from(prefix)).unwrap_or(0);
+
+            u32::from(ip) & mask == u32::from(network) & mask
+        }
+        (IpAddr::V6(ip), IpAddr::V6(network)) => {
+            let mask = u128::MAX.checked_shl(128 - u32::from(prefix)).unwrap_or(0);
+
+            u128::from(ip) & mask == u128::from(network) & mask
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(ip: &str) -> IpAddr {
+        ip.parse().expect("ip")
+    }
+
+    #[test]
+    fn matches_addresses_and_ranges() {
+        let proxies =
+            TrustedProxies::new(["10.0.0.0/8", "192.168.1.5", "fd00::/8"]).expect("parses");
+
+        assert!(proxies.contains(ip("10.1.2.3")));
+        assert!(!proxies.contains(ip("11.0.0.1")));
+        assert!(proxies.contains(ip("192.168.1.5")));
+        assert!(!proxies.contains(ip("192.168.1.6")));
+        assert!(proxies.contains(ip("fd12::1")));
+        assert!(!proxies.contains(ip("fe80::1")));
+        assert!(proxies.contains(ip("::ffff:10.0.0.1")));
+    }
+
+    #[test]
+    fn everything_matches_a_zero_prefix() {
+        let proxies = TrustedProxies::new(["0.0.0.0/0"]).expect("parses");
+
+        assert!(proxies.contains(ip("203.0.113.9")));
+        assert!(!proxies.contains(ip("2001:db8::1")));
+    }
+
+    #[test]
+    fn rejects_what_isnt_a_network() {
+        assert!(TrustedProxies::new(["10.0.0.0/33"]).is_err());
+        assert!(TrustedProxies::new(["proxy.internal"]).is_err());
+        assert!(TrustedProxies::new(["10.0.0.0/x"]).is_err());
+        assert!(!TrustedProxies::default().contains(ip("10.0.0.1")));
+    }
+}
@@ -0,0 +1,53 @@
+use tokio::{io::AsyncWriteExt, sync::broadcast::error::RecvError};
+
+use crate::{Exit, Next, Session};
+
+/// Middleware that prints [broadcasts](crate::ServerHandle::broadcast) on
+/// PTY sessions' terminals as they arrive, in the style of `wall(1)`.
+///
+/// The banner is written straight over whatever the app is drawing, so a
+/// full-screen app may want [`Session::broadcasts`] instead, to show them
+/// its own way. Sessions without a PTY are left alone.
+pub async fn wall(session: &mut Session, next: Next<'_>) -> Exit {
+    let (Some(_), Ok(mut stdout)) = (session.pty(), session.stdout()) else {
+        return next.run(session).await;
+    };
+
+    let mut messages = session.broadcasts();
+    let closed = session.closed();
+
+    let banners = tokio::spawn(async move {
+        tokio::pin!(closed);
+
+        loop {
+            let message = tokio::select! {
+                () = &mut closed => break,
+                message = messages.recv() => message,
+            };
+
+            let message = match message {
+                Ok(message) => message,
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => break,
+            };
+
+            if stdout.write_all(banner(&message).as_bytes()).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    let exit = next.run(session).await;
+    banners.abort();
+
+    exit
+}
+
+fn banner(message: &str) -> String {
+    let message = message
+        .trim_end()
+        .replace("\r\n", "\n")
+        .replace('\n', "\r\n");
+
+    format!("\r\n\x07Broadcast message from the server:\r\n\r\n{message}\r\n\r\n")
+}
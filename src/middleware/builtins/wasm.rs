@@ -0,0 +1,334 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+};
+
+use wasmtime::{
+    Caller, Config, Engine, Extern, InstancePre, Linker, Module, Store, StoreLimits,
+    StoreLimitsBuilder,
+};
+
+use crate::{Error, Exit, Middleware, Next, Result, Session, SessionKind};
+
+/// Fuel each plugin call gets unless set: plenty for a policy check, not
+/// enough to spin forever.
+const DEFAULT_FUEL: u64 = 10_000_000;
+
+/// Memory each plugin instance may grow to.
+const MAX_MEMORY: usize = 16 << 20;
+
+/// Middleware running a policy plugin compiled to WebAssembly, so operators
+/// can ship and swap policies without rebuilding the server.
+///
+/// A plugin is a module exporting its `memory` and a function
+/// `on_session() -> i32`, called once per session. Returning 0 lets the
+/// session through; anything else turns it away with that exit status. It
+/// may import, from module `shenron`:
+///
+/// - `meta(key_ptr, key_len, buf_ptr, buf_cap) -> i32`: copies the session
+///   metadata named by the UTF-8 key into the buffer, returning its full
+///   length (which may exceed `buf_cap`), or -1 if there's none. Keys are
+///   `user`, `remote_addr`, `session_id`, `kind` (`shell`, `exec` or
+///   `subsystem`), `command`, `subsystem`, `term`, `key_fingerprint`, and
+///   `env.NAME` for the client's environment.
+/// - `write(ptr, len)` and `write_stderr(ptr, len)`: queue a message for the
+///   client, sent once `on_session` returns.
+///
+/// Plugins run sandboxed with a fuel budget ([`fuel`](Self::fuel)) and
+/// 16 MiB of memory. One that traps, runs out of fuel or breaks the ABI
+/// fails closed: the error is logged and the session exits with status 1.
+///
+/// Clones share the plugin, so keep one to [`reload`](Self::reload) it once
+/// the server is running:
+///
+/// ```no_run
+/// # use shenron::{Server, middleware::WasmPlugin};
+/// # fn main() -> shenron::Result {
+/// let policy = WasmPlugin::load("/etc/ssh-policy.wasm")?;
+/// let _server = Server::new().with(policy.clone());
+///
+/// // Later, after deploying a new policy:
+/// policy.reload()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct WasmPlugin {
+    engine: Engine,
+    plugin: Arc<RwLock<InstancePre<Host>>>,
+    path: Option<Arc<Path>>,
+    fuel: u64,
+}
+
+impl WasmPlugin {
+    /// A plugin from a module's bytes, binary or in the text format.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the module doesn't compile or follow the plugin ABI
+    pub fn new(module: impl AsRef<[u8]>) -> Result<Self> {
+        let engine = Engine::new(Config::new().consume_fuel(true))?;
+        let plugin = compile(&engine, module.as_ref())?;
+
+        Ok(Self {
+            engine,
+            plugin: Arc::new(RwLock::new(plugin)),
+            path: None,
+            fuel: DEFAULT_FUEL,
+        })
+    }
+
+    /// A plugin from the module at `path`, which [`reload`](Self::reload)
+    /// reads again.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the file can't be read, or as for [`new`](Self::new)
+    pub fn load(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+
+        Ok(Self {
+            path: Some(path.as_path().into()),
+            ..Self::new(std::fs::read(&path)?)?
+        })
+    }
+
+    /// Swap in the module now at the path the plugin was
+    /// [loaded](Self::load) from. Sessions already past the plugin are
+    /// unaffected; if the new module doesn't compile or follow the ABI, the
+    /// old one stays.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the plugin wasn't loaded from a file, or as for
+    /// [`load`](Self::load)
+    #[expect(
+        clippy::missing_panics_doc,
+        reason = "the lock is only held to swap the module, which can't panic"
+    )]
+    pub fn reload(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Err(Error::Config(
+                "only plugins loaded from a file can be reloaded".into(),
+            ));
+        };
+
+        let plugin = compile(&self.engine, &std::fs::read(path)?)?;
+        *self.plugin.write().expect("wasm plugin poisoned") = plugin;
+
+        tracing::info!(path = %path.display(), "wasm plugin reloaded");
+
+        Ok(())
+    }
+
+    /// How much fuel, roughly one per instruction, each call may burn;
+    /// 10 million unless set.
+    #[must_use]
+    pub const fn fuel(mut self, fuel: u64) -> Self {
+        self.fuel = fuel;
+
+        self
+    }
+
+    /// Run the plugin's `on_session` against `meta`, returning its verdict
+    /// and the messages it queued.
+    fn run(
+        plugin: &InstancePre<Host>,
+        fuel: u64,
+        meta: HashMap<String, String>,
+    ) -> wasmtime::Result<(i32, Vec<Message>)> {
+        let mut store = Store::new(
+            plugin.module().engine(),
+            Host {
+                meta,
+                messages: Vec::new(),
+                limits: StoreLimitsBuilder::new().memory_size(MAX_MEMORY).build(),
+            },
+        );
+        store.limiter(|host| &mut host.limits);
+        store.set_fuel(fuel)?;
+
+        let instance = plugin.instantiate(&mut store)?;
+        let verdict = instance
+            .get_typed_func::<(), i32>(&mut store, "on_session")?
+            .call(&mut store, ())?;
+
+        Ok((verdict, store.into_data().messages))
+    }
+}
+
+impl Middleware for WasmPlugin {
+    type Output = Result<Exit>;
+
+    async fn handle(&self, session: &'_ mut Session, next: Next<'_>) -> Result<Exit> {
+        let plugin = self.plugin.read().expect("wasm plugin poisoned").clone();
+        let fuel = self.fuel;
+        let meta = metadata(session);
+
+        let run = tokio::task::spawn_blocking(move || Self::run(&plugin, fuel, meta)).await;
+
+        let (verdict, messages) = match run {
+            Ok(Ok(run)) => run,
+            Ok(Err(e)) => {
+                tracing::warn!(session = %session.id(), error = %e, "wasm plugin failed, refusing session");
+
+                return Ok(Exit::Code(1));
+            }
+            Err(e) => return Err(Error::Panic(e.to_string())),
+        };
+
+        for message in messages {
+            match message {
+                Message::Stdout(data) => session.write(&data).await?,
+                Message::Stderr(data) => session.write_stderr(&data).await?,
+            }
+        }
+
+        if verdict == 0 {
+            return Ok(next.run(session).await);
+        }
+
+        tracing::info!(session = %session.id(), user = %session.user(), verdict, "wasm plugin refused session");
+
+        Ok(Exit::Code(u32::try_from(verdict).unwrap_or(1)))
+    }
+}
+
+/// What a plugin instance sees of its session.
+struct Host {
+    meta: HashMap<String, String>,
+    messages: Vec<Message>,
+    limits: StoreLimits,
+}
+
+enum Message {
+    Stdout(Vec<u8>),
+    Stderr(Vec<u8>),
+}
+
+/// Compile `module` and link it against the host functions, failing early
+/// if it doesn't follow the plugin ABI.
+fn compile(engine: &Engine, module: &[u8]) -> Result<InstancePre<Host>> {
+    let module = Module::new(engine, module)?;
+
+    if module.get_export("on_session").is_none() || module.get_export("memory").is_none() {
+        return Err(Error::Config(
+            "wasm plugins must export `memory` and `on_session`".into(),
+        ));
+    }
+
+    let mut linker = Linker::new(engine);
+
+    linker.func_wrap(
+        "shenron",
+        "meta",
+        |mut caller: Caller<'_, Host>, key_ptr: u32, key_len: u32, buf_ptr: u32, buf_cap: u32| {
+            let (bytes, host) = memory(&mut caller)?.data_and_store_mut(&mut caller);
+            let key = std::str::from_utf8(slice(bytes, key_ptr, key_len)?)?;
+
+            let Some(value) = host.meta.get(key) else {
+                return Ok(-1);
+            };
+
+            let buf = slice_mut(bytes, buf_ptr, buf_cap)?;
+            let copied = value.len().min(buf.len());
+            buf[..copied].copy_from_slice(&value.as_bytes()[..copied]);
+
+            Ok(i32::try_from(value.len()).unwrap_or(i32::MAX))
+        },
+    )?;
+
+    linker.func_wrap(
+        "shenron",
+        "write",
+        |mut caller: Caller<'_, Host>, ptr: u32, len: u32| {
+            let (bytes, host) = memory(&mut caller)?.data_and_store_mut(&mut caller);
+            host.messages
+                .push(Message::Stdout(slice(bytes, ptr, len)?.to_vec()));
+
+            wasmtime::Result::<()>::Ok(())
+        },
+    )?;
+
+    linker.func_wrap(
+        "shenron",
+        "write_stderr",
+        |mut caller: Caller<'_, Host>, ptr: u32, len: u32| {
+            let (bytes, host) = memory(&mut caller)?.data_and_store_mut(&mut caller);
+            host.messages
+                .push(Message::Stderr(slice(bytes, ptr, len)?.to_vec()));
+
+            wasmtime::Result::<()>::Ok(())
+        },
+    )?;
+
+    Ok(linker.instantiate_pre(&module)?)
+}
+
+fn memory(caller: &mut Caller<'_, Host>) -> wasmtime::Result<wasmtime::Memory> {
+    caller
+        .get_export("memory")
+        .and_then(Extern::into_memory)
+        .ok_or_else(|| wasmtime::Error::msg("plugin exports no memory"))
+}
+
+fn slice(memory: &[u8], ptr: u32, len: u32) -> wasmtime::Result<&[u8]> {
+    let start = usize::try_from(ptr)?;
+
+    memory
+        .get(start..start + usize::try_from(len)?)
+        .ok_or_else(|| wasmtime::Error::msg("pointer out of bounds"))
+}
+
+fn slice_mut(memory: &mut [u8], ptr: u32, len: u32) -> wasmtime::Result<&mut [u8]> {
+    let start = usize::try_from(ptr)?;
+
+    memory
+        .get_mut(start..start + usize::try_from(len)?)
+        .ok_or_else(|| wasmtime::Error::msg("pointer out of bounds"))
+}
+
+/// The session metadata plugins can read with `meta`.
+fn metadata(session: &Session) -> HashMap<String, String> {
+    let mut meta = HashMap::from([
+        ("user".to_owned(), session.user().to_owned()),
+        ("remote_addr".to_owned(), session.remote_addr().to_string()),
+        ("session_id".to_owned(), session.id().to_string()),
+        (
+            "kind".to_owned(),
+            match session.kind() {
+                SessionKind::Shell => "shell",
+                SessionKind::Exec { .. } => "exec",
+                SessionKind::Subsystem { .. } => "subsystem",
+            }
+            .to_owned(),
+        ),
+    ]);
+
+    let optional = [
+        ("command", session.raw_command().map(str::to_owned)),
+        ("subsystem", session.subsystem().map(str::to_owned)),
+        ("term", session.term().map(str::to_owned)),
+        (
+            "key_fingerprint",
+            session
+                .key_fingerprint()
+                .map(|fingerprint| fingerprint.to_string()),
+        ),
+    ];
+
+    meta.extend(
+        optional
+            .into_iter()
+            .filter_map(|(key, value)| Some((key.to_owned(), value?))),
+    );
+    meta.extend(
+        session
+            .env()
+            .iter()
+            .map(|(name, value)| (format!("env.{name}"), value.clone())),
+    );
+
+    meta
+}
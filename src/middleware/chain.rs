@@ -1,19 +1,25 @@
-use std::{pin::Pin, sync::Arc};
+use std::{pin::Pin, sync::Arc, time::Instant};
 
 use crate::{
     Exit, Next, Session,
-    middleware::{ErasedHandler, ErasedMiddleware},
+    middleware::{ErasedHandler, ErasedMiddleware, Marks, TimedNext, TimingHook},
 };
 
 type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
 
-pub(crate) fn build_chain(middleware: Vec<Arc<dyn ErasedMiddleware>>) -> Arc<dyn ErasedHandler> {
+/// The chain of `middleware`, each layer timed and reported to `timing` if
+/// given.
+pub(crate) fn build_chain(
+    middleware: Vec<Arc<dyn ErasedMiddleware>>,
+    timing: Option<TimingHook>,
+) -> Arc<dyn ErasedHandler> {
     let mut chain: Arc<dyn ErasedHandler> = Arc::new(Base);
 
     for mw in middleware.into_iter().rev() {
         chain = Arc::new(MiddlewareHandler {
             middleware: mw,
             next: chain,
+            timing: timing.clone(),
         });
     }
 
@@ -32,12 +38,41 @@ impl ErasedHandler for Base {
 struct MiddlewareHandler {
     middleware: Arc<dyn ErasedMiddleware>,
     next: Arc<dyn ErasedHandler>,
+    timing: Option<TimingHook>,
 }
 
 impl ErasedHandler for MiddlewareHandler {
     fn call<'a>(&'a self, session: &'a mut Session) -> BoxFuture<'a, Exit> {
-        let next = Next::new(self.next.as_ref());
+        let Some(report) = &self.timing else {
+            let next = Next::new(self.next.as_ref());
 
-        self.middleware.handle(session, next)
+            return self.middleware.handle(session, next);
+        };
+
+        Box::pin(async move {
+            let id = session.id();
+            let marks = Marks::default();
+            let next = TimedNext {
+                next: self.next.as_ref(),
+                marks: &marks,
+            };
+
+            let started = Instant::now();
+            let exit = self.middleware.handle(session, Next::new(&next)).await;
+            let timing = marks.timing(self.middleware.name(), id, started, Instant::now());
+
+            tracing::debug!(
+                target: "shenron::middleware::timing",
+                session = %id,
+                layer = timing.layer,
+                before = ?timing.before,
+                after = ?timing.after,
+                ran_next = timing.ran_next,
+                "middleware timing"
+            );
+            report(&timing);
+
+            exit
+        })
     }
 }
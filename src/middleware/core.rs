@@ -87,3 +87,57 @@ where
         (self.0)(session)
     }
 }
+
+/// Middleware from a function that also gets shared state, so ad-hoc
+/// stateful middleware needn't be a struct with a [`Middleware`] impl.
+/// Build it with [`from_fn_with_state`].
+pub struct FromFnWithState<S, F> {
+    state: S,
+    f: F,
+}
+
+/// Adapt `Fn(&S, &mut Session, Next) -> impl IntoExit` into a
+/// [`Middleware`] that lends it `state` on every session, like axum's
+/// function of the same name.
+///
+/// ```
+/// use std::sync::atomic::{AtomicU64, Ordering};
+/// use shenron::{Exit, Next, Server, Session, middleware::from_fn_with_state};
+///
+/// async fn count(sessions: &AtomicU64, session: &mut Session, next: Next<'_>) -> Exit {
+///     sessions.fetch_add(1, Ordering::Relaxed);
+///     next.run(session).await
+/// }
+///
+/// let _server = Server::new().with(from_fn_with_state(AtomicU64::new(0), count));
+/// ```
+///
+/// The state is borrowed, not cloned, so counters and caches need no `Arc`
+/// unless something outside the server shares them too.
+pub const fn from_fn_with_state<S, F, R>(state: S, f: F) -> FromFnWithState<S, F>
+where
+    S: Send + Sync + 'static,
+    F: AsyncFn(&S, &mut Session, Next<'_>) -> R + Send + Sync + 'static,
+    for<'a> <F as AsyncFnMut<(&'a S, &'a mut Session, Next<'a>)>>::CallRefFuture<'a>: Send,
+    R: IntoExit,
+{
+    FromFnWithState { state, f }
+}
+
+impl<S, F, R> Middleware for FromFnWithState<S, F>
+where
+    S: Send + Sync + 'static,
+    F: AsyncFn(&S, &mut Session, Next<'_>) -> R + Send + Sync + 'static,
+    for<'a> <F as AsyncFnMut<(&'a S, &'a mut Session, Next<'a>)>>::CallRefFuture<'a>: Send,
+    R: IntoExit,
+{
+    type Output = R;
+
+    fn handle<'a>(
+        &'a self,
+        session: &'a mut Session,
+        next: Next<'a>,
+    ) -> impl Future<Output = R> + Send + 'a {
+        (self.f)(&self.state, session, next)
+    }
+}
@@ -8,12 +8,19 @@ type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
 /// [`Middleware::Output`] is erased to [`Exit`] here, at the boxing boundary.
 pub(crate) trait ErasedMiddleware: Send + Sync {
     fn handle<'a>(&'a self, session: &'a mut Session, next: Next<'a>) -> BoxFuture<'a, Exit>;
+
+    /// The middleware's type name, to tell layers apart in timings.
+    fn name(&self) -> &'static str;
 }
 
 impl<M: Middleware> ErasedMiddleware for M {
     fn handle<'a>(&'a self, session: &'a mut Session, next: Next<'a>) -> BoxFuture<'a, Exit> {
         Box::pin(async move { Middleware::handle(self, session, next).await.into_exit() })
     }
+
+    fn name(&self) -> &'static str {
+        std::any::type_name::<M>()
+    }
 }
 
 /// Type-erased handler for the middleware chain. Implemented only by the chain's
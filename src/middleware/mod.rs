@@ -3,9 +3,15 @@ pub mod chain;
 pub mod core;
 pub mod erased;
 mod next;
+mod stack;
+mod timing;
+#[cfg(feature = "tower")]
+pub mod tower;
 
 pub use builtins::*;
 pub(crate) use chain::*;
 pub use core::*;
 pub(crate) use erased::*;
 pub use next::*;
+pub use stack::*;
+pub use timing::*;
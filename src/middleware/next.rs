@@ -10,6 +10,12 @@ impl<'a> Next<'a> {
         Self { inner }
     }
 
+    /// The rest of the chain, for middleware that run layers of their own
+    /// in front of it.
+    pub(crate) const fn into_inner(self) -> &'a dyn ErasedHandler {
+        self.inner
+    }
+
     /// Run the next middleware in the chain, resolving its return value to
     /// an [`Exit`]. Failures arrive as [`Exit::Error`] rather than `Err`, so
     /// callers inspect rather than `?`.
@@ -0,0 +1,84 @@
+use std::{pin::Pin, sync::Arc};
+
+use crate::{
+    Exit, Middleware, Next, Session,
+    middleware::{ErasedHandler, ErasedMiddleware},
+};
+
+type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// Several middleware composed into one, to share a set of layers between
+/// servers or put them in front of a single route.
+///
+/// Layers run outside-in in the order they were added, exactly as if each
+/// had been passed to [`Server::with`](crate::Server::with), and the last
+/// one's `next` is whatever follows the stack. A `Stack` is a [`Middleware`]
+/// itself, and cloning one shares its layers.
+///
+/// ```no_run
+/// # use shenron::{Server, Session, middleware::{Command, Stack, logging, terminal}};
+/// # async fn audit(session: &mut Session, next: shenron::Next<'_>) -> shenron::Exit { next.run(session).await }
+/// # async fn deploy(session: &mut Session) -> shenron::Result { Ok(()) }
+/// let common = Stack::new().with(logging).with(audit);
+///
+/// let _public = Server::new().with(common.clone());
+/// let _admin = Server::new().with(common).with(Command::new(
+///     "deploy",
+///     Stack::new().with(audit).with(terminal(deploy)),
+/// ));
+/// ```
+#[derive(Clone, Default)]
+pub struct Stack {
+    layers: Vec<Arc<dyn ErasedMiddleware>>,
+}
+
+impl Stack {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add `middleware` inside the layers added so far.
+    #[must_use]
+    pub fn with<M: Middleware>(mut self, middleware: M) -> Self {
+        self.layers.push(Arc::new(middleware));
+
+        self
+    }
+}
+
+impl Middleware for Stack {
+    type Output = Exit;
+
+    async fn handle(&self, session: &'_ mut Session, next: Next<'_>) -> Exit {
+        Layers {
+            layers: &self.layers,
+            next: next.into_inner(),
+        }
+        .call(session)
+        .await
+    }
+}
+
+/// What's left of a [`Stack`] to run, then the chain after it.
+struct Layers<'s> {
+    layers: &'s [Arc<dyn ErasedMiddleware>],
+    next: &'s dyn ErasedHandler,
+}
+
+impl ErasedHandler for Layers<'_> {
+    fn call<'a>(&'a self, session: &'a mut Session) -> BoxFuture<'a, Exit> {
+        let Some((layer, rest)) = self.layers.split_first() else {
+            return self.next.call(session);
+        };
+
+        Box::pin(async move {
+            let rest = Layers {
+                layers: rest,
+                next: self.next,
+            };
+
+            layer.handle(session, Next::new(&rest)).await
+        })
+    }
+}
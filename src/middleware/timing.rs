@@ -0,0 +1,110 @@
+use std::{
+    pin::Pin,
+    sync::{Arc, Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+
+use crate::{Exit, Session, SessionId, middleware::ErasedHandler};
+
+type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// Callback registered with
+/// [`Server::on_middleware_timing`](crate::Server::on_middleware_timing).
+pub(crate) type TimingHook = Arc<dyn Fn(&LayerTiming) + Send + Sync>;
+
+/// How long one middleware layer spent on a session, reported by
+/// [`Server::time_middleware`](crate::Server::time_middleware).
+///
+/// Time spent in the rest of the chain is excluded: `before` runs from the
+/// layer starting until it called `next`, and `after` from `next` returning,
+/// or the layer cancelling it, until the layer did.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct LayerTiming {
+    /// The middleware's type name, e.g.
+    /// `shenron::middleware::builtins::logging::logging`.
+    pub layer: &'static str,
+    pub session: SessionId,
+    pub before: Duration,
+    /// Zero if the layer never called `next`.
+    pub after: Duration,
+    /// Whether the layer called `next`, rather than ending the session
+    /// itself.
+    pub ran_next: bool,
+}
+
+/// When a layer called its `next`, and when that returned or was cancelled.
+#[derive(Default)]
+pub(crate) struct Marks(Mutex<(Option<Instant>, Option<Instant>)>);
+
+impl Marks {
+    /// The layer's timing, given when it started and finished.
+    pub(crate) fn timing(
+        &self,
+        layer: &'static str,
+        session: SessionId,
+        started: Instant,
+        finished: Instant,
+    ) -> LayerTiming {
+        let (called, returned) = *self.0.lock().expect("timing marks poisoned");
+
+        match (called, returned) {
+            (Some(called), Some(returned)) => LayerTiming {
+                layer,
+                session,
+                before: called - started,
+                after: finished.saturating_duration_since(returned),
+                ran_next: true,
+            },
+            // `next` outlived the layer, e.g. leaked rather than dropped.
+            (Some(called), None) => LayerTiming {
+                layer,
+                session,
+                before: called - started,
+                after: Duration::ZERO,
+                ran_next: true,
+            },
+            (None, _) => LayerTiming {
+                layer,
+                session,
+                before: finished - started,
+                after: Duration::ZERO,
+                ran_next: false,
+            },
+        }
+    }
+}
+
+/// A layer's `next`, noting when the layer calls it and when it returns.
+pub(crate) struct TimedNext<'m> {
+    pub(crate) next: &'m dyn ErasedHandler,
+    pub(crate) marks: &'m Marks,
+}
+
+impl ErasedHandler for TimedNext<'_> {
+    fn call<'a>(&'a self, session: &'a mut Session) -> BoxFuture<'a, Exit> {
+        Box::pin(async move {
+            {
+                let mut marks = self.marks.0.lock().expect("timing marks poisoned");
+                marks.0.get_or_insert_with(Instant::now);
+            }
+
+            // Marks the return when dropped, so a layer that cancels `next`
+            // (a timeout, an eviction) is timed from the cancellation.
+            let _returned = Returned(self.marks);
+
+            self.next.call(session).await
+        })
+    }
+}
+
+/// Notes when `next` returned, or was cancelled, as it's dropped.
+struct Returned<'m>(&'m Marks);
+
+impl Drop for Returned<'_> {
+    fn drop(&mut self) {
+        // Not `expect`: this may run while unwinding.
+        let mut marks = self.0.0.lock().unwrap_or_else(PoisonError::into_inner);
+        marks.1 = Some(Instant::now());
+    }
+}
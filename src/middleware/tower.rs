@@ -0,0 +1,147 @@
+//! Interop with [tower](https://docs.rs/tower). Requires the `tower` feature.
+//!
+//! The rest of a chain is a [`Service`], and tower [`Layer`]s are
+//! middleware, so ecosystem layers for timeouts, load shedding or metrics
+//! work on sessions.
+//!
+//! Sessions are lent, not owned, so the request type is `&mut Session`:
+//! layers that need `'static` or `Clone` requests, like `Buffer` or
+//! `Retry`, don't apply.
+//!
+//! ```no_run
+//! # use std::time::Duration;
+//! # use shenron::{Server, middleware::tower::TowerLayer};
+//! # use tower::timeout::TimeoutLayer;
+//! let _server = Server::new().with(TowerLayer::new(TimeoutLayer::new(Duration::from_secs(3600))));
+//! ```
+
+use std::{
+    convert::Infallible,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use ::tower::{BoxError, Layer, Service};
+
+use crate::{Error, Exit, Middleware, Next, Session, middleware::ErasedHandler};
+
+type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// The rest of a middleware chain as a [`Service`], from
+/// [`Next::into_service`]. Always ready, and never fails: failures arrive as
+/// [`Exit::Error`], as from [`Next::run`].
+#[derive(Clone, Copy)]
+pub struct NextService<'a> {
+    inner: &'a dyn ErasedHandler,
+}
+
+impl<'a> Next<'a> {
+    /// The rest of the chain as a tower [`Service`].
+    #[must_use]
+    pub const fn into_service(self) -> NextService<'a> {
+        NextService {
+            inner: self.into_inner(),
+        }
+    }
+}
+
+// Sessions borrowed for less than the chain let layers reborrow one around
+// the call, to use it again afterwards.
+impl<'a: 's, 's> Service<&'s mut Session> for NextService<'a> {
+    type Response = Exit;
+    type Error = Infallible;
+    type Future = BoxFuture<'s, Result<Exit, Infallible>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Infallible>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, session: &'s mut Session) -> Self::Future {
+        let inner = self.inner;
+
+        Box::pin(async move { Ok(inner.call(session).await) })
+    }
+}
+
+/// The service `L` makes of the rest of a chain.
+type Wrapped<'a, L> = <L as Layer<NextService<'a>>>::Service;
+
+/// A tower [`Layer`] as middleware: it wraps the rest of the chain, as a
+/// [`NextService`], for each session.
+///
+/// A layer's error, like an elapsed timeout, ends the session as an
+/// [`Exit::Error`] holding [`Error::Tower`].
+pub struct TowerLayer<L> {
+    layer: L,
+}
+
+impl<L> TowerLayer<L> {
+    pub const fn new(layer: L) -> Self {
+        Self { layer }
+    }
+}
+
+impl<L> Middleware for TowerLayer<L>
+where
+    L: for<'a> Layer<NextService<'a>> + Send + Sync + 'static,
+    for<'a> Wrapped<'a, L>: Service<&'a mut Session, Response = Exit> + Send,
+    for<'a> <Wrapped<'a, L> as Service<&'a mut Session>>::Future: Send,
+    for<'a> <Wrapped<'a, L> as Service<&'a mut Session>>::Error: Into<BoxError>,
+{
+    type Output = Exit;
+
+    fn handle<'a>(
+        &'a self,
+        session: &'a mut Session,
+        next: Next<'a>,
+    ) -> impl Future<Output = Exit> + Send + 'a {
+        Serve {
+            service: Box::new(self.layer.layer(next.into_service())),
+            session: Some(session),
+            future: None,
+        }
+    }
+}
+
+/// Runs a session through a layered service once it's ready.
+///
+/// A hand-written future rather than an `async` block: the compiler can't
+/// prove an `async` block holding `S::Future` is `Send` for every lifetime.
+/// Everything is boxed so it's `Unpin` whatever `S` is.
+struct Serve<'a, S: Service<&'a mut Session>> {
+    service: Box<S>,
+    session: Option<&'a mut Session>,
+    future: Option<Pin<Box<S::Future>>>,
+}
+
+impl<'a, S> Future for Serve<'a, S>
+where
+    S: Service<&'a mut Session, Response = Exit>,
+    S::Error: Into<BoxError>,
+{
+    type Output = Exit;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Exit> {
+        let this = self.get_mut();
+
+        if let Some(session) = this.session.take() {
+            match this.service.poll_ready(cx) {
+                Poll::Pending => {
+                    this.session = Some(session);
+                    return Poll::Pending;
+                }
+                Poll::Ready(Err(err)) => return Poll::Ready(Exit::Error(Error::Tower(err.into()))),
+                Poll::Ready(Ok(())) => this.future = Some(Box::pin(this.service.call(session))),
+            }
+        }
+
+        let Some(future) = &mut this.future else {
+            return Poll::Pending;
+        };
+
+        future
+            .as_mut()
+            .poll(cx)
+            .map(|result| result.unwrap_or_else(|err| Exit::Error(Error::Tower(err.into()))))
+    }
+}
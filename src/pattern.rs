@@ -0,0 +1,56 @@
+/// Whether `text` matches the shell-style `pattern`: `*` matches any run of
+/// characters (including none), `?` any single character, and everything
+/// else itself. Like OpenSSH's `DenyUsers` patterns, there's no escaping.
+pub fn glob(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+
+    let (mut p, mut t) = (0, 0);
+    // Where the last `*` was, and the text position it's currently covering
+    // up to; on a mismatch it swallows one more character and we retry.
+    let mut star = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => {
+                let Some((star_p, star_t)) = star else {
+                    return false;
+                };
+
+                p = star_p + 1;
+                t = star_t + 1;
+                star = Some((star_p, t));
+            }
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wildcards_match_runs_and_single_characters() {
+        assert!(glob("root", "root"));
+        assert!(!glob("root", "roots"));
+        assert!(glob("admin*", "admin"));
+        assert!(glob("admin*", "administrator"));
+        assert!(!glob("admin*", "sysadmin"));
+        assert!(glob("*adm*n*", "sysadmin"));
+        assert!(glob("user?", "user1"));
+        assert!(!glob("user?", "user"));
+        assert!(glob("*", ""));
+        assert!(glob("a*b*c", "aXbYbZc"));
+        assert!(!glob("a*b*c", "aXbYbZ"));
+    }
+}
@@ -0,0 +1,210 @@
+#![expect(unsafe_code, reason = "TIOCSWINSZ has no safe wrapper in nix")]
+
+//! Passing what the client does to a child process, for handlers that wrap
+//! a real command. Requires the `process` feature; Unix only.
+//!
+//! OpenSSH's server delivers the client's signals to the command it runs
+//! and keeps its terminal's size in step with the client's window. A
+//! [`SignalForwarder`] does the same for a child the handler spawned:
+//!
+//! ```no_run
+//! use shenron::{Event, Session, process::SignalForwarder};
+//! use tokio::{io::AsyncWriteExt, process::Command};
+//!
+//! async fn wrap(session: &mut Session) -> shenron::Result<std::process::ExitStatus> {
+//!     let mut child = Command::new("make")
+//!         .stdin(std::process::Stdio::piped())
+//!         .spawn()?;
+//!     let forwarder = SignalForwarder::new(child.id().expect("running"));
+//!     let mut stdin = child.stdin.take().expect("piped");
+//!
+//!     loop {
+//!         tokio::select! {
+//!             status = child.wait() => return Ok(status?),
+//!             Some(event) = session.next() => {
+//!                 forwarder.forward(&event)?;
+//!                 if let Event::Input(data) = event {
+//!                     stdin.write_all(&data).await?;
+//!                 }
+//!             }
+//!         }
+//!     }
+//! }
+//! ```
+
+use std::{
+    io,
+    os::fd::{AsRawFd, OwnedFd},
+    str::FromStr,
+};
+
+use nix::{
+    pty::Winsize,
+    sys::signal::{self, Signal as UnixSignal},
+    unistd::Pid,
+};
+
+use crate::{Event, PtySize, Signal};
+
+nix::ioctl_write_ptr_bad!(set_window_size, nix::libc::TIOCSWINSZ, Winsize);
+
+/// Delivers a session's signals to a child process, and its window changes
+/// to the child's pseudo-terminal.
+///
+/// Without a pty, nothing on the server turns Ctrl+C into a signal, so the
+/// forwarder does: Ctrl+C in the input sends `SIGINT`, Ctrl+\ `SIGQUIT`.
+/// With one (see [`with_pty`](Self::with_pty)), the terminal does that
+/// itself once the input is written to it.
+#[derive(Debug)]
+pub struct SignalForwarder {
+    pid: Pid,
+    group: bool,
+    pty: Option<OwnedFd>,
+}
+
+impl SignalForwarder {
+    /// Forward to the process `pid`, e.g. from
+    /// [`Child::id`](tokio::process::Child::id).
+    #[must_use]
+    pub const fn new(pid: u32) -> Self {
+        Self {
+            pid: Pid::from_raw(pid.cast_signed()),
+            group: false,
+            pty: None,
+        }
+    }
+
+    /// The child runs on the pseudo-terminal whose controlling side is
+    /// `master`: resize it with the client's window, and leave Ctrl+C to it.
+    #[must_use]
+    pub fn with_pty(mut self, master: OwnedFd) -> Self {
+        self.pty = Some(master);
+
+        self
+    }
+
+    /// Signal the child's whole process group rather than the child alone,
+    /// for a child spawned as a group leader
+    /// ([`process_group(0)`](tokio::process::Command::process_group)) so a
+    /// shell's pipeline gets the signal too.
+    #[must_use]
+    pub const fn process_group(mut self, group: bool) -> Self {
+        self.group = group;
+
+        self
+    }
+
+    /// Apply `event` to the child: signals are sent, resizes set the pty's
+    /// window size, and Ctrl+C in input without a pty interrupts. Input
+    /// itself is left to the caller to write to the child.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the child is gone, the signal has no Unix number, or
+    /// the resize fails.
+    pub fn forward(&self, event: &Event) -> io::Result<()> {
+        match event {
+            Event::Signal(signal) => self.signal(signal),
+            Event::Resize(size) if self.pty.is_some() => self.resize(*size),
+            Event::Input(data) if self.pty.is_none() => {
+                for byte in data {
+                    match byte {
+                        0x03 => self.send(UnixSignal::SIGINT)?,
+                        0x1c => self.send(UnixSignal::SIGQUIT)?,
+                        _ => {}
+                    }
+                }
+
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Send the child `signal`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the child is gone or the signal has no Unix number.
+    pub fn signal(&self, signal: &Signal) -> io::Result<()> {
+        self.send(unix_signal(signal)?)
+    }
+
+    /// Set the child's pty to `size`, which delivers it `SIGWINCH`. A no-op
+    /// without a pty.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the ioctl fails.
+    pub fn resize(&self, size: PtySize) -> io::Result<()> {
+        let Some(pty) = &self.pty else {
+            return Ok(());
+        };
+
+        let winsize = Winsize {
+            ws_row: clamp(size.height),
+            ws_col: clamp(size.width),
+            ws_xpixel: clamp(size.pixel_width),
+            ws_ypixel: clamp(size.pixel_height),
+        };
+
+        // SAFETY: the fd is open for as long as `self` owns it, and the
+        // ioctl only reads the `Winsize` it's given.
+        unsafe { set_window_size(pty.as_raw_fd(), &raw const winsize) }?;
+
+        Ok(())
+    }
+
+    fn send(&self, signal: UnixSignal) -> io::Result<()> {
+        if self.group {
+            signal::killpg(self.pid, signal)?;
+        } else {
+            signal::kill(self.pid, signal)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The Unix signal an SSH signal name stands for.
+fn unix_signal(signal: &Signal) -> io::Result<UnixSignal> {
+    Ok(match signal {
+        Signal::HUP => UnixSignal::SIGHUP,
+        Signal::INT => UnixSignal::SIGINT,
+        Signal::QUIT => UnixSignal::SIGQUIT,
+        Signal::ILL => UnixSignal::SIGILL,
+        Signal::ABRT => UnixSignal::SIGABRT,
+        Signal::FPE => UnixSignal::SIGFPE,
+        Signal::KILL => UnixSignal::SIGKILL,
+        Signal::USR1 => UnixSignal::SIGUSR1,
+        Signal::SEGV => UnixSignal::SIGSEGV,
+        Signal::PIPE => UnixSignal::SIGPIPE,
+        Signal::ALRM => UnixSignal::SIGALRM,
+        Signal::TERM => UnixSignal::SIGTERM,
+        Signal::Custom(name) => UnixSignal::from_str(&format!("SIG{name}")).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("unknown signal {name}"),
+            )
+        })?,
+    })
+}
+
+fn clamp(n: u32) -> u16 {
+    u16::try_from(n).unwrap_or(u16::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_ssh_signal_names() {
+        assert_eq!(unix_signal(&Signal::TERM).ok(), Some(UnixSignal::SIGTERM));
+        assert_eq!(
+            unix_signal(&Signal::Custom("USR2".into())).ok(),
+            Some(UnixSignal::SIGUSR2)
+        );
+        assert!(unix_signal(&Signal::Custom("BOGUS".into())).is_err());
+    }
+}
@@ -0,0 +1,332 @@
+//! Auth decisions and session policies written in [Rhai] scripts, for rules
+//! that need to change faster than the server can be rebuilt. Requires the
+//! `rhai` feature.
+//!
+//! [Rhai]: https://rhai.rs
+
+use std::{
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+};
+
+use rhai::{AST, Dynamic, Engine, Map, Scope};
+use russh::keys::{HashAlg, PublicKey};
+
+use crate::{
+    Auth, Error, Exit, Middleware, Next, Result, Session, SessionKind,
+    auth::{AuthContext, AuthMethod, AuthProvider},
+};
+
+/// Operations a script may run per call before it's stopped.
+const MAX_OPERATIONS: u64 = 100_000;
+
+/// A Rhai script answering auth and screening sessions: an [`AuthProvider`]
+/// for passwords and public keys, and a [`Middleware`].
+///
+/// For auth, the script defines `authenticate(ctx)`, where `ctx` has `user`,
+/// `method` (`"password"` or `"publickey"`), `password` or
+/// `key_fingerprint`, `remote_addr`, `client_version` and `attempt`.
+/// As middleware, it defines `on_session(session)`, where `session` has
+/// `user`, `remote_addr`, `session_id`, `kind`, `command`, `subsystem`,
+/// `term`, `key_fingerprint` and `env`; without one, sessions pass through.
+/// Either function returns:
+///
+/// - `true` (or nothing, for `on_session`) to allow,
+/// - `false` to deny,
+/// - a string to deny with that message: the rejection reason for auth, or
+///   shown to the session on stderr,
+/// - `#{ allow: bool, message: "..." }` to decide and say something either
+///   way; a session's message goes to stdout when it's allowed.
+///
+/// Denied sessions exit with status 1. A script that fails, returns
+/// anything else, or runs past 100,000 operations denies too, with the
+/// error logged. `print` goes to the log.
+///
+/// ```no_run
+/// # use shenron::{Server, script::Script};
+/// # fn main() -> shenron::Result {
+/// let policy = Script::new(r#"
+///     fn authenticate(ctx) { ctx.user == "admin" && ctx.password == "hunter2" }
+///
+///     fn on_session(session) {
+///         if session.kind == "exec" && session.command.starts_with("rm ") {
+///             return "Not here.\n";
+///         }
+///     }
+/// "#)?;
+///
+/// let _server = Server::new().auth_provider(policy.clone()).with(policy);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Clones share the script, so one kept aside can [`reload`](Self::reload)
+/// a script [loaded](Self::load) from a file once the server is running.
+#[derive(Clone)]
+pub struct Script {
+    engine: Arc<Engine>,
+    ast: Arc<RwLock<AST>>,
+    path: Option<Arc<Path>>,
+}
+
+impl Script {
+    /// A script from its source.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the script doesn't parse
+    pub fn new(source: &str) -> Result<Self> {
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+        engine.on_print(|text| tracing::info!(target: "shenron::script", "{text}"));
+        engine.on_debug(|text, _, _| tracing::debug!(target: "shenron::script", "{text}"));
+
+        let ast = compile(&engine, source)?;
+
+        Ok(Self {
+            engine: Arc::new(engine),
+            ast: Arc::new(RwLock::new(ast)),
+            path: None,
+        })
+    }
+
+    /// A script from the file at `path`, which [`reload`](Self::reload)
+    /// reads again.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the file can't be read, or as for [`new`](Self::new)
+    pub fn load(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+
+        Ok(Self {
+            path: Some(path.as_path().into()),
+            ..Self::new(&std::fs::read_to_string(&path)?)?
+        })
+    }
+
+    /// Swap in the script now at the path it was [loaded](Self::load) from.
+    /// If the new script doesn't parse, the old one stays.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the script wasn't loaded from a file, or as for
+    /// [`load`](Self::load)
+    #[expect(
+        clippy::missing_panics_doc,
+        reason = "the lock is only held to swap the script, which can't panic"
+    )]
+    pub fn reload(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Err(Error::Config(
+                "only scripts loaded from a file can be reloaded".into(),
+            ));
+        };
+
+        let ast = compile(&self.engine, &std::fs::read_to_string(path)?)?;
+        *self.ast.write().expect("script poisoned") = ast;
+
+        tracing::info!(path = %path.display(), "script reloaded");
+
+        Ok(())
+    }
+
+    /// Call the script's `function` with `arg`, if it defines one.
+    fn call(&self, function: &str, arg: Map) -> Option<Decision> {
+        let ast = self.ast.read().expect("script poisoned").clone();
+
+        if !ast
+            .iter_functions()
+            .any(|f| f.name == function && f.params.len() == 1)
+        {
+            return None;
+        }
+
+        let decision = self
+            .engine
+            .call_fn::<Dynamic>(&mut Scope::new(), &ast, function, (arg,))
+            .map_err(|e| e.to_string())
+            .and_then(Decision::from_dynamic);
+
+        Some(decision.unwrap_or_else(|e| {
+            tracing::warn!(function, error = %e, "script failed, denying");
+
+            Decision {
+                allow: false,
+                message: None,
+            }
+        }))
+    }
+
+    fn authenticate(&self, user: &str, credential: Credential<'_>, ctx: &AuthContext) -> Auth {
+        let mut arg = Map::new();
+        arg.insert("user".into(), user.into());
+        arg.insert("remote_addr".into(), ctx.remote_addr().to_string().into());
+        arg.insert("client_version".into(), ctx.client_version().into());
+        arg.insert("attempt".into(), i64::from(ctx.attempt()).into());
+
+        match credential {
+            Credential::Password(password) => {
+                arg.insert("method".into(), "password".into());
+                arg.insert("password".into(), password.into());
+            }
+            Credential::PublicKey(key) => {
+                arg.insert("method".into(), "publickey".into());
+                arg.insert(
+                    "key_fingerprint".into(),
+                    key.fingerprint(HashAlg::Sha256).to_string().into(),
+                );
+            }
+        }
+
+        let Some(decision) = self.call("authenticate", arg) else {
+            tracing::warn!(user, "script has no `authenticate` function, rejecting");
+
+            return Auth::reject();
+        };
+
+        match decision.message {
+            Some(reason) if !decision.allow => Auth::reject().reason(reason),
+            _ => Auth::from(decision.allow),
+        }
+    }
+}
+
+impl AuthProvider for Script {
+    const METHODS: &'static [AuthMethod] = &[AuthMethod::Password, AuthMethod::PublicKey];
+
+    async fn password(&self, user: &str, password: &str, ctx: &AuthContext) -> Auth {
+        self.authenticate(user, Credential::Password(password), ctx)
+    }
+
+    async fn pubkey(&self, user: &str, key: &PublicKey, ctx: &AuthContext) -> Auth {
+        self.authenticate(user, Credential::PublicKey(key), ctx)
+    }
+}
+
+impl Middleware for Script {
+    type Output = Result<Exit>;
+
+    async fn handle(&self, session: &'_ mut Session, next: Next<'_>) -> Result<Exit> {
+        let Some(decision) = self.call("on_session", metadata(session)) else {
+            return Ok(next.run(session).await);
+        };
+
+        if decision.allow {
+            if let Some(message) = &decision.message {
+                session.write_str(message).await?;
+            }
+
+            return Ok(next.run(session).await);
+        }
+
+        tracing::info!(session = %session.id(), user = %session.user(), "script denied session");
+
+        if let Some(message) = &decision.message {
+            session.write_stderr_str(message).await?;
+        }
+
+        Ok(Exit::Code(1))
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Credential<'a> {
+    Password(&'a str),
+    PublicKey(&'a PublicKey),
+}
+
+/// What a script's function returned.
+struct Decision {
+    allow: bool,
+    message: Option<String>,
+}
+
+impl Decision {
+    fn from_dynamic(value: Dynamic) -> std::result::Result<Self, String> {
+        let type_name = value.type_name();
+
+        if value.is_unit() {
+            return Ok(Self {
+                allow: true,
+                message: None,
+            });
+        }
+
+        if let Ok(allow) = value.as_bool() {
+            return Ok(Self {
+                allow,
+                message: None,
+            });
+        }
+
+        if value.is_string() {
+            return Ok(Self {
+                allow: false,
+                message: value.into_string().ok(),
+            });
+        }
+
+        if let Some(map) = value.try_cast::<Map>() {
+            return Ok(Self {
+                allow: map
+                    .get("allow")
+                    .and_then(|allow| allow.as_bool().ok())
+                    .unwrap_or(false),
+                message: map
+                    .get("message")
+                    .and_then(|message| message.clone().into_string().ok()),
+            });
+        }
+
+        Err(format!("expected a bool, string or map, got {type_name}"))
+    }
+}
+
+fn compile(engine: &Engine, source: &str) -> Result<AST> {
+    engine
+        .compile(source)
+        .map_err(|e| Error::Config(format!("script: {e}")))
+}
+
+/// The session as scripts see it.
+fn metadata(session: &Session) -> Map {
+    let optional = |value: Option<&str>| value.map_or(Dynamic::UNIT, Into::into);
+
+    let mut meta = Map::new();
+    meta.insert("user".into(), session.user().into());
+    meta.insert(
+        "remote_addr".into(),
+        session.remote_addr().to_string().into(),
+    );
+    meta.insert("session_id".into(), session.id().to_string().into());
+    meta.insert(
+        "kind".into(),
+        match session.kind() {
+            SessionKind::Shell => "shell",
+            SessionKind::Exec { .. } => "exec",
+            SessionKind::Subsystem { .. } => "subsystem",
+        }
+        .into(),
+    );
+    meta.insert("command".into(), optional(session.raw_command()));
+    meta.insert("subsystem".into(), optional(session.subsystem()));
+    meta.insert("term".into(), optional(session.term()));
+    meta.insert(
+        "key_fingerprint".into(),
+        session
+            .key_fingerprint()
+            .map_or(Dynamic::UNIT, |fingerprint| fingerprint.to_string().into()),
+    );
+    meta.insert(
+        "env".into(),
+        session
+            .env()
+            .iter()
+            .map(|(name, value)| (name.into(), value.into()))
+            .collect::<Map>()
+            .into(),
+    );
+
+    meta
+}
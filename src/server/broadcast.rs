@@ -0,0 +1,27 @@
+use tokio::sync::broadcast;
+
+/// Messages buffered per session before it starts missing them.
+const BROADCAST_CAPACITY: usize = 16;
+
+/// Operator messages for every running session: sent with
+/// [`ServerHandle::broadcast`](crate::ServerHandle::broadcast), received with
+/// [`Session::broadcasts`](crate::Session::broadcasts).
+#[derive(Clone)]
+pub struct Broadcasts(broadcast::Sender<String>);
+
+impl Default for Broadcasts {
+    fn default() -> Self {
+        Self(broadcast::Sender::new(BROADCAST_CAPACITY))
+    }
+}
+
+impl Broadcasts {
+    pub fn subscribe(&self) -> broadcast::Receiver<String> {
+        self.0.subscribe()
+    }
+
+    /// How many sessions were listening.
+    pub fn send(&self, message: String) -> usize {
+        self.0.send(message).unwrap_or(0)
+    }
+}
@@ -0,0 +1,559 @@
+use std::{
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
+
+use serde::{Deserialize, Deserializer};
+
+use crate::{Server, auth::AuthBackoff, server::OverflowPolicy};
+
+/// Declarative server settings, usually loaded from a TOML file so
+/// deployments can be tuned without recompiling.
+///
+/// Every field is optional; anything left out keeps the builder's default.
+/// Durations are in seconds and may be fractional. Unknown keys are an error,
+/// so a typo doesn't silently fall back to a default.
+///
+/// ```toml
+/// bind = "0.0.0.0:2222"
+/// host_keys = ["/etc/shenron/ssh_host_ed25519_key"]
+/// banner_file = "/etc/shenron/banner.txt"
+/// inactivity_timeout = 600
+///
+/// [auth]
+/// authorized_keys = "/etc/shenron/authorized_keys"
+/// max_attempts = 6
+/// rejection_delay = 0.1
+///
+/// [auth.backoff]
+/// initial = 0.25
+/// max = 8
+///
+/// [pre_auth]
+/// timeout = 120
+/// max_connections = 10
+///
+/// [tcp]
+/// nodelay = true
+/// keepalive = 60
+///
+/// [rate_limit]
+/// per_minute = 30
+/// burst = 5
+/// ```
+///
+/// Apply it with [`Server::from_config`], then add the app and any
+/// middleware as usual.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+#[non_exhaustive]
+pub struct ServerConfig {
+    /// Address to listen on, e.g. `"0.0.0.0:2222"`.
+    pub bind: Option<String>,
+    /// Host key files to load. They must exist; with none listed, the default
+    /// key is generated as usual.
+    pub host_keys: Vec<PathBuf>,
+    pub banner: Option<String>,
+    /// Read the banner from a file. Conflicts with `banner`.
+    pub banner_file: Option<PathBuf>,
+    #[serde(deserialize_with = "seconds")]
+    pub inactivity_timeout: Option<Duration>,
+    /// Cap on open connections. See [`Server::max_connections`].
+    pub max_connections: Option<usize>,
+    /// `"queue"`, `"drop"`, or `{ reject = "message" }`.
+    pub overflow: Option<OverflowPolicy>,
+    /// Timeout for reverse DNS lookups; setting it enables them. Requires the
+    /// `reverse-dns` feature.
+    #[serde(deserialize_with = "seconds")]
+    pub reverse_dns: Option<Duration>,
+    /// Client environment variable patterns to keep. See
+    /// [`Server::accept_env`].
+    pub accept_env: Option<Vec<String>>,
+    pub max_env_bytes: Option<usize>,
+    pub keepalive: KeepaliveConfig,
+    pub tcp: TcpConfig,
+    pub pre_auth: PreAuthConfig,
+    pub auth: ServerAuthConfig,
+    pub rate_limit: Option<RateLimitConfig>,
+}
+
+/// SSH-level keepalives. See [`Server::keepalive_interval`].
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+#[non_exhaustive]
+pub struct KeepaliveConfig {
+    #[serde(deserialize_with = "seconds")]
+    pub interval: Option<Duration>,
+    pub max: Option<usize>,
+}
+
+/// Socket options. See [`Server::tcp_nodelay`].
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+#[non_exhaustive]
+pub struct TcpConfig {
+    pub nodelay: Option<bool>,
+    #[serde(deserialize_with = "seconds")]
+    pub keepalive: Option<Duration>,
+    pub backlog: Option<u32>,
+}
+
+/// Limits on unauthenticated connections. See
+/// [`Server::max_pre_auth_connections`].
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+#[non_exhaustive]
+pub struct PreAuthConfig {
+    #[serde(deserialize_with = "seconds")]
+    pub timeout: Option<Duration>,
+    pub max_connections: Option<usize>,
+    pub max_bytes: Option<u64>,
+}
+
+/// File-backed auth methods and brute-force settings.
+///
+/// Password and keyboard-interactive auth need code, so they stay on the
+/// builder.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+#[non_exhaustive]
+pub struct ServerAuthConfig {
+    /// Set `false` to refuse to start without an auth handler. See
+    /// [`Server::open_auth`].
+    pub open: Option<bool>,
+    /// See [`authorized_keys`](crate::auth::authorized_keys).
+    pub authorized_keys: Option<PathBuf>,
+    /// Pick up edits to `authorized_keys` without a restart. See
+    /// [`authorized_keys_reloading`](crate::auth::authorized_keys_reloading).
+    pub reload_authorized_keys: bool,
+    /// See [`trusted_ca_keys`](crate::auth::trusted_ca_keys).
+    pub trusted_ca_keys: Option<PathBuf>,
+    pub max_attempts: Option<usize>,
+    #[serde(deserialize_with = "seconds")]
+    pub rejection_delay: Option<Duration>,
+    #[serde(deserialize_with = "seconds")]
+    pub rejection_delay_initial: Option<Duration>,
+    pub backoff: Option<BackoffConfig>,
+    /// Username patterns to refuse. See [`Server::reject_users`].
+    pub reject_users: Vec<String>,
+}
+
+/// Growing delay per failed auth attempt. Unset fields keep
+/// [`AuthBackoff`](crate::auth::AuthBackoff)'s defaults. See
+/// [`Server::auth_backoff`].
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+#[non_exhaustive]
+pub struct BackoffConfig {
+    #[serde(deserialize_with = "seconds")]
+    pub initial: Option<Duration>,
+    pub multiplier: Option<f64>,
+    #[serde(deserialize_with = "seconds")]
+    pub max: Option<Duration>,
+}
+
+/// Per-IP session rate limit. Set exactly one of the rates. Requires the
+/// `rate-limiting` feature.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+#[non_exhaustive]
+pub struct RateLimitConfig {
+    pub per_second: Option<u32>,
+    pub per_minute: Option<u32>,
+    pub per_hour: Option<u32>,
+    pub burst: Option<u32>,
+}
+
+impl ServerConfig {
+    /// Load and parse a TOML config file.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the file cannot be read or is not a valid config
+    pub fn from_file(path: impl AsRef<Path>) -> crate::Result<Self> {
+        let path = path.as_ref();
+
+        std::fs::read_to_string(path)?
+            .parse()
+            .map_err(|e| crate::Error::Config(format!("{}: {e}", path.display())))
+    }
+}
+
+impl FromStr for ServerConfig {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> crate::Result<Self> {
+        toml::from_str(s).map_err(|e| crate::Error::Config(e.to_string()))
+    }
+}
+
+impl Server {
+    /// Build a server from a [`ServerConfig`].
+    ///
+    /// Equivalent to calling the matching builder methods, so anything can
+    /// still be adjusted afterwards. A configured rate limit is added as the
+    /// outermost middleware.
+    ///
+    /// ```no_run
+    /// # use shenron::{Server, Session, server::ServerConfig};
+    /// # async fn app(session: &mut Session) -> shenron::Result {
+    /// #     Ok(())
+    /// # }
+    /// # async fn run() -> shenron::Result<()> {
+    /// let config = ServerConfig::from_file("/etc/shenron/server.toml")?;
+    ///
+    /// Server::from_config(config)?.app(app).serve().await
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if a referenced file (host key, banner, authorized keys,
+    /// CA keys) cannot be loaded, or the settings are contradictory
+    pub fn from_config(config: ServerConfig) -> crate::Result<Self> {
+        let mut server = Self::new();
+
+        if let Some(addr) = config.bind {
+            server = server.bind(addr);
+        }
+
+        for path in &config.host_keys {
+            server = server.host_key_file(path)?;
+        }
+
+        server = match (config.banner, config.banner_file) {
+            (Some(_), Some(_)) => {
+                return Err(crate::Error::Config(
+                    "set either banner or banner_file, not both".into(),
+                ));
+            }
+            (Some(banner), None) => server.banner(banner),
+            (None, Some(path)) => server.banner_file(path)?,
+            (None, None) => server,
+        };
+
+        if let Some(timeout) = config.inactivity_timeout {
+            server = server.inactivity_timeout(timeout);
+        }
+
+        if let Some(max) = config.max_connections {
+            server = server.max_connections(max);
+        }
+
+        if let Some(policy) = config.overflow {
+            server = server.overflow_policy(policy);
+        }
+
+        if let Some(timeout) = config.reverse_dns {
+            server = apply_reverse_dns(server, timeout)?;
+        }
+
+        if let Some(patterns) = config.accept_env {
+            server = server.accept_env(patterns);
+        }
+
+        if let Some(bytes) = config.max_env_bytes {
+            server = server.max_env_bytes(bytes);
+        }
+
+        server = apply_keepalive(server, &config.keepalive);
+        server = apply_tcp(server, &config.tcp);
+        server = apply_pre_auth(server, &config.pre_auth);
+        server = apply_auth(server, config.auth)?;
+
+        if let Some(rate_limit) = config.rate_limit {
+            server = apply_rate_limit(server, &rate_limit)?;
+        }
+
+        Ok(server)
+    }
+}
+
+const fn apply_keepalive(mut server: Server, config: &KeepaliveConfig) -> Server {
+    if let Some(interval) = config.interval {
+        server = server.keepalive_interval(interval);
+    }
+
+    if let Some(max) = config.max {
+        server = server.keepalive_max(max);
+    }
+
+    server
+}
+
+const fn apply_tcp(mut server: Server, config: &TcpConfig) -> Server {
+    if let Some(nodelay) = config.nodelay {
+        server = server.tcp_nodelay(nodelay);
+    }
+
+    if let Some(idle) = config.keepalive {
+        server = server.tcp_keepalive(idle);
+    }
+
+    if let Some(backlog) = config.backlog {
+        server = server.listen_backlog(backlog);
+    }
+
+    server
+}
+
+const fn apply_pre_auth(mut server: Server, config: &PreAuthConfig) -> Server {
+    if let Some(timeout) = config.timeout {
+        server = server.pre_auth_timeout(timeout);
+    }
+
+    if let Some(max) = config.max_connections {
+        server = server.max_pre_auth_connections(max);
+    }
+
+    if let Some(bytes) = config.max_bytes {
+        server = server.max_pre_auth_bytes(bytes);
+    }
+
+    server
+}
+
+fn apply_auth(mut server: Server, config: ServerAuthConfig) -> crate::Result<Server> {
+    if let Some(open) = config.open {
+        server = server.open_auth(open);
+    }
+
+    if let Some(path) = config.authorized_keys {
+        let handler = if config.reload_authorized_keys {
+            crate::auth::authorized_keys_reloading(path)?
+        } else {
+            crate::auth::authorized_keys(path)?
+        };
+
+        server = server.pubkey_auth(handler);
+    }
+
+    if let Some(path) = config.trusted_ca_keys {
+        server = server.cert_auth(crate::auth::trusted_ca_keys(path)?);
+    }
+
+    if !config.reject_users.is_empty() {
+        server = server.reject_users(config.reject_users);
+    }
+
+    if let Some(attempts) = config.max_attempts {
+        server = server.max_auth_attempts(attempts);
+    }
+
+    if let Some(delay) = config.rejection_delay {
+        server = server.auth_rejection_delay(delay);
+    }
+
+    if let Some(delay) = config.rejection_delay_initial {
+        server = server.auth_rejection_delay_initial(delay);
+    }
+
+    if let Some(config) = config.backoff {
+        let mut backoff = AuthBackoff::new();
+
+        if let Some(initial) = config.initial {
+            backoff = backoff.initial(initial);
+        }
+
+        if let Some(multiplier) = config.multiplier {
+            backoff = backoff.multiplier(multiplier);
+        }
+
+        if let Some(max) = config.max {
+            backoff = backoff.max(max);
+        }
+
+        server = server.auth_backoff(backoff);
+    }
+
+    Ok(server)
+}
+
+#[cfg(feature = "reverse-dns")]
+#[expect(
+    clippy::unnecessary_wraps,
+    reason = "matches the fallback without the feature"
+)]
+fn apply_reverse_dns(server: Server, timeout: Duration) -> crate::Result<Server> {
+    Ok(server.reverse_dns(timeout))
+}
+
+#[cfg(not(feature = "reverse-dns"))]
+fn apply_reverse_dns(_server: Server, _timeout: Duration) -> crate::Result<Server> {
+    Err(crate::Error::Config(
+        "reverse_dns requires the `reverse-dns` feature".into(),
+    ))
+}
+
+#[cfg(feature = "rate-limiting")]
+fn apply_rate_limit(server: Server, config: &RateLimitConfig) -> crate::Result<Server> {
+    use crate::middleware::builtins::RateLimiter;
+
+    let nonzero = |n: u32, key: &str| {
+        if n == 0 {
+            Err(crate::Error::Config(format!(
+                "rate_limit.{key} must be > 0"
+            )))
+        } else {
+            Ok(n)
+        }
+    };
+
+    let limiter = match (config.per_second, config.per_minute, config.per_hour) {
+        (Some(n), None, None) => RateLimiter::per_second(nonzero(n, "per_second")?),
+        (None, Some(n), None) => RateLimiter::per_minute(nonzero(n, "per_minute")?),
+        (None, None, Some(n)) => RateLimiter::per_hour(nonzero(n, "per_hour")?),
+        _ => {
+            return Err(crate::Error::Config(
+                "rate_limit needs exactly one of per_second, per_minute, per_hour".into(),
+            ));
+        }
+    };
+
+    let limiter = match config.burst {
+        Some(n) => limiter.burst(nonzero(n, "burst")?),
+        None => limiter,
+    };
+
+    Ok(server.with(limiter))
+}
+
+#[cfg(not(feature = "rate-limiting"))]
+fn apply_rate_limit(_server: Server, _config: &RateLimitConfig) -> crate::Result<Server> {
+    Err(crate::Error::Config(
+        "rate_limit requires the `rate-limiting` feature".into(),
+    ))
+}
+
+/// Durations in config files are (possibly fractional) seconds.
+fn seconds<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Duration>, D::Error> {
+    let Some(secs) = Option::<f64>::deserialize(deserializer)? else {
+        return Ok(None);
+    };
+
+    Duration::try_from_secs_f64(secs)
+        .map(Some)
+        .map_err(serde::de::Error::custom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_full_config() {
+        let config: ServerConfig = r#"
+            bind = "0.0.0.0:2222"
+            host_keys = ["/etc/ssh/key"]
+            banner = "hi"
+            inactivity_timeout = 600
+            reverse_dns = 1.5
+            max_connections = 500
+            overflow = { reject = "busy" }
+            accept_env = ["LANG", "LC_*"]
+
+            [keepalive]
+            interval = 15
+            max = 3
+
+            [tcp]
+            nodelay = true
+            keepalive = 60
+            backlog = 128
+
+            [pre_auth]
+            timeout = 30
+            max_connections = 10
+            max_bytes = 65536
+
+            [auth]
+            authorized_keys = "/etc/ssh/authorized_keys"
+            reload_authorized_keys = true
+            max_attempts = 6
+            rejection_delay = 0.25
+            reject_users = ["root", "admin*"]
+
+            [auth.backoff]
+            initial = 0.1
+            multiplier = 3
+
+            [rate_limit]
+            per_minute = 30
+            burst = 5
+        "#
+        .parse()
+        .expect("parse");
+
+        assert_eq!(config.bind.as_deref(), Some("0.0.0.0:2222"));
+        assert_eq!(config.host_keys, [PathBuf::from("/etc/ssh/key")]);
+        assert_eq!(config.inactivity_timeout, Some(Duration::from_mins(10)));
+        assert_eq!(config.reverse_dns, Some(Duration::from_millis(1500)));
+        assert_eq!(config.max_connections, Some(500));
+        assert_eq!(
+            config.accept_env.as_deref(),
+            Some(&["LANG".to_string(), "LC_*".to_string()][..])
+        );
+        assert_eq!(config.overflow, Some(OverflowPolicy::Reject("busy".into())));
+        assert_eq!(config.keepalive.max, Some(3));
+        assert_eq!(config.tcp.nodelay, Some(true));
+        assert_eq!(config.pre_auth.max_bytes, Some(65536));
+        assert!(config.auth.reload_authorized_keys);
+        assert_eq!(
+            config.auth.rejection_delay,
+            Some(Duration::from_millis(250))
+        );
+        assert_eq!(config.auth.reject_users, ["root", "admin*"]);
+        let backoff = config.auth.backoff.expect("backoff");
+        assert_eq!(backoff.initial, Some(Duration::from_millis(100)));
+        assert_eq!(backoff.multiplier, Some(3.0));
+        assert!(backoff.max.is_none());
+        assert_eq!(config.rate_limit.and_then(|r| r.per_minute), Some(30));
+    }
+
+    #[test]
+    fn empty_config_is_all_defaults() {
+        let config: ServerConfig = "".parse().expect("parse");
+
+        assert!(config.bind.is_none());
+        assert!(config.host_keys.is_empty());
+        assert!(config.rate_limit.is_none());
+    }
+
+    #[test]
+    fn unknown_keys_are_rejected() {
+        let err = "inactivty_timeout = 5"
+            .parse::<ServerConfig>()
+            .expect_err("typo must not parse");
+
+        assert!(err.to_string().contains("inactivty_timeout"), "{err}");
+    }
+
+    #[test]
+    fn negative_durations_are_rejected() {
+        assert!("[pre_auth]\ntimeout = -1".parse::<ServerConfig>().is_err());
+    }
+
+    #[test]
+    fn banner_and_banner_file_conflict() {
+        let config: ServerConfig = "banner = \"a\"\nbanner_file = \"b\""
+            .parse()
+            .expect("parse");
+
+        assert!(Server::from_config(config).is_err());
+    }
+
+    #[cfg(feature = "rate-limiting")]
+    #[test]
+    fn rate_limit_needs_exactly_one_rate() {
+        let none: ServerConfig = "[rate_limit]\nburst = 3".parse().expect("parse");
+        let two: ServerConfig = "[rate_limit]\nper_second = 1\nper_hour = 2"
+            .parse()
+            .expect("parse");
+        let zero: ServerConfig = "[rate_limit]\nper_second = 0".parse().expect("parse");
+
+        assert!(Server::from_config(none).is_err());
+        assert!(Server::from_config(two).is_err());
+        assert!(Server::from_config(zero).is_err());
+    }
+}
@@ -1,15 +1,34 @@
-use std::{path::Path, pin::Pin, sync::Arc, time::Duration};
+use std::{
+    net::{IpAddr, SocketAddr},
+    path::Path,
+    pin::Pin,
+    sync::Arc,
+    time::Duration,
+};
 
 use russh::{
-    keys::{PrivateKey, PublicKey},
-    server::{Config, Server as _},
+    keys::{Algorithm, PrivateKey, PublicKey},
+    server::Config,
+};
+use tokio::{
+    net::TcpListener,
+    sync::{Notify, broadcast},
+    task::JoinHandle,
 };
 
 use crate::{
-    Middleware, Session,
-    auth::AuthConfig,
-    middleware::{self, ErasedMiddleware},
-    server::{ShenronServer, keygen, keygen::HostKeyOptions},
+    Bus, Middleware, Session,
+    auth::{
+        AuthBackoff, AuthConfig, AuthContext, AuthMethod, AuthProvider, KeyPolicy, Lockout,
+        PasswordWithContext, PubkeyWithContext, UserResolver,
+    },
+    middleware::{self, ErasedMiddleware, LayerTiming, TimingHook},
+    server::{
+        AuthEvent, AuthHook, Broadcasts, EnvPolicy, ForwardApprover, ForwardRequest, ReverseDns,
+        ServerEvent, ServerEvents, ShenronServer, keygen,
+        keygen::{HostKeyOptions, PassphraseProvider},
+        listener::{self, ConnectionLimits, OverflowPolicy, PreAuthLimits, TcpOptions},
+    },
 };
 
 type ShutdownFuture = Pin<Box<dyn Future<Output = ()> + Send>>;
@@ -28,20 +47,40 @@ const DEFAULT_HOST_KEY_PATH: &str = "id_ed25519";
 /// reachable from an untrusted network, configure
 /// [`password_auth`](Self::password_auth) and/or
 /// [`pubkey_auth`](Self::pubkey_auth); once either is set, only those
-/// methods are advertised and `none` is rejected.
+/// methods are advertised and `none` is rejected. To keep a server open on
+/// purpose, say so with [`anonymous_auth`](Self::anonymous_auth); to make a
+/// forgotten handler a startup error instead, use
+/// [`open_auth(false)`](Self::open_auth).
 #[derive(Default)]
 pub struct Server {
     addr: Option<String>,
     keys: Vec<PrivateKey>,
     middleware: Vec<Arc<dyn ErasedMiddleware>>,
+    middleware_timing: Option<TimingHook>,
     auth: AuthConfig,
     shutdown: Option<ShutdownFuture>,
     auth_rejection_delay: Option<Duration>,
     auth_rejection_delay_initial: Option<Duration>,
+    max_auth_attempts: Option<usize>,
+    auth_backoff: Option<AuthBackoff>,
+    lockout: Option<Lockout>,
+    deny_open_auth: bool,
     inactivity_timeout: Option<Duration>,
     banner: Option<String>,
     keepalive_interval: Option<Duration>,
     keepalive_max: Option<usize>,
+    nodelay: bool,
+    tcp: TcpOptions,
+    pre_auth: PreAuthLimits,
+    connections: ConnectionLimits,
+    reverse_dns: Option<Arc<ReverseDns>>,
+    events: ServerEvents,
+    auth_hook: Option<AuthHook>,
+    forward_approver: Option<ForwardApprover>,
+    env: EnvPolicy,
+    #[cfg(feature = "resume")]
+    resumable: bool,
+    bus: Bus,
 }
 
 impl Server {
@@ -119,9 +158,13 @@ impl Server {
 
     /// Add a passphrase-encrypted host key from a file
     ///
+    /// To fetch the passphrase lazily (e.g. from a secrets manager), use
+    /// [`host_key_file_with_provider`](Self::host_key_file_with_provider).
+    ///
     /// # Errors
     ///
     /// Returns `Err` if the key cannot be loaded or decrypted
+    #[doc(alias = "host_key_file_with_passphrase")]
     pub fn host_key_file_with(
         self,
         path: impl AsRef<Path>,
@@ -132,6 +175,40 @@ impl Server {
         Ok(self.host_key(key))
     }
 
+    /// Add a host key from a file, asking `provider` for the passphrase if
+    /// the key is encrypted
+    ///
+    /// One provider can serve several keys; it receives each key's path.
+    ///
+    /// ```no_run
+    /// # use std::path::Path;
+    /// # use shenron::Server;
+    /// # fn main() -> shenron::Result<()> {
+    /// let from_env = |_path: &Path| {
+    ///     std::env::var("HOST_KEY_PASSPHRASE").map_err(|e| shenron::Error::Config(e.to_string()))
+    /// };
+    ///
+    /// let _server = Server::new()
+    ///     .host_key_file_with_provider("ssh_host_ed25519_key", &from_env)?
+    ///     .host_key_file_with_provider("ssh_host_ecdsa_key", &from_env)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the key cannot be loaded or decrypted, or the
+    /// provider fails
+    pub fn host_key_file_with_provider(
+        self,
+        path: impl AsRef<Path>,
+        provider: &impl PassphraseProvider,
+    ) -> crate::Result<Self> {
+        let key = keygen::load_with_provider(path.as_ref(), provider)?;
+
+        Ok(self.host_key(key))
+    }
+
     /// Add a host key from raw OpenSSH/PEM bytes (Wish `WithHostKeyPEM`)
     ///
     /// # Errors
@@ -189,6 +266,170 @@ impl Server {
         self
     }
 
+    /// Disable Nagle's algorithm on accepted connections.
+    ///
+    /// Interactive sessions send many tiny packets (one per keystroke or
+    /// redraw); with Nagle on they are batched and feel laggy. Off by default.
+    #[must_use]
+    pub const fn tcp_nodelay(mut self, nodelay: bool) -> Self {
+        self.nodelay = nodelay;
+
+        self
+    }
+
+    /// Enable TCP keepalive on accepted connections, probing after the
+    /// connection has been idle for `idle`.
+    ///
+    /// Unlike [`keepalive_interval`](Self::keepalive_interval), which pings at
+    /// the SSH layer, this is handled by the kernel and also keeps NAT and
+    /// firewall state alive.
+    #[must_use]
+    pub const fn tcp_keepalive(mut self, idle: Duration) -> Self {
+        self.tcp.keepalive = Some(idle);
+
+        self
+    }
+
+    /// Length of the kernel's queue of connections waiting to be accepted.
+    /// Defaults to 1024.
+    #[must_use]
+    pub const fn listen_backlog(mut self, backlog: u32) -> Self {
+        self.tcp.backlog = backlog;
+
+        self
+    }
+
+    /// Cap how many connections may be mid-handshake or authenticating at
+    /// once.
+    ///
+    /// Connections beyond the cap are dropped as soon as they are accepted,
+    /// before any SSH state is allocated, so a flood of half-open handshakes
+    /// can't exhaust memory. Authenticated connections don't count. Unlimited
+    /// by default; sshd's `MaxStartups` starts refusing at 10.
+    #[must_use]
+    pub const fn max_pre_auth_connections(mut self, max: usize) -> Self {
+        self.pre_auth.max_connections = Some(max);
+
+        self
+    }
+
+    /// Disconnect clients that haven't authenticated this long after
+    /// connecting (sshd's `LoginGraceTime`, 120 seconds by default there).
+    ///
+    /// Covers the whole pre-auth phase: version exchange, key exchange, and
+    /// every auth attempt. Unlimited by default.
+    #[must_use]
+    pub const fn pre_auth_timeout(mut self, timeout: Duration) -> Self {
+        self.pre_auth.timeout = Some(timeout);
+
+        self
+    }
+
+    /// Drop connections that send more than `bytes` before authenticating.
+    ///
+    /// A legitimate handshake plus a few auth attempts is a handful of
+    /// kilobytes; anything far beyond that is abuse. Unlimited by default.
+    #[must_use]
+    pub const fn max_pre_auth_bytes(mut self, bytes: u64) -> Self {
+        self.pre_auth.max_bytes = Some(bytes);
+
+        self
+    }
+
+    /// Cap how many connections may be open at once, authenticated or not.
+    ///
+    /// Every connection runs on its own task; without a cap a flood of
+    /// clients spawns tasks until the runtime (or memory) gives out. What
+    /// happens to connections over the cap is set by
+    /// [`overflow_policy`](Self::overflow_policy) — by default they queue.
+    /// Unlimited by default.
+    #[must_use]
+    pub const fn max_connections(mut self, max: usize) -> Self {
+        self.connections.max = Some(max);
+
+        self
+    }
+
+    /// What to do with new connections once
+    /// [`max_connections`](Self::max_connections) is reached: queue them,
+    /// reject them with a message, or drop them.
+    ///
+    /// ```no_run
+    /// # use shenron::{Server, server::OverflowPolicy};
+    /// let _server = Server::new()
+    ///     .max_connections(500)
+    ///     .overflow_policy(OverflowPolicy::Reject("server busy, try again later".into()));
+    /// ```
+    #[must_use]
+    pub fn overflow_policy(mut self, policy: OverflowPolicy) -> Self {
+        self.connections.overflow = policy;
+
+        self
+    }
+
+    /// Look up each client's hostname once it authenticates, exposed as
+    /// [`Session::remote_hostname`].
+    ///
+    /// The PTR record is only trusted if it resolves back to the client's
+    /// address (sshd's `UseDNS`). Lookups that take longer than `timeout` are
+    /// abandoned and delay login by at most that long; results are cached for
+    /// a few minutes. Off by default, so no DNS traffic is generated.
+    /// Requires the `reverse-dns` feature.
+    #[cfg(feature = "reverse-dns")]
+    #[must_use]
+    pub fn reverse_dns(mut self, timeout: Duration) -> Self {
+        self.reverse_dns = Some(Arc::new(ReverseDns::new(timeout)));
+
+        self
+    }
+
+    /// Like [`reverse_dns`](Self::reverse_dns), but `lookup` names each
+    /// client address instead of the system resolver: for a resolver of your
+    /// own, or a fixed table. It runs on a blocking thread, and whatever name
+    /// it returns is trusted as is.
+    ///
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// # use shenron::Server;
+    /// let _server = Server::new().reverse_dns_with(Duration::from_secs(2), |ip| {
+    ///     ip.is_loopback().then(|| "localhost".to_string())
+    /// });
+    /// ```
+    #[must_use]
+    pub fn reverse_dns_with<F>(mut self, timeout: Duration, lookup: F) -> Self
+    where
+        F: Fn(IpAddr) -> Option<String> + Send + Sync + 'static,
+    {
+        self.reverse_dns = Some(Arc::new(ReverseDns::with_lookup(timeout, Arc::new(lookup))));
+
+        self
+    }
+
+    /// Keep only the client environment variables whose names match one of
+    /// `patterns` (`*` and `?` wildcards), like OpenSSH's `AcceptEnv`;
+    /// requests for others are dropped. Without it every name is accepted.
+    ///
+    /// ```no_run
+    /// # use shenron::Server;
+    /// let _server = Server::new().accept_env(["LANG", "LC_*", "MY_APP_*"]);
+    /// ```
+    #[must_use]
+    pub fn accept_env(mut self, patterns: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.env.accept(patterns.into_iter().map(Into::into));
+
+        self
+    }
+
+    /// Cap the total size of a session's client environment, names and
+    /// values together; a variable that would go over is dropped. 64 KiB by
+    /// default.
+    #[must_use]
+    pub const fn max_env_bytes(mut self, bytes: usize) -> Self {
+        self.env.set_max_bytes(bytes);
+
+        self
+    }
+
     /// Add a middleware to the middleware stack
     ///
     /// Middleware are executed outside-in: the first middleware
@@ -200,6 +441,88 @@ impl Server {
         self
     }
 
+    /// Time each middleware layer on every session, to find the slow ones
+    /// (a GeoIP lookup, a database check) in production.
+    ///
+    /// Each layer's [`LayerTiming`](crate::middleware::LayerTiming) — the
+    /// time it spent before calling `next` and after it returned, excluding
+    /// the rest of the chain — is logged as a `debug` event with target
+    /// `shenron::middleware::timing`. A [`Stack`](crate::middleware::Stack)
+    /// is timed as one layer.
+    #[must_use]
+    pub fn time_middleware(mut self) -> Self {
+        self.middleware_timing
+            .get_or_insert_with(|| Arc::new(|_: &LayerTiming| {}));
+
+        self
+    }
+
+    /// Like [`time_middleware`](Self::time_middleware), also calling `hook`
+    /// with each layer's timing, e.g. to feed a histogram. It runs inline
+    /// on the session's task, so keep it quick.
+    ///
+    /// ```no_run
+    /// # use shenron::Server;
+    /// let _server = Server::new().on_middleware_timing(|timing| {
+    ///     if timing.before + timing.after > std::time::Duration::from_millis(100) {
+    ///         tracing::warn!(layer = timing.layer, ?timing.before, ?timing.after, "slow middleware");
+    ///     }
+    /// });
+    /// ```
+    #[must_use]
+    pub fn on_middleware_timing(
+        mut self,
+        hook: impl Fn(&LayerTiming) + Send + Sync + 'static,
+    ) -> Self {
+        self.middleware_timing = Some(Arc::new(hook));
+
+        self
+    }
+
+    /// Accept the `none` method — no credentials — for users `handler`
+    /// approves.
+    ///
+    /// The explicit form of the open default: the handler sees the username
+    /// and returns an [`Auth`](crate::Auth) like any other, so a public demo
+    /// can let anyone in as `guest` while other usernames still need a
+    /// configured method. `none` is advertised next to those methods.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use shenron::Server;
+    /// let _server = Server::new()
+    ///     .anonymous_auth(|user| async move { user == "guest" })
+    ///     .password_auth(|user, password| async move {
+    ///         user == "admin" && password == "admin"
+    ///     });
+    /// ```
+    #[must_use]
+    pub fn anonymous_auth<F, Fut>(mut self, handler: F) -> Self
+    where
+        F: Fn(String) -> Fut + Send + Sync + 'static,
+        Fut: Future + Send + 'static,
+        Fut::Output: Into<crate::Auth>,
+    {
+        self.auth.anonymous = Some(Arc::new(handler));
+
+        self
+    }
+
+    /// Whether a server with no auth handlers lets everyone in. On by
+    /// default, like Wish.
+    ///
+    /// Pass `false` in production so that forgetting (or misconfiguring)
+    /// every handler makes [`listen`](Self::listen) fail instead of serving
+    /// an open server. Use [`anonymous_auth`](Self::anonymous_auth) for
+    /// deliberate open access.
+    #[must_use]
+    pub const fn open_auth(mut self, allow: bool) -> Self {
+        self.deny_open_auth = !allow;
+
+        self
+    }
+
     /// Set a password authentication handler
     ///
     /// The handler receives the username and password and returns
@@ -226,6 +549,29 @@ impl Server {
         self
     }
 
+    /// Like [`password_auth`](Self::password_auth), but the handler also
+    /// gets the attempt's [`AuthContext`]: the client's address, version
+    /// string and fingerprint, and which attempt this is.
+    ///
+    /// ```no_run
+    /// # use shenron::Server;
+    /// let _server = Server::new()
+    ///     .password_auth_with_context(|user, password, ctx| async move {
+    ///         ctx.remote_addr().ip().is_loopback() && user == "admin" && password == "admin"
+    ///     });
+    /// ```
+    #[must_use]
+    pub fn password_auth_with_context<F, Fut>(mut self, handler: F) -> Self
+    where
+        F: Fn(String, String, AuthContext) -> Fut + Send + Sync + 'static,
+        Fut: Future + Send + 'static,
+        Fut::Output: Into<crate::Auth>,
+    {
+        self.auth.password = Some(Arc::new(PasswordWithContext(handler)));
+
+        self
+    }
+
     /// Set a public key authentication handler
     ///
     /// The handler receives the username and public key, and returns
@@ -252,6 +598,67 @@ impl Server {
         self
     }
 
+    /// Like [`pubkey_auth`](Self::pubkey_auth), but the handler also gets
+    /// the attempt's [`AuthContext`].
+    ///
+    /// ```no_run
+    /// # use shenron::Server;
+    /// let _server = Server::new()
+    ///     // Keys only on the internal interface.
+    ///     .pubkey_auth_with_context(|_user, _key, ctx| async move {
+    ///         ctx.local_addr().ip().is_loopback()
+    ///     });
+    /// ```
+    #[must_use]
+    pub fn pubkey_auth_with_context<F, Fut>(mut self, handler: F) -> Self
+    where
+        F: Fn(String, PublicKey, AuthContext) -> Fut + Send + Sync + 'static,
+        Fut: Future + Send + 'static,
+        Fut::Output: Into<crate::Auth>,
+    {
+        self.auth.pubkey = Some(Arc::new(PubkeyWithContext(handler)));
+
+        self
+    }
+
+    /// Screen public keys when they're offered, before the client proves it
+    /// holds the private key.
+    ///
+    /// Clients probe with each key they have before signing with one. A
+    /// `false` here turns the probe away cheaply — no signature check, no
+    /// [`pubkey_auth`](Self::pubkey_auth) call — and, since the client
+    /// hasn't authenticated anything yet, doesn't count as a failed attempt
+    /// for [`lockout`](Self::lockout) or
+    /// [`max_auth_attempts`](Self::max_auth_attempts). Keys that pass still
+    /// go through `pubkey_auth` once signed, so this is a filter, not the
+    /// decision.
+    ///
+    /// Certificate offers are screened by the key inside the certificate,
+    /// so with [`cert_auth`](Self::cert_auth) configured don't reject keys
+    /// just for being unknown.
+    ///
+    /// ```no_run
+    /// # use std::{collections::HashSet, sync::Arc};
+    /// # use shenron::Server;
+    /// # let known: Arc<HashSet<String>> = Arc::default();
+    /// let _server = Server::new()
+    ///     .pubkey_offered(move |_user, key| {
+    ///         let known = Arc::clone(&known);
+    ///         async move { known.contains(&key.to_openssh().unwrap_or_default()) }
+    ///     })
+    ///     .pubkey_auth(|user, key| async move { true });
+    /// ```
+    #[must_use]
+    pub fn pubkey_offered<F, Fut>(mut self, filter: F) -> Self
+    where
+        F: Fn(String, PublicKey) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = bool> + Send + 'static,
+    {
+        self.auth.pubkey_offered = Some(Arc::new(filter));
+
+        self
+    }
+
     /// Set an OpenSSH certificate authentication handler
     ///
     /// Called when a client authenticates with a certificate instead of a
@@ -311,6 +718,130 @@ impl Server {
         self
     }
 
+    /// Answer auth with a stateful [`AuthProvider`] instead of closures.
+    ///
+    /// The provider handles each method in its
+    /// [`METHODS`](AuthProvider::METHODS), replacing any handler set for that
+    /// method before; handlers set afterwards replace the provider's in turn.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use shenron::{Auth, Server, auth::{AuthContext, AuthMethod, AuthProvider}};
+    /// # use russh::keys::PublicKey;
+    /// struct Directory;
+    ///
+    /// impl AuthProvider for Directory {
+    ///     const METHODS: &'static [AuthMethod] = &[AuthMethod::PublicKey];
+    ///
+    ///     async fn pubkey(&self, user: &str, key: &PublicKey, ctx: &AuthContext) -> Auth {
+    ///         Auth::from(user == "admin" && ctx.remote_addr().ip().is_loopback())
+    ///     }
+    /// }
+    ///
+    /// let _server = Server::new().auth_provider(Directory);
+    /// ```
+    #[must_use]
+    pub fn auth_provider(mut self, provider: impl AuthProvider) -> Self {
+        crate::auth::install_provider(&mut self.auth, provider);
+
+        self
+    }
+
+    /// Refuse usernames matching any of `patterns` before an auth handler
+    /// sees them, like OpenSSH's `DenyUsers`.
+    ///
+    /// `*` matches any run of characters and `?` any one. A denied attempt
+    /// counts as a failure — toward [`max_auth_attempts`](Self::max_auth_attempts)
+    /// and any [`lockout`](Self::lockout) — and applies even to an open
+    /// server. Calling it again adds patterns.
+    ///
+    /// ```no_run
+    /// # use shenron::Server;
+    /// let _server = Server::new()
+    ///     .reject_users(["root", "admin*"])
+    ///     .password_auth(|_, _| async { true });
+    /// ```
+    #[must_use]
+    pub fn reject_users(mut self, patterns: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.auth
+            .denied_users
+            .extend(patterns.into_iter().map(Into::into));
+
+        self
+    }
+
+    /// Refuse client keys the policy rules out — DSA, short RSA keys, or
+    /// anything outside an allowlist — before an auth handler sees them.
+    ///
+    /// Offers of a refused key are declined so the client tries its next
+    /// one; a signed attempt with one counts as a failure, with the reason
+    /// in the log and the [`AuthEvent`]. Applies to certificates (both the
+    /// key and its CA) as well as plain keys. See [`KeyPolicy`].
+    ///
+    /// ```no_run
+    /// # use shenron::{Server, auth::KeyPolicy};
+    /// let _server = Server::new()
+    ///     .key_policy(KeyPolicy::new().min_rsa_bits(3072))
+    ///     .pubkey_auth(|_, _| async { true });
+    /// ```
+    #[must_use]
+    pub fn key_policy(mut self, policy: KeyPolicy) -> Self {
+        self.auth.key_policy = Some(policy);
+
+        self
+    }
+
+    /// Look up each authenticated user's account and attach it to their
+    /// sessions.
+    ///
+    /// Runs once auth has passed; a user the resolver has no account for is
+    /// refused. The app and middleware read the account back with
+    /// `session.get::<R::User>()`. See [`UserResolver`].
+    ///
+    /// ```no_run
+    /// # use shenron::Server;
+    /// #[derive(Clone)]
+    /// struct Account { id: u64 }
+    ///
+    /// let _server = Server::new()
+    ///     .password_auth(|_, _| async { true })
+    ///     .user_resolver(|user: String| async move {
+    ///         (user == "alice").then_some(Account { id: 1 })
+    ///     });
+    /// ```
+    #[must_use]
+    pub fn user_resolver(mut self, resolver: impl UserResolver) -> Self {
+        self.auth.resolver = Some(Arc::new(resolver));
+
+        self
+    }
+
+    /// Require every method in `methods` to pass before a client is accepted.
+    ///
+    /// Call it more than once to allow alternatives, like OpenSSH's
+    /// `AuthenticationMethods`: a client is in once it completes any one set,
+    /// in any order. After each factor the server offers only the methods
+    /// that are still missing and flags the reply `partial_success` (russh
+    /// currently puts that flag on the wire only for keyboard-interactive;
+    /// clients follow the narrowed method list either way).
+    /// A certificate counts as [`AuthMethod::PublicKey`]. Every method named
+    /// needs its handler configured, or the server fails to start.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use shenron::{Server, auth::AuthMethod};
+    /// let _server = Server::new()
+    ///     .pubkey_auth(|_, _| async { true })
+    ///     .password_auth(|_, _| async { true })
+    ///     .require_auth_methods([AuthMethod::PublicKey, AuthMethod::Password]);
+    /// ```
+    #[must_use]
+    pub fn require_auth_methods(mut self, methods: impl IntoIterator<Item = AuthMethod>) -> Self {
+        self.auth.required.push(methods.into_iter().collect());
+
+        self
+    }
+
     /// Constant delay before every *failed* auth attempt is answered.
     ///
     /// This is a brute-force throttle and timing-side-channel mitigation, not
@@ -338,6 +869,63 @@ impl Server {
         self
     }
 
+    /// Disconnect a client after this many failed authentication attempts.
+    ///
+    /// Every rejected password, public key, certificate, or
+    /// keyboard-interactive attempt counts; the `none` probe clients send to
+    /// discover methods does not. Once the limit is reached the connection is
+    /// dropped instead of answering, so a brute-forcer has to reconnect (and
+    /// redo the handshake) to keep guessing. Unlimited by default; OpenSSH's
+    /// `MaxAuthTries` defaults to 6.
+    #[must_use]
+    pub const fn max_auth_attempts(mut self, attempts: usize) -> Self {
+        self.max_auth_attempts = Some(attempts);
+
+        self
+    }
+
+    /// Slow down each failed auth attempt on a connection more than the last.
+    ///
+    /// [`auth_rejection_delay`](Self::auth_rejection_delay) is one constant
+    /// for every failure; a backoff starts short, so a mistyped password is
+    /// quickly retried, and grows for a client that keeps guessing. The
+    /// constant delay still applies as a floor, so lower it when using a
+    /// backoff — a rejection takes whichever is longer.
+    ///
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// # use shenron::{Server, auth::AuthBackoff};
+    /// let _server = Server::new()
+    ///     .password_auth(|_, _| async { false })
+    ///     .auth_rejection_delay(Duration::from_millis(100))
+    ///     .auth_backoff(AuthBackoff::new().max(Duration::from_secs(10)));
+    /// ```
+    #[must_use]
+    pub const fn auth_backoff(mut self, backoff: AuthBackoff) -> Self {
+        self.auth_backoff = Some(backoff);
+
+        self
+    }
+
+    /// Ban clients that keep failing auth, fail2ban-style.
+    ///
+    /// Unlike [`max_auth_attempts`](Self::max_auth_attempts), which only ends
+    /// one connection, a [`Lockout`] remembers failures across connections
+    /// and turns a banned client away before any auth handler runs.
+    ///
+    /// ```no_run
+    /// # use shenron::{Server, auth::Lockout};
+    /// let _server = Server::new()
+    ///     .password_auth(|_, _| async { false })
+    ///     .lockout(Lockout::new().threshold(5));
+    /// ```
+    #[must_use]
+    pub fn lockout(mut self, lockout: Lockout) -> Self {
+        self.lockout = Some(lockout);
+
+        self
+    }
+
     #[must_use]
     pub const fn inactivity_timeout(mut self, duration: Duration) -> Self {
         self.inactivity_timeout = Some(duration);
@@ -345,6 +933,91 @@ impl Server {
         self
     }
 
+    /// Route the subsystem `name` (`ssh -s name`) to its own handler.
+    ///
+    /// Sugar for [`with(Subsystem::new(name, terminal(handler)))`](crate::middleware::Subsystem):
+    /// it takes the same kind of function as [`app`](Self::app), and sessions
+    /// for anything else continue down the chain. Like any middleware, it
+    /// sees only what reaches it — register routes before `app`, and after the
+    /// middleware that should wrap them.
+    ///
+    /// ```no_run
+    /// # use shenron::{Server, Session};
+    /// # async fn metrics(session: &mut Session) -> shenron::Result { Ok(()) }
+    /// # async fn backup(session: &mut Session) -> shenron::Result { Ok(()) }
+    /// # async fn shell(session: &mut Session) -> shenron::Result { Ok(()) }
+    /// let _server = Server::new()
+    ///     .with(shenron::middleware::logging)
+    ///     .subsystem("metrics", metrics)
+    ///     .subsystem("backup", backup)
+    ///     .app(shell);
+    /// ```
+    #[must_use]
+    pub fn subsystem<F, R>(self, name: impl Into<String>, handler: F) -> Self
+    where
+        F: AsyncFn(&mut Session) -> R + Send + Sync + 'static,
+        for<'a> <F as std::ops::AsyncFnMut<(&'a mut Session,)>>::CallRefFuture<'a>: Send,
+        R: crate::IntoExit,
+    {
+        self.with(middleware::Subsystem::new(
+            name,
+            middleware::terminal(handler),
+        ))
+    }
+
+    /// Route exec requests for `program` (`ssh host deploy prod`) to their
+    /// own handler, which reads its arguments from
+    /// [`Session::command`].
+    ///
+    /// Sugar for [`with(Command::new(program, terminal(handler)))`](crate::middleware::Command).
+    /// Routes are middleware: everything registered before them — logging,
+    /// access control, rate limits — wraps each command. Pair with
+    /// [`command_fallback`](Self::command_fallback) for unknown commands.
+    ///
+    /// ```no_run
+    /// # use shenron::{Server, Session};
+    /// # async fn deploy(session: &mut Session) -> shenron::Result { Ok(()) }
+    /// # async fn status(session: &mut Session) -> shenron::Result { Ok(()) }
+    /// # async fn shell(session: &mut Session) -> shenron::Result { Ok(()) }
+    /// let _server = Server::new()
+    ///     .with(shenron::middleware::logging)
+    ///     .command("deploy", deploy)
+    ///     .command("status", status)
+    ///     .command_fallback(async |session: &mut Session| {
+    ///         let raw = session.raw_command().unwrap_or_default().to_string();
+    ///         session.write_stderr_str(&format!("unknown command: {raw}\n")).await?;
+    ///
+    ///         shenron::Result::Ok(127)
+    ///     })
+    ///     .app(shell);
+    /// ```
+    #[must_use]
+    pub fn command<F, R>(self, program: impl Into<String>, handler: F) -> Self
+    where
+        F: AsyncFn(&mut Session) -> R + Send + Sync + 'static,
+        for<'a> <F as std::ops::AsyncFnMut<(&'a mut Session,)>>::CallRefFuture<'a>: Send,
+        R: crate::IntoExit,
+    {
+        self.with(middleware::Command::new(
+            program,
+            middleware::terminal(handler),
+        ))
+    }
+
+    /// Handle every exec request no earlier [`command`](Self::command) route
+    /// matched. Shells and subsystems still continue to the app.
+    #[must_use]
+    pub fn command_fallback<F, R>(self, handler: F) -> Self
+    where
+        F: AsyncFn(&mut Session) -> R + Send + Sync + 'static,
+        for<'a> <F as std::ops::AsyncFnMut<(&'a mut Session,)>>::CallRefFuture<'a>: Send,
+        R: crate::IntoExit,
+    {
+        self.with(middleware::CommandFallback::new(middleware::terminal(
+            handler,
+        )))
+    }
+
     /// Add a terminal application as the innermost layer.
     ///
     /// Sugar for [`with(terminal(app))`](Self::with): the app is just a
@@ -364,6 +1037,121 @@ impl Server {
         self.with(middleware::terminal(app))
     }
 
+    /// Subscribe to the server's [`ServerEvent`]s: connections, auth
+    /// outcomes, and session starts and ends.
+    ///
+    /// Subscribe before [`serve`](Self::serve) (or [`spawn`](Self::spawn));
+    /// call it once per observer. A receiver that falls more than 1024 events
+    /// behind skips the oldest and sees
+    /// [`RecvError::Lagged`](tokio::sync::broadcast::error::RecvError::Lagged).
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use shenron::{Server, ServerEvent};
+    /// let server = Server::new();
+    /// let mut events = server.events();
+    ///
+    /// tokio::spawn(async move {
+    ///     while let Ok(event) = events.recv().await {
+    ///         if let ServerEvent::AuthFailed { user, remote_addr, .. } = event {
+    ///             eprintln!("failed login for {user} from {remote_addr}");
+    ///         }
+    ///     }
+    /// });
+    /// ```
+    #[must_use]
+    pub fn events(&self) -> broadcast::Receiver<ServerEvent> {
+        self.events.subscribe()
+    }
+
+    /// The [`Bus`] the server's sessions share, for code outside them to
+    /// publish to and subscribe to the same topics, e.g. a task feeding
+    /// a live dashboard.
+    #[must_use]
+    pub fn bus(&self) -> Bus {
+        self.bus.clone()
+    }
+
+    /// Call `hook` with every authentication decision — accepted, partial,
+    /// or rejected — along with the key fingerprint, client version, and
+    /// timing; for forwarding to a SIEM or audit log.
+    ///
+    /// It runs inline on the connection's task, so hand the event off (to a
+    /// channel, a non-blocking logger) rather than doing I/O in it. Unlike
+    /// [`events`](Self::events), nothing is dropped when a consumer lags.
+    ///
+    /// ```no_run
+    /// # use shenron::{AuthDecision, Server};
+    /// let _server = Server::new().on_auth_event(|event| {
+    ///     if let AuthDecision::Rejected { reason } = &event.decision {
+    ///         tracing::warn!(
+    ///             user = event.user,
+    ///             addr = %event.remote_addr,
+    ///             method = ?event.method,
+    ///             fingerprint = ?event.fingerprint,
+    ///             ?reason,
+    ///             "login rejected",
+    ///         );
+    ///     }
+    /// });
+    /// ```
+    #[must_use]
+    pub fn on_auth_event(mut self, hook: impl Fn(AuthEvent) + Send + Sync + 'static) -> Self {
+        self.auth_hook = Some(Arc::new(hook));
+
+        self
+    }
+
+    /// Let clients forward ports from the server back to themselves
+    /// (`ssh -R`), for tunnel servers in the style of ngrok.
+    ///
+    /// `approve` sees each [`ForwardRequest`] — who's asking and which
+    /// address and port they want — and returns whether to listen. For
+    /// approved ones the server binds the address and hands each
+    /// connection to the client over a `forwarded-tcpip` channel; the
+    /// listener lives until the client cancels it or disconnects. Without
+    /// an approver every request is refused. [`events`](Self::events)
+    /// reports forwards opening, connections arriving, and forwards
+    /// closing.
+    ///
+    /// ```no_run
+    /// # use shenron::Server;
+    /// // Only unprivileged ports, and only on loopback.
+    /// let _server = Server::new().remote_forwarding(|request| async move {
+    ///     request.address == "localhost" && (request.port == 0 || request.port >= 1024)
+    /// });
+    /// ```
+    #[must_use]
+    pub fn remote_forwarding<F, Fut>(mut self, approve: F) -> Self
+    where
+        F: Fn(ForwardRequest) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = bool> + Send + 'static,
+    {
+        self.forward_approver = Some(Arc::new(move |request| Box::pin(approve(request))));
+
+        self
+    }
+
+    /// Let sessions survive their client disconnecting, tmux-style: a
+    /// handler that calls [`Session::set_resumable`] keeps running, and
+    /// when the same user opens a shell again they're switched back to it,
+    /// with its screen redrawn, instead of starting a new session.
+    /// Exec requests and subsystems always start fresh. Requires the
+    /// `resume` feature.
+    ///
+    /// ```no_run
+    /// # use shenron::Server;
+    /// let _server = Server::new().resumable_sessions();
+    /// ```
+    #[must_use]
+    #[cfg(feature = "resume")]
+    pub const fn resumable_sessions(mut self) -> Self {
+        self.resumable = true;
+
+        self
+    }
+
     /// Set a graceful shutdown signal
     ///
     /// When the future completes, the server will stop accepting new connections.
@@ -397,13 +1185,99 @@ impl Server {
 
     /// Start the server and listen for connections
     ///
+    /// Shorthand for [`listen`](Self::listen) followed by
+    /// [`Listening::serve`].
+    ///
     /// # Errors
     ///
     /// Returns `Err` if
     /// - No bind address was specified
+    /// - A [required auth method](Self::require_auth_methods) has no handler
+    /// - [Open auth](Self::open_auth) is disabled and no handler is configured
+    /// - The [overflow reject message](OverflowPolicy::Reject) has a line
+    ///   starting with `SSH-`
     /// - A default host key had to be generated and writing it failed
     /// - The server failed to start
-    pub async fn serve(mut self) -> crate::Result<()> {
+    pub async fn serve(self) -> crate::Result<()> {
+        self.listen().await?.serve().await
+    }
+
+    /// Bind the listener and run the accept loop on a background task.
+    ///
+    /// Unlike [`serve`](Self::serve), this returns as soon as the listener is
+    /// bound, so the server can run alongside other services in one binary.
+    /// The returned [`ServerHandle`] reports the bound address and stops the
+    /// server on request; a configured
+    /// [`shutdown_signal`](Self::shutdown_signal) still applies too.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use shenron::{Server, Session};
+    /// # async fn app(session: &mut Session) -> shenron::Result {
+    /// #     Ok(())
+    /// # }
+    /// # async fn run() -> shenron::Result<()> {
+    /// let server = Server::new().bind("127.0.0.1:0").app(app).spawn().await?;
+    ///
+    /// println!("listening on {}", server.local_addr());
+    ///
+    /// // ... run other services ...
+    ///
+    /// server.shutdown();
+    /// server.stopped().await
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` under the same conditions as [`listen`](Self::listen)
+    pub async fn spawn(self) -> crate::Result<ServerHandle> {
+        Ok(self.listen().await?.spawn())
+    }
+
+    /// Bind the listener without accepting connections yet.
+    ///
+    /// Useful when binding to port 0: the returned [`Listening`] reports the
+    /// port the OS actually picked via [`local_addr`](Listening::local_addr)
+    /// before [`serve`](Listening::serve) starts the accept loop.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use shenron::{Server, Session};
+    /// # async fn app(session: &mut Session) -> shenron::Result {
+    /// #     Ok(())
+    /// # }
+    /// # async fn run() -> shenron::Result<()> {
+    /// let listening = Server::new().bind("127.0.0.1:0").app(app).listen().await?;
+    ///
+    /// println!("listening on {}", listening.local_addr());
+    ///
+    /// listening.serve().await
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if
+    /// - No bind address was specified
+    /// - A [required auth method](Self::require_auth_methods) has no handler
+    /// - [Open auth](Self::open_auth) is disabled and no handler is configured
+    /// - The [overflow reject message](OverflowPolicy::Reject) has a line
+    ///   starting with `SSH-`
+    /// - A default host key had to be generated and writing it failed
+    /// - The address could not be bound
+    pub async fn listen(mut self) -> crate::Result<Listening> {
+        if self.deny_open_auth && self.auth.is_empty() {
+            return Err(crate::Error::Config(
+                "no auth handlers configured and open auth is disabled".into(),
+            ));
+        }
+
+        self.auth.validate()?;
+        self.connections.overflow.validate()?;
+
         if self.keys.is_empty() {
             self = self.host_key_path(DEFAULT_HOST_KEY_PATH)?;
         }
@@ -414,32 +1288,43 @@ impl Server {
             .addr
             .ok_or_else(|| crate::Error::Config("No bind address specified".into()))?;
 
-        let handler = middleware::build_chain(std::mem::take(&mut self.middleware));
+        let listener = listener::bind(&addr, &self.tcp).await?;
+        let local_addr = listener.local_addr()?;
+
+        let handler = middleware::build_chain(
+            std::mem::take(&mut self.middleware),
+            self.middleware_timing.take(),
+        );
 
         let auth = Arc::new(self.auth);
-        let mut sh = ShenronServer {
+        let server = ShenronServer {
             handler,
             auth,
             banner: self.banner,
+            max_auth_attempts: self.max_auth_attempts,
+            backoff: self.auth_backoff,
+            auth_hook: self.auth_hook,
+            lockout: self.lockout.map(Arc::new),
+            reverse_dns: self.reverse_dns,
+            events: self.events,
+            forward_approver: self.forward_approver,
+            env: Arc::new(self.env),
+            #[cfg(feature = "resume")]
+            resumable: self.resumable.then(Arc::default),
+            broadcasts: Broadcasts::default(),
+            bus: self.bus,
         };
 
-        match self.shutdown {
-            Some(shutdown) => {
-                tokio::select! {
-                    result = sh.run_on_address(config, addr) => {
-                        result?;
-                    }
-                    () = shutdown => {
-                        tracing::info!("Shutdown signal received");
-                    }
-                }
-            }
-            None => {
-                sh.run_on_address(config, addr).await?;
-            }
-        }
-
-        Ok(())
+        Ok(Listening {
+            listener,
+            local_addr,
+            config,
+            server,
+            shutdown: self.shutdown,
+            tcp: self.tcp,
+            pre_auth: self.pre_auth,
+            connections: self.connections,
+        })
     }
 
     fn config(&self) -> Arc<Config> {
@@ -448,6 +1333,22 @@ impl Server {
         config.keys.clone_from(&self.keys);
         config.methods = self.auth.methods();
 
+        if self
+            .auth
+            .key_policy
+            .as_ref()
+            .is_some_and(|policy| !policy.advertises_rsa_sha1())
+        {
+            config.preferred.key = config
+                .preferred
+                .key
+                .iter()
+                .filter(|algorithm| **algorithm != Algorithm::Rsa { hash: None })
+                .cloned()
+                .collect::<Vec<_>>()
+                .into();
+        }
+
         if let Some(delay) = self.auth_rejection_delay {
             config.auth_rejection_time = delay;
         }
@@ -466,6 +1367,149 @@ impl Server {
             config.keepalive_max = max;
         }
 
+        config.nodelay = self.nodelay;
+
         Arc::new(config)
     }
 }
+
+/// A [`Server`] whose listener is bound but not yet accepting connections.
+///
+/// Built by [`Server::listen`].
+pub struct Listening {
+    listener: TcpListener,
+    local_addr: SocketAddr,
+    config: Arc<Config>,
+    server: ShenronServer,
+    shutdown: Option<ShutdownFuture>,
+    tcp: TcpOptions,
+    pre_auth: PreAuthLimits,
+    connections: ConnectionLimits,
+}
+
+impl Listening {
+    /// The address the listener is bound to, including the OS-assigned port
+    /// when bound to port 0.
+    #[must_use]
+    pub const fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Run the accept loop on a background task. See [`Server::spawn`].
+    #[must_use]
+    pub fn spawn(mut self) -> ServerHandle {
+        let stop = Arc::new(Notify::new());
+        let notified = Arc::clone(&stop);
+        let signal = self.shutdown.take();
+
+        self.shutdown = Some(Box::pin(async move {
+            match signal {
+                Some(signal) => {
+                    tokio::select! {
+                        () = signal => {}
+                        () = notified.notified() => {}
+                    }
+                }
+                None => notified.notified().await,
+            }
+        }));
+
+        let local_addr = self.local_addr;
+        let broadcasts = self.server.broadcasts.clone();
+        let join = tokio::spawn(self.serve());
+
+        ServerHandle {
+            local_addr,
+            stop,
+            broadcasts,
+            join,
+        }
+    }
+
+    /// Run the accept loop until it fails or the
+    /// [`shutdown_signal`](Server::shutdown_signal) fires.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if accepting connections fails
+    pub async fn serve(self) -> crate::Result<()> {
+        let Self {
+            listener,
+            config,
+            server,
+            shutdown,
+            tcp,
+            pre_auth,
+            connections,
+            ..
+        } = self;
+
+        let shutdown = async move {
+            match shutdown {
+                Some(shutdown) => shutdown.await,
+                None => std::future::pending().await,
+            }
+        };
+
+        listener::serve(
+            listener,
+            config,
+            server,
+            tcp,
+            pre_auth,
+            connections,
+            shutdown,
+        )
+        .await?;
+
+        Ok(())
+    }
+}
+
+/// A server running on a background task, returned by [`Server::spawn`].
+///
+/// Dropping the handle leaves the server running; call
+/// [`shutdown`](Self::shutdown) to stop it.
+pub struct ServerHandle {
+    local_addr: SocketAddr,
+    stop: Arc<Notify>,
+    broadcasts: Broadcasts,
+    join: JoinHandle<crate::Result<()>>,
+}
+
+impl ServerHandle {
+    /// The address the listener is bound to, including the OS-assigned port
+    /// when bound to port 0.
+    #[must_use]
+    pub const fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Stop accepting new connections. Returns immediately; await
+    /// [`stopped`](Self::stopped) to wait for the accept loop to exit.
+    pub fn shutdown(&self) {
+        self.stop.notify_one();
+    }
+
+    /// Send `message` to every running session, e.g. to warn users before
+    /// maintenance. Sessions see it through
+    /// [`Session::broadcasts`](crate::Session::broadcasts); the
+    /// [`wall`](crate::middleware::wall) middleware shows it on their
+    /// terminals. Returns how many sessions were listening.
+    pub fn broadcast(&self, message: impl Into<String>) -> usize {
+        self.broadcasts.send(message.into())
+    }
+
+    /// Wait for the accept loop to exit, after [`shutdown`](Self::shutdown),
+    /// the [`shutdown_signal`](Server::shutdown_signal), or a failure.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if accepting connections failed or the accept loop
+    /// panicked
+    pub async fn stopped(self) -> crate::Result<()> {
+        self.join
+            .await
+            .map_err(|e| crate::Error::Panic(e.to_string()))?
+    }
+}
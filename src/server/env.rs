@@ -0,0 +1,92 @@
+use std::collections::HashMap;
+
+use crate::pattern;
+
+/// Client-controlled env vars are stored per channel; cap them so a hostile
+/// client can't grow memory without bound. Requests beyond the cap are
+/// silently dropped, like OpenSSH's `AcceptEnv` rejections.
+const MAX_ENV_VARS: usize = 128;
+
+/// Default for [`Server::max_env_bytes`](crate::Server::max_env_bytes).
+const DEFAULT_MAX_ENV_BYTES: usize = 64 * 1024;
+
+/// Which of a client's `env` requests a session keeps: the names
+/// [`Server::accept_env`](crate::Server::accept_env) allows, within the
+/// size cap.
+#[derive(Debug, Clone)]
+pub struct EnvPolicy {
+    /// Name patterns; `None` accepts any name.
+    accept: Option<Vec<String>>,
+    max_bytes: usize,
+}
+
+impl Default for EnvPolicy {
+    fn default() -> Self {
+        Self {
+            accept: None,
+            max_bytes: DEFAULT_MAX_ENV_BYTES,
+        }
+    }
+}
+
+impl EnvPolicy {
+    pub fn accept(&mut self, patterns: impl IntoIterator<Item = String>) {
+        self.accept.get_or_insert_with(Vec::new).extend(patterns);
+    }
+
+    pub const fn set_max_bytes(&mut self, max_bytes: usize) {
+        self.max_bytes = max_bytes;
+    }
+
+    /// Whether `name=value` may join `env`; why not, if it may not.
+    pub fn check(
+        &self,
+        env: &HashMap<String, String>,
+        name: &str,
+        value: &str,
+    ) -> Result<(), &'static str> {
+        if let Some(accept) = &self.accept
+            && !accept.iter().any(|p| pattern::glob(p, name))
+        {
+            return Err("not accepted");
+        }
+
+        if !env.contains_key(name) && env.len() >= MAX_ENV_VARS {
+            return Err("too many variables");
+        }
+
+        let others: usize = env
+            .iter()
+            .filter(|(other, _)| *other != name)
+            .map(|(other, value)| other.len() + value.len())
+            .sum();
+
+        if others + name.len() + value.len() > self.max_bytes {
+            return Err("environment too large");
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_matching_names_within_the_cap() {
+        let mut policy = EnvPolicy::default();
+        policy.accept(["LANG".to_string(), "LC_*".to_string()]);
+        policy.set_max_bytes(16);
+
+        let mut env = HashMap::new();
+        assert!(policy.check(&env, "LANG", "C").is_ok());
+        assert!(policy.check(&env, "LC_ALL", "C").is_ok());
+        assert!(policy.check(&env, "PATH", "/bin").is_err());
+        assert!(policy.check(&env, "LANG", "en_US.UTF-8 long").is_err());
+
+        env.insert("LC_ALL".to_string(), "en_US.UTF-8".to_string());
+        assert!(policy.check(&env, "LANG", "C").is_err());
+        assert!(policy.check(&env, "LC_ALL", "C.UTF-8").is_ok());
+    }
+}
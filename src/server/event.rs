@@ -0,0 +1,171 @@
+use std::{
+    net::SocketAddr,
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use russh::keys::ssh_key::Fingerprint;
+use tokio::sync::broadcast;
+
+use crate::{ConnectionId, SessionId, SessionKind, auth::AuthMethod};
+
+/// Events buffered per subscriber before it starts missing them.
+const EVENT_CAPACITY: usize = 1024;
+
+/// Something that happened on the server, delivered to every receiver from
+/// [`Server::events`](crate::Server::events).
+///
+/// Meant for observers — dashboards, metrics, alerting — that want to watch
+/// the server without sitting in the middleware chain.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub enum ServerEvent {
+    /// A TCP connection was accepted.
+    ConnectionOpened {
+        remote_addr: SocketAddr,
+        connection_id: ConnectionId,
+    },
+    /// A connection ended, for whatever reason.
+    ConnectionClosed {
+        remote_addr: SocketAddr,
+        connection_id: ConnectionId,
+    },
+    /// A client authenticated.
+    AuthSucceeded {
+        user: String,
+        remote_addr: SocketAddr,
+        method: AuthMethod,
+    },
+    /// One factor of a multi-factor login passed; the client must still
+    /// complete the rest of a set from
+    /// [`Server::require_auth_methods`](crate::Server::require_auth_methods).
+    AuthPartial {
+        user: String,
+        remote_addr: SocketAddr,
+        method: AuthMethod,
+    },
+    /// An authentication attempt was rejected. The `none` probe clients send
+    /// to discover methods is not reported.
+    AuthFailed {
+        user: String,
+        remote_addr: SocketAddr,
+        method: AuthMethod,
+        /// Why the handler rejected it, if it said; see
+        /// [`Auth::reason`](crate::Auth::reason).
+        reason: Option<String>,
+    },
+    /// A client failed auth often enough to be banned by
+    /// [`Server::lockout`](crate::Server::lockout).
+    LockedOut {
+        user: String,
+        remote_addr: SocketAddr,
+        duration: Duration,
+    },
+    /// A session channel started running the middleware chain.
+    SessionStarted {
+        session_id: SessionId,
+        connection_id: ConnectionId,
+        user: String,
+        remote_addr: SocketAddr,
+        kind: SessionKind,
+    },
+    /// A session's chain returned and its channel was closed.
+    SessionEnded {
+        session_id: SessionId,
+        connection_id: ConnectionId,
+        user: String,
+        remote_addr: SocketAddr,
+        exit_code: u32,
+        duration: Duration,
+    },
+    /// The server started listening for a client's remote forward
+    /// (`ssh -R`), approved by
+    /// [`Server::remote_forwarding`](crate::Server::remote_forwarding).
+    /// `port` is the one bound, even when the client asked for 0.
+    ForwardOpened {
+        connection_id: ConnectionId,
+        address: String,
+        port: u32,
+    },
+    /// A connection arrived on a remote forward and is being handed to the
+    /// client.
+    ForwardConnection {
+        connection_id: ConnectionId,
+        address: String,
+        port: u32,
+        originator: SocketAddr,
+    },
+    /// A remote forward stopped listening: the client cancelled it or
+    /// disconnected, or its listener kept failing to accept.
+    ForwardClosed {
+        connection_id: ConnectionId,
+        address: String,
+        port: u32,
+    },
+}
+
+/// One authentication decision, passed to
+/// [`Server::on_auth_event`](crate::Server::on_auth_event) — the fields a
+/// SIEM or audit log wants, in one place.
+///
+/// Like [`ServerEvent::AuthFailed`], rejected `none` probes aren't
+/// reported.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct AuthEvent {
+    /// The connection the attempt arrived on; sessions it goes on to open
+    /// report the same [`Session::connection_id`](crate::Session::connection_id).
+    pub connection_id: ConnectionId,
+    pub user: String,
+    pub remote_addr: SocketAddr,
+    pub method: AuthMethod,
+    pub decision: AuthDecision,
+    /// SHA-256 fingerprint of the key tried, for public-key and certificate
+    /// attempts (a certificate's is its inner key's).
+    pub fingerprint: Option<Fingerprint>,
+    /// The client's identification string, e.g. `SSH-2.0-OpenSSH_9.6`.
+    pub client_version: String,
+    /// When the decision was made.
+    pub at: SystemTime,
+    /// How long the attempt took, from its request arriving to the
+    /// decision — handler time plus any lookups, not counting rejection
+    /// delays.
+    pub elapsed: Duration,
+}
+
+/// What became of the attempt an [`AuthEvent`] reports.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum AuthDecision {
+    /// The client is in.
+    Accepted,
+    /// One factor of several passed.
+    Partial,
+    /// Rejected, with the handler's [`Auth::reason`](crate::Auth::reason)
+    /// if it gave one.
+    Rejected { reason: Option<String> },
+}
+
+/// Callback registered with [`Server::on_auth_event`](crate::Server::on_auth_event).
+pub type AuthHook = Arc<dyn Fn(AuthEvent) + Send + Sync>;
+
+/// The sending half shared by the server, its connections, and sessions.
+/// Sends with no subscribers are dropped.
+#[derive(Clone)]
+pub struct ServerEvents(broadcast::Sender<ServerEvent>);
+
+impl Default for ServerEvents {
+    fn default() -> Self {
+        Self(broadcast::Sender::new(EVENT_CAPACITY))
+    }
+}
+
+impl ServerEvents {
+    pub fn subscribe(&self) -> broadcast::Receiver<ServerEvent> {
+        self.0.subscribe()
+    }
+
+    pub fn emit(&self, event: ServerEvent) {
+        let _ = self.0.send(event);
+    }
+}
@@ -0,0 +1,239 @@
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use russh::server::Handle;
+use tokio::{net::TcpListener, task::JoinHandle, time::Instant};
+
+use crate::{
+    BoxFuture, ConnectionId,
+    server::{ServerEvent, ServerEvents},
+};
+
+/// A client asking the server to listen on its behalf (`ssh -R`), passed to
+/// the approver set with
+/// [`Server::remote_forwarding`](crate::Server::remote_forwarding).
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct ForwardRequest {
+    pub user: String,
+    pub remote_addr: SocketAddr,
+    pub connection_id: ConnectionId,
+    /// The address to bind, as the client sent it. Empty, `*` and `0.0.0.0`
+    /// mean every interface; `localhost` means loopback.
+    pub address: String,
+    /// The port to bind; 0 lets the server pick one and tell the client.
+    pub port: u32,
+}
+
+/// Decides which [`ForwardRequest`]s the server listens for.
+pub type ForwardApprover = Arc<dyn Fn(ForwardRequest) -> BoxFuture<bool> + Send + Sync>;
+
+/// Pauses between attempts while a forward's listener fails to accept,
+/// doubling from the first to the second.
+const MIN_ACCEPT_BACKOFF: Duration = Duration::from_millis(10);
+const MAX_ACCEPT_BACKOFF: Duration = Duration::from_secs(1);
+
+/// How long a forward's listener may keep failing to accept before it's
+/// closed.
+const ACCEPT_GIVE_UP: Duration = Duration::from_secs(60);
+
+type Listeners = Arc<Mutex<HashMap<(String, u32), JoinHandle<()>>>>;
+
+/// A connection's remote forwards: one accept loop per bound address,
+/// stopped when cancelled, when the connection goes away, or when its
+/// listener keeps failing.
+#[derive(Default)]
+pub struct RemoteForwards {
+    listeners: Listeners,
+}
+
+impl RemoteForwards {
+    /// Bind `address:port` and forward each connection to it back to the
+    /// client over a `forwarded-tcpip` channel. Returns the bound port.
+    pub async fn open(
+        &mut self,
+        address: &str,
+        port: u32,
+        handle: Handle,
+        connection_id: ConnectionId,
+        events: ServerEvents,
+    ) -> crate::Result<u32> {
+        let host = match address {
+            "" | "*" => "0.0.0.0",
+            "localhost" => "127.0.0.1",
+            other => other,
+        };
+
+        let listener = TcpListener::bind((host, u16::try_from(port)?)).await?;
+        let port = u32::from(listener.local_addr()?.port());
+        let address = address.to_string();
+
+        events.emit(ServerEvent::ForwardOpened {
+            connection_id,
+            address: address.clone(),
+            port,
+        });
+
+        // Held until the task is in the map, so a forward that fails at
+        // once still finds its own entry to remove.
+        let mut listeners = self.listeners.lock().expect("forwards poisoned");
+
+        let task = tokio::spawn(accept(
+            listener,
+            handle,
+            connection_id,
+            events,
+            address.clone(),
+            port,
+            Arc::clone(&self.listeners),
+        ));
+
+        if let Some(previous) = listeners.insert((address, port), task) {
+            previous.abort();
+        }
+
+        drop(listeners);
+
+        Ok(port)
+    }
+
+    /// Stop listening on `address:port`. Connections already forwarded run
+    /// on until either side closes them, as in OpenSSH. `false` if there was
+    /// no such forward.
+    pub fn cancel(&mut self, address: &str, port: u32) -> bool {
+        self.listeners
+            .lock()
+            .expect("forwards poisoned")
+            .remove(&(address.to_string(), port))
+            .map(|task| task.abort())
+            .is_some()
+    }
+
+    /// The forwards still open, for reporting them closed.
+    pub fn addresses(&self) -> Vec<(String, u32)> {
+        self.listeners
+            .lock()
+            .expect("forwards poisoned")
+            .keys()
+            .cloned()
+            .collect()
+    }
+}
+
+impl Drop for RemoteForwards {
+    fn drop(&mut self) {
+        for task in self.listeners.lock().expect("forwards poisoned").values() {
+            task.abort();
+        }
+    }
+}
+
+/// Forward each connection to `listener` back to the client. Failures to
+/// accept, like running out of file descriptors, are retried with a growing
+/// pause; after [`ACCEPT_GIVE_UP`] of nothing but failures the forward is
+/// dropped and reported closed.
+async fn accept(
+    listener: TcpListener,
+    handle: Handle,
+    connection_id: ConnectionId,
+    events: ServerEvents,
+    address: String,
+    port: u32,
+    listeners: Listeners,
+) {
+    let mut backoff = MIN_ACCEPT_BACKOFF;
+    let mut failing_since = None;
+
+    loop {
+        match listener.accept().await {
+            Ok((stream, originator)) => {
+                backoff = MIN_ACCEPT_BACKOFF;
+                failing_since = None;
+
+                events.emit(ServerEvent::ForwardConnection {
+                    connection_id,
+                    address: address.clone(),
+                    port,
+                    originator,
+                });
+
+                tokio::spawn(relay(
+                    stream,
+                    originator,
+                    handle.clone(),
+                    address.clone(),
+                    port,
+                ));
+            }
+            Err(e) => {
+                let since = *failing_since.get_or_insert_with(Instant::now);
+
+                if since.elapsed() >= ACCEPT_GIVE_UP {
+                    tracing::warn!("closing remote forward on {address}:{port}: {e}");
+
+                    break;
+                }
+
+                tracing::warn!("remote forward on {address}:{port} failed to accept: {e}");
+
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(MAX_ACCEPT_BACKOFF);
+            }
+        }
+    }
+
+    let key = (address, port);
+    let mut listeners = listeners.lock().expect("forwards poisoned");
+
+    // A forward replaced on the same address has its own entry by now.
+    if listeners
+        .get(&key)
+        .is_some_and(|task| task.id() == tokio::task::id())
+    {
+        listeners.remove(&key);
+        drop(listeners);
+
+        let (address, port) = key;
+
+        events.emit(ServerEvent::ForwardClosed {
+            connection_id,
+            address,
+            port,
+        });
+    }
+}
+
+async fn relay(
+    mut stream: tokio::net::TcpStream,
+    originator: SocketAddr,
+    handle: Handle,
+    address: String,
+    port: u32,
+) {
+    let channel = match handle
+        .channel_open_forwarded_tcpip(
+            address,
+            port,
+            originator.ip().to_string(),
+            u32::from(originator.port()),
+        )
+        .await
+    {
+        Ok(channel) => channel,
+        Err(e) => {
+            tracing::debug!("client refused forwarded connection from {originator}: {e}");
+
+            return;
+        }
+    };
+
+    let mut channel = channel.into_stream();
+
+    if let Err(e) = tokio::io::copy_bidirectional(&mut stream, &mut channel).await {
+        tracing::debug!("forwarded connection from {originator} ended: {e}");
+    }
+}
@@ -0,0 +1,171 @@
+const SSH_MSG_KEXINIT: u8 = 20;
+const COOKIE_LEN: usize = 16;
+
+/// The algorithms a client proposed in its first key exchange message, and
+/// the [HASSH](https://github.com/salesforce/hassh) fingerprint of them.
+///
+/// Clients built on the same SSH library propose the same lists, so the
+/// fingerprint identifies the software behind a connection whatever its
+/// version string claims: handy for spotting scanners and bots. Like the
+/// version string it's client-supplied, so good for heuristics and logs,
+/// not for trust. The fingerprint itself needs the `hassh` feature; the
+/// algorithm lists are always there.
+///
+/// Available before auth through
+/// [`AuthContext::client_fingerprint`](crate::auth::AuthContext::client_fingerprint)
+/// and afterwards through
+/// [`Session::client_fingerprint`](crate::Session::client_fingerprint).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientFingerprint {
+    kex: String,
+    host_key: String,
+    ciphers: String,
+    macs: String,
+    compression: String,
+    #[cfg(feature = "hassh")]
+    hassh: String,
+}
+
+impl ClientFingerprint {
+    /// Read a `SSH_MSG_KEXINIT` payload; `None` if it isn't one.
+    pub(crate) fn parse(payload: &[u8]) -> Option<Self> {
+        let (&kind, mut rest) = payload.split_first()?;
+
+        if kind != SSH_MSG_KEXINIT {
+            return None;
+        }
+
+        rest = rest.get(COOKIE_LEN..)?;
+
+        let mut lists = Vec::with_capacity(7);
+
+        for _ in 0..7 {
+            let (len, tail) = rest.split_first_chunk::<4>()?;
+            let len = u32::from_be_bytes(*len) as usize;
+            let (list, tail) = tail.split_at_checked(len)?;
+
+            lists.push(String::from_utf8_lossy(list).into_owned());
+            rest = tail;
+        }
+
+        // Server-to-client lists sit between the client-to-server ones.
+        let [kex, host_key, ciphers, _, macs, _, compression] =
+            <[String; 7]>::try_from(lists).ok()?;
+
+        #[cfg(feature = "hassh")]
+        let hassh = format!(
+            "{:x}",
+            md5::compute(format!("{kex};{ciphers};{macs};{compression}"))
+        );
+
+        Some(Self {
+            kex,
+            host_key,
+            ciphers,
+            macs,
+            compression,
+            #[cfg(feature = "hassh")]
+            hassh,
+        })
+    }
+
+    /// The HASSH fingerprint: the MD5, in hex, of
+    /// [`hassh_algorithms`](Self::hassh_algorithms). Requires the `hassh`
+    /// feature.
+    #[cfg(feature = "hassh")]
+    #[must_use]
+    pub fn hassh(&self) -> &str {
+        &self.hassh
+    }
+
+    /// The string HASSH hashes: the key exchange, cipher, MAC and
+    /// compression lists, joined with `;`.
+    #[must_use]
+    pub fn hassh_algorithms(&self) -> String {
+        format!(
+            "{};{};{};{}",
+            self.kex, self.ciphers, self.macs, self.compression
+        )
+    }
+
+    /// Key exchange algorithms, comma-separated in the client's order.
+    #[must_use]
+    pub fn kex_algorithms(&self) -> &str {
+        &self.kex
+    }
+
+    /// Host key algorithms the client accepts.
+    #[must_use]
+    pub fn host_key_algorithms(&self) -> &str {
+        &self.host_key
+    }
+
+    /// Client-to-server ciphers.
+    #[must_use]
+    pub fn ciphers(&self) -> &str {
+        &self.ciphers
+    }
+
+    /// Client-to-server MACs.
+    #[must_use]
+    pub fn macs(&self) -> &str {
+        &self.macs
+    }
+
+    /// Client-to-server compression methods.
+    #[must_use]
+    pub fn compression(&self) -> &str {
+        &self.compression
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kexinit(lists: [&str; 10]) -> Vec<u8> {
+        let mut payload = vec![SSH_MSG_KEXINIT];
+        payload.extend([0u8; COOKIE_LEN]);
+
+        for list in lists {
+            payload.extend(u32::try_from(list.len()).expect("len").to_be_bytes());
+            payload.extend(list.as_bytes());
+        }
+
+        payload.extend([0, 0, 0, 0, 0]);
+
+        payload
+    }
+
+    #[test]
+    fn hassh_covers_the_client_to_server_lists() {
+        let payload = kexinit([
+            "curve25519-sha256",
+            "ssh-ed25519",
+            "aes128-ctr",
+            "aes256-ctr",
+            "hmac-sha2-256",
+            "hmac-sha2-512",
+            "none",
+            "zlib",
+            "",
+            "",
+        ]);
+
+        let fingerprint = ClientFingerprint::parse(&payload).expect("kexinit");
+
+        assert_eq!(
+            fingerprint.hassh_algorithms(),
+            "curve25519-sha256;aes128-ctr;hmac-sha2-256;none"
+        );
+        #[cfg(feature = "hassh")]
+        assert_eq!(
+            fingerprint.hassh(),
+            format!("{:x}", md5::compute(fingerprint.hassh_algorithms()))
+        );
+        assert_eq!(fingerprint.host_key_algorithms(), "ssh-ed25519");
+
+        assert!(ClientFingerprint::parse(&payload[..30]).is_none());
+        assert!(ClientFingerprint::parse(&[21]).is_none());
+    }
+}
@@ -0,0 +1,753 @@
+use std::{
+    io,
+    net::SocketAddr,
+    pin::Pin,
+    sync::{Arc, OnceLock},
+    task::{Context, Poll, ready},
+    time::Duration,
+};
+
+use russh::{
+    Disconnect,
+    server::{Config, Handle, Server as _, run_stream},
+};
+use socket2::{SockRef, TcpKeepalive};
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, ReadBuf},
+    net::{TcpListener, TcpSocket, TcpStream},
+    sync::{OwnedSemaphorePermit, Semaphore, watch},
+    time::Instant,
+};
+
+use crate::server::{ClientFingerprint, ShenronHandler, ShenronServer};
+
+/// Pending-connection queue length when none is configured. Matches tokio's
+/// `TcpListener::bind`.
+const DEFAULT_BACKLOG: u32 = 1024;
+
+/// Socket-level options for the listener and every accepted connection.
+#[derive(Clone, Copy)]
+pub struct TcpOptions {
+    pub keepalive: Option<Duration>,
+    pub backlog: u32,
+}
+
+impl Default for TcpOptions {
+    fn default() -> Self {
+        Self {
+            keepalive: None,
+            backlog: DEFAULT_BACKLOG,
+        }
+    }
+}
+
+/// Guardrails for connections that have not authenticated yet. Unset fields
+/// are unlimited.
+#[derive(Clone, Copy, Default)]
+pub struct PreAuthLimits {
+    pub max_connections: Option<usize>,
+    pub timeout: Option<Duration>,
+    pub max_bytes: Option<u64>,
+}
+
+/// What the accept loop does with a new connection while
+/// [`max_connections`](crate::Server::max_connections) are already open.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "config",
+    derive(serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+#[non_exhaustive]
+pub enum OverflowPolicy {
+    /// Stop accepting until a connection closes. New clients wait in the
+    /// kernel's listen backlog, so nothing is spent on them until there's
+    /// room.
+    #[default]
+    Queue,
+    /// Accept, send this line, and close. It goes out before the SSH version
+    /// exchange (RFC 4253 allows such lines); OpenSSH shows it with `-v`.
+    /// No line of it may start with `SSH-`.
+    Reject(String),
+    /// Accept and close immediately.
+    Drop,
+}
+
+impl OverflowPolicy {
+    /// Check a reject message can't be taken for the server's version line.
+    pub(crate) fn validate(&self) -> crate::Result {
+        if let Self::Reject(message) = self
+            && message.lines().any(|line| line.starts_with("SSH-"))
+        {
+            return Err(crate::Error::Config(
+                "overflow reject message lines must not start with \"SSH-\"".into(),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// How long a rejected connection gets to take its reject line and close.
+const OVERFLOW_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Most rejected connections being sent their reject line at once; past
+/// this, a flood's extra connections are dropped without one.
+const MAX_REJECTING: usize = 64;
+
+/// Cap on open connections, authenticated or not.
+#[derive(Clone, Default)]
+pub struct ConnectionLimits {
+    pub max: Option<usize>,
+    pub overflow: OverflowPolicy,
+}
+
+/// Bind the first resolved address that accepts a listener, like
+/// `TcpListener::bind`, but with a configurable backlog.
+pub async fn bind(addr: &str, options: &TcpOptions) -> io::Result<TcpListener> {
+    let mut last_err = None;
+
+    for addr in tokio::net::lookup_host(addr).await? {
+        match bind_one(addr, options) {
+            Ok(listener) => return Ok(listener),
+            Err(e) => last_err = Some(e),
+        }
+    }
+
+    Err(last_err.unwrap_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "could not resolve to any address",
+        )
+    }))
+}
+
+fn bind_one(addr: SocketAddr, options: &TcpOptions) -> io::Result<TcpListener> {
+    let socket = if addr.is_ipv4() {
+        TcpSocket::new_v4()?
+    } else {
+        TcpSocket::new_v6()?
+    };
+
+    // Same as std/tokio: lets a restarted server rebind while old connections
+    // sit in TIME_WAIT.
+    #[cfg(unix)]
+    socket.set_reuseaddr(true)?;
+
+    socket.bind(addr)?;
+    socket.listen(options.backlog)
+}
+
+/// Apply per-connection options, including russh's [`Config::nodelay`],
+/// which only its own accept loop applies and `run_stream` doesn't. Failures
+/// are logged, not fatal: a socket that refuses `TCP_NODELAY` still carries a
+/// working SSH session.
+fn configure(stream: &TcpStream, config: &Config, options: &TcpOptions) {
+    if config.nodelay
+        && let Err(e) = stream.set_nodelay(true)
+    {
+        tracing::warn!("set_nodelay failed: {e}");
+    }
+
+    if let Some(time) = options.keepalive {
+        let keepalive = TcpKeepalive::new().with_time(time);
+
+        if let Err(e) = SockRef::from(stream).set_tcp_keepalive(&keepalive) {
+            tracing::warn!("set_tcp_keepalive failed: {e}");
+        }
+    }
+}
+
+/// Accept connections until accepting fails or `shutdown` completes.
+///
+/// On shutdown every live connection is disconnected: each watches the `stop`
+/// channel, which closes when this function returns.
+pub async fn serve(
+    listener: TcpListener,
+    config: Arc<Config>,
+    mut server: ShenronServer,
+    options: TcpOptions,
+    limits: PreAuthLimits,
+    connections: ConnectionLimits,
+    shutdown: impl Future<Output = ()>,
+) -> io::Result<()> {
+    let (_stop, stopped) = watch::channel(());
+    let pre_auth = limits
+        .max_connections
+        .map(|max| Arc::new(Semaphore::new(max)));
+    let open = connections.max.map(|max| Arc::new(Semaphore::new(max)));
+    let rejecting = Arc::new(Semaphore::new(MAX_REJECTING));
+
+    let accept = async {
+        loop {
+            let (stream, peer, slot) =
+                admit(&listener, open.as_ref(), &connections.overflow, &rejecting).await?;
+
+            // Like sshd's `MaxStartups`: when the pre-auth pool is full, new
+            // connections are dropped before any SSH state is allocated.
+            let permit = match pre_auth.as_ref().map(|s| Arc::clone(s).try_acquire_owned()) {
+                Some(Err(_)) => {
+                    tracing::debug!(%peer, "pre-auth connection limit reached, dropping");
+
+                    continue;
+                }
+                Some(Ok(permit)) => Some(permit),
+                None => None,
+            };
+
+            let handler = server.new_client(Some(peer));
+            let handler = match stream.local_addr() {
+                Ok(local) => handler.with_local_addr(local),
+                Err(_) => handler,
+            };
+
+            tokio::spawn(connection(
+                stream,
+                Arc::clone(&config),
+                handler,
+                Setup {
+                    options,
+                    limits,
+                    permit,
+                    slot,
+                },
+                stopped.clone(),
+            ));
+        }
+    };
+
+    tokio::select! {
+        result = accept => result,
+        () = shutdown => {
+            tracing::info!("Shutdown signal received");
+
+            Ok(())
+        }
+    }
+}
+
+/// Accept the next connection that fits under the open-connection cap,
+/// along with its slot. Over-cap connections are turned away here per
+/// `policy`, except under [`OverflowPolicy::Queue`], which waits for a slot
+/// *before* accepting so the kernel holds the backlog instead of us.
+async fn admit(
+    listener: &TcpListener,
+    open: Option<&Arc<Semaphore>>,
+    policy: &OverflowPolicy,
+    rejecting: &Arc<Semaphore>,
+) -> io::Result<(TcpStream, SocketAddr, Option<OwnedSemaphorePermit>)> {
+    let Some(open) = open else {
+        let (stream, peer) = listener.accept().await?;
+
+        return Ok((stream, peer, None));
+    };
+
+    if *policy == OverflowPolicy::Queue {
+        let slot = Arc::clone(open)
+            .acquire_owned()
+            .await
+            .expect("connection semaphore is never closed");
+        let (stream, peer) = listener.accept().await?;
+
+        return Ok((stream, peer, Some(slot)));
+    }
+
+    loop {
+        let (stream, peer) = listener.accept().await?;
+
+        if let Ok(slot) = Arc::clone(open).try_acquire_owned() {
+            return Ok((stream, peer, Some(slot)));
+        }
+
+        tracing::debug!(%peer, "connection limit reached, rejecting");
+        overflow(stream, policy, rejecting);
+    }
+}
+
+/// Turn away a connection accepted over the limit. The reject line goes
+/// out on a task of its own, so a client that won't read it can't hold up
+/// the accept loop, and is given up on after [`OVERFLOW_TIMEOUT`]. With
+/// [`MAX_REJECTING`] such tasks already running, the connection is just
+/// dropped.
+fn overflow(mut stream: TcpStream, policy: &OverflowPolicy, rejecting: &Arc<Semaphore>) {
+    let OverflowPolicy::Reject(message) = policy else {
+        return;
+    };
+
+    let Ok(permit) = Arc::clone(rejecting).try_acquire_owned() else {
+        tracing::debug!("too many rejections in flight, dropping");
+
+        return;
+    };
+
+    let line = format!("{message}\r\n");
+
+    tokio::spawn(async move {
+        let _permit = permit;
+
+        let reject = async {
+            stream.write_all(line.as_bytes()).await?;
+
+            // Closing with the client's version string still unread makes
+            // the kernel answer with a reset, which can destroy the message
+            // in flight. Finish the write side and discard whatever has
+            // arrived first.
+            stream.shutdown().await?;
+
+            let mut discard = [0u8; 256];
+            while stream.read(&mut discard).await? > 0 {}
+
+            io::Result::Ok(())
+        };
+
+        match tokio::time::timeout(OVERFLOW_TIMEOUT, reject).await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => tracing::debug!("failed to send overflow message: {e}"),
+            Err(_) => tracing::debug!("overflow message timed out"),
+        }
+    });
+}
+
+/// Per-connection settings carried from the accept loop into its task.
+struct Setup {
+    options: TcpOptions,
+    limits: PreAuthLimits,
+    /// Slot in the pre-auth pool, released once the connection authenticates.
+    permit: Option<OwnedSemaphorePermit>,
+    /// Slot in the open-connection pool, held until the task ends.
+    slot: Option<OwnedSemaphorePermit>,
+}
+
+async fn connection(
+    stream: TcpStream,
+    config: Arc<Config>,
+    handler: ShenronHandler,
+    setup: Setup,
+    mut stopped: watch::Receiver<()>,
+) {
+    let Setup {
+        options,
+        limits,
+        permit,
+        slot: _slot,
+    } = setup;
+
+    configure(&stream, &config, &options);
+
+    let mut authenticated = handler.authenticated();
+    let deadline = limits.timeout.map(|timeout| Instant::now() + timeout);
+    let stream = PreAuthStream::new(stream, limits.max_bytes, authenticated.clone())
+        .client_hello(handler.client_version(), handler.client_fingerprint());
+
+    let session = match until(deadline, run_stream(config, stream, handler)).await {
+        Some(Ok(session)) => session,
+        Some(Err(e)) => {
+            tracing::debug!("connection setup failed: {e}");
+
+            return;
+        }
+        None => {
+            tracing::debug!("pre-auth timeout during handshake");
+
+            return;
+        }
+    };
+
+    let handle = session.handle();
+    let mut session = std::pin::pin!(session);
+
+    let authenticating = async { authenticated.wait_for(|done| *done).await.is_ok() };
+
+    tokio::select! {
+        done = until(deadline, authenticating) => {
+            if done.is_none() {
+                tracing::debug!("pre-auth timeout, disconnecting");
+                disconnect(&handle, "authentication timed out").await;
+
+                return;
+            }
+
+            drop(permit);
+        }
+        _ = stopped.changed() => {
+            disconnect(&handle, "server shutting down").await;
+
+            return;
+        }
+        result = &mut session => {
+            log_closed(result);
+
+            return;
+        }
+    }
+
+    tokio::select! {
+        _ = stopped.changed() => disconnect(&handle, "server shutting down").await,
+        result = session => log_closed(result),
+    }
+}
+
+/// Await `fut`, giving up at `deadline` if one is set.
+async fn until<T>(deadline: Option<Instant>, fut: impl Future<Output = T>) -> Option<T> {
+    match deadline {
+        Some(deadline) => tokio::time::timeout_at(deadline, fut).await.ok(),
+        None => Some(fut.await),
+    }
+}
+
+async fn disconnect(handle: &Handle, reason: &str) {
+    if handle
+        .disconnect(Disconnect::ByApplication, reason.into(), String::new())
+        .await
+        .is_err()
+    {
+        tracing::debug!("failed to send disconnect message");
+    }
+}
+
+fn log_closed(result: crate::Result<()>) {
+    if let Err(e) = result {
+        tracing::debug!("connection closed with error: {e}");
+    }
+}
+
+/// A connection's socket, failing reads once more than `remaining` bytes
+/// arrive before authentication. The error ends the session; after auth the
+/// stream is a plain passthrough.
+///
+/// It also picks the client's identification line and key exchange proposal
+/// out of the first bytes read, since russh keeps them from auth handlers.
+struct PreAuthStream {
+    inner: TcpStream,
+    remaining: Option<u64>,
+    authenticated: watch::Receiver<bool>,
+    hello: Option<ClientHello>,
+}
+
+impl PreAuthStream {
+    const fn new(
+        inner: TcpStream,
+        max_bytes: Option<u64>,
+        authenticated: watch::Receiver<bool>,
+    ) -> Self {
+        Self {
+            inner,
+            remaining: max_bytes,
+            authenticated,
+            hello: None,
+        }
+    }
+
+    /// Store the client's identification line and key exchange fingerprint
+    /// in these slots once they arrive.
+    fn client_hello(
+        mut self,
+        version: Arc<OnceLock<String>>,
+        fingerprint: Arc<OnceLock<ClientFingerprint>>,
+    ) -> Self {
+        self.hello = Some(ClientHello::new(version, fingerprint));
+
+        self
+    }
+}
+
+/// Collects the client's `SSH-2.0-...` line, then its first binary
+/// packet: the cleartext `KEXINIT` a [`ClientFingerprint`] is read from.
+/// RFC 4253 caps the line at 255 bytes including the CRLF; a longer line, or
+/// a packet longer than [`MAX_PACKET`](Self::MAX_PACKET), is left unrecorded.
+struct ClientHello {
+    line: Vec<u8>,
+    version: Arc<OnceLock<String>>,
+    /// `None` until the identification line is in.
+    packet: Option<Vec<u8>>,
+    fingerprint: Arc<OnceLock<ClientFingerprint>>,
+}
+
+impl ClientHello {
+    const MAX_LEN: usize = 255;
+    /// RFC 4253's minimum supported packet size, far more than any real
+    /// `KEXINIT` needs.
+    const MAX_PACKET: usize = 35_000;
+
+    const fn new(
+        version: Arc<OnceLock<String>>,
+        fingerprint: Arc<OnceLock<ClientFingerprint>>,
+    ) -> Self {
+        Self {
+            line: Vec::new(),
+            version,
+            packet: None,
+            fingerprint,
+        }
+    }
+
+    /// Feed freshly read bytes; `true` once there's nothing left to find.
+    fn feed(&mut self, mut bytes: &[u8]) -> bool {
+        while self.packet.is_none() {
+            let Some((&byte, rest)) = bytes.split_first() else {
+                return false;
+            };
+            bytes = rest;
+
+            if byte != b'\n' {
+                if self.line.len() >= Self::MAX_LEN {
+                    return true;
+                }
+
+                self.line.push(byte);
+
+                continue;
+            }
+
+            // Lines before the identification are allowed, and skipped.
+            if self.line.starts_with(b"SSH-") {
+                let line = self.line.strip_suffix(b"\r").unwrap_or(&self.line);
+                let _ = self.version.set(String::from_utf8_lossy(line).into_owned());
+
+                self.packet = Some(Vec::new());
+            }
+
+            self.line.clear();
+        }
+
+        let Some(packet) = &mut self.packet else {
+            return false;
+        };
+
+        packet.extend_from_slice(bytes);
+
+        let Some(len) = packet.first_chunk::<4>() else {
+            return false;
+        };
+        let len = u32::from_be_bytes(*len) as usize;
+
+        if len > Self::MAX_PACKET {
+            return true;
+        }
+
+        let Some(body) = packet.get(4..4 + len) else {
+            return false;
+        };
+
+        // A padding length byte, the payload, then that much padding.
+        if let Some((&padding, rest)) = body.split_first()
+            && let Some(payload) = rest.get(..rest.len().saturating_sub(padding.into()))
+            && let Some(fingerprint) = ClientFingerprint::parse(payload)
+        {
+            let _ = self.fingerprint.set(fingerprint);
+        }
+
+        true
+    }
+}
+
+impl AsyncRead for PreAuthStream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let before = buf.filled().len();
+
+        ready!(Pin::new(&mut self.inner).poll_read(cx, buf))?;
+
+        if let Some(hello) = &mut self.hello
+            && hello.feed(&buf.filled()[before..])
+        {
+            self.hello = None;
+        }
+
+        let Some(remaining) = self.remaining else {
+            return Poll::Ready(Ok(()));
+        };
+
+        if *self.authenticated.borrow() {
+            self.remaining = None;
+
+            return Poll::Ready(Ok(()));
+        }
+
+        let read = (buf.filled().len() - before) as u64;
+
+        let Some(left) = remaining.checked_sub(read) else {
+            buf.set_filled(before);
+
+            return Poll::Ready(Err(io::Error::other("pre-auth byte limit exceeded")));
+        };
+
+        self.remaining = Some(left);
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncWrite for PreAuthStream {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_write(cx, buf)
+    }
+
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_write_vectored(cx, bufs)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.inner.is_write_vectored()
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn accepted_sockets_carry_configured_options() {
+        let config = Config {
+            nodelay: true,
+            ..Config::default()
+        };
+        let options = TcpOptions {
+            keepalive: Some(Duration::from_secs(30)),
+            ..TcpOptions::default()
+        };
+
+        let listener = bind("127.0.0.1:0", &options).await.expect("bind");
+        let addr = listener.local_addr().expect("local addr");
+
+        let _client = TcpStream::connect(addr).await.expect("connect");
+        let (stream, _) = listener.accept().await.expect("accept");
+
+        configure(&stream, &config, &options);
+
+        assert!(stream.nodelay().expect("nodelay"));
+
+        let sock = SockRef::from(&stream);
+        assert!(sock.keepalive().expect("keepalive"));
+        assert_eq!(
+            sock.tcp_keepalive_time().expect("keepalive time"),
+            Duration::from_secs(30)
+        );
+    }
+
+    /// A connected pair: the accepted server end and the client end.
+    async fn pair() -> (TcpStream, TcpStream) {
+        let listener = bind("127.0.0.1:0", &TcpOptions::default())
+            .await
+            .expect("bind");
+        let client = TcpStream::connect(listener.local_addr().expect("addr"))
+            .await
+            .expect("connect");
+        let (server, _) = listener.accept().await.expect("accept");
+
+        (server, client)
+    }
+
+    #[tokio::test]
+    async fn pre_auth_reads_beyond_limit_fail() {
+        let (server, mut client) = pair().await;
+        let (_auth, authenticated) = watch::channel(false);
+        let mut stream = PreAuthStream::new(server, Some(4), authenticated);
+
+        client.write_all(b"abcdefgh").await.expect("write");
+
+        let mut buf = Vec::new();
+
+        assert!(stream.read_to_end(&mut buf).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn authenticated_reads_are_unlimited() {
+        let (server, mut client) = pair().await;
+        let (auth, authenticated) = watch::channel(false);
+        let mut stream = PreAuthStream::new(server, Some(4), authenticated);
+
+        auth.send_replace(true);
+        client.write_all(b"abcdefgh").await.expect("write");
+        drop(client);
+
+        let mut buf = Vec::new();
+        stream.read_to_end(&mut buf).await.expect("read");
+
+        assert_eq!(buf, b"abcdefgh");
+    }
+
+    #[test]
+    fn reject_messages_cannot_look_like_a_version_line() {
+        let reject = |message: &str| OverflowPolicy::Reject(message.into()).validate();
+
+        assert!(reject("server busy").is_ok());
+        assert!(reject("busy, try SSH-2.0 later").is_ok());
+        assert!(reject("SSH-2.0-busy").is_err());
+        assert!(reject("busy\r\nSSH-2.0-busy").is_err());
+        assert!(OverflowPolicy::Drop.validate().is_ok());
+    }
+
+    #[test]
+    fn client_hello_takes_the_ssh_line_across_reads() {
+        let version = Arc::new(OnceLock::new());
+        let mut hello = ClientHello::new(Arc::clone(&version), Arc::default());
+
+        assert!(!hello.feed(b"hello\r\nSSH-2.0-Open"));
+        assert!(!hello.feed(b"SSH_9.6\r\n\0\0"));
+        assert_eq!(
+            version.get().map(String::as_str),
+            Some("SSH-2.0-OpenSSH_9.6")
+        );
+
+        let version = Arc::new(OnceLock::new());
+        let mut hello = ClientHello::new(Arc::clone(&version), Arc::default());
+
+        assert!(hello.feed(&[b'x'; 300]));
+        assert!(version.get().is_none());
+    }
+
+    #[test]
+    fn client_hello_fingerprints_the_first_packet() {
+        let mut payload = vec![20];
+        payload.extend([0u8; 16]);
+        for list in [
+            "kex", "key", "aes", "aes", "mac", "mac", "none", "none", "", "",
+        ] {
+            payload.extend(u32::try_from(list.len()).expect("len").to_be_bytes());
+            payload.extend(list.as_bytes());
+        }
+        payload.extend([0u8; 5]);
+
+        let padding = [0u8; 4];
+        let mut wire = b"SSH-2.0-test\r\n".to_vec();
+        let len = u32::try_from(1 + payload.len() + padding.len()).expect("len");
+        wire.extend(len.to_be_bytes());
+        wire.push(4);
+        wire.extend(&payload);
+        wire.extend(padding);
+
+        let fingerprint = Arc::new(OnceLock::new());
+        let mut hello = ClientHello::new(Arc::default(), Arc::clone(&fingerprint));
+
+        let (head, tail) = wire.split_at(20);
+        assert!(!hello.feed(head));
+        assert!(hello.feed(tail));
+        assert_eq!(
+            fingerprint.get().map(ClientFingerprint::hassh_algorithms),
+            Some("kex;aes;mac;none".into())
+        );
+
+        let mut hello = ClientHello::new(Arc::default(), Arc::default());
+        assert!(hello.feed(b"SSH-2.0-test\r\n\xff\xff\xff\xff"));
+    }
+}
@@ -1,7 +1,27 @@
+mod broadcast;
+#[cfg(feature = "config")]
+mod config;
 mod core;
+mod env;
+mod event;
+mod forward;
+mod hassh;
 mod keygen;
+mod listener;
+mod resolver;
 pub mod russh;
 
+pub(crate) use broadcast::Broadcasts;
+#[cfg(feature = "config")]
+pub use config::*;
 pub use core::*;
-pub use keygen::HostKeyOptions;
+pub(crate) use env::EnvPolicy;
+pub use event::{AuthDecision, AuthEvent, ServerEvent};
+pub(crate) use event::{AuthHook, ServerEvents};
+pub use forward::ForwardRequest;
+pub(crate) use forward::{ForwardApprover, RemoteForwards};
+pub use hassh::ClientFingerprint;
+pub use keygen::{HostKeyOptions, PassphraseProvider};
+pub use listener::OverflowPolicy;
+pub(crate) use resolver::ReverseDns;
 pub(crate) use russh::*;
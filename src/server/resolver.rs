@@ -0,0 +1,178 @@
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// How long a lookup result (including "no name") is reused.
+const CACHE_TTL: Duration = Duration::from_mins(5);
+
+/// Addresses remembered at once; the oldest entry is evicted beyond this.
+const CACHE_CAPACITY: usize = 1024;
+
+type Lookup = Arc<dyn Fn(IpAddr) -> Option<String> + Send + Sync>;
+
+/// Reverse DNS for client addresses, bounded by a timeout and backed by a
+/// small TTL cache so reconnecting clients don't re-query.
+pub struct ReverseDns {
+    timeout: Duration,
+    lookup: Lookup,
+    cache: Mutex<HashMap<IpAddr, Entry>>,
+}
+
+/// A remembered lookup; `name` is `None` when the address has no verified
+/// hostname, which is worth caching too.
+#[derive(Clone)]
+struct Entry {
+    name: Option<String>,
+    at: Instant,
+}
+
+impl ReverseDns {
+    #[cfg(feature = "reverse-dns")]
+    pub fn new(timeout: Duration) -> Self {
+        Self::with_lookup(timeout, Arc::new(system_lookup))
+    }
+
+    pub fn with_lookup(timeout: Duration, lookup: Lookup) -> Self {
+        Self {
+            timeout,
+            lookup,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The verified hostname for `ip`, or `None` if it has none, the lookup
+    /// failed, or it didn't finish within the timeout.
+    ///
+    /// Timeouts aren't cached, so a slow resolver gets another chance on the
+    /// next connection.
+    pub async fn resolve(&self, ip: IpAddr) -> Option<String> {
+        let ip = ip.to_canonical();
+
+        if let Some(entry) = self.cached(ip) {
+            return entry.name;
+        }
+
+        let lookup = Arc::clone(&self.lookup);
+        let task = tokio::task::spawn_blocking(move || lookup(ip));
+
+        let Ok(Ok(name)) = tokio::time::timeout(self.timeout, task).await else {
+            tracing::debug!(%ip, "reverse DNS lookup timed out");
+
+            return None;
+        };
+
+        self.store(ip, name.clone());
+
+        name
+    }
+
+    fn cached(&self, ip: IpAddr) -> Option<Entry> {
+        let entry = self
+            .cache
+            .lock()
+            .expect("resolver cache poisoned")
+            .get(&ip)
+            .cloned()?;
+
+        (entry.at.elapsed() < CACHE_TTL).then_some(entry)
+    }
+
+    fn store(&self, ip: IpAddr, name: Option<String>) {
+        let mut cache = self.cache.lock().expect("resolver cache poisoned");
+
+        if cache.len() >= CACHE_CAPACITY && !cache.contains_key(&ip) {
+            cache.retain(|_, entry| entry.at.elapsed() < CACHE_TTL);
+
+            let oldest = cache
+                .iter()
+                .min_by_key(|(_, entry)| entry.at)
+                .map(|(ip, _)| *ip);
+
+            if cache.len() >= CACHE_CAPACITY
+                && let Some(oldest) = oldest
+            {
+                cache.remove(&oldest);
+            }
+        }
+
+        cache.insert(
+            ip,
+            Entry {
+                name,
+                at: Instant::now(),
+            },
+        );
+    }
+}
+
+/// PTR lookup confirmed by a forward lookup, like sshd's `UseDNS`: the name
+/// only counts if it resolves back to `ip`. Otherwise whoever controls the
+/// reverse zone for an address could claim any hostname.
+#[cfg(feature = "reverse-dns")]
+fn system_lookup(ip: IpAddr) -> Option<String> {
+    let name = dns_lookup::lookup_addr(&ip).ok()?;
+    let mut forward = dns_lookup::lookup_host(&name).ok()?;
+
+    forward
+        .any(|addr| addr.to_canonical() == ip)
+        .then_some(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::Ipv4Addr,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    use super::*;
+
+    const ADDR: IpAddr = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 7));
+
+    #[tokio::test]
+    async fn results_are_cached() {
+        static CALLS: AtomicUsize = AtomicUsize::new(0);
+
+        let dns = ReverseDns::with_lookup(
+            Duration::from_secs(1),
+            Arc::new(|_| {
+                CALLS.fetch_add(1, Ordering::SeqCst);
+
+                Some("host.example".into())
+            }),
+        );
+
+        assert_eq!(dns.resolve(ADDR).await.as_deref(), Some("host.example"));
+        assert_eq!(dns.resolve(ADDR).await.as_deref(), Some("host.example"));
+        assert_eq!(CALLS.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn slow_lookups_time_out() {
+        let dns = ReverseDns::with_lookup(
+            Duration::from_millis(10),
+            Arc::new(|_| {
+                std::thread::sleep(Duration::from_millis(200));
+
+                Some("late.example".into())
+            }),
+        );
+
+        assert_eq!(dns.resolve(ADDR).await, None);
+        assert!(dns.cached(ADDR).is_none());
+    }
+
+    #[test]
+    fn cache_is_bounded() {
+        let dns = ReverseDns::with_lookup(Duration::from_secs(1), Arc::new(|_| None));
+
+        for i in 0..=u32::try_from(CACHE_CAPACITY).expect("capacity fits") {
+            dns.store(IpAddr::from(i.to_be_bytes()), None);
+        }
+
+        assert_eq!(dns.cache.lock().expect("lock").len(), CACHE_CAPACITY);
+    }
+}
@@ -3,46 +3,82 @@ use std::{
     collections::HashMap,
     net::SocketAddr,
     sync::{
-        Arc,
+        Arc, OnceLock,
         atomic::{AtomicUsize, Ordering},
     },
+    time::SystemTime,
 };
 
 use russh::{
     Channel, ChannelId,
-    keys::{Certificate, PublicKey},
+    keys::{Certificate, HashAlg, PublicKey, ssh_key::Fingerprint},
     server::{Auth, Msg, Response, Session as RusshSession},
 };
-use tokio::{sync::oneshot, task::JoinHandle};
+use tokio::{
+    sync::{oneshot, watch},
+    task::JoinHandle,
+    time::Instant,
+};
 
+#[cfg(feature = "resume")]
+use crate::session::{Attachment, Resumable};
 use crate::{
-    Auth as AuthOutcome, Extensions, PtySize, Session, SessionKind,
-    auth::{AuthConfig, Challenge},
+    Auth as AuthOutcome, Bus, ConnectionId, Extensions, PtySize, Session, SessionId, SessionKind,
+    auth::{
+        AuthBackoff, AuthConfig, AuthContext, AuthMethod, Challenge, Lockout, factor, kind,
+        outcome::Verdict,
+    },
     middleware::ErasedHandler,
+    server::{
+        AuthDecision, AuthEvent, AuthHook, Broadcasts, ClientFingerprint, EnvPolicy,
+        ForwardApprover, ForwardRequest, RemoteForwards, ReverseDns, ServerEvent, ServerEvents,
+    },
 };
 
 /// Concurrent session channels allowed per connection (pending + running).
 /// Matches OpenSSH's `MaxSessions` default.
 const MAX_SESSIONS: usize = 10;
 
-/// Client-controlled env vars are stored per channel; cap them so a hostile
-/// client can't grow memory without bound. Requests beyond the cap are
-/// silently dropped, like OpenSSH's `AcceptEnv` rejections.
-const MAX_ENV_VARS: usize = 128;
-
 pub(crate) struct ShenronServer {
     pub(crate) handler: Arc<dyn ErasedHandler>,
     pub(crate) auth: Arc<AuthConfig>,
     pub(crate) banner: Option<String>,
+    pub(crate) max_auth_attempts: Option<usize>,
+    pub(crate) backoff: Option<AuthBackoff>,
+    pub(crate) auth_hook: Option<AuthHook>,
+    pub(crate) lockout: Option<Arc<Lockout>>,
+    pub(crate) reverse_dns: Option<Arc<ReverseDns>>,
+    pub(crate) events: ServerEvents,
+    pub(crate) forward_approver: Option<ForwardApprover>,
+    pub(crate) env: Arc<EnvPolicy>,
+    #[cfg(feature = "resume")]
+    pub(crate) resumable: Option<Arc<Resumable>>,
+    pub(crate) broadcasts: Broadcasts,
+    pub(crate) bus: Bus,
 }
 
 impl russh::server::Server for ShenronServer {
     type Handler = ShenronHandler;
 
     fn new_client(&mut self, addr: Option<SocketAddr>) -> Self::Handler {
+        let connection_id = ConnectionId::new();
+
+        if let Some(remote_addr) = addr {
+            self.events.emit(ServerEvent::ConnectionOpened {
+                remote_addr,
+                connection_id,
+            });
+        }
+
         ShenronHandler {
             handler: Arc::clone(&self.handler),
+            connection_id,
             remote_addr: addr,
+            local_addr: None,
+            remote_hostname: None,
+            client_version: Arc::default(),
+            client_fingerprint: Arc::default(),
+            reverse_dns: self.reverse_dns.clone(),
             pending: HashMap::new(),
             running: Arc::new(AtomicUsize::new(0)),
             user: None,
@@ -51,6 +87,28 @@ impl russh::server::Server for ShenronServer {
             extensions: Extensions::default(),
             banner: self.banner.clone(),
             kbi: None,
+            max_auth_attempts: self.max_auth_attempts,
+            backoff: self.backoff,
+            lockout: self.lockout.clone(),
+            failed_auth_attempts: 0,
+            auth_attempts: 0,
+            attempt_started: Instant::now(),
+            attempt_fingerprint: None,
+            auth_hook: self.auth_hook.clone(),
+            passed_methods: Vec::new(),
+            passed_user: None,
+            auth_methods: Vec::new(),
+            demanded: None,
+            authenticated: watch::Sender::new(false),
+            events: self.events.clone(),
+            forward_approver: self.forward_approver.clone(),
+            env: Arc::clone(&self.env),
+            #[cfg(feature = "resume")]
+            resumable: self.resumable.clone(),
+            broadcasts: self.broadcasts.clone(),
+            bus: self.bus.clone(),
+            forwards: RemoteForwards::default(),
+            open: HashMap::new(),
         }
     }
 }
@@ -79,6 +137,7 @@ struct PendingChannel {
     channel: Channel<Msg>,
     env: HashMap<String, String>,
     pty: Option<(String, PtySize)>,
+    agent_forwarding: bool,
 }
 
 /// In-flight keyboard-interactive conversation. russh calls us once per round;
@@ -102,9 +161,36 @@ fn decode_answers<B: AsRef<[u8]>>(answers: impl IntoIterator<Item = B>) -> Optio
         .ok()
 }
 
+/// The verdict for a key the key policy refuses.
+const fn weak_key(reason: String) -> Verdict {
+    Verdict::Reject {
+        reason: Some(reason),
+        retry: true,
+    }
+}
+
+/// The verdict for a username matching a deny pattern.
+fn denied() -> Verdict {
+    Verdict::Reject {
+        reason: Some("username denied".into()),
+        retry: true,
+    }
+}
+
 pub(crate) struct ShenronHandler {
     handler: Arc<dyn ErasedHandler>,
+    connection_id: ConnectionId,
     remote_addr: Option<SocketAddr>,
+    /// The listener address the connection arrived on, set by the accept
+    /// loop.
+    local_addr: Option<SocketAddr>,
+    remote_hostname: Option<String>,
+    /// The client's identification line, filled in by the listener as it
+    /// reads the handshake.
+    client_version: Arc<OnceLock<String>>,
+    /// The client's key exchange proposal, likewise.
+    client_fingerprint: Arc<OnceLock<ClientFingerprint>>,
+    reverse_dns: Option<Arc<ReverseDns>>,
     pending: HashMap<ChannelId, PendingChannel>,
     running: Arc<AtomicUsize>,
     user: Option<String>,
@@ -113,39 +199,432 @@ pub(crate) struct ShenronHandler {
     extensions: Extensions,
     banner: Option<String>,
     kbi: Option<KbiState>,
+    max_auth_attempts: Option<usize>,
+    backoff: Option<AuthBackoff>,
+    lockout: Option<Arc<Lockout>>,
+    failed_auth_attempts: usize,
+    /// Auth handler calls so far, for [`AuthContext::attempt`].
+    auth_attempts: u32,
+    /// When the auth request being handled arrived; backoff delays count
+    /// from here, so a slow handler doesn't add to them.
+    attempt_started: Instant,
+    /// The key the current attempt tries, for [`AuthEvent::fingerprint`].
+    attempt_fingerprint: Option<Fingerprint>,
+    auth_hook: Option<AuthHook>,
+    /// Factors that have passed toward a required set of methods, and the
+    /// user they passed for.
+    passed_methods: Vec<AuthMethod>,
+    passed_user: Option<String>,
+    /// The methods that passed, as used rather than as factors; handed to
+    /// sessions.
+    auth_methods: Vec<AuthMethod>,
+    /// Set when a handler passed a factor with [`Auth::partial`]: the next
+    /// one must be among these.
+    ///
+    /// [`Auth::partial`]: crate::Auth::partial
+    demanded: Option<Vec<AuthMethod>>,
+    authenticated: watch::Sender<bool>,
+    events: ServerEvents,
+    forward_approver: Option<ForwardApprover>,
+    env: Arc<EnvPolicy>,
+    /// Detached sessions, with
+    /// [`Server::resumable_sessions`](crate::Server::resumable_sessions).
+    #[cfg(feature = "resume")]
+    resumable: Option<Arc<Resumable>>,
+    broadcasts: Broadcasts,
+    bus: Bus,
+    /// Listeners for the client's `ssh -R` forwards.
+    forwards: RemoteForwards,
+    /// Close signals for started sessions, until their channels close.
+    open: HashMap<ChannelId, watch::Sender<bool>>,
+}
+
+impl Drop for ShenronHandler {
+    fn drop(&mut self) {
+        for closed in self.open.values() {
+            closed.send_replace(true);
+        }
+
+        for (address, port) in self.forwards.addresses() {
+            self.events.emit(ServerEvent::ForwardClosed {
+                connection_id: self.connection_id,
+                address,
+                port,
+            });
+        }
+
+        if let Some(remote_addr) = self.remote_addr {
+            self.events.emit(ServerEvent::ConnectionClosed {
+                remote_addr,
+                connection_id: self.connection_id,
+            });
+        }
+    }
 }
 
 impl ShenronHandler {
+    pub(crate) const fn with_local_addr(mut self, addr: SocketAddr) -> Self {
+        self.local_addr = Some(addr);
+
+        self
+    }
+
+    /// Flips to `true` once the connection authenticates; the accept loop
+    /// watches it to lift pre-auth limits.
+    pub(crate) fn authenticated(&self) -> watch::Receiver<bool> {
+        self.authenticated.subscribe()
+    }
+
+    /// Where the listener records the client's identification line.
+    pub(crate) fn client_version(&self) -> Arc<OnceLock<String>> {
+        Arc::clone(&self.client_version)
+    }
+
+    /// Where the listener records the client's key exchange fingerprint.
+    pub(crate) fn client_fingerprint(&self) -> Arc<OnceLock<ClientFingerprint>> {
+        Arc::clone(&self.client_fingerprint)
+    }
+
+    /// Context for the next auth handler call, counting it as an attempt.
+    /// `None` without a peer address, which fails auth anyway.
+    fn auth_context(&mut self) -> Option<AuthContext> {
+        let remote_addr = self.remote_addr?;
+        let local_addr = self.local_addr?;
+        self.auth_attempts = self.auth_attempts.saturating_add(1);
+
+        let version = self.client_version.get().cloned().unwrap_or_default();
+        let fingerprint = self.client_fingerprint.get().cloned();
+
+        Some(AuthContext::new(
+            remote_addr,
+            local_addr,
+            version,
+            fingerprint,
+            self.auth_attempts,
+        ))
+    }
+
     /// Record the user on success, or build a rejection that only advertises
     /// the auth methods this server actually has configured.
-    fn finish_auth(&mut self, user: &str, accepted: bool) -> Auth {
+    async fn finish_auth(&mut self, user: &str, method: AuthMethod, verdict: Verdict) -> Auth {
         // A connection whose peer address can't be read is already broken;
         // refuse it rather than hand consumers (rate limiting, logging,
         // allow-lists) a fabricated address they would trust.
-        if self.remote_addr.is_none() {
+        let Some(remote_addr) = self.remote_addr else {
             tracing::warn!(user, "rejecting connection with no peer address");
 
             return Auth::Reject {
                 proceed_with_methods: Some(russh::MethodSet::empty()),
                 partial_success: false,
             };
+        };
+
+        if verdict.passed() {
+            let advanced = self
+                .demanded
+                .as_ref()
+                .is_none_or(|demanded| demanded.iter().any(|m| factor(*m) == factor(method)))
+                && (self.auth.required.is_empty()
+                    || self.auth.advances(&self.passed_methods, method));
+
+            if advanced {
+                if !self.passed_methods.contains(&factor(method)) {
+                    self.passed_methods.push(factor(method));
+                }
+
+                self.auth_methods.push(method);
+
+                self.passed_user = Some(user.to_string());
+                self.demanded = match verdict {
+                    Verdict::Partial(remaining) => Some(remaining),
+                    _ => None,
+                };
+            }
+
+            if advanced && self.demanded.is_none() && self.auth.satisfied(&self.passed_methods) {
+                if let Err(reason) = self.resolve_user(user).await {
+                    self.report(
+                        user,
+                        remote_addr,
+                        method,
+                        AuthDecision::Rejected {
+                            reason: Some(reason.into()),
+                        },
+                    );
+
+                    // Retrying can't conjure an account, so offer nothing.
+                    return Auth::Reject {
+                        proceed_with_methods: Some(russh::MethodSet::empty()),
+                        partial_success: false,
+                    };
+                }
+
+                self.user = Some(user.to_string());
+                self.report(user, remote_addr, method, AuthDecision::Accepted);
+
+                return Auth::Accept;
+            }
+
+            // A handler demanding only methods this server has no handler
+            // for would leave the client nothing to try.
+            if advanced && self.demanded.is_some() && self.offered().is_empty() {
+                tracing::warn!(
+                    user,
+                    demanded = ?self.demanded,
+                    "Auth::partial demanded only unconfigured methods, rejecting"
+                );
+
+                self.report(
+                    user,
+                    remote_addr,
+                    method,
+                    AuthDecision::Rejected {
+                        reason: Some("demanded auth method not configured".into()),
+                    },
+                );
+
+                return Auth::Reject {
+                    proceed_with_methods: Some(russh::MethodSet::empty()),
+                    partial_success: false,
+                };
+            }
+
+            // One factor of several: tell the client it worked and what's
+            // left. A success that fits no required set counts for nothing.
+            if advanced {
+                self.report(user, remote_addr, method, AuthDecision::Partial);
+            }
+
+            return Auth::Reject {
+                proceed_with_methods: Some(self.offered()),
+                partial_success: advanced,
+            };
         }
 
-        if accepted {
-            self.user = Some(user.to_string());
+        if method != AuthMethod::None {
+            let reason = match verdict {
+                Verdict::Reject { reason, .. } => reason,
+                _ => None,
+            };
 
-            return Auth::Accept;
+            self.report(user, remote_addr, method, AuthDecision::Rejected { reason });
         }
 
         Auth::Reject {
-            proceed_with_methods: Some(self.auth.methods()),
+            proceed_with_methods: Some(self.offered()),
             partial_success: false,
         }
     }
 
+    /// Note the start of an auth attempt, and the key it tries if any.
+    fn begin_attempt(&mut self, key: Option<&PublicKey>) {
+        self.attempt_started = Instant::now();
+        self.attempt_fingerprint = key.map(|key| key.fingerprint(HashAlg::Sha256));
+    }
+
+    /// Publish an auth decision as a [`ServerEvent`] and to the
+    /// [`on_auth_event`](crate::Server::on_auth_event) hook.
+    fn report(
+        &self,
+        user: &str,
+        remote_addr: SocketAddr,
+        method: AuthMethod,
+        decision: AuthDecision,
+    ) {
+        let user = user.to_string();
+
+        self.events.emit(match &decision {
+            AuthDecision::Accepted => ServerEvent::AuthSucceeded {
+                user: user.clone(),
+                remote_addr,
+                method,
+            },
+            AuthDecision::Partial => ServerEvent::AuthPartial {
+                user: user.clone(),
+                remote_addr,
+                method,
+            },
+            AuthDecision::Rejected { reason } => ServerEvent::AuthFailed {
+                user: user.clone(),
+                remote_addr,
+                method,
+                reason: reason.clone(),
+            },
+        });
+
+        let Some(hook) = &self.auth_hook else {
+            return;
+        };
+
+        hook(AuthEvent {
+            connection_id: self.connection_id,
+            user,
+            remote_addr,
+            method,
+            decision,
+            fingerprint: self.attempt_fingerprint,
+            client_version: self.client_version.get().cloned().unwrap_or_default(),
+            at: SystemTime::now(),
+            elapsed: self.attempt_started.elapsed(),
+        });
+    }
+
+    /// Attach the user's account from the configured resolver, if any.
+    /// Errs with the reason to refuse the login.
+    async fn resolve_user(&mut self, user: &str) -> Result<(), &'static str> {
+        let Some(resolver) = self.auth.resolver.clone() else {
+            return Ok(());
+        };
+
+        match resolver.resolve(user).await {
+            Ok(Some(account)) => {
+                self.extensions.merge(account);
+
+                Ok(())
+            }
+            Ok(None) => {
+                tracing::warn!(user, "authenticated user has no account");
+
+                Err("no account")
+            }
+            Err(e) => {
+                tracing::warn!(user, error = %e, "account lookup failed");
+
+                Err("account lookup failed")
+            }
+        }
+    }
+
+    /// The methods worth offering next: whatever completes a required set,
+    /// narrowed to a handler's [`Auth::partial`](crate::Auth::partial) demand.
+    fn offered(&self) -> russh::MethodSet {
+        let methods = self.auth.methods_after(&self.passed_methods);
+
+        let Some(demanded) = &self.demanded else {
+            return methods;
+        };
+
+        methods
+            .iter()
+            .filter(|method| demanded.iter().any(|m| kind(*m) == **method))
+            .copied()
+            .collect::<Vec<_>>()
+            .as_slice()
+            .into()
+    }
+
+    /// Turn away a client the [`Lockout`] has banned, before its credentials
+    /// reach a handler.
+    async fn check_lockout(&self, user: &str) -> crate::Result<()> {
+        let (Some(lockout), Some(remote_addr)) = (&self.lockout, self.remote_addr) else {
+            return Ok(());
+        };
+
+        if let Some(remaining) = lockout.banned(remote_addr.ip(), user).await {
+            tracing::warn!(user, %remote_addr, ?remaining, "locked out, disconnecting");
+
+            return Err(crate::Error::Protocol("locked out".into()));
+        }
+
+        Ok(())
+    }
+
+    /// Count a verdict toward the [`Lockout`], if one is configured.
+    async fn record_lockout(&self, user: &str, verdict: &Verdict) {
+        let (Some(lockout), Some(remote_addr)) = (&self.lockout, self.remote_addr) else {
+            return;
+        };
+
+        match verdict {
+            Verdict::Accept => lockout.succeeded(user).await,
+            Verdict::Partial(_) => {}
+            Verdict::Reject { .. } => {
+                if let Some(duration) = lockout.failed(remote_addr.ip(), user).await {
+                    tracing::warn!(user, %remote_addr, ?duration, "repeated auth failures, locked out");
+
+                    self.events.emit(ServerEvent::LockedOut {
+                        user: user.to_string(),
+                        remote_addr,
+                        duration,
+                    });
+                }
+            }
+        }
+    }
+
+    /// [`finish_auth`](Self::finish_auth) for a real credential attempt:
+    /// failures count toward the connection's `max_auth_attempts`, and the
+    /// one that reaches it errors out, which drops the connection.
+    async fn conclude_auth(
+        &mut self,
+        user: &str,
+        method: AuthMethod,
+        verdict: impl Into<Verdict>,
+    ) -> crate::Result<Auth> {
+        let verdict = verdict.into();
+
+        // Factors must all be for one user; like OpenSSH, switching users
+        // midway ends the connection rather than mixing credentials.
+        if let Some(passed_user) = &self.passed_user
+            && passed_user != user
+        {
+            tracing::warn!(user, passed_user, "username changed during authentication");
+
+            return Err(crate::Error::Protocol(
+                "username changed during authentication".into(),
+            ));
+        }
+
+        self.record_lockout(user, &verdict).await;
+
+        if let Verdict::Reject { reason, retry } = &verdict {
+            self.failed_auth_attempts += 1;
+
+            if !retry {
+                tracing::warn!(
+                    user,
+                    reason,
+                    "authentication rejected without retry, disconnecting"
+                );
+
+                // Still report the failure before the connection goes.
+                self.finish_auth(user, method, verdict.clone()).await;
+
+                return Err(crate::Error::Protocol("authentication rejected".into()));
+            }
+
+            if let Some(max) = self.max_auth_attempts
+                && self.failed_auth_attempts >= max
+            {
+                tracing::warn!(
+                    user,
+                    attempts = self.failed_auth_attempts,
+                    "too many authentication failures, disconnecting"
+                );
+
+                return Err(crate::Error::Protocol(
+                    "too many authentication failures".into(),
+                ));
+            }
+
+            if let Some(backoff) = &self.backoff {
+                let delay = backoff.delay(self.failed_auth_attempts);
+
+                tokio::time::sleep_until(self.attempt_started + delay).await;
+            }
+        }
+
+        Ok(self.finish_auth(user, method, verdict).await)
+    }
+
     /// Pull the pending channel for `id` and build the app session from its
     /// accumulated state plus a snapshot of the connection's auth data.
-    fn start_session(&mut self, id: ChannelId, kind: SessionKind) -> crate::Result<Session> {
+    fn start_session(
+        &mut self,
+        id: ChannelId,
+        kind: SessionKind,
+        handle: russh::server::Handle,
+    ) -> crate::Result<Session> {
         let pending = self
             .pending
             .remove(&id)
@@ -156,17 +635,82 @@ impl ShenronHandler {
         let remote_addr = self
             .remote_addr
             .ok_or_else(|| crate::Error::Protocol("No peer address".into()))?;
+        let local_addr = self
+            .local_addr
+            .ok_or_else(|| crate::Error::Protocol("No local address".into()))?;
+
+        let closed = watch::Sender::new(false);
+        self.open.insert(id, closed.clone());
 
-        Ok(Session::new(
+        let session = Session::new(
             pending.channel,
+            handle,
+            self.connection_id,
+            SessionId::new(),
             kind,
             pending.pty,
+            pending.agent_forwarding,
             self.user.clone().unwrap_or_else(|| "unknown".into()),
+            self.auth_methods.clone(),
             self.public_key.clone(),
             pending.env,
             self.extensions.clone(),
             remote_addr,
-        ))
+            local_addr,
+            self.remote_hostname.clone(),
+            self.client_fingerprint.get().cloned(),
+            closed,
+            self.broadcasts.clone(),
+            self.bus.clone(),
+        );
+
+        #[cfg(feature = "resume")]
+        let session = session.resumable_in(self.resumable.clone());
+
+        Ok(session)
+    }
+
+    /// Hand the shell channel `id` to a session its user left detached, if
+    /// there is one; `false` to start a new session instead.
+    #[cfg(feature = "resume")]
+    fn resume_session(&mut self, id: ChannelId, handle: russh::server::Handle) -> bool {
+        let (Some(resumable), Some(user)) = (&self.resumable, &self.user) else {
+            return false;
+        };
+        let (Some(remote_addr), Some(local_addr)) = (self.remote_addr, self.local_addr) else {
+            return false;
+        };
+        if !self.pending.contains_key(&id) {
+            return false;
+        }
+        let Some(detached) = resumable.take(user) else {
+            return false;
+        };
+        let Some(pending) = self.pending.remove(&id) else {
+            return false;
+        };
+
+        let closed = watch::Sender::new(false);
+        self.open.insert(id, closed.clone());
+
+        let attachment = Attachment {
+            channel: pending.channel,
+            handle,
+            connection_id: self.connection_id,
+            remote_addr,
+            local_addr,
+            pty: pending.pty,
+            closed,
+        };
+
+        // The session ended between being taken and handed the channel.
+        if let Err(tokio::sync::mpsc::error::SendError(attachment)) = detached.send(attachment) {
+            tokio::spawn(async move {
+                let _ = attachment.channel.close().await;
+            });
+        }
+
+        true
     }
 
     /// Pump the keyboard-interactive task one step: wait for its next challenge
@@ -189,13 +733,15 @@ impl ShenronHandler {
                 .await
                 .map_err(|e| crate::Error::Panic(e.to_string()))??;
 
-            let accepted = outcome.accepted();
+            let (verdict, extensions) = outcome.into_parts();
 
-            if accepted {
-                self.extensions.merge(outcome.into_extensions());
+            if verdict.passed() {
+                self.extensions.merge(extensions);
             }
 
-            return Ok(self.finish_auth(user, accepted));
+            return self
+                .conclude_auth(user, AuthMethod::KeyboardInteractive, verdict)
+                .await;
         };
 
         let prompts: Vec<(Cow<'static, str>, bool)> = challenge
@@ -218,9 +764,19 @@ impl ShenronHandler {
     fn run_handler(&self, mut session: Session) {
         let handler = Arc::clone(&self.handler);
         let running = RunningGuard::new(Arc::clone(&self.running));
+        let events = self.events.clone();
+
+        events.emit(ServerEvent::SessionStarted {
+            session_id: session.id(),
+            connection_id: session.connection_id(),
+            user: session.user().to_string(),
+            remote_addr: session.remote_addr(),
+            kind: session.kind().clone(),
+        });
 
         tokio::spawn(async move {
             let _running = running;
+            let start = std::time::Instant::now();
 
             let exit = handler.call(&mut session).await;
 
@@ -228,9 +784,20 @@ impl ShenronHandler {
                 tracing::error!("Handler error: {e}");
             }
 
-            if let Err(e) = session.finish(exit.code()).await {
+            let exit_code = exit.code();
+
+            if let Err(e) = session.conclude(&exit).await {
                 tracing::debug!("failed to close session channel: {e}");
             }
+
+            events.emit(ServerEvent::SessionEnded {
+                session_id: session.id(),
+                connection_id: session.connection_id(),
+                user: session.user().to_string(),
+                remote_addr: session.remote_addr(),
+                exit_code,
+                duration: start.elapsed(),
+            });
         });
     }
 }
@@ -242,6 +809,18 @@ impl russh::server::Handler for ShenronHandler {
         Ok(self.banner.clone())
     }
 
+    async fn auth_succeeded(&mut self, _session: &mut RusshSession) -> crate::Result<()> {
+        self.authenticated.send_replace(true);
+
+        // Resolved here rather than on connect, so unauthenticated clients
+        // can't make the server generate DNS traffic.
+        if let (Some(dns), Some(addr)) = (&self.reverse_dns, self.remote_addr) {
+            self.remote_hostname = dns.resolve(addr.ip()).await;
+        }
+
+        Ok(())
+    }
+
     async fn channel_open_session(
         &mut self,
         channel: Channel<Msg>,
@@ -257,6 +836,7 @@ impl russh::server::Handler for ShenronHandler {
                 channel,
                 env: HashMap::new(),
                 pty: None,
+                agent_forwarding: false,
             },
         );
 
@@ -271,31 +851,116 @@ impl russh::server::Handler for ShenronHandler {
         // A pending channel closed without starting a session; free its slot.
         self.pending.remove(&channel);
 
+        if let Some(closed) = self.open.remove(&channel) {
+            closed.send_replace(true);
+        }
+
         Ok(())
     }
 
     /// An open server (no auth configured) accepts `none` so clients connect
-    /// without a credential prompt, like Wish. Configured servers reject it
-    /// and point the client at the real methods.
+    /// without a credential prompt, like Wish; so does an anonymous handler
+    /// that approves the user. Other servers reject it and point the client
+    /// at the real methods.
+    ///
+    /// `none` is also how clients discover methods, so a rejection here never
+    /// counts as a failed attempt.
     async fn auth_none(&mut self, user: &str) -> crate::Result<Auth> {
-        Ok(self.finish_auth(user, self.auth.is_empty()))
+        self.begin_attempt(None);
+
+        if self.auth.denies(user) {
+            return Ok(self.finish_auth(user, AuthMethod::None, denied()).await);
+        }
+
+        let Some(handler) = self.auth.anonymous.clone() else {
+            return Ok(self
+                .finish_auth(user, AuthMethod::None, self.auth.is_empty().into())
+                .await);
+        };
+
+        let (verdict, extensions) = handler.verify(user).await.into_parts();
+
+        if verdict.passed() {
+            self.extensions.merge(extensions);
+        }
+
+        Ok(self.finish_auth(user, AuthMethod::None, verdict).await)
+    }
+
+    /// A key probe, before any signature. Declining one isn't a failed
+    /// attempt: no lockout, no attempt count, no event.
+    async fn auth_publickey_offered(
+        &mut self,
+        user: &str,
+        public_key: &PublicKey,
+    ) -> crate::Result<Auth> {
+        if self.auth.denies(user) {
+            return Ok(Auth::Reject {
+                proceed_with_methods: Some(self.offered()),
+                partial_success: false,
+            });
+        }
+
+        if let Some(reason) = self.auth.refuses_key(public_key.key_data()) {
+            tracing::debug!(user, reason, "public key offer declined by key policy");
+
+            return Ok(Auth::Reject {
+                proceed_with_methods: Some(self.offered()),
+                partial_success: false,
+            });
+        }
+
+        let Some(filter) = self.auth.pubkey_offered.clone() else {
+            return Ok(Auth::Accept);
+        };
+
+        if filter.consider(user, public_key).await {
+            return Ok(Auth::Accept);
+        }
+
+        tracing::debug!(user, "public key offer declined");
+
+        Ok(Auth::Reject {
+            proceed_with_methods: Some(self.offered()),
+            partial_success: false,
+        })
     }
 
     async fn auth_publickey(&mut self, user: &str, public_key: &PublicKey) -> crate::Result<Auth> {
-        let outcome: AuthOutcome = if let Some(ref handler) = self.auth.pubkey {
-            handler.verify(user, public_key).await
+        self.begin_attempt(Some(public_key));
+        self.check_lockout(user).await?;
+
+        if self.auth.denies(user) {
+            return self
+                .conclude_auth(user, AuthMethod::PublicKey, denied())
+                .await;
+        }
+
+        if let Some(reason) = self.auth.refuses_key(public_key.key_data()) {
+            return self
+                .conclude_auth(user, AuthMethod::PublicKey, weak_key(reason))
+                .await;
+        }
+
+        let outcome: AuthOutcome = if let Some(handler) = self.auth.pubkey.clone() {
+            let Some(ctx) = self.auth_context() else {
+                return self.conclude_auth(user, AuthMethod::PublicKey, false).await;
+            };
+
+            handler.verify(user, public_key, &ctx).await
         } else {
             self.auth.is_empty().into()
         };
 
-        let accepted = outcome.accepted();
+        let (verdict, extensions) = outcome.into_parts();
 
-        if accepted {
+        if verdict.passed() {
             self.public_key = Some(public_key.clone());
-            self.extensions.merge(outcome.into_extensions());
+            self.extensions.merge(extensions);
         }
 
-        Ok(self.finish_auth(user, accepted))
+        self.conclude_auth(user, AuthMethod::PublicKey, verdict)
+            .await
     }
 
     /// Certificate-bearing publickey auth. russh has already verified the
@@ -307,22 +972,48 @@ impl russh::server::Handler for ShenronHandler {
         user: &str,
         cert: &Certificate,
     ) -> crate::Result<Auth> {
-        let outcome: AuthOutcome = if let Some(ref handler) = self.auth.cert {
-            handler.verify(user, cert).await
+        self.begin_attempt(Some(&PublicKey::new(cert.public_key().clone(), "")));
+        self.check_lockout(user).await?;
+
+        if self.auth.denies(user) {
+            return self
+                .conclude_auth(user, AuthMethod::Certificate, denied())
+                .await;
+        }
+
+        if let Some(reason) = self
+            .auth
+            .refuses_key(cert.public_key())
+            .or_else(|| self.auth.refuses_key(cert.signature_key()))
+        {
+            return self
+                .conclude_auth(user, AuthMethod::Certificate, weak_key(reason))
+                .await;
+        }
+
+        let outcome: AuthOutcome = if let Some(handler) = self.auth.cert.clone() {
+            let Some(ctx) = self.auth_context() else {
+                return self
+                    .conclude_auth(user, AuthMethod::Certificate, false)
+                    .await;
+            };
+
+            handler.verify(user, cert, &ctx).await
         } else {
             self.auth.is_empty().into()
         };
 
-        let accepted = outcome.accepted();
+        let (verdict, extensions) = outcome.into_parts();
 
-        if accepted {
+        if verdict.passed() {
             // Sessions see the cert's inner key, so key-based middleware
             // works the same for cert and plain pubkey logins.
             self.public_key = Some(PublicKey::new(cert.public_key().clone(), ""));
-            self.extensions.merge(outcome.into_extensions());
+            self.extensions.merge(extensions);
         }
 
-        Ok(self.finish_auth(user, accepted))
+        self.conclude_auth(user, AuthMethod::Certificate, verdict)
+            .await
     }
 
     async fn auth_password(
@@ -330,19 +1021,33 @@ impl russh::server::Handler for ShenronHandler {
         user: &str,
         password: &str,
     ) -> crate::Result<russh::server::Auth> {
-        let outcome: AuthOutcome = if let Some(ref handler) = self.auth.password {
-            handler.verify(user, password).await
+        self.begin_attempt(None);
+        self.check_lockout(user).await?;
+
+        if self.auth.denies(user) {
+            return self
+                .conclude_auth(user, AuthMethod::Password, denied())
+                .await;
+        }
+
+        let outcome: AuthOutcome = if let Some(handler) = self.auth.password.clone() {
+            let Some(ctx) = self.auth_context() else {
+                return self.conclude_auth(user, AuthMethod::Password, false).await;
+            };
+
+            handler.verify(user, password, &ctx).await
         } else {
             self.auth.is_empty().into()
         };
 
-        let accepted = outcome.accepted();
+        let (verdict, extensions) = outcome.into_parts();
 
-        if accepted {
-            self.extensions.merge(outcome.into_extensions());
+        if verdict.passed() {
+            self.extensions.merge(extensions);
         }
 
-        Ok(self.finish_auth(user, accepted))
+        self.conclude_auth(user, AuthMethod::Password, verdict)
+            .await
     }
 
     /// Challenge-response auth. russh drives this once per round: `None`
@@ -356,16 +1061,35 @@ impl russh::server::Handler for ShenronHandler {
         _submethods: &str,
         response: Option<Response<'a>>,
     ) -> crate::Result<Auth> {
+        self.begin_attempt(None);
+
         let Some(handler) = self.auth.keyboard_interactive.clone() else {
-            return Ok(self.finish_auth(user, false));
+            return self
+                .conclude_auth(user, AuthMethod::KeyboardInteractive, false)
+                .await;
         };
 
         let Some(response) = response else {
             // First round: spawn the handler and relay its first challenge.
+            self.check_lockout(user).await?;
+
+            if self.auth.denies(user) {
+                return self
+                    .conclude_auth(user, AuthMethod::KeyboardInteractive, denied())
+                    .await;
+            }
+
+            let Some(ctx) = self.auth_context() else {
+                return self
+                    .conclude_auth(user, AuthMethod::KeyboardInteractive, false)
+                    .await;
+            };
+
             let (challenger, rx) = crate::auth::channel();
 
             let owned_user = user.to_string();
-            let join = tokio::spawn(async move { handler.verify(&owned_user, challenger).await });
+            let join =
+                tokio::spawn(async move { handler.verify(&owned_user, challenger, &ctx).await });
 
             self.kbi = Some(KbiState {
                 rx,
@@ -380,13 +1104,17 @@ impl russh::server::Handler for ShenronHandler {
         // A missing state or reply slot means answers arrived with no challenge
         // outstanding — a protocol violation, so reject.
         let Some(reply) = self.kbi.as_mut().and_then(|s| s.pending.take()) else {
-            return Ok(self.finish_auth(user, false));
+            return self
+                .conclude_auth(user, AuthMethod::KeyboardInteractive, false)
+                .await;
         };
 
         // Invalid input rejects the attempt — dropping `reply` unwinds the
         // waiting handler — and the client may restart.
         let Some(answers) = decode_answers(response) else {
-            return Ok(self.finish_auth(user, false));
+            return self
+                .conclude_auth(user, AuthMethod::KeyboardInteractive, false)
+                .await;
         };
 
         // A dropped receiver means the handler already ended; kbi_advance will
@@ -407,8 +1135,9 @@ impl russh::server::Handler for ShenronHandler {
             return Ok(());
         };
 
-        if pending.env.len() >= MAX_ENV_VARS {
-            tracing::debug!("env var limit reached, dropping {variable_name}");
+        // Dropped silently, as OpenSSH does with `AcceptEnv` rejections.
+        if let Err(reason) = self.env.check(&pending.env, variable_name, variable_value) {
+            tracing::debug!("dropping env var {variable_name}: {reason}");
 
             return Ok(());
         }
@@ -452,6 +1181,96 @@ impl russh::server::Handler for ShenronHandler {
         Ok(())
     }
 
+    async fn tcpip_forward(
+        &mut self,
+        address: &str,
+        port: &mut u32,
+        session: &mut RusshSession,
+    ) -> crate::Result<bool> {
+        let (Some(approve), Some(user), Some(remote_addr)) =
+            (&self.forward_approver, self.user.clone(), self.remote_addr)
+        else {
+            return Ok(false);
+        };
+
+        if !*self.authenticated.borrow() {
+            return Ok(false);
+        }
+
+        let request = ForwardRequest {
+            user,
+            remote_addr,
+            connection_id: self.connection_id,
+            address: address.to_string(),
+            port: *port,
+        };
+
+        if !approve(request).await {
+            return Ok(false);
+        }
+
+        match self
+            .forwards
+            .open(
+                address,
+                *port,
+                session.handle(),
+                self.connection_id,
+                self.events.clone(),
+            )
+            .await
+        {
+            Ok(bound) => {
+                *port = bound;
+
+                Ok(true)
+            }
+            Err(e) => {
+                tracing::debug!("remote forward to {address}:{port} failed: {e}");
+
+                Ok(false)
+            }
+        }
+    }
+
+    async fn cancel_tcpip_forward(
+        &mut self,
+        address: &str,
+        port: u32,
+        _session: &mut RusshSession,
+    ) -> crate::Result<bool> {
+        let cancelled = self.forwards.cancel(address, port);
+
+        if cancelled {
+            self.events.emit(ServerEvent::ForwardClosed {
+                connection_id: self.connection_id,
+                address: address.to_string(),
+                port,
+            });
+        }
+
+        Ok(cancelled)
+    }
+
+    async fn agent_request(
+        &mut self,
+        channel_id: russh::ChannelId,
+        session: &mut RusshSession,
+    ) -> crate::Result<bool> {
+        match self.pending.get_mut(&channel_id) {
+            Some(pending) => {
+                pending.agent_forwarding = true;
+                session.channel_success(channel_id)?;
+            }
+            None => session.channel_failure(channel_id)?,
+        }
+
+        // russh answers this channel request as if it were a global one:
+        // `false` would send a stray `REQUEST_FAILURE`, `true` sends nothing.
+        // The reply above is the real one.
+        Ok(true)
+    }
+
     async fn exec_request(
         &mut self,
         channel_id: russh::ChannelId,
@@ -459,7 +1278,8 @@ impl russh::server::Handler for ShenronHandler {
         session: &mut RusshSession,
     ) -> crate::Result<()> {
         let command = String::from_utf8_lossy(data).to_string();
-        let app_session = self.start_session(channel_id, SessionKind::Exec { command })?;
+        let app_session =
+            self.start_session(channel_id, SessionKind::Exec { command }, session.handle())?;
 
         session.channel_success(channel_id)?;
 
@@ -473,7 +1293,14 @@ impl russh::server::Handler for ShenronHandler {
         channel_id: russh::ChannelId,
         session: &mut RusshSession,
     ) -> crate::Result<()> {
-        let app_session = self.start_session(channel_id, SessionKind::Shell)?;
+        #[cfg(feature = "resume")]
+        if self.resume_session(channel_id, session.handle()) {
+            session.channel_success(channel_id)?;
+
+            return Ok(());
+        }
+
+        let app_session = self.start_session(channel_id, SessionKind::Shell, session.handle())?;
 
         session.channel_success(channel_id)?;
 
@@ -491,7 +1318,7 @@ impl russh::server::Handler for ShenronHandler {
         let kind = SessionKind::Subsystem {
             name: name.to_string(),
         };
-        let app_session = self.start_session(channel_id, kind)?;
+        let app_session = self.start_session(channel_id, kind, session.handle())?;
 
         session.channel_success(channel_id)?;
 
@@ -508,8 +1335,14 @@ mod tests {
 
     fn handler_with_addr(remote_addr: Option<SocketAddr>) -> ShenronHandler {
         ShenronHandler {
-            handler: middleware::build_chain(vec![]),
+            handler: middleware::build_chain(vec![], None),
+            connection_id: ConnectionId::new(),
             remote_addr,
+            local_addr: remote_addr,
+            remote_hostname: None,
+            client_version: Arc::default(),
+            client_fingerprint: Arc::default(),
+            reverse_dns: None,
             pending: HashMap::new(),
             running: Arc::new(AtomicUsize::new(0)),
             user: None,
@@ -518,14 +1351,38 @@ mod tests {
             extensions: Extensions::default(),
             banner: None,
             kbi: None,
+            max_auth_attempts: None,
+            backoff: None,
+            lockout: None,
+            failed_auth_attempts: 0,
+            auth_attempts: 0,
+            attempt_started: Instant::now(),
+            attempt_fingerprint: None,
+            auth_hook: None,
+            passed_methods: Vec::new(),
+            passed_user: None,
+            auth_methods: Vec::new(),
+            demanded: None,
+            authenticated: watch::Sender::new(false),
+            events: ServerEvents::default(),
+            forward_approver: None,
+            env: Arc::default(),
+            #[cfg(feature = "resume")]
+            resumable: None,
+            broadcasts: Broadcasts::default(),
+            bus: Bus::default(),
+            forwards: RemoteForwards::default(),
+            open: HashMap::new(),
         }
     }
 
-    #[test]
-    fn addr_less_connection_is_rejected_even_when_auth_accepts() {
+    #[tokio::test]
+    async fn addr_less_connection_is_rejected_even_when_auth_accepts() {
         let mut h = handler_with_addr(None);
 
-        let auth = h.finish_auth("anyone", true);
+        let auth = h
+            .finish_auth("anyone", AuthMethod::Password, true.into())
+            .await;
 
         let Auth::Reject {
             proceed_with_methods,
@@ -538,14 +1395,187 @@ mod tests {
         assert!(h.user.is_none());
     }
 
-    #[test]
-    fn connection_with_addr_is_accepted() {
+    #[tokio::test]
+    async fn connection_with_addr_is_accepted() {
         let mut h = handler_with_addr(Some(SocketAddr::from(([127, 0, 0, 1], 2222))));
 
-        assert!(matches!(h.finish_auth("anyone", true), Auth::Accept));
+        assert!(matches!(
+            h.finish_auth("anyone", AuthMethod::Password, true.into())
+                .await,
+            Auth::Accept
+        ));
         assert_eq!(h.user.as_deref(), Some("anyone"));
     }
 
+    #[tokio::test]
+    async fn reaching_max_auth_attempts_disconnects() {
+        let mut h = handler_with_addr(Some(SocketAddr::from(([127, 0, 0, 1], 2222))));
+        h.max_auth_attempts = Some(2);
+
+        assert!(matches!(
+            h.conclude_auth("mallory", AuthMethod::Password, false)
+                .await,
+            Ok(Auth::Reject { .. })
+        ));
+        assert!(
+            h.conclude_auth("mallory", AuthMethod::Password, false)
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn successes_do_not_count_toward_max_auth_attempts() {
+        let mut h = handler_with_addr(Some(SocketAddr::from(([127, 0, 0, 1], 2222))));
+        h.max_auth_attempts = Some(1);
+
+        assert!(matches!(
+            h.conclude_auth("alice", AuthMethod::Password, true).await,
+            Ok(Auth::Accept)
+        ));
+        assert_eq!(h.failed_auth_attempts, 0);
+    }
+
+    fn mfa_handler() -> ShenronHandler {
+        let mut h = handler_with_addr(Some(SocketAddr::from(([127, 0, 0, 1], 2222))));
+        h.auth = Arc::new(AuthConfig {
+            password: Some(Arc::new(|_user: String, _pw: String| async { true })),
+            pubkey: Some(Arc::new(|_user: String, _key: PublicKey| async { true })),
+            required: vec![vec![AuthMethod::PublicKey, AuthMethod::Password]],
+            ..AuthConfig::default()
+        });
+
+        h
+    }
+
+    #[tokio::test]
+    async fn first_factor_is_a_partial_success() {
+        let mut h = mfa_handler();
+
+        let Ok(Auth::Reject {
+            proceed_with_methods,
+            partial_success,
+        }) = h.conclude_auth("alice", AuthMethod::PublicKey, true).await
+        else {
+            panic!("one factor must not accept");
+        };
+        let methods = proceed_with_methods.expect("methods");
+
+        assert!(partial_success);
+        assert!(methods.contains(&russh::MethodKind::Password));
+        assert!(!methods.contains(&russh::MethodKind::PublicKey));
+        assert!(h.user.is_none());
+
+        assert!(matches!(
+            h.conclude_auth("alice", AuthMethod::Password, true).await,
+            Ok(Auth::Accept)
+        ));
+        assert_eq!(h.user.as_deref(), Some("alice"));
+    }
+
+    #[tokio::test]
+    async fn repeating_a_factor_does_not_complete_the_set() {
+        let mut h = mfa_handler();
+
+        let _ = h.conclude_auth("alice", AuthMethod::PublicKey, true).await;
+
+        assert!(matches!(
+            h.conclude_auth("alice", AuthMethod::Certificate, true)
+                .await,
+            Ok(Auth::Reject {
+                partial_success: false,
+                ..
+            })
+        ));
+        assert!(h.user.is_none());
+    }
+
+    #[tokio::test]
+    async fn changing_user_between_factors_disconnects() {
+        let mut h = mfa_handler();
+
+        let _ = h.conclude_auth("alice", AuthMethod::PublicKey, true).await;
+
+        assert!(
+            h.conclude_auth("mallory", AuthMethod::Password, true)
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn handler_partial_demands_one_of_its_methods() {
+        let mut h = mfa_handler();
+        h.auth = Arc::new(AuthConfig {
+            required: vec![],
+            ..AuthConfig::clone(&h.auth)
+        });
+
+        let Ok(Auth::Reject {
+            proceed_with_methods,
+            partial_success: true,
+        }) = h
+            .conclude_auth(
+                "alice",
+                AuthMethod::Password,
+                Verdict::Partial(vec![AuthMethod::PublicKey]),
+            )
+            .await
+        else {
+            panic!("a partial verdict must not accept");
+        };
+        let methods = proceed_with_methods.expect("methods");
+
+        assert!(methods.contains(&russh::MethodKind::PublicKey));
+        assert!(!methods.contains(&russh::MethodKind::Password));
+
+        assert!(matches!(
+            h.conclude_auth("alice", AuthMethod::PublicKey, true).await,
+            Ok(Auth::Accept)
+        ));
+    }
+
+    #[tokio::test]
+    async fn partial_demanding_unconfigured_methods_is_rejected() {
+        let mut h = mfa_handler();
+        h.auth = Arc::new(AuthConfig {
+            required: vec![],
+            ..AuthConfig::clone(&h.auth)
+        });
+
+        let Ok(Auth::Reject {
+            proceed_with_methods,
+            partial_success: false,
+        }) = h
+            .conclude_auth(
+                "alice",
+                AuthMethod::Password,
+                Verdict::Partial(vec![AuthMethod::KeyboardInteractive]),
+            )
+            .await
+        else {
+            panic!("a demand nothing can meet must reject");
+        };
+
+        assert!(proceed_with_methods.expect("methods").is_empty());
+    }
+
+    #[tokio::test]
+    async fn rejection_without_retry_disconnects() {
+        let mut h = handler_with_addr(Some(SocketAddr::from(([127, 0, 0, 1], 2222))));
+
+        let verdict = Verdict::Reject {
+            reason: Some("revoked key".into()),
+            retry: false,
+        };
+
+        assert!(
+            h.conclude_auth("mallory", AuthMethod::PublicKey, verdict)
+                .await
+                .is_err()
+        );
+    }
+
     #[test]
     fn decode_answers_accepts_utf8_including_empty() {
         let answers = decode_answers([b"1234".as_slice(), b"".as_slice()]);
@@ -0,0 +1,171 @@
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use tokio::sync::broadcast;
+
+/// Messages buffered per subscriber before it starts missing them.
+const TOPIC_CAPACITY: usize = 256;
+
+type Topics = HashMap<(String, TypeId), Box<dyn Topic>>;
+
+/// A topic's sender, with its message type erased.
+trait Topic: Any + Send + Sync {
+    fn receiver_count(&self) -> usize;
+}
+
+impl<T: Send + 'static> Topic for broadcast::Sender<T> {
+    fn receiver_count(&self) -> usize {
+        self.receiver_count()
+    }
+}
+
+/// Publish/subscribe between a server's sessions: a chat room, a
+/// multiplayer game's moves, a dashboard's live numbers.
+///
+/// Every session on a server shares one bus, from
+/// [`Session::bus`](crate::Session::bus); code outside the sessions gets it
+/// from [`Server::bus`](crate::Server::bus). A topic is a name and a message
+/// type: subscribers to `"lobby"` as `String` don't see `"lobby"` messages
+/// of another type. Like [`tokio::sync::broadcast`], which carries them, a
+/// subscriber that falls too far behind misses the oldest messages and is
+/// told how many with [`RecvError::Lagged`](broadcast::error::RecvError::Lagged).
+///
+/// ```no_run
+/// # use shenron::Session;
+/// async fn chat(session: &mut Session) -> shenron::Result {
+///     let bus = session.bus().clone();
+///     let mut room = bus.subscribe::<String>("lobby");
+///
+///     loop {
+///         tokio::select! {
+///             Ok(Some(line)) = session.read_line("> ") => {
+///                 bus.publish("lobby", format!("{}: {line}", session.user()));
+///             }
+///             Ok(message) = room.recv() => {
+///                 session.write_str(&format!("{message}\r\n")).await?;
+///             }
+///             else => return Ok(()),
+///         }
+///     }
+/// }
+/// ```
+#[derive(Clone, Default)]
+pub struct Bus {
+    topics: Arc<Mutex<Topics>>,
+}
+
+impl Bus {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Send `message` to everyone subscribed to `topic` as `T`. Returns how
+    /// many subscribers there were.
+    pub fn publish<T>(&self, topic: &str, message: T) -> usize
+    where
+        T: Clone + Send + Sync + 'static,
+    {
+        let mut topics = self.lock();
+        let key = (topic.to_string(), TypeId::of::<T>());
+
+        let receivers = topics
+            .get(&key)
+            .map(|sender| downcast::<T>(sender.as_ref()).send(message).unwrap_or(0));
+
+        // Everyone has gone; forget the topic until someone subscribes.
+        if receivers == Some(0) {
+            topics.remove(&key);
+        }
+        drop(topics);
+
+        receivers.unwrap_or(0)
+    }
+
+    /// Receive what's published to `topic` as `T` from now on.
+    #[must_use]
+    pub fn subscribe<T>(&self, topic: &str) -> broadcast::Receiver<T>
+    where
+        T: Clone + Send + Sync + 'static,
+    {
+        let mut topics = self.lock();
+
+        // Forget topics whose subscribers have all gone, even if nobody
+        // publishes to them again.
+        topics.retain(|_, sender| sender.receiver_count() > 0);
+
+        let sender = topics
+            .entry((topic.to_string(), TypeId::of::<T>()))
+            .or_insert_with(|| Box::new(broadcast::Sender::<T>::new(TOPIC_CAPACITY)));
+
+        let receiver = downcast::<T>(sender.as_ref()).subscribe();
+        drop(topics);
+
+        receiver
+    }
+
+    /// How many subscribers `topic` has for messages of type `T`.
+    #[must_use]
+    pub fn subscribers<T>(&self, topic: &str) -> usize
+    where
+        T: Clone + Send + Sync + 'static,
+    {
+        self.lock()
+            .get(&(topic.to_string(), TypeId::of::<T>()))
+            .map_or(0, |sender| downcast::<T>(sender.as_ref()).receiver_count())
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Topics> {
+        self.topics.lock().expect("bus topics poisoned")
+    }
+}
+
+/// A topic's sender, which is stored under its message type.
+fn downcast<T: 'static>(sender: &dyn Topic) -> &broadcast::Sender<T> {
+    (sender as &dyn Any)
+        .downcast_ref()
+        .expect("topic keyed by its message type")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn topics_are_keyed_by_name_and_type() {
+        let bus = Bus::new();
+        let mut strings = bus.subscribe::<String>("lobby");
+        let mut numbers = bus.subscribe::<u32>("lobby");
+
+        assert_eq!(bus.publish("lobby", "hi".to_string()), 1);
+        assert_eq!(bus.publish("lobby", 7_u32), 1);
+        assert_eq!(bus.publish("elsewhere", 7_u32), 0);
+
+        assert_eq!(strings.recv().await.ok().as_deref(), Some("hi"));
+        assert_eq!(numbers.recv().await.ok(), Some(7));
+    }
+
+    #[test]
+    fn topics_without_subscribers_are_forgotten() {
+        let bus = Bus::new();
+        let room = bus.subscribe::<String>("lobby");
+        assert_eq!(bus.subscribers::<String>("lobby"), 1);
+
+        drop(room);
+        assert_eq!(bus.publish("lobby", "anyone?".to_string()), 0);
+        assert!(bus.lock().is_empty());
+    }
+
+    #[test]
+    fn subscribing_forgets_abandoned_topics() {
+        let bus = Bus::new();
+        drop(bus.subscribe::<String>("lobby"));
+
+        let _game = bus.subscribe::<u32>("game");
+        assert_eq!(bus.lock().len(), 1);
+        assert_eq!(bus.subscribers::<u32>("game"), 1);
+    }
+}
@@ -1,81 +1,401 @@
-use std::{any::Any, collections::HashMap, net::SocketAddr};
+use std::{
+    any::Any,
+    borrow::Cow,
+    collections::HashMap,
+    io::IoSlice,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
-use russh::{Channel, ChannelMsg, keys::PublicKey, server::Msg};
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    sync::{broadcast, watch},
+};
 
-use crate::{Event, Extensions, PtySize, SessionKind};
+use russh::{
+    Channel, ChannelMsg, ChannelReadHalf, ChannelStream, ChannelWriteHalf,
+    keys::{HashAlg, PublicKey, ssh_key::Fingerprint},
+    server::{Handle, Msg},
+};
+
+#[cfg(feature = "resume")]
+use crate::session::resume::{Resumable, Resumed, Suspend};
+use crate::{
+    Bus, ChannelWriter, ClientFingerprint, ConnectionId, Direction, Event, Exit, Extensions,
+    PtySize, SessionId, SessionKind, SessionStream, Signal, Tap, Utf8Decoder,
+    auth::AuthMethod,
+    server::Broadcasts,
+    session::{
+        line::{LineEditor, Step},
+        stats::{Counters, SessionStats},
+        writer,
+    },
+};
 
 pub struct Session {
-    channel: Option<Channel<Msg>>,
+    /// `None` once a subsystem has taken the channel over.
+    reader: Option<ChannelReadHalf>,
+    writer: Option<Arc<ChannelWriteHalf<Msg>>>,
+    id: SessionId,
+    connection_id: ConnectionId,
+    /// The connection, for channel requests the channel itself can't send.
+    handle: Handle,
     kind: SessionKind,
     pty: Option<(String, PtySize)>,
+    agent_forwarding: bool,
     user: String,
+    auth_methods: Vec<AuthMethod>,
     public_key: Option<PublicKey>,
     env: HashMap<String, String>,
     extensions: Extensions,
     remote_addr: SocketAddr,
+    local_addr: SocketAddr,
+    remote_hostname: Option<String>,
+    client_fingerprint: Option<ClientFingerprint>,
+    /// How long [`next`](Session::next) waits for the client before
+    /// reporting [`Event::IdleTimeout`].
+    idle_timeout: Option<Duration>,
+    /// [`read_line`](Session::read_line)'s state, kept between calls for
+    /// typed-ahead input.
+    line: LineEditor,
+    /// [`next_text`](Session::next_text)'s partial character.
+    text: Utf8Decoder,
+    counters: Arc<Counters>,
+    /// Set when the server allows [`set_resumable`](Session::set_resumable).
+    #[cfg(feature = "resume")]
+    resumable_sessions: Option<Arc<Resumable>>,
+    #[cfg(feature = "resume")]
+    suspend: Option<Suspend>,
+    broadcasts: Broadcasts,
+    bus: Bus,
+    /// Output held back by [`set_write_buffer`](Session::set_write_buffer)
+    /// until [`flush`](Session::flush).
+    buffer: Mutex<Vec<u8>>,
+    buffer_capacity: usize,
+    /// Flipped when the channel closes, from either end.
+    closed: watch::Sender<bool>,
     exited: bool,
+    /// Set once an [`io`](Session::io) adapter has sent EOF, so
+    /// [`conclude`](Session::conclude) doesn't send another.
+    eof_sent: bool,
 }
 
+/// A client for a user's forwarded SSH agent, from [`Session::agent`].
+pub type AgentClient = russh::keys::agent::client::AgentClient<ChannelStream<Msg>>;
+
 impl Session {
     #[expect(clippy::too_many_arguments, reason = "pub(crate), one call site")]
-    pub(crate) const fn new(
+    pub(crate) fn new(
         channel: Channel<Msg>,
+        handle: Handle,
+        connection_id: ConnectionId,
+        id: SessionId,
         kind: SessionKind,
         pty: Option<(String, PtySize)>,
+        agent_forwarding: bool,
         user: String,
+        auth_methods: Vec<AuthMethod>,
         public_key: Option<PublicKey>,
         env: HashMap<String, String>,
         extensions: Extensions,
         remote_addr: SocketAddr,
+        local_addr: SocketAddr,
+        remote_hostname: Option<String>,
+        client_fingerprint: Option<ClientFingerprint>,
+        closed: watch::Sender<bool>,
+        broadcasts: Broadcasts,
+        bus: Bus,
     ) -> Self {
+        let (reader, writer) = channel.split();
+
         Self {
-            channel: Some(channel),
+            reader: Some(reader),
+            writer: Some(Arc::new(writer)),
+            handle,
+            id,
+            connection_id,
             kind,
             pty,
+            agent_forwarding,
             user,
+            auth_methods,
             public_key,
             env,
             extensions,
             remote_addr,
+            local_addr,
+            remote_hostname,
+            client_fingerprint,
+            idle_timeout: None,
+            line: LineEditor::default(),
+            text: Utf8Decoder::new(),
+            counters: Arc::new(Counters::new()),
+            #[cfg(feature = "resume")]
+            resumable_sessions: None,
+            broadcasts,
+            bus,
+            #[cfg(feature = "resume")]
+            suspend: None,
+            buffer: Mutex::new(Vec::new()),
+            buffer_capacity: 0,
+            closed,
             exited: false,
+            eof_sent: false,
         }
     }
 
+    /// Let the session be [resumable](Self::set_resumable), parking it in
+    /// `sessions` while its user is away.
+    #[cfg(feature = "resume")]
+    pub(crate) fn resumable_in(mut self, sessions: Option<Arc<Resumable>>) -> Self {
+        self.resumable_sessions = sessions;
+
+        self
+    }
+
+    /// Await the client's next event; `None` once the channel closes.
+    ///
+    /// With an [`idle_timeout`](Self::set_idle_timeout) set, a client
+    /// silent that long yields [`Event::IdleTimeout`] instead. Cancel-safe.
     pub async fn next(&mut self) -> Option<Event> {
+        match self.idle_timeout {
+            Some(timeout) => self.next_timeout(timeout).await,
+            None => self.next_event().await,
+        }
+    }
+
+    /// Like [`next`](Self::next), but gives up after `timeout` with
+    /// [`Event::IdleTimeout`], whatever [`set_idle_timeout`](Self::set_idle_timeout)
+    /// says. Nothing is lost on timing out: input arriving later is
+    /// returned by the next call.
+    ///
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// # use shenron::{Event, Session};
+    /// async fn prompt(session: &mut Session) -> shenron::Result {
+    ///     session.write_str("Continue? [y/n] ").await?;
+    ///
+    ///     match session.next_timeout(Duration::from_secs(30)).await {
+    ///         Some(Event::Input(answer)) if answer.starts_with(b"y") => Ok(()),
+    ///         Some(Event::IdleTimeout) => session.write_str("\r\ntoo slow\r\n").await,
+    ///         _ => Ok(()),
+    ///     }
+    /// }
+    /// ```
+    pub async fn next_timeout(&mut self, timeout: Duration) -> Option<Event> {
+        tokio::time::timeout(timeout, self.next_event())
+            .await
+            .unwrap_or(Some(Event::IdleTimeout))
+    }
+
+    /// Make [`next`](Self::next) (and everything built on it:
+    /// [`input`](Self::input), [`events`](crate::events::Events), TUIs)
+    /// report [`Event::IdleTimeout`] when the client sends nothing for
+    /// `timeout`. `None`, the default, waits forever.
+    ///
+    /// Unlike [`Server::inactivity_timeout`](crate::Server::inactivity_timeout)
+    /// this only counts this session's input and leaves the decision to the
+    /// handler; each timeout is reported once per `next` call, so a handler
+    /// that carries on waits another `timeout`.
+    pub const fn set_idle_timeout(&mut self, timeout: Option<Duration>) {
+        self.idle_timeout = timeout;
+    }
+
+    #[must_use]
+    pub const fn idle_timeout(&self) -> Option<Duration> {
+        self.idle_timeout
+    }
+
+    /// Keep the session running when the client disconnects, for the same
+    /// user to pick up where they left off, tmux-style, by opening a shell
+    /// within `timeout`. `None`, the default, ends input when the client
+    /// goes. Needs [`Server::resumable_sessions`](crate::Server::resumable_sessions);
+    /// without it this does nothing.
+    ///
+    /// The session is suspended as soon as its channel closes, whatever the
+    /// handler is doing. While suspended, writes succeed and
+    /// [`next`](Self::next) waits for the user. Output is kept on a virtual
+    /// terminal the size of the client's, and a returning user's terminal
+    /// is redrawn to match it, full-screen apps included; `next` then
+    /// reports an [`Event::Resize`] (when the new client has a pty) so the
+    /// app can redraw at the new size. If the user doesn't come back in
+    /// time, `next` returns `None` as for an ordinary close and writes fail.
+    /// Output through [`stdout`](Self::stdout) handles and the
+    /// [`io`](Self::io) adapter isn't kept, and fails while suspended.
+    /// Requires the `resume` feature.
+    ///
+    /// ```no_run
+    /// # use shenron::Session;
+    /// # use std::time::Duration;
+    /// async fn job(session: &mut Session) -> shenron::Result {
+    ///     session.set_resumable(Some(Duration::from_hours(1)));
+    ///
+    ///     // ... a long build, reporting progress with session.write ...
+    ///     # Ok(())
+    /// }
+    /// ```
+    #[cfg(feature = "resume")]
+    pub fn set_resumable(&mut self, timeout: Option<Duration>) {
+        let Some(timeout) = timeout else {
+            self.suspend = None;
+
+            return;
+        };
+
+        if let Some(suspend) = &self.suspend {
+            suspend.set_timeout(timeout);
+
+            return;
+        }
+
+        let (Some(sessions), Some(writer)) = (&self.resumable_sessions, &self.writer) else {
+            tracing::debug!(
+                "set_resumable without Server::resumable_sessions, or after take_stream, has no effect"
+            );
+
+            return;
+        };
+
+        self.suspend = Some(Suspend::new(
+            Arc::clone(sessions),
+            self.id,
+            self.user.clone(),
+            timeout,
+            Arc::clone(writer),
+            &self.closed,
+            self.pty_size(),
+            Arc::clone(&self.counters),
+        ));
+    }
+
+    /// How long the session waits for its user after a disconnect, if it's
+    /// [resumable](Self::set_resumable).
+    #[must_use]
+    #[cfg(feature = "resume")]
+    pub fn resumable(&self) -> Option<Duration> {
+        self.suspend.as_ref().map(Suspend::timeout)
+    }
+
+    /// The next event, however long it takes. Dropping this loses no input.
+    async fn next_event(&mut self) -> Option<Event> {
+        // Whatever the app wrote should reach the client before it waits
+        // on the client's reply; a failure here shows up as the close.
+        let _ = self.flush().await;
+
         loop {
-            let event = self.channel.as_mut()?.wait().await?;
-
-            match event {
-                ChannelMsg::Data { data } => return Some(Event::Input(data.to_vec())),
-                ChannelMsg::WindowChange {
-                    col_width,
-                    row_height,
-                    pix_width,
-                    pix_height,
-                } => {
-                    let new_size = PtySize {
-                        width: col_width,
-                        height: row_height,
-                        pixel_width: pix_width,
-                        pixel_height: pix_height,
-                    };
-
-                    if let Some((_, ref mut size)) = self.pty {
-                        *size = new_size;
+            #[cfg(feature = "resume")]
+            if self.catch_up()
+                && let Some((_, size)) = self.pty
+            {
+                return Some(Event::Resize(size));
+            }
+
+            let Some(msg) = self.reader.as_mut()?.wait().await else {
+                #[cfg(feature = "resume")]
+                if self.resume().await {
+                    if let Some((_, size)) = self.pty {
+                        return Some(Event::Resize(size));
                     }
 
-                    return Some(Event::Resize(new_size));
+                    continue;
                 }
-                ChannelMsg::Signal { signal } => return Some(Event::Signal(signal)),
-                ChannelMsg::Eof => return Some(Event::Eof),
 
-                // Skip protocol messages
-                _ => {}
+                return None;
+            };
+
+            if let Some(event) = self.apply(msg) {
+                return Some(event);
             }
         }
     }
 
-    /// Next chunk of input bytes, or `None` once the client is done sending.
+    /// The channel is gone; if the session is resumable, wait for its user
+    /// to come back and switch to their channel. `false` if they don't.
+    #[cfg(feature = "resume")]
+    async fn resume(&mut self) -> bool {
+        let Some(suspend) = self.suspend.as_mut() else {
+            return false;
+        };
+
+        let Some(resumed) = suspend.resumed().await else {
+            return false;
+        };
+
+        self.switch_to(resumed);
+
+        true
+    }
+
+    /// Switch to the channel the user came back on, if they did while the
+    /// session wasn't reading. `false` if there's none.
+    #[cfg(feature = "resume")]
+    fn catch_up(&mut self) -> bool {
+        let Some(resumed) = self.suspend.as_mut().and_then(Suspend::try_resumed) else {
+            return false;
+        };
+
+        self.switch_to(resumed);
+
+        true
+    }
+
+    #[cfg(feature = "resume")]
+    fn switch_to(&mut self, resumed: Resumed) {
+        self.reader = Some(resumed.reader);
+        self.writer = Some(resumed.writer);
+        self.handle = resumed.handle;
+        self.connection_id = resumed.connection_id;
+        self.remote_addr = resumed.remote_addr;
+        self.local_addr = resumed.local_addr;
+        self.pty = resumed.pty;
+        self.closed = resumed.closed;
+        self.eof_sent = false;
+    }
+
+    /// Turn a channel message into an event, keeping [`pty`](Self::pty) in
+    /// step with resizes. `None` for protocol messages apps don't see.
+    pub(crate) fn apply(&mut self, msg: ChannelMsg) -> Option<Event> {
+        match msg {
+            ChannelMsg::Data { data } => {
+                self.counters.add_read(&data);
+
+                Some(Event::Input(data.to_vec()))
+            }
+            ChannelMsg::WindowChange {
+                col_width,
+                row_height,
+                pix_width,
+                pix_height,
+            } => {
+                let new_size = PtySize {
+                    width: col_width,
+                    height: row_height,
+                    pixel_width: pix_width,
+                    pixel_height: pix_height,
+                };
+
+                if let Some((_, ref mut size)) = self.pty {
+                    *size = new_size;
+                }
+
+                #[cfg(feature = "resume")]
+                if let Some(suspend) = &self.suspend {
+                    suspend.resize(new_size);
+                }
+
+                Some(Event::Resize(new_size))
+            }
+            ChannelMsg::Signal { signal } => Some(Event::Signal(signal)),
+            ChannelMsg::Eof => Some(Event::Eof),
+
+            // Skip protocol messages
+            _ => None,
+        }
+    }
+
+    /// Next chunk of input bytes, or `None` once the client is done sending
+    /// (or, with an [idle timeout](Self::set_idle_timeout), goes quiet).
     ///
     /// Non-input events arriving in between are consumed and discarded
     /// (resizes still update [`Session::pty`]). To observe resizes or
@@ -84,12 +404,93 @@ impl Session {
         loop {
             match self.next().await? {
                 Event::Input(data) => return Some(data),
-                Event::Eof => return None,
+                Event::Eof | Event::IdleTimeout => return None,
                 _ => {}
             }
         }
     }
 
+    /// Like [`input`](Self::input), as text: a UTF-8 character split across
+    /// packets is held back until it's whole (see [`Utf8Decoder`]). Chunks
+    /// are never empty; `None` once input ends, dropping a character it cut
+    /// off.
+    pub async fn next_text(&mut self) -> Option<String> {
+        loop {
+            let Some(data) = self.input().await else {
+                self.text.finish();
+
+                return None;
+            };
+
+            let text = self.text.decode(&data);
+            if !text.is_empty() {
+                return Some(text);
+            }
+        }
+    }
+
+    /// Show `prompt` and read a line of input, with the editing keys a
+    /// shell user expects: backspace, Ctrl+U and Ctrl+W to delete, arrows
+    /// and Home/End (or Ctrl+A/E) to move. `None` if the user cancels with
+    /// Ctrl+C or Ctrl+D on an empty line, or the input ends.
+    ///
+    /// ```no_run
+    /// # use shenron::Session;
+    /// async fn greet(session: &mut Session) -> shenron::Result {
+    ///     while let Some(name) = session.read_line("name: ").await? {
+    ///         session.write_str(&format!("hello, {name}\r\n")).await?;
+    ///     }
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    ///
+    /// Input is echoed only on a PTY; without one (`ssh host cmd`, piped
+    /// input) lines are read silently, ending at LF or CRLF. Input past the
+    /// end of the line is kept for the next `read_line`, not returned by
+    /// [`next`](Self::next). Resizes still update [`pty`](Self::pty);
+    /// signals are dropped.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the prompt or echo fails to send.
+    pub async fn read_line(&mut self, prompt: &str) -> crate::Result<Option<String>> {
+        let echo = self.pty.is_some();
+
+        self.write_str(prompt).await?;
+        self.line.start(prompt);
+
+        loop {
+            let mut out = Vec::new();
+            let step = self.line.feed(echo, &mut out);
+
+            if !out.is_empty() {
+                self.write(&out).await?;
+            }
+
+            match step {
+                Step::Done(line) => return Ok(Some(line)),
+                Step::Cancelled => return Ok(None),
+                Step::Pending => {}
+            }
+
+            match self.next().await {
+                Some(Event::Input(data)) => self.line.push(&data),
+                Some(Event::Resize(_) | Event::Signal(_)) => {}
+                Some(Event::Eof | Event::IdleTimeout) | None => return Ok(None),
+            }
+        }
+    }
+
+    /// Bytes transferred so far and how long the session has run, for
+    /// bandwidth limits, billing, and logs. Includes writes through
+    /// [`stdout`](Self::stdout) handles and subsystems that took the
+    /// channel over.
+    #[must_use]
+    pub fn stats(&self) -> SessionStats {
+        self.counters.snapshot()
+    }
+
     #[must_use]
     pub const fn kind(&self) -> &SessionKind {
         &self.kind
@@ -117,6 +518,19 @@ impl Session {
         }
     }
 
+    /// The arguments of the exec command, after the program: the tail of
+    /// [`command`](Self::command), so `ssh host sh -c "do thing"` gives
+    /// `["-c", "do thing"]`.
+    ///
+    /// Empty for non-exec sessions and for commands with invalid quoting, as
+    /// well as for commands without arguments.
+    #[must_use]
+    pub fn command_args(&self) -> Vec<String> {
+        self.command()
+            .map(|argv| argv.into_iter().skip(1).collect())
+            .unwrap_or_default()
+    }
+
     /// The exec command exactly as the client sent it (Wish's `RawCommand()`).
     #[must_use]
     pub fn raw_command(&self) -> Option<&str> {
@@ -153,6 +567,26 @@ impl Session {
         &self.user
     }
 
+    /// How the user authenticated: the method that completed the login.
+    ///
+    /// [`AuthMethod::None`] on an open server. With several
+    /// [required methods](crate::Server::require_auth_methods) this is the
+    /// last factor; [`auth_methods`](Self::auth_methods) has them all.
+    #[must_use]
+    pub fn auth_method(&self) -> AuthMethod {
+        self.auth_methods
+            .last()
+            .copied()
+            .unwrap_or(AuthMethod::None)
+    }
+
+    /// Every method that passed on the way in, in order. One entry unless
+    /// the login needed multiple factors.
+    #[must_use]
+    pub fn auth_methods(&self) -> &[AuthMethod] {
+        &self.auth_methods
+    }
+
     /// The public key the session authenticated with, if any.
     ///
     /// Returns `None` when the user authenticated by password or when no auth
@@ -162,6 +596,15 @@ impl Session {
         self.public_key.as_ref()
     }
 
+    /// SHA-256 fingerprint of [`public_key`](Self::public_key), the form
+    /// `ssh-keygen -l` prints (`SHA256:...`).
+    #[must_use]
+    pub fn key_fingerprint(&self) -> Option<Fingerprint> {
+        self.public_key
+            .as_ref()
+            .map(|key| key.fingerprint(HashAlg::Sha256))
+    }
+
     /// The client's address, as reported by the accepted socket.
     ///
     /// Always the real peer address: connections whose address can't be read
@@ -171,11 +614,97 @@ impl Session {
         self.remote_addr
     }
 
+    /// The server address the client connected to: which listener, and on
+    /// a wildcard bind which interface. Lets one app tell an internal
+    /// listener from a public one.
+    #[must_use]
+    pub const fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Random, unique to this session, and fixed for its lifetime: the key
+    /// for correlating logs across middleware, handlers and audit sinks.
+    /// The builtin [`logging`](crate::middleware::builtins::logging)
+    /// middleware and [`ServerEvent`](crate::ServerEvent)s carry it too.
+    #[must_use]
+    pub const fn id(&self) -> SessionId {
+        self.id
+    }
+
+    /// The connection this session's channel belongs to, shared by every
+    /// session the client opens on it and by its
+    /// [`AuthEvent`](crate::AuthEvent)s.
+    #[must_use]
+    pub const fn connection_id(&self) -> ConnectionId {
+        self.connection_id
+    }
+
+    /// The client's hostname, when [`Server::reverse_dns`](crate::Server::reverse_dns)
+    /// is enabled and the address has a PTR record that resolves back to it,
+    /// or whatever name a [`reverse_dns_with`](crate::Server::reverse_dns_with)
+    /// lookup gave it.
+    ///
+    /// `None` if the lookup is disabled, failed, or timed out. Only as
+    /// trustworthy as DNS itself; prefer [`remote_addr`](Self::remote_addr)
+    /// for security decisions.
+    #[must_use]
+    pub fn remote_hostname(&self) -> Option<&str> {
+        self.remote_hostname.as_deref()
+    }
+
+    /// The algorithms the client proposed when connecting, and their HASSH
+    /// fingerprint — for telling scanners and bots from real clients. See
+    /// [`ClientFingerprint`].
+    #[must_use]
+    pub const fn client_fingerprint(&self) -> Option<&ClientFingerprint> {
+        self.client_fingerprint.as_ref()
+    }
+
     #[must_use]
     pub const fn env(&self) -> &HashMap<String, String> {
         &self.env
     }
 
+    /// Whether the client asked to forward its SSH agent (`ssh -A`), making
+    /// [`agent`](Self::agent) available.
+    #[must_use]
+    pub const fn agent_forwarding(&self) -> bool {
+        self.agent_forwarding
+    }
+
+    /// Connect to the client's forwarded SSH agent, to list and sign with
+    /// the user's own keys — e.g. to authenticate to an upstream git server
+    /// on their behalf. Each call opens a new agent channel to the client.
+    ///
+    /// ```no_run
+    /// # use shenron::Session;
+    /// async fn keys(session: &mut Session) -> shenron::Result {
+    ///     let mut agent = session.agent().await?;
+    ///
+    ///     for key in agent.request_identities().await? {
+    ///         session.write_str(&format!("{}\r\n", key.comment())).await?;
+    ///     }
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails if the client didn't request agent forwarding, or refuses the
+    /// channel.
+    pub async fn agent(&self) -> crate::Result<AgentClient> {
+        if !self.agent_forwarding {
+            return Err(crate::Error::Protocol(
+                "client did not request agent forwarding".into(),
+            ));
+        }
+
+        let channel = self.handle.channel_open_agent().await?;
+
+        Ok(AgentClient::connect(channel.into_stream()))
+    }
+
     /// Borrow a typed value attached during auth or by a middleware.
     ///
     /// Returns `None` if nothing of type `T` was stored. See
@@ -196,6 +725,19 @@ impl Session {
         self.extensions.get_mut::<T>()
     }
 
+    /// The session's whole [`Extensions`] map, for code that works with it
+    /// generically — copying it along, or handing it to a helper.
+    #[must_use]
+    pub const fn extensions(&self) -> &Extensions {
+        &self.extensions
+    }
+
+    /// Mutable access to the session's [`Extensions`], e.g. for
+    /// [`get_or_insert_with`](Extensions::get_or_insert_with).
+    pub const fn extensions_mut(&mut self) -> &mut Extensions {
+        &mut self.extensions
+    }
+
     /// Take the stored value of type `T` out of the session, if present.
     pub fn remove<T: Any>(&mut self) -> Option<T> {
         self.extensions.remove::<T>()
@@ -203,11 +745,106 @@ impl Session {
 
     /// Write data to the channel
     ///
+    /// With a [write buffer](Self::set_write_buffer) set, small writes are
+    /// held back and sent together.
+    ///
     /// # Errors
     ///
     /// Returns `Err` if the message fails to send
     pub async fn write(&self, data: &[u8]) -> crate::Result {
-        self.channel()?.data(data).await.map_err(crate::Error::Ssh)
+        match self.stage(data) {
+            Some(batch) => self.send(None, &batch).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Write several buffers as one message (or into the
+    /// [write buffer](Self::set_write_buffer)), rather than one message
+    /// each.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the message fails to send
+    pub async fn write_vectored(&self, bufs: &[IoSlice<'_>]) -> crate::Result {
+        let mut data = Vec::with_capacity(bufs.iter().map(|buf| buf.len()).sum());
+        for buf in bufs {
+            data.extend_from_slice(buf);
+        }
+
+        self.write(&data).await
+    }
+
+    /// Buffer up to `capacity` bytes of [`write`](Self::write) output
+    /// before sending it, so apps that write a few bytes at a time send
+    /// fewer, larger packets. 0, the default, sends every write at once.
+    ///
+    /// The buffer is flushed when it fills, by [`flush`](Self::flush),
+    /// before waiting for the client in [`next`](Self::next) and the
+    /// methods built on it, before [`write_stderr`](Self::write_stderr),
+    /// and when the session finishes. [`stdout`](Self::stdout) handles and
+    /// the [`io`](Self::io) adapter write around it, so flush before
+    /// handing them out.
+    pub const fn set_write_buffer(&mut self, capacity: usize) {
+        self.buffer_capacity = capacity;
+    }
+
+    /// The capacity set with [`set_write_buffer`](Self::set_write_buffer).
+    #[must_use]
+    pub const fn write_buffer(&self) -> usize {
+        self.buffer_capacity
+    }
+
+    /// Send any buffered output now.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the message fails to send
+    pub async fn flush(&self) -> crate::Result {
+        let batch = self.take_buffer();
+
+        if batch.is_empty() {
+            return Ok(());
+        }
+
+        self.send(None, &batch).await
+    }
+
+    /// Add `data` to the write buffer; what should be sent now, if anything.
+    fn stage<'a>(&self, data: &'a [u8]) -> Option<Cow<'a, [u8]>> {
+        let mut buffer = self.buffer.lock().expect("write buffer poisoned");
+
+        if buffer.is_empty() && data.len() >= self.buffer_capacity {
+            return Some(Cow::Borrowed(data));
+        }
+
+        buffer.extend_from_slice(data);
+
+        (buffer.len() >= self.buffer_capacity).then(|| Cow::Owned(std::mem::take(&mut *buffer)))
+    }
+
+    fn take_buffer(&self) -> Vec<u8> {
+        std::mem::take(&mut *self.buffer.lock().expect("write buffer poisoned"))
+    }
+
+    async fn send(&self, ext: Option<u32>, data: &[u8]) -> crate::Result {
+        #[cfg(feature = "resume")]
+        if let Some(suspend) = &self.suspend {
+            return suspend.send(ext, data, &self.counters).await;
+        }
+
+        writer::send(self.writer()?, ext, data, &self.counters).await
+    }
+
+    /// How many bytes the client will take right now without a write
+    /// waiting, up to one packet: the SSH flow-control window it has left.
+    /// 0 means it has fallen behind, and the next write waits until it
+    /// reads; a TUI might skip a frame, a log stream coalesce lines.
+    /// [`stats`](Self::stats) counts the writes that had to wait.
+    pub async fn send_window(&self) -> usize {
+        match &self.writer {
+            Some(writer) => writer.writable_packet_size().await,
+            None => 0,
+        }
     }
 
     /// Write a string to the channel
@@ -219,16 +856,43 @@ impl Session {
         self.write(s.as_bytes()).await
     }
 
+    /// A cloneable handle writing to the client's stdout, for tasks that
+    /// outlive a borrow of the session — progress output from a spawned
+    /// worker, say. See [`ChannelWriter`].
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if a subsystem has taken the channel over.
+    pub fn stdout(&self) -> crate::Result<ChannelWriter> {
+        Ok(ChannelWriter::new(
+            Arc::clone(self.writer()?),
+            None,
+            Arc::clone(&self.counters),
+        ))
+    }
+
+    /// Like [`stdout`](Self::stdout), for stderr.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if a subsystem has taken the channel over.
+    pub fn stderr(&self) -> crate::Result<ChannelWriter> {
+        Ok(ChannelWriter::new(
+            Arc::clone(self.writer()?),
+            Some(1),
+            Arc::clone(&self.counters),
+        ))
+    }
+
     /// Write to stderr on the channel
     ///
     /// # Errors
     ///
     /// Returns `Err` if the message fails to send
     pub async fn write_stderr(&self, data: &[u8]) -> crate::Result {
-        self.channel()?
-            .extended_data(1, data)
-            .await
-            .map_err(crate::Error::Ssh)
+        self.flush().await?;
+
+        self.send(Some(1), data).await
     }
 
     /// Write a string to stderr on the channel
@@ -240,9 +904,37 @@ impl Session {
         self.write_stderr(s.as_bytes()).await
     }
 
+    /// End the session successfully now: exit status 0, EOF, and close,
+    /// without waiting for the handler to return. Like
+    /// [`abort(0)`](Self::abort).
+    ///
+    /// Returning from the handler does this too, so `finish` is for a
+    /// handler that has more to do after the client is gone — cleanup,
+    /// logging — and shouldn't keep the client waiting for it. Idempotent:
+    /// once a session has finished, later calls and the handler's eventual
+    /// return value are ignored.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if
+    ///   - Setting exit status fails
+    ///   - Sending the eof message fails
+    ///   - Closing the channel fails
+    pub async fn finish(&mut self) -> crate::Result {
+        self.conclude(&Exit::Code(0)).await
+    }
+
+    /// Whether the session has sent its exit status, by
+    /// [`finish`](Self::finish), [`abort`](Self::abort) or
+    /// [`exit_signal`](Self::exit_signal).
+    #[must_use]
+    pub const fn is_finished(&self) -> bool {
+        self.exited
+    }
+
     /// Send the exit status and close the channel immediately, without
     /// waiting for the handler to return. The handler's eventual return value
-    /// is then ignored ([`finish`](Self::finish) is idempotent).
+    /// is then ignored, as after [`finish`](Self::finish).
     ///
     /// Rarely needed: returning from the handler is the normal way to exit —
     /// `Ok(())` reports 0, a `u32` reports that code, `Err` reports 1.
@@ -254,7 +946,75 @@ impl Session {
     ///   - Sending the eof message fails
     ///   - Closing the channel fails
     pub async fn abort(&mut self, code: u32) -> crate::Result {
-        self.finish(code).await
+        self.conclude(&Exit::Code(code)).await
+    }
+
+    /// Report that the session was terminated by `signal` and close the
+    /// channel, like [`abort`](Self::abort) with SSH's `exit-signal` in place
+    /// of an exit status. OpenSSH's client prints `message` and exits as if
+    /// killed by the signal.
+    ///
+    /// Returning [`Exit::Signal`] from the handler does the same, and a
+    /// child's [`ExitStatus`](std::process::ExitStatus) returned as-is
+    /// reports the signal that killed it.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if sending the signal or closing the channel fails.
+    pub async fn exit_signal(
+        &mut self,
+        signal: Signal,
+        core_dumped: bool,
+        message: impl Into<String>,
+    ) -> crate::Result {
+        self.conclude(&Exit::Signal {
+            signal,
+            core_dumped,
+            message: message.into(),
+        })
+        .await
+    }
+
+    /// Borrow the session as an [`AsyncRead`](tokio::io::AsyncRead) +
+    /// [`AsyncWrite`](tokio::io::AsyncWrite) byte stream, for
+    /// `tokio::io::copy`, codecs, and other libraries built on the standard
+    /// IO traits. See [`SessionIo`](crate::SessionIo).
+    pub const fn io(&mut self) -> crate::session::SessionIo<'_> {
+        crate::session::SessionIo::new(self)
+    }
+
+    /// Connect the client to `io` until `io` is done: input is copied to
+    /// it, its output back to the client's stdout. Makes proxying to a
+    /// container, a serial port, or an upstream host a few lines.
+    ///
+    /// ```no_run
+    /// # use shenron::Session;
+    /// # use tokio::net::TcpStream;
+    /// async fn redis_cli(session: &mut Session) -> shenron::Result {
+    ///     let upstream = TcpStream::connect("127.0.0.1:6379").await?;
+    ///
+    ///     session.pipe(upstream).await
+    /// }
+    /// ```
+    ///
+    /// The client's EOF shuts down `io`'s write side, and reading carries
+    /// on; when `io`'s output ends the client gets EOF and `pipe` returns,
+    /// even if the client was still typing. For a child process, join its
+    /// pipes with [`tokio::io::join`]. Resizes update [`pty`](Self::pty)
+    /// along the way; signals are dropped.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if either side fails, including the client closing
+    /// the channel before `io` finishes.
+    pub async fn pipe<T>(&mut self, io: T) -> crate::Result
+    where
+        T: AsyncRead + AsyncWrite,
+    {
+        self.flush().await?;
+        crate::session::io::pipe(self.io(), io).await?;
+
+        Ok(())
     }
 
     /// Begin an own-the-loop session: merges SSH input with application
@@ -284,37 +1044,204 @@ impl Session {
         self.pty.is_some() || matches!(self.kind, SessionKind::Shell)
     }
 
-    fn channel(&self) -> crate::Result<&Channel<Msg>> {
-        self.channel
+    fn writer(&self) -> crate::Result<&Arc<ChannelWriteHalf<Msg>>> {
+        self.writer
             .as_ref()
             .ok_or_else(|| crate::Error::Protocol("channel unavailable".into()))
     }
 
-    /// Take ownership of the underlying channel, leaving the session without one.
+    pub(crate) const fn reader_mut(&mut self) -> Option<&mut ChannelReadHalf> {
+        self.reader.as_mut()
+    }
+
+    pub(crate) const fn mark_eof_sent(&mut self) {
+        self.eof_sent = true;
+    }
+
+    /// Take the channel over as a byte stream, for subsystems that drive
+    /// their own protocol over it — the way the builtin SFTP server does.
+    ///
+    /// ```no_run
+    /// # use shenron::Session;
+    /// async fn echo(session: &mut Session) -> shenron::Result {
+    ///     let stream = session.take_stream()?;
+    ///     let (mut reader, mut writer) = tokio::io::split(stream);
+    ///     tokio::io::copy(&mut reader, &mut writer).await?;
     ///
-    /// Subsequent reads/writes on the session will fail. Used by subsystems
-    /// like SFTP that need to drive the raw channel themselves.
-    #[cfg(feature = "sftp")]
-    pub(crate) const fn take_channel(&mut self) -> Option<Channel<Msg>> {
-        self.channel.take()
+    ///     Ok(())
+    /// }
+    /// ```
+    ///
+    /// The session is left [detached](Self::is_detached): reads see no
+    /// more input, writes fail, and the handler's return no longer sends an
+    /// exit status — use [`SessionStream::close`] for that. Everything
+    /// else (user, extensions, [`stats`](Self::stats)) stays available.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the channel was already taken.
+    pub fn take_stream(&mut self) -> crate::Result<SessionStream> {
+        let (Some(reader), Some(writer)) = (self.reader.take(), self.writer.take()) else {
+            return Err(crate::Error::Protocol("channel already taken".into()));
+        };
+
+        // The stream's owner drives the channel; there's no screen to keep.
+        #[cfg(feature = "resume")]
+        self.suspend = None;
+
+        Ok(SessionStream::new(
+            reader,
+            writer,
+            Arc::clone(&self.counters),
+        ))
     }
 
-    /// Send the exit status, EOF, and close the channel. Idempotent — once a
-    /// session has finished, later calls (and a later natural handler return)
-    /// no-op.
-    pub(crate) async fn finish(&mut self, code: u32) -> crate::Result {
+    /// Resolves once the channel is closed — by the client, by the
+    /// connection dropping, or by the session finishing. Doesn't borrow
+    /// the session, so background tasks can stop promptly instead of
+    /// finding out from a failed write:
+    ///
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// # use shenron::Session;
+    /// async fn clock(session: &mut Session) -> shenron::Result {
+    ///     let out = session.stdout()?;
+    ///     let closed = session.closed();
+    ///
+    ///     tokio::spawn(async move {
+    ///         let tick = async {
+    ///             loop {
+    ///                 tokio::time::sleep(Duration::from_secs(1)).await;
+    ///                 let _ = out.write_str(".").await;
+    ///             }
+    ///         };
+    ///
+    ///         tokio::select! {
+    ///             () = tick => {}
+    ///             () = closed => {}
+    ///         }
+    ///     });
+    ///
+    ///     while session.next().await.is_some() {}
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn closed(&self) -> impl Future<Output = ()> + Send + 'static {
+        let mut closed = self.closed.subscribe();
+
+        async move {
+            let _ = closed.wait_for(|closed| *closed).await;
+        }
+    }
+
+    /// Call `tap` with everything that passes through the session from now
+    /// on: the client's input as it's read, and output to stdout and stderr
+    /// as it's sent, by the session, its [`ChannelWriter`]s, its
+    /// [`io`](Self::io) adapter, or a [`SessionStream`]. For recording and
+    /// auditing; see [`Recorder`](crate::middleware::Recorder).
+    ///
+    /// The tap runs inline on the I/O path, so it should be quick: hand the
+    /// data to a channel rather than writing it out. It's removed when the
+    /// returned [`Tap`] is dropped.
+    pub fn tap(&self, tap: impl Fn(Direction, &[u8]) + Send + Sync + 'static) -> Tap {
+        let id = self.counters.taps.add(Box::new(tap));
+
+        Tap::new(&self.counters, id)
+    }
+
+    /// Messages the operator sends every session with
+    /// [`ServerHandle::broadcast`](crate::ServerHandle::broadcast), from now
+    /// on. The [`wall`](crate::middleware::wall) middleware prints them;
+    /// subscribe directly to show them some other way, e.g. in a TUI's
+    /// status bar.
+    #[must_use]
+    pub fn broadcasts(&self) -> broadcast::Receiver<String> {
+        self.broadcasts.subscribe()
+    }
+
+    /// The server's [`Bus`], shared by all its sessions, for publishing to
+    /// and subscribing to topics.
+    #[must_use]
+    pub const fn bus(&self) -> &Bus {
+        &self.bus
+    }
+
+    /// Whether the channel has closed; see [`closed`](Self::closed).
+    #[must_use]
+    pub fn is_closed(&self) -> bool {
+        *self.closed.borrow()
+    }
+
+    /// Whether [`take_stream`](Self::take_stream) has taken the channel.
+    #[must_use]
+    pub const fn is_detached(&self) -> bool {
+        self.writer.is_none()
+    }
+
+    /// Send the exit status (or signal), EOF, and close the channel.
+    /// Idempotent — once a session has finished, later calls (and a later
+    /// natural handler return) no-op.
+    pub(crate) async fn conclude(&mut self, exit: &Exit) -> crate::Result {
         if self.exited {
             return Ok(());
         }
 
-        let Some(channel) = self.channel.as_ref() else {
+        // A user who came back while the handler was only writing gets the
+        // exit status on their channel; a finished session isn't resumed.
+        #[cfg(feature = "resume")]
+        self.catch_up();
+        let _ = self.flush().await;
+        #[cfg(feature = "resume")]
+        self.suspend = None;
+
+        let Some(channel) = self.writer.as_ref() else {
             return Ok(());
         };
 
         self.exited = true;
 
-        channel.exit_status(code).await?;
-        channel.eof().await?;
-        channel.close().await.map_err(crate::Error::Ssh)
+        if let Exit::Signal {
+            signal,
+            core_dumped,
+            message,
+        } = exit
+        {
+            self.handle
+                .exit_signal_request(
+                    channel.id(),
+                    signal.clone(),
+                    *core_dumped,
+                    message.clone(),
+                    String::new(),
+                )
+                .await
+                .map_err(|()| crate::Error::Protocol("connection closed".into()))?;
+        } else {
+            channel.exit_status(exit.code()).await?;
+        }
+
+        if !self.eof_sent {
+            channel.eof().await?;
+        }
+
+        let result = channel.close().await.map_err(crate::Error::Ssh);
+        self.closed.send_replace(true);
+
+        result
+    }
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        // The server concludes every session its handler returns from, so
+        // this is a panic, or a session held somewhere it outlived its task;
+        // either way the client waits for an exit status that never comes.
+        if cfg!(debug_assertions) && !self.exited && self.writer.is_some() {
+            tracing::warn!(
+                session = %self.id,
+                "session dropped without an exit status; the client may hang until it times out"
+            );
+        }
     }
 }
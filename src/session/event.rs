@@ -6,4 +6,9 @@ pub enum Event {
     Resize(PtySize),
     Signal(Signal),
     Eof,
+    /// The client sent nothing for the session's
+    /// [idle timeout](crate::Session::set_idle_timeout), or the one given to
+    /// [`next_timeout`](crate::Session::next_timeout). Never reported
+    /// otherwise.
+    IdleTimeout,
 }
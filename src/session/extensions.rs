@@ -17,6 +17,14 @@ use std::{
 #[derive(Default, Clone)]
 pub struct Extensions(HashMap<TypeId, Box<dyn CloneAny>>);
 
+impl std::fmt::Debug for Extensions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Extensions")
+            .field("len", &self.0.len())
+            .finish_non_exhaustive()
+    }
+}
+
 /// Object-safe `Any + Clone`. `DynClone` makes `Box<dyn CloneAny>: Clone`;
 /// the `as_any*`/`into_any` accessors recover `dyn Any` for downcasting,
 /// which a subtrait of `Any` can't do directly.
@@ -66,6 +74,45 @@ impl Extensions {
         (**boxed).as_any_mut().downcast_mut::<T>()
     }
 
+    /// Mutably borrow the stored value of type `T`, inserting `init()`
+    /// first if there isn't one — for a counter or bucket that middleware
+    /// creates on first use.
+    #[expect(
+        clippy::missing_panics_doc,
+        reason = "values are keyed by their own TypeId"
+    )]
+    pub fn get_or_insert_with<T: Any + Clone + Send + Sync>(
+        &mut self,
+        init: impl FnOnce() -> T,
+    ) -> &mut T {
+        let boxed = self
+            .0
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(init()));
+
+        (**boxed)
+            .as_any_mut()
+            .downcast_mut::<T>()
+            .expect("extensions are keyed by their own TypeId")
+    }
+
+    /// Whether a value of type `T` is stored.
+    #[must_use]
+    pub fn contains<T: Any>(&self) -> bool {
+        self.0.contains_key(&TypeId::of::<T>())
+    }
+
+    /// How many values are stored.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
     /// Take the stored value of type `T` out of the bag, if present.
     pub fn remove<T: Any>(&mut self) -> Option<T> {
         let boxed = self.0.remove(&TypeId::of::<T>())?;
@@ -143,6 +190,19 @@ mod tests {
         assert_eq!(ext.remove::<Account>(), None);
     }
 
+    #[test]
+    fn get_or_insert_with_initializes_once() {
+        let mut ext = Extensions::default();
+
+        ext.get_or_insert_with(|| Account(0)).0 += 1;
+        ext.get_or_insert_with(|| Account(100)).0 += 1;
+
+        assert_eq!(ext.get::<Account>(), Some(&Account(2)));
+        assert!(ext.contains::<Account>());
+        assert!(!ext.contains::<RequestId>());
+        assert_eq!(ext.len(), 1);
+    }
+
     #[test]
     fn clones_are_independent() {
         let mut original = Extensions::default();
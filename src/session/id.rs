@@ -0,0 +1,48 @@
+use std::fmt;
+
+/// Identifies one SSH connection, for correlating its auth attempts and
+/// sessions in logs and audit sinks. Random, so unique across restarts and
+/// server instances in practice; displayed as 16 hex digits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ConnectionId(u64);
+
+/// Identifies one session (a channel running the middleware chain) within
+/// its [`ConnectionId`]. Random like it, and displayed the same way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SessionId(u64);
+
+impl ConnectionId {
+    pub(crate) fn new() -> Self {
+        Self(rand::random())
+    }
+
+    /// The raw value.
+    #[must_use]
+    pub const fn as_u64(self) -> u64 {
+        self.0
+    }
+}
+
+impl SessionId {
+    pub(crate) fn new() -> Self {
+        Self(rand::random())
+    }
+
+    /// The raw value.
+    #[must_use]
+    pub const fn as_u64(self) -> u64 {
+        self.0
+    }
+}
+
+impl fmt::Display for ConnectionId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+impl fmt::Display for SessionId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
@@ -0,0 +1,161 @@
+use std::{
+    io,
+    pin::{Pin, pin},
+    task::{Context, Poll, ready},
+};
+
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt, ReadBuf};
+
+use crate::{ChannelWriter, Event, Session};
+
+/// A [`Session`] as a byte stream: reads are the client's input, writes go
+/// to its stdout. Built by [`Session::io`].
+///
+/// ```no_run
+/// # use shenron::Session;
+/// // An echo server.
+/// async fn echo(session: &mut Session) -> shenron::Result {
+///     let (mut reader, mut writer) = tokio::io::split(session.io());
+///     tokio::io::copy(&mut reader, &mut writer).await?;
+///
+///     Ok(())
+/// }
+/// ```
+///
+/// Reading ends (returns 0 bytes) at the client's EOF. Resizes arriving in
+/// between still update [`Session::pty`], but signals are dropped; use
+/// [`Session::next`] to see them. Writes respect the channel's flow
+/// control, and shutting the writer down sends EOF.
+///
+/// Borrows the session; drop it to use the session again.
+pub struct SessionIo<'a> {
+    session: &'a mut Session,
+    /// Input received but not yet read, and how far into it reads got.
+    pending: Option<(Vec<u8>, usize)>,
+    eof: bool,
+    writer: Option<ChannelWriter>,
+}
+
+impl<'a> SessionIo<'a> {
+    pub(crate) const fn new(session: &'a mut Session) -> Self {
+        Self {
+            session,
+            pending: None,
+            eof: false,
+            writer: None,
+        }
+    }
+
+    fn writer(&mut self) -> io::Result<Pin<&mut ChannelWriter>> {
+        if self.writer.is_none() {
+            let writer = self
+                .session
+                .stdout()
+                .map_err(|_| io::Error::from(io::ErrorKind::NotConnected))?;
+
+            self.writer = Some(writer);
+        }
+
+        Ok(Pin::new(self.writer.as_mut().expect("writer just set")))
+    }
+}
+
+impl AsyncRead for SessionIo<'_> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = &mut *self;
+
+        while this.pending.is_none() && !this.eof {
+            let Some(channel) = this.session.reader_mut() else {
+                this.eof = true;
+                break;
+            };
+
+            // `wait` is a channel receive, so polling a fresh one each time
+            // loses nothing.
+            let Some(msg) = ready!(pin!(channel.wait()).poll(cx)) else {
+                this.eof = true;
+                break;
+            };
+
+            match this.session.apply(msg) {
+                Some(Event::Input(data)) if !data.is_empty() => this.pending = Some((data, 0)),
+                Some(Event::Eof) => this.eof = true,
+                _ => {}
+            }
+        }
+
+        drain(&mut this.pending, buf);
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncWrite for SessionIo<'_> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.writer()?.poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.writer()?.poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        ready!(self.writer()?.poll_shutdown(cx))?;
+        self.session.mark_eof_sent();
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// [`Session::pipe`]: copy both ways, finishing with `upstream`'s output.
+pub async fn pipe<T>(session: SessionIo<'_>, upstream: T) -> io::Result<()>
+where
+    T: AsyncRead + AsyncWrite,
+{
+    let (mut client_rx, mut client_tx) = tokio::io::split(session);
+    let (mut upstream_rx, mut upstream_tx) = tokio::io::split(upstream);
+
+    let inbound = async {
+        tokio::io::copy(&mut client_rx, &mut upstream_tx).await?;
+        upstream_tx.shutdown().await
+    };
+    let outbound = async {
+        tokio::io::copy(&mut upstream_rx, &mut client_tx).await?;
+        client_tx.shutdown().await
+    };
+
+    let mut inbound = pin!(inbound);
+    let mut outbound = pin!(outbound);
+    let mut client_done = false;
+
+    loop {
+        tokio::select! {
+            result = &mut inbound, if !client_done => {
+                result?;
+                client_done = true;
+            }
+            result = &mut outbound => return result,
+        }
+    }
+}
+
+/// Copy as much pending input into `buf` as fits.
+pub fn drain(pending: &mut Option<(Vec<u8>, usize)>, buf: &mut ReadBuf<'_>) {
+    if let Some((data, offset)) = pending {
+        let n = buf.remaining().min(data.len() - *offset);
+        buf.put_slice(&data[*offset..*offset + n]);
+        *offset += n;
+
+        if *offset == data.len() {
+            *pending = None;
+        }
+    }
+}
@@ -0,0 +1,364 @@
+use std::{cmp::Ordering, fmt::Write as _};
+
+/// The state behind [`Session::read_line`](crate::Session::read_line): the
+/// line being edited, and input that arrived past the end of the last one.
+#[derive(Debug, Default)]
+pub struct LineEditor {
+    prompt: String,
+    line: Vec<char>,
+    cursor: usize,
+    /// Bytes not yet handled: typed-ahead lines, or the start of an escape
+    /// sequence or UTF-8 character split across reads.
+    pending: Vec<u8>,
+    /// The last line ended at a CR, so an LF right after it is part of the
+    /// same Enter.
+    after_cr: bool,
+}
+
+/// How far [`LineEditor::feed`] got.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Step {
+    /// The line isn't finished; feed more input.
+    Pending,
+    /// Enter was pressed.
+    Done(String),
+    /// Ctrl+C, or Ctrl+D on an empty line.
+    Cancelled,
+}
+
+impl LineEditor {
+    /// Start a new line after `prompt`, which the caller has already shown.
+    pub fn start(&mut self, prompt: &str) {
+        prompt.clone_into(&mut self.prompt);
+        self.line.clear();
+        self.cursor = 0;
+    }
+
+    pub fn push(&mut self, input: &[u8]) {
+        self.pending.extend_from_slice(input);
+    }
+
+    /// Apply pending input until the line is finished or input runs out,
+    /// appending what the client's terminal should show to `out` when
+    /// `echo` is on.
+    pub fn feed(&mut self, echo: bool, out: &mut Vec<u8>) -> Step {
+        let mut display = Vec::new();
+        let mut consumed = 0;
+
+        let step = loop {
+            let input = &self.pending[consumed..];
+
+            let Some(&byte) = input.first() else {
+                break Step::Pending;
+            };
+
+            if self.after_cr {
+                self.after_cr = false;
+
+                if byte == b'\n' {
+                    consumed += 1;
+                    continue;
+                }
+            }
+
+            let (used, key) = match parse(input) {
+                Parsed::Key(used, key) => (used, key),
+                Parsed::Skip(used) => {
+                    consumed += used;
+                    continue;
+                }
+                Parsed::Incomplete => break Step::Pending,
+            };
+
+            consumed += used;
+
+            match key {
+                Key::Enter { cr } => {
+                    self.after_cr = cr;
+                    display.extend_from_slice(b"\r\n");
+
+                    break Step::Done(self.line.drain(..).collect());
+                }
+                Key::Interrupt => {
+                    display.extend_from_slice(b"^C\r\n");
+
+                    break Step::Cancelled;
+                }
+                Key::EndOfFile if self.line.is_empty() => {
+                    display.extend_from_slice(b"\r\n");
+
+                    break Step::Cancelled;
+                }
+                Key::Char(c) if self.cursor == self.line.len() => {
+                    self.line.push(c);
+                    self.cursor += 1;
+
+                    let mut utf8 = [0; 4];
+                    display.extend_from_slice(c.encode_utf8(&mut utf8).as_bytes());
+                }
+                key => self.edit(key, &mut display),
+            }
+        };
+
+        self.pending.drain(..consumed);
+
+        if echo {
+            out.extend(display);
+        }
+
+        step
+    }
+
+    /// Apply an editing key, showing the result: cursor moves as moves,
+    /// anything else by redrawing the line.
+    fn edit(&mut self, key: Key, display: &mut Vec<u8>) {
+        let len = self.line.len();
+
+        let target = match key {
+            Key::Left => self.cursor.saturating_sub(1),
+            Key::Right => (self.cursor + 1).min(len),
+            Key::Home => 0,
+            Key::End => len,
+            key => {
+                if self.change(key) {
+                    self.redraw(display);
+                }
+
+                return;
+            }
+        };
+
+        let moved = match target.cmp(&self.cursor) {
+            Ordering::Less => format!("\x1b[{}D", self.cursor - target),
+            Ordering::Greater => format!("\x1b[{}C", target - self.cursor),
+            Ordering::Equal => String::new(),
+        };
+
+        display.extend_from_slice(moved.as_bytes());
+        self.cursor = target;
+    }
+
+    /// Change the line's contents; `true` if anything changed.
+    fn change(&mut self, key: Key) -> bool {
+        let before = (self.line.len(), self.cursor);
+
+        match key {
+            Key::Char(c) => {
+                self.line.insert(self.cursor, c);
+                self.cursor += 1;
+            }
+            Key::Backspace if self.cursor > 0 => {
+                self.cursor -= 1;
+                self.line.remove(self.cursor);
+            }
+            Key::Delete | Key::EndOfFile if self.cursor < self.line.len() => {
+                self.line.remove(self.cursor);
+            }
+            Key::KillLine => {
+                self.line.drain(..self.cursor);
+                self.cursor = 0;
+            }
+            Key::KillWord => {
+                let start = self.line[..self.cursor]
+                    .iter()
+                    .rposition(|c| !c.is_whitespace())
+                    .map_or(0, |end| {
+                        self.line[..end]
+                            .iter()
+                            .rposition(|c| c.is_whitespace())
+                            .map_or(0, |space| space + 1)
+                    });
+
+                self.line.drain(start..self.cursor);
+                self.cursor = start;
+            }
+            _ => {}
+        }
+
+        before != (self.line.len(), self.cursor)
+    }
+
+    /// Rewrite the whole line and put the cursor back where it belongs.
+    fn redraw(&self, display: &mut Vec<u8>) {
+        let mut s = format!("\r{}", self.prompt);
+        s.extend(&self.line);
+        s.push_str("\x1b[K");
+
+        let behind = self.line.len() - self.cursor;
+        if behind > 0 {
+            let _ = write!(s, "\x1b[{behind}D");
+        }
+
+        display.extend_from_slice(s.as_bytes());
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Key {
+    Char(char),
+    Enter { cr: bool },
+    Backspace,
+    Delete,
+    KillLine,
+    KillWord,
+    Left,
+    Right,
+    Home,
+    End,
+    Interrupt,
+    EndOfFile,
+}
+
+enum Parsed {
+    /// A key, and how many bytes it took.
+    Key(usize, Key),
+    /// Bytes that mean nothing to a line editor.
+    Skip(usize),
+    /// The start of something; wait for more.
+    Incomplete,
+}
+
+fn parse(input: &[u8]) -> Parsed {
+    let key = match input[0] {
+        b'\r' => Key::Enter { cr: true },
+        b'\n' => Key::Enter { cr: false },
+        0x7f | 0x08 => Key::Backspace,
+        0x01 => Key::Home,
+        0x02 => Key::Left,
+        0x03 => Key::Interrupt,
+        0x04 => Key::EndOfFile,
+        0x05 => Key::End,
+        0x06 => Key::Right,
+        0x15 => Key::KillLine,
+        0x17 => Key::KillWord,
+        0x1b => return parse_escape(input),
+        byte if byte < 0x20 => return Parsed::Skip(1),
+        _ => return parse_char(input),
+    };
+
+    Parsed::Key(1, key)
+}
+
+/// CSI (`ESC [`) and SS3 (`ESC O`) sequences; the editing keys among them
+/// become keys, the rest are skipped whole.
+fn parse_escape(input: &[u8]) -> Parsed {
+    match input.get(1) {
+        None => Parsed::Incomplete,
+        Some(b'O') => match input.get(2) {
+            None => Parsed::Incomplete,
+            Some(&last) => arrow(last).map_or(Parsed::Skip(3), |key| Parsed::Key(3, key)),
+        },
+        Some(b'[') => {
+            let Some(end) = input[2..].iter().position(|b| (0x40..=0x7e).contains(b)) else {
+                return Parsed::Incomplete;
+            };
+            let len = end + 3;
+
+            let key = match &input[2..len] {
+                [last] => arrow(*last),
+                b"1~" | b"7~" => Some(Key::Home),
+                b"4~" | b"8~" => Some(Key::End),
+                b"3~" => Some(Key::Delete),
+                _ => None,
+            };
+
+            key.map_or(Parsed::Skip(len), |key| Parsed::Key(len, key))
+        }
+        // Alt+key: drop the escape, keep the key.
+        Some(_) => Parsed::Skip(1),
+    }
+}
+
+const fn arrow(last: u8) -> Option<Key> {
+    match last {
+        b'C' => Some(Key::Right),
+        b'D' => Some(Key::Left),
+        b'H' => Some(Key::Home),
+        b'F' => Some(Key::End),
+        _ => None,
+    }
+}
+
+fn parse_char(input: &[u8]) -> Parsed {
+    let len = input.len().min(4);
+
+    match std::str::from_utf8(&input[..len]) {
+        Ok(s) => s
+            .chars()
+            .next()
+            .map_or(Parsed::Skip(1), |c| Parsed::Key(c.len_utf8(), Key::Char(c))),
+        Err(e) if e.valid_up_to() > 0 => {
+            let c = std::str::from_utf8(&input[..e.valid_up_to()])
+                .ok()
+                .and_then(|s| s.chars().next());
+
+            c.map_or(Parsed::Skip(1), |c| Parsed::Key(c.len_utf8(), Key::Char(c)))
+        }
+        Err(e) if e.error_len().is_none() => Parsed::Incomplete,
+        Err(_) => Parsed::Skip(1),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(inputs: &[&[u8]]) -> (Step, String) {
+        let mut editor = LineEditor::default();
+        editor.start("> ");
+
+        let mut out = Vec::new();
+        let mut step = Step::Pending;
+
+        for input in inputs {
+            editor.push(input);
+            step = editor.feed(true, &mut out);
+        }
+
+        (step, String::from_utf8(out).expect("utf-8"))
+    }
+
+    #[test]
+    fn edits_the_line() {
+        let cases: [(&[&[u8]], &str); 7] = [
+            (&[b"hello\r"], "hello"),
+            (&[b"helo\x7f\x7fllo\r"], "hello"),
+            (
+                &[b"world\x1b[D\x1b[D\x1b[D\x1b[D\x1bODhello \r"],
+                "hello world",
+            ),
+            (&[b"junk\x15hi\r"], "hi"),
+            (&[b"one two  \x17three\r"], "one three"),
+            (&[b"ab\x01\x1b[3~\x05c\n"], "bc"),
+            (&[b"caf\xc3", b"\xa9 \x1b", b"[Dx\r"], "caf\u{e9}x "),
+        ];
+
+        for (inputs, line) in cases {
+            assert_eq!(run(inputs).0, Step::Done(line.into()), "{inputs:?}");
+        }
+    }
+
+    #[test]
+    fn echoes_appends_and_redraws_edits() {
+        let (_, out) = run(&[b"ab\x1b[Dc\r"]);
+
+        assert_eq!(out, "ab\x1b[1D\r> acb\x1b[K\x1b[1D\r\n");
+    }
+
+    #[test]
+    fn cancels_and_keeps_typed_ahead_input() {
+        assert_eq!(run(&[b"oops\x03"]).0, Step::Cancelled);
+        assert_eq!(run(&[b"\x04"]).0, Step::Cancelled);
+
+        let mut editor = LineEditor::default();
+        let mut out = Vec::new();
+
+        editor.start("");
+        editor.push(b"first\r\nsecond\r");
+        assert_eq!(editor.feed(false, &mut out), Step::Done("first".into()));
+
+        editor.start("");
+        assert_eq!(editor.feed(false, &mut out), Step::Done("second".into()));
+        assert!(out.is_empty());
+    }
+}
@@ -1,13 +1,34 @@
 pub use russh::Sig as Signal;
 
+mod bus;
 pub mod core;
 mod event;
 mod extensions;
+mod id;
+mod io;
 mod kind;
+mod line;
 mod pty;
+#[cfg(feature = "resume")]
+mod resume;
+mod stats;
+mod stream;
+mod tap;
+mod utf8;
+mod writer;
 
+pub use bus::Bus;
 pub use core::*;
 pub use event::*;
 pub use extensions::*;
+pub use id::{ConnectionId, SessionId};
+pub use io::SessionIo;
 pub use kind::*;
 pub use pty::*;
+#[cfg(feature = "resume")]
+pub use resume::{Attachment, Resumable};
+pub use stats::SessionStats;
+pub use stream::SessionStream;
+pub use tap::{Direction, Tap};
+pub use utf8::Utf8Decoder;
+pub use writer::ChannelWriter;
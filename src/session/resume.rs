@@ -0,0 +1,367 @@
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use russh::{
+    Channel, ChannelReadHalf, ChannelWriteHalf,
+    server::{Handle, Msg},
+};
+use tokio::{
+    sync::{mpsc, watch},
+    task::JoinHandle,
+};
+
+use crate::{
+    ConnectionId, PtySize, SessionId,
+    session::{stats::Counters, writer},
+};
+
+type Parked = mpsc::UnboundedSender<Attachment>;
+
+/// Suspended sessions waiting for their users to come back, shared by every
+/// connection of a server with
+/// [`resumable_sessions`](crate::Server::resumable_sessions) on.
+#[derive(Default)]
+pub struct Resumable {
+    suspended: Mutex<HashMap<String, Vec<Parked>>>,
+}
+
+impl Resumable {
+    /// Take `user`'s most recently suspended session, if one is still
+    /// waiting.
+    pub fn take(&self, user: &str) -> Option<Parked> {
+        let mut suspended = self.suspended.lock().expect("resumable sessions poisoned");
+        let parked = suspended.get_mut(user)?;
+
+        parked.retain(|tx| !tx.is_closed());
+        let tx = parked.pop();
+
+        if parked.is_empty() {
+            suspended.remove(user);
+        }
+
+        tx
+    }
+
+    fn park(&self, user: &str, tx: &Parked) {
+        let mut suspended = self.suspended.lock().expect("resumable sessions poisoned");
+        let parked = suspended.entry(user.to_string()).or_default();
+
+        parked.retain(|other| !other.is_closed() && !other.same_channel(tx));
+        parked.push(tx.clone());
+        drop(suspended);
+    }
+
+    fn unpark(&self, user: &str, tx: &Parked) {
+        let mut suspended = self.suspended.lock().expect("resumable sessions poisoned");
+
+        if let Some(parked) = suspended.get_mut(user) {
+            parked.retain(|other| !other.same_channel(tx));
+
+            if parked.is_empty() {
+                suspended.remove(user);
+            }
+        }
+    }
+}
+
+/// A new channel for a suspended session: the returning user's shell
+/// request, and the connection it came in on.
+pub struct Attachment {
+    pub channel: Channel<Msg>,
+    pub handle: Handle,
+    pub connection_id: ConnectionId,
+    pub remote_addr: SocketAddr,
+    pub local_addr: SocketAddr,
+    pub pty: Option<(String, PtySize)>,
+    pub closed: watch::Sender<bool>,
+}
+
+/// A returning user's channel, already showing the screen they left, for
+/// the session to switch to.
+pub struct Resumed {
+    pub reader: ChannelReadHalf,
+    pub writer: Arc<ChannelWriteHalf<Msg>>,
+    pub handle: Handle,
+    pub connection_id: ConnectionId,
+    pub remote_addr: SocketAddr,
+    pub local_addr: SocketAddr,
+    pub pty: Option<(String, PtySize)>,
+    pub closed: watch::Sender<bool>,
+}
+
+/// Where a resumable session's output goes: the channel it's on, or `None`
+/// once its user has been gone past the timeout.
+type Output = Arc<tokio::sync::Mutex<Option<Arc<ChannelWriteHalf<Msg>>>>>;
+
+/// A resumable session's side. Its output is kept on a virtual terminal;
+/// a watcher suspends the session as soon as its channel closes and, when
+/// the user comes back, draws that terminal's screen on their new one and
+/// moves the output over.
+pub struct Suspend {
+    timeout: watch::Sender<Duration>,
+    screen: Arc<Mutex<vt100::Parser>>,
+    output: Output,
+    resumed: mpsc::UnboundedReceiver<Resumed>,
+    watcher: JoinHandle<()>,
+}
+
+impl Suspend {
+    #[expect(clippy::too_many_arguments, reason = "pub(crate), one call site")]
+    pub fn new(
+        resumable: Arc<Resumable>,
+        id: SessionId,
+        user: String,
+        timeout: Duration,
+        writer: Arc<ChannelWriteHalf<Msg>>,
+        closed: &watch::Sender<bool>,
+        size: Option<PtySize>,
+        counters: Arc<Counters>,
+    ) -> Self {
+        // Without a pty, a terminal's usual size.
+        let (rows, cols) = size.map_or((24, 80), dimensions);
+        let screen = Arc::new(Mutex::new(vt100::Parser::new(rows, cols, 0)));
+        let output = Arc::new(tokio::sync::Mutex::new(Some(writer)));
+        let (timeout, timeout_rx) = watch::channel(timeout);
+        let (resumed_tx, resumed) = mpsc::unbounded_channel();
+        let (tx, rx) = mpsc::unbounded_channel();
+
+        let watcher = Watcher {
+            resumable,
+            id,
+            user,
+            timeout: timeout_rx,
+            closed: closed.subscribe(),
+            tx,
+            rx,
+            screen: Arc::clone(&screen),
+            output: Arc::clone(&output),
+            counters,
+            resumed: resumed_tx,
+        };
+
+        Self {
+            timeout,
+            screen,
+            output,
+            resumed,
+            watcher: tokio::spawn(watcher.run()),
+        }
+    }
+
+    pub fn timeout(&self) -> Duration {
+        *self.timeout.borrow()
+    }
+
+    pub fn set_timeout(&self, timeout: Duration) {
+        self.timeout.send_replace(timeout);
+    }
+
+    /// Keep the virtual terminal the size of the client's.
+    pub fn resize(&self, size: PtySize) {
+        let (rows, cols) = dimensions(size);
+
+        self.screen
+            .lock()
+            .expect("screen poisoned")
+            .set_size(rows, cols);
+    }
+
+    /// Put `data` on the virtual terminal and send it to the user, if
+    /// they're there.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` once the user has been gone past the timeout.
+    pub async fn send(&self, ext: Option<u32>, data: &[u8], counters: &Counters) -> crate::Result {
+        let output = self.output.lock().await;
+
+        let Some(writer) = output.as_ref() else {
+            return Err(crate::Error::Protocol("session expired".into()));
+        };
+
+        self.screen.lock().expect("screen poisoned").process(data);
+
+        // Suspended, the channel is gone and this fails; the output is on
+        // the screen for when the user comes back.
+        let _ = writer::send(writer, ext, data, counters).await;
+
+        Ok(())
+    }
+
+    /// A channel the user came back on, if one's waiting.
+    pub fn try_resumed(&mut self) -> Option<Resumed> {
+        self.resumed.try_recv().ok()
+    }
+
+    /// Wait for the user to come back; `None` if they don't in time.
+    pub async fn resumed(&mut self) -> Option<Resumed> {
+        self.resumed.recv().await
+    }
+}
+
+impl Drop for Suspend {
+    fn drop(&mut self) {
+        self.watcher.abort();
+    }
+}
+
+/// Suspends a session when its channel closes, and brings returning users'
+/// channels up to date.
+struct Watcher {
+    resumable: Arc<Resumable>,
+    id: SessionId,
+    user: String,
+    timeout: watch::Receiver<Duration>,
+    /// The current channel's close signal.
+    closed: watch::Receiver<bool>,
+    tx: Parked,
+    rx: mpsc::UnboundedReceiver<Attachment>,
+    screen: Arc<Mutex<vt100::Parser>>,
+    output: Output,
+    counters: Arc<Counters>,
+    resumed: mpsc::UnboundedSender<Resumed>,
+}
+
+impl Watcher {
+    async fn run(mut self) {
+        loop {
+            // A dropped sender means the connection is gone too.
+            let _ = self.closed.wait_for(|closed| *closed).await;
+
+            tracing::info!(session = %self.id, user = %self.user, "session suspended");
+
+            let Some(attachment) = self.wait().await else {
+                // Dropping `resumed` tells the session its user is gone.
+                *self.output.lock().await = None;
+
+                return;
+            };
+
+            let resumed = self.resume(attachment).await;
+            self.closed = resumed.closed.subscribe();
+
+            if self.resumed.send(resumed).is_err() {
+                return;
+            }
+        }
+    }
+
+    /// Wait, as `user`'s suspended session, for them to come back; `None`
+    /// once the timeout passes without them.
+    async fn wait(&mut self) -> Option<Attachment> {
+        self.resumable.park(&self.user, &self.tx);
+
+        let timeout = *self.timeout.borrow();
+
+        if let Ok(attachment) = tokio::time::timeout(timeout, self.rx.recv()).await {
+            return attachment;
+        }
+
+        // Once unparked no new attachment can arrive, but one may have come
+        // in just before.
+        self.resumable.unpark(&self.user, &self.tx);
+
+        self.rx.try_recv().ok()
+    }
+
+    /// Draw the screen the user left on their new terminal and send output
+    /// there from now on.
+    async fn resume(&self, attachment: Attachment) -> Resumed {
+        let (reader, writer) = attachment.channel.split();
+        let writer = Arc::new(writer);
+
+        // Held until the replay is sent, so no output slips in before it.
+        let mut output = self.output.lock().await;
+
+        let replay = {
+            let mut screen = self.screen.lock().expect("screen poisoned");
+
+            if let Some((_, size)) = attachment.pty {
+                let (rows, cols) = dimensions(size);
+                screen.set_size(rows, cols);
+            }
+
+            // Reset the new terminal, then bring it up to date.
+            let mut replay = b"\x1bc".to_vec();
+            replay.extend(screen.screen().state_formatted());
+
+            replay
+        };
+
+        let _ = writer::send(&writer, None, &replay, &self.counters).await;
+        *output = Some(Arc::clone(&writer));
+        drop(output);
+
+        tracing::info!(
+            session = %self.id,
+            connection = %attachment.connection_id,
+            user = %self.user,
+            "session resumed"
+        );
+
+        Resumed {
+            reader,
+            writer,
+            handle: attachment.handle,
+            connection_id: attachment.connection_id,
+            remote_addr: attachment.remote_addr,
+            local_addr: attachment.local_addr,
+            pty: attachment.pty,
+            closed: attachment.closed,
+        }
+    }
+}
+
+impl Drop for Watcher {
+    fn drop(&mut self) {
+        self.resumable.unpark(&self.user, &self.tx);
+        self.rx.close();
+
+        // Returning users handed to a session that then ended: close their
+        // channels rather than leave them hanging.
+        while let Ok(attachment) = self.rx.try_recv() {
+            tokio::spawn(async move {
+                let _ = attachment.channel.close().await;
+            });
+        }
+    }
+}
+
+/// Most rows or columns the virtual terminal takes on. Clients pick the
+/// size, and vt100 allocates every cell up front.
+const MAX_DIMENSION: u16 = 1000;
+
+/// `size` as the virtual terminal's rows and columns, within
+/// [`MAX_DIMENSION`].
+fn dimensions(size: PtySize) -> (u16, u16) {
+    let clamp = |n: u32| u16::try_from(n).map_or(MAX_DIMENSION, |n| n.clamp(1, MAX_DIMENSION));
+
+    (clamp(size.height), clamp(size.width))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MAX_DIMENSION, dimensions};
+    use crate::PtySize;
+
+    #[test]
+    fn terminal_sizes_are_bounded() {
+        let size = |width, height| PtySize {
+            width,
+            height,
+            pixel_width: 0,
+            pixel_height: 0,
+        };
+
+        assert_eq!(dimensions(size(80, 24)), (24, 80));
+        assert_eq!(dimensions(size(0, 0)), (1, 1));
+        assert_eq!(
+            dimensions(size(65_535, u32::MAX)),
+            (MAX_DIMENSION, MAX_DIMENSION)
+        );
+    }
+}
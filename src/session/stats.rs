@@ -0,0 +1,137 @@
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, Instant},
+};
+
+use crate::session::tap::{Direction, Taps};
+
+/// Live byte counts, and the taps watching the bytes go by, shared by a
+/// session and every writer and stream made from it.
+pub struct Counters {
+    started: Instant,
+    read: AtomicU64,
+    written: AtomicU64,
+    stalls: AtomicU64,
+    /// Nanoseconds spent in stalled writes.
+    stalled: AtomicU64,
+    pub taps: Taps,
+}
+
+impl Counters {
+    pub fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            read: AtomicU64::new(0),
+            written: AtomicU64::new(0),
+            stalls: AtomicU64::new(0),
+            stalled: AtomicU64::new(0),
+            taps: Taps::default(),
+        }
+    }
+
+    pub fn add_read(&self, data: &[u8]) {
+        self.read.fetch_add(data.len() as u64, Ordering::Relaxed);
+        self.taps.notify(Direction::Input, data);
+    }
+
+    /// Count `data` sent to stdout (`ext` `None`) or an extended stream.
+    pub fn add_written(&self, ext: Option<u32>, data: &[u8]) {
+        self.written.fetch_add(data.len() as u64, Ordering::Relaxed);
+
+        let direction = if ext.is_some() {
+            Direction::Stderr
+        } else {
+            Direction::Stdout
+        };
+        self.taps.notify(direction, data);
+    }
+
+    pub fn add_stall(&self, waited: Duration) {
+        let nanos = u64::try_from(waited.as_nanos()).unwrap_or(u64::MAX);
+
+        self.stalls.fetch_add(1, Ordering::Relaxed);
+        self.stalled.fetch_add(nanos, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> SessionStats {
+        SessionStats {
+            bytes_read: self.read.load(Ordering::Relaxed),
+            bytes_written: self.written.load(Ordering::Relaxed),
+            elapsed: self.started.elapsed(),
+            window_stalls: self.stalls.load(Ordering::Relaxed),
+            stalled: Duration::from_nanos(self.stalled.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+/// How much a session has transferred so far, from
+/// [`Session::stats`](crate::Session::stats).
+///
+/// Counts channel payload: input the client sent, and what went to its
+/// stdout and stderr through the session, its
+/// [`ChannelWriter`](crate::ChannelWriter)s, or a subsystem that took the
+/// channel over. SSH framing and encryption overhead aren't included.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SessionStats {
+    bytes_read: u64,
+    bytes_written: u64,
+    elapsed: Duration,
+    window_stalls: u64,
+    stalled: Duration,
+}
+
+impl SessionStats {
+    /// Bytes received from the client.
+    #[must_use]
+    pub const fn bytes_read(&self) -> u64 {
+        self.bytes_read
+    }
+
+    /// Bytes sent to the client, stdout and stderr together.
+    #[must_use]
+    pub const fn bytes_written(&self) -> u64 {
+        self.bytes_written
+    }
+
+    /// Time since the session started.
+    #[must_use]
+    pub const fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// Writes that found the client's window shut — it had taken all the
+    /// data it would accept — and had to wait for it to open again. A count
+    /// that keeps growing means the client reads slower than the app
+    /// writes. Counts [`write`](crate::Session::write)s and
+    /// [`ChannelWriter::write`](crate::ChannelWriter::write)s, not writes
+    /// through the `AsyncWrite` adapters.
+    #[must_use]
+    pub const fn window_stalls(&self) -> u64 {
+        self.window_stalls
+    }
+
+    /// Time spent in those stalled writes.
+    #[must_use]
+    pub const fn stalled(&self) -> Duration {
+        self.stalled
+    }
+
+    /// Average bytes per second received over the session so far.
+    #[must_use]
+    pub fn read_rate(&self) -> f64 {
+        rate(self.bytes_read, self.elapsed)
+    }
+
+    /// Average bytes per second sent over the session so far.
+    #[must_use]
+    pub fn write_rate(&self) -> f64 {
+        rate(self.bytes_written, self.elapsed)
+    }
+}
+
+#[expect(clippy::cast_precision_loss, reason = "a rate, not an exact count")]
+fn rate(bytes: u64, elapsed: Duration) -> f64 {
+    let secs = elapsed.as_secs_f64();
+
+    if secs > 0.0 { bytes as f64 / secs } else { 0.0 }
+}
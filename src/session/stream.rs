@@ -0,0 +1,129 @@
+use std::{
+    io,
+    pin::{Pin, pin},
+    sync::Arc,
+    task::{Context, Poll, ready},
+};
+
+use russh::{ChannelMsg, ChannelReadHalf, ChannelWriteHalf, server::Msg};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+use crate::{
+    ChannelWriter,
+    session::{io::drain, stats::Counters},
+};
+
+/// A session's channel, taken over for a subsystem's own protocol.
+///
+/// Built by [`Session::take_stream`](crate::Session::take_stream): the
+/// client's input and stdout as one [`AsyncRead`] + [`AsyncWrite`] stream.
+///
+/// Unlike [`SessionIo`](crate::SessionIo) it owns the channel, so it can be
+/// handed to a protocol library or moved into a task. Dropping it closes the
+/// channel without an exit status; [`close`](Self::close) reports one.
+pub struct SessionStream {
+    rx: ChannelReadHalf,
+    pending: Option<(Vec<u8>, usize)>,
+    eof: bool,
+    tx: ChannelWriter,
+    half: Arc<ChannelWriteHalf<Msg>>,
+    counters: Arc<Counters>,
+    closed: bool,
+}
+
+impl SessionStream {
+    pub(crate) fn new(
+        reader: ChannelReadHalf,
+        half: Arc<ChannelWriteHalf<Msg>>,
+        counters: Arc<Counters>,
+    ) -> Self {
+        Self {
+            rx: reader,
+            pending: None,
+            eof: false,
+            tx: ChannelWriter::new(Arc::clone(&half), None, Arc::clone(&counters)),
+            half,
+            counters,
+            closed: false,
+        }
+    }
+
+    /// A handle writing to the client's stderr, for diagnostics alongside
+    /// the protocol on stdout.
+    #[must_use]
+    pub fn stderr(&self) -> ChannelWriter {
+        ChannelWriter::new(Arc::clone(&self.half), Some(1), Arc::clone(&self.counters))
+    }
+
+    /// Report `code` as the exit status, send EOF, and close the channel.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the channel has already closed.
+    pub async fn close(mut self, code: u32) -> crate::Result {
+        self.closed = true;
+
+        self.half.exit_status(code).await?;
+        self.half.eof().await?;
+        self.half.close().await?;
+
+        Ok(())
+    }
+}
+
+impl Drop for SessionStream {
+    fn drop(&mut self) {
+        if self.closed {
+            return;
+        }
+
+        let half = Arc::clone(&self.half);
+
+        tokio::spawn(async move {
+            let _ = half.close().await;
+        });
+    }
+}
+
+impl AsyncRead for SessionStream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = &mut *self;
+
+        while this.pending.is_none() && !this.eof {
+            match ready!(pin!(this.rx.wait()).poll(cx)) {
+                Some(ChannelMsg::Data { data }) if !data.is_empty() => {
+                    this.counters.add_read(&data);
+                    this.pending = Some((data.to_vec(), 0));
+                }
+                Some(ChannelMsg::Eof) | None => this.eof = true,
+                Some(_) => {}
+            }
+        }
+
+        drain(&mut this.pending, buf);
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncWrite for SessionStream {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.tx).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.tx).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.tx).poll_shutdown(cx)
+    }
+}
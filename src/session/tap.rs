@@ -0,0 +1,78 @@
+use std::sync::{
+    Arc, Mutex, Weak,
+    atomic::{AtomicU64, Ordering},
+};
+
+use crate::session::stats::Counters;
+
+type TapFn = dyn Fn(Direction, &[u8]) + Send + Sync;
+
+/// Which way data passed through a session, for a [`Session::tap`](crate::Session::tap).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// From the client.
+    Input,
+    /// To the client's stdout.
+    Stdout,
+    /// To the client's stderr.
+    Stderr,
+}
+
+/// Callbacks seeing a session's traffic.
+#[derive(Default)]
+pub struct Taps {
+    next_id: AtomicU64,
+    taps: Mutex<Vec<(u64, Box<TapFn>)>>,
+}
+
+impl Taps {
+    pub fn add(&self, tap: Box<TapFn>) -> u64 {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.lock().push((id, tap));
+
+        id
+    }
+
+    pub fn notify(&self, direction: Direction, data: &[u8]) {
+        if data.is_empty() {
+            return;
+        }
+
+        for (_, tap) in self.lock().iter() {
+            tap(direction, data);
+        }
+    }
+
+    fn remove(&self, id: u64) {
+        self.lock().retain(|(other, _)| *other != id);
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<(u64, Box<TapFn>)>> {
+        self.taps.lock().expect("session taps poisoned")
+    }
+}
+
+/// A callback installed with [`Session::tap`](crate::Session::tap); dropping
+/// it removes the callback.
+#[must_use = "the tap is removed when this is dropped"]
+pub struct Tap {
+    counters: Weak<Counters>,
+    id: u64,
+}
+
+impl Tap {
+    pub(crate) fn new(counters: &Arc<Counters>, id: u64) -> Self {
+        Self {
+            counters: Arc::downgrade(counters),
+            id,
+        }
+    }
+}
+
+impl Drop for Tap {
+    fn drop(&mut self) {
+        if let Some(counters) = self.counters.upgrade() {
+            counters.taps.remove(self.id);
+        }
+    }
+}
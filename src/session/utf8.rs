@@ -0,0 +1,112 @@
+/// Turns client input into text when a UTF-8 character can arrive split
+/// across [`Event::Input`](crate::Event::Input) packets.
+///
+/// Each [`decode`](Self::decode) returns the complete characters so far and
+/// holds back the start of one still arriving. Bytes that can never be
+/// UTF-8 become U+FFFD, as in [`String::from_utf8_lossy`].
+/// [`Session::next_text`](crate::Session::next_text) does this for the
+/// session's own input.
+///
+/// ```
+/// # use shenron::Utf8Decoder;
+/// let mut decoder = Utf8Decoder::new();
+///
+/// assert_eq!(decoder.decode(b"caf\xc3"), "caf");
+/// assert_eq!(decoder.decode(b"\xa9!"), "\u{e9}!");
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct Utf8Decoder {
+    pending: Vec<u8>,
+}
+
+impl Utf8Decoder {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            pending: Vec::new(),
+        }
+    }
+
+    /// Decode `input`, after whatever the last call held back.
+    pub fn decode(&mut self, input: &[u8]) -> String {
+        self.pending.extend_from_slice(input);
+
+        let mut text = String::with_capacity(self.pending.len());
+        let mut rest = self.pending.as_slice();
+
+        while !rest.is_empty() {
+            let error = match std::str::from_utf8(rest) {
+                Ok(valid) => {
+                    text.push_str(valid);
+                    rest = &[];
+                    break;
+                }
+                Err(error) => error,
+            };
+
+            let (valid, after) = rest.split_at(error.valid_up_to());
+            text.push_str(&String::from_utf8_lossy(valid));
+
+            // No error length: the start of a character the next packet
+            // finishes.
+            let Some(len) = error.error_len() else {
+                rest = after;
+                break;
+            };
+
+            text.push(char::REPLACEMENT_CHARACTER);
+            rest = &after[len..];
+        }
+
+        let consumed = self.pending.len() - rest.len();
+        self.pending.drain(..consumed);
+
+        text
+    }
+
+    /// End the input: a character left unfinished becomes U+FFFD. `None`
+    /// if nothing was held back.
+    pub fn finish(&mut self) -> Option<String> {
+        if self.pending.is_empty() {
+            return None;
+        }
+
+        self.pending.clear();
+
+        Some(char::REPLACEMENT_CHARACTER.to_string())
+    }
+
+    /// Whether part of a character is waiting for the rest.
+    #[must_use]
+    pub const fn is_pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn joins_characters_split_across_inputs() {
+        let mut decoder = Utf8Decoder::new();
+        let snowman = "\u{2603}".as_bytes();
+
+        assert_eq!(decoder.decode(&snowman[..1]), "");
+        assert_eq!(decoder.decode(&snowman[1..2]), "");
+        assert!(decoder.is_pending());
+        assert_eq!(decoder.decode(&snowman[2..]), "\u{2603}");
+        assert!(!decoder.is_pending());
+    }
+
+    #[test]
+    fn replaces_bytes_that_are_never_utf8() {
+        let mut decoder = Utf8Decoder::new();
+
+        assert_eq!(decoder.decode(b"a\xffb\xc3"), "a\u{fffd}b");
+        assert_eq!(decoder.decode(b"x"), "\u{fffd}x");
+        assert_eq!(decoder.decode(b"\xe2\x98"), "");
+        assert_eq!(decoder.finish().as_deref(), Some("\u{fffd}"));
+        assert_eq!(decoder.finish(), None);
+    }
+}
@@ -0,0 +1,158 @@
+use std::{
+    fmt, io,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll, ready},
+    time::Instant,
+};
+
+use russh::{ChannelWriteHalf, server::Msg};
+use tokio::io::AsyncWrite;
+
+use crate::session::stats::Counters;
+
+/// A cloneable handle writing to a session's stdout or stderr, from
+/// [`Session::stdout`](crate::Session::stdout) and
+/// [`Session::stderr`](crate::Session::stderr).
+///
+/// It doesn't borrow the session, so it can move into spawned tasks. Each
+/// clone is an independent [`AsyncWrite`] respecting the channel's flow
+/// control; [`write`](Self::write) sends a whole buffer without needing
+/// `&mut`. Writes fail once the channel has closed.
+///
+/// ```no_run
+/// # use shenron::Session;
+/// # use std::time::Duration;
+/// async fn build(session: &mut Session) -> shenron::Result {
+///     let progress = session.stderr()?;
+///
+///     let ticker = tokio::spawn(async move {
+///         loop {
+///             tokio::time::sleep(Duration::from_secs(1)).await;
+///             if progress.write(b".").await.is_err() {
+///                 break;
+///             }
+///         }
+///     });
+///
+///     // ... the long-running work ...
+///     ticker.abort();
+///
+///     session.write_str("done\r\n").await
+/// }
+/// ```
+pub struct ChannelWriter {
+    half: Arc<ChannelWriteHalf<Msg>>,
+    ext: Option<u32>,
+    counters: Arc<Counters>,
+    /// Created on first [`AsyncWrite`] use.
+    tx: Option<Pin<Box<dyn AsyncWrite + Send + Sync>>>,
+}
+
+impl ChannelWriter {
+    pub(crate) const fn new(
+        half: Arc<ChannelWriteHalf<Msg>>,
+        ext: Option<u32>,
+        counters: Arc<Counters>,
+    ) -> Self {
+        Self {
+            half,
+            ext,
+            counters,
+            tx: None,
+        }
+    }
+
+    /// Write all of `data`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the message fails to send.
+    pub async fn write(&self, data: &[u8]) -> crate::Result {
+        send(&self.half, self.ext, data, &self.counters).await
+    }
+
+    /// How many bytes the client will take right now without a write
+    /// waiting, up to one packet. See [`Session::send_window`](crate::Session::send_window).
+    pub async fn send_window(&self) -> usize {
+        self.half.writable_packet_size().await
+    }
+
+    /// Write a string.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the message fails to send.
+    pub async fn write_str(&self, s: &str) -> crate::Result {
+        self.write(s.as_bytes()).await
+    }
+
+    fn tx(&mut self) -> Pin<&mut (dyn AsyncWrite + Send + Sync)> {
+        let half = &self.half;
+        let ext = self.ext;
+
+        self.tx
+            .get_or_insert_with(|| Box::pin(half.make_writer_ext(ext)))
+            .as_mut()
+    }
+}
+
+/// Send `data` to stdout (`ext` `None`) or an extended stream, counting it,
+/// and counting a stall if the client's window was shut when it started.
+pub async fn send(
+    half: &ChannelWriteHalf<Msg>,
+    ext: Option<u32>,
+    data: &[u8],
+    counters: &Counters,
+) -> crate::Result {
+    let stalled = !data.is_empty() && half.writable_packet_size().await == 0;
+    let started = Instant::now();
+
+    match ext {
+        None => half.data(data).await,
+        Some(ext) => half.extended_data(ext, data).await,
+    }
+    .map_err(crate::Error::Ssh)?;
+
+    if stalled {
+        counters.add_stall(started.elapsed());
+    }
+    counters.add_written(ext, data);
+
+    Ok(())
+}
+
+impl Clone for ChannelWriter {
+    fn clone(&self) -> Self {
+        Self::new(Arc::clone(&self.half), self.ext, Arc::clone(&self.counters))
+    }
+}
+
+impl fmt::Debug for ChannelWriter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ChannelWriter")
+            .field("stderr", &self.ext.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+impl AsyncWrite for ChannelWriter {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let written = ready!(self.tx().poll_write(cx, buf))?;
+        self.counters.add_written(self.ext, &buf[..written]);
+
+        Poll::Ready(Ok(written))
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.tx().poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.tx().poll_shutdown(cx)
+    }
+}
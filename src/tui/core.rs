@@ -9,11 +9,8 @@ use tokio::sync::mpsc::UnboundedSender;
 use crate::{
     Error, Result, Session,
     events::{Event as RawEvent, Events},
-    tui::{
-        event::Event,
-        key::{Input, parse_input},
-        writer::SessionWriter,
-    },
+    input::{self, Input},
+    tui::{event::Event, writer::SessionWriter},
 };
 
 type Backend = CrosstermBackend<SessionWriter>;
@@ -119,13 +116,13 @@ impl<'a, M> Tui<'a, M> {
         loop {
             if let Some(input) = self.pending.pop_front() {
                 return Some(match input {
-                    Input::Key(key) => Event::Key(key),
+                    Input::Key(key) => Event::Key(key.into()),
                     Input::Paste(text) => Event::Paste(text),
                 });
             }
 
             match self.events.next().await? {
-                RawEvent::Input(bytes) => self.pending.extend(parse_input(&bytes)),
+                RawEvent::Input(bytes) => self.pending.extend(input::parse(&bytes)),
                 RawEvent::Resize(size) => {
                     if let Ok(rect) = size.try_into() {
                         let _ = self.terminal.resize(rect);
@@ -135,6 +132,7 @@ impl<'a, M> Tui<'a, M> {
                 RawEvent::Signal(_) => {}
                 RawEvent::App(msg) => return Some(Event::App(msg)),
                 RawEvent::Eof => return Some(Event::Eof),
+                RawEvent::IdleTimeout => return Some(Event::IdleTimeout),
             }
         }
     }
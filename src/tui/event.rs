@@ -21,4 +21,7 @@ pub enum Event<M = ()> {
     App(M),
     /// The client sent EOF; no more input will arrive.
     Eof,
+    /// The client was silent for the session's
+    /// [idle timeout](crate::Session::set_idle_timeout).
+    IdleTimeout,
 }
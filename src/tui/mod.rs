@@ -1,6 +1,5 @@
 pub mod core;
 mod event;
-mod key;
 pub(crate) mod writer;
 
 pub use core::Tui;
@@ -0,0 +1,116 @@
+//! Agent forwarding: a client that asks for it (`ssh -A`) lets handlers
+//! reach its agent through `Session::agent`.
+
+#![feature(async_fn_traits, unboxed_closures)]
+
+mod common;
+
+use std::sync::Arc;
+
+use common::{read_to_close, start_server};
+use russh::{
+    Channel, ChannelMsg,
+    client::{self, AuthResult, Msg},
+    keys::{Algorithm, PrivateKey, PublicKey},
+};
+use shenron::Session;
+
+async fn list_keys(session: &mut Session) -> shenron::Result {
+    let listing = match session.agent().await {
+        Ok(mut agent) => agent
+            .request_identities()
+            .await?
+            .iter()
+            .map(|id| id.comment().to_string())
+            .collect::<Vec<_>>()
+            .join(","),
+        Err(_) => "no agent".into(),
+    };
+
+    session.write_str(&listing).await
+}
+
+/// Plays the user's agent: answers one identities request with `key`.
+struct WithAgent(PublicKey);
+
+impl client::Handler for WithAgent {
+    type Error = russh::Error;
+
+    async fn check_server_key(&mut self, _key: &PublicKey) -> Result<bool, Self::Error> {
+        Ok(true)
+    }
+
+    async fn server_channel_open_agent_forward(
+        &mut self,
+        mut channel: Channel<Msg>,
+        _session: &mut client::Session,
+    ) -> Result<(), Self::Error> {
+        let blob = self.0.to_bytes().expect("encode key");
+
+        tokio::spawn(async move {
+            // One request, whatever it is: the test only sends the one.
+            while let Some(msg) = channel.wait().await {
+                if matches!(msg, ChannelMsg::Data { .. }) {
+                    break;
+                }
+            }
+
+            let comment = b"alice@laptop";
+            let mut body = vec![12];
+            body.extend(1u32.to_be_bytes());
+            body.extend(u32::try_from(blob.len()).expect("len").to_be_bytes());
+            body.extend(&blob);
+            body.extend(u32::try_from(comment.len()).expect("len").to_be_bytes());
+            body.extend(comment);
+
+            let mut reply = u32::try_from(body.len())
+                .expect("len")
+                .to_be_bytes()
+                .to_vec();
+            reply.extend(body);
+            channel.data(&reply[..]).await.expect("agent reply");
+        });
+
+        Ok(())
+    }
+}
+
+async fn run(port: u16, forward_agent: bool) -> String {
+    let key = PrivateKey::random(&mut rand::rng(), Algorithm::Ed25519).expect("keygen");
+    let config = Arc::new(client::Config::default());
+    let mut handle = client::connect(
+        config,
+        ("127.0.0.1", port),
+        WithAgent(key.public_key().clone()),
+    )
+    .await
+    .expect("connect");
+
+    let result = handle
+        .authenticate_password("alice", "hunter2")
+        .await
+        .expect("auth request");
+    assert!(matches!(result, AuthResult::Success));
+
+    let mut channel = handle.channel_open_session().await.expect("channel");
+    if forward_agent {
+        channel.agent_forward(true).await.expect("agent forward");
+    }
+    channel.exec(true, "keys").await.expect("exec");
+
+    read_to_close(&mut channel).await.stdout
+}
+
+#[tokio::test]
+async fn handlers_reach_the_forwarded_agent() {
+    let port = start_server(list_keys).await;
+
+    assert_eq!(run(port, true).await, "alice@laptop");
+}
+
+#[tokio::test]
+async fn agent_is_unavailable_without_forwarding() {
+    let port = start_server(list_keys).await;
+
+    assert_eq!(run(port, false).await, "no agent");
+}
@@ -6,7 +6,10 @@
 mod common;
 
 use std::{
-    sync::Arc,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
     time::{Duration, SystemTime},
 };
 
@@ -15,11 +18,14 @@ use russh::{
     MethodKind,
     client::{self, AuthResult},
     keys::{
-        Algorithm, Certificate, PrivateKey, PrivateKeyWithHashAlg,
+        Algorithm, Certificate, EcdsaCurve, HashAlg, PrivateKey, PrivateKeyWithHashAlg,
         ssh_key::certificate::{Builder, CertType},
     },
 };
-use shenron::Session;
+use shenron::{
+    Session,
+    auth::{AuthContext, AuthMethod, AuthProvider, KeyPolicy},
+};
 
 async fn noop(_session: &mut Session) -> shenron::Result {
     Ok(())
@@ -128,6 +134,96 @@ async fn authorized_keys_allows_listed_key_only() {
     assert!(matches!(result, AuthResult::Failure { .. }));
 }
 
+#[tokio::test]
+async fn declined_key_offers_skip_the_handler_and_the_attempt_count() {
+    let known = generate();
+    let known_key = known.public_key().clone();
+    let verified = Arc::new(AtomicUsize::new(0));
+
+    let port = start_server_with(noop, {
+        let verified = Arc::clone(&verified);
+
+        move |server| {
+            server
+                .max_auth_attempts(1)
+                .pubkey_offered(move |_user, key| {
+                    let known = key == known_key;
+                    async move { known }
+                })
+                .pubkey_auth(move |_user, _key| {
+                    verified.fetch_add(1, Ordering::SeqCst);
+                    async { true }
+                })
+        }
+    })
+    .await;
+
+    let mut handle = connect(port).await;
+    for _ in 0..2 {
+        let result = handle
+            .authenticate_publickey(
+                "alice",
+                PrivateKeyWithHashAlg::new(Arc::new(generate()), None),
+            )
+            .await
+            .expect("auth request");
+        assert!(matches!(result, AuthResult::Failure { .. }));
+    }
+    assert_eq!(verified.load(Ordering::SeqCst), 0);
+
+    let result = handle
+        .authenticate_publickey("alice", PrivateKeyWithHashAlg::new(Arc::new(known), None))
+        .await
+        .expect("auth request");
+    assert!(matches!(result, AuthResult::Success));
+    assert_eq!(verified.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn key_policy_refuses_keys_before_the_handler() {
+    let verified = Arc::new(AtomicUsize::new(0));
+
+    let port = start_server_with(noop, {
+        let verified = Arc::clone(&verified);
+
+        move |server| {
+            server
+                .key_policy(KeyPolicy::new().allow_only([Algorithm::Ed25519]))
+                .pubkey_auth(move |_user, _key| {
+                    verified.fetch_add(1, Ordering::SeqCst);
+                    async { true }
+                })
+        }
+    })
+    .await;
+
+    let p256 = PrivateKey::random(
+        &mut rand::rng(),
+        Algorithm::Ecdsa {
+            curve: EcdsaCurve::NistP256,
+        },
+    )
+    .expect("keygen");
+
+    let mut handle = connect(port).await;
+    let result = handle
+        .authenticate_publickey("alice", PrivateKeyWithHashAlg::new(Arc::new(p256), None))
+        .await
+        .expect("auth request");
+    assert!(matches!(result, AuthResult::Failure { .. }));
+    assert_eq!(verified.load(Ordering::SeqCst), 0);
+
+    let result = handle
+        .authenticate_publickey(
+            "alice",
+            PrivateKeyWithHashAlg::new(Arc::new(generate()), None),
+        )
+        .await
+        .expect("auth request");
+    assert!(matches!(result, AuthResult::Success));
+    assert_eq!(verified.load(Ordering::SeqCst), 1);
+}
+
 #[tokio::test]
 async fn trusted_ca_accepts_cert_for_principal_only() {
     let ca = generate();
@@ -185,3 +281,403 @@ async fn cert_only_server_rejects_plain_pubkey() {
 
     assert!(remaining_methods.contains(&MethodKind::PublicKey));
 }
+
+#[tokio::test]
+async fn required_methods_chain_through_partial_success() {
+    let key = generate();
+    let file = write_lines(&[key.public_key().to_openssh().expect("openssh")]);
+
+    let port = start_server_with(noop, |server| {
+        server
+            .pubkey_auth(shenron::auth::authorized_keys(file.path()).expect("parse"))
+            .password_auth(|_user, password| async move { password == "hunter2" })
+            .require_auth_methods([AuthMethod::PublicKey, AuthMethod::Password])
+    })
+    .await;
+
+    let mut handle = connect(port).await;
+    let result = handle
+        .authenticate_publickey("alice", PrivateKeyWithHashAlg::new(Arc::new(key), None))
+        .await
+        .expect("auth request");
+
+    // russh drops `partial_success` on publickey replies, so the narrowed
+    // method list is what tells the client the key counted.
+    let AuthResult::Failure {
+        remaining_methods, ..
+    } = result
+    else {
+        panic!("a single factor must not be enough");
+    };
+    assert!(remaining_methods.contains(&MethodKind::Password));
+    assert!(!remaining_methods.contains(&MethodKind::PublicKey));
+
+    let result = handle
+        .authenticate_password("alice", "wrong")
+        .await
+        .expect("auth request");
+    assert!(matches!(
+        result,
+        AuthResult::Failure {
+            partial_success: false,
+            ..
+        }
+    ));
+
+    let result = handle
+        .authenticate_password("alice", "hunter2")
+        .await
+        .expect("auth request");
+    assert!(matches!(result, AuthResult::Success));
+}
+
+#[tokio::test]
+async fn required_method_without_handler_fails_to_start() {
+    let result = shenron::Server::new()
+        .bind("127.0.0.1:0")
+        .password_auth(|_user, _password| async { true })
+        .require_auth_methods([AuthMethod::PublicKey, AuthMethod::Password])
+        .listen()
+        .await;
+
+    assert!(matches!(result, Err(shenron::Error::Config(_))));
+}
+
+async fn report_auth(session: &mut Session) -> shenron::Result {
+    let fingerprint = session
+        .key_fingerprint()
+        .map(|fp| fp.to_string())
+        .unwrap_or_default();
+
+    session
+        .write_str(&format!("{:?} {fingerprint}", session.auth_methods()))
+        .await
+}
+
+#[tokio::test]
+async fn session_reports_how_the_user_authenticated() {
+    let key = generate();
+    let fingerprint = key.public_key().fingerprint(HashAlg::Sha256).to_string();
+    let file = write_lines(&[key.public_key().to_openssh().expect("openssh")]);
+
+    let port = start_server_with(report_auth, |server| {
+        server
+            .pubkey_auth(shenron::auth::authorized_keys(file.path()).expect("parse"))
+            .password_auth(|_user, _password| async { true })
+            .require_auth_methods([AuthMethod::Password, AuthMethod::PublicKey])
+    })
+    .await;
+
+    let mut handle = connect(port).await;
+    let _ = handle
+        .authenticate_password("alice", "hunter2")
+        .await
+        .expect("auth request");
+    let result = handle
+        .authenticate_publickey("alice", PrivateKeyWithHashAlg::new(Arc::new(key), None))
+        .await
+        .expect("auth request");
+    assert!(matches!(result, AuthResult::Success));
+
+    let mut channel = handle.channel_open_session().await.expect("open");
+    channel.exec(true, "whoami").await.expect("exec");
+
+    let output = common::read_to_close(&mut channel).await;
+    assert_eq!(
+        output.stdout,
+        format!("[Password, PublicKey] {fingerprint}")
+    );
+}
+
+#[derive(Clone)]
+struct Account {
+    id: u64,
+}
+
+async fn report_account(session: &mut Session) -> shenron::Result {
+    let id = session.get::<Account>().map(|account| account.id);
+
+    session.write_str(&format!("{id:?}")).await
+}
+
+#[tokio::test]
+async fn resolved_accounts_reach_the_session() {
+    let port = start_server_with(report_account, |server| {
+        server
+            .password_auth(|_user, _password| async { true })
+            .user_resolver(
+                |user: String| async move { (user == "alice").then_some(Account { id: 7 }) },
+            )
+    })
+    .await;
+
+    let mut handle = connect(port).await;
+    let result = handle
+        .authenticate_password("mallory", "anything")
+        .await
+        .expect("auth request");
+    assert!(matches!(result, AuthResult::Failure { .. }));
+
+    let mut handle = connect(port).await;
+    let result = handle
+        .authenticate_password("alice", "anything")
+        .await
+        .expect("auth request");
+    assert!(matches!(result, AuthResult::Success));
+
+    let mut channel = handle.channel_open_session().await.expect("open");
+    channel.exec(true, "whoami").await.expect("exec");
+
+    let output = common::read_to_close(&mut channel).await;
+    assert_eq!(output.stdout, "Some(7)");
+}
+
+/// A provider with its state in `self` rather than captured by closures.
+struct Accounts {
+    passwords: std::collections::HashMap<String, String>,
+    seen: Arc<Mutex<Vec<AuthContext>>>,
+}
+
+impl AuthProvider for Accounts {
+    const METHODS: &'static [AuthMethod] = &[AuthMethod::Password];
+
+    async fn password(&self, user: &str, password: &str, ctx: &AuthContext) -> shenron::Auth {
+        self.seen.lock().expect("lock").push(ctx.clone());
+
+        shenron::Auth::from(self.passwords.get(user).is_some_and(|p| p == password))
+    }
+}
+
+#[tokio::test]
+async fn auth_provider_answers_its_methods() {
+    let port = start_server_with(noop, |server| {
+        server.auth_provider(Accounts {
+            passwords: [("alice".into(), "hunter2".into())].into(),
+            seen: Arc::default(),
+        })
+    })
+    .await;
+
+    let mut handle = connect(port).await;
+    let result = handle
+        .authenticate_none("alice")
+        .await
+        .expect("auth request");
+    let AuthResult::Failure {
+        remaining_methods, ..
+    } = result
+    else {
+        panic!("none must be rejected");
+    };
+    assert!(remaining_methods.contains(&MethodKind::Password));
+    assert!(!remaining_methods.contains(&MethodKind::PublicKey));
+
+    let result = handle
+        .authenticate_password("alice", "hunter2")
+        .await
+        .expect("auth request");
+    assert!(matches!(result, AuthResult::Success));
+}
+
+#[tokio::test]
+async fn providers_see_the_connection_context() {
+    let seen = Arc::new(Mutex::new(Vec::new()));
+
+    let port = start_server_with(noop, {
+        let seen = Arc::clone(&seen);
+
+        move |server| {
+            server.auth_provider(Accounts {
+                passwords: [("alice".into(), "hunter2".into())].into(),
+                seen,
+            })
+        }
+    })
+    .await;
+
+    let mut handle = connect(port).await;
+    for password in ["wrong", "hunter2"] {
+        handle
+            .authenticate_password("alice", password)
+            .await
+            .expect("auth request");
+    }
+
+    let seen = seen.lock().expect("lock").clone();
+    let attempts: Vec<u32> = seen.iter().map(AuthContext::attempt).collect();
+    assert_eq!(attempts, [1, 2]);
+    assert!(seen[0].remote_addr().ip().is_loopback());
+    assert_eq!(seen[0].local_addr().port(), port);
+    assert!(
+        seen[0].client_version().starts_with("SSH-2.0-"),
+        "{}",
+        seen[0].client_version()
+    );
+    assert!(seen[0].client_fingerprint().is_some());
+}
+
+#[tokio::test]
+async fn closures_can_take_the_connection_context() {
+    let port = start_server_with(noop, |server| {
+        server.password_auth_with_context(|_user, password, ctx| async move {
+            password == "hunter2" && ctx.attempt() == 2
+        })
+    })
+    .await;
+
+    let mut handle = connect(port).await;
+    let mut results = Vec::new();
+    for password in ["hunter2", "hunter2"] {
+        let result = handle
+            .authenticate_password("alice", password)
+            .await
+            .expect("auth request");
+        results.push(matches!(result, AuthResult::Success));
+    }
+
+    assert_eq!(results, [false, true]);
+}
+
+#[cfg(feature = "hassh")]
+async fn report_fingerprint(session: &mut Session) -> shenron::Result {
+    let line = session
+        .client_fingerprint()
+        .map(|fp| format!("{} {}", fp.hassh(), fp.kex_algorithms()))
+        .unwrap_or_default();
+
+    session.write_str(&line).await
+}
+
+#[cfg(feature = "hassh")]
+#[tokio::test]
+async fn sessions_carry_the_client_hassh() {
+    let port = start_open_server(report_fingerprint).await;
+
+    let mut handle = connect(port).await;
+    handle.authenticate_none("alice").await.expect("auth");
+
+    let mut channel = handle.channel_open_session().await.expect("open");
+    channel.exec(true, "hassh").await.expect("exec");
+
+    let output = common::read_to_close(&mut channel).await;
+    let (hassh, kex) = output.stdout.split_once(' ').expect("fingerprint");
+    assert_eq!(hassh.len(), 32);
+    assert!(hassh.chars().all(|c| c.is_ascii_hexdigit()), "{hassh}");
+    assert!(kex.contains("curve25519-sha256"), "{kex}");
+}
+
+async fn report_local_addr(session: &mut Session) -> shenron::Result {
+    session.write_str(&session.local_addr().to_string()).await
+}
+
+#[tokio::test]
+async fn sessions_know_the_listener_they_arrived_on() {
+    let port = start_open_server(report_local_addr).await;
+
+    let mut handle = connect(port).await;
+    handle.authenticate_none("alice").await.expect("auth");
+
+    let mut channel = handle.channel_open_session().await.expect("open");
+    channel.exec(true, "where").await.expect("exec");
+
+    let output = common::read_to_close(&mut channel).await;
+    assert_eq!(output.stdout, format!("127.0.0.1:{port}"));
+}
+
+#[tokio::test]
+async fn denied_usernames_never_reach_a_handler() {
+    let calls = Arc::new(AtomicUsize::new(0));
+
+    let port = start_server_with(noop, {
+        let calls = Arc::clone(&calls);
+
+        move |server| {
+            server
+                .reject_users(["root", "adm*"])
+                .password_auth(move |_user, _password| {
+                    calls.fetch_add(1, Ordering::SeqCst);
+
+                    async { true }
+                })
+        }
+    })
+    .await;
+
+    for user in ["root", "administrator"] {
+        let result = connect(port)
+            .await
+            .authenticate_password(user, "anything")
+            .await
+            .expect("auth request");
+
+        assert!(matches!(result, AuthResult::Failure { .. }), "{user}");
+    }
+    assert_eq!(calls.load(Ordering::SeqCst), 0);
+
+    let result = connect(port)
+        .await
+        .authenticate_password("alice", "anything")
+        .await
+        .expect("auth request");
+    assert!(matches!(result, AuthResult::Success));
+}
+
+#[tokio::test]
+async fn open_servers_still_deny_listed_usernames() {
+    let port = start_server_with(noop, |server| server.reject_users(["root"])).await;
+
+    let denied = connect(port)
+        .await
+        .authenticate_none("root")
+        .await
+        .expect("auth request");
+    assert!(matches!(denied, AuthResult::Failure { .. }));
+
+    let allowed = connect(port)
+        .await
+        .authenticate_none("guest")
+        .await
+        .expect("auth request");
+    assert!(matches!(allowed, AuthResult::Success));
+}
+
+#[tokio::test]
+async fn anonymous_auth_admits_only_approved_users() {
+    let port = start_server_with(noop, |server| {
+        server
+            .anonymous_auth(|user| async move { user == "guest" })
+            .password_auth(|_user, _password| async { false })
+    })
+    .await;
+
+    let mut handle = connect(port).await;
+    let result = handle
+        .authenticate_none("guest")
+        .await
+        .expect("auth request");
+    assert!(matches!(result, AuthResult::Success));
+
+    let mut handle = connect(port).await;
+    let result = handle
+        .authenticate_none("admin")
+        .await
+        .expect("auth request");
+    let AuthResult::Failure {
+        remaining_methods, ..
+    } = result
+    else {
+        panic!("anonymous handler must reject other users");
+    };
+    assert!(remaining_methods.contains(&MethodKind::Password));
+}
+
+#[tokio::test]
+async fn closed_server_without_handlers_fails_to_start() {
+    let result = shenron::Server::new()
+        .bind("127.0.0.1:0")
+        .open_auth(false)
+        .listen()
+        .await;
+
+    assert!(matches!(result, Err(shenron::Error::Config(_))));
+}
@@ -0,0 +1,108 @@
+//! `Server::max_connections`: once the cap is reached, new connections queue,
+//! get a reject line, or are dropped, per the overflow policy.
+
+use std::time::Duration;
+
+use shenron::{Server, Session, server::OverflowPolicy};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    net::TcpStream,
+};
+
+async fn noop(_session: &mut Session) -> shenron::Result {
+    Ok(())
+}
+
+async fn start(policy: OverflowPolicy) -> (tempfile::TempDir, std::net::SocketAddr) {
+    let tmp = tempfile::TempDir::new().expect("tempdir");
+
+    let server = Server::new()
+        .bind("127.0.0.1:0")
+        .host_key_path(tmp.path().join("host_key"))
+        .expect("host key")
+        .max_connections(1)
+        .overflow_policy(policy)
+        .app(noop)
+        .spawn()
+        .await
+        .expect("spawn");
+
+    (tmp, server.local_addr())
+}
+
+/// The first line the server sends, or `None` if it closes without one.
+async fn first_line(stream: TcpStream) -> Option<String> {
+    let mut line = String::new();
+    let mut reader = BufReader::new(stream);
+    let read = reader.read_line(&mut line);
+
+    match tokio::time::timeout(Duration::from_secs(2), read).await {
+        Ok(Ok(0)) => None,
+        Ok(Ok(_)) => Some(line.trim_end().to_string()),
+        Ok(Err(e)) => panic!("read failed: {e}"),
+        Err(elapsed) => panic!("server kept the connection open silently: {elapsed}"),
+    }
+}
+
+/// Hold the only slot: connect and wait for the server's version line, so
+/// the connection is known to be accepted.
+async fn occupy(addr: std::net::SocketAddr) -> BufReader<TcpStream> {
+    let mut held = BufReader::new(TcpStream::connect(addr).await.expect("connect"));
+    let mut line = String::new();
+    held.read_line(&mut line).await.expect("version");
+    assert!(line.starts_with("SSH-"), "{line}");
+
+    held
+}
+
+#[tokio::test]
+async fn reject_sends_message_and_closes() {
+    let (_tmp, addr) = start(OverflowPolicy::Reject("server busy".into())).await;
+    let _held = occupy(addr).await;
+
+    // Real clients send their version string right away; the rejection must
+    // survive it arriving unread.
+    let mut extra = TcpStream::connect(addr).await.expect("connect");
+    extra.write_all(b"SSH-2.0-test\r\n").await.expect("write");
+
+    assert_eq!(first_line(extra).await.as_deref(), Some("server busy"));
+}
+
+#[tokio::test]
+async fn drop_closes_silently() {
+    let (_tmp, addr) = start(OverflowPolicy::Drop).await;
+    let _held = occupy(addr).await;
+
+    let extra = TcpStream::connect(addr).await.expect("connect");
+
+    assert_eq!(first_line(extra).await, None);
+}
+
+#[tokio::test]
+async fn queue_admits_once_a_slot_frees() {
+    let (_tmp, addr) = start(OverflowPolicy::Queue).await;
+    let held = occupy(addr).await;
+
+    let mut queued = TcpStream::connect(addr).await.expect("connect");
+    let mut byte = [0u8; 1];
+
+    let waited = tokio::time::timeout(Duration::from_millis(200), queued.read(&mut byte)).await;
+    assert!(waited.is_err(), "queued connection was served while full");
+
+    drop(held);
+
+    let mut rest = String::new();
+    let served = async {
+        queued.read_exact(&mut byte).await.expect("read");
+        BufReader::new(&mut queued)
+            .read_line(&mut rest)
+            .await
+            .expect("read");
+    };
+    tokio::time::timeout(Duration::from_secs(2), served)
+        .await
+        .expect("queued connection never admitted");
+
+    assert_eq!(byte[0], b'S');
+    assert!(rest.starts_with("SH-"), "{rest}");
+}
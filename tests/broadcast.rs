@@ -0,0 +1,66 @@
+//! `ServerHandle::broadcast` reaching running sessions, and the `wall`
+//! middleware printing it on their terminals.
+
+#![feature(async_fn_traits, unboxed_closures)]
+
+mod common;
+
+use common::{connect_and_auth, read_until};
+use shenron::{Server, ServerHandle, Session, middleware::wall};
+
+async fn app(session: &mut Session) -> shenron::Result {
+    session.write_str("ready\r\n").await?;
+
+    while session.next().await.is_some() {}
+
+    Ok(())
+}
+
+async fn spawn(tmp: &tempfile::TempDir) -> ServerHandle {
+    Server::new()
+        .bind("127.0.0.1:0")
+        .host_key_path(tmp.path().join("host_key"))
+        .expect("host key")
+        .with(wall)
+        .app(app)
+        .spawn()
+        .await
+        .expect("spawn")
+}
+
+/// Everything the channel sends until `needle` shows up.
+#[tokio::test]
+async fn broadcasts_reach_pty_sessions_as_banners() {
+    let tmp = tempfile::TempDir::new().expect("tempdir");
+    let server = spawn(&tmp).await;
+    let handle = connect_and_auth(server.local_addr().port()).await;
+
+    let mut shell = handle.channel_open_session().await.expect("channel");
+    shell
+        .request_pty(true, "xterm", 80, 24, 0, 0, &[])
+        .await
+        .expect("pty");
+    shell.request_shell(true).await.expect("shell");
+    read_until(&mut shell, "ready").await;
+
+    let mut exec = handle.channel_open_session().await.expect("channel");
+    exec.exec(true, "quiet").await.expect("exec");
+    read_until(&mut exec, "ready").await;
+
+    assert_eq!(server.broadcast("Going down in 5\nminutes"), 1);
+
+    let banner = read_until(&mut shell, "minutes").await;
+
+    assert_eq!(
+        banner,
+        "\r\n\x07Broadcast message from the server:\r\n\r\nGoing down in 5\r\nminutes\r\n\r\n"
+    );
+}
+
+#[tokio::test]
+async fn broadcast_without_sessions_reaches_no_one() {
+    let tmp = tempfile::TempDir::new().expect("tempdir");
+    let server = spawn(&tmp).await;
+
+    assert_eq!(server.broadcast("anyone?"), 0);
+}
@@ -0,0 +1,56 @@
+//! The session bus: sessions on one server publishing to each other.
+
+#![feature(async_fn_traits, unboxed_closures)]
+
+mod common;
+
+use std::time::Duration;
+
+use common::{connect_and_auth, read_to_close, start_server};
+use russh::ChannelMsg;
+use shenron::Session;
+
+/// `listen` prints the next message on the room; `say <text>` sends one.
+async fn app(session: &mut Session) -> shenron::Result {
+    let command = session.command().unwrap_or_default();
+    let bus = session.bus().clone();
+
+    match command.as_slice() {
+        [listen] if listen == "listen" => {
+            let mut room = bus.subscribe::<String>("room");
+            session.write_str("ready;").await?;
+
+            let message = room.recv().await.unwrap_or_default();
+            session.write_str(&message).await?;
+        }
+        [say, text] if say == "say" => {
+            let heard = bus.publish("room", format!("{}: {text}", session.user()));
+            session.write_str(&format!("heard by {heard}")).await?;
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn sessions_hear_each_other() {
+    let port = start_server(app).await;
+    let handle = connect_and_auth(port).await;
+
+    let mut listener = handle.channel_open_session().await.expect("channel");
+    listener.exec(true, "listen").await.expect("exec");
+
+    tokio::time::timeout(Duration::from_secs(2), async {
+        while !matches!(listener.wait().await, Some(ChannelMsg::Data { .. }) | None) {}
+    })
+    .await
+    .expect("listener never subscribed");
+
+    let other = connect_and_auth(port).await;
+    let mut speaker = other.channel_open_session().await.expect("channel");
+    speaker.exec(true, "say hello").await.expect("exec");
+
+    assert_eq!(read_to_close(&mut speaker).await.stdout, "heard by 1");
+    assert_eq!(read_to_close(&mut listener).await.stdout, "alice: hello");
+}
@@ -57,35 +57,18 @@ where
     R: shenron::IntoExit,
     C: FnOnce(Server) -> Server,
 {
-    let port = std::net::TcpListener::bind("127.0.0.1:0")
-        .expect("bind probe")
-        .local_addr()
-        .expect("local addr")
-        .port();
-
     let tmp = tempfile::TempDir::new().expect("tempdir");
 
     let server = configure(
         Server::new()
-            .bind(format!("127.0.0.1:{port}"))
+            .bind("127.0.0.1:0")
             .host_key_path(tmp.path().join("host_key"))
             .expect("host key"),
     );
 
-    tokio::spawn(server.app(app).serve());
-
-    for _ in 0..100 {
-        if tokio::net::TcpStream::connect(("127.0.0.1", port))
-            .await
-            .is_ok()
-        {
-            return port;
-        }
-
-        tokio::time::sleep(Duration::from_millis(10)).await;
-    }
+    let server = server.app(app).spawn().await.expect("spawn");
 
-    panic!("server did not start listening");
+    server.local_addr().port()
 }
 
 pub struct AcceptAll;
@@ -115,20 +98,22 @@ pub async fn connect_and_auth(port: u16) -> client::Handle<AcceptAll> {
 
 pub struct Output {
     pub stdout: String,
+    pub stderr: String,
     pub exit_status: Option<u32>,
 }
 
-/// Collect stdout and the exit status until the server closes the channel.
+/// Collect stdout, stderr and the exit status until the server closes the channel.
 /// Bounded by a timeout so a server that never closes fails the test instead
 /// of hanging it.
 pub async fn read_to_close(channel: &mut russh::Channel<client::Msg>) -> Output {
-    let mut stdout = Vec::new();
+    let (mut stdout, mut stderr) = (Vec::new(), Vec::new());
     let mut exit_status = None;
 
     let drain = async {
         while let Some(msg) = channel.wait().await {
             match msg {
                 ChannelMsg::Data { data } => stdout.extend_from_slice(&data),
+                ChannelMsg::ExtendedData { data, ext: 1 } => stderr.extend_from_slice(&data),
                 ChannelMsg::ExitStatus { exit_status: code } => exit_status = Some(code),
                 _ => {}
             }
@@ -141,6 +126,36 @@ pub async fn read_to_close(channel: &mut russh::Channel<client::Msg>) -> Output
 
     Output {
         stdout: String::from_utf8_lossy(&stdout).into_owned(),
+        stderr: String::from_utf8_lossy(&stderr).into_owned(),
         exit_status,
     }
 }
+
+/// Run `command` as a fresh connection's exec request and collect its output.
+pub async fn exec_output(port: u16, command: &str) -> Output {
+    let handle = connect_and_auth(port).await;
+    let mut channel = handle.channel_open_session().await.expect("open");
+    channel.exec(true, command).await.expect("exec");
+
+    read_to_close(&mut channel).await
+}
+
+/// Collect output until it contains `needle` or the channel closes, failing
+/// the test after two seconds.
+pub async fn read_until(channel: &mut russh::Channel<client::Msg>, needle: &str) -> String {
+    let mut out = String::new();
+
+    tokio::time::timeout(Duration::from_secs(2), async {
+        while !out.contains(needle) {
+            match channel.wait().await {
+                Some(ChannelMsg::Data { data }) => out.push_str(&String::from_utf8_lossy(&data)),
+                Some(_) => {}
+                None => break,
+            }
+        }
+    })
+    .await
+    .expect("timed out");
+
+    out
+}
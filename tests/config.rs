@@ -0,0 +1,82 @@
+//! `Server::from_config`: a TOML file alone is enough to stand up a server
+//! with file-backed pubkey auth.
+
+#![cfg(feature = "config")]
+#![feature(async_fn_traits, unboxed_closures)]
+
+mod common;
+
+use std::{fs, sync::Arc};
+
+use common::AcceptAll;
+use russh::{
+    client::{self, AuthResult},
+    keys::{Algorithm, PrivateKey, PrivateKeyWithHashAlg, ssh_key::LineEnding},
+};
+use shenron::{Server, Session, server::ServerConfig};
+
+async fn noop(_session: &mut Session) -> shenron::Result {
+    Ok(())
+}
+
+fn generate() -> PrivateKey {
+    PrivateKey::random(&mut rand::rng(), Algorithm::Ed25519).expect("keygen")
+}
+
+#[tokio::test]
+async fn config_file_drives_bind_host_key_and_auth() {
+    let tmp = tempfile::TempDir::new().expect("tempdir");
+    let user = generate();
+
+    let host_key = tmp.path().join("host_key");
+    generate()
+        .write_openssh_file(&host_key, LineEnding::LF)
+        .expect("write host key");
+
+    let authorized = tmp.path().join("authorized_keys");
+    fs::write(
+        &authorized,
+        user.public_key().to_openssh().expect("openssh"),
+    )
+    .expect("write authorized_keys");
+
+    let path = tmp.path().join("server.toml");
+    fs::write(
+        &path,
+        format!(
+            "bind = \"127.0.0.1:0\"\nhost_keys = [{host_key:?}]\n\n[auth]\nauthorized_keys = {authorized:?}\n"
+        ),
+    )
+    .expect("write config");
+
+    let config = ServerConfig::from_file(&path).expect("load config");
+    let server = Server::from_config(config)
+        .expect("from_config")
+        .app(noop)
+        .spawn()
+        .await
+        .expect("spawn");
+
+    let mut handle = client::connect(
+        Arc::new(client::Config::default()),
+        server.local_addr(),
+        AcceptAll,
+    )
+    .await
+    .expect("connect");
+
+    let result = handle
+        .authenticate_publickey("alice", PrivateKeyWithHashAlg::new(Arc::new(user), None))
+        .await
+        .expect("auth request");
+    assert!(matches!(result, AuthResult::Success));
+}
+
+#[test]
+fn missing_host_key_is_an_error() {
+    let config: ServerConfig = "host_keys = [\"/nonexistent/host_key\"]"
+        .parse()
+        .expect("parse");
+
+    assert!(Server::from_config(config).is_err());
+}
@@ -0,0 +1,45 @@
+//! `Elapsed`: where the session's duration goes, and when it's skipped.
+
+#![feature(async_fn_traits, unboxed_closures)]
+
+mod common;
+
+use std::time::Duration;
+
+use common::{exec_output, start_server_with};
+use shenron::{
+    Session,
+    middleware::{Elapsed, ElapsedTarget},
+};
+
+async fn app(session: &mut Session) -> shenron::Result {
+    session.write_str("app\n").await
+}
+
+async fn run(elapsed: Elapsed) -> String {
+    let port = start_server_with(app, move |server| server.with(elapsed)).await;
+
+    exec_output(port, "x").await.stdout
+}
+
+#[tokio::test]
+async fn reports_on_stdout_from_the_template() {
+    let stdout = run(Elapsed::new().message("{user} took {elapsed}\n")).await;
+
+    assert!(stdout.starts_with("app\nalice took "), "{stdout:?}");
+}
+
+#[tokio::test]
+async fn short_sessions_and_other_targets_stay_off_stdout() {
+    let threshold = Elapsed::new().threshold(Duration::from_secs(3600));
+    assert_eq!(run(threshold).await, "app\n");
+
+    assert_eq!(
+        run(Elapsed::new().target(ElapsedTarget::Stderr)).await,
+        "app\n"
+    );
+    assert_eq!(
+        run(Elapsed::new().target(ElapsedTarget::Log)).await,
+        "app\n"
+    );
+}
@@ -0,0 +1,37 @@
+//! `Server::accept_env`: only allowlisted client environment variables reach
+//! the session.
+
+#![feature(async_fn_traits, unboxed_closures)]
+
+mod common;
+
+use common::{connect_and_auth, read_to_close, start_server_with};
+use shenron::Session;
+
+/// Lists the session's environment, sorted.
+async fn env(session: &mut Session) -> shenron::Result {
+    let mut vars: Vec<_> = session
+        .env()
+        .iter()
+        .map(|(name, value)| format!("{name}={value}"))
+        .collect();
+    vars.sort();
+
+    session.write_str(&vars.join(" ")).await
+}
+
+#[tokio::test]
+async fn only_accepted_names_are_kept() {
+    let port = start_server_with(env, |server| server.accept_env(["LANG", "LC_*"])).await;
+    let handle = connect_and_auth(port).await;
+
+    let mut channel = handle.channel_open_session().await.expect("channel");
+    for (name, value) in [("LANG", "C"), ("LC_TIME", "C"), ("LD_PRELOAD", "/evil.so")] {
+        channel.set_env(false, name, value).await.expect("env");
+    }
+    channel.exec(true, "env").await.expect("exec");
+
+    let out = read_to_close(&mut channel).await;
+
+    assert_eq!(out.stdout, "LANG=C LC_TIME=C");
+}
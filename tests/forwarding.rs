@@ -0,0 +1,157 @@
+//! Remote port forwarding (`ssh -R`): approved requests get a listener on
+//! the server whose connections are handed back to the client.
+
+#![feature(async_fn_traits, unboxed_closures)]
+
+mod common;
+
+use std::{sync::Arc, time::Duration};
+
+use common::start_server_with;
+use russh::{
+    Channel,
+    client::{self, AuthResult, Msg},
+    keys::PublicKey,
+};
+use shenron::{Auth, ServerEvent, Session};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+    sync::broadcast,
+};
+
+async fn idle(_session: &mut Session) -> shenron::Result {
+    Ok(())
+}
+
+/// The client end of a tunnel: greets whoever connects with where they
+/// came in.
+struct Tunnel;
+
+impl client::Handler for Tunnel {
+    type Error = russh::Error;
+
+    async fn check_server_key(&mut self, _key: &PublicKey) -> Result<bool, Self::Error> {
+        Ok(true)
+    }
+
+    async fn server_channel_open_forwarded_tcpip(
+        &mut self,
+        channel: Channel<Msg>,
+        connected_address: &str,
+        connected_port: u32,
+        _originator_address: &str,
+        _originator_port: u32,
+        _session: &mut client::Session,
+    ) -> Result<(), Self::Error> {
+        let greeting = format!("via {connected_address}:{connected_port}");
+
+        tokio::spawn(async move {
+            let mut stream = channel.into_stream();
+            stream.write_all(greeting.as_bytes()).await.expect("write");
+            stream.shutdown().await.expect("shutdown");
+        });
+
+        Ok(())
+    }
+}
+
+async fn connect(port: u16) -> client::Handle<Tunnel> {
+    let config = Arc::new(client::Config::default());
+    let mut handle = client::connect(config, ("127.0.0.1", port), Tunnel)
+        .await
+        .expect("connect");
+
+    let result = handle
+        .authenticate_password("alice", "hunter2")
+        .await
+        .expect("auth request");
+    assert!(matches!(result, AuthResult::Success));
+
+    handle
+}
+
+async fn next(events: &mut broadcast::Receiver<ServerEvent>) -> ServerEvent {
+    tokio::time::timeout(Duration::from_secs(2), events.recv())
+        .await
+        .expect("no event arrived")
+        .expect("event stream closed")
+}
+
+#[tokio::test]
+async fn approved_forwards_reach_the_client() {
+    let mut subscribed = None;
+
+    let port = start_server_with(idle, |server| {
+        subscribed = Some(server.events());
+
+        server
+            .password_auth(|_user, _password| async { Auth::accept() })
+            .remote_forwarding(|request| async move { request.user == "alice" })
+    })
+    .await;
+
+    let mut events = subscribed.expect("subscribed");
+    let handle = connect(port).await;
+
+    let bound = handle
+        .tcpip_forward("localhost", 0)
+        .await
+        .expect("forward approved");
+    assert_ne!(bound, 0);
+
+    let mut stream = TcpStream::connect(("127.0.0.1", u16::try_from(bound).expect("port")))
+        .await
+        .expect("connect to forward");
+    let mut greeting = String::new();
+    stream.read_to_string(&mut greeting).await.expect("read");
+    assert_eq!(greeting, format!("via localhost:{bound}"));
+
+    handle
+        .cancel_tcpip_forward("localhost", bound)
+        .await
+        .expect("cancel");
+
+    let mut seen = Vec::new();
+    while !matches!(seen.last(), Some(ServerEvent::ForwardClosed { .. })) {
+        seen.push(next(&mut events).await);
+    }
+    assert!(
+        seen.iter().any(
+            |event| matches!(event, ServerEvent::ForwardOpened { port, .. } if *port == bound)
+        )
+    );
+    assert!(
+        seen.iter()
+            .any(|event| matches!(event, ServerEvent::ForwardConnection { .. }))
+    );
+}
+
+#[tokio::test]
+async fn forwards_are_refused_unless_approved() {
+    let open = start_server_with(idle, |server| {
+        server.password_auth(|_user, _password| async { Auth::accept() })
+    })
+    .await;
+    let picky = start_server_with(idle, |server| {
+        server
+            .password_auth(|_user, _password| async { Auth::accept() })
+            .remote_forwarding(|request| async move { request.port >= 1024 })
+    })
+    .await;
+
+    assert!(
+        connect(open)
+            .await
+            .tcpip_forward("localhost", 0)
+            .await
+            .is_err()
+    );
+    assert!(
+        connect(picky)
+            .await
+            .tcpip_forward("localhost", 0)
+            .await
+            .is_err()
+    );
+}
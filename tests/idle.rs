@@ -0,0 +1,147 @@
+//! Idle timeouts: `Session::next_timeout` and `set_idle_timeout` report a
+//! silent client as `Event::IdleTimeout` without losing later input, and the
+//! `IdleTimeout` middleware ends sessions whose user stops typing.
+
+#![feature(async_fn_traits, unboxed_closures)]
+
+mod common;
+
+use std::time::Duration;
+
+use common::{connect_and_auth, read_to_close, start_server, start_server_with};
+use russh::ChannelMsg;
+use shenron::{Event, Session, middleware::IdleTimeout};
+
+/// Wait briefly, then for real: reports what each wait saw.
+async fn patient(session: &mut Session) -> shenron::Result {
+    for timeout in [Duration::from_millis(50), Duration::from_secs(5)] {
+        let seen = match session.next_timeout(timeout).await {
+            Some(Event::Input(data)) => String::from_utf8_lossy(&data).into_owned(),
+            Some(Event::IdleTimeout) => "timeout".into(),
+            other => format!("{other:?}"),
+        };
+
+        session.write_str(&format!("{seen};")).await?;
+    }
+
+    Ok(())
+}
+
+async fn echo_until_idle(session: &mut Session) -> shenron::Result {
+    session.set_idle_timeout(Some(Duration::from_millis(100)));
+
+    while let Some(data) = session.input().await {
+        session.write(&data).await?;
+    }
+
+    session.write_str("bye").await
+}
+
+#[tokio::test]
+async fn next_timeout_keeps_input_that_arrives_late() {
+    let port = start_server(patient).await;
+    let handle = connect_and_auth(port).await;
+
+    let mut channel = handle.channel_open_session().await.expect("channel");
+    channel.exec(true, "wait").await.expect("exec");
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    channel.data(&b"late"[..]).await.expect("data");
+
+    let out = read_to_close(&mut channel).await;
+
+    assert_eq!(out.stdout, "timeout;late;");
+}
+
+#[tokio::test]
+async fn idle_timeout_ends_input() {
+    let port = start_server(echo_until_idle).await;
+    let handle = connect_and_auth(port).await;
+
+    let mut channel = handle.channel_open_session().await.expect("channel");
+    channel.exec(true, "echo").await.expect("exec");
+    channel.data(&b"hi "[..]).await.expect("data");
+
+    let out = read_to_close(&mut channel).await;
+
+    assert_eq!(out.stdout, "hi bye");
+    assert_eq!(out.exit_status, Some(0));
+}
+
+/// Output that never stops, while never reading input.
+async fn chatty(session: &mut Session) -> shenron::Result {
+    loop {
+        session.write_str(".").await?;
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+}
+
+/// Echoes input until `q`.
+async fn echo_until_q(session: &mut Session) -> shenron::Result {
+    while let Some(data) = session.input().await {
+        if data == b"q" {
+            break;
+        }
+        session.write(&data).await?;
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn idle_timeout_middleware_ignores_output() {
+    let port = start_server_with(chatty, |server| {
+        server
+            .with(IdleTimeout::new(Duration::from_millis(300)).warning(Duration::from_millis(150)))
+    })
+    .await;
+    let handle = connect_and_auth(port).await;
+
+    let mut channel = handle.channel_open_session().await.expect("channel");
+    channel.exec(true, "chatty").await.expect("exec");
+
+    let mut stdout = 0;
+    let mut stderr = Vec::new();
+    let mut exit_status = None;
+
+    tokio::time::timeout(Duration::from_secs(2), async {
+        while let Some(msg) = channel.wait().await {
+            match msg {
+                ChannelMsg::Data { data } => stdout += data.len(),
+                ChannelMsg::ExtendedData { data, ext: 1 } => stderr.extend_from_slice(&data),
+                ChannelMsg::ExitStatus { exit_status: code } => exit_status = Some(code),
+                _ => {}
+            }
+        }
+    })
+    .await
+    .expect("server never closed the channel");
+
+    assert!(stdout > 0);
+    assert_eq!(
+        String::from_utf8_lossy(&stderr),
+        "\r\nsession idle; closing in 1s unless there's input\r\n\r\nsession closed after idling\r\n"
+    );
+    assert_eq!(exit_status, Some(124));
+}
+
+#[tokio::test]
+async fn idle_timeout_middleware_is_reset_by_input() {
+    let port = start_server_with(echo_until_q, |server| {
+        server.with(IdleTimeout::new(Duration::from_millis(150)))
+    })
+    .await;
+    let handle = connect_and_auth(port).await;
+
+    let mut channel = handle.channel_open_session().await.expect("channel");
+    channel.exec(true, "echo").await.expect("exec");
+
+    for key in ["a", "b", "c", "q"] {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        channel.data(key.as_bytes()).await.expect("data");
+    }
+
+    let out = read_to_close(&mut channel).await;
+
+    assert_eq!(out.stdout, "abc");
+    assert_eq!(out.exit_status, Some(0));
+}
@@ -0,0 +1,100 @@
+//! `IoHooks`: callbacks see a session's input and output, redacted if asked.
+
+#![feature(async_fn_traits, unboxed_closures)]
+
+mod common;
+
+use std::sync::{Arc, Mutex};
+
+use common::{connect_and_auth, read_to_close, start_server_with};
+use shenron::{
+    Direction, Event, Session,
+    middleware::{Chunk, IoHooks},
+};
+
+/// Echoes input back until EOF.
+async fn echo(session: &mut Session) -> shenron::Result {
+    while let Some(event) = session.next().await {
+        match event {
+            Event::Input(data) => session.write(&data).await?,
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    session.write_stderr_str("bye").await
+}
+
+type Seen = Arc<Mutex<Vec<(Direction, Vec<u8>)>>>;
+
+fn hooks(seen: &Seen) -> IoHooks {
+    let record = |seen: Seen| {
+        move |chunk: &Chunk<'_>| {
+            seen.lock()
+                .expect("seen poisoned")
+                .push((chunk.direction, chunk.data.to_vec()));
+        }
+    };
+
+    IoHooks::new()
+        .on_input(record(Arc::clone(seen)))
+        .on_output(record(Arc::clone(seen)))
+}
+
+fn joined(seen: &Seen, direction: Direction) -> String {
+    let bytes: Vec<u8> = seen
+        .lock()
+        .expect("seen poisoned")
+        .iter()
+        .filter(|(d, _)| *d == direction)
+        .flat_map(|(_, data)| data.clone())
+        .collect();
+
+    String::from_utf8(bytes).expect("utf-8")
+}
+
+#[tokio::test]
+async fn hooks_see_input_and_output() {
+    let seen = Seen::default();
+    let port = start_server_with(echo, {
+        let hooks = hooks(&seen);
+        move |server| server.with(hooks)
+    })
+    .await;
+    let handle = connect_and_auth(port).await;
+
+    let mut channel = handle.channel_open_session().await.expect("open");
+    channel.exec(true, "echo").await.expect("exec");
+    channel.data(&b"hunter2\n"[..]).await.expect("data");
+    channel.eof().await.expect("eof");
+    assert_eq!(read_to_close(&mut channel).await.stdout, "hunter2\n");
+
+    assert_eq!(joined(&seen, Direction::Input), "hunter2\n");
+    assert_eq!(joined(&seen, Direction::Stdout), "hunter2\n");
+    assert_eq!(joined(&seen, Direction::Stderr), "bye");
+}
+
+#[tokio::test]
+async fn input_can_be_redacted() {
+    let seen = Seen::default();
+    let port = start_server_with(echo, {
+        let hooks = hooks(&seen).redact_input().redact(|_, data| {
+            String::from_utf8_lossy(data)
+                .replace("bye", "***")
+                .into_bytes()
+        });
+        move |server| server.with(hooks)
+    })
+    .await;
+    let handle = connect_and_auth(port).await;
+
+    let mut channel = handle.channel_open_session().await.expect("open");
+    channel.exec(true, "echo").await.expect("exec");
+    channel.data(&b"hunter2\n"[..]).await.expect("data");
+    channel.eof().await.expect("eof");
+    assert_eq!(read_to_close(&mut channel).await.stdout, "hunter2\n");
+
+    assert_eq!(joined(&seen, Direction::Input), "*******\n");
+    assert_eq!(joined(&seen, Direction::Stdout), "hunter2\n");
+    assert_eq!(joined(&seen, Direction::Stderr), "***");
+}
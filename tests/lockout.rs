@@ -0,0 +1,103 @@
+//! Brute-force throttling: lockout bans the client's address across
+//! connections, and backoff slows each failure within one.
+
+#![feature(async_fn_traits, unboxed_closures)]
+
+mod common;
+
+use std::{sync::Arc, time::Duration};
+
+use common::{AcceptAll, start_server_with};
+use russh::client::{self, AuthResult};
+use shenron::{
+    ServerEvent, Session,
+    auth::{AuthBackoff, Lockout},
+};
+
+async fn noop(_session: &mut Session) -> shenron::Result {
+    Ok(())
+}
+
+async fn try_password(port: u16, password: &str) -> Result<AuthResult, russh::Error> {
+    let config = Arc::new(client::Config::default());
+    let mut handle = client::connect(config, ("127.0.0.1", port), AcceptAll).await?;
+
+    handle.authenticate_password("alice", password).await
+}
+
+#[tokio::test]
+async fn repeated_failures_ban_the_address() {
+    let mut events = None;
+
+    let port = start_server_with(noop, |server| {
+        events = Some(server.events());
+
+        server
+            .auth_rejection_delay(Duration::ZERO)
+            .password_auth(|_user, password| async move { password == "hunter2" })
+            .lockout(Lockout::new().threshold(2))
+    })
+    .await;
+
+    for _ in 0..2 {
+        let result = try_password(port, "wrong").await.expect("auth request");
+        assert!(matches!(result, AuthResult::Failure { .. }));
+    }
+
+    // Banned now: even the right password is turned away before it's checked.
+    assert!(!matches!(
+        try_password(port, "hunter2").await,
+        Ok(AuthResult::Success)
+    ));
+
+    let mut events = events.expect("subscribed");
+    let banned = async {
+        loop {
+            if let Ok(ServerEvent::LockedOut { user, duration, .. }) = events.recv().await {
+                return (user, duration);
+            }
+        }
+    };
+    let (user, duration) = tokio::time::timeout(Duration::from_secs(2), banned)
+        .await
+        .expect("lockout event");
+
+    assert_eq!(user, "alice");
+    assert_eq!(duration, Duration::from_mins(1));
+}
+
+#[tokio::test]
+async fn backoff_grows_with_each_failure() {
+    let port = start_server_with(noop, |server| {
+        server
+            .auth_rejection_delay(Duration::ZERO)
+            .auth_rejection_delay_initial(Duration::ZERO)
+            .password_auth(|_user, _password| async { false })
+            .auth_backoff(
+                AuthBackoff::new()
+                    .initial(Duration::from_millis(200))
+                    .multiplier(2.0),
+            )
+    })
+    .await;
+
+    let config = Arc::new(client::Config::default());
+    let mut handle = client::connect(config, ("127.0.0.1", port), AcceptAll)
+        .await
+        .expect("connect");
+
+    let mut timings = Vec::new();
+    for _ in 0..2 {
+        let started = tokio::time::Instant::now();
+        let result = handle
+            .authenticate_password("alice", "wrong")
+            .await
+            .expect("auth request");
+
+        assert!(matches!(result, AuthResult::Failure { .. }));
+        timings.push(started.elapsed());
+    }
+
+    assert!(timings[0] >= Duration::from_millis(200), "{timings:?}");
+    assert!(timings[1] >= Duration::from_millis(400), "{timings:?}");
+}
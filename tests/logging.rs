@@ -0,0 +1,97 @@
+//! `Logging`: JSON lines with the chosen fields, custom fields and redacted
+//! commands.
+
+#![feature(async_fn_traits, unboxed_closures)]
+
+mod common;
+
+use std::{
+    io::Write,
+    sync::{Arc, Mutex},
+};
+
+use common::{connect_and_auth, read_to_close, start_server_with};
+use shenron::{
+    Session,
+    middleware::{LogField, Logging},
+};
+
+/// A writer the test can read back after the server wrote to it.
+#[derive(Clone, Default)]
+struct Lines(Arc<Mutex<Vec<u8>>>);
+
+impl Lines {
+    fn lines(&self) -> Vec<String> {
+        String::from_utf8(self.0.lock().expect("lock").clone())
+            .expect("utf-8")
+            .lines()
+            .map(String::from)
+            .collect()
+    }
+}
+
+impl Write for Lines {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().expect("lock").extend_from_slice(buf);
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+async fn app(session: &mut Session) -> shenron::Result<u32> {
+    session.write_str("done").await?;
+
+    Ok(3)
+}
+
+#[tokio::test]
+async fn logs_json_lines_with_chosen_fields() {
+    let lines = Lines::default();
+    let writer = lines.clone();
+
+    let port = start_server_with(app, move |server| {
+        server.with(
+            Logging::new()
+                .fields([
+                    LogField::User,
+                    LogField::Kind,
+                    LogField::Command,
+                    LogField::Bytes,
+                ])
+                .field("service", "deploy")
+                .redact(["--token=*"])
+                .json(writer),
+        )
+    })
+    .await;
+
+    let handle = connect_and_auth(port).await;
+    let mut channel = handle.channel_open_session().await.expect("channel");
+    channel
+        .exec(true, "deploy --token=s3cret \"prod\"")
+        .await
+        .expect("exec");
+    assert_eq!(read_to_close(&mut channel).await.exit_status, Some(3));
+
+    let lines = lines.lines();
+    assert_eq!(lines.len(), 2);
+
+    let started = &lines[0];
+    assert!(started.contains(r#""level":"info","message":"session started""#));
+    assert!(started.contains(r#""user":"alice""#));
+    assert!(started.contains(r#""kind":"exec(deploy [redacted] \"prod\")""#));
+    assert!(started.ends_with(r#""service":"deploy"}"#));
+    assert!(!started.contains("s3cret"));
+    assert!(!started.contains("session\":"));
+    assert!(!started.contains("remote"));
+
+    let ended = &lines[1];
+    assert!(ended.contains(r#""message":"session ended""#));
+    assert!(ended.contains(r#""bytes_written":4"#));
+    assert!(ended.ends_with(r#""exit_code":3}"#));
+    assert!(!ended.contains("elapsed_ms"));
+}
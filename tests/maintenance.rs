@@ -0,0 +1,61 @@
+//! `Maintenance`: new sessions are turned away while the flag is on, except
+//! for exempt users.
+
+#![feature(async_fn_traits, unboxed_closures)]
+
+mod common;
+
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, Ordering},
+};
+
+use common::{exec_output, start_server_with};
+use shenron::{Session, middleware::Maintenance};
+use tokio::sync::watch;
+
+async fn app(session: &mut Session) -> shenron::Result {
+    session.write_str("app").await
+}
+
+async fn run(port: u16) -> (String, String, Option<u32>) {
+    let output = exec_output(port, "anything").await;
+
+    (output.stdout, output.stderr, output.exit_status)
+}
+
+#[tokio::test]
+async fn the_flag_toggles_at_runtime() {
+    let flag = Arc::new(AtomicBool::new(false));
+    let port = start_server_with(app, {
+        let flag = Arc::clone(&flag);
+        move |server| server.with(Maintenance::new(flag).message("brb\r\n"))
+    })
+    .await;
+
+    assert_eq!(run(port).await, ("app".into(), String::new(), Some(0)));
+
+    flag.store(true, Ordering::Relaxed);
+    assert_eq!(run(port).await, (String::new(), "brb\r\n".into(), Some(1)));
+
+    flag.store(false, Ordering::Relaxed);
+    assert_eq!(run(port).await.0, "app");
+}
+
+#[tokio::test]
+async fn exempt_users_get_in() {
+    let (tx, rx) = watch::channel(true);
+    let port = start_server_with(app, move |server| {
+        server.with(Maintenance::new(rx).exempt(["alice"]))
+    })
+    .await;
+
+    assert_eq!(run(port).await.0, "app");
+
+    let other = start_server_with(app, |server| {
+        server.with(Maintenance::new(tx.subscribe()).exempt(["root"]))
+    })
+    .await;
+
+    assert_eq!(run(other).await.2, Some(1));
+}
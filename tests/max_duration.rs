@@ -0,0 +1,76 @@
+//! `MaxDuration`: sessions past their time limit are cut off with a
+//! distinct exit status.
+
+#![feature(async_fn_traits, unboxed_closures)]
+
+mod common;
+
+use std::time::Duration;
+
+use common::{connect_and_auth, read_to_close, start_server_with};
+use russh::ChannelMsg;
+use shenron::{Session, middleware::MaxDuration};
+
+/// Runs for 300ms unless cut off.
+async fn slow(session: &mut Session) -> shenron::Result {
+    session.write_str("started;").await?;
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    session.write_str("finished").await
+}
+
+#[tokio::test]
+async fn sessions_past_the_limit_are_cut_off() {
+    let port = start_server_with(slow, |server| {
+        server.with(MaxDuration::new(Duration::from_millis(100)))
+    })
+    .await;
+    let handle = connect_and_auth(port).await;
+
+    let mut channel = handle.channel_open_session().await.expect("channel");
+    channel.exec(true, "work").await.expect("exec");
+
+    let mut stderr = Vec::new();
+    let mut stdout = Vec::new();
+    let mut exit_status = None;
+
+    tokio::time::timeout(Duration::from_secs(2), async {
+        while let Some(msg) = channel.wait().await {
+            match msg {
+                ChannelMsg::Data { data } => stdout.extend_from_slice(&data),
+                ChannelMsg::ExtendedData { data, ext: 1 } => stderr.extend_from_slice(&data),
+                ChannelMsg::ExitStatus { exit_status: code } => exit_status = Some(code),
+                _ => {}
+            }
+        }
+    })
+    .await
+    .expect("server never closed the channel");
+
+    assert_eq!(stdout, b"started;");
+    assert_eq!(stderr, b"\r\nsession time limit reached\r\n");
+    assert_eq!(exit_status, Some(124));
+}
+
+#[tokio::test]
+async fn per_session_limits_can_be_lifted() {
+    let port = start_server_with(slow, |server| {
+        server.with(
+            MaxDuration::from_fn(|session| {
+                (session.raw_command() != Some("vip")).then_some(Duration::from_millis(100))
+            })
+            .exit_code(75),
+        )
+    })
+    .await;
+    let handle = connect_and_auth(port).await;
+
+    let mut vip = handle.channel_open_session().await.expect("channel");
+    vip.exec(true, "vip").await.expect("exec");
+    let out = read_to_close(&mut vip).await;
+    assert_eq!(out.stdout, "started;finished");
+    assert_eq!(out.exit_status, Some(0));
+
+    let mut other = handle.channel_open_session().await.expect("channel");
+    other.exec(true, "other").await.expect("exec");
+    assert_eq!(read_to_close(&mut other).await.exit_status, Some(75));
+}
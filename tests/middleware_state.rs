@@ -0,0 +1,41 @@
+//! `from_fn_with_state`: ad-hoc middleware lent shared state.
+
+#![feature(async_fn_traits, unboxed_closures)]
+
+mod common;
+
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use common::{connect_and_auth, read_to_close, start_server_with};
+use shenron::{Exit, Next, Session, middleware::from_fn_with_state};
+
+async fn app(session: &mut Session) -> shenron::Result {
+    session.write_str("app").await
+}
+
+async fn numbered(
+    counter: &AtomicU32,
+    session: &mut Session,
+    next: Next<'_>,
+) -> shenron::Result<Exit> {
+    let n = counter.fetch_add(1, Ordering::Relaxed) + 1;
+    session.write_str(&format!("#{n} ")).await?;
+
+    Ok(next.run(session).await)
+}
+
+#[tokio::test]
+async fn state_is_shared_across_sessions() {
+    let port = start_server_with(app, |server| {
+        server.with(from_fn_with_state(AtomicU32::new(0), numbered))
+    })
+    .await;
+    let handle = connect_and_auth(port).await;
+
+    for expected in ["#1 app", "#2 app"] {
+        let mut channel = handle.channel_open_session().await.expect("open");
+        channel.exec(true, "x").await.expect("exec");
+
+        assert_eq!(read_to_close(&mut channel).await.stdout, expected);
+    }
+}
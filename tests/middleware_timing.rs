@@ -0,0 +1,102 @@
+//! `on_middleware_timing`: each layer's own time, before and after `next`.
+
+#![feature(async_fn_traits, unboxed_closures)]
+
+mod common;
+
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use common::{connect_and_auth, read_to_close, start_server_with};
+use shenron::{Exit, Next, Session, middleware::LayerTiming};
+
+async fn app(session: &mut Session) -> shenron::Result {
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    session.write_str("app").await
+}
+
+async fn slow(session: &mut Session, next: Next<'_>) -> Exit {
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    next.run(session).await
+}
+
+/// Gives the rest of the chain 50ms, then takes 30ms of its own.
+async fn cutoff(session: &mut Session, next: Next<'_>) -> Exit {
+    let exit = tokio::time::timeout(Duration::from_millis(50), next.run(session))
+        .await
+        .unwrap_or(Exit::Code(1));
+
+    tokio::time::sleep(Duration::from_millis(30)).await;
+
+    exit
+}
+
+#[tokio::test]
+async fn layers_are_timed_without_the_rest_of_the_chain() {
+    let timings = Arc::new(Mutex::new(Vec::<LayerTiming>::new()));
+
+    let port = start_server_with(app, {
+        let timings = Arc::clone(&timings);
+        move |server| {
+            server
+                .on_middleware_timing(move |timing| {
+                    timings.lock().expect("lock").push(timing.clone());
+                })
+                .with(slow)
+        }
+    })
+    .await;
+
+    let handle = connect_and_auth(port).await;
+    let mut channel = handle.channel_open_session().await.expect("channel");
+    channel.exec(true, "x").await.expect("exec");
+    assert_eq!(read_to_close(&mut channel).await.stdout, "app");
+
+    let timings = timings.lock().expect("lock");
+    let slow = timings
+        .iter()
+        .find(|timing| timing.layer.ends_with("::slow"))
+        .expect("slow layer timed");
+
+    assert!(slow.ran_next);
+    assert!(slow.before >= Duration::from_millis(50));
+    assert!(slow.before < Duration::from_millis(100), "{slow:?}");
+    assert!(slow.after < Duration::from_millis(50), "{slow:?}");
+}
+
+#[tokio::test]
+async fn layers_that_cancel_next_are_timed_from_the_cancellation() {
+    let timings = Arc::new(Mutex::new(Vec::<LayerTiming>::new()));
+
+    let port = start_server_with(app, {
+        let timings = Arc::clone(&timings);
+        move |server| {
+            server
+                .on_middleware_timing(move |timing| {
+                    timings.lock().expect("lock").push(timing.clone());
+                })
+                .with(cutoff)
+        }
+    })
+    .await;
+
+    let handle = connect_and_auth(port).await;
+    let mut channel = handle.channel_open_session().await.expect("channel");
+    channel.exec(true, "x").await.expect("exec");
+    assert_eq!(read_to_close(&mut channel).await.exit_status, Some(1));
+
+    let timings = timings.lock().expect("lock");
+    let cutoff = timings
+        .iter()
+        .find(|timing| timing.layer.ends_with("::cutoff"))
+        .expect("cutoff layer timed");
+
+    assert!(cutoff.ran_next);
+    assert!(cutoff.before < Duration::from_millis(50), "{cutoff:?}");
+    assert!(cutoff.after >= Duration::from_millis(30), "{cutoff:?}");
+    assert!(cutoff.after < Duration::from_millis(80), "{cutoff:?}");
+}
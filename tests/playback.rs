@@ -0,0 +1,46 @@
+//! `Playback`: a recorded cast is replayed to the client.
+
+#![cfg(feature = "playback")]
+#![feature(async_fn_traits, unboxed_closures)]
+
+mod common;
+
+use std::time::{Duration, Instant};
+
+use common::{connect_and_auth, read_to_close, start_server_with};
+use shenron::{
+    Session,
+    middleware::{Cast, Playback},
+};
+
+const CAST: &str = concat!(
+    "{\"version\": 2, \"width\": 80, \"height\": 24, \"env\": {\"TERM\": \"xterm\"}}\n",
+    "[0.1, \"o\", \"$ \"]\n",
+    "[0.2, \"i\", \"ls\\r\"]\n",
+    "[0.4, \"o\", \"ls\\r\\n\"]\n",
+    "[10.4, \"o\", \"done\\r\\n\"]\n",
+);
+
+async fn app(session: &mut Session) -> shenron::Result {
+    session.write_str("app").await
+}
+
+#[tokio::test]
+async fn casts_are_played_back() {
+    let cast = Cast::parse(CAST).expect("parses");
+    let playback = Playback::new(cast)
+        .speed(2.0)
+        .max_idle(Duration::from_millis(100));
+    let port = start_server_with(app, move |server| server.with(playback)).await;
+
+    let handle = connect_and_auth(port).await;
+    let mut channel = handle.channel_open_session().await.expect("channel");
+    channel.exec(true, "replay").await.expect("exec");
+
+    let started = Instant::now();
+    let output = read_to_close(&mut channel).await;
+
+    assert_eq!(output.stdout, "$ ls\r\ndone\r\n");
+    assert_eq!(output.exit_status, Some(0));
+    assert!(started.elapsed() < Duration::from_secs(2));
+}
@@ -0,0 +1,70 @@
+//! Pre-auth guardrails: connections that never authenticate can't hold
+//! server resources indefinitely.
+
+#![feature(async_fn_traits, unboxed_closures)]
+
+mod common;
+
+use std::{sync::Arc, time::Duration};
+
+use common::{AcceptAll, start_server_with};
+use russh::client;
+use shenron::Session;
+use tokio::{io::AsyncReadExt, net::TcpStream};
+
+async fn noop(_session: &mut Session) -> shenron::Result {
+    Ok(())
+}
+
+/// Read until the server closes the socket, bounded so a server that never
+/// hangs up fails the test instead of stalling it.
+async fn closed_within(stream: &mut TcpStream, limit: Duration) -> bool {
+    let mut buf = [0u8; 1024];
+
+    let drain = async {
+        while let Ok(n) = stream.read(&mut buf).await {
+            if n == 0 {
+                break;
+            }
+        }
+    };
+
+    tokio::time::timeout(limit, drain).await.is_ok()
+}
+
+#[tokio::test]
+async fn pre_auth_timeout_disconnects_idle_handshake() {
+    let port = start_server_with(noop, |server| {
+        server.pre_auth_timeout(Duration::from_millis(200))
+    })
+    .await;
+
+    // Connect but never speak SSH.
+    let mut stream = TcpStream::connect(("127.0.0.1", port))
+        .await
+        .expect("connect");
+
+    assert!(closed_within(&mut stream, Duration::from_secs(2)).await);
+}
+
+#[tokio::test]
+async fn connections_beyond_pre_auth_cap_are_dropped() {
+    let port = start_server_with(noop, |server| server.max_pre_auth_connections(1)).await;
+
+    // Holds the only pre-auth slot.
+    let _squatter = TcpStream::connect(("127.0.0.1", port))
+        .await
+        .expect("connect");
+
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    let config = Arc::new(client::Config::default());
+    let result = tokio::time::timeout(
+        Duration::from_secs(2),
+        client::connect(config, ("127.0.0.1", port), AcceptAll),
+    )
+    .await
+    .expect("connect attempt hung");
+
+    assert!(result.is_err(), "second pre-auth connection must be refused");
+}
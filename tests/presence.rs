@@ -0,0 +1,41 @@
+//! `Presence`: sessions are listed while connected and gone once they end.
+
+#![feature(async_fn_traits, unboxed_closures)]
+
+mod common;
+
+use common::{connect_and_auth, read_to_close, start_server_with};
+use shenron::{Session, middleware::Presence};
+
+async fn who(session: &mut Session) -> shenron::Result {
+    let presence = session.get::<Presence>().cloned().expect("presence");
+
+    for present in presence.list() {
+        session
+            .write_str(&format!("{} {}\n", present.user, present.session))
+            .await?;
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn lists_connected_sessions() {
+    let presence = Presence::new();
+    let port = start_server_with(who, {
+        let presence = presence.clone();
+        move |server| server.with(presence)
+    })
+    .await;
+
+    let handle = connect_and_auth(port).await;
+    let mut channel = handle.channel_open_session().await.expect("channel");
+    channel.exec(true, "who").await.expect("exec");
+
+    let output = read_to_close(&mut channel).await;
+    assert!(output.stdout.starts_with("alice "), "{:?}", output.stdout);
+    assert_eq!(output.stdout.lines().count(), 1);
+
+    assert!(presence.is_empty());
+    assert!(!presence.is_online("alice"));
+}
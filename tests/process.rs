@@ -0,0 +1,84 @@
+//! `SignalForwarder` delivering a session's signals and Ctrl+C to a child
+//! process, which then reports its death by signal like OpenSSH would.
+
+#![cfg(all(unix, feature = "process"))]
+#![feature(async_fn_traits, unboxed_closures)]
+
+mod common;
+
+use std::{process::ExitStatus, time::Duration};
+
+use common::{connect_and_auth, start_server};
+use russh::ChannelMsg;
+use shenron::{Session, Signal, process::SignalForwarder};
+use tokio::process::Command;
+
+/// Runs `sleep` until it exits, forwarding every event to it.
+async fn sleeper(session: &mut Session) -> shenron::Result<ExitStatus> {
+    let mut child = Command::new("sleep").arg("30").kill_on_drop(true).spawn()?;
+    let forwarder = SignalForwarder::new(child.id().expect("running"));
+
+    session.write_str("started").await?;
+
+    loop {
+        tokio::select! {
+            status = child.wait() => return Ok(status?),
+            Some(event) = session.next() => forwarder.forward(&event)?,
+        }
+    }
+}
+
+/// Waits for the handler to start, pokes it with `poke`, and returns the
+/// signal the session reported dying of.
+async fn killed_by<F>(poke: F) -> Option<Signal>
+where
+    F: AsyncFnOnce(&mut russh::Channel<russh::client::Msg>),
+{
+    let port = start_server(sleeper).await;
+    let handle = connect_and_auth(port).await;
+
+    let mut channel = handle.channel_open_session().await.expect("channel");
+    channel.exec(true, "sleep").await.expect("exec");
+
+    while let Some(msg) = channel.wait().await {
+        if matches!(msg, ChannelMsg::Data { .. }) {
+            break;
+        }
+    }
+
+    poke(&mut channel).await;
+
+    let mut signal = None;
+    let drain = async {
+        while let Some(msg) = channel.wait().await {
+            if let ChannelMsg::ExitSignal { signal_name, .. } = msg {
+                signal = Some(signal_name);
+            }
+        }
+    };
+    tokio::time::timeout(Duration::from_secs(5), drain)
+        .await
+        .expect("child never exited");
+
+    signal
+}
+
+#[tokio::test]
+async fn signals_reach_the_child() {
+    let signal = killed_by(async |channel| {
+        channel.signal(Signal::TERM).await.expect("signal");
+    })
+    .await;
+
+    assert!(matches!(signal, Some(Signal::TERM)), "{signal:?}");
+}
+
+#[tokio::test]
+async fn ctrl_c_interrupts_without_a_pty() {
+    let signal = killed_by(async |channel| {
+        channel.data(&b"\x03"[..]).await.expect("data");
+    })
+    .await;
+
+    assert!(matches!(signal, Some(Signal::INT)), "{signal:?}");
+}
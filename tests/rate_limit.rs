@@ -0,0 +1,61 @@
+//! `RateLimiter`: sessions over the quota are turned away, by default or by
+//! a custom handler.
+
+#![cfg(feature = "rate-limiting")]
+#![feature(async_fn_traits, unboxed_closures)]
+
+mod common;
+
+use common::{connect_and_auth, read_to_close, start_server_with};
+use shenron::{
+    Exit, Session,
+    middleware::{RateLimited, RateLimiter},
+};
+
+async fn app(session: &mut Session) -> shenron::Result {
+    session.write_str("app").await
+}
+
+#[tokio::test]
+async fn sessions_over_the_quota_exit_with_status_1() {
+    let port = start_server_with(app, |server| server.with(RateLimiter::per_hour(1))).await;
+    let handle = connect_and_auth(port).await;
+
+    let mut first = handle.channel_open_session().await.expect("open");
+    first.exec(true, "ls").await.expect("exec");
+    assert_eq!(read_to_close(&mut first).await.stdout, "app");
+
+    let mut second = handle.channel_open_session().await.expect("open");
+    second.exec(true, "ls").await.expect("exec");
+    let limited = read_to_close(&mut second).await;
+    assert_eq!(limited.stdout, "");
+    assert_eq!(limited.exit_status, Some(1));
+}
+
+#[tokio::test]
+async fn rejections_can_be_handled_by_the_app() {
+    let port = start_server_with(app, |server| {
+        server.with(
+            RateLimiter::per_hour(1).on_reject(async |session: &mut Session| {
+                let retry_after = session.get::<RateLimited>().expect("attached").retry_after;
+                session
+                    .write_str(&format!("retry in {}m", retry_after.as_secs().div_ceil(60)))
+                    .await?;
+
+                Ok::<_, shenron::Error>(Exit::Code(75))
+            }),
+        )
+    })
+    .await;
+    let handle = connect_and_auth(port).await;
+
+    let mut first = handle.channel_open_session().await.expect("open");
+    first.exec(true, "ls").await.expect("exec");
+    assert_eq!(read_to_close(&mut first).await.stdout, "app");
+
+    let mut second = handle.channel_open_session().await.expect("open");
+    second.exec(true, "ls").await.expect("exec");
+    let limited = read_to_close(&mut second).await;
+    assert_eq!(limited.stdout, "retry in 60m");
+    assert_eq!(limited.exit_status, Some(75));
+}
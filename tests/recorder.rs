@@ -0,0 +1,70 @@
+//! `Recorder`: PTY sessions written out as asciinema casts through the
+//! session tap.
+
+#![feature(async_fn_traits, unboxed_closures)]
+
+mod common;
+
+use common::{connect_and_auth, read_to_close, start_server_with};
+use shenron::{
+    Session,
+    middleware::{CastDirectory, Recorder},
+};
+
+async fn app(session: &mut Session) -> shenron::Result {
+    session.write_str("caf\u{e9}\r\n").await?;
+    let typed = session.next_text().await.unwrap_or_default();
+    session.write_stderr_str(&format!("typed {typed}")).await
+}
+
+#[tokio::test]
+async fn pty_sessions_are_recorded_as_casts() {
+    let dir = tempfile::TempDir::new().expect("tempdir");
+    let casts = dir.path().to_path_buf();
+
+    let port = start_server_with(app, move |server| {
+        server.with(Recorder::new(CastDirectory::new(casts)).record_input(true))
+    })
+    .await;
+
+    let handle = connect_and_auth(port).await;
+    let mut channel = handle.channel_open_session().await.expect("channel");
+    channel
+        .request_pty(true, "xterm", 100, 30, 0, 0, &[])
+        .await
+        .expect("pty");
+    channel.request_shell(true).await.expect("shell");
+    channel.data(&b"ls"[..]).await.expect("send");
+    read_to_close(&mut channel).await;
+
+    // Exec sessions without a PTY aren't recorded.
+    let mut exec = handle.channel_open_session().await.expect("channel");
+    exec.data(&b"x"[..]).await.expect("send");
+    exec.exec(true, "quiet").await.expect("exec");
+    read_to_close(&mut exec).await;
+
+    let files: Vec<_> = std::fs::read_dir(dir.path())
+        .expect("casts dir")
+        .map(|entry| entry.expect("entry").path())
+        .collect();
+    assert_eq!(files.len(), 1);
+
+    let cast = std::fs::read_to_string(&files[0]).expect("cast");
+    let lines: Vec<_> = cast.lines().collect();
+
+    assert!(lines[0].starts_with("{\"version\": 2, \"width\": 100, \"height\": 30,"));
+    assert!(lines[0].ends_with("\"env\": {\"TERM\": \"xterm\"}}"));
+
+    let events: Vec<_> = lines[1..]
+        .iter()
+        .map(|line| line.split_once(", ").expect("time").1)
+        .collect();
+    assert_eq!(
+        events,
+        [
+            "\"o\", \"caf\u{e9}\\r\\n\"]",
+            "\"i\", \"ls\"]",
+            "\"o\", \"typed ls\"]"
+        ]
+    );
+}
@@ -0,0 +1,160 @@
+//! Resumable sessions: a client that drops and comes back as the same user
+//! gets its running session back, its screen redrawn as it left it.
+
+#![cfg(feature = "resume")]
+#![feature(async_fn_traits, unboxed_closures)]
+
+mod common;
+
+use std::time::Duration;
+
+use common::{connect_and_auth, read_to_close, read_until, start_server, start_server_with};
+use russh::{Disconnect, client};
+use shenron::{Event, Server, Session};
+
+async fn app(session: &mut Session) -> shenron::Result {
+    session.set_resumable(Some(Duration::from_secs(5)));
+    session.write_str("ready\r\n").await?;
+
+    while let Some(event) = session.next().await {
+        match event {
+            Event::Input(data) if data == b"q" => break,
+            Event::Input(data) => {
+                let text = String::from_utf8_lossy(&data).into_owned();
+                session.write_str(&format!("got {text}\r\n")).await?;
+            }
+            Event::Resize(size) => {
+                session
+                    .write_str(&format!("redraw {}\r\n", size.width))
+                    .await?;
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// Everything the channel sends until `needle` shows up.
+/// What a 24-row terminal `width` wide shows after `output`.
+fn screen(output: &str, width: u16) -> vt100::Screen {
+    let mut terminal = vt100::Parser::new(24, width, 0);
+    terminal.process(output.as_bytes());
+
+    terminal.screen().clone()
+}
+
+async fn open_shell(
+    handle: &client::Handle<common::AcceptAll>,
+    width: u32,
+) -> russh::Channel<client::Msg> {
+    let channel = handle.channel_open_session().await.expect("channel");
+    channel
+        .request_pty(true, "xterm", width, 24, 0, 0, &[])
+        .await
+        .expect("pty");
+    channel.request_shell(true).await.expect("shell");
+
+    channel
+}
+
+#[tokio::test]
+async fn reconnecting_picks_up_the_detached_session() {
+    let port = start_server_with(app, Server::resumable_sessions).await;
+
+    let first = connect_and_auth(port).await;
+    let mut channel = open_shell(&first, 80).await;
+    assert!(read_until(&mut channel, "ready").await.contains("ready"));
+
+    channel.data(&b"a"[..]).await.expect("send");
+    assert!(read_until(&mut channel, "got a").await.contains("got a"));
+
+    first
+        .disconnect(Disconnect::ByApplication, "", "en")
+        .await
+        .expect("disconnect");
+    drop(channel);
+
+    let second = connect_and_auth(port).await;
+    let mut channel = open_shell(&second, 100).await;
+    let replayed = read_until(&mut channel, "redraw 100").await;
+
+    assert!(replayed.starts_with("\x1bc"), "{replayed:?}");
+    assert!(
+        screen(&replayed, 100)
+            .contents()
+            .starts_with("ready\ngot a\nredraw 100"),
+        "{replayed:?}"
+    );
+
+    channel.data(&b"b"[..]).await.expect("send");
+    channel.data(&b"q"[..]).await.expect("send");
+
+    let out = read_to_close(&mut channel).await;
+
+    assert_eq!(out.stdout, "got b\r\n");
+    assert_eq!(out.exit_status, Some(0));
+}
+
+#[tokio::test]
+async fn without_resumable_sessions_a_new_shell_starts_fresh() {
+    let port = start_server(app).await;
+
+    let first = connect_and_auth(port).await;
+    let mut channel = open_shell(&first, 80).await;
+    read_until(&mut channel, "ready").await;
+
+    first
+        .disconnect(Disconnect::ByApplication, "", "en")
+        .await
+        .expect("disconnect");
+    drop(channel);
+
+    let second = connect_and_auth(port).await;
+    let mut channel = open_shell(&second, 100).await;
+
+    assert!(read_until(&mut channel, "ready").await.starts_with("ready"));
+}
+
+/// A full-screen dashboard redrawn in place, that never reads input.
+async fn dashboard(session: &mut Session) -> shenron::Result {
+    session.set_resumable(Some(Duration::from_secs(5)));
+    session.write_str("\x1b[?1049h\x1b[2J").await?;
+
+    for tick in 0..50 {
+        session
+            .write_str(&format!("\x1b[5;10Htick {tick:02}"))
+            .await?;
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn sessions_that_only_write_are_resumed_with_their_screen() {
+    let port = start_server_with(dashboard, Server::resumable_sessions).await;
+
+    let first = connect_and_auth(port).await;
+    let mut channel = open_shell(&first, 80).await;
+    read_until(&mut channel, "tick 03").await;
+
+    first
+        .disconnect(Disconnect::ByApplication, "", "en")
+        .await
+        .expect("disconnect");
+    drop(channel);
+
+    let second = connect_and_auth(port).await;
+    let mut channel = open_shell(&second, 80).await;
+    let out = read_to_close(&mut channel).await;
+
+    assert!(out.stdout.starts_with("\x1bc"), "{:?}", out.stdout);
+    assert_eq!(out.exit_status, Some(0));
+
+    let screen = screen(&out.stdout, 80);
+    let row = screen.rows(0, 80).nth(4).expect("row");
+
+    assert!(screen.alternate_screen());
+    assert_eq!(row, "         tick 49");
+}
@@ -0,0 +1,33 @@
+//! `Server::reverse_dns_with`: sessions see the name the lookup gives the
+//! client's address, and nothing when the lookup is off.
+
+#![feature(async_fn_traits, unboxed_closures)]
+
+mod common;
+
+use std::time::Duration;
+
+use common::{exec_output, start_server, start_server_with};
+use shenron::Session;
+
+async fn hostname(session: &mut Session) -> shenron::Result {
+    let name = session.remote_hostname().unwrap_or("-").to_string();
+    session.write_str(&name).await
+}
+
+#[tokio::test]
+async fn the_lookup_names_the_client_address() {
+    let port = start_server_with(hostname, |server| {
+        server.reverse_dns_with(Duration::from_secs(2), |ip| Some(format!("host-{ip}")))
+    })
+    .await;
+
+    assert_eq!(exec_output(port, "name").await.stdout, "host-127.0.0.1");
+}
+
+#[tokio::test]
+async fn hostname_is_unset_by_default() {
+    let port = start_server(hostname).await;
+
+    assert_eq!(exec_output(port, "name").await.stdout, "-");
+}
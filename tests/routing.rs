@@ -0,0 +1,160 @@
+//! Builder-level routing: registered subsystems and exec commands get their
+//! own handlers, everything else falls through to the app, and middleware
+//! registered earlier wraps the routes.
+
+#![feature(async_fn_traits, unboxed_closures)]
+
+mod common;
+
+use common::{connect_and_auth, exec_output, read_to_close, start_server_with};
+use shenron::{Auth, Exit, Next, Session, middleware::Route};
+
+async fn metrics(session: &mut Session) -> shenron::Result {
+    session.write_str("metrics").await
+}
+
+async fn backup(session: &mut Session) -> shenron::Result {
+    session.write_str("backup").await
+}
+
+async fn app(session: &mut Session) -> shenron::Result {
+    let name = session.subsystem().unwrap_or("-").to_string();
+    session.write_str(&format!("app:{name}")).await
+}
+
+async fn tag(session: &mut Session, next: Next<'_>) -> shenron::Result<Exit> {
+    session.write_str("[").await?;
+    let exit = next.run(session).await;
+    session.write_str("]").await?;
+
+    Ok(exit)
+}
+
+async fn subsystem_output(port: u16, name: &str) -> String {
+    let handle = connect_and_auth(port).await;
+    let mut channel = handle.channel_open_session().await.expect("open");
+    channel
+        .request_subsystem(true, name)
+        .await
+        .expect("subsystem");
+
+    read_to_close(&mut channel).await.stdout
+}
+
+#[tokio::test]
+async fn subsystems_route_to_their_handlers() {
+    let port = start_server_with(app, |server| {
+        server
+            .password_auth(|_user, _password| async { Auth::accept() })
+            .with(tag)
+            .subsystem("metrics", metrics)
+            .subsystem("backup", backup)
+    })
+    .await;
+
+    assert_eq!(subsystem_output(port, "metrics").await, "[metrics]");
+    assert_eq!(subsystem_output(port, "backup").await, "[backup]");
+    assert_eq!(subsystem_output(port, "other").await, "[app:other]");
+}
+
+async fn deploy(session: &mut Session) -> shenron::Result {
+    let args = session.command_args().join(",");
+    session.write_str(&format!("deploy:{args}")).await
+}
+
+async fn status(session: &mut Session) -> shenron::Result {
+    session.write_str("status").await
+}
+
+async fn unknown(session: &mut Session) -> shenron::Result<u32> {
+    session.write_str("unknown").await?;
+
+    Ok(127)
+}
+
+#[tokio::test]
+async fn commands_route_by_program_with_fallback() {
+    let port = start_server_with(app, |server| {
+        server
+            .password_auth(|_user, _password| async { Auth::accept() })
+            .with(tag)
+            .command("deploy", deploy)
+            .command("status", status)
+            .command_fallback(unknown)
+    })
+    .await;
+
+    assert_eq!(
+        exec_output(port, "deploy prod 'eu west'").await.stdout,
+        "[deploy:prod,eu west]"
+    );
+    assert_eq!(
+        exec_output(port, r#"deploy -c "do \"the\" thing""#)
+            .await
+            .stdout,
+        r#"[deploy:-c,do "the" thing]"#
+    );
+    assert_eq!(exec_output(port, "status").await.stdout, "[status]");
+    assert_eq!(exec_output(port, "deployer").await.stdout, "[unknown]");
+    assert_eq!(exec_output(port, "nope").await.exit_status, Some(127));
+
+    // Non-exec sessions skip the fallback.
+    assert_eq!(subsystem_output(port, "x").await, "[app:x]");
+}
+
+async fn shell_output(port: u16, pty: bool) -> String {
+    let handle = connect_and_auth(port).await;
+    let mut channel = handle.channel_open_session().await.expect("open");
+    if pty {
+        channel
+            .request_pty(true, "xterm", 80, 24, 0, 0, &[])
+            .await
+            .expect("pty");
+    }
+    channel.request_shell(true).await.expect("shell");
+
+    read_to_close(&mut channel).await.stdout
+}
+
+#[tokio::test]
+async fn route_picks_the_most_specific_arm() {
+    let port = start_server_with(app, |server| {
+        server.with(tag).with(
+            Route::new()
+                .pty(async |session: &mut Session| session.write_str("tui").await)
+                .exec(async |session: &mut Session| session.write_str("cli").await)
+                .command("deploy", deploy)
+                .subsystem("metrics", metrics),
+        )
+    })
+    .await;
+
+    assert_eq!(shell_output(port, true).await, "[tui]");
+    assert_eq!(exec_output(port, "ls -l").await.stdout, "[cli]");
+    assert_eq!(
+        exec_output(port, "deploy prod").await.stdout,
+        "[deploy:prod]"
+    );
+    assert_eq!(subsystem_output(port, "metrics").await, "[metrics]");
+
+    // No arm takes these, so they continue to the app.
+    assert_eq!(shell_output(port, false).await, "[app:-]");
+    assert_eq!(subsystem_output(port, "backup").await, "[app:backup]");
+}
+
+#[tokio::test]
+async fn route_fallback_takes_the_rest() {
+    let port = start_server_with(app, |server| {
+        server.with(
+            Route::new()
+                .shell(async |session: &mut Session| session.write_str("shell").await)
+                .fallback(unknown),
+        )
+    })
+    .await;
+
+    assert_eq!(shell_output(port, true).await, "shell");
+    assert_eq!(shell_output(port, false).await, "shell");
+    assert_eq!(exec_output(port, "ls").await.exit_status, Some(127));
+    assert_eq!(subsystem_output(port, "metrics").await, "unknown");
+}
@@ -0,0 +1,60 @@
+//! `Rsync`: `rsync --server` exec requests are confined to a root and
+//! handed to a backend.
+
+#![feature(async_fn_traits, unboxed_closures)]
+
+mod common;
+
+use common::{exec_output, start_server_with};
+use shenron::{
+    Exit, Session,
+    middleware::{Rsync, RsyncBackend, RsyncProcess, RsyncRequest},
+};
+
+async fn app(session: &mut Session) -> shenron::Result {
+    session.write_str("app").await
+}
+
+#[tokio::test]
+async fn transfers_are_bridged_to_the_process() {
+    let root = tempfile::tempdir().expect("tempdir");
+    let rsync = Rsync::new(root.path()).backend(RsyncProcess::new().program("echo"));
+    let port = start_server_with(app, move |server| server.with(rsync)).await;
+
+    let out = exec_output(port, "rsync --server -vlogDtpre.iLsfxC . /backups/today").await;
+    assert_eq!(
+        out.stdout,
+        "--server --munge-links -vlogDtpre.iLsfxC . backups/today\n"
+    );
+    assert_eq!(out.exit_status, Some(0));
+
+    let escape = exec_output(port, "rsync --server -vlogDtpre.iLsfxC . ../elsewhere").await;
+    assert_eq!(escape.stdout, "");
+    assert_eq!(escape.exit_status, Some(1));
+
+    assert_eq!(exec_output(port, "ls").await.stdout, "app");
+}
+
+/// Reports where it was asked to work instead of transferring anything.
+struct Report;
+
+impl RsyncBackend for Report {
+    async fn serve(&self, session: &mut Session, request: &RsyncRequest) -> shenron::Result<Exit> {
+        let line = format!("{} {:?}", request.root.display(), request.paths());
+        session.write_str(&line).await?;
+
+        Ok(Exit::Code(0))
+    }
+}
+
+#[tokio::test]
+async fn roots_can_differ_per_user() {
+    let rsync = Rsync::from_fn(|session| {
+        (session.user() == "alice").then(|| format!("/srv/{}", session.user()).into())
+    })
+    .backend(Report);
+    let port = start_server_with(app, move |server| server.with(rsync)).await;
+
+    let out = exec_output(port, "rsync --server --sender -r . docs/").await;
+    assert_eq!(out.stdout, r#"/srv/alice ["docs/"]"#);
+}
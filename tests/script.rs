@@ -0,0 +1,111 @@
+//! Rhai scripts deciding auth and screening sessions.
+
+#![cfg(feature = "rhai")]
+#![feature(async_fn_traits, unboxed_closures)]
+
+mod common;
+
+use std::sync::Arc;
+
+use common::{AcceptAll, exec_output, start_server_with};
+use russh::client::{self, AuthResult};
+use shenron::{Session, script::Script};
+
+const POLICY: &str = r#"
+    fn authenticate(ctx) {
+        if ctx.method != "password" || ctx.password != "hunter2" {
+            return "bad password";
+        }
+
+        ctx.user == "alice"
+    }
+
+    fn on_session(session) {
+        if session.kind == "exec" && session.command.starts_with("rm ") {
+            return "not on my watch\n";
+        }
+
+        #{ allow: true, message: "hi " + session.user + "\n" }
+    }
+"#;
+
+async fn app(session: &mut Session) -> shenron::Result {
+    session.write_str("app").await
+}
+
+async fn password(port: u16, user: &str, password: &str) -> bool {
+    let config = Arc::new(client::Config::default());
+    let mut handle = client::connect(config, ("127.0.0.1", port), AcceptAll)
+        .await
+        .expect("connect");
+
+    let result = handle
+        .authenticate_password(user, password)
+        .await
+        .expect("auth request");
+
+    matches!(result, AuthResult::Success)
+}
+
+fn serve(script: Script) -> impl FnOnce(shenron::Server) -> shenron::Server {
+    move |server| server.auth_provider(script.clone()).with(script)
+}
+
+#[tokio::test]
+async fn scripts_decide_auth() {
+    let port = start_server_with(app, serve(Script::new(POLICY).expect("parses"))).await;
+
+    assert!(password(port, "alice", "hunter2").await);
+    assert!(!password(port, "alice", "wrong").await);
+    assert!(!password(port, "bob", "hunter2").await);
+}
+
+#[tokio::test]
+async fn scripts_screen_sessions() {
+    let port = start_server_with(app, serve(Script::new(POLICY).expect("parses"))).await;
+
+    let allowed = exec_output(port, "ls").await;
+    assert_eq!(allowed.stdout, "hi alice\napp");
+    assert_eq!(allowed.exit_status, Some(0));
+
+    let denied = exec_output(port, "rm -rf /").await;
+    assert_eq!(denied.stdout, "");
+    assert_eq!(denied.exit_status, Some(1));
+}
+
+#[tokio::test]
+async fn failing_scripts_deny() {
+    let script = Script::new(
+        r#"
+            fn authenticate(ctx) { true }
+            fn on_session(session) { loop {} }
+        "#,
+    )
+    .expect("parses");
+    let port = start_server_with(app, serve(script)).await;
+
+    assert_eq!(exec_output(port, "ls").await.exit_status, Some(1));
+}
+
+#[tokio::test]
+async fn scripts_can_be_reloaded() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("policy.rhai");
+    std::fs::write(&path, POLICY).expect("write");
+
+    let script = Script::load(&path).expect("loads");
+    let port = start_server_with(app, serve(script.clone())).await;
+    assert_eq!(exec_output(port, "ls").await.exit_status, Some(0));
+
+    std::fs::write(
+        &path,
+        "fn authenticate(ctx) { true }\nfn on_session(s) { false }",
+    )
+    .expect("write");
+    script.reload().expect("reloads");
+    assert_eq!(exec_output(port, "ls").await.exit_status, Some(1));
+
+    std::fs::write(&path, "fn on_session(s) {").expect("write");
+    assert!(script.reload().is_err());
+    assert_eq!(exec_output(port, "ls").await.exit_status, Some(1));
+}
@@ -0,0 +1,185 @@
+//! `Server::events` reports the lifecycle of a connection in order, and
+//! `Server::on_auth_event` each auth decision.
+
+#![feature(async_fn_traits, unboxed_closures)]
+
+mod common;
+
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use common::{AcceptAll, connect_and_auth, read_to_close, start_server_with};
+use russh::{
+    client,
+    keys::{Algorithm, HashAlg, PrivateKey, PrivateKeyWithHashAlg},
+};
+use shenron::{Auth, AuthDecision, AuthEvent, ServerEvent, Session, auth::AuthMethod};
+use tokio::sync::broadcast;
+
+async fn exits_two(_session: &mut Session) -> shenron::Result<u32> {
+    Ok(2)
+}
+
+async fn next(events: &mut broadcast::Receiver<ServerEvent>) -> ServerEvent {
+    tokio::time::timeout(Duration::from_secs(2), events.recv())
+        .await
+        .expect("no event arrived")
+        .expect("event stream closed")
+}
+
+#[tokio::test]
+async fn reports_connection_auth_and_session_lifecycle() {
+    let mut subscribed = None;
+
+    let port = start_server_with(exits_two, |server| {
+        subscribed = Some(server.events());
+
+        server.password_auth(|_user, password| async move { Auth::from(password == "hunter2") })
+    })
+    .await;
+
+    let mut events = subscribed.expect("subscribed");
+
+    let handle = connect_and_auth(port).await;
+    let mut channel = handle.channel_open_session().await.expect("channel");
+    channel.exec(true, "anything").await.expect("exec");
+    read_to_close(&mut channel).await;
+
+    assert!(matches!(
+        next(&mut events).await,
+        ServerEvent::ConnectionOpened { .. }
+    ));
+    assert!(matches!(
+        next(&mut events).await,
+        ServerEvent::AuthSucceeded { user, method: AuthMethod::Password, .. } if user == "alice"
+    ));
+    assert!(matches!(
+        next(&mut events).await,
+        ServerEvent::SessionStarted { .. }
+    ));
+    assert!(matches!(
+        next(&mut events).await,
+        ServerEvent::SessionEnded { exit_code: 2, .. }
+    ));
+
+    drop(channel);
+    drop(handle);
+
+    assert!(matches!(
+        next(&mut events).await,
+        ServerEvent::ConnectionClosed { .. }
+    ));
+}
+
+#[tokio::test]
+async fn auth_hook_sees_each_decision_with_its_key() {
+    let seen: Arc<Mutex<Vec<AuthEvent>>> = Arc::default();
+
+    let port = start_server_with(exits_two, {
+        let seen = Arc::clone(&seen);
+
+        move |server| {
+            server
+                .pubkey_auth(|_user, _key| async { Auth::reject().reason("unknown key") })
+                .password_auth(|_user, password| async move { password == "hunter2" })
+                .on_auth_event(move |event| seen.lock().expect("lock").push(event))
+        }
+    })
+    .await;
+
+    let key = PrivateKey::random(&mut rand::rng(), Algorithm::Ed25519).expect("keygen");
+    let fingerprint = key.public_key().fingerprint(HashAlg::Sha256);
+
+    let config = Arc::new(client::Config::default());
+    let mut handle = client::connect(config, ("127.0.0.1", port), AcceptAll)
+        .await
+        .expect("connect");
+    let _ = handle
+        .authenticate_publickey("alice", PrivateKeyWithHashAlg::new(Arc::new(key), None))
+        .await
+        .expect("auth request");
+    let _ = handle
+        .authenticate_password("alice", "hunter2")
+        .await
+        .expect("auth request");
+
+    let seen = seen.lock().expect("lock").clone();
+    let [rejected, accepted] = seen.as_slice() else {
+        panic!("expected two events, got {seen:?}");
+    };
+
+    assert_eq!(rejected.method, AuthMethod::PublicKey);
+    assert_eq!(
+        rejected.decision,
+        AuthDecision::Rejected {
+            reason: Some("unknown key".into())
+        }
+    );
+    assert_eq!(rejected.fingerprint, Some(fingerprint));
+    assert!(rejected.client_version.starts_with("SSH-2.0-"));
+
+    assert_eq!(accepted.method, AuthMethod::Password);
+    assert_eq!(accepted.decision, AuthDecision::Accepted);
+    assert_eq!(accepted.fingerprint, None);
+    assert_eq!(accepted.user, "alice");
+}
+
+async fn prints_ids(session: &mut Session) -> shenron::Result {
+    session
+        .write_str(&format!("{} {}", session.id(), session.connection_id()))
+        .await
+}
+
+#[tokio::test]
+async fn events_carry_the_session_and_connection_ids() {
+    let mut subscribed = None;
+
+    let port = start_server_with(prints_ids, |server| {
+        subscribed = Some(server.events());
+
+        server.password_auth(|_user, password| async move { Auth::from(password == "hunter2") })
+    })
+    .await;
+
+    let mut events = subscribed.expect("subscribed");
+
+    let handle = connect_and_auth(port).await;
+    let mut outputs = Vec::new();
+    for _ in 0..2 {
+        let mut channel = handle.channel_open_session().await.expect("channel");
+        channel.exec(true, "ids").await.expect("exec");
+        outputs.push(read_to_close(&mut channel).await.stdout);
+    }
+
+    let ServerEvent::ConnectionOpened { connection_id, .. } = next(&mut events).await else {
+        panic!("expected the connection first");
+    };
+    next(&mut events).await;
+
+    let mut session_ids = Vec::new();
+    for output in &outputs {
+        let ServerEvent::SessionStarted {
+            session_id,
+            connection_id: started_on,
+            ..
+        } = next(&mut events).await
+        else {
+            panic!("expected a session start");
+        };
+        let ServerEvent::SessionEnded {
+            session_id: ended, ..
+        } = next(&mut events).await
+        else {
+            panic!("expected a session end");
+        };
+
+        assert_eq!(started_on, connection_id);
+        assert_eq!(ended, session_id);
+        assert_eq!(*output, format!("{session_id} {connection_id}"));
+        session_ids.push(session_id);
+    }
+
+    assert_ne!(session_ids[0], session_ids[1]);
+}
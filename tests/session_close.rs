@@ -1,12 +1,17 @@
 //! A session channel must close when the handler returns, however it returns.
-//! Regression tests for handlers that hang the client.
+//! Regression tests for handlers that hang the client; and the other way
+//! round, `Session::closed` noticing when the client goes first.
 
 #![feature(async_fn_traits, unboxed_closures)]
 
 mod common;
 
+use std::time::Duration;
+
 use common::{connect_and_auth, read_to_close, start_server};
-use shenron::Session;
+use russh::ChannelMsg;
+use shenron::{Session, Signal};
+use tokio::sync::mpsc;
 
 /// Returning `Ok(())` reports success.
 async fn returns_without_exit(session: &mut Session) -> shenron::Result {
@@ -23,6 +28,23 @@ async fn exits_nonzero(_session: &mut Session) -> shenron::Result<u32> {
     Ok(3)
 }
 
+async fn killed(session: &mut Session) -> shenron::Result {
+    session.write_str("working").await?;
+    session.exit_signal(Signal::KILL, true, "killed").await
+}
+
+/// Finishes, then does slow cleanup the client shouldn't wait for; the
+/// error it returns afterwards is ignored.
+async fn finishes_early(session: &mut Session) -> shenron::Result {
+    session.write_str("bye").await?;
+    session.finish().await?;
+    assert!(session.is_finished());
+
+    tokio::time::sleep(Duration::from_secs(5)).await;
+
+    Err(shenron::Error::Protocol("too late to matter".into()))
+}
+
 #[tokio::test]
 async fn ok_without_exit_closes_with_status_zero() {
     let port = start_server(returns_without_exit).await;
@@ -62,3 +84,115 @@ async fn explicit_exit_code_is_reported() {
 
     assert_eq!(out.exit_status, Some(3));
 }
+
+#[tokio::test]
+async fn exit_signal_is_reported_instead_of_a_status() {
+    let port = start_server(killed).await;
+    let handle = connect_and_auth(port).await;
+
+    let mut channel = handle.channel_open_session().await.expect("channel");
+    channel.exec(true, "anything").await.expect("exec");
+
+    let mut signal = None;
+    let mut status = None;
+    let drain = async {
+        while let Some(msg) = channel.wait().await {
+            match msg {
+                ChannelMsg::ExitSignal {
+                    signal_name,
+                    core_dumped,
+                    error_message,
+                    ..
+                } => signal = Some((signal_name, core_dumped, error_message)),
+                ChannelMsg::ExitStatus { exit_status } => status = Some(exit_status),
+                _ => {}
+            }
+        }
+    };
+    tokio::time::timeout(std::time::Duration::from_secs(2), drain)
+        .await
+        .expect("server never closed the channel");
+
+    assert!(
+        matches!(&signal, Some((Signal::KILL, true, message)) if message == "killed"),
+        "{signal:?}"
+    );
+    assert_eq!(status, None);
+}
+
+/// Serves a session that never reads, and reports whether `closed()` fired
+/// and `is_closed()` agreed, once the client has done `hang_up` — which
+/// hands back the connection if it should stay up until then.
+async fn watch_close<F, Fut>(hang_up: F) -> (bool, bool)
+where
+    F: FnOnce(russh::client::Handle<common::AcceptAll>, russh::Channel<russh::client::Msg>) -> Fut,
+    Fut: Future<Output = Option<russh::client::Handle<common::AcceptAll>>>,
+{
+    let (tx, mut rx) = mpsc::unbounded_channel();
+
+    let port = start_server(async move |session: &mut Session| -> shenron::Result {
+        assert!(!session.is_closed());
+        session.write_str("ready").await?;
+
+        let closed = tokio::time::timeout(Duration::from_secs(2), session.closed())
+            .await
+            .is_ok();
+        let _ = tx.send((closed, session.is_closed()));
+
+        Ok(())
+    })
+    .await;
+
+    let handle = connect_and_auth(port).await;
+    let mut channel = handle.channel_open_session().await.expect("channel");
+    channel.exec(true, "wait").await.expect("exec");
+
+    while !matches!(channel.wait().await, Some(ChannelMsg::Data { .. })) {}
+
+    let _kept = hang_up(handle, channel).await;
+
+    tokio::time::timeout(Duration::from_secs(3), rx.recv())
+        .await
+        .expect("handler reported")
+        .expect("sender alive")
+}
+
+#[tokio::test]
+async fn closed_resolves_when_the_client_closes_the_channel() {
+    let seen = watch_close(async |handle, channel| {
+        channel.close().await.expect("close");
+
+        // Keep the connection up: only the channel goes.
+        Some(handle)
+    })
+    .await;
+
+    assert_eq!(seen, (true, true));
+}
+
+#[tokio::test]
+async fn closed_resolves_when_the_connection_drops() {
+    let seen = watch_close(async |handle, channel| {
+        drop(channel);
+        drop(handle);
+
+        None
+    })
+    .await;
+
+    assert_eq!(seen, (true, true));
+}
+
+#[tokio::test]
+async fn finish_closes_before_the_handler_returns() {
+    let port = start_server(finishes_early).await;
+    let handle = connect_and_auth(port).await;
+
+    let mut channel = handle.channel_open_session().await.expect("channel");
+    channel.exec(true, "anything").await.expect("exec");
+
+    let out = read_to_close(&mut channel).await;
+
+    assert_eq!(out.stdout, "bye");
+    assert_eq!(out.exit_status, Some(0));
+}
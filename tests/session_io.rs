@@ -0,0 +1,344 @@
+//! Session IO beyond `write`: `Session::io` as a standard `AsyncRead` +
+//! `AsyncWrite` stream, cloneable writer handles for spawned tasks,
+//! `read_line` and `next_text`, the byte counts `Session::stats` reports,
+//! taking the channel over with `Session::take_stream`, `Session::pipe`, and
+//! buffered and vectored writes.
+
+#![feature(async_fn_traits, unboxed_closures)]
+
+mod common;
+
+use std::io::IoSlice;
+
+use common::{connect_and_auth, read_to_close, start_server};
+use russh::ChannelMsg;
+use shenron::Session;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+/// Echo input back, uppercased, until the client's EOF.
+async fn shout(session: &mut Session) -> shenron::Result {
+    let mut io = session.io();
+    let mut input = String::new();
+    io.read_to_string(&mut input).await?;
+
+    io.write_all(input.to_uppercase().as_bytes()).await?;
+    io.shutdown().await?;
+
+    Ok(())
+}
+
+async fn echo(session: &mut Session) -> shenron::Result {
+    let (mut reader, mut writer) = tokio::io::split(session.io());
+    tokio::io::copy(&mut reader, &mut writer).await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn reads_until_eof_and_writes_back() {
+    let port = start_server(shout).await;
+    let handle = connect_and_auth(port).await;
+
+    let mut channel = handle.channel_open_session().await.expect("channel");
+    channel.exec(true, "shout").await.expect("exec");
+    channel.data(&b"hello, "[..]).await.expect("data");
+    channel.data(&b"world"[..]).await.expect("data");
+    channel.eof().await.expect("eof");
+
+    let out = read_to_close(&mut channel).await;
+
+    assert_eq!(out.stdout, "HELLO, WORLD");
+    assert_eq!(out.exit_status, Some(0));
+}
+
+#[tokio::test]
+async fn copies_through_split_halves() {
+    let port = start_server(echo).await;
+    let handle = connect_and_auth(port).await;
+
+    let mut channel = handle.channel_open_session().await.expect("channel");
+    channel.request_shell(true).await.expect("shell");
+    channel.data(&b"ping"[..]).await.expect("data");
+
+    let mut echoed = Vec::new();
+    while echoed.len() < 4 {
+        if let ChannelMsg::Data { data } = channel.wait().await.expect("channel open") {
+            echoed.extend_from_slice(&data);
+        }
+    }
+    assert_eq!(echoed, b"ping");
+
+    channel.eof().await.expect("eof");
+    let out = read_to_close(&mut channel).await;
+    assert_eq!(out.exit_status, Some(0));
+}
+
+async fn background_writers(session: &mut Session) -> shenron::Result {
+    let stdout = session.stdout()?;
+
+    let tasks: Vec<_> = (1..=3)
+        .map(|n| {
+            let mut out = stdout.clone();
+            tokio::spawn(async move { out.write_all(format!("{n}").as_bytes()).await })
+        })
+        .collect();
+
+    for task in tasks {
+        task.await.expect("join")?;
+    }
+
+    stdout.write_str(".").await
+}
+
+#[tokio::test]
+async fn spawned_tasks_write_through_cloned_handles() {
+    let port = start_server(background_writers).await;
+    let handle = connect_and_auth(port).await;
+
+    let mut channel = handle.channel_open_session().await.expect("channel");
+    channel.exec(true, "count").await.expect("exec");
+
+    let out = read_to_close(&mut channel).await;
+
+    let mut digits: Vec<char> = out.stdout.trim_end_matches('.').chars().collect();
+    digits.sort_unstable();
+    assert_eq!(digits, ['1', '2', '3']);
+    assert!(out.stdout.ends_with('.'));
+    assert_eq!(out.exit_status, Some(0));
+}
+
+async fn names(session: &mut Session) -> shenron::Result {
+    while let Some(name) = session.read_line("name? ").await? {
+        session.write_str(&format!("[{name}]")).await?;
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn read_line_edits_and_echoes_on_a_pty() {
+    let port = start_server(names).await;
+    let handle = connect_and_auth(port).await;
+
+    let mut channel = handle.channel_open_session().await.expect("channel");
+    channel
+        .request_pty(true, "xterm", 80, 24, 0, 0, &[])
+        .await
+        .expect("pty");
+    channel.request_shell(true).await.expect("shell");
+    channel.data(&b"bop\x7f\x7fob\r"[..]).await.expect("data");
+    channel.data(&b"\x04"[..]).await.expect("data");
+
+    let out = read_to_close(&mut channel).await;
+
+    assert_eq!(
+        out.stdout,
+        "name? bop\rname? bo\x1b[K\rname? b\x1b[Kob\r\n[bob]name? \r\n"
+    );
+}
+
+#[tokio::test]
+async fn read_line_reads_plain_lines_without_a_pty() {
+    let port = start_server(names).await;
+    let handle = connect_and_auth(port).await;
+
+    let mut channel = handle.channel_open_session().await.expect("channel");
+    channel.exec(true, "names").await.expect("exec");
+    channel.data(&b"ann\r\nbo"[..]).await.expect("data");
+    channel.data(&b"b\n"[..]).await.expect("data");
+    channel.eof().await.expect("eof");
+
+    let out = read_to_close(&mut channel).await;
+
+    assert_eq!(out.stdout, "name? [ann]name? [bob]name? ");
+}
+
+/// Reports each chunk of text it gets, bracketed.
+async fn chunks(session: &mut Session) -> shenron::Result {
+    while let Some(text) = session.next_text().await {
+        session.write_str(&format!("[{text}]")).await?;
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn next_text_joins_split_characters() {
+    let port = start_server(chunks).await;
+    let handle = connect_and_auth(port).await;
+
+    let mut channel = handle.channel_open_session().await.expect("channel");
+    channel.exec(true, "chunks").await.expect("exec");
+
+    for part in [&b"caf\xc3"[..], b"\xa9", b"\xe2\x98"] {
+        channel.data(part).await.expect("data");
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    }
+    channel.eof().await.expect("eof");
+
+    let out = read_to_close(&mut channel).await;
+
+    assert_eq!(out.stdout, "[caf][\u{e9}]");
+}
+
+/// Reports its own stats after a read and writes through every path.
+async fn counted(session: &mut Session) -> shenron::Result {
+    session.input().await;
+    session.write_str("abc").await?;
+    session.write_stderr_str("de").await?;
+    session.stdout()?.write_all(b"fgh").await?;
+
+    let window = session.send_window().await;
+    let stats = session.stats();
+    session
+        .write_str(&format!(
+            " {}/{} open={} stalls={}",
+            stats.bytes_read(),
+            stats.bytes_written(),
+            window > 0,
+            stats.window_stalls()
+        ))
+        .await
+}
+
+#[tokio::test]
+async fn stats_count_bytes_in_and_out() {
+    let port = start_server(counted).await;
+    let handle = connect_and_auth(port).await;
+
+    let mut channel = handle.channel_open_session().await.expect("channel");
+    channel.exec(true, "count").await.expect("exec");
+    channel.data(&b"hello"[..]).await.expect("data");
+
+    let out = read_to_close(&mut channel).await;
+
+    assert_eq!(out.stdout, "abcfgh 5/8 open=true stalls=0");
+}
+
+/// A tiny protocol of its own: uppercase everything, exit 3.
+async fn takeover(session: &mut Session) -> shenron::Result {
+    let mut stream = session.take_stream()?;
+    assert!(session.is_detached());
+    assert!(session.take_stream().is_err());
+
+    let mut input = String::new();
+    stream.read_to_string(&mut input).await?;
+    stream.write_all(input.to_uppercase().as_bytes()).await?;
+
+    stream.close(3).await
+}
+
+#[tokio::test]
+async fn take_stream_hands_over_the_channel() {
+    let port = start_server(takeover).await;
+    let handle = connect_and_auth(port).await;
+
+    let mut channel = handle.channel_open_session().await.expect("channel");
+    channel
+        .request_subsystem(true, "upper")
+        .await
+        .expect("subsystem");
+    channel.data(&b"quiet"[..]).await.expect("data");
+    channel.eof().await.expect("eof");
+
+    let out = read_to_close(&mut channel).await;
+
+    assert_eq!(out.stdout, "QUIET");
+    assert_eq!(out.exit_status, Some(3));
+}
+
+/// Proxies to an in-process "upstream" that answers the whole input,
+/// uppercased, once the client is done.
+async fn proxied(session: &mut Session) -> shenron::Result {
+    let (near, mut far) = tokio::io::duplex(64);
+
+    tokio::spawn(async move {
+        let mut input = Vec::new();
+        far.read_to_end(&mut input).await?;
+        far.write_all(&input.to_ascii_uppercase()).await?;
+        far.shutdown().await
+    });
+
+    session.pipe(near).await
+}
+
+/// Proxies to an upstream that hangs up straight away.
+async fn hung_up(session: &mut Session) -> shenron::Result {
+    let (near, mut far) = tokio::io::duplex(64);
+    far.write_all(b"bye").await?;
+    drop(far);
+
+    session.pipe(near).await
+}
+
+#[tokio::test]
+async fn pipe_carries_eof_both_ways() {
+    let port = start_server(proxied).await;
+    let handle = connect_and_auth(port).await;
+
+    let mut channel = handle.channel_open_session().await.expect("channel");
+    channel.exec(true, "proxy").await.expect("exec");
+    channel.data(&b"over "[..]).await.expect("data");
+    channel.data(&b"and out"[..]).await.expect("data");
+    channel.eof().await.expect("eof");
+
+    let out = read_to_close(&mut channel).await;
+
+    assert_eq!(out.stdout, "OVER AND OUT");
+    assert_eq!(out.exit_status, Some(0));
+}
+
+#[tokio::test]
+async fn pipe_ends_when_upstream_does() {
+    let port = start_server(hung_up).await;
+    let handle = connect_and_auth(port).await;
+
+    let mut channel = handle.channel_open_session().await.expect("channel");
+    channel.request_shell(true).await.expect("shell");
+
+    let out = read_to_close(&mut channel).await;
+
+    assert_eq!(out.stdout, "bye");
+    assert_eq!(out.exit_status, Some(0));
+}
+
+/// Writes a byte at a time into a buffer, then a vectored write, each
+/// group flushed in one message; stderr flushes what's pending first.
+async fn batched(session: &mut Session) -> shenron::Result {
+    session.set_write_buffer(1024);
+
+    for byte in b"abc" {
+        session.write(&[*byte]).await?;
+    }
+    session.flush().await?;
+
+    session
+        .write_vectored(&[IoSlice::new(b"de"), IoSlice::new(b"fg")])
+        .await?;
+    session.write_stderr(b"!").await?;
+
+    session.write(b"h").await
+}
+
+#[tokio::test]
+async fn buffered_writes_batch_into_fewer_messages() {
+    let port = start_server(batched).await;
+    let handle = connect_and_auth(port).await;
+
+    let mut channel = handle.channel_open_session().await.expect("channel");
+    channel.exec(true, "batch").await.expect("exec");
+
+    let mut messages = Vec::new();
+    while let Some(msg) = channel.wait().await {
+        match msg {
+            ChannelMsg::Data { data } => messages.push(String::from_utf8_lossy(&data).into_owned()),
+            ChannelMsg::ExtendedData { data, .. } => {
+                messages.push(format!("err:{}", String::from_utf8_lossy(&data)));
+            }
+            ChannelMsg::Close => break,
+            _ => {}
+        }
+    }
+
+    assert_eq!(messages, ["abc", "defg", "err:!", "h"]);
+}
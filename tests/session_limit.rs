@@ -0,0 +1,96 @@
+//! Concurrent session caps: sessions past the limit are turned away until
+//! a slot frees up.
+
+#![feature(async_fn_traits, unboxed_closures)]
+
+mod common;
+
+use common::{AcceptAll, connect_and_auth, read_to_close, start_server_with};
+use russh::ChannelMsg;
+use shenron::{
+    Event, Session,
+    middleware::{MaxSessionsPerIp, MaxSessionsPerUser},
+};
+
+/// Holds its slot until the client sends EOF.
+async fn hold(session: &mut Session) -> shenron::Result {
+    session.write_str("in").await?;
+
+    while let Some(event) = session.next().await {
+        if matches!(event, Event::Eof) {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+/// Opens a session running `hold` and waits until it's in.
+async fn held(handle: &russh::client::Handle<AcceptAll>) -> russh::Channel<russh::client::Msg> {
+    let mut channel = handle.channel_open_session().await.expect("open");
+    channel.exec(true, "hold").await.expect("exec");
+
+    while let Some(msg) = channel.wait().await {
+        if matches!(msg, ChannelMsg::Data { .. }) {
+            break;
+        }
+    }
+
+    channel
+}
+
+#[tokio::test]
+async fn sessions_past_the_cap_wait_for_a_slot() {
+    let port = start_server_with(hold, |server| server.with(MaxSessionsPerIp::new(1))).await;
+    let handle = connect_and_auth(port).await;
+
+    let mut first = held(&handle).await;
+
+    // Another connection from the same address counts too.
+    let other = connect_and_auth(port).await;
+    let mut second = other.channel_open_session().await.expect("open");
+    second.exec(true, "hold").await.expect("exec");
+    let turned_away = read_to_close(&mut second).await;
+    assert_eq!(turned_away.stdout, "");
+    assert_eq!(turned_away.exit_status, Some(1));
+
+    first.eof().await.expect("eof");
+    assert_eq!(read_to_close(&mut first).await.exit_status, Some(0));
+
+    let mut third = other.channel_open_session().await.expect("open");
+    third.exec(true, "hold").await.expect("exec");
+    third.eof().await.expect("eof");
+    assert_eq!(read_to_close(&mut third).await.stdout, "in");
+}
+
+#[tokio::test]
+async fn users_past_the_cap_are_turned_away() {
+    let port = start_server_with(hold, |server| server.with(MaxSessionsPerUser::new(1))).await;
+    let handle = connect_and_auth(port).await;
+
+    let mut first = held(&handle).await;
+
+    let mut second = handle.channel_open_session().await.expect("open");
+    second.exec(true, "hold").await.expect("exec");
+    assert_eq!(read_to_close(&mut second).await.exit_status, Some(1));
+
+    first.eof().await.expect("eof");
+    assert_eq!(read_to_close(&mut first).await.exit_status, Some(0));
+}
+
+#[tokio::test]
+async fn the_oldest_session_can_be_evicted_instead() {
+    let port = start_server_with(hold, |server| {
+        server.with(MaxSessionsPerUser::new(1).evict_oldest())
+    })
+    .await;
+    let handle = connect_and_auth(port).await;
+
+    let mut first = held(&handle).await;
+    let mut second = held(&handle).await;
+
+    assert_eq!(read_to_close(&mut first).await.exit_status, Some(1));
+
+    second.eof().await.expect("eof");
+    assert_eq!(read_to_close(&mut second).await.exit_status, Some(0));
+}
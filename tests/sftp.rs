@@ -0,0 +1,350 @@
+//! The SFTP subsystem end to end, driven by russh-sftp's client.
+
+#![cfg(feature = "sftp")]
+#![feature(async_fn_traits, unboxed_closures)]
+
+mod common;
+
+use std::{
+    fs, io,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use common::{AcceptAll, start_server_with};
+use russh::client::{self, AuthResult};
+use russh_sftp::{
+    client::{SftpSession, error::Error},
+    protocol::OpenFlags,
+};
+use shenron::{
+    Auth, Session,
+    sftp::{
+        DirEntry, FileAttr, Filesystem, LocalFile, LocalFilesystem, Sftp, SftpContext, SftpEvent,
+        SftpOp,
+    },
+};
+use tokio::io::AsyncWriteExt;
+
+async fn app(session: &mut Session) -> shenron::Result {
+    session.write_str("not sftp").await
+}
+
+/// An SFTP session as `user`, and the connection it runs over, which has to
+/// outlive it.
+async fn sftp_as(port: u16, user: &str) -> (client::Handle<AcceptAll>, Result<SftpSession, Error>) {
+    let config = Arc::new(client::Config::default());
+    let mut handle = client::connect(config, ("127.0.0.1", port), AcceptAll)
+        .await
+        .expect("connect");
+
+    let result = handle
+        .authenticate_password(user, "hunter2")
+        .await
+        .expect("auth request");
+    assert!(matches!(result, AuthResult::Success));
+
+    let channel = handle.channel_open_session().await.expect("channel");
+    channel
+        .request_subsystem(true, "sftp")
+        .await
+        .expect("subsystem");
+
+    let sftp = SftpSession::new(channel.into_stream()).await;
+
+    (handle, sftp)
+}
+
+#[tokio::test]
+async fn per_session_serves_each_user_their_own_root() {
+    let tmp = tempfile::tempdir().expect("tempdir");
+
+    for user in ["alice", "bob"] {
+        fs::create_dir(tmp.path().join(user)).expect("create root");
+        fs::write(tmp.path().join(user).join("whoami"), user).expect("seed file");
+    }
+
+    let roots = tmp.path().to_path_buf();
+    let port = start_server_with(app, move |server| {
+        server
+            .password_auth(|_user, _password| async { Auth::accept() })
+            .with(Sftp::per_session(move |session| {
+                let root = roots.join(session.user());
+
+                async move { LocalFilesystem::try_new(root) }
+            }))
+    })
+    .await;
+
+    for user in ["alice", "bob"] {
+        let (_handle, sftp) = sftp_as(port, user).await;
+        let data = sftp.expect("sftp").read("/whoami").await.expect("read");
+
+        assert_eq!(data, user.as_bytes());
+    }
+
+    let (_handle, sftp) = sftp_as(port, "mallory").await;
+
+    assert!(sftp.is_err(), "a user without a root is refused");
+}
+
+/// Anyone may read; only alice may upload, and only to `/incoming`.
+fn allow(context: &SftpContext, op: &SftpOp<'_>) -> bool {
+    match op {
+        SftpOp::Read(_) | SftpOp::ReadDir(_) => true,
+        SftpOp::Write(path) => context.user == "alice" && path.starts_with("/incoming/"),
+        _ => false,
+    }
+}
+
+#[tokio::test]
+async fn policy_denies_operations_before_they_run() {
+    let tmp = tempfile::tempdir().expect("tempdir");
+    fs::create_dir(tmp.path().join("incoming")).expect("create dir");
+    fs::write(tmp.path().join("readme"), "hi").expect("seed file");
+
+    let root = tmp.path().to_path_buf();
+    let port = start_server_with(app, move |server| {
+        server
+            .password_auth(|_user, _password| async { Auth::accept() })
+            .with(Sftp::local(root).policy(allow))
+    })
+    .await;
+
+    let (_handle, sftp) = sftp_as(port, "alice").await;
+    let sftp = sftp.expect("sftp");
+
+    assert_eq!(sftp.read("/readme").await.expect("read"), b"hi");
+    let mut upload = sftp
+        .create("/incoming/upload")
+        .await
+        .expect("create allowed");
+    upload.write_all(b"data").await.expect("write");
+    upload.shutdown().await.expect("close");
+
+    assert!(sftp.write("/readme", b"overwritten").await.is_err());
+    assert!(sftp.remove_file("/readme").await.is_err());
+    assert!(sftp.rename("/readme", "/incoming/readme").await.is_err());
+
+    assert_eq!(
+        fs::read(tmp.path().join("readme")).expect("still there"),
+        b"hi"
+    );
+    assert_eq!(
+        fs::read(tmp.path().join("incoming/upload")).expect("uploaded"),
+        b"data"
+    );
+}
+
+#[tokio::test]
+async fn events_report_transfers_and_changes() {
+    let tmp = tempfile::tempdir().expect("tempdir");
+    fs::write(tmp.path().join("readme"), "hi").expect("seed file");
+
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let seen = Arc::clone(&events);
+
+    let root = tmp.path().to_path_buf();
+    let port = start_server_with(app, move |server| {
+        server
+            .password_auth(|_user, _password| async { Auth::accept() })
+            .with(
+                Sftp::local(root).on_event(move |context: &SftpContext, event: &SftpEvent| {
+                    assert_eq!(context.user, "alice");
+                    seen.lock().expect("events").push(event.clone());
+                }),
+            )
+    })
+    .await;
+
+    let (_handle, sftp) = sftp_as(port, "alice").await;
+    let sftp = sftp.expect("sftp");
+
+    assert_eq!(sftp.read("/readme").await.expect("read"), b"hi");
+
+    let mut upload = sftp.create("/upload").await.expect("create");
+    upload.write_all(b"data").await.expect("write");
+    upload.shutdown().await.expect("close");
+
+    sftp.rename("/upload", "/landed").await.expect("rename");
+    sftp.remove_file("/readme").await.expect("remove");
+
+    let events = events.lock().expect("events");
+    let finished = |event: &SftpEvent| match event {
+        SftpEvent::DownloadFinished { path, bytes, .. } => Some(("download", path.clone(), *bytes)),
+        SftpEvent::UploadFinished { path, bytes, .. } => Some(("upload", path.clone(), *bytes)),
+        _ => None,
+    };
+
+    assert_eq!(
+        events.iter().filter_map(finished).collect::<Vec<_>>(),
+        [
+            ("download", "/readme".to_string(), 2),
+            ("upload", "/upload".to_string(), 4),
+        ]
+    );
+    assert_eq!(
+        events[0],
+        SftpEvent::DownloadStarted {
+            path: "/readme".into()
+        }
+    );
+    assert!(events.contains(&SftpEvent::UploadStarted {
+        path: "/upload".into()
+    }));
+    assert_eq!(
+        events[events.len() - 2..],
+        [
+            SftpEvent::Renamed {
+                from: "/upload".into(),
+                to: "/landed".into(),
+            },
+            SftpEvent::Removed {
+                path: "/readme".into()
+            },
+        ]
+    );
+}
+
+#[tokio::test]
+async fn max_rate_holds_transfers_to_the_rate() {
+    let tmp = tempfile::tempdir().expect("tempdir");
+    fs::write(tmp.path().join("blob"), vec![7; 64 * 1024]).expect("seed file");
+
+    let root = tmp.path().to_path_buf();
+    let port = start_server_with(app, move |server| {
+        server
+            .password_auth(|_user, _password| async { Auth::accept() })
+            .with(Sftp::local(root).max_rate(128 * 1024))
+    })
+    .await;
+
+    let (_handle, sftp) = sftp_as(port, "alice").await;
+    let sftp = sftp.expect("sftp");
+
+    let started = Instant::now();
+    let data = sftp.read("/blob").await.expect("read");
+
+    assert_eq!(data.len(), 64 * 1024);
+    assert!(
+        started.elapsed() >= Duration::from_millis(450),
+        "64 KiB at 128 KiB/s took {:?}",
+        started.elapsed()
+    );
+}
+
+#[derive(Debug, Clone)]
+struct Tenant(&'static str);
+
+/// A [`LocalFilesystem`] logging who does what to it.
+#[derive(Clone)]
+struct Audited {
+    inner: LocalFilesystem,
+    who: String,
+    log: Arc<Mutex<Vec<String>>>,
+}
+
+impl Audited {
+    fn record(&self, op: &str, path: &str) {
+        self.log
+            .lock()
+            .expect("log")
+            .push(format!("{} {op} {path}", self.who));
+    }
+}
+
+impl Filesystem for Audited {
+    type Handle = LocalFile;
+
+    fn for_session(&self, context: &SftpContext) -> Self {
+        let tenant = context
+            .extensions
+            .get::<Tenant>()
+            .map_or("-", |tenant| tenant.0);
+
+        Self {
+            who: format!("{}@{tenant}", context.user),
+            ..self.clone()
+        }
+    }
+
+    async fn read_dir(&self, path: &str) -> io::Result<Vec<DirEntry>> {
+        self.inner.read_dir(path).await
+    }
+
+    async fn stat(&self, path: &str) -> io::Result<FileAttr> {
+        self.inner.stat(path).await
+    }
+
+    async fn lstat(&self, path: &str) -> io::Result<FileAttr> {
+        self.inner.lstat(path).await
+    }
+
+    async fn open_read(&self, path: &str) -> io::Result<Self::Handle> {
+        self.record("read", path);
+        self.inner.open_read(path).await
+    }
+
+    async fn open_write(
+        &self,
+        path: &str,
+        flags: OpenFlags,
+        attrs: FileAttr,
+    ) -> io::Result<Self::Handle> {
+        self.record("write", path);
+        self.inner.open_write(path, flags, attrs).await
+    }
+
+    async fn mkdir(&self, path: &str, attrs: FileAttr) -> io::Result<()> {
+        self.inner.mkdir(path, attrs).await
+    }
+
+    async fn rmdir(&self, path: &str) -> io::Result<()> {
+        self.inner.rmdir(path).await
+    }
+
+    async fn remove(&self, path: &str) -> io::Result<()> {
+        self.record("remove", path);
+        self.inner.remove(path).await
+    }
+
+    async fn rename(&self, from: &str, to: &str) -> io::Result<()> {
+        self.inner.rename(from, to).await
+    }
+
+    async fn set_stat(&self, path: &str, attrs: FileAttr) -> io::Result<()> {
+        self.inner.set_stat(path, attrs).await
+    }
+
+    async fn realpath(&self, path: &str) -> io::Result<String> {
+        self.inner.realpath(path).await
+    }
+}
+
+#[tokio::test]
+async fn filesystems_are_told_who_is_operating() {
+    let tmp = tempfile::tempdir().expect("tempdir");
+    fs::write(tmp.path().join("readme"), "hi").expect("seed file");
+
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let audited = Audited {
+        inner: LocalFilesystem::new(tmp.path()),
+        who: "nobody".into(),
+        log: Arc::clone(&log),
+    };
+
+    let port = start_server_with(app, move |server| {
+        server
+            .password_auth(|_user, _password| async { Auth::accept().with(Tenant("acme")) })
+            .with(Sftp::new(audited).read_only())
+    })
+    .await;
+
+    let (_handle, sftp) = sftp_as(port, "alice").await;
+    let sftp = sftp.expect("sftp");
+
+    assert_eq!(sftp.read("/readme").await.expect("read"), b"hi");
+    assert!(sftp.remove_file("/readme").await.is_err());
+
+    assert_eq!(*log.lock().expect("log"), ["alice@acme read /readme"]);
+}
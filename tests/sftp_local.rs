@@ -2,7 +2,10 @@
 
 use std::fs;
 
-use shenron::sftp::{FileAttr, FileHandle, Filesystem, LocalFilesystem};
+use shenron::sftp::{
+    FileAttr, FileHandle, Filesystem, LocalFilesystem, MemoryUsage, MountFs, Quota, ReadOnly,
+    Usage, UsageStore,
+};
 use tempfile::TempDir;
 
 /// The served root is a subdirectory of the returned `TempDir`, so escape
@@ -165,6 +168,31 @@ async fn realpath_is_virtual_not_host_path() {
     assert_eq!(resolved, "/hello.txt");
 }
 
+#[cfg(windows)]
+#[tokio::test]
+async fn modes_map_to_the_read_only_flag() {
+    let (outer, fs) = sandboxed_root();
+
+    let attrs = fs.stat("/hello.txt").await.expect("stat");
+    assert_eq!(attrs.permissions, Some(0o100_666));
+
+    fs.set_stat(
+        "/hello.txt",
+        FileAttr {
+            permissions: Some(0o444),
+            ..Default::default()
+        },
+    )
+    .await
+    .expect("set permissions");
+
+    let meta = fs::metadata(root(&outer).join("hello.txt")).expect("meta");
+    assert!(meta.permissions().readonly());
+
+    let attrs = fs.stat("/hello.txt").await.expect("stat");
+    assert_eq!(attrs.permissions, Some(0o100_444));
+}
+
 #[cfg(unix)]
 #[tokio::test]
 async fn set_stat_applies_permissions_and_truncates() {
@@ -198,6 +226,37 @@ async fn set_stat_applies_permissions_and_truncates() {
     assert_eq!(fs.stat("/hello.txt").await.expect("stat").size, Some(2));
 }
 
+#[tokio::test]
+async fn set_stat_applies_times() {
+    let (_outer, fs) = sandboxed_root();
+
+    fs.set_stat(
+        "/hello.txt",
+        FileAttr {
+            mtime: Some(1_600_000_000),
+            ..Default::default()
+        },
+    )
+    .await
+    .expect("set mtime");
+
+    let attrs = fs.stat("/hello.txt").await.expect("stat");
+    assert_eq!(attrs.mtime, Some(1_600_000_000));
+
+    let mut file = fs.open_read("/hello.txt").await.expect("open");
+    file.set_stat(FileAttr {
+        atime: Some(1_500_000_000),
+        mtime: Some(1_700_000_000),
+        ..Default::default()
+    })
+    .await
+    .expect("set times");
+
+    let attrs = file.stat().await.expect("fstat");
+    assert_eq!(attrs.atime, Some(1_500_000_000));
+    assert_eq!(attrs.mtime, Some(1_700_000_000));
+}
+
 #[cfg(unix)]
 #[tokio::test]
 async fn create_honors_client_permissions() {
@@ -254,3 +313,160 @@ async fn mkdir_and_rmdir() {
     fs.rmdir("/sub").await.expect("rmdir");
     assert!(fs.stat("/sub").await.is_err());
 }
+
+#[tokio::test]
+async fn read_only_serves_reads_and_denies_changes() {
+    let (outer, fs) = sandboxed_root();
+    let fs = ReadOnly::new(fs);
+
+    let mut file = fs.open_read("/hello.txt").await.expect("open");
+    assert_eq!(file.read(0, 64).await.expect("read"), b"hi there");
+    assert_eq!(
+        file.write(0, b"x".to_vec())
+            .await
+            .expect_err("write")
+            .kind(),
+        std::io::ErrorKind::PermissionDenied
+    );
+    assert!(fs.read_dir("/").await.is_ok());
+
+    let denied = [
+        fs.open_write(
+            "/new.txt",
+            russh_sftp::protocol::OpenFlags::CREATE | russh_sftp::protocol::OpenFlags::WRITE,
+            FileAttr::default(),
+        )
+        .await
+        .err(),
+        fs.remove("/hello.txt").await.err(),
+        fs.rename("/hello.txt", "/moved.txt").await.err(),
+        fs.mkdir("/sub", FileAttr::default()).await.err(),
+        fs.set_stat("/hello.txt", FileAttr::default()).await.err(),
+    ];
+
+    for error in denied {
+        assert_eq!(
+            error.expect("denied").kind(),
+            std::io::ErrorKind::PermissionDenied
+        );
+    }
+
+    assert!(root(&outer).join("hello.txt").exists());
+    assert!(!root(&outer).join("new.txt").exists());
+}
+
+#[tokio::test]
+async fn quota_counts_writes_and_refuses_to_go_over() {
+    use russh_sftp::protocol::OpenFlags;
+
+    let (_outer, fs) = sandboxed_root();
+    let usage = MemoryUsage::new(Usage::measure(&fs).await.expect("measure"));
+    assert_eq!(
+        usage.usage().await.expect("usage"),
+        Usage { bytes: 8, files: 1 }
+    );
+
+    let fs = Quota::new(fs, usage.clone())
+        .max_bytes(15)
+        .max_file_size(10)
+        .max_files(2);
+    let create = OpenFlags::CREATE | OpenFlags::WRITE;
+
+    let mut file = fs
+        .open_write("/new.txt", create, FileAttr::default())
+        .await
+        .expect("open");
+    file.write(0, b"12345".to_vec()).await.expect("write");
+    file.write(3, b"456".to_vec()).await.expect("overwrite");
+    assert_eq!(
+        usage.usage().await.expect("usage"),
+        Usage {
+            bytes: 14,
+            files: 2
+        }
+    );
+
+    let too_large = file
+        .write(6, b"78901".to_vec())
+        .await
+        .expect_err("too large");
+    assert_eq!(too_large.kind(), std::io::ErrorKind::FileTooLarge);
+    file.close().await.expect("close");
+
+    let too_many = fs
+        .open_write("/third.txt", create, FileAttr::default())
+        .await
+        .err()
+        .expect("too many files");
+    assert_eq!(too_many.kind(), std::io::ErrorKind::QuotaExceeded);
+
+    let mut file = fs
+        .open_write("/hello.txt", create, FileAttr::default())
+        .await
+        .expect("open");
+    let too_much = file.write(8, b"12".to_vec()).await.expect_err("over quota");
+    assert_eq!(too_much.kind(), std::io::ErrorKind::QuotaExceeded);
+    assert!(too_much.to_string().contains("15 bytes"));
+    file.close().await.expect("close");
+
+    fs.remove("/hello.txt").await.expect("remove");
+    assert_eq!(
+        usage.usage().await.expect("usage"),
+        Usage { bytes: 6, files: 1 }
+    );
+}
+
+#[tokio::test]
+async fn mount_fs_routes_paths_to_their_mounts() {
+    let (outer, public) = sandboxed_root();
+    let uploads = tempfile::tempdir().expect("tempdir");
+
+    let fs = MountFs::new()
+        .mount("/public", ReadOnly::new(public))
+        .mount("/srv/uploads", LocalFilesystem::new(uploads.path()));
+
+    let mut names: Vec<_> = fs
+        .read_dir("/")
+        .await
+        .expect("list root")
+        .into_iter()
+        .map(|entry| entry.name)
+        .collect();
+    names.sort();
+    assert_eq!(names, ["public", "srv"]);
+    assert!(fs.stat("/srv").await.is_ok());
+
+    let mut file = fs.open_read("/public/hello.txt").await.expect("open");
+    assert_eq!(file.read(0, 64).await.expect("read"), b"hi there");
+
+    let mut file = fs
+        .open_write(
+            "/srv/uploads/../uploads/new.txt",
+            russh_sftp::protocol::OpenFlags::CREATE | russh_sftp::protocol::OpenFlags::WRITE,
+            FileAttr::default(),
+        )
+        .await
+        .expect("open for writing");
+    file.write(0, b"landed".to_vec()).await.expect("write");
+    file.close().await.expect("close");
+    assert_eq!(
+        fs::read(uploads.path().join("new.txt")).expect("written through"),
+        b"landed"
+    );
+
+    assert!(fs.remove("/public/hello.txt").await.is_err());
+    assert!(fs.rmdir("/srv/uploads").await.is_err());
+    assert!(
+        fs.rename("/srv/uploads/new.txt", "/public/new.txt")
+            .await
+            .is_err()
+    );
+    assert!(fs.stat("/elsewhere").await.is_err());
+    assert_eq!(
+        fs.realpath("/srv/uploads/./new.txt")
+            .await
+            .expect("realpath"),
+        "/srv/uploads/new.txt"
+    );
+    assert!(root(&outer).join("hello.txt").exists());
+}
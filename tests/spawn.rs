@@ -0,0 +1,40 @@
+//! `Server::spawn`: the handle reports the bound port and stops the accept
+//! loop on request.
+
+use std::time::Duration;
+
+use shenron::{Server, Session};
+
+async fn noop(_session: &mut Session) -> shenron::Result {
+    Ok(())
+}
+
+#[tokio::test]
+async fn shutdown_stops_accepting_connections() {
+    let tmp = tempfile::TempDir::new().expect("tempdir");
+
+    let server = Server::new()
+        .bind("127.0.0.1:0")
+        .host_key_path(tmp.path().join("host_key"))
+        .expect("host key")
+        .app(noop)
+        .spawn()
+        .await
+        .expect("spawn");
+
+    let addr = server.local_addr();
+    assert_ne!(addr.port(), 0, "port 0 must resolve to the bound port");
+
+    tokio::net::TcpStream::connect(addr)
+        .await
+        .expect("server accepts while running");
+
+    server.shutdown();
+
+    tokio::time::timeout(Duration::from_secs(2), server.stopped())
+        .await
+        .expect("accept loop never exited")
+        .expect("clean shutdown");
+
+    assert!(tokio::net::TcpStream::connect(addr).await.is_err());
+}
@@ -0,0 +1,73 @@
+//! `Stack`: middleware composed into one reusable layer.
+
+#![feature(async_fn_traits, unboxed_closures)]
+
+mod common;
+
+use common::{exec_output, start_server_with};
+use shenron::{
+    Exit, Next, Session,
+    middleware::{Command, Stack, terminal},
+};
+
+async fn app(session: &mut Session) -> shenron::Result {
+    session.write_str("app").await
+}
+
+async fn deploy(session: &mut Session) -> shenron::Result {
+    session.write_str("deploy").await
+}
+
+async fn outer(session: &mut Session, next: Next<'_>) -> shenron::Result<Exit> {
+    session.write_str("<outer ").await?;
+    let exit = next.run(session).await;
+    session.write_str(" outer>").await?;
+
+    Ok(exit)
+}
+
+async fn inner(session: &mut Session, next: Next<'_>) -> shenron::Result<Exit> {
+    session.write_str("<inner ").await?;
+    let exit = next.run(session).await;
+    session.write_str(" inner>").await?;
+
+    Ok(exit)
+}
+
+#[tokio::test]
+async fn layers_run_in_order_around_the_rest_of_the_chain() {
+    let stack = Stack::new().with(outer).with(inner);
+
+    let first = start_server_with(app, {
+        let stack = stack.clone();
+        move |server| server.with(stack)
+    })
+    .await;
+    let second = start_server_with(app, move |server| server.with(stack)).await;
+
+    assert_eq!(
+        exec_output(first, "x").await.stdout,
+        "<outer <inner app inner> outer>"
+    );
+    assert_eq!(
+        exec_output(second, "x").await.stdout,
+        "<outer <inner app inner> outer>"
+    );
+}
+
+#[tokio::test]
+async fn stacks_can_guard_a_single_route() {
+    let port = start_server_with(app, |server| {
+        server.with(Command::new(
+            "deploy",
+            Stack::new().with(inner).with(terminal(deploy)),
+        ))
+    })
+    .await;
+
+    assert_eq!(
+        exec_output(port, "deploy").await.stdout,
+        "<inner deploy inner>"
+    );
+    assert_eq!(exec_output(port, "status").await.stdout, "app");
+}
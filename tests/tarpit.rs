@@ -0,0 +1,50 @@
+//! `Tarpit`: trapped sessions get a slow drip of bytes and never finish.
+
+#![feature(async_fn_traits, unboxed_closures)]
+
+mod common;
+
+use std::time::Duration;
+
+use common::{connect_and_auth, read_to_close, start_server_with};
+use russh::ChannelMsg;
+use shenron::{Session, middleware::Tarpit};
+
+async fn app(session: &mut Session) -> shenron::Result {
+    session.write_str("app").await
+}
+
+#[tokio::test]
+async fn trapped_sessions_drip_until_the_client_leaves() {
+    let port = start_server_with(app, |server| {
+        server.with(
+            Tarpit::new(|session| session.raw_command() == Some("scan"))
+                .interval(Duration::from_millis(20)),
+        )
+    })
+    .await;
+    let handle = connect_and_auth(port).await;
+
+    let mut trapped = handle.channel_open_session().await.expect("open");
+    trapped.exec(true, "scan").await.expect("exec");
+    trapped.eof().await.expect("eof");
+
+    let mut dripped = Vec::new();
+    let _ = tokio::time::timeout(Duration::from_millis(300), async {
+        while let Some(msg) = trapped.wait().await {
+            match msg {
+                ChannelMsg::Data { data } => dripped.extend_from_slice(&data),
+                ChannelMsg::ExitStatus { .. } | ChannelMsg::Close => panic!("the tarpit let go"),
+                _ => {}
+            }
+        }
+    })
+    .await;
+
+    assert!(dripped.len() >= 3, "only {} bytes dripped", dripped.len());
+    assert!(dripped.iter().all(|byte| (b' '..=b'~').contains(byte)));
+
+    let mut other = handle.channel_open_session().await.expect("open");
+    other.exec(true, "ls").await.expect("exec");
+    assert_eq!(read_to_close(&mut other).await.stdout, "app");
+}
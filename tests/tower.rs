@@ -0,0 +1,101 @@
+//! Tower interop: tower layers as middleware, and the rest of the chain as a
+//! tower service.
+
+#![cfg(feature = "tower")]
+#![feature(async_fn_traits, unboxed_closures)]
+
+mod common;
+
+use std::time::Duration;
+
+use common::{exec_output, start_server_with};
+use shenron::{
+    Exit, Next, Session,
+    middleware::tower::{NextService, TowerLayer},
+};
+use tower::{Layer, Service, timeout::TimeoutLayer};
+
+/// Runs for 300ms unless cut off.
+async fn slow(session: &mut Session) -> shenron::Result {
+    session.write_str("started;").await?;
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    session.write_str("finished").await
+}
+
+#[tokio::test]
+async fn tower_layers_wrap_the_rest_of_the_chain() {
+    let short = start_server_with(slow, |server| {
+        server.with(TowerLayer::new(TimeoutLayer::new(Duration::from_millis(
+            100,
+        ))))
+    })
+    .await;
+    let long = start_server_with(slow, |server| {
+        server.with(TowerLayer::new(TimeoutLayer::new(Duration::from_secs(2))))
+    })
+    .await;
+
+    let cut_off = exec_output(short, "x").await;
+    assert_eq!(cut_off.stdout, "started;");
+    assert_eq!(cut_off.exit_status, Some(1));
+
+    let finished = exec_output(long, "x").await;
+    assert_eq!(finished.stdout, "started;finished");
+    assert_eq!(finished.exit_status, Some(0));
+}
+
+/// A hand-rolled layer that tags the output, to check layers see the
+/// session.
+struct Tag;
+
+impl<'a> Layer<NextService<'a>> for Tag {
+    type Service = Tagged<'a>;
+
+    fn layer(&self, inner: NextService<'a>) -> Tagged<'a> {
+        Tagged(inner)
+    }
+}
+
+struct Tagged<'a>(NextService<'a>);
+
+impl<'a> Service<&'a mut Session> for Tagged<'a> {
+    type Response = Exit;
+    type Error = shenron::Error;
+    type Future = std::pin::Pin<Box<dyn Future<Output = shenron::Result<Exit>> + Send + 'a>>;
+
+    fn poll_ready(
+        &mut self,
+        _cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<shenron::Result<()>> {
+        std::task::Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, session: &'a mut Session) -> Self::Future {
+        let mut inner = self.0;
+
+        Box::pin(async move {
+            session.write_str("[").await?;
+            let Ok(exit) = inner.call(&mut *session).await;
+            session.write_str("]").await?;
+
+            Ok(exit)
+        })
+    }
+}
+
+/// Calls the rest of the chain through its service form.
+async fn via_service(session: &mut Session, next: Next<'_>) -> Exit {
+    let Ok(exit) = next.into_service().call(session).await;
+
+    exit
+}
+
+#[tokio::test]
+async fn the_chain_is_a_service() {
+    let port = start_server_with(slow, |server| {
+        server.with(TowerLayer::new(Tag)).with(via_service)
+    })
+    .await;
+
+    assert_eq!(exec_output(port, "x").await.stdout, "[started;finished]");
+}
@@ -0,0 +1,90 @@
+//! `WasmPlugin`: policies compiled to WebAssembly allow or deny sessions.
+
+#![cfg(feature = "wasm")]
+#![feature(async_fn_traits, unboxed_closures)]
+
+mod common;
+
+use common::{exec_output, start_server_with};
+use shenron::{Session, middleware::WasmPlugin};
+
+/// Denies commands starting with `d`, telling the client why, and greets
+/// everything else.
+const POLICY: &str = r#"
+(module
+  (import "shenron" "meta" (func $meta (param i32 i32 i32 i32) (result i32)))
+  (import "shenron" "write" (func $write (param i32 i32)))
+  (import "shenron" "write_stderr" (func $write_stderr (param i32 i32)))
+  (memory (export "memory") 1)
+  (data (i32.const 0) "command")
+  (data (i32.const 16) "denied by policy\n")
+  (data (i32.const 48) "checked\n")
+  (func (export "on_session") (result i32)
+    (drop (call $meta (i32.const 0) (i32.const 7) (i32.const 64) (i32.const 64)))
+    (if (result i32) (i32.eq (i32.load8_u (i32.const 64)) (i32.const 100))
+      (then (call $write_stderr (i32.const 16) (i32.const 17)) (i32.const 3))
+      (else (call $write (i32.const 48) (i32.const 8)) (i32.const 0)))))
+"#;
+
+const DENY_ALL: &str = r#"
+(module
+  (memory (export "memory") 1)
+  (func (export "on_session") (result i32) (i32.const 4)))
+"#;
+
+const SPIN: &str = r#"
+(module
+  (memory (export "memory") 1)
+  (func (export "on_session") (result i32) (loop $forever (br $forever)) (i32.const 0)))
+"#;
+
+async fn app(session: &mut Session) -> shenron::Result {
+    session.write_str("app").await
+}
+
+#[tokio::test]
+async fn plugins_allow_and_deny_sessions() {
+    let plugin = WasmPlugin::new(POLICY).expect("compiles");
+    let port = start_server_with(app, |server| server.with(plugin)).await;
+
+    let allowed = exec_output(port, "ls").await;
+    assert_eq!(allowed.stdout, "checked\napp");
+    assert_eq!(allowed.exit_status, Some(0));
+
+    let denied = exec_output(port, "deploy").await;
+    assert_eq!(denied.stdout, "");
+    assert_eq!(denied.exit_status, Some(3));
+}
+
+#[tokio::test]
+async fn runaway_plugins_fail_closed() {
+    let plugin = WasmPlugin::new(SPIN).expect("compiles").fuel(10_000);
+    let port = start_server_with(app, |server| server.with(plugin)).await;
+
+    let output = exec_output(port, "ls").await;
+    assert_eq!(output.stdout, "");
+    assert_eq!(output.exit_status, Some(1));
+}
+
+#[tokio::test]
+async fn plugins_can_be_reloaded() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("policy.wat");
+    std::fs::write(&path, POLICY).expect("write");
+
+    let plugin = WasmPlugin::load(&path).expect("loads");
+    let port = start_server_with(app, {
+        let plugin = plugin.clone();
+        move |server| server.with(plugin)
+    })
+    .await;
+    assert_eq!(exec_output(port, "ls").await.exit_status, Some(0));
+
+    std::fs::write(&path, DENY_ALL).expect("write");
+    plugin.reload().expect("reloads");
+    assert_eq!(exec_output(port, "ls").await.exit_status, Some(4));
+
+    std::fs::write(&path, "(module)").expect("write");
+    assert!(plugin.reload().is_err());
+    assert_eq!(exec_output(port, "ls").await.exit_status, Some(4));
+}